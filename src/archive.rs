@@ -0,0 +1,16 @@
+//! Archive formats used for importing and exporting packs
+//!
+//! Everything here works on streams, so that exporting a pack with gigabytes of bundled assets
+//! never needs to hold a whole file in memory.
+
+mod crc32;
+mod deflate;
+mod inflate;
+mod tar;
+mod zip;
+
+pub use tar::TarWriter;
+pub use zip::{
+    Compression, DeterministicZipWriter, DosDateTime, FileOptions, NameEncoding, ZipArchive,
+    ZipEntry, ZipEntryReader, ZipError, ZipWriter,
+};
@@ -0,0 +1,92 @@
+//! CRC-32 (IEEE 802.3) checksum, as used by the zip format
+
+/// Lookup tables for the slice-by-8 implementation, generated at compile time
+static TABLES: [[u32; 256]; 8] = build_tables();
+
+/// Builds the slice-by-8 lookup tables for the reflected `0xEDB88320` polynomial
+#[allow(clippy::cast_possible_truncation)]
+const fn build_tables() -> [[u32; 256]; 8] {
+    let mut tables = [[0_u32; 256]; 8];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 0 {
+                crc >> 1
+            } else {
+                (crc >> 1) ^ 0xEDB8_8320
+            };
+            bit += 1;
+        }
+        tables[0][i] = crc;
+        i += 1;
+    }
+    let mut i = 0;
+    while i < 256 {
+        let mut table = 1;
+        while table < 8 {
+            let previous = tables[table - 1][i];
+            tables[table][i] = (previous >> 8) ^ tables[0][(previous & 0xFF) as usize];
+            table += 1;
+        }
+        i += 1;
+    }
+    tables
+}
+
+/// Incremental CRC-32 hasher
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Crc32 {
+    /// The running (inverted) checksum
+    state: u32,
+}
+
+impl Crc32 {
+    /// Creates a new hasher with no data fed into it
+    pub(crate) fn new() -> Self {
+        Self { state: 0xFFFF_FFFF }
+    }
+
+    /// Feeds more data into the hasher
+    pub(crate) fn update(&mut self, mut data: &[u8]) {
+        let mut crc = self.state;
+        while data.len() >= 8 {
+            let low = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) ^ crc;
+            let high = u32::from_le_bytes([data[4], data[5], data[6], data[7]]);
+            crc = TABLES[7][(low & 0xFF) as usize]
+                ^ TABLES[6][((low >> 8) & 0xFF) as usize]
+                ^ TABLES[5][((low >> 16) & 0xFF) as usize]
+                ^ TABLES[4][(low >> 24) as usize]
+                ^ TABLES[3][(high & 0xFF) as usize]
+                ^ TABLES[2][((high >> 8) & 0xFF) as usize]
+                ^ TABLES[1][((high >> 16) & 0xFF) as usize]
+                ^ TABLES[0][(high >> 24) as usize];
+            data = &data[8..];
+        }
+        for &byte in data {
+            crc = (crc >> 8) ^ TABLES[0][((crc ^ u32::from(byte)) & 0xFF) as usize];
+        }
+        self.state = crc;
+    }
+
+    /// Returns the checksum of all the data fed in so far
+    pub(crate) fn finish(self) -> u32 {
+        !self.state
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    // Check against the standard check value, split across several updates
+    #[test]
+    fn check_value() {
+        let mut crc = Crc32::new();
+        crc.update(b"1234");
+        crc.update(b"56789");
+        assert_eq!(crc.finish(), 0xCBF4_3926);
+        assert_eq!(Crc32::new().finish(), 0);
+    }
+}
@@ -0,0 +1,341 @@
+//! Minimal streaming DEFLATE ([RFC 1951](https://www.rfc-editor.org/rfc/rfc1951)) encoder
+//!
+//! This uses a greedy LZ77 matcher with the fixed huffman codes, falling back to stored blocks for
+//! data that doesn't compress. Memory use is bounded by the window and block sizes regardless of
+//! how much data is fed through it.
+
+// Everything here is bit twiddling on values whose ranges are bounded by the format
+#![allow(clippy::cast_possible_truncation)]
+
+use std::io::{self, Write};
+
+/// Size of the sliding window, the maximum distance a match can refer back
+pub(super) const WINDOW_SIZE: usize = 32 * 1024;
+/// Amount of new input collected before a block is emitted
+const BLOCK_SIZE: usize = 64 * 1024;
+/// Shortest match deflate can encode
+const MIN_MATCH: usize = 3;
+/// Longest match deflate can encode
+const MAX_MATCH: usize = 258;
+/// Number of bits in the match finder's hash
+const HASH_BITS: u32 = 15;
+/// How many candidates the match finder will try before giving up
+const MAX_CHAIN: usize = 64;
+/// Largest payload of a single stored block
+const MAX_STORED: usize = 0xFFFF;
+/// The end of block symbol
+const END_OF_BLOCK: u16 = 256;
+
+/// Base match length for each length symbol (257 through 285)
+pub(super) const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+/// Extra bits following each length symbol
+pub(super) const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+/// Base distance for each distance symbol
+pub(super) const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+/// Extra bits following each distance symbol
+pub(super) const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// A symbol produced by the match finder
+#[derive(Debug, Clone, Copy)]
+enum Token {
+    /// A single literal byte
+    Literal(u8),
+    /// A back reference
+    Match {
+        /// Length of the match
+        length: u16,
+        /// Distance back to the start of the match
+        distance: u16,
+    },
+}
+
+/// Returns the index of the last entry in `bases` that is less than or equal to `value`
+fn symbol_index(bases: &[u16], value: u16) -> usize {
+    bases.partition_point(|&base| base <= value) - 1
+}
+
+/// Returns the fixed huffman code for a literal/length symbol as `(code, length)`
+///
+/// The code is returned bit-reversed, ready to be written least significant bit first
+fn fixed_literal_code(symbol: u16) -> (u16, u8) {
+    let (code, length) = match symbol {
+        0..=143 => (0x30 + symbol, 8),
+        144..=255 => (0x190 + symbol - 144, 9),
+        256..=279 => (symbol - 256, 7),
+        _ => (0xC0 + symbol - 280, 8),
+    };
+    (reverse_bits(code, length), length)
+}
+
+/// Reverses the lowest `length` bits of `code`
+pub(super) fn reverse_bits(code: u16, length: u8) -> u16 {
+    code.reverse_bits() >> (16 - u32::from(length))
+}
+
+/// Accumulates bits least significant bit first
+#[derive(Debug, Default)]
+struct BitWriter {
+    /// Bits that haven't filled a byte yet
+    accumulator: u64,
+    /// Number of valid bits in `accumulator`
+    count: u32,
+    /// Completed bytes waiting to be written out
+    bytes: Vec<u8>,
+}
+
+impl BitWriter {
+    /// Appends the lowest `count` bits of `value`
+    fn write_bits(&mut self, value: u32, count: u8) {
+        self.accumulator |= u64::from(value) << self.count;
+        self.count += u32::from(count);
+        while self.count >= 8 {
+            self.bytes.push((self.accumulator & 0xFF) as u8);
+            self.accumulator >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    /// Pads with zero bits up to the next byte boundary
+    fn align(&mut self) {
+        if self.count > 0 {
+            self.write_bits(0, (8 - self.count) as u8);
+        }
+    }
+}
+
+/// Streaming deflate encoder
+///
+/// Compressed output is written to the writer passed to [`Deflater::write`] and
+/// [`Deflater::finish`] a block at a time.
+#[derive(Debug)]
+pub(crate) struct Deflater {
+    /// Up to [`WINDOW_SIZE`] bytes of history followed by input not yet encoded
+    buffer: Vec<u8>,
+    /// Number of bytes at the start of `buffer` that have already been encoded
+    history: usize,
+    /// Absolute stream position of `buffer[0]`
+    base: u64,
+    /// Most recent absolute position (plus one) for each hash, zero meaning none
+    head: Vec<u64>,
+    /// Previous absolute position (plus one) with the same hash, indexed by position in the window
+    prev: Vec<u64>,
+    /// Output bit stream
+    bits: BitWriter,
+}
+
+impl Deflater {
+    /// Creates a new encoder
+    pub(crate) fn new() -> Self {
+        Self {
+            buffer: Vec::with_capacity(WINDOW_SIZE + BLOCK_SIZE),
+            history: 0,
+            base: 0,
+            head: vec![0; 1 << HASH_BITS],
+            prev: vec![0; WINDOW_SIZE],
+            bits: BitWriter::default(),
+        }
+    }
+
+    /// Feeds data into the encoder, writing out any blocks that have been completed
+    pub(crate) fn write(&mut self, mut data: &[u8], output: &mut impl Write) -> io::Result<()> {
+        while !data.is_empty() {
+            let space = BLOCK_SIZE - (self.buffer.len() - self.history);
+            let (now, later) = data.split_at(space.min(data.len()));
+            self.buffer.extend_from_slice(now);
+            data = later;
+            if self.buffer.len() - self.history >= BLOCK_SIZE {
+                self.encode_block(false);
+                output.write_all(&self.bits.bytes)?;
+                self.bits.bytes.clear();
+            }
+        }
+        Ok(())
+    }
+
+    /// Encodes any remaining input as the final block and flushes it to `output`
+    pub(crate) fn finish(&mut self, output: &mut impl Write) -> io::Result<()> {
+        self.encode_block(true);
+        self.bits.align();
+        output.write_all(&self.bits.bytes)?;
+        self.bits.bytes.clear();
+        Ok(())
+    }
+
+    /// Hashes the three bytes starting at `index` in the buffer
+    fn hash(&self, index: usize) -> usize {
+        let bytes = [
+            self.buffer[index],
+            self.buffer[index + 1],
+            self.buffer[index + 2],
+            0,
+        ];
+        (u32::from_le_bytes(bytes).wrapping_mul(0x9E37_79B1) >> (32 - HASH_BITS)) as usize
+    }
+
+    /// Records the position `index` in the match finder's hash chains
+    fn insert(&mut self, index: usize, end: usize) {
+        if index + MIN_MATCH <= end {
+            let hash = self.hash(index);
+            let absolute = self.base + index as u64;
+            self.prev[(absolute % WINDOW_SIZE as u64) as usize] = self.head[hash];
+            self.head[hash] = absolute + 1;
+        }
+    }
+
+    /// Finds the longest match for the data at `index`, returning `(length, distance)`
+    fn longest_match(&self, index: usize, end: usize) -> (usize, usize) {
+        let mut best = (0, 0);
+        if index + MIN_MATCH > end {
+            return best;
+        }
+        let absolute = self.base + index as u64;
+        let max_length = MAX_MATCH.min(end - index);
+        let mut candidate = self.head[self.hash(index)];
+        let mut chain = MAX_CHAIN;
+        while candidate != 0 && chain > 0 {
+            let position = candidate - 1;
+            if position < self.base
+                || position >= absolute
+                || absolute - position > WINDOW_SIZE as u64
+            {
+                break;
+            }
+            let start = (position - self.base) as usize;
+            let length = self.buffer[start..start + max_length]
+                .iter()
+                .zip(&self.buffer[index..index + max_length])
+                .take_while(|(a, b)| a == b)
+                .count();
+            if length > best.0 {
+                best = (length, (absolute - position) as usize);
+                if length == max_length {
+                    break;
+                }
+            }
+            candidate = self.prev[(position % WINDOW_SIZE as u64) as usize];
+            chain -= 1;
+        }
+        best
+    }
+
+    /// Encodes all pending input as one block, then slides the window forward
+    fn encode_block(&mut self, last: bool) {
+        let start = self.history;
+        let end = self.buffer.len();
+        // Run the match finder over the pending input
+        let mut tokens = Vec::with_capacity(end - start);
+        let mut index = start;
+        while index < end {
+            let (length, distance) = self.longest_match(index, end);
+            if length >= MIN_MATCH {
+                tokens.push(Token::Match {
+                    length: length as u16,
+                    distance: distance as u16,
+                });
+                for position in index..index + length {
+                    self.insert(position, end);
+                }
+                index += length;
+            } else {
+                tokens.push(Token::Literal(self.buffer[index]));
+                self.insert(index, end);
+                index += 1;
+            }
+        }
+        // Pick whichever of a fixed huffman or stored block is smaller
+        let fixed_bits: usize =
+            3 + tokens.iter().map(|token| token_cost(*token)).sum::<usize>() + 7;
+        let stored_bits = ((end - start) / MAX_STORED + 1) * (3 + 7 + 32) + (end - start) * 8;
+        if fixed_bits <= stored_bits {
+            self.bits.write_bits(u32::from(last), 1);
+            self.bits.write_bits(1, 2);
+            for token in tokens {
+                self.write_token(token);
+            }
+            let (code, length) = fixed_literal_code(END_OF_BLOCK);
+            self.bits.write_bits(u32::from(code), length);
+        } else {
+            self.write_stored(start, end, last);
+        }
+        // Keep at most a window's worth of history around
+        if self.buffer.len() > WINDOW_SIZE {
+            let drop = self.buffer.len() - WINDOW_SIZE;
+            self.buffer.drain(..drop);
+            self.base += drop as u64;
+        }
+        self.history = self.buffer.len();
+    }
+
+    /// Writes `buffer[start..end]` as one or more stored blocks
+    fn write_stored(&mut self, start: usize, end: usize, last: bool) {
+        let mut chunk_start = start;
+        loop {
+            let chunk_end = end.min(chunk_start + MAX_STORED);
+            let final_chunk = chunk_end == end;
+            self.bits.write_bits(u32::from(last && final_chunk), 1);
+            self.bits.write_bits(0, 2);
+            self.bits.align();
+            let length = (chunk_end - chunk_start) as u16;
+            self.bits.bytes.extend_from_slice(&length.to_le_bytes());
+            self.bits.bytes.extend_from_slice(&(!length).to_le_bytes());
+            self.bits
+                .bytes
+                .extend_from_slice(&self.buffer[chunk_start..chunk_end]);
+            if final_chunk {
+                break;
+            }
+            chunk_start = chunk_end;
+        }
+    }
+
+    /// Writes a single token using the fixed huffman codes
+    fn write_token(&mut self, token: Token) {
+        match token {
+            Token::Literal(byte) => {
+                let (code, length) = fixed_literal_code(u16::from(byte));
+                self.bits.write_bits(u32::from(code), length);
+            }
+            Token::Match { length, distance } => {
+                let index = symbol_index(&LENGTH_BASE, length);
+                let (code, code_length) = fixed_literal_code(257 + index as u16);
+                self.bits.write_bits(u32::from(code), code_length);
+                self.bits
+                    .write_bits(u32::from(length - LENGTH_BASE[index]), LENGTH_EXTRA[index]);
+                let index = symbol_index(&DISTANCE_BASE, distance);
+                self.bits
+                    .write_bits(u32::from(reverse_bits(index as u16, 5)), 5);
+                self.bits.write_bits(
+                    u32::from(distance - DISTANCE_BASE[index]),
+                    DISTANCE_EXTRA[index],
+                );
+            }
+        }
+    }
+}
+
+/// Number of bits a token takes up when encoded with the fixed huffman codes
+fn token_cost(token: Token) -> usize {
+    match token {
+        Token::Literal(byte) => usize::from(fixed_literal_code(u16::from(byte)).1),
+        Token::Match { length, distance } => {
+            let length_index = symbol_index(&LENGTH_BASE, length);
+            let distance_index = symbol_index(&DISTANCE_BASE, distance);
+            let code_length = fixed_literal_code(257 + length_index as u16).1;
+            usize::from(code_length)
+                + usize::from(LENGTH_EXTRA[length_index])
+                + 5
+                + usize::from(DISTANCE_EXTRA[distance_index])
+        }
+    }
+}
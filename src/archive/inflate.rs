@@ -0,0 +1,421 @@
+//! Streaming DEFLATE ([RFC 1951](https://www.rfc-editor.org/rfc/rfc1951)) decoder
+//!
+//! The decoder only keeps the last window of output around, so arbitrarily large entries can be
+//! decompressed in constant memory.
+
+// Everything here is bit twiddling on values whose ranges are bounded by the format
+#![allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_possible_wrap,
+    clippy::cast_sign_loss
+)]
+
+use std::io::{self, Read};
+
+use super::deflate::{DISTANCE_BASE, DISTANCE_EXTRA, LENGTH_BASE, LENGTH_EXTRA, WINDOW_SIZE};
+
+/// Longest code length allowed by deflate
+const MAX_BITS: usize = 15;
+/// Order in which code length code lengths are transmitted in a dynamic block header
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+/// Amount of output decoded ahead of the reader before we stop and hand it over
+const OUTPUT_CHUNK: usize = 16 * 1024;
+
+/// Shorthand for the error returned on malformed input
+fn corrupt(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// A canonical huffman code, decoded one bit at a time
+#[derive(Debug)]
+struct Huffman {
+    /// Number of codes of each length
+    counts: [u16; MAX_BITS + 1],
+    /// Symbols ordered by their code
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    /// Builds the code for the given code lengths, indexed by symbol
+    fn new(lengths: &[u8]) -> io::Result<Self> {
+        let mut counts = [0_u16; MAX_BITS + 1];
+        for &length in lengths {
+            counts[usize::from(length)] += 1;
+        }
+        counts[0] = 0;
+        // Reject over-subscribed codes, incomplete ones are allowed by the format
+        let mut left: i32 = 1;
+        for &count in &counts[1..] {
+            left = (left << 1) - i32::from(count);
+            if left < 0 {
+                return Err(corrupt("over-subscribed huffman code"));
+            }
+        }
+        let mut offsets = [0_u16; MAX_BITS + 2];
+        for length in 1..=MAX_BITS {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                symbols[usize::from(offsets[usize::from(length)])] = symbol as u16;
+                offsets[usize::from(length)] += 1;
+            }
+        }
+        Ok(Self { counts, symbols })
+    }
+}
+
+/// Reads bits least significant bit first from a byte stream
+#[derive(Debug)]
+struct BitReader<R> {
+    /// The compressed stream
+    inner: R,
+    /// Buffered input bytes
+    buffer: Box<[u8]>,
+    /// Position of the next unread byte in `buffer`
+    position: usize,
+    /// Number of valid bytes in `buffer`
+    filled: usize,
+    /// Bits read from the stream but not yet consumed
+    bits: u32,
+    /// Number of valid bits in `bits`
+    count: u32,
+}
+
+impl<R: Read> BitReader<R> {
+    /// Wraps a byte stream
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            buffer: vec![0; 8 * 1024].into_boxed_slice(),
+            position: 0,
+            filled: 0,
+            bits: 0,
+            count: 0,
+        }
+    }
+
+    /// Reads the next whole byte from the stream
+    fn byte(&mut self) -> io::Result<u8> {
+        if self.position == self.filled {
+            self.filled = loop {
+                match self.inner.read(&mut self.buffer) {
+                    Ok(0) => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            "deflate stream ended early",
+                        ))
+                    }
+                    Ok(read) => break read,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                    Err(e) => return Err(e),
+                }
+            };
+            self.position = 0;
+        }
+        let byte = self.buffer[self.position];
+        self.position += 1;
+        Ok(byte)
+    }
+
+    /// Consumes `count` bits from the stream
+    fn bits(&mut self, count: u32) -> io::Result<u32> {
+        while self.count < count {
+            self.bits |= u32::from(self.byte()?) << self.count;
+            self.count += 8;
+        }
+        let value = self.bits & ((1_u64 << count) - 1) as u32;
+        self.bits = self.bits.checked_shr(count).unwrap_or(0);
+        self.count -= count;
+        Ok(value)
+    }
+
+    /// Discards bits up to the next byte boundary
+    fn align(&mut self) {
+        self.bits = 0;
+        self.count = 0;
+    }
+
+    /// Decodes a single symbol with the given code
+    fn decode(&mut self, huffman: &Huffman) -> io::Result<u16> {
+        let mut code: i32 = 0;
+        let mut first: i32 = 0;
+        let mut index: i32 = 0;
+        for &count in &huffman.counts[1..] {
+            code |= self.bits(1)? as i32;
+            let count = i32::from(count);
+            if code - count < first {
+                return Ok(huffman.symbols[(index + (code - first)) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(corrupt("invalid huffman code"))
+    }
+}
+
+/// Where the decoder is within the stream
+#[derive(Debug)]
+enum State {
+    /// Expecting a block header
+    Header,
+    /// Inside a stored block with this many bytes left
+    Stored(usize),
+    /// Inside a huffman coded block
+    Codes {
+        /// Literal/length code
+        literals: Huffman,
+        /// Distance code
+        distances: Huffman,
+    },
+    /// The final block has been fully decoded
+    Done,
+}
+
+/// Streaming deflate decoder, exposing the decompressed data through [`Read`]
+#[derive(Debug)]
+pub(crate) struct Inflater<R> {
+    /// The compressed input
+    input: BitReader<R>,
+    /// Up to a window of already returned output, followed by output not yet returned
+    window: Vec<u8>,
+    /// Index of the first byte of `window` not yet returned
+    position: usize,
+    /// Decoder state
+    state: State,
+    /// Whether the current block is the last one
+    last_block: bool,
+}
+
+impl<R: Read> Inflater<R> {
+    /// Creates a decoder reading compressed data from `input`
+    pub(crate) fn new(input: R) -> Self {
+        Self {
+            input: BitReader::new(input),
+            window: Vec::with_capacity(WINDOW_SIZE * 3),
+            position: 0,
+            state: State::Header,
+            last_block: false,
+        }
+    }
+
+    /// Decodes until at least [`OUTPUT_CHUNK`] bytes are pending or the stream ends
+    fn fill(&mut self) -> io::Result<()> {
+        // Forget history that can no longer be referenced
+        if self.position > WINDOW_SIZE * 2 {
+            let drop = self.position - WINDOW_SIZE;
+            self.window.drain(..drop);
+            self.position -= drop;
+        }
+        while self.window.len() - self.position < OUTPUT_CHUNK {
+            match &mut self.state {
+                State::Done => break,
+                State::Header => {
+                    if self.last_block {
+                        self.state = State::Done;
+                        continue;
+                    }
+                    self.last_block = self.input.bits(1)? == 1;
+                    self.state = match self.input.bits(2)? {
+                        0 => {
+                            self.input.align();
+                            let mut header = [0_u8; 4];
+                            for byte in &mut header {
+                                *byte = self.input.byte()?;
+                            }
+                            let length = u16::from_le_bytes([header[0], header[1]]);
+                            let inverse = u16::from_le_bytes([header[2], header[3]]);
+                            if length != !inverse {
+                                return Err(corrupt("stored block length mismatch"));
+                            }
+                            State::Stored(usize::from(length))
+                        }
+                        1 => fixed_codes()?,
+                        2 => self.dynamic_codes()?,
+                        _ => return Err(corrupt("invalid block type")),
+                    };
+                }
+                State::Stored(remaining) => {
+                    if *remaining == 0 {
+                        self.state = State::Header;
+                        continue;
+                    }
+                    *remaining -= 1;
+                    let byte = self.input.byte()?;
+                    self.window.push(byte);
+                }
+                State::Codes {
+                    literals,
+                    distances,
+                } => {
+                    let symbol = self.input.decode(literals)?;
+                    match symbol {
+                        0..=255 => self.window.push(symbol as u8),
+                        256 => self.state = State::Header,
+                        257..=285 => {
+                            let index = usize::from(symbol - 257);
+                            let length = usize::from(LENGTH_BASE[index])
+                                + self.input.bits(u32::from(LENGTH_EXTRA[index]))? as usize;
+                            let index = usize::from(self.input.decode(distances)?);
+                            if index >= DISTANCE_BASE.len() {
+                                return Err(corrupt("invalid distance symbol"));
+                            }
+                            let distance = usize::from(DISTANCE_BASE[index])
+                                + self.input.bits(u32::from(DISTANCE_EXTRA[index]))? as usize;
+                            if distance > self.window.len() {
+                                return Err(corrupt("distance too far back"));
+                            }
+                            let start = self.window.len() - distance;
+                            for offset in 0..length {
+                                let byte = self.window[start + offset];
+                                self.window.push(byte);
+                            }
+                        }
+                        _ => return Err(corrupt("invalid literal/length symbol")),
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the code definitions of a dynamic huffman block
+    fn dynamic_codes(&mut self) -> io::Result<State> {
+        let literal_count = self.input.bits(5)? as usize + 257;
+        let distance_count = self.input.bits(5)? as usize + 1;
+        let code_length_count = self.input.bits(4)? as usize + 4;
+        if literal_count > 286 || distance_count > 30 {
+            return Err(corrupt("too many codes in dynamic block"));
+        }
+        let mut code_lengths = [0_u8; 19];
+        for &index in &CODE_LENGTH_ORDER[..code_length_count] {
+            code_lengths[index] = self.input.bits(3)? as u8;
+        }
+        let code_length_code = Huffman::new(&code_lengths)?;
+        let mut lengths = Vec::with_capacity(literal_count + distance_count);
+        while lengths.len() < literal_count + distance_count {
+            let symbol = self.input.decode(&code_length_code)?;
+            let (value, repeat) = match symbol {
+                0..=15 => (symbol as u8, 1),
+                16 => {
+                    let previous = *lengths
+                        .last()
+                        .ok_or_else(|| corrupt("repeat with no previous length"))?;
+                    (previous, 3 + self.input.bits(2)?)
+                }
+                17 => (0, 3 + self.input.bits(3)?),
+                _ => (0, 11 + self.input.bits(7)?),
+            };
+            for _ in 0..repeat {
+                lengths.push(value);
+            }
+        }
+        if lengths.len() > literal_count + distance_count {
+            return Err(corrupt("code lengths overflow the block header"));
+        }
+        if lengths[256] == 0 {
+            return Err(corrupt("missing end of block code"));
+        }
+        Ok(State::Codes {
+            literals: Huffman::new(&lengths[..literal_count])?,
+            distances: Huffman::new(&lengths[literal_count..])?,
+        })
+    }
+}
+
+/// The state for a block using the fixed huffman codes
+fn fixed_codes() -> io::Result<State> {
+    let mut lengths = [0_u8; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    Ok(State::Codes {
+        literals: Huffman::new(&lengths)?,
+        distances: Huffman::new(&[5; 30])?,
+    })
+}
+
+impl<R: Read> Read for Inflater<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position == self.window.len() {
+            self.fill()?;
+        }
+        let available = &self.window[self.position..];
+        let count = available.len().min(buf.len());
+        buf[..count].copy_from_slice(&available[..count]);
+        self.position += count;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::{super::deflate::Deflater, *};
+
+    /// Compresses `data` with our encoder, feeding it through in uneven pieces
+    fn compress(data: &[u8]) -> Vec<u8> {
+        let mut deflater = Deflater::new();
+        let mut output = Vec::new();
+        for chunk in data.chunks(7919) {
+            deflater.write(chunk, &mut output).unwrap();
+        }
+        deflater.finish(&mut output).unwrap();
+        output
+    }
+
+    /// Decompresses `data` with our decoder
+    fn decompress(data: &[u8]) -> Vec<u8> {
+        let mut output = Vec::new();
+        Inflater::new(data).read_to_end(&mut output).unwrap();
+        output
+    }
+
+    // Round trip data that exercises matches, literals, and stored blocks
+    #[test]
+    fn round_trip() {
+        let text: Vec<u8> = (0..50_000)
+            .flat_map(|i| format!("config option {} = {}\n", i % 97, i % 13).into_bytes())
+            .collect();
+        // xorshift noise, which the encoder should fall back to stored blocks for
+        let mut state = 0x2545_F491_u32;
+        let noise: Vec<u8> = (0..200_000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect();
+        for data in [&b""[..], b"a", &text, &noise] {
+            let compressed = compress(data);
+            assert_eq!(decompress(&compressed), data);
+        }
+        assert!(compress(&text).len() < text.len() / 4);
+        assert!(compress(&noise).len() < noise.len() + noise.len() / 100);
+    }
+
+    // A dynamic huffman stream produced by zlib (python's `zlib.compress(..., wbits=-15)`)
+    #[test]
+    fn dynamic_block() {
+        let compressed = [
+            0xb5, 0x8d, 0x4b, 0x16, 0x83, 0x20, 0x10, 0x04, 0xaf, 0xd2, 0xb9, 0x80, 0xe7, 0xc8,
+            0x32, 0x0b, 0x2f, 0x00, 0x3a, 0x20, 0x09, 0x32, 0x91, 0xaf, 0x70, 0x7a, 0xe7, 0xe5,
+            0xe5, 0x0a, 0xae, 0xab, 0xba, 0x7a, 0xde, 0x08, 0x47, 0x71, 0xcb, 0x07, 0x3a, 0x72,
+            0x0b, 0x30, 0x7c, 0xe2, 0x5d, 0xf6, 0x6f, 0x02, 0x57, 0x8a, 0xc8, 0x82, 0xbd, 0x1a,
+            0x1d, 0x2b, 0xdb, 0x09, 0xf3, 0x6d, 0xf2, 0x4b, 0x89, 0xb7, 0x77, 0x68, 0x91, 0x9a,
+            0xcb, 0x1b, 0x8c, 0xab, 0x24, 0x68, 0x50, 0x80, 0x77, 0x47, 0xe1, 0x28, 0x5b, 0x9b,
+            0x1e, 0x78, 0x72, 0x43, 0xa5, 0xd3, 0x05, 0xeb, 0xfb, 0x3f, 0xbf, 0x2a, 0x93, 0x31,
+            0x48, 0x47, 0x95, 0x7e, 0x07, 0xd3, 0x05,
+        ];
+        let expected = format!(
+            "{}Pack my box with five dozen liquor jugs! How vexingly quick daft zebras jump.",
+            "The quick brown fox jumps over the lazy dog. ".repeat(3)
+        );
+        assert_eq!(decompress(&compressed), expected.as_bytes());
+    }
+}
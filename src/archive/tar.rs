@@ -0,0 +1,158 @@
+//! Streaming tar archive writer
+//!
+//! Entries are written in the ustar format, which every tar implementation reads. Each entry's
+//! size goes in its header, ahead of the data, so it has to be known when the entry is added.
+//! Owners and timestamps are left zeroed, as they mean nothing on the machine unpacking the
+//! archive.
+
+use std::io::{self, Read, Write};
+
+use tracing::debug;
+
+/// The size of tar headers and of the blocks data is padded to
+const BLOCK: usize = 512;
+
+/// The longest name that fits in a header's name field
+const NAME_LENGTH: usize = 100;
+
+/// The longest directory that fits in a header's prefix field
+const PREFIX_LENGTH: usize = 155;
+
+/// Streaming tar archive writer
+///
+/// Add entries with [`TarWriter::append`], then call [`TarWriter::finish`] to write the end of
+/// the archive.
+#[derive(Debug)]
+pub struct TarWriter<W: Write> {
+    /// The underlying writer
+    writer: W,
+}
+
+impl<W: Write> TarWriter<W> {
+    /// Creates a new archive writing to `writer`
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    /// Adds a regular file at `name` with the given unix permissions, streaming exactly `size`
+    /// bytes of contents from `reader`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the name doesn't fit in a ustar header, `reader` ends early, or
+    /// either stream fails
+    pub fn append(
+        &mut self,
+        name: &str,
+        mode: u32,
+        size: u64,
+        reader: &mut impl Read,
+    ) -> io::Result<()> {
+        debug!(%name, size, "Adding entry");
+        self.writer.write_all(&header(name, mode, size)?)?;
+        let copied = io::copy(&mut reader.take(size), &mut self.writer)?;
+        if copied != size {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("{name} ended after {copied} of {size} bytes"),
+            ));
+        }
+        // Data is padded out to a whole block
+        let padding = size.next_multiple_of(BLOCK as u64) - size;
+        io::copy(&mut io::repeat(0).take(padding), &mut self.writer).map(drop)
+    }
+
+    /// Writes the two empty blocks that end an archive, returning the underlying writer
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the underlying writer fails
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.write_all(&[0; BLOCK * 2])?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Splits a name into the prefix and name fields of a header, at a `/` if it is too long for
+/// the name field alone
+fn split_name(name: &str) -> Option<(&str, &str)> {
+    if name.len() <= NAME_LENGTH {
+        return Some(("", name));
+    }
+    name.match_indices('/')
+        .map(|(index, _)| (&name[..index], &name[index + 1..]))
+        .find(|(prefix, rest)| prefix.len() <= PREFIX_LENGTH && rest.len() <= NAME_LENGTH)
+}
+
+/// Writes `value` into a header field as zero padded octal, followed by a NUL
+fn octal(field: &mut [u8], value: u64) {
+    let digits = format!("{value:0width$o}", width = field.len() - 1);
+    field[..digits.len()].copy_from_slice(digits.as_bytes());
+}
+
+/// Builds the ustar header of a regular file
+fn header(name: &str, mode: u32, size: u64) -> io::Result<[u8; BLOCK]> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidInput, message.to_owned());
+    let (prefix, rest) = split_name(name).ok_or_else(|| invalid("Name too long for tar"))?;
+    if size >= 8_u64.pow(11) {
+        return Err(invalid("File too large for tar"));
+    }
+    let mut header = [0; BLOCK];
+    header[..rest.len()].copy_from_slice(rest.as_bytes());
+    octal(&mut header[100..108], u64::from(mode & 0o7777));
+    octal(&mut header[108..116], 0);
+    octal(&mut header[116..124], 0);
+    octal(&mut header[124..136], size);
+    octal(&mut header[136..148], 0);
+    header[156] = b'0';
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+    header[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
+    // The checksum is taken with its own field filled with spaces
+    header[148..156].fill(b' ');
+    let checksum: u32 = header.iter().map(|&byte| u32::from(byte)).sum();
+    let digits = format!("{checksum:06o}\0 ");
+    header[148..156].copy_from_slice(digits.as_bytes());
+    Ok(header)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    // Entries are a header and their padded data, and long names are split into the prefix
+    #[test]
+    fn append() {
+        let mut tar = TarWriter::new(Vec::new());
+        tar.append("start.sh", 0o755, 5, &mut &b"hello"[..])
+            .unwrap();
+        let long = format!("{}/{}", "d".repeat(120), "f".repeat(90));
+        tar.append(&long, 0o644, 0, &mut &b""[..]).unwrap();
+        assert!(tar.append("short.txt", 0o644, 10, &mut &b"hi"[..]).is_err());
+        let bytes = tar.finish().unwrap();
+
+        assert_eq!(&bytes[..8], b"start.sh");
+        assert_eq!(&bytes[100..107], b"0000755");
+        assert_eq!(&bytes[124..135], b"00000000005");
+        assert_eq!(&bytes[257..262], b"ustar");
+        let sum: u32 = bytes[..BLOCK]
+            .iter()
+            .enumerate()
+            .map(|(index, &byte)| {
+                if (148..156).contains(&index) {
+                    32
+                } else {
+                    u32::from(byte)
+                }
+            })
+            .sum();
+        let recorded = std::str::from_utf8(&bytes[148..154]).unwrap();
+        assert_eq!(u32::from_str_radix(recorded, 8).unwrap(), sum);
+        assert_eq!(&bytes[BLOCK..BLOCK + 5], b"hello");
+
+        let second = &bytes[BLOCK * 2..BLOCK * 3];
+        assert_eq!(&second[..90], "f".repeat(90).as_bytes());
+        assert_eq!(&second[345..465], "d".repeat(120).as_bytes());
+    }
+}
@@ -0,0 +1,238 @@
+//! Zip archive support
+//!
+//! Both halves work on streams: the writer never seeks and never holds more than a deflate block
+//! of any one file in memory, and the reader decompresses entries on the fly.
+//!
+//! Entry names are handled explicitly rather than trusting defaults, as community packs routinely
+//! contain non-ASCII file names. Names are always written as UTF-8 (flagged as such when they
+//! aren't plain ASCII), and the reader honors the UTF-8 flag, the Info-ZIP unicode path extra
+//! field, and falls back to the legacy CP437 encoding, recording which one it used.
+
+use std::{io, time::SystemTime};
+
+use snafu::Snafu;
+
+mod deterministic;
+mod read;
+mod write;
+
+pub use deterministic::DeterministicZipWriter;
+pub use read::{NameEncoding, ZipArchive, ZipEntry, ZipEntryReader};
+pub use write::{FileOptions, ZipWriter};
+
+/// Signature of a local file header
+const LOCAL_FILE_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+/// Signature of a data descriptor
+const DATA_DESCRIPTOR_SIGNATURE: u32 = 0x0807_4b50;
+/// Signature of a central directory file header
+const CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0201_4b50;
+/// Signature of the zip64 end of central directory record
+const ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0606_4b50;
+/// Signature of the zip64 end of central directory locator
+const ZIP64_LOCATOR_SIGNATURE: u32 = 0x0706_4b50;
+/// Signature of the end of central directory record
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+/// Header ID of the zip64 extended information extra field
+const ZIP64_EXTRA_FIELD: u16 = 0x0001;
+/// Header ID of the Info-ZIP unicode path extra field
+const UNICODE_PATH_EXTRA_FIELD: u16 = 0x7075;
+/// Marker value for 32-bit fields that have been moved into the zip64 extra field
+const ZIP64_MARKER: u32 = 0xFFFF_FFFF;
+/// Version needed to extract a plain deflate/stored entry (2.0)
+const VERSION_DEFAULT: u16 = 20;
+/// Version needed to extract an entry using zip64 (4.5)
+const VERSION_ZIP64: u16 = 45;
+/// "Version made by" host system for unix, so that readers honor our permissions
+const HOST_UNIX: u16 = 3 << 8;
+/// General purpose flag: crc and sizes follow the data in a data descriptor
+const FLAG_DATA_DESCRIPTOR: u16 = 1 << 3;
+/// General purpose flag: the file name is UTF-8
+const FLAG_UTF8: u16 = 1 << 11;
+
+/// The compression method used for a single entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Compression {
+    /// Store the data as is
+    Stored,
+    /// Compress the data with deflate
+    Deflated,
+}
+
+impl Compression {
+    /// Picks a compression method for a file based on its extension
+    ///
+    /// Formats that are already compressed (jars, zips, images, audio) gain nothing from being
+    /// deflated again, so they are stored, and everything else is deflated
+    pub fn for_path(path: &str) -> Self {
+        /// Extensions of formats that are already compressed
+        const STORED_EXTENSIONS: &[&str] = &[
+            "jar", "zip", "mrpack", "litemod", "png", "jpg", "jpeg", "gif", "webp", "ogg", "mp3",
+            "gz", "xz", "bz2", "zst", "7z",
+        ];
+        let extension = path
+            .rsplit_once('.')
+            .map(|(_, extension)| extension.to_ascii_lowercase());
+        match extension {
+            Some(extension) if STORED_EXTENSIONS.contains(&extension.as_str()) => Self::Stored,
+            _ => Self::Deflated,
+        }
+    }
+
+    /// The method id stored in the zip headers
+    fn method(self) -> u16 {
+        match self {
+            Compression::Stored => 0,
+            Compression::Deflated => 8,
+        }
+    }
+}
+
+/// A timestamp in the MS-DOS format used by zip headers
+///
+/// This has a two second resolution and can only represent the years 1980 through 2107
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DosDateTime {
+    /// Packed date (`yyyyyyym mmmddddd`, years since 1980)
+    date: u16,
+    /// Packed time (`hhhhhmmm mmmsssss`, seconds halved)
+    time: u16,
+}
+
+impl DosDateTime {
+    /// Midnight on the first of January 1980, the earliest representable time
+    pub const EPOCH: Self = Self {
+        date: (1 << 5) | 1,
+        time: 0,
+    };
+
+    /// Converts a [`SystemTime`] (interpreted as UTC), clamping it to the representable range
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_possible_wrap,
+        clippy::cast_sign_loss
+    )]
+    pub fn from_system_time(time: SystemTime) -> Self {
+        let seconds = match time.duration_since(SystemTime::UNIX_EPOCH) {
+            Ok(duration) => duration.as_secs(),
+            Err(_) => return Self::EPOCH,
+        };
+        let (year, month, day) = civil_from_days((seconds / 86400) as i64);
+        if year < 1980 {
+            return Self::EPOCH;
+        }
+        if year > 2107 {
+            return Self {
+                date: (127 << 9) | (12 << 5) | 31,
+                time: (23 << 11) | (59 << 5) | (58 / 2),
+            };
+        }
+        let seconds_of_day = seconds % 86400;
+        Self {
+            date: (((year - 1980) as u16) << 9) | ((month as u16) << 5) | day as u16,
+            time: (((seconds_of_day / 3600) as u16) << 11)
+                | ((((seconds_of_day / 60) % 60) as u16) << 5)
+                | ((seconds_of_day % 60) / 2) as u16,
+        }
+    }
+
+    /// The current time
+    pub fn now() -> Self {
+        Self::from_system_time(SystemTime::now())
+    }
+
+    /// Constructs a timestamp from the packed header fields
+    fn from_parts(date: u16, time: u16) -> Self {
+        Self { date, time }
+    }
+
+    /// The packed date field
+    fn date(self) -> u16 {
+        self.date
+    }
+
+    /// The packed time field
+    fn time(self) -> u16 {
+        self.time
+    }
+}
+
+impl Default for DosDateTime {
+    fn default() -> Self {
+        Self::EPOCH
+    }
+}
+
+/// Converts days since the unix epoch into a `(year, month, day)` civil date
+///
+/// Uses Howard Hinnant's `civil_from_days` algorithm
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Error that occurs while working with a zip archive
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum ZipError {
+    /// Error reading from or writing to the underlying stream
+    #[snafu(display("I/O error while processing zip archive"))]
+    Io {
+        /// Underlying I/O error
+        source: io::Error,
+    },
+    /// An entry name was used twice
+    #[snafu(display("Duplicate zip entry: {}", name))]
+    DuplicateEntry {
+        /// The duplicated name
+        name: String,
+    },
+    /// An entry name doesn't fit in a zip header
+    #[snafu(display("Zip entry name is too long: {}", name))]
+    NameTooLong {
+        /// The offending name
+        name: String,
+    },
+    /// An entry name is not a valid relative path, or is flagged as UTF-8 but isn't
+    #[snafu(display("Invalid zip entry name: {}", name))]
+    InvalidName {
+        /// The offending name
+        name: String,
+    },
+    /// The archive is malformed
+    #[snafu(display("Invalid zip archive: {}", reason))]
+    InvalidArchive {
+        /// What was wrong with the archive
+        reason: &'static str,
+    },
+    /// An entry uses a compression method we don't support
+    #[snafu(display("Zip entry {} uses unsupported compression method {}", name, method))]
+    UnsupportedCompression {
+        /// The name of the entry
+        name: String,
+        /// The compression method id
+        method: u16,
+    },
+    /// An entry grew past 4 GiB without being declared as a large file
+    #[snafu(display(
+        "Zip entry {} is 4 GiB or larger but was not declared as a large file",
+        name
+    ))]
+    UndeclaredLargeFile {
+        /// The name of the entry
+        name: String,
+    },
+}
@@ -0,0 +1,156 @@
+//! Zip archives that are byte for byte the same for the same contents
+//!
+//! Ordinary archives record when and in which order their entries were written, and with which
+//! permissions the files had on disk, so exporting the same pack twice yields different bytes.
+//! Here every entry gets the same timestamp and permissions, and entries are written sorted by
+//! name once they are all known, so release artifacts can be checksummed.
+
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use snafu::ResultExt;
+use tracing::{debug, instrument};
+
+use super::{DosDateTime, FileOptions, IoSnafu, ZipError, ZipWriter, ZIP64_MARKER};
+
+/// The permissions every entry is recorded with
+const PERMISSIONS: u32 = 0o644;
+
+/// Where the contents of an entry come from
+#[derive(Debug)]
+enum Contents {
+    /// A file on disk, read when the archive is written
+    File {
+        /// The path of the file
+        path: PathBuf,
+        /// The size of the file when it was added
+        size: u64,
+    },
+    /// Contents held in memory
+    Bytes(Vec<u8>),
+}
+
+/// Zip archive writer whose output only depends on the entries' names and contents
+///
+/// Entries are collected with [`DeterministicZipWriter::add_file`] and
+/// [`DeterministicZipWriter::add_bytes`], and written sorted by name by
+/// [`DeterministicZipWriter::finish`], all dated [`DosDateTime::EPOCH`] with `0644`
+/// permissions. Files are only read when the archive is written, so they are never held in
+/// memory.
+#[derive(Debug)]
+pub struct DeterministicZipWriter<W: Write> {
+    /// The underlying writer
+    writer: W,
+    /// The entries to write, by name
+    entries: BTreeMap<String, Contents>,
+}
+
+impl<W: Write> DeterministicZipWriter<W> {
+    /// Creates a new archive writing to `writer`
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Adds an entry at `name` holding the file at `path`
+    ///
+    /// Adding a name again replaces its entry.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be found
+    pub fn add_file(
+        &mut self,
+        name: impl Into<String>,
+        path: impl Into<PathBuf>,
+    ) -> io::Result<()> {
+        let path = path.into();
+        let size = fs::metadata(&path)?.len();
+        self.entries
+            .insert(name.into(), Contents::File { path, size });
+        Ok(())
+    }
+
+    /// Adds an entry at `name` holding `contents`
+    ///
+    /// Adding a name again replaces its entry.
+    pub fn add_bytes(&mut self, name: impl Into<String>, contents: impl Into<Vec<u8>>) {
+        self.entries
+            .insert(name.into(), Contents::Bytes(contents.into()));
+    }
+
+    /// Writes the entries and the central directory, returning the underlying writer
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a file can't be read, a name isn't valid in a zip archive, or the
+    /// underlying writer fails
+    #[instrument(skip(self), fields(entries = self.entries.len()))]
+    pub fn finish(self) -> Result<W, ZipError> {
+        let mut zip = ZipWriter::new(self.writer);
+        for (name, contents) in self.entries {
+            let options = FileOptions::for_path(&name)
+                .last_modified(DosDateTime::EPOCH)
+                .unix_permissions(PERMISSIONS);
+            debug!(%name, "Writing entry");
+            match contents {
+                Contents::File { path, size } => {
+                    let options = options.large_file(size >= u64::from(ZIP64_MARKER));
+                    let mut file = File::open(path).context(IoSnafu)?;
+                    zip.write_file(&name, options, &mut file)?;
+                }
+                Contents::Bytes(bytes) => {
+                    let options = options.large_file(bytes.len() as u64 >= u64::from(ZIP64_MARKER));
+                    zip.write_file(&name, options, &mut &bytes[..])?;
+                }
+            }
+        }
+        zip.finish()
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::archive::{ZipArchive, ZipEntry};
+
+    // The same entries give the same bytes whatever order they were added in
+    #[test]
+    fn reproducible() {
+        let dir = std::env::temp_dir().join(format!("ffpack-deterministic-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("mod.jar"), b"mod").unwrap();
+
+        let mut first = DeterministicZipWriter::new(Vec::new());
+        first.add_bytes("modrinth.index.json", "{}");
+        first
+            .add_file("overrides/mods/mod.jar", dir.join("mod.jar"))
+            .unwrap();
+        let mut second = DeterministicZipWriter::new(Vec::new());
+        second
+            .add_file("overrides/mods/mod.jar", dir.join("mod.jar"))
+            .unwrap();
+        second.add_bytes("modrinth.index.json", "{}");
+        assert!(second
+            .add_file("missing.jar", dir.join("missing.jar"))
+            .is_err());
+        let (first, second) = (first.finish().unwrap(), second.finish().unwrap());
+        assert_eq!(first, second);
+
+        let archive = ZipArchive::new(Cursor::new(first)).unwrap();
+        let names: Vec<_> = archive.entries().iter().map(ZipEntry::name).collect();
+        assert_eq!(names, ["modrinth.index.json", "overrides/mods/mod.jar"]);
+        let entry = &archive.entries()[1];
+        assert_eq!(entry.last_modified(), DosDateTime::EPOCH);
+        assert_eq!(entry.unix_permissions(), Some(PERMISSIONS));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,703 @@
+//! Zip archive reader
+//!
+//! The central directory is parsed up front, and entries are then streamed out one at a time,
+//! decompressing on the fly and verifying their checksums once fully read.
+
+use std::io::{self, Read, Seek, SeekFrom, Take};
+
+use relative_path::RelativePathBuf;
+use snafu::{ensure, OptionExt, ResultExt};
+use tracing::{debug, instrument, trace, warn};
+
+use super::{
+    Compression, DosDateTime, InvalidArchiveSnafu, InvalidNameSnafu, IoSnafu,
+    UnsupportedCompressionSnafu, ZipError, CENTRAL_DIRECTORY_SIGNATURE,
+    END_OF_CENTRAL_DIRECTORY_SIGNATURE, FLAG_UTF8, LOCAL_FILE_HEADER_SIGNATURE,
+    UNICODE_PATH_EXTRA_FIELD, ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE, ZIP64_EXTRA_FIELD,
+    ZIP64_LOCATOR_SIGNATURE, ZIP64_MARKER,
+};
+use crate::archive::{crc32::Crc32, inflate::Inflater};
+
+/// Size of the fixed part of the end of central directory record
+const END_OF_CENTRAL_DIRECTORY_SIZE: usize = 22;
+/// Size of the zip64 end of central directory locator
+const ZIP64_LOCATOR_SIZE: usize = 20;
+/// Size of the fixed part of the zip64 end of central directory record
+const ZIP64_END_OF_CENTRAL_DIRECTORY_SIZE: usize = 56;
+/// Size of the fixed part of a central directory header
+const CENTRAL_DIRECTORY_HEADER_SIZE: usize = 46;
+/// Size of the fixed part of a local file header
+const LOCAL_FILE_HEADER_SIZE: usize = 30;
+/// Upper nibble of the "version made by" field for archives created on unix
+const HOST_UNIX: u8 = 3;
+
+/// The characters for bytes `0x80` through `0xFF` in code page 437
+const CP437_HIGH: [char; 128] = [
+    '\u{00C7}', '\u{00FC}', '\u{00E9}', '\u{00E2}', '\u{00E4}', '\u{00E0}', '\u{00E5}', '\u{00E7}',
+    '\u{00EA}', '\u{00EB}', '\u{00E8}', '\u{00EF}', '\u{00EE}', '\u{00EC}', '\u{00C4}', '\u{00C5}',
+    '\u{00C9}', '\u{00E6}', '\u{00C6}', '\u{00F4}', '\u{00F6}', '\u{00F2}', '\u{00FB}', '\u{00F9}',
+    '\u{00FF}', '\u{00D6}', '\u{00DC}', '\u{00A2}', '\u{00A3}', '\u{00A5}', '\u{20A7}', '\u{0192}',
+    '\u{00E1}', '\u{00ED}', '\u{00F3}', '\u{00FA}', '\u{00F1}', '\u{00D1}', '\u{00AA}', '\u{00BA}',
+    '\u{00BF}', '\u{2310}', '\u{00AC}', '\u{00BD}', '\u{00BC}', '\u{00A1}', '\u{00AB}', '\u{00BB}',
+    '\u{2591}', '\u{2592}', '\u{2593}', '\u{2502}', '\u{2524}', '\u{2561}', '\u{2562}', '\u{2556}',
+    '\u{2555}', '\u{2563}', '\u{2551}', '\u{2557}', '\u{255D}', '\u{255C}', '\u{255B}', '\u{2510}',
+    '\u{2514}', '\u{2534}', '\u{252C}', '\u{251C}', '\u{2500}', '\u{253C}', '\u{255E}', '\u{255F}',
+    '\u{255A}', '\u{2554}', '\u{2569}', '\u{2566}', '\u{2560}', '\u{2550}', '\u{256C}', '\u{2567}',
+    '\u{2568}', '\u{2564}', '\u{2565}', '\u{2559}', '\u{2558}', '\u{2552}', '\u{2553}', '\u{256B}',
+    '\u{256A}', '\u{2518}', '\u{250C}', '\u{2588}', '\u{2584}', '\u{258C}', '\u{2590}', '\u{2580}',
+    '\u{03B1}', '\u{00DF}', '\u{0393}', '\u{03C0}', '\u{03A3}', '\u{03C3}', '\u{00B5}', '\u{03C4}',
+    '\u{03A6}', '\u{0398}', '\u{03A9}', '\u{03B4}', '\u{221E}', '\u{03C6}', '\u{03B5}', '\u{2229}',
+    '\u{2261}', '\u{00B1}', '\u{2265}', '\u{2264}', '\u{2320}', '\u{2321}', '\u{00F7}', '\u{2248}',
+    '\u{00B0}', '\u{2219}', '\u{00B7}', '\u{221A}', '\u{207F}', '\u{00B2}', '\u{25A0}', '\u{00A0}',
+];
+
+/// How an entry's name was decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NameEncoding {
+    /// The name is plain ASCII, so the encoding doesn't matter
+    Ascii,
+    /// The entry has the UTF-8 flag set
+    Utf8,
+    /// The name was taken from an Info-ZIP unicode path extra field
+    UnicodePathField,
+    /// The entry isn't flagged, but the name is valid UTF-8
+    ///
+    /// Plenty of tools write UTF-8 without setting the flag, and CP437 text essentially never
+    /// happens to form valid multi-byte UTF-8, so this is treated as UTF-8
+    DetectedUtf8,
+    /// The name was decoded from code page 437, the encoding the zip specification defaults to
+    Cp437,
+}
+
+/// Decodes a name from code page 437
+fn decode_cp437(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&byte| {
+            if byte < 0x80 {
+                char::from(byte)
+            } else {
+                CP437_HIGH[usize::from(byte - 0x80)]
+            }
+        })
+        .collect()
+}
+
+/// Reads a little endian `u16` at `offset`
+fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+/// Reads a little endian `u32` at `offset`
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+/// Reads a little endian `u64` at `offset`
+fn u64_at(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+/// Iterates over the `(id, data)` pairs of an extra field block
+fn extra_fields(mut extra: &[u8]) -> impl Iterator<Item = (u16, &[u8])> {
+    std::iter::from_fn(move || {
+        if extra.len() < 4 {
+            return None;
+        }
+        let id = u16_at(extra, 0);
+        let length = usize::from(u16_at(extra, 2));
+        let data = extra.get(4..4 + length)?;
+        extra = &extra[4 + length..];
+        Some((id, data))
+    })
+}
+
+/// A single entry in a zip archive's central directory
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ZipEntry {
+    /// The decoded name
+    name: String,
+    /// The name exactly as it appears in the archive
+    raw_name: Vec<u8>,
+    /// How the name was decoded
+    encoding: NameEncoding,
+    /// The compression method id
+    method: u16,
+    /// CRC-32 of the uncompressed data
+    crc: u32,
+    /// Size of the data as stored in the archive
+    compressed_size: u64,
+    /// Size of the data once extracted
+    size: u64,
+    /// Offset of the local file header
+    header_offset: u64,
+    /// Modification time
+    last_modified: DosDateTime,
+    /// Unix permission bits, if the archive was created on unix
+    unix_permissions: Option<u32>,
+}
+
+impl ZipEntry {
+    /// The decoded name of the entry
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The name of the entry exactly as stored in the archive
+    pub fn raw_name(&self) -> &[u8] {
+        &self.raw_name
+    }
+
+    /// How the name of the entry was decoded
+    pub fn encoding(&self) -> NameEncoding {
+        self.encoding
+    }
+
+    /// The compression method, or `None` if it is one we don't support
+    pub fn compression(&self) -> Option<Compression> {
+        match self.method {
+            0 => Some(Compression::Stored),
+            8 => Some(Compression::Deflated),
+            _ => None,
+        }
+    }
+
+    /// CRC-32 of the uncompressed data
+    pub fn crc32(&self) -> u32 {
+        self.crc
+    }
+
+    /// Size of the data as stored in the archive
+    pub fn compressed_size(&self) -> u64 {
+        self.compressed_size
+    }
+
+    /// Size of the data once extracted
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Modification time of the entry
+    pub fn last_modified(&self) -> DosDateTime {
+        self.last_modified
+    }
+
+    /// Unix permission bits, if the archive was created on a unix system
+    pub fn unix_permissions(&self) -> Option<u32> {
+        self.unix_permissions
+    }
+
+    /// Whether this entry is a directory
+    pub fn is_directory(&self) -> bool {
+        self.name.ends_with('/')
+    }
+
+    /// The name as a relative path, if it is safe to extract
+    ///
+    /// Backslashes are treated as separators, as some Windows tools wrongly write them. Returns
+    /// `None` for absolute paths, drive letters, and names that would escape the extraction
+    /// directory through `..`.
+    pub fn enclosed_name(&self) -> Option<RelativePathBuf> {
+        let name = self.name.replace('\\', "/");
+        if name.starts_with('/') || name.contains('\0') {
+            return None;
+        }
+        let mut path = RelativePathBuf::new();
+        for component in name.split('/') {
+            match component {
+                "" | "." => {}
+                ".." => return None,
+                _ if component.contains(':') => return None,
+                _ => path.push(component),
+            }
+        }
+        if path.as_str().is_empty() {
+            None
+        } else {
+            Some(path)
+        }
+    }
+}
+
+/// A zip archive opened for reading
+#[derive(Debug)]
+pub struct ZipArchive<R> {
+    /// The underlying reader
+    reader: R,
+    /// Entries from the central directory, in archive order
+    entries: Vec<ZipEntry>,
+}
+
+impl<R: Read + Seek> ZipArchive<R> {
+    /// Opens an archive, reading its central directory
+    #[instrument(skip(reader))]
+    pub fn new(mut reader: R) -> Result<Self, ZipError> {
+        let length = reader.seek(SeekFrom::End(0)).context(IoSnafu)?;
+        // The end record sits at the very end, followed only by a comment of at most 64 KiB
+        let tail_length =
+            length.min((END_OF_CENTRAL_DIRECTORY_SIZE + 0xFFFF + ZIP64_LOCATOR_SIZE) as u64);
+        let tail_start = length - tail_length;
+        reader.seek(SeekFrom::Start(tail_start)).context(IoSnafu)?;
+        let mut tail = vec![0; usize::try_from(tail_length).unwrap_or(usize::MAX)];
+        reader.read_exact(&mut tail).context(IoSnafu)?;
+        let end = (0..(tail.len() + 1).saturating_sub(END_OF_CENTRAL_DIRECTORY_SIZE))
+            .rev()
+            .find(|&offset| {
+                u32_at(&tail, offset) == END_OF_CENTRAL_DIRECTORY_SIGNATURE
+                    && offset
+                        + END_OF_CENTRAL_DIRECTORY_SIZE
+                        + usize::from(u16_at(&tail, offset + 20))
+                        <= tail.len()
+            })
+            .context(InvalidArchiveSnafu {
+                reason: "no end of central directory record",
+            })?;
+        let mut entry_count = u64::from(u16_at(&tail, end + 10));
+        let mut directory_size = u64::from(u32_at(&tail, end + 12));
+        let mut directory_offset = u64::from(u32_at(&tail, end + 16));
+        // Check for a zip64 locator immediately before the end record
+        if end >= ZIP64_LOCATOR_SIZE
+            && u32_at(&tail, end - ZIP64_LOCATOR_SIZE) == ZIP64_LOCATOR_SIGNATURE
+        {
+            let record_offset = u64_at(&tail, end - ZIP64_LOCATOR_SIZE + 8);
+            trace!(record_offset, "Reading zip64 end of central directory");
+            reader
+                .seek(SeekFrom::Start(record_offset))
+                .context(IoSnafu)?;
+            let mut record = [0; ZIP64_END_OF_CENTRAL_DIRECTORY_SIZE];
+            reader.read_exact(&mut record).context(IoSnafu)?;
+            ensure!(
+                u32_at(&record, 0) == ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE,
+                InvalidArchiveSnafu {
+                    reason: "zip64 locator doesn't point at a zip64 end record",
+                }
+            );
+            entry_count = u64_at(&record, 32);
+            directory_size = u64_at(&record, 40);
+            directory_offset = u64_at(&record, 48);
+        }
+        ensure!(
+            directory_offset
+                .checked_add(directory_size)
+                .is_some_and(|directory_end| directory_end <= length),
+            InvalidArchiveSnafu {
+                reason: "central directory lies outside the archive",
+            }
+        );
+        debug!(entry_count, directory_size, "Reading central directory");
+        reader
+            .seek(SeekFrom::Start(directory_offset))
+            .context(IoSnafu)?;
+        let mut directory = vec![0; usize::try_from(directory_size).unwrap_or(usize::MAX)];
+        reader.read_exact(&mut directory).context(IoSnafu)?;
+        let mut entries = Vec::new();
+        let mut offset = 0;
+        while entries.len() as u64 != entry_count {
+            let (entry, next) = parse_central_header(&directory, offset)?;
+            entries.push(entry);
+            offset = next;
+        }
+        Ok(Self { reader, entries })
+    }
+
+    /// The entries of the archive, in the order they appear in the central directory
+    pub fn entries(&self) -> &[ZipEntry] {
+        &self.entries
+    }
+
+    /// Looks up an entry by its decoded name
+    pub fn by_name(&self, name: &str) -> Option<&ZipEntry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    /// Opens the entry at `index` for reading
+    ///
+    /// The returned reader decompresses on the fly, and fails with
+    /// [`io::ErrorKind::InvalidData`] if the data doesn't match the recorded size or checksum
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the entry uses an unsupported compression method or its local header
+    /// can't be read
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds
+    pub fn open(&mut self, index: usize) -> Result<ZipEntryReader<'_, R>, ZipError> {
+        let entry = &self.entries[index];
+        let compression = entry.compression().context(UnsupportedCompressionSnafu {
+            name: entry.name.clone(),
+            method: entry.method,
+        })?;
+        self.reader
+            .seek(SeekFrom::Start(entry.header_offset))
+            .context(IoSnafu)?;
+        let mut header = [0; LOCAL_FILE_HEADER_SIZE];
+        self.reader.read_exact(&mut header).context(IoSnafu)?;
+        ensure!(
+            u32_at(&header, 0) == LOCAL_FILE_HEADER_SIGNATURE,
+            InvalidArchiveSnafu {
+                reason: "central directory points at something other than a local header",
+            }
+        );
+        // The local name and extra field may differ from the central ones, so skip over whatever
+        // is actually there
+        let skip = i64::from(u16_at(&header, 26)) + i64::from(u16_at(&header, 28));
+        self.reader.seek(SeekFrom::Current(skip)).context(IoSnafu)?;
+        let data = (&mut self.reader).take(entry.compressed_size);
+        Ok(ZipEntryReader {
+            inner: match compression {
+                Compression::Stored => EntryData::Stored(data),
+                Compression::Deflated => EntryData::Deflated(Inflater::new(data)),
+            },
+            crc: Crc32::new(),
+            read: 0,
+            expected_crc: entry.crc,
+            expected_size: entry.size,
+        })
+    }
+
+    /// Consumes the archive, returning the underlying reader
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+/// Parses the central directory header at `offset`, returning the entry and the next offset
+fn parse_central_header(directory: &[u8], offset: usize) -> Result<(ZipEntry, usize), ZipError> {
+    let header = directory
+        .get(offset..offset + CENTRAL_DIRECTORY_HEADER_SIZE)
+        .context(InvalidArchiveSnafu {
+            reason: "central directory is truncated",
+        })?;
+    ensure!(
+        u32_at(header, 0) == CENTRAL_DIRECTORY_SIGNATURE,
+        InvalidArchiveSnafu {
+            reason: "bad central directory header signature",
+        }
+    );
+    let made_by = u16_at(header, 4);
+    let flags = u16_at(header, 8);
+    let method = u16_at(header, 10);
+    let time = u16_at(header, 12);
+    let date = u16_at(header, 14);
+    let crc = u32_at(header, 16);
+    let mut compressed_size = u64::from(u32_at(header, 20));
+    let mut size = u64::from(u32_at(header, 24));
+    let name_length = usize::from(u16_at(header, 28));
+    let extra_length = usize::from(u16_at(header, 30));
+    let comment_length = usize::from(u16_at(header, 32));
+    let external = u32_at(header, 38);
+    let mut header_offset = u64::from(u32_at(header, 42));
+    let variable_start = offset + CENTRAL_DIRECTORY_HEADER_SIZE;
+    let next = variable_start + name_length + extra_length + comment_length;
+    ensure!(
+        next <= directory.len(),
+        InvalidArchiveSnafu {
+            reason: "central directory is truncated",
+        }
+    );
+    let raw_name = &directory[variable_start..variable_start + name_length];
+    let extra =
+        &directory[variable_start + name_length..variable_start + name_length + extra_length];
+    let mut unicode_name = None;
+    for (id, data) in extra_fields(extra) {
+        match id {
+            ZIP64_EXTRA_FIELD => {
+                // Only the fields that overflowed are present, in this order
+                let mut fields = data.chunks_exact(8).map(|chunk| u64_at(chunk, 0));
+                for field in [&mut size, &mut compressed_size, &mut header_offset] {
+                    if *field == u64::from(ZIP64_MARKER) {
+                        *field = fields.next().context(InvalidArchiveSnafu {
+                            reason: "zip64 extra field is too short",
+                        })?;
+                    }
+                }
+            }
+            // Only trust the field if it was written for this exact name
+            UNICODE_PATH_EXTRA_FIELD if data.len() > 5 && data[0] == 1 => {
+                let mut name_crc = Crc32::new();
+                name_crc.update(raw_name);
+                if name_crc.finish() == u32_at(data, 1) {
+                    unicode_name = std::str::from_utf8(&data[5..]).ok().map(String::from);
+                } else {
+                    warn!("Ignoring stale unicode path extra field");
+                }
+            }
+            _ => {}
+        }
+    }
+    let (name, encoding) = if flags & FLAG_UTF8 != 0 {
+        let name = std::str::from_utf8(raw_name)
+            .ok()
+            .context(InvalidNameSnafu {
+                name: String::from_utf8_lossy(raw_name).into_owned(),
+            })?;
+        (name.to_string(), NameEncoding::Utf8)
+    } else if let Some(name) = unicode_name {
+        (name, NameEncoding::UnicodePathField)
+    } else if raw_name.is_ascii() {
+        (decode_cp437(raw_name), NameEncoding::Ascii)
+    } else if let Ok(name) = std::str::from_utf8(raw_name) {
+        (name.to_string(), NameEncoding::DetectedUtf8)
+    } else {
+        (decode_cp437(raw_name), NameEncoding::Cp437)
+    };
+    let unix_permissions = if (made_by >> 8) as u8 == HOST_UNIX && external >> 16 != 0 {
+        Some((external >> 16) & 0o7777)
+    } else {
+        None
+    };
+    let entry = ZipEntry {
+        name,
+        raw_name: raw_name.to_vec(),
+        encoding,
+        method,
+        crc,
+        compressed_size,
+        size,
+        header_offset,
+        last_modified: DosDateTime::from_parts(date, time),
+        unix_permissions,
+    };
+    Ok((entry, next))
+}
+
+/// The raw data stream of an entry
+#[derive(Debug)]
+enum EntryData<'a, R> {
+    /// Stored data, read as is
+    Stored(Take<&'a mut R>),
+    /// Deflated data, decompressed on the fly
+    Deflated(Inflater<Take<&'a mut R>>),
+}
+
+/// Reader over the decompressed contents of a single entry
+#[derive(Debug)]
+pub struct ZipEntryReader<'a, R> {
+    /// The entry's data
+    inner: EntryData<'a, R>,
+    /// Checksum of the data read so far
+    crc: Crc32,
+    /// Number of bytes read so far
+    read: u64,
+    /// Checksum recorded in the central directory
+    expected_crc: u32,
+    /// Size recorded in the central directory
+    expected_size: u64,
+}
+
+impl<R: Read> Read for ZipEntryReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = match &mut self.inner {
+            EntryData::Stored(data) => data.read(buf)?,
+            EntryData::Deflated(data) => data.read(buf)?,
+        };
+        self.crc.update(&buf[..count]);
+        self.read += count as u64;
+        if self.read > self.expected_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "zip entry is larger than recorded",
+            ));
+        }
+        if count == 0 && !buf.is_empty() {
+            if self.read != self.expected_size {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "zip entry is shorter than recorded",
+                ));
+            }
+            if self.crc.finish() != self.expected_crc {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "zip entry checksum mismatch",
+                ));
+            }
+        }
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::cast_possible_truncation)]
+mod unit_tests {
+    use std::io::{Cursor, Write};
+
+    use super::{super::FileOptions, *};
+    use crate::archive::ZipWriter;
+
+    /// Reads the full contents of the entry at `index`
+    fn read_entry(archive: &mut ZipArchive<Cursor<Vec<u8>>>, index: usize) -> Vec<u8> {
+        let mut contents = Vec::new();
+        archive
+            .open(index)
+            .unwrap()
+            .read_to_end(&mut contents)
+            .unwrap();
+        contents
+    }
+
+    /// Builds a single entry, stored, unflagged archive by hand, like an old tool would
+    fn legacy_archive(raw_name: &[u8], extra: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut crc = Crc32::new();
+        crc.update(data);
+        let crc = crc.finish();
+        let mut archive = Vec::new();
+        let sizes = [crc, data.len() as u32, data.len() as u32];
+        // Local header
+        archive.extend_from_slice(&LOCAL_FILE_HEADER_SIGNATURE.to_le_bytes());
+        archive.extend_from_slice(&[20, 0, 0, 0, 0, 0, 0, 0, 0x21, 0]);
+        for size in sizes {
+            archive.extend_from_slice(&size.to_le_bytes());
+        }
+        archive.extend_from_slice(&(raw_name.len() as u16).to_le_bytes());
+        archive.extend_from_slice(&0_u16.to_le_bytes());
+        archive.extend_from_slice(raw_name);
+        archive.extend_from_slice(data);
+        // Central directory
+        let directory_offset = archive.len() as u32;
+        archive.extend_from_slice(&CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+        archive.extend_from_slice(&[20, 0, 20, 0, 0, 0, 0, 0, 0, 0, 0x21, 0]);
+        for size in sizes {
+            archive.extend_from_slice(&size.to_le_bytes());
+        }
+        archive.extend_from_slice(&(raw_name.len() as u16).to_le_bytes());
+        archive.extend_from_slice(&(extra.len() as u16).to_le_bytes());
+        archive.extend_from_slice(&[0; 14]);
+        archive.extend_from_slice(raw_name);
+        archive.extend_from_slice(extra);
+        let directory_size = archive.len() as u32 - directory_offset;
+        // End record
+        archive.extend_from_slice(&END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+        archive.extend_from_slice(&[0, 0, 0, 0, 1, 0, 1, 0]);
+        archive.extend_from_slice(&directory_size.to_le_bytes());
+        archive.extend_from_slice(&directory_offset.to_le_bytes());
+        archive.extend_from_slice(&0_u16.to_le_bytes());
+        archive
+    }
+
+    // Non-ASCII names survive a round trip through the writer and reader
+    #[test]
+    fn unicode_round_trip() {
+        let names = [
+            "config/plain.toml",
+            "config/日本語の設定.json",
+            "resourcepacks/中文材质包.zip",
+            "kubejs/assets/ñandú/lang/de_de.json",
+        ];
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for name in names {
+            zip.start_file(name, FileOptions::for_path(name)).unwrap();
+            zip.write_all(name.as_bytes()).unwrap();
+        }
+        let bytes = zip.finish().unwrap().into_inner();
+        let mut archive = ZipArchive::new(Cursor::new(bytes)).unwrap();
+        for (index, name) in names.iter().enumerate() {
+            let entry = &archive.entries()[index];
+            assert_eq!(entry.name(), *name);
+            let expected = if name.is_ascii() {
+                NameEncoding::Ascii
+            } else {
+                NameEncoding::Utf8
+            };
+            assert_eq!(entry.encoding(), expected);
+            assert_eq!(entry.unix_permissions(), Some(0o644));
+            assert_eq!(read_entry(&mut archive, index), name.as_bytes());
+        }
+    }
+
+    // Unflagged names fall back to CP437 or detected UTF-8, and honor the unicode path field
+    #[test]
+    fn legacy_names() {
+        // "café.txt" in CP437
+        let bytes = legacy_archive(b"caf\x82.txt", &[], b"hi");
+        let archive = ZipArchive::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(archive.entries()[0].name(), "café.txt");
+        assert_eq!(archive.entries()[0].encoding(), NameEncoding::Cp437);
+        // UTF-8 written without the flag
+        let bytes = legacy_archive("模组.jar".as_bytes(), &[], b"hi");
+        let archive = ZipArchive::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(archive.entries()[0].name(), "模组.jar");
+        assert_eq!(archive.entries()[0].encoding(), NameEncoding::DetectedUtf8);
+        // A Shift-JIS name with a unicode path field, as written by Info-ZIP
+        let raw_name = b"\x83\x82\x83h.jar";
+        let mut name_crc = Crc32::new();
+        name_crc.update(raw_name);
+        let unicode = "モッド.jar".as_bytes();
+        let mut extra = Vec::new();
+        extra.extend_from_slice(&UNICODE_PATH_EXTRA_FIELD.to_le_bytes());
+        extra.extend_from_slice(&(5 + unicode.len() as u16).to_le_bytes());
+        extra.push(1);
+        extra.extend_from_slice(&name_crc.finish().to_le_bytes());
+        extra.extend_from_slice(unicode);
+        let bytes = legacy_archive(raw_name, &extra, b"hi");
+        let mut archive = ZipArchive::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(archive.entries()[0].name(), "モッド.jar");
+        assert_eq!(
+            archive.entries()[0].encoding(),
+            NameEncoding::UnicodePathField
+        );
+        assert_eq!(archive.entries()[0].raw_name(), raw_name);
+        assert_eq!(read_entry(&mut archive, 0), b"hi");
+    }
+
+    // More entries than the classic end record can count forces the zip64 records
+    #[test]
+    fn zip64_entry_count() {
+        let count = 0x1_0010;
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for index in 0..count {
+            zip.start_file(
+                &format!("{index}"),
+                FileOptions::default().large_file(index % 2 == 0),
+            )
+            .unwrap();
+        }
+        zip.start_file("last.txt", FileOptions::default()).unwrap();
+        zip.write_all(b"the end").unwrap();
+        let bytes = zip.finish().unwrap().into_inner();
+        let mut archive = ZipArchive::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(archive.entries().len(), count + 1);
+        assert_eq!(archive.entries()[1234].name(), "1234");
+        assert_eq!(read_entry(&mut archive, count), b"the end");
+        assert!(read_entry(&mut archive, 0).is_empty());
+    }
+
+    // Corrupted data is caught by the checksum
+    #[test]
+    fn checksum_mismatch() {
+        let mut bytes = legacy_archive(b"a.txt", &[], b"hello");
+        let data = LOCAL_FILE_HEADER_SIZE + 5;
+        bytes[data] = b'j';
+        let mut archive = ZipArchive::new(Cursor::new(bytes)).unwrap();
+        let mut contents = Vec::new();
+        let error = archive
+            .open(0)
+            .unwrap()
+            .read_to_end(&mut contents)
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        // Files too short to hold an end record aren't archives at all
+        assert!(ZipArchive::new(Cursor::new(b"jar")).is_err());
+    }
+
+    // Names that would escape the extraction directory are rejected
+    #[test]
+    fn enclosed_names() {
+        let cases = [
+            (&b"mods/a.jar"[..], Some("mods/a.jar")),
+            (b"config\\windows.cfg", Some("config/windows.cfg")),
+            (b"./overrides//b.txt", Some("overrides/b.txt")),
+            (b"../evil.jar", None),
+            (b"mods/../../evil.jar", None),
+            (b"/etc/passwd", None),
+            (b"C:/evil.jar", None),
+        ];
+        for (raw_name, expected) in cases {
+            let archive = ZipArchive::new(Cursor::new(legacy_archive(raw_name, &[], b""))).unwrap();
+            let enclosed = archive.entries()[0].enclosed_name();
+            assert_eq!(enclosed.as_ref().map(|path| path.as_str()), expected);
+        }
+    }
+}
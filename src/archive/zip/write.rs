@@ -0,0 +1,639 @@
+//! Streaming zip archive writer
+//!
+//! Entries are written straight through to the underlying writer as they are produced, with their
+//! sizes and checksums trailing the data in a data descriptor, so the writer never needs to seek.
+
+use std::{
+    collections::HashSet,
+    io::{self, Read, Write},
+};
+
+use snafu::{ensure, ResultExt};
+use tracing::{debug, instrument, trace};
+
+use super::{
+    Compression, DosDateTime, DuplicateEntrySnafu, InvalidNameSnafu, IoSnafu, NameTooLongSnafu,
+    UndeclaredLargeFileSnafu, ZipError, CENTRAL_DIRECTORY_SIGNATURE, DATA_DESCRIPTOR_SIGNATURE,
+    END_OF_CENTRAL_DIRECTORY_SIGNATURE, FLAG_DATA_DESCRIPTOR, FLAG_UTF8, HOST_UNIX,
+    LOCAL_FILE_HEADER_SIGNATURE, VERSION_DEFAULT, VERSION_ZIP64,
+    ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE, ZIP64_EXTRA_FIELD, ZIP64_LOCATOR_SIGNATURE,
+    ZIP64_MARKER,
+};
+use crate::archive::{crc32::Crc32, deflate::Deflater};
+
+/// Options controlling how a single entry is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileOptions {
+    /// Compression method for the entry
+    pub compression: Compression,
+    /// Modification time recorded for the entry
+    pub last_modified: DosDateTime,
+    /// Unix permission bits recorded for the entry
+    pub unix_permissions: u32,
+    /// Whether the entry may be 4 GiB or larger
+    ///
+    /// As the sizes are not known when the local header is written, entries that might need zip64
+    /// have to declare it up front. Writing more than 4 GiB to an entry without this set is an
+    /// error.
+    pub large_file: bool,
+}
+
+impl FileOptions {
+    /// Default options, with the compression method picked from the file's extension
+    pub fn for_path(path: &str) -> Self {
+        Self {
+            compression: Compression::for_path(path),
+            ..Self::default()
+        }
+    }
+
+    /// Sets the compression method
+    #[must_use]
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Sets the modification time
+    #[must_use]
+    pub fn last_modified(mut self, last_modified: DosDateTime) -> Self {
+        self.last_modified = last_modified;
+        self
+    }
+
+    /// Sets the unix permission bits
+    #[must_use]
+    pub fn unix_permissions(mut self, unix_permissions: u32) -> Self {
+        self.unix_permissions = unix_permissions;
+        self
+    }
+
+    /// Sets whether the entry may be 4 GiB or larger
+    #[must_use]
+    pub fn large_file(mut self, large_file: bool) -> Self {
+        self.large_file = large_file;
+        self
+    }
+}
+
+impl Default for FileOptions {
+    fn default() -> Self {
+        Self {
+            compression: Compression::Deflated,
+            last_modified: DosDateTime::now(),
+            unix_permissions: 0o644,
+            large_file: false,
+        }
+    }
+}
+
+/// Everything needed to write an entry's central directory record
+#[derive(Debug)]
+struct EntryRecord {
+    /// The entry's name, as written to the archive
+    name: Vec<u8>,
+    /// General purpose flags
+    flags: u16,
+    /// The options the entry was written with
+    options: FileOptions,
+    /// CRC-32 of the uncompressed data
+    crc: u32,
+    /// Size of the data as stored in the archive
+    compressed_size: u64,
+    /// Size of the data once extracted
+    uncompressed_size: u64,
+    /// Offset of the entry's local header from the start of the archive
+    offset: u64,
+}
+
+/// The entry currently being written
+#[derive(Debug)]
+struct OpenEntry {
+    /// Record for the entry, with the sizes filled in on close
+    record: EntryRecord,
+    /// Running checksum of the uncompressed data
+    crc: Crc32,
+    /// Encoder, if the entry is deflated
+    deflater: Option<Deflater>,
+    /// Archive offset at which the entry's data starts
+    data_start: u64,
+}
+
+/// Writer wrapper that keeps track of how many bytes have passed through it
+#[derive(Debug)]
+struct CountingWriter<W> {
+    /// The wrapped writer
+    inner: W,
+    /// Number of bytes written so far
+    count: u64,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Streaming zip archive writer
+///
+/// Start an entry with [`ZipWriter::start_file`], write its contents through the [`Write`]
+/// implementation, and call [`ZipWriter::finish`] once all entries have been written. Zip64
+/// records are emitted automatically once the archive outgrows the classic format's limits.
+#[derive(Debug)]
+pub struct ZipWriter<W: Write> {
+    /// The underlying writer
+    writer: CountingWriter<W>,
+    /// Records of the entries that have been closed
+    entries: Vec<EntryRecord>,
+    /// Names already used in this archive
+    names: HashSet<Vec<u8>>,
+    /// The entry being written, if any
+    current: Option<OpenEntry>,
+}
+
+impl<W: Write> ZipWriter<W> {
+    /// Creates a new archive writing to `writer`
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: CountingWriter {
+                inner: writer,
+                count: 0,
+            },
+            entries: Vec::new(),
+            names: HashSet::new(),
+            current: None,
+        }
+    }
+
+    /// Closes the current entry, if any, and starts a new one at `name`
+    ///
+    /// Names use `/` as their separator and must be unique within the archive
+    #[instrument(skip(self, options))]
+    pub fn start_file(&mut self, name: &str, options: FileOptions) -> Result<(), ZipError> {
+        self.close_entry()?;
+        debug!(?options.compression, "Starting entry");
+        ensure!(valid_name(name), InvalidNameSnafu { name });
+        let name_bytes = name.as_bytes().to_vec();
+        ensure!(
+            u16::try_from(name_bytes.len()).is_ok(),
+            NameTooLongSnafu { name }
+        );
+        ensure!(
+            self.names.insert(name_bytes.clone()),
+            DuplicateEntrySnafu { name }
+        );
+        // Names are always UTF-8, but only flag them as such when it makes a difference, as plain
+        // ASCII reads the same in every encoding
+        let flags = if name.is_ascii() {
+            FLAG_DATA_DESCRIPTOR
+        } else {
+            FLAG_DATA_DESCRIPTOR | FLAG_UTF8
+        };
+        let record = EntryRecord {
+            name: name_bytes,
+            flags,
+            options,
+            crc: 0,
+            compressed_size: 0,
+            uncompressed_size: 0,
+            offset: self.writer.count,
+        };
+        self.write_local_header(&record).context(IoSnafu)?;
+        self.current = Some(OpenEntry {
+            record,
+            crc: Crc32::new(),
+            deflater: match options.compression {
+                Compression::Stored => None,
+                Compression::Deflated => Some(Deflater::new()),
+            },
+            data_start: self.writer.count,
+        });
+        Ok(())
+    }
+
+    /// Adds an entry at `name`, streaming its contents from `reader`
+    ///
+    /// Returns the number of bytes read
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the entry can't be started or either stream fails
+    pub fn write_file(
+        &mut self,
+        name: &str,
+        options: FileOptions,
+        reader: &mut impl Read,
+    ) -> Result<u64, ZipError> {
+        self.start_file(name, options)?;
+        io::copy(reader, self).context(IoSnafu)
+    }
+
+    /// Closes the last entry and writes the central directory, returning the underlying writer
+    #[instrument(skip(self))]
+    pub fn finish(mut self) -> Result<W, ZipError> {
+        self.close_entry()?;
+        let directory_start = self.writer.count;
+        debug!(entries = self.entries.len(), "Writing central directory");
+        for record in &self.entries {
+            let header = central_directory_header(record);
+            self.writer.write_all(&header).context(IoSnafu)?;
+        }
+        let directory_end = self.writer.count;
+        let directory_size = directory_end - directory_start;
+        let entry_count = self.entries.len() as u64;
+        let needs_zip64 = entry_count >= 0xFFFF
+            || directory_size >= u64::from(ZIP64_MARKER)
+            || directory_start >= u64::from(ZIP64_MARKER);
+        let mut trailer = Vec::with_capacity(98);
+        if needs_zip64 {
+            trace!("Writing zip64 end of central directory");
+            // Zip64 end of central directory record
+            trailer.extend_from_slice(&ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+            trailer.extend_from_slice(&44_u64.to_le_bytes());
+            trailer.extend_from_slice(&(HOST_UNIX | VERSION_ZIP64).to_le_bytes());
+            trailer.extend_from_slice(&VERSION_ZIP64.to_le_bytes());
+            trailer.extend_from_slice(&0_u32.to_le_bytes());
+            trailer.extend_from_slice(&0_u32.to_le_bytes());
+            trailer.extend_from_slice(&entry_count.to_le_bytes());
+            trailer.extend_from_slice(&entry_count.to_le_bytes());
+            trailer.extend_from_slice(&directory_size.to_le_bytes());
+            trailer.extend_from_slice(&directory_start.to_le_bytes());
+            // Zip64 end of central directory locator
+            trailer.extend_from_slice(&ZIP64_LOCATOR_SIGNATURE.to_le_bytes());
+            trailer.extend_from_slice(&0_u32.to_le_bytes());
+            trailer.extend_from_slice(&directory_end.to_le_bytes());
+            trailer.extend_from_slice(&1_u32.to_le_bytes());
+        }
+        // End of central directory record, with any overflowing fields saturated
+        let short_count = u16::try_from(entry_count).unwrap_or(0xFFFF);
+        trailer.extend_from_slice(&END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+        trailer.extend_from_slice(&0_u16.to_le_bytes());
+        trailer.extend_from_slice(&0_u16.to_le_bytes());
+        trailer.extend_from_slice(&short_count.to_le_bytes());
+        trailer.extend_from_slice(&short_count.to_le_bytes());
+        trailer.extend_from_slice(&saturate(directory_size).to_le_bytes());
+        trailer.extend_from_slice(&saturate(directory_start).to_le_bytes());
+        trailer.extend_from_slice(&0_u16.to_le_bytes());
+        self.writer.write_all(&trailer).context(IoSnafu)?;
+        self.writer.flush().context(IoSnafu)?;
+        Ok(self.writer.inner)
+    }
+
+    /// Writes the local file header for a new entry
+    fn write_local_header(&mut self, record: &EntryRecord) -> io::Result<()> {
+        let large = record.options.large_file;
+        let mut header = Vec::with_capacity(30 + record.name.len() + 20);
+        header.extend_from_slice(&LOCAL_FILE_HEADER_SIGNATURE.to_le_bytes());
+        header.extend_from_slice(&version_needed(large).to_le_bytes());
+        header.extend_from_slice(&record.flags.to_le_bytes());
+        header.extend_from_slice(&record.options.compression.method().to_le_bytes());
+        header.extend_from_slice(&record.options.last_modified.time().to_le_bytes());
+        header.extend_from_slice(&record.options.last_modified.date().to_le_bytes());
+        // The crc and sizes follow the data in the data descriptor
+        header.extend_from_slice(&0_u32.to_le_bytes());
+        let size = if large { ZIP64_MARKER } else { 0 };
+        header.extend_from_slice(&size.to_le_bytes());
+        header.extend_from_slice(&size.to_le_bytes());
+        #[allow(clippy::cast_possible_truncation)]
+        header.extend_from_slice(&(record.name.len() as u16).to_le_bytes());
+        header.extend_from_slice(&(if large { 20_u16 } else { 0 }).to_le_bytes());
+        header.extend_from_slice(&record.name);
+        if large {
+            header.extend_from_slice(&ZIP64_EXTRA_FIELD.to_le_bytes());
+            header.extend_from_slice(&16_u16.to_le_bytes());
+            header.extend_from_slice(&0_u64.to_le_bytes());
+            header.extend_from_slice(&0_u64.to_le_bytes());
+        }
+        self.writer.write_all(&header)
+    }
+
+    /// Finishes the current entry, if any, writing its data descriptor
+    fn close_entry(&mut self) -> Result<(), ZipError> {
+        let Some(mut entry) = self.current.take() else {
+            return Ok(());
+        };
+        if let Some(deflater) = entry.deflater.as_mut() {
+            deflater.finish(&mut self.writer).context(IoSnafu)?;
+        }
+        let mut record = entry.record;
+        record.crc = entry.crc.finish();
+        record.compressed_size = self.writer.count - entry.data_start;
+        let large = record.options.large_file;
+        ensure!(
+            large
+                || (record.compressed_size < u64::from(ZIP64_MARKER)
+                    && record.uncompressed_size < u64::from(ZIP64_MARKER)),
+            UndeclaredLargeFileSnafu {
+                name: String::from_utf8_lossy(&record.name).into_owned(),
+            }
+        );
+        trace!(
+            crc = record.crc,
+            compressed = record.compressed_size,
+            uncompressed = record.uncompressed_size,
+            "Closing entry"
+        );
+        let mut descriptor = Vec::with_capacity(24);
+        descriptor.extend_from_slice(&DATA_DESCRIPTOR_SIGNATURE.to_le_bytes());
+        descriptor.extend_from_slice(&record.crc.to_le_bytes());
+        if large {
+            descriptor.extend_from_slice(&record.compressed_size.to_le_bytes());
+            descriptor.extend_from_slice(&record.uncompressed_size.to_le_bytes());
+        } else {
+            descriptor.extend_from_slice(&saturate(record.compressed_size).to_le_bytes());
+            descriptor.extend_from_slice(&saturate(record.uncompressed_size).to_le_bytes());
+        }
+        self.writer.write_all(&descriptor).context(IoSnafu)?;
+        self.entries.push(record);
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for ZipWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let entry = self
+            .current
+            .as_mut()
+            .ok_or_else(|| io::Error::other("no zip entry has been started"))?;
+        entry.crc.update(buf);
+        entry.record.uncompressed_size += buf.len() as u64;
+        match entry.deflater.as_mut() {
+            Some(deflater) => deflater.write(buf, &mut self.writer)?,
+            None => self.writer.write_all(buf)?,
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Checks that a name is a plain relative path using `/` as its separator
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('/')
+        && !name.contains(['\\', '\0'])
+        && name
+            .split('/')
+            .all(|component| component != ".." && !component.contains(':'))
+}
+
+/// Version needed to extract an entry
+fn version_needed(zip64: bool) -> u16 {
+    if zip64 {
+        VERSION_ZIP64
+    } else {
+        VERSION_DEFAULT
+    }
+}
+
+/// Clamps a value to 32 bits, using the zip64 marker if it doesn't fit
+fn saturate(value: u64) -> u32 {
+    u32::try_from(value).unwrap_or(ZIP64_MARKER)
+}
+
+/// Serializes the central directory header for an entry
+fn central_directory_header(record: &EntryRecord) -> Vec<u8> {
+    // Collect the fields that overflow into the zip64 extra field, in the order the spec requires
+    let mut zip64 = Vec::new();
+    for value in [
+        record.uncompressed_size,
+        record.compressed_size,
+        record.offset,
+    ] {
+        if value >= u64::from(ZIP64_MARKER) {
+            zip64.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    let uses_zip64 = !zip64.is_empty() || record.options.large_file;
+    let mut header = Vec::with_capacity(46 + record.name.len() + 4 + zip64.len());
+    header.extend_from_slice(&CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+    header.extend_from_slice(&(HOST_UNIX | version_needed(uses_zip64)).to_le_bytes());
+    header.extend_from_slice(&version_needed(uses_zip64).to_le_bytes());
+    header.extend_from_slice(&record.flags.to_le_bytes());
+    header.extend_from_slice(&record.options.compression.method().to_le_bytes());
+    header.extend_from_slice(&record.options.last_modified.time().to_le_bytes());
+    header.extend_from_slice(&record.options.last_modified.date().to_le_bytes());
+    header.extend_from_slice(&record.crc.to_le_bytes());
+    header.extend_from_slice(&saturate(record.compressed_size).to_le_bytes());
+    header.extend_from_slice(&saturate(record.uncompressed_size).to_le_bytes());
+    #[allow(clippy::cast_possible_truncation)]
+    {
+        header.extend_from_slice(&(record.name.len() as u16).to_le_bytes());
+        let extra_length = if zip64.is_empty() { 0 } else { 4 + zip64.len() };
+        header.extend_from_slice(&(extra_length as u16).to_le_bytes());
+    }
+    // Comment length, disk number, internal attributes
+    header.extend_from_slice(&0_u16.to_le_bytes());
+    header.extend_from_slice(&0_u16.to_le_bytes());
+    header.extend_from_slice(&0_u16.to_le_bytes());
+    // External attributes carry the unix mode in the high word, marked as a regular file
+    let external = (0o100_000 | (record.options.unix_permissions & 0o7777)) << 16;
+    header.extend_from_slice(&external.to_le_bytes());
+    header.extend_from_slice(&saturate(record.offset).to_le_bytes());
+    header.extend_from_slice(&record.name);
+    if !zip64.is_empty() {
+        header.extend_from_slice(&ZIP64_EXTRA_FIELD.to_le_bytes());
+        #[allow(clippy::cast_possible_truncation)]
+        header.extend_from_slice(&(zip64.len() as u16).to_le_bytes());
+        header.extend_from_slice(&zip64);
+    }
+    header
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    /// Reads a little endian `u16` at `offset`
+    fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+        u16::from_le_bytes(bytes[offset..offset + 2].try_into().unwrap())
+    }
+
+    /// Reads a little endian `u32` at `offset`
+    fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    /// Reads a little endian `u64` at `offset`
+    fn u64_at(bytes: &[u8], offset: usize) -> u64 {
+        u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+    }
+
+    // Write a small stored archive and check the layout by hand
+    #[test]
+    fn stored_layout() {
+        let mut zip = ZipWriter::new(Vec::new());
+        let options = FileOptions::for_path("mods/test.jar").last_modified(DosDateTime::EPOCH);
+        assert_eq!(options.compression, Compression::Stored);
+        zip.start_file("mods/test.jar", options).unwrap();
+        zip.write_all(b"123456789").unwrap();
+        let bytes = zip.finish().unwrap();
+        // Local header, immediately followed by the data and a data descriptor
+        assert_eq!(u32_at(&bytes, 0), LOCAL_FILE_HEADER_SIGNATURE);
+        let data_start = 30 + "mods/test.jar".len();
+        assert_eq!(&bytes[data_start..data_start + 9], b"123456789");
+        assert_eq!(u32_at(&bytes, data_start + 9), DATA_DESCRIPTOR_SIGNATURE);
+        assert_eq!(u32_at(&bytes, data_start + 13), 0xCBF4_3926);
+        // Central directory and end record
+        let eocd = bytes.len() - 22;
+        assert_eq!(u32_at(&bytes, eocd), END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+        assert_eq!(u16_at(&bytes, eocd + 10), 1);
+        let directory = u32_at(&bytes, eocd + 16) as usize;
+        assert_eq!(u32_at(&bytes, directory), CENTRAL_DIRECTORY_SIGNATURE);
+        assert_eq!(u32_at(&bytes, directory + 16), 0xCBF4_3926);
+        assert_eq!(u32_at(&bytes, directory + 20), 9);
+        assert_eq!(u32_at(&bytes, directory + 24), 9);
+    }
+
+    // Duplicate names are rejected
+    #[test]
+    fn duplicate_entry() {
+        let mut zip = ZipWriter::new(Vec::new());
+        zip.start_file("a.txt", FileOptions::default()).unwrap();
+        assert!(matches!(
+            zip.start_file("a.txt", FileOptions::default()),
+            Err(ZipError::DuplicateEntry { .. })
+        ));
+    }
+
+    // Names that aren't plain relative paths are rejected
+    #[test]
+    fn invalid_names() {
+        let mut zip = ZipWriter::new(Vec::new());
+        for name in [
+            "",
+            "/abs.txt",
+            "a\\b.txt",
+            "../up.txt",
+            "mods/../../up.txt",
+            "C:/x",
+        ] {
+            assert!(
+                matches!(
+                    zip.start_file(name, FileOptions::default()),
+                    Err(ZipError::InvalidName { .. })
+                ),
+                "{name}"
+            );
+        }
+        zip.start_file("mods/ok.jar", FileOptions::default())
+            .unwrap();
+    }
+
+    // Check timestamp conversion against a known date
+    #[test]
+    fn dos_time() {
+        // 2022-06-20 12:34:56 UTC
+        let time =
+            std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_655_728_496);
+        let dos = DosDateTime::from_system_time(time);
+        assert_eq!(dos.date(), (42 << 9) | (6 << 5) | 0x14);
+        assert_eq!(dos.time(), (12 << 11) | (34 << 5) | (56 / 2));
+    }
+
+    /// Sink that only remembers the tail end of what has been written to it
+    struct TailSink {
+        /// Total bytes written
+        count: u64,
+        /// The last bytes written
+        tail: Vec<u8>,
+    }
+
+    impl Write for TailSink {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            /// How much of the tail to keep
+            const KEEP: usize = 64 * 1024;
+            self.count += buf.len() as u64;
+            self.tail.extend_from_slice(buf);
+            if self.tail.len() > KEEP * 2 {
+                self.tail.drain(..self.tail.len() - KEEP);
+            }
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // Stream a >4 GiB archive without buffering it and check the zip64 records. The data is
+    // thrown away as it is written, so this only needs memory for the tail of the archive.
+    #[test]
+    fn zip64_large_archive() {
+        const CHUNK: usize = 1024 * 1024;
+        const SIZE: u64 = (4 << 30) + (64 << 20);
+        let mut zip = ZipWriter::new(TailSink {
+            count: 0,
+            tail: Vec::new(),
+        });
+        zip.start_file("small.txt", FileOptions::default()).unwrap();
+        zip.write_all(b"hello").unwrap();
+        let options = FileOptions::default()
+            .compression(Compression::Stored)
+            .large_file(true);
+        zip.start_file("huge.bin", options).unwrap();
+        let chunk = vec![0_u8; CHUNK];
+        let mut written = 0;
+        while written < SIZE {
+            zip.write_all(&chunk).unwrap();
+            written += CHUNK as u64;
+        }
+        // An entry after the 4 GiB mark needs a zip64 offset
+        zip.start_file("after.txt", FileOptions::default()).unwrap();
+        zip.write_all(b"world").unwrap();
+        let sink = zip.finish().unwrap();
+        let tail = &sink.tail;
+        let tail_start = sink.count - tail.len() as u64;
+        let local = |absolute: u64| usize::try_from(absolute - tail_start).unwrap();
+        // Classic end record is saturated and points at the zip64 locator
+        let eocd = tail.len() - 22;
+        assert_eq!(u32_at(tail, eocd), END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+        assert_eq!(u32_at(tail, eocd + 16), ZIP64_MARKER);
+        let locator = eocd - 20;
+        assert_eq!(u32_at(tail, locator), ZIP64_LOCATOR_SIGNATURE);
+        let record = local(u64_at(tail, locator + 8));
+        assert_eq!(
+            u32_at(tail, record),
+            ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE
+        );
+        assert_eq!(u64_at(tail, record + 32), 3);
+        let directory_start = u64_at(tail, record + 48);
+        assert!(directory_start > SIZE);
+        // Walk the central directory
+        let mut offset = local(directory_start);
+        let mut names = Vec::new();
+        for _ in 0..3 {
+            assert_eq!(u32_at(tail, offset), CENTRAL_DIRECTORY_SIGNATURE);
+            let name_length = usize::from(u16_at(tail, offset + 28));
+            let extra_length = usize::from(u16_at(tail, offset + 30));
+            let name =
+                String::from_utf8(tail[offset + 46..offset + 46 + name_length].to_vec()).unwrap();
+            let extra = &tail[offset + 46 + name_length..offset + 46 + name_length + extra_length];
+            match name.as_str() {
+                "huge.bin" => {
+                    assert_eq!(u32_at(tail, offset + 20), ZIP64_MARKER);
+                    assert_eq!(u16_at(extra, 0), ZIP64_EXTRA_FIELD);
+                    assert_eq!(u64_at(extra, 4), SIZE);
+                    assert_eq!(u64_at(extra, 12), SIZE);
+                }
+                "after.txt" => {
+                    assert_eq!(u32_at(tail, offset + 42), ZIP64_MARKER);
+                    assert_eq!(u16_at(extra, 0), ZIP64_EXTRA_FIELD);
+                    assert!(u64_at(extra, 4) > SIZE);
+                }
+                _ => assert!(extra.is_empty()),
+            }
+            names.push(name);
+            offset += 46 + name_length + extra_length;
+        }
+        assert_eq!(names, ["small.txt", "huge.bin", "after.txt"]);
+    }
+}
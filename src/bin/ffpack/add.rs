@@ -0,0 +1,343 @@
+//! `ffpack add`: adding a file to the pack from wherever it is published
+//!
+//! The source is worked out from what is given: a Modrinth or CurseForge page or slug, a GitHub
+//! repository or forge slug, or any other url. Everything but plain urls is resolved against the
+//! pack's versions before it is added, which confirms the project exists and has a compatible
+//! file, and gives the file its name and path. Plain urls are downloaded once for their digests.
+//! Versions are only pinned when the link names one, leaving the rest for the lockfile.
+
+use std::path::Path;
+
+use ffpack::{
+    http::{RetryPolicy, RetryingClient},
+    rehash::Rehasher,
+    types::{FileKind, ForgeSlug, HashAlgorithm, Hashes, ManagedFile, Side, Source},
+    Pack,
+};
+use relative_path::RelativePathBuf;
+use snafu::{ensure, OptionExt, ResultExt};
+use url::Url;
+
+use crate::{
+    args::{self, Global, Options},
+    config,
+    net::{self, block_on, CurlClient},
+    save, CliError, DuplicateSnafu, PinSnafu, ResolveSnafu, UnrecognizedSnafu, UsageSnafu,
+};
+
+/// The artifacts releases are expected to carry when no regex is given
+const DEFAULT_ARTIFACT: &str = r"\.jar$";
+
+/// The folders files of each Modrinth project type install into
+const MODRINTH_FOLDERS: [(&str, &str); 3] = [
+    ("resourcepack", "resourcepacks"),
+    ("shader", "shaderpacks"),
+    ("datapack", "datapacks"),
+];
+
+/// The folders files of each CurseForge class install into, by the class's name in urls
+const CURSEFORGE_FOLDERS: [(&str, &str); 3] = [
+    ("texture-packs", "resourcepacks"),
+    ("shaders", "shaderpacks"),
+    ("data-packs", "datapacks"),
+];
+
+/// A source worked out from the command line, and the folder its files install into
+#[derive(Debug, PartialEq, Eq)]
+struct Detected {
+    /// The source, with a placeholder digest for urls until they are downloaded
+    source: Source,
+    /// The folder the file goes in, unless a path is given
+    folder: &'static str,
+}
+
+impl Detected {
+    /// Detects a source that installs into `mods`
+    fn new(source: Source) -> Self {
+        Self {
+            source,
+            folder: "mods",
+        }
+    }
+
+    /// Sets the folder from a project type, if it is one that doesn't install into `mods`
+    fn in_folder(mut self, folders: &[(&str, &'static str)], kind: &str) -> Self {
+        if let Some((_, folder)) = folders.iter().find(|(name, _)| *name == kind) {
+            self.folder = folder;
+        }
+        self
+    }
+}
+
+/// Returns true for what could be a Modrinth or CurseForge slug
+fn is_slug(input: &str) -> bool {
+    !input.is_empty()
+        && input
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Detects the source of a release artifact from a forge slug
+fn releases(slug: String, artifact: Option<&str>) -> Detected {
+    Detected::new(Source::SlugReleases {
+        slug,
+        artifact_regex: artifact.unwrap_or(DEFAULT_ARTIFACT).to_string(),
+        release_regex: None,
+    })
+}
+
+/// Works out the source `input` points at, with `artifact` matching the artifacts of releases
+fn detect(input: &str, artifact: Option<&str>) -> Option<Detected> {
+    if let Some(slug) = input.strip_prefix("modrinth:") {
+        let source = Source::Modrinth {
+            slug: slug.to_string(),
+            version_id: None,
+        };
+        return is_slug(slug).then(|| Detected::new(source));
+    }
+    if let Some(slug) = input.strip_prefix("curseforge:") {
+        let source = Source::Curseforge {
+            slug: slug.to_string(),
+            file_id: None,
+        };
+        return is_slug(slug).then(|| Detected::new(source));
+    }
+    if is_slug(input) {
+        return Some(Detected::new(Source::Modrinth {
+            slug: input.to_string(),
+            version_id: None,
+        }));
+    }
+    if ForgeSlug::new(input).is_ok() {
+        return Some(releases(input.to_string(), artifact));
+    }
+    let url = Url::parse(input).ok()?;
+    if !["http", "https"].contains(&url.scheme()) {
+        return None;
+    }
+    let host = url.host_str()?;
+    let host = host.strip_prefix("www.").unwrap_or(host);
+    let segments: Vec<_> = url
+        .path_segments()
+        .map(|segments| segments.filter(|segment| !segment.is_empty()).collect())
+        .unwrap_or_default();
+    let detected = match (host, segments.as_slice()) {
+        ("modrinth.com", [kind, slug, rest @ ..]) => {
+            let version_id = match rest {
+                ["version", id, ..] => Some((*id).to_string()),
+                _ => None,
+            };
+            let source = Source::Modrinth {
+                slug: (*slug).to_string(),
+                version_id,
+            };
+            Detected::new(source).in_folder(&MODRINTH_FOLDERS, kind)
+        }
+        ("curseforge.com", ["minecraft", class, slug, rest @ ..]) => {
+            let file_id = match rest {
+                ["files" | "download", id, ..] => Some(id.parse().ok()?),
+                _ => None,
+            };
+            let source = Source::Curseforge {
+                slug: (*slug).to_string(),
+                file_id,
+            };
+            Detected::new(source).in_folder(&CURSEFORGE_FOLDERS, class)
+        }
+        ("github.com", [owner, repo, rest @ ..])
+            if !matches!(rest, ["releases", "download", ..]) =>
+        {
+            let repo = repo.strip_suffix(".git").unwrap_or(repo);
+            releases(format!("github:{owner}/{repo}"), artifact)
+        }
+        _ => {
+            let placeholder = Source::Url {
+                url,
+                blake3: [0; 32],
+                hashes: Hashes::default(),
+                mirrors: Vec::new(),
+            };
+            Detected::new(placeholder)
+        }
+    };
+    Some(detected)
+}
+
+/// Where a file is added, and how it is described, as options say
+#[derive(Debug, Default)]
+pub struct Placement {
+    /// The path the file goes at, instead of its filename in its folder
+    pub path: Option<RelativePathBuf>,
+    /// The file's name, instead of its project's
+    pub name: Option<String>,
+    /// The side the file is installed on
+    pub side: Side,
+}
+
+/// Resolves a source that isn't a plain url, returning the file that adds it to the pack
+///
+/// The file goes in `folder` unless the placement names a path, and is named after its
+/// project unless the placement names it.
+pub fn resolve(
+    manifest: &Path,
+    pack: &Pack,
+    source: Source,
+    folder: &str,
+    placement: Placement,
+    input: &str,
+) -> Result<ManagedFile, CliError> {
+    let mut file = ManagedFile {
+        name: None,
+        description: None,
+        filename: String::new(),
+        devel: true,
+        path: RelativePathBuf::new(),
+        side: placement.side,
+        source,
+        enabled: true,
+        notes: None,
+        kind: FileKind::Regular,
+        collection: None,
+    };
+    let client = net::api_client();
+    let resolver = net::resolver(&client, manifest, pack);
+    let artifact =
+        block_on(resolver.resolve(&file, &pack.versions)).context(ResolveSnafu { input })?;
+    file.path = placement
+        .path
+        .unwrap_or_else(|| RelativePathBuf::from(folder).join(&artifact.filename));
+    ensure!(
+        !pack
+            .managed_files
+            .iter()
+            .any(|other| other.path == file.path),
+        DuplicateSnafu { path: file.path }
+    );
+    file.name = placement.name.or(artifact.details.name.clone());
+    file.filename = artifact.filename.clone();
+    if let Some(version) = artifact.version_label() {
+        println!("Resolved {input} to {} {version}", artifact.filename);
+    }
+    Ok(file)
+}
+
+/// Runs `ffpack add <SOURCE>`, adding the file to the manifest in use
+pub fn run(global: &Global, mut options: Options) -> Result<(), CliError> {
+    let path = options.value("--path", None).context(UsageSnafu)?;
+    let name = options.value("--name", None).context(UsageSnafu)?;
+    let artifact = options.value("--artifact", None).context(UsageSnafu)?;
+    let side = options
+        .value("--side", None)
+        .context(UsageSnafu)?
+        .map(|side| args::side("--side", &side))
+        .transpose()
+        .context(UsageSnafu)?
+        .unwrap_or_default();
+    let input = options.required("SOURCE").context(UsageSnafu)?;
+    options.finish().context(UsageSnafu)?;
+    let path = path
+        .map(|path| RelativePathBuf::from_path(&path).map_err(|_| path))
+        .transpose()
+        .map_err(|value| args::UsageError::InvalidValue {
+            option: "--path".into(),
+            value,
+            expected: "a relative path".into(),
+        })
+        .context(UsageSnafu)?;
+
+    let (manifest, mut pack) = global.load()?;
+    let detected =
+        detect(&input, artifact.as_deref()).context(UnrecognizedSnafu { input: &input })?;
+    let file = match detected.source {
+        Source::Url { url, .. } => {
+            let filename = url
+                .path_segments()
+                .and_then(Iterator::last)
+                .filter(|name| !name.is_empty())
+                .unwrap_or("download");
+            let path =
+                path.unwrap_or_else(|| RelativePathBuf::from(detected.folder).join(filename));
+            ensure!(
+                !pack.managed_files.iter().any(|file| file.path == path),
+                DuplicateSnafu { path }
+            );
+            let client = RetryingClient::new(CurlClient::new(), RetryPolicy::default());
+            let rehasher = Rehasher::new(&client, net::cache_dir().join("pins"))
+                .with_credentials(config::get().credentials());
+            let algorithms = [HashAlgorithm::Sha1, HashAlgorithm::Sha512];
+            let pinned = block_on(rehasher.pin_url(&mut pack, path, url, algorithms))
+                .context(PinSnafu { input: &input })?;
+            ManagedFile {
+                name,
+                devel: true,
+                side,
+                ..pinned
+            }
+        }
+        source => {
+            let placement = Placement { path, name, side };
+            resolve(&manifest, &pack, source, detected.folder, placement, &input)?
+        }
+    };
+    println!("Added {}", file.path);
+    pack.managed_files.replace(file);
+    save(&manifest, &pack)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    // Pages, slugs, and repositories are told apart, and anything else is a plain url
+    #[test]
+    fn detect() {
+        let detected = super::detect("https://modrinth.com/shader/iris/version/1.6.4", None);
+        assert_eq!(
+            detected,
+            Some(Detected {
+                source: Source::Modrinth {
+                    slug: "iris".into(),
+                    version_id: Some("1.6.4".into()),
+                },
+                folder: "shaderpacks",
+            })
+        );
+        let detected = super::detect(
+            "https://www.curseforge.com/minecraft/mc-mods/jei/files/4712866",
+            None,
+        );
+        assert_eq!(
+            detected.unwrap().source,
+            Source::Curseforge {
+                slug: "jei".into(),
+                file_id: Some(4_712_866),
+            }
+        );
+        let detected = super::detect("https://github.com/owner/repo.git", Some("-fabric.jar$"));
+        assert_eq!(
+            detected.unwrap().source,
+            Source::SlugReleases {
+                slug: "github:owner/repo".into(),
+                artifact_regex: "-fabric.jar$".into(),
+                release_regex: None,
+            }
+        );
+        assert_eq!(
+            super::detect("sodium", None).unwrap().source,
+            Source::Modrinth {
+                slug: "sodium".into(),
+                version_id: None,
+            }
+        );
+        let url = "https://github.com/owner/repo/releases/download/v1/mod.jar";
+        assert!(matches!(
+            super::detect(url, None).unwrap().source,
+            Source::Url { .. }
+        ));
+        assert!(matches!(
+            super::detect("codeberg:owner/repo", None).unwrap().source,
+            Source::SlugReleases { .. }
+        ));
+        assert_eq!(super::detect("not a source", None), None);
+    }
+}
@@ -0,0 +1,231 @@
+//! Command line argument parsing
+//!
+//! Options may appear anywhere on the command line, as `--name value`, `--name=value`, or a short
+//! `-n value`. Global options are taken out first, and the rest is left for the command to parse
+//! with the same [`Options`].
+
+use std::path::PathBuf;
+
+use ffpack::types::{HashAlgorithm, Side};
+use snafu::{ensure, OptionExt, Snafu};
+
+/// The options shared by every command
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Global {
+    /// The manifest to operate on, instead of searching for one
+    pub manifest_path: Option<PathBuf>,
+    /// The pack to operate on, in a repository holding several
+    pub pack: Option<String>,
+    /// Print usage instead of running the command
+    pub help: bool,
+    /// Let git sources run their builds
+    pub allow_build: bool,
+    /// Run in CI mode, as [`ci`](crate::ci) describes
+    pub ci: bool,
+}
+
+impl Global {
+    /// Takes the global options out of `options`
+    pub fn parse(options: &mut Options) -> Result<Self, UsageError> {
+        Ok(Self {
+            manifest_path: options.value("--manifest-path", None)?.map(PathBuf::from),
+            pack: options.value("--pack", Some('p'))?,
+            help: options.flag("--help", Some('h')),
+            allow_build: options.flag("--allow-build", None),
+            ci: options.flag("--ci", None),
+        })
+    }
+}
+
+/// The arguments not yet consumed
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Options {
+    /// The remaining arguments, in order
+    args: Vec<String>,
+}
+
+impl Options {
+    /// Wraps the arguments, without the program name
+    pub fn new(args: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            args: args.into_iter().collect(),
+        }
+    }
+
+    /// Returns true if an argument matches the option `long` or `short`
+    fn matches(arg: &str, long: &str, short: Option<char>) -> bool {
+        arg == long || short.is_some_and(|short| arg.strip_prefix('-') == Some(&short.to_string()))
+    }
+
+    /// Takes a flag, returning whether it was present
+    pub fn flag(&mut self, long: &str, short: Option<char>) -> bool {
+        let before = self.args.len();
+        self.args.retain(|arg| !Self::matches(arg, long, short));
+        self.args.len() != before
+    }
+
+    /// Takes an option with a value, returning the last one given
+    pub fn value(&mut self, long: &str, short: Option<char>) -> Result<Option<String>, UsageError> {
+        Ok(self.values(long, short)?.pop())
+    }
+
+    /// Takes every occurrence of an option with a value, in order
+    pub fn values(&mut self, long: &str, short: Option<char>) -> Result<Vec<String>, UsageError> {
+        let mut values = Vec::new();
+        let mut index = 0;
+        while index < self.args.len() {
+            let arg = &self.args[index];
+            if arg == "--" {
+                break;
+            }
+            if let Some(value) = arg
+                .strip_prefix(long)
+                .and_then(|rest| rest.strip_prefix('='))
+            {
+                values.push(value.to_string());
+                self.args.remove(index);
+            } else if Self::matches(arg, long, short) {
+                ensure!(
+                    index + 1 < self.args.len(),
+                    MissingValueSnafu { option: long }
+                );
+                self.args.remove(index);
+                values.push(self.args.remove(index));
+            } else {
+                index += 1;
+            }
+        }
+        Ok(values)
+    }
+
+    /// Takes the next positional argument
+    pub fn positional(&mut self) -> Option<String> {
+        let index = self.args.iter().position(|arg| !arg.starts_with('-'))?;
+        Some(self.args.remove(index))
+    }
+
+    /// Takes the next positional argument, which the command can't run without
+    pub fn required(&mut self, name: &str) -> Result<String, UsageError> {
+        self.positional()
+            .ok_or_else(|| UsageError::MissingArgument { name: name.into() })
+    }
+
+    /// Checks that every argument was consumed
+    pub fn finish(mut self) -> Result<(), UsageError> {
+        self.args.retain(|arg| arg != "--");
+        match self.args.first() {
+            Some(arg) => UnexpectedSnafu { arg }.fail(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Parses the value of an option naming a side
+pub fn side(option: &str, value: &str) -> Result<Side, UsageError> {
+    match value.to_ascii_lowercase().as_str() {
+        "client" => Ok(Side::Client),
+        "server" => Ok(Side::Server),
+        "both" => Ok(Side::Both),
+        _ => InvalidValueSnafu {
+            option,
+            value,
+            expected: "client, server, or both",
+        }
+        .fail(),
+    }
+}
+
+/// Parses the value of an option naming a hash algorithm
+pub fn algorithm(option: &str, value: &str) -> Result<HashAlgorithm, UsageError> {
+    HashAlgorithm::from_name(value).context(InvalidValueSnafu {
+        option,
+        value,
+        expected: "blake3, sha512, sha256, sha1, or md5",
+    })
+}
+
+/// The command line couldn't be understood
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum UsageError {
+    /// An option was given without its value
+    #[snafu(display("{} requires a value", option))]
+    MissingValue {
+        /// The option
+        option: String,
+    },
+    /// An argument wasn't recognized
+    #[snafu(display("Unexpected argument {}", arg))]
+    Unexpected {
+        /// The argument
+        arg: String,
+    },
+    /// A required argument was left out
+    #[snafu(display("Missing {}", name))]
+    MissingArgument {
+        /// What the argument is
+        name: String,
+    },
+    /// An option was given a value it doesn't take
+    #[snafu(display("{} takes {}, not {}", option, expected, value))]
+    InvalidValue {
+        /// The option
+        option: String,
+        /// The value given
+        value: String,
+        /// What the option takes
+        expected: String,
+    },
+    /// The command wasn't recognized
+    #[snafu(display("Unknown command {}", command))]
+    UnknownCommand {
+        /// The command
+        command: String,
+    },
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    /// Splits a command line on spaces
+    fn options(line: &str) -> Options {
+        Options::new(line.split(' ').map(String::from))
+    }
+
+    // Global options are found in any position and form, leaving the command's own arguments
+    #[test]
+    fn global() {
+        let mut args = options("-p alpha show --manifest-path=packs/x/ffpack.json extra");
+        let global = Global::parse(&mut args).unwrap();
+        assert_eq!(global.pack.as_deref(), Some("alpha"));
+        assert_eq!(
+            global.manifest_path,
+            Some(PathBuf::from("packs/x/ffpack.json"))
+        );
+        assert_eq!(args.positional().as_deref(), Some("show"));
+        assert!(matches!(
+            args.finish(),
+            Err(UsageError::Unexpected { arg }) if arg == "extra"
+        ));
+
+        let mut args = options("show --pack");
+        assert!(matches!(
+            Global::parse(&mut args),
+            Err(UsageError::MissingValue { .. })
+        ));
+    }
+
+    // Algorithms are named as the manifest names them, dashes and case aside
+    #[test]
+    fn algorithm() {
+        assert_eq!(
+            super::algorithm("--to", "SHA-512").unwrap(),
+            HashAlgorithm::Sha512
+        );
+        assert!(matches!(
+            super::algorithm("--to", "sha3"),
+            Err(UsageError::InvalidValue { value, .. }) if value == "sha3"
+        ));
+    }
+}
@@ -0,0 +1,151 @@
+//! The download cache shared by every pack
+//!
+//! Artifacts with a known digest are kept under `files` in the [cache directory](cache_dir),
+//! named by their strongest digest, so packs sharing a mod only download it once and installing
+//! again needs no network. Cached copies are hashed again before they are used, and replaced if
+//! they no longer match. Artifacts without digests can't be checked, so they are downloaded
+//! every time.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use ffpack::{
+    download::{DownloadJob, DownloadOptions, Downloader},
+    hash::hash_file,
+    http::{RetryPolicy, RetryingClient},
+    resolve::ResolvedArtifact,
+    types::HashAlgorithm,
+};
+use relative_path::RelativePathBuf;
+use snafu::{ensure, ResultExt};
+
+use crate::{
+    config,
+    net::{block_on, cache_dir, CurlClient},
+    CliError, DownloadSnafu, WriteSnafu,
+};
+
+/// How the files of a fetch were come by
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Fetched {
+    /// Files that had to be downloaded
+    pub downloaded: usize,
+    /// Files copied from the cache
+    pub cached: usize,
+}
+
+/// Returns the name an artifact is cached under, if it has a digest to name it by
+fn key(artifact: &ResolvedArtifact) -> Option<RelativePathBuf> {
+    let (algorithm, digest) = artifact.hashes.preferred(&HashAlgorithm::ALL)?;
+    Some(RelativePathBuf::from(format!(
+        "{}/{}",
+        algorithm.name(),
+        hex::encode(digest)
+    )))
+}
+
+/// Returns true if the cached copy at `path` matches every digest known for the artifact
+fn is_valid(path: &Path, artifact: &ResolvedArtifact) -> bool {
+    path.is_file()
+        && hash_file(path, artifact.hashes.algorithms())
+            .is_ok_and(|actual| artifact.hashes.mismatch(&actual).is_none())
+}
+
+/// Returns where the artifact is cached, if it has a digest to be cached by
+pub fn path(artifact: &ResolvedArtifact) -> Option<PathBuf> {
+    key(artifact).map(|key| key.to_path(cache_dir().join("files")))
+}
+
+/// Puts the artifact of every job at its path under `target`, from the cache where it can
+///
+/// Every job is attempted; each failed download is reported as it is found, and then the fetch
+/// fails as a whole.
+pub fn fetch(jobs: &[DownloadJob], target: &Path) -> Result<Fetched, CliError> {
+    fetch_into(jobs, Some(target))
+}
+
+/// Puts the artifact of every job that has a digest into the cache, leaving out the others
+///
+/// Failures are reported the way [`fetch`] reports them.
+pub fn fill(jobs: &[DownloadJob]) -> Result<Fetched, CliError> {
+    fetch_into(jobs, None)
+}
+
+/// Puts the artifacts of the jobs in the cache and, with a `target`, at their paths under it
+fn fetch_into(jobs: &[DownloadJob], target: Option<&Path>) -> Result<Fetched, CliError> {
+    let cache = cache_dir().join("files");
+    let mut fetched = Fetched::default();
+    let mut copies = Vec::new();
+    let mut downloads = Vec::new();
+    for job in jobs {
+        match key(&job.artifact) {
+            Some(key) => {
+                let cached = key.to_path(&cache);
+                if is_valid(&cached, &job.artifact) {
+                    fetched.cached += 1;
+                } else {
+                    let _ = fs::remove_file(&cached);
+                    downloads.push((
+                        job,
+                        DownloadJob {
+                            artifact: job.artifact.clone(),
+                            path: key,
+                        },
+                        cache.as_path(),
+                    ));
+                }
+                if let Some(target) = target {
+                    copies.push((cached, job.path.to_path(target)));
+                }
+            }
+            None => {
+                if let Some(target) = target {
+                    downloads.push((job, job.clone(), target));
+                }
+            }
+        }
+    }
+
+    let client = RetryingClient::new(CurlClient::new(), RetryPolicy::default());
+    let config = config::get();
+    let options = DownloadOptions::default()
+        .with_parallelism(config.jobs.value)
+        .with_credentials(config.credentials());
+    let downloader = Downloader::new(client, options);
+    let mut failed = 0_usize;
+    // Jobs are downloaded into the cache and the target separately, each as one batch
+    for directory in std::iter::once(cache.as_path()).chain(target) {
+        let (original, batch): (Vec<_>, Vec<_>) = downloads
+            .iter()
+            .filter(|(_, _, into)| *into == directory)
+            .map(|(job, download, _)| (*job, download.clone()))
+            .unzip();
+        let results = block_on(downloader.download(&batch, directory));
+        for (job, result) in original.into_iter().zip(results) {
+            match result {
+                Ok(_) => fetched.downloaded += 1,
+                Err(error) => {
+                    eprintln!("error: Failed to download {}: {error}", job.path);
+                    if let Some(suggestion) = error.suggestion() {
+                        eprintln!("help: {suggestion}");
+                    }
+                    failed += 1;
+                }
+            }
+        }
+    }
+    ensure!(failed == 0, DownloadSnafu { failed });
+
+    for (cached, local) in copies {
+        if !cached.is_file() {
+            continue;
+        }
+        if let Some(parent) = local.parent() {
+            fs::create_dir_all(parent).context(WriteSnafu { path: parent })?;
+        }
+        fs::copy(&cached, &local).context(WriteSnafu { path: &local })?;
+    }
+    Ok(fetched)
+}
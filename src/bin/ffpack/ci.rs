@@ -0,0 +1,167 @@
+//! Running in CI, where nobody answers questions and findings belong on the job's page
+//!
+//! CI mode is turned on by `--ci`, or by `GITHUB_ACTIONS` when the command runs in a GitHub
+//! Actions workflow. Questions take their defaults, as with `--yes`, and ffpack draws no progress
+//! output to turn off. The commands that check the pack, `doctor`, `outdated`, and `verify`, also
+//! report what they find as the workflow commands GitHub shows as annotations, and add a Markdown
+//! summary to the job's page through `GITHUB_STEP_SUMMARY`. A command that fails annotates its
+//! error the same way.
+
+use std::{
+    env,
+    fs::OpenOptions,
+    io::Write,
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use snafu::ResultExt;
+
+use crate::{CliError, WriteSnafu};
+
+/// The variable GitHub Actions sets to `true` in workflows
+const ACTIONS_VAR: &str = "GITHUB_ACTIONS";
+
+/// The variable naming the file the job's summary is appended to
+const SUMMARY_VAR: &str = "GITHUB_STEP_SUMMARY";
+
+/// Whether CI mode is on for this run
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Turns CI mode on for the rest of the run
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Returns true if CI mode is on
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Returns true if the environment says this runs in a GitHub Actions workflow
+pub fn detected() -> bool {
+    env::var(ACTIONS_VAR).is_ok_and(|value| value == "true")
+}
+
+/// How serious an annotation is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    /// Something that won't work until it is fixed
+    Error,
+    /// Something worth looking at
+    Warning,
+}
+
+/// Escapes `text` for a workflow command, as its message or, with `property`, a property value
+fn escape(text: &str, property: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '%' => escaped.push_str("%25"),
+            '\r' => escaped.push_str("%0D"),
+            '\n' => escaped.push_str("%0A"),
+            ':' if property => escaped.push_str("%3A"),
+            ',' if property => escaped.push_str("%2C"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Formats the workflow command annotating `message` on `file`, if it is about one
+fn annotation(level: Level, file: Option<&str>, title: &str, message: &str) -> String {
+    let command = match level {
+        Level::Error => "error",
+        Level::Warning => "warning",
+    };
+    let mut properties = Vec::new();
+    if let Some(file) = file {
+        properties.push(format!("file={}", escape(file, true)));
+    }
+    properties.push(format!("title={}", escape(title, true)));
+    format!(
+        "::{command} {}::{}",
+        properties.join(","),
+        escape(message, false)
+    )
+}
+
+/// Returns `path` relative to the working directory, which workflows check the repository out to
+fn relative(path: &Path) -> String {
+    let relative = env::current_dir()
+        .ok()
+        .and_then(|cwd| path.strip_prefix(cwd).ok().map(Path::to_path_buf));
+    relative.as_deref().unwrap_or(path).display().to_string()
+}
+
+/// Prints `message` as an annotation titled `title`, on `file` if it is about one, when CI mode
+/// is on
+pub fn annotate(level: Level, file: Option<&Path>, title: &str, message: &str) {
+    if enabled() {
+        let file = file.map(relative);
+        println!("{}", annotation(level, file.as_deref(), title, message));
+    }
+}
+
+/// Lays `rows` out as a Markdown table under `headings`
+pub fn table<const N: usize>(headings: [&str; N], rows: &[[String; N]]) -> String {
+    let line = |cells: Vec<String>| format!("| {} |\n", cells.join(" | "));
+    let mut table = line(headings.map(String::from).to_vec());
+    table.push_str(&line(vec!["---".to_string(); N]));
+    for row in rows {
+        table.push_str(&line(
+            row.iter()
+                .map(|cell| cell.replace('|', "\\|").replace('\n', " "))
+                .collect(),
+        ));
+    }
+    table
+}
+
+/// Adds `markdown` to the summary of the job, when CI mode is on and the job has one
+///
+/// # Errors
+///
+/// Returns an error if the summary can't be written
+pub fn summarize(markdown: &str) -> Result<(), CliError> {
+    if !enabled() {
+        return Ok(());
+    }
+    let Some(path) = env::var_os(SUMMARY_VAR).filter(|path| !path.is_empty()) else {
+        return Ok(());
+    };
+    let path = Path::new(&path);
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| writeln!(file, "{markdown}"))
+        .context(WriteSnafu { path })
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    // Annotations escape what would end their properties or message, and summaries their cells
+    #[test]
+    fn annotation() {
+        assert_eq!(
+            super::annotation(
+                Level::Warning,
+                Some("packs/a,b/ffpack.json"),
+                "ffpack doctor: lockfile",
+                "50% done\nor so"
+            ),
+            "::warning file=packs/a%2Cb/ffpack.json,title=ffpack doctor%3A lockfile::50%25 done%0Aor so"
+        );
+        assert_eq!(
+            super::annotation(Level::Error, None, "ffpack", "Failed: twice"),
+            "::error title=ffpack::Failed: twice"
+        );
+        assert_eq!(
+            table(["Name", "Version"], &[["a|b".into(), "1.0".into()]]),
+            "| Name | Version |\n| --- | --- |\n| a\\|b | 1.0 |\n"
+        );
+    }
+}
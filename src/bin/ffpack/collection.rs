@@ -0,0 +1,131 @@
+//! `ffpack collection`: following Modrinth collections as sources of files
+//!
+//! Each collection named, by its id or its page, is followed from then on: its projects are added
+//! to the pack, resolved the way `ffpack add` resolves them and marked with the collection's id.
+//! Without any named, every collection the pack follows is synced again, adding the projects that
+//! joined it upstream and removing the files whose projects left. Projects that can't be added,
+//! because nothing compatible is published or the path is taken, are warned about and tried
+//! again the next time the collection is synced.
+
+use std::collections::BTreeSet;
+
+use ffpack::{collection, Pack};
+use snafu::ResultExt;
+
+use crate::{
+    add::{self, Placement},
+    args::{Global, Options, UsageError},
+    list,
+    net::{self, block_on},
+    save, CliError, ResolveSnafu, UsageSnafu,
+};
+
+/// Returns the ids of the collections the pack's files were added from
+fn followed(pack: &Pack) -> BTreeSet<String> {
+    pack.managed_files
+        .iter()
+        .filter_map(|file| file.collection.clone())
+        .collect()
+}
+
+/// Runs `ffpack collection [COLLECTION]...`, following the collections named, or syncing the
+/// ones the pack follows
+pub fn run(global: &Global, mut options: Options) -> Result<(), CliError> {
+    let dry_run = options.flag("--dry-run", None);
+    let mut named = BTreeSet::new();
+    while let Some(input) = options.positional() {
+        let id = collection::collection_id(&input)
+            .ok_or_else(|| UsageError::InvalidValue {
+                option: "COLLECTION".into(),
+                value: input,
+                expected: "a Modrinth collection id or page".into(),
+            })
+            .context(UsageSnafu)?;
+        named.insert(id);
+    }
+    options.finish().context(UsageSnafu)?;
+
+    let (manifest, mut pack) = global.load()?;
+    let ids = if named.is_empty() {
+        followed(&pack)
+    } else {
+        named
+    };
+    if ids.is_empty() {
+        println!("The pack follows no collections, so name one to follow it");
+        return Ok(());
+    }
+    let client = net::api_client();
+    let resolver = net::resolver(&client, &manifest, &pack);
+    for id in &ids {
+        let collection =
+            block_on(resolver.modrinth_collection(id)).context(ResolveSnafu { input: id })?;
+        let changes = collection.changes(&pack);
+        if changes.is_empty() {
+            println!("{} ({id}) is up to date", collection.name);
+            continue;
+        }
+        println!("{} ({id}):", collection.name);
+        for path in &changes.removed {
+            if dry_run {
+                println!("Would remove {path}");
+                continue;
+            }
+            pack.managed_files.retain(|file| file.path != *path);
+            println!("Removed {path}");
+        }
+        for hit in changes.added.into_values() {
+            let input = format!("modrinth:{}", list::slug(&hit.source).unwrap_or_default());
+            if dry_run {
+                println!("Would add {input}");
+                continue;
+            }
+            let resolved = add::resolve(
+                &manifest,
+                &pack,
+                hit.source,
+                hit.folder,
+                Placement::default(),
+                &input,
+            );
+            let mut file = match resolved {
+                Ok(file) => file,
+                Err(error @ (CliError::Resolve { .. } | CliError::Duplicate { .. })) => {
+                    eprintln!("warning: Skipping {input}: {error}");
+                    continue;
+                }
+                Err(error) => return Err(error),
+            };
+            file.collection = Some(id.clone());
+            println!("Added {}", file.path);
+            pack.managed_files.replace(file);
+        }
+    }
+    if dry_run {
+        return Ok(());
+    }
+    save(&manifest, &pack)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use ffpack::types::ManagedFile;
+
+    use super::*;
+
+    // Only the collections files were added from are followed
+    #[test]
+    fn followed() {
+        let mut pack = Pack::default();
+        assert!(super::followed(&pack).is_empty());
+        pack.managed_files.insert(ManagedFile {
+            path: "mods/sodium.jar".into(),
+            collection: Some("abc123".into()),
+            ..ManagedFile::default()
+        });
+        assert_eq!(
+            super::followed(&pack).into_iter().collect::<Vec<_>>(),
+            ["abc123"]
+        );
+    }
+}
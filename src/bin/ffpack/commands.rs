@@ -0,0 +1,594 @@
+//! What the command line accepts, described once for `--help`, the completions, and the manual
+//!
+//! Each command lists its arguments and options here as well as parsing them where it runs, so a
+//! command or option added to one needs adding to the other. A test reads the sources parsing
+//! them to check that both agree.
+
+use std::fmt::Write;
+
+/// The sides an option naming one takes
+const SIDES: &[&str] = &["client", "server", "both"];
+
+/// A command
+#[derive(Debug)]
+pub struct Command {
+    /// The name it is run by
+    pub name: &'static str,
+    /// What it does, in one line
+    pub about: &'static str,
+    /// Its positional arguments, as usage shows them
+    pub args: &'static str,
+    /// The values its first positional argument can take, if it only takes some
+    pub choices: &'static [&'static str],
+    /// Its options
+    pub options: &'static [Opt],
+}
+
+/// An option of a command, or of every command
+#[derive(Debug, Clone, Copy)]
+pub struct Opt {
+    /// The long form, with its dashes
+    pub long: &'static str,
+    /// The short form, without its dash
+    pub short: Option<char>,
+    /// The name of the value it takes, if it takes one
+    pub value: Option<&'static str>,
+    /// The values it can take, if it only takes some
+    pub choices: &'static [&'static str],
+    /// What it does, in one line
+    pub about: &'static str,
+}
+
+impl Opt {
+    /// An option that takes no value
+    const fn flag(long: &'static str, short: Option<char>, about: &'static str) -> Self {
+        Self {
+            long,
+            short,
+            value: None,
+            choices: &[],
+            about,
+        }
+    }
+
+    /// An option that takes a value
+    const fn value(long: &'static str, value: &'static str, about: &'static str) -> Self {
+        Self {
+            long,
+            short: None,
+            value: Some(value),
+            choices: &[],
+            about,
+        }
+    }
+
+    /// An option that takes one of a few values
+    const fn choice(
+        long: &'static str,
+        value: &'static str,
+        choices: &'static [&'static str],
+        about: &'static str,
+    ) -> Self {
+        Self {
+            long,
+            short: None,
+            value: Some(value),
+            choices,
+            about,
+        }
+    }
+
+    /// Returns how the option is written, like `-o, --output <PATH>`
+    pub fn synopsis(&self) -> String {
+        let mut synopsis = match self.short {
+            Some(short) => format!("-{short}, {}", self.long),
+            None => format!("    {}", self.long),
+        };
+        if let Some(value) = self.value {
+            write!(synopsis, " <{value}>").expect("Strings can be written to");
+        }
+        synopsis
+    }
+}
+
+/// The options every command takes
+pub const GLOBAL: &[Opt] = &[
+    Opt::value(
+        "--manifest-path",
+        "PATH",
+        "Use this manifest instead of searching for one",
+    ),
+    Opt {
+        short: Some('p'),
+        ..Opt::value(
+            "--pack",
+            "NAME",
+            "Select a pack in a repository holding several",
+        )
+    },
+    Opt::flag(
+        "--allow-build",
+        None,
+        "Let git sources run their build commands, for packs you trust",
+    ),
+    Opt::flag(
+        "--ci",
+        None,
+        "Never ask questions, and annotate findings for GitHub Actions",
+    ),
+    Opt::flag("--help", Some('h'), "Print this summary"),
+];
+
+/// `--yes`, taken by the commands that ask questions
+const YES: Opt = Opt::flag(
+    "--yes",
+    Some('y'),
+    "Take the default answer to every question",
+);
+
+/// The options choosing what an install of the pack includes
+const SELECTION: [Opt; 2] = [
+    Opt::choice("--side", "SIDE", SIDES, "Install the files for this side"),
+    Opt::flag(
+        "--no-devel",
+        None,
+        "Leave out the files only used in development",
+    ),
+];
+
+/// The arguments of `ffpack config`, which only manages tokens with the `keyring` feature
+#[cfg(feature = "keyring")]
+const CONFIG_ARGS: &str = "[ACTION] [SERVICE]";
+/// The arguments of `ffpack config`, which only manages tokens with the `keyring` feature
+#[cfg(not(feature = "keyring"))]
+const CONFIG_ARGS: &str = "";
+
+/// What `ffpack config` can do besides printing the settings
+#[cfg(feature = "keyring")]
+const CONFIG_ACTIONS: &[&str] = &["set-token", "delete-token"];
+/// What `ffpack config` can do besides printing the settings
+#[cfg(not(feature = "keyring"))]
+const CONFIG_ACTIONS: &[&str] = &[];
+
+/// Every command, in the order they are listed
+pub const COMMANDS: &[Command] = &[
+    Command {
+        name: "add",
+        about: "Add a file from Modrinth, CurseForge, GitHub, or a url",
+        args: "<SOURCE>",
+        choices: &[],
+        options: &[
+            Opt::value("--path", "PATH", "Put the file at this path in the pack"),
+            Opt::value("--name", "NAME", "Name the file"),
+            Opt::value(
+                "--artifact",
+                "REGEX",
+                "Pick the release artifact matching this regex",
+            ),
+            Opt::choice(
+                "--side",
+                "SIDE",
+                SIDES,
+                "Only install the file on this side",
+            ),
+        ],
+    },
+    Command {
+        name: "collection",
+        about: "Follow Modrinth collections, or sync the ones followed with upstream",
+        args: "[COLLECTION]...",
+        choices: &[],
+        options: &[Opt::flag(
+            "--dry-run",
+            None,
+            "Print what syncing would change without changing it",
+        )],
+    },
+    Command {
+        name: "completions",
+        about: "Print a completion script for bash, zsh, fish, or powershell",
+        args: "<SHELL>",
+        choices: &["bash", "zsh", "fish", "powershell"],
+        options: &[],
+    },
+    Command {
+        name: "config",
+        about: "Print the settings in effect, and what set each of them",
+        args: CONFIG_ARGS,
+        choices: CONFIG_ACTIONS,
+        options: &[],
+    },
+    Command {
+        name: "diff",
+        about: "Describe how one version of a pack differs from another",
+        args: "<OLD> <NEW>",
+        choices: &[],
+        options: &[Opt::choice(
+            "--format",
+            "FORMAT",
+            &["text", "markdown", "json"],
+            "Print the changes in this format",
+        )],
+    },
+    Command {
+        name: "disable",
+        about: "Keep files in the pack without installing them, by path, name, or slug",
+        args: "<FILE>...",
+        choices: &[],
+        options: &[],
+    },
+    Command {
+        name: "doctor",
+        about: "Check the manifest, lockfile, credentials, and cache for problems",
+        args: "",
+        choices: &[],
+        options: &[],
+    },
+    Command {
+        name: "enable",
+        about: "Install disabled files again, by path, name, or slug",
+        args: "<FILE>...",
+        choices: &[],
+        options: &[],
+    },
+    Command {
+        name: "export",
+        about: "Export the pack as mrpack, curseforge, server, multimc, or packwiz",
+        args: "<FORMAT>",
+        choices: &["mrpack", "curseforge", "server", "multimc", "packwiz"],
+        options: &[
+            Opt {
+                short: Some('o'),
+                ..Opt::value("--output", "PATH", "Write the export here")
+            },
+            Opt::choice(
+                "--side",
+                "SIDE",
+                SIDES,
+                "Only export the files for this side",
+            ),
+            Opt::flag("--tar", None, "Export a server as a tarball"),
+            Opt::flag("--launcher", None, "Carry the loader's server launcher"),
+            Opt::value(
+                "--start-scripts",
+                "ARGS",
+                "Generate start scripts passing these arguments to java",
+            ),
+            Opt::flag(
+                "--record",
+                None,
+                "Record the lockfile as what the pack's version was published with",
+            ),
+            Opt::flag(
+                "--force",
+                None,
+                "Record the lockfile even if the version was recorded with another",
+            ),
+        ],
+    },
+    Command {
+        name: "import",
+        about: "Write a manifest for an mrpack, CurseForge zip, packwiz project, or mods folder",
+        args: "<FROM> [DIR]",
+        choices: &[],
+        options: &[YES],
+    },
+    Command {
+        name: "init",
+        about: "Create the manifest of a new pack, asking for its details",
+        args: "[DIR]",
+        choices: &[],
+        options: &[
+            Opt::value("--name", "NAME", "Name the pack"),
+            Opt::value("--author", "AUTHOR", "Credit the pack to this author"),
+            Opt::value(
+                "--minecraft",
+                "VERSION",
+                "Make the pack for this minecraft version",
+            ),
+            Opt::choice(
+                "--loader",
+                "LOADER",
+                &["quilt", "fabric", "forge"],
+                "Make the pack for this loader",
+            ),
+            Opt::value(
+                "--loader-version",
+                "VERSION",
+                "Use this version of the loader",
+            ),
+            YES,
+        ],
+    },
+    Command {
+        name: "install",
+        about: "Install the pack into an instance directory",
+        args: "<DIR>",
+        choices: &[],
+        options: &SELECTION,
+    },
+    Command {
+        name: "instances",
+        about: "Register instances of the pack, and sync some or all of them at once",
+        args: "<ACTION> [DIR | NAME]...",
+        choices: &["add", "list", "remove", "sync"],
+        options: &[
+            SELECTION[0],
+            SELECTION[1],
+            Opt::value("--name", "NAME", "Register the instance under this name"),
+            Opt::flag("--all", None, "Sync every registered instance"),
+        ],
+    },
+    Command {
+        name: "list",
+        about: "List the pack's files, optionally filtered",
+        args: "",
+        choices: &[],
+        options: &[
+            Opt::flag("--json", None, "Print the files as JSON"),
+            Opt::choice("--side", "SIDE", SIDES, "Only list the files for this side"),
+            Opt::value(
+                "--source",
+                "KIND",
+                "Only list the files from this kind of source",
+            ),
+            Opt::flag("--devel", None, "Only list the files used in development"),
+            Opt::flag("--verbose", None, "Print each file's notes under it"),
+        ],
+    },
+    Command {
+        name: "lock",
+        about: "Lock the files that aren't locked yet, printing what changed",
+        args: "",
+        choices: &[],
+        options: &[],
+    },
+    Command {
+        name: "locate",
+        about: "Print the path of the manifest in use",
+        args: "",
+        choices: &[],
+        options: &[],
+    },
+    Command {
+        name: "man",
+        about: "Print the manual page, in roff",
+        args: "",
+        choices: &[],
+        options: &[],
+    },
+    Command {
+        name: "outdated",
+        about: "List the files with newer compatible versions, failing if there are any",
+        args: "",
+        choices: &[],
+        options: &[Opt::flag("--json", None, "Print the updates as JSON")],
+    },
+    Command {
+        name: "quickstart",
+        about: "Tour creating a pack: init, search and add mods, lock, and export an mrpack",
+        args: "[DIR]",
+        choices: &[],
+        options: &[
+            Opt::value(
+                "--mod",
+                "QUERY",
+                "Search for a mod to add before asking for more, as often as given",
+            ),
+            YES,
+        ],
+    },
+    Command {
+        name: "rehash",
+        about: "Record the digests of url and path files under another algorithm",
+        args: "[DIR]",
+        choices: &[],
+        options: &[Opt::choice(
+            "--to",
+            "ALGORITHM",
+            &["blake3", "sha512", "sha256", "sha1", "md5"],
+            "Compute digests with this algorithm, from the install at DIR if given",
+        )],
+    },
+    Command {
+        name: "remove",
+        about: "Remove files by path, name, or slug",
+        args: "<FILE>...",
+        choices: &[],
+        options: &[],
+    },
+    Command {
+        name: "search",
+        about: "Search Modrinth and CurseForge for mods, optionally adding one",
+        args: "<QUERY>",
+        choices: &[],
+        options: &[
+            Opt::flag("--add", None, "Ask which result to add to the pack"),
+            YES,
+            Opt::choice(
+                "--platform",
+                "PLATFORM",
+                &["modrinth", "curseforge"],
+                "Only search this platform",
+            ),
+            Opt::value(
+                "--limit",
+                "COUNT",
+                "Show at most this many results from each platform",
+            ),
+        ],
+    },
+    Command {
+        name: "serve",
+        about: "Serve the manifest, lockfile, and cached artifacts over HTTP",
+        args: "",
+        choices: &[],
+        options: &[Opt::value(
+            "--bind",
+            "ADDR",
+            "Listen on this address instead of 127.0.0.1:8080",
+        )],
+    },
+    Command {
+        name: "show",
+        about: "Print the manifest in use, as ffpack reads it",
+        args: "",
+        choices: &[],
+        options: &[],
+    },
+    Command {
+        name: "template",
+        about: "Print an example manifest",
+        args: "",
+        choices: &[],
+        options: &[],
+    },
+    Command {
+        name: "update",
+        about:
+            "Resolve floating sources again, for every file or each FILE named (-p picks the pack)",
+        args: "[FILE]...",
+        choices: &[],
+        options: &[Opt::value(
+            "--write-branch-report",
+            "PATH",
+            "Write what changed as JSON for update bots, exiting with 3 if anything did",
+        )],
+    },
+    Command {
+        name: "verify",
+        about: "Check an installed instance against the lockfile",
+        args: "<DIR>",
+        choices: &[],
+        options: &[
+            SELECTION[0],
+            SELECTION[1],
+            Opt::flag(
+                "--fix",
+                None,
+                "Restore the pack's files and remove the others",
+            ),
+        ],
+    },
+];
+
+/// Returns the usage summary printed by `--help`
+pub fn usage() -> String {
+    let mut usage = String::from("Usage: ffpack [OPTIONS] <COMMAND>\n\nCommands:\n");
+    let width = COMMANDS.iter().map(|command| command.name.len()).max();
+    let width = width.unwrap_or_default();
+    for command in COMMANDS {
+        writeln!(usage, "  {:width$}  {}", command.name, command.about)
+            .expect("Strings can be written to");
+    }
+    usage.push_str("\nOptions:\n");
+    let synopses: Vec<_> = GLOBAL.iter().map(Opt::synopsis).collect();
+    let width = synopses.iter().map(String::len).max().unwrap_or_default();
+    for (option, synopsis) in GLOBAL.iter().zip(&synopses) {
+        writeln!(usage, "  {synopsis:width$}  {}", option.about)
+            .expect("Strings can be written to");
+    }
+    usage
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use std::collections::BTreeSet;
+
+    use super::{Opt, COMMANDS, GLOBAL};
+
+    /// The sources parsing each command's options, by the command's name
+    const SOURCES: &[(&str, &[&str])] = &[
+        ("add", &[include_str!("add.rs")]),
+        ("collection", &[include_str!("collection.rs")]),
+        ("completions", &[include_str!("completions.rs")]),
+        ("config", &[include_str!("config.rs")]),
+        ("diff", &[include_str!("diff.rs")]),
+        ("disable", &[include_str!("enable.rs")]),
+        ("doctor", &[include_str!("doctor.rs")]),
+        ("enable", &[include_str!("enable.rs")]),
+        ("export", &[include_str!("export.rs")]),
+        ("import", &[include_str!("import.rs")]),
+        ("init", &[include_str!("init.rs")]),
+        ("install", &[include_str!("install.rs")]),
+        (
+            "instances",
+            &[include_str!("instances.rs"), include_str!("install.rs")],
+        ),
+        ("list", &[include_str!("list.rs")]),
+        ("lock", &[include_str!("lock.rs")]),
+        ("locate", &[]),
+        ("man", &[include_str!("man.rs")]),
+        ("outdated", &[include_str!("outdated.rs")]),
+        ("quickstart", &[include_str!("quickstart.rs")]),
+        ("rehash", &[include_str!("rehash.rs")]),
+        ("remove", &[include_str!("remove.rs")]),
+        ("search", &[include_str!("search.rs")]),
+        ("serve", &[include_str!("serve.rs")]),
+        ("show", &[]),
+        ("template", &[]),
+        ("update", &[include_str!("update.rs")]),
+        (
+            "verify",
+            &[include_str!("verify.rs"), include_str!("install.rs")],
+        ),
+    ];
+
+    /// Returns the options `source` takes out of its arguments, as `--long` and `-s`, leaving its
+    /// tests out
+    fn parsed(source: &str) -> BTreeSet<String> {
+        let source = source.split("#[cfg(test)]").next().unwrap_or_default();
+        let mut options = BTreeSet::new();
+        for call in [".flag(\"", ".value(\"", ".values(\""] {
+            for (start, _) in source.match_indices(call) {
+                let rest = &source[start + call.len()..];
+                let (long, rest) = rest.split_once('"').unwrap();
+                options.insert(long.to_string());
+                let short = rest.trim_start_matches([',', ' ']);
+                if let Some(short) = short.strip_prefix("Some('") {
+                    options.insert(format!("-{}", &short[..1]));
+                }
+            }
+        }
+        options
+    }
+
+    /// Returns the options listed, as `--long` and `-s`
+    fn listed(options: &[Opt]) -> BTreeSet<String> {
+        let long = options.iter().map(|option| option.long.to_string());
+        let short = options.iter().filter_map(|option| option.short);
+        long.chain(short.map(|short| format!("-{short}"))).collect()
+    }
+
+    // Every command run is listed, and takes exactly the options listed for it
+    #[test]
+    fn listed_as_parsed() {
+        let main = include_str!("main.rs");
+        let run: BTreeSet<_> = main
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix('"')?.split_once("\" =>"))
+            .map(|(name, _)| name)
+            .collect();
+        let names: BTreeSet<_> = COMMANDS.iter().map(|command| command.name).collect();
+        assert_eq!(run, names);
+        let sourced: BTreeSet<_> = SOURCES.iter().map(|(name, _)| *name).collect();
+        assert_eq!(sourced, names);
+
+        assert_eq!(parsed(include_str!("args.rs")), listed(GLOBAL));
+        for (name, sources) in SOURCES {
+            let command = COMMANDS.iter().find(|command| command.name == *name);
+            let parsed: BTreeSet<_> = sources.iter().flat_map(|source| parsed(source)).collect();
+            assert_eq!(parsed, listed(command.unwrap().options), "ffpack {name}");
+        }
+    }
+
+    // Summaries line up their descriptions, with short forms ahead of long ones
+    #[test]
+    fn usage() {
+        let usage = super::usage();
+        assert!(usage.contains("\n  add          Add a file from"));
+        assert!(usage.contains("\n  completions  Print a completion"));
+        assert!(usage.contains("\n      --manifest-path <PATH>  Use this manifest"));
+        assert!(usage.contains("\n  -p, --pack <NAME>           Select a pack"));
+    }
+}
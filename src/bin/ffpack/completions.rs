@@ -0,0 +1,506 @@
+//! `ffpack completions`: completion scripts for the shells people run ffpack from
+//!
+//! The scripts are generated from [`COMMANDS`], completing command names, each command's
+//! options, and the values of the options and arguments that only take a few. Anything else
+//! completes as a path. Distribution packages can generate them while building, or users can
+//! source the output from their shell's startup file.
+
+use std::fmt::Write;
+
+use snafu::ResultExt;
+
+use crate::{
+    args::{Options, UsageError},
+    commands::{Command, Opt, COMMANDS, GLOBAL},
+    CliError, UsageSnafu,
+};
+
+/// The shells there are completions for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Shell {
+    /// Bash
+    Bash,
+    /// Zsh
+    Zsh,
+    /// Fish
+    Fish,
+    /// PowerShell
+    Powershell,
+}
+
+impl Shell {
+    /// Every shell, by the name it is chosen with
+    const ALL: [(&'static str, Shell); 4] = [
+        ("bash", Shell::Bash),
+        ("zsh", Shell::Zsh),
+        ("fish", Shell::Fish),
+        ("powershell", Shell::Powershell),
+    ];
+
+    /// Parses the name of a shell
+    fn parse(name: &str) -> Result<Self, UsageError> {
+        Self::ALL
+            .into_iter()
+            .find(|(known, _)| name.eq_ignore_ascii_case(known))
+            .map(|(_, shell)| shell)
+            .ok_or_else(|| UsageError::InvalidValue {
+                option: "SHELL".into(),
+                value: name.into(),
+                expected: "bash, zsh, fish, or powershell".into(),
+            })
+    }
+
+    /// Returns the completion script for the shell
+    fn script(self) -> String {
+        match self {
+            Shell::Bash => bash(),
+            Shell::Zsh => zsh(),
+            Shell::Fish => fish(),
+            Shell::Powershell => powershell(),
+        }
+    }
+}
+
+/// Returns every way of writing the options, short forms included
+fn spellings(options: &[Opt]) -> Vec<String> {
+    let mut spellings = Vec::new();
+    for option in options {
+        spellings.push(option.long.to_string());
+        spellings.extend(option.short.map(|short| format!("-{short}")));
+    }
+    spellings
+}
+
+/// Returns the ways of writing the global options that take values, which the command name
+/// never follows directly
+fn global_values() -> Vec<String> {
+    let taking: Vec<_> = GLOBAL
+        .iter()
+        .filter(|o| o.value.is_some())
+        .copied()
+        .collect();
+    spellings(&taking)
+}
+
+/// Quotes `text` for the shells whose single quotes can't hold a single quote, by closing them
+/// around an escaped one
+fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+/// Appends a line to a script being built
+macro_rules! emit {
+    ($script:expr) => {
+        $script.push('\n')
+    };
+    ($script:expr, $($arg:tt)*) => {
+        writeln!($script, $($arg)*).expect("Strings can be written to")
+    };
+}
+
+/// The cases of a bash `case "$prev"` completing the values of `options`
+fn bash_values(script: &mut String, options: &[Opt]) {
+    let (choices, paths): (Vec<Opt>, Vec<Opt>) = options
+        .iter()
+        .copied()
+        .filter(|option| option.value.is_some())
+        .partition(|option| !option.choices.is_empty());
+    for option in choices {
+        emit!(
+            script,
+            "                {}) COMPREPLY=($(compgen -W {} -- \"$cur\")); return ;;",
+            spellings(&[option]).join("|"),
+            quote(&option.choices.join(" "))
+        );
+    }
+    if !paths.is_empty() {
+        emit!(
+            script,
+            "                {}) COMPREPLY=($(compgen -f -- \"$cur\")); return ;;",
+            spellings(&paths).join("|")
+        );
+    }
+}
+
+/// Generates the completions for bash
+fn bash() -> String {
+    let mut script = String::new();
+    emit!(script, "_ffpack() {{");
+    emit!(script, "    local cur=\"${{COMP_WORDS[COMP_CWORD]}}\"");
+    emit!(script, "    local prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"");
+    emit!(script, "    local command=\"\" i");
+    emit!(script, "    for ((i = 1; i < COMP_CWORD; i++)); do");
+    emit!(script, "        case \"${{COMP_WORDS[i]}}\" in");
+    emit!(
+        script,
+        "            {}) ((i++)) ;;",
+        global_values().join("|")
+    );
+    emit!(script, "            -*) ;;");
+    emit!(
+        script,
+        "            *) command=\"${{COMP_WORDS[i]}}\"; break ;;"
+    );
+    emit!(script, "        esac");
+    emit!(script, "    done");
+    emit!(script, "    case \"$command\" in");
+    emit!(script, "        \"\")");
+    emit!(script, "            case \"$prev\" in");
+    bash_values(&mut script, GLOBAL);
+    emit!(script, "            esac");
+    let names = COMMANDS.iter().map(|command| command.name.to_string());
+    let words: Vec<_> = names.chain(spellings(GLOBAL)).collect();
+    emit!(
+        script,
+        "            COMPREPLY=($(compgen -W {} -- \"$cur\")) ;;",
+        quote(&words.join(" "))
+    );
+    for command in COMMANDS {
+        let options = [command.options, GLOBAL].concat();
+        emit!(script, "        {})", command.name);
+        emit!(script, "            case \"$prev\" in");
+        bash_values(&mut script, &options);
+        emit!(script, "            esac");
+        emit!(script, "            if [[ \"$cur\" == -* ]]; then");
+        emit!(
+            script,
+            "                COMPREPLY=($(compgen -W {} -- \"$cur\"))",
+            quote(&spellings(&options).join(" "))
+        );
+        if command.args.is_empty() {
+            emit!(script, "            fi ;;");
+        } else if command.choices.is_empty() {
+            emit!(script, "            else");
+            emit!(
+                script,
+                "                COMPREPLY=($(compgen -f -- \"$cur\"))"
+            );
+            emit!(script, "            fi ;;");
+        } else {
+            emit!(script, "            else");
+            emit!(
+                script,
+                "                COMPREPLY=($(compgen -W {} -- \"$cur\"))",
+                quote(&command.choices.join(" "))
+            );
+            emit!(script, "            fi ;;");
+        }
+    }
+    emit!(script, "    esac");
+    emit!(script, "}}");
+    emit!(script, "complete -F _ffpack ffpack");
+    script
+}
+
+/// Escapes `text` for a zsh `_arguments` description, in brackets, or `_describe` item
+fn zsh_escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        if matches!(c, '[' | ']' | ':' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Returns the zsh `_arguments` specification of an option
+fn zsh_option(option: &Opt) -> String {
+    let value = match option.value {
+        None => String::new(),
+        Some(value) if option.choices.is_empty() => format!(":{value}:_files"),
+        Some(value) => format!(":{value}:({})", option.choices.join(" ")),
+    };
+    let equals = if option.value.is_some() { "=" } else { "" };
+    let rest = format!("[{}]{value}", zsh_escape(option.about));
+    match option.short {
+        None => quote(&format!("{}{equals}{rest}", option.long)),
+        Some(short) => format!(
+            "'(-{short} {long})'{{-{short},{long}{equals}}}{}",
+            quote(&rest),
+            long = option.long
+        ),
+    }
+}
+
+/// Returns the zsh `_arguments` specifications of a command's positional arguments
+fn zsh_arguments(command: &Command) -> Vec<String> {
+    command
+        .args
+        .split_whitespace()
+        .enumerate()
+        .map(|(index, arg)| {
+            let name = arg.trim_matches(|c| "<>[].".contains(c));
+            let action = if index == 0 && !command.choices.is_empty() {
+                format!("({})", command.choices.join(" "))
+            } else {
+                "_files".into()
+            };
+            let prefix = if arg.ends_with("...") {
+                "*:"
+            } else if arg.starts_with('[') {
+                "::"
+            } else {
+                ":"
+            };
+            quote(&format!("{prefix}{name}:{action}"))
+        })
+        .collect()
+}
+
+/// Generates the completions for zsh
+fn zsh() -> String {
+    let mut script = String::new();
+    emit!(script, "#compdef ffpack");
+    emit!(script);
+    emit!(script, "_ffpack() {{");
+    emit!(script, "    local line state");
+    emit!(script, "    _arguments -C \\");
+    for option in GLOBAL {
+        emit!(script, "        {} \\", zsh_option(option));
+    }
+    emit!(script, "        '1: :->command' \\");
+    emit!(script, "        '*:: :->args'");
+    emit!(script, "    case $state in");
+    emit!(script, "        command)");
+    emit!(script, "            local -a commands=(");
+    for command in COMMANDS {
+        let item = format!("{}:{}", command.name, zsh_escape(command.about));
+        emit!(script, "                {}", quote(&item));
+    }
+    emit!(script, "            )");
+    emit!(script, "            _describe command commands ;;");
+    emit!(script, "        args)");
+    emit!(script, "            case $line[1] in");
+    for command in COMMANDS {
+        let specs: Vec<_> = [command.options, GLOBAL]
+            .concat()
+            .iter()
+            .map(zsh_option)
+            .chain(zsh_arguments(command))
+            .collect();
+        emit!(script, "                {})", command.name);
+        emit!(script, "                    _arguments \\");
+        emit!(
+            script,
+            "                        {} ;;",
+            specs.join(" \\\n                        ")
+        );
+    }
+    emit!(script, "            esac ;;");
+    emit!(script, "    esac");
+    emit!(script, "}}");
+    emit!(script);
+    emit!(script, "_ffpack \"$@\"");
+    script
+}
+
+/// Quotes `text` for fish, whose single quotes hold escaped single quotes
+fn fish_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\\', r"\\").replace('\'', r"\'"))
+}
+
+/// Returns the arguments of a fish `complete` describing an option
+fn fish_option(option: &Opt) -> String {
+    let mut spec = format!("-l {}", option.long.trim_start_matches('-'));
+    if let Some(short) = option.short {
+        write!(spec, " -s {short}").expect("Strings can be written to");
+    }
+    match option.value {
+        None => {}
+        Some(_) if option.choices.is_empty() => spec.push_str(" -r -F"),
+        Some(_) => {
+            let choices = fish_quote(&option.choices.join(" "));
+            write!(spec, " -x -a {choices}").expect("Strings can be written to");
+        }
+    }
+    write!(spec, " -d {}", fish_quote(option.about)).expect("Strings can be written to");
+    spec
+}
+
+/// Generates the completions for fish
+fn fish() -> String {
+    let mut script = String::new();
+    emit!(script, "complete -c ffpack -f");
+    for option in GLOBAL {
+        emit!(script, "complete -c ffpack {}", fish_option(option));
+    }
+    for command in COMMANDS {
+        emit!(
+            script,
+            "complete -c ffpack -n __fish_use_subcommand -a {} -d {}",
+            command.name,
+            fish_quote(command.about)
+        );
+    }
+    for command in COMMANDS {
+        let seen = format!("-n '__fish_seen_subcommand_from {}'", command.name);
+        for option in command.options {
+            emit!(script, "complete -c ffpack {seen} {}", fish_option(option));
+        }
+        if !command.choices.is_empty() {
+            let choices = fish_quote(&command.choices.join(" "));
+            emit!(script, "complete -c ffpack {seen} -a {choices}");
+        } else if !command.args.is_empty() {
+            emit!(script, "complete -c ffpack {seen} -F");
+        }
+    }
+    script
+}
+
+/// Quotes `text` for PowerShell, whose single quotes hold doubled single quotes
+fn powershell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+/// Returns a PowerShell array of quoted strings
+fn powershell_array<'a>(items: impl IntoIterator<Item = &'a str>) -> String {
+    let items: Vec<_> = items.into_iter().map(powershell_quote).collect();
+    format!("@({})", items.join(", "))
+}
+
+/// Generates the completions for PowerShell
+fn powershell() -> String {
+    let mut script = String::new();
+    emit!(
+        script,
+        "Register-ArgumentCompleter -Native -CommandName ffpack -ScriptBlock {{"
+    );
+    emit!(
+        script,
+        "    param($wordToComplete, $commandAst, $cursorPosition)"
+    );
+    let global = spellings(GLOBAL);
+    emit!(
+        script,
+        "    $global = {}",
+        powershell_array(global.iter().map(String::as_str))
+    );
+    let takes_values = global_values();
+    emit!(
+        script,
+        "    $globalValues = {}",
+        powershell_array(takes_values.iter().map(String::as_str))
+    );
+    emit!(script, "    $commands = [ordered]@{{");
+    for command in COMMANDS {
+        let options = spellings(command.options);
+        emit!(
+            script,
+            "        {} = {}",
+            powershell_quote(command.name),
+            powershell_array(options.iter().map(String::as_str))
+        );
+    }
+    emit!(script, "    }}");
+    emit!(script, "    $arguments = @{{");
+    for command in COMMANDS.iter().filter(|c| !c.choices.is_empty()) {
+        emit!(
+            script,
+            "        {} = {}",
+            powershell_quote(command.name),
+            powershell_array(command.choices.iter().copied())
+        );
+    }
+    emit!(script, "    }}");
+    emit!(script, "    $values = @{{");
+    let mut seen = Vec::new();
+    let options = COMMANDS.iter().flat_map(|command| command.options);
+    for option in options.filter(|option| !option.choices.is_empty()) {
+        // Options of the same name take the same values, whichever command they are given to
+        if !seen.contains(&option.long) {
+            seen.push(option.long);
+            emit!(
+                script,
+                "        {} = {}",
+                powershell_quote(option.long),
+                powershell_array(option.choices.iter().copied())
+            );
+        }
+    }
+    emit!(script, "    }}");
+    emit!(
+        script,
+        "    $words = @($commandAst.CommandElements | Select-Object -Skip 1 |"
+    );
+    emit!(
+        script,
+        "        Where-Object {{ $_.Extent.EndOffset -lt $cursorPosition }} |"
+    );
+    emit!(script, "        ForEach-Object {{ $_.ToString() }})");
+    emit!(script, "    $command = $null");
+    emit!(script, "    $skip = $false");
+    emit!(script, "    foreach ($word in $words) {{");
+    emit!(script, "        if ($skip) {{ $skip = $false }}");
+    emit!(
+        script,
+        "        elseif ($globalValues -contains $word) {{ $skip = $true }}"
+    );
+    emit!(
+        script,
+        "        elseif (-not $command -and -not $word.StartsWith('-')) {{ $command = $word }}"
+    );
+    emit!(script, "    }}");
+    emit!(script, "    $previous = if ($words) {{ $words[-1] }}");
+    emit!(
+        script,
+        "    if ($previous -and $values.Contains($previous)) {{"
+    );
+    emit!(script, "        $candidates = $values[$previous]");
+    emit!(script, "    }} elseif (-not $command) {{");
+    emit!(script, "        $candidates = @($commands.Keys) + $global");
+    emit!(script, "    }} elseif ($wordToComplete.StartsWith('-')) {{");
+    emit!(
+        script,
+        "        $candidates = $commands[$command] + $global"
+    );
+    emit!(script, "    }} elseif ($arguments.Contains($command)) {{");
+    emit!(script, "        $candidates = $arguments[$command]");
+    emit!(script, "    }} else {{");
+    emit!(script, "        return");
+    emit!(script, "    }}");
+    emit!(
+        script,
+        "    $candidates | Where-Object {{ $_ -like \"$wordToComplete*\" }} | ForEach-Object {{"
+    );
+    emit!(
+        script,
+        "        [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)"
+    );
+    emit!(script, "    }}");
+    emit!(script, "}}");
+    script
+}
+
+/// Runs `ffpack completions <SHELL>`, printing the completion script for `SHELL`
+pub fn run(mut options: Options) -> Result<(), CliError> {
+    let shell = options.required("SHELL").context(UsageSnafu)?;
+    let shell = Shell::parse(&shell).context(UsageSnafu)?;
+    options.finish().context(UsageSnafu)?;
+    print!("{}", shell.script());
+    Ok(())
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    // Every shell completes every command, and quoting survives the descriptions' apostrophes
+    #[test]
+    fn scripts() {
+        for (_, shell) in Shell::ALL {
+            let script = shell.script();
+            for command in COMMANDS {
+                assert!(
+                    script.contains(command.name),
+                    "{shell:?} lacks {}",
+                    command.name
+                );
+            }
+        }
+        assert!(bash().contains("--side) COMPREPLY=($(compgen -W 'client server both'"));
+        assert!(zsh().contains(r"'list:List the pack'\''s files, optionally filtered'"));
+        assert!(fish().contains(r"-d 'List the pack\'s files, optionally filtered'"));
+        assert!(powershell().contains("'--side' = @('client', 'server', 'both')"));
+    }
+}
@@ -0,0 +1,689 @@
+//! The user's configuration file, and `ffpack config` printing the settings in effect
+//!
+//! Settings come from `config.toml` in the user's configuration directory, or the file
+//! `FFPACK_CONFIG` names, and each can be overridden by an environment variable, so a setting
+//! given for one run wins over the file:
+//!
+//! ```toml
+//! cache-dir = "~/.cache/ffpack"
+//! jobs = 8
+//!
+//! [tokens]
+//! curseforge = "..."
+//! github = "..."
+//! modrinth = "..."
+//!
+//! [proxy]
+//! https = "http://proxy.corp:3128"
+//! no-proxy = "localhost,.corp"
+//! ```
+//!
+//! With the `keyring` feature, tokens that neither sets are looked up in the platform's
+//! keychain, where `ffpack config set-token` stores them. The file is read once at startup, and
+//! commands take their settings from [`get`].
+
+use std::{
+    env,
+    fmt::{self, Display},
+    fs, io,
+    path::PathBuf,
+    sync::OnceLock,
+};
+
+use ffpack::{
+    http::{Proxy, ProxyConfig, ProxyError},
+    resolve::Credentials,
+    toml::{self, Table, TomlError, Value},
+};
+use snafu::{ResultExt, Snafu};
+
+use crate::{
+    args::{Global, Options, UsageError},
+    CliError, UsageSnafu,
+};
+#[cfg(feature = "keyring")]
+use crate::{keyring, prompt::Prompter, KeyringSnafu, PromptSnafu};
+
+/// The variable naming another configuration file to read
+pub const CONFIG_VAR: &str = "FFPACK_CONFIG";
+
+/// The variable overriding the cache directory
+pub const CACHE_DIR_VAR: &str = "FFPACK_CACHE_DIR";
+
+/// The variable overriding how many files are downloaded at once
+pub const JOBS_VAR: &str = "FFPACK_JOBS";
+
+/// How many files are downloaded at once unless a setting says otherwise
+const DEFAULT_JOBS: usize = 4;
+
+/// The configuration read at startup
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Sets one of the proxies of a configuration
+type ProxyField = fn(ProxyConfig, Proxy) -> ProxyConfig;
+
+/// Where a setting's value came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Origin {
+    /// Nothing set it, so it has its default
+    Default,
+    /// The configuration file
+    File,
+    /// An environment variable
+    Env(&'static str),
+    /// The platform's keychain
+    #[cfg(feature = "keyring")]
+    Keyring,
+}
+
+impl Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Origin::Default => f.write_str("default"),
+            Origin::File => f.write_str("config file"),
+            Origin::Env(variable) => f.write_str(variable),
+            #[cfg(feature = "keyring")]
+            Origin::Keyring => f.write_str("keyring"),
+        }
+    }
+}
+
+/// A setting's value, and what set it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Setting<T> {
+    /// The value in effect
+    pub value: T,
+    /// What set it
+    pub origin: Origin,
+}
+
+/// The settings in effect
+#[derive(Debug)]
+pub struct Config {
+    /// The configuration file read, or that would have been read if it existed
+    pub path: PathBuf,
+    /// Whether the configuration file exists
+    pub found: bool,
+    /// Where downloads and API responses are cached
+    pub cache_dir: Setting<PathBuf>,
+    /// How many files are downloaded at once
+    pub jobs: Setting<usize>,
+    /// The CurseForge API key
+    pub curseforge: Setting<Option<String>>,
+    /// The GitHub token
+    pub github: Setting<Option<String>>,
+    /// The Modrinth token
+    pub modrinth: Setting<Option<String>>,
+    /// The proxy for `http` urls
+    pub http_proxy: Setting<Option<String>>,
+    /// The proxy for `https` urls
+    pub https_proxy: Setting<Option<String>>,
+    /// The proxy for urls without a more specific one
+    pub all_proxy: Setting<Option<String>>,
+    /// The hosts reached without a proxy
+    pub no_proxy: Setting<Option<String>>,
+    /// Which proxies requests go through, from the proxy settings
+    pub proxy: ProxyConfig,
+    /// Problems with the file that didn't stop it being read, such as unknown settings
+    pub warnings: Vec<String>,
+}
+
+impl Config {
+    /// Reads the configuration file and the environment
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be read, or a setting has a value it doesn't take
+    pub fn from_env() -> Result<Self, ConfigError> {
+        let var = |name: &str| env::var(name).ok();
+        let path = config_path(var);
+        let document = match fs::read_to_string(&path) {
+            Ok(document) => Some(document),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => None,
+            Err(source) => return Err(ConfigError::Read { path, source }),
+        };
+        #[allow(unused_mut)]
+        let mut config = Self::from_sources(path, document.as_deref(), var)?;
+        #[cfg(feature = "keyring")]
+        config.fill_from_keyring();
+        Ok(config)
+    }
+
+    /// Looks up the tokens nothing else set in the platform's keychain
+    #[cfg(feature = "keyring")]
+    fn fill_from_keyring(&mut self) {
+        let tokens = [&mut self.curseforge, &mut self.github, &mut self.modrinth];
+        for (account, token) in keyring::ACCOUNTS.into_iter().zip(tokens) {
+            if token.value.is_none() {
+                if let Some(value) = keyring::fetch(account) {
+                    *token = Setting {
+                        value: Some(value),
+                        origin: Origin::Keyring,
+                    };
+                }
+            }
+        }
+    }
+
+    /// Builds the configuration from the file at `path`, if it was found, and variables looked
+    /// up by `var`
+    fn from_sources(
+        path: PathBuf,
+        document: Option<&str>,
+        var: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, ConfigError> {
+        let var = |name: &str| var(name).filter(|value| !value.trim().is_empty());
+        let mut file = match document {
+            Some(document) => toml::parse(document).context(ParseSnafu { path: &path })?,
+            None => Table::new(),
+        };
+        let mut tokens = take_table(&mut file, "tokens")?;
+        let mut proxies = take_table(&mut file, "proxy")?;
+
+        let cache_dir = match (var(CACHE_DIR_VAR), take_string(&mut file, "cache-dir")?) {
+            (Some(dir), _) => Setting {
+                value: dir.into(),
+                origin: Origin::Env(CACHE_DIR_VAR),
+            },
+            (None, Some(dir)) => Setting {
+                value: expand_home(&dir, &var),
+                origin: Origin::File,
+            },
+            (None, None) => Setting {
+                value: default_cache_dir(&var),
+                origin: Origin::Default,
+            },
+        };
+        let jobs = match (var(JOBS_VAR), file.remove("jobs")) {
+            (Some(jobs), _) => Setting {
+                value: jobs.trim().parse().ok().filter(|jobs| *jobs > 0).ok_or(
+                    ConfigError::Invalid {
+                        setting: "jobs",
+                        origin: Origin::Env(JOBS_VAR),
+                        expected: "a positive number",
+                    },
+                )?,
+                origin: Origin::Env(JOBS_VAR),
+            },
+            (None, Some(jobs)) => Setting {
+                value: jobs
+                    .as_integer()
+                    .and_then(|jobs| usize::try_from(jobs).ok())
+                    .filter(|jobs| *jobs > 0)
+                    .ok_or(ConfigError::Invalid {
+                        setting: "jobs",
+                        origin: Origin::File,
+                        expected: "a positive number",
+                    })?,
+                origin: Origin::File,
+            },
+            (None, None) => Setting {
+                value: DEFAULT_JOBS,
+                origin: Origin::Default,
+            },
+        };
+        // The conventional GitHub variable is meant for every tool, so ffpack's own settings win
+        let github = setting(&var, Credentials::GITHUB_VAR)
+            .or_file(&mut tokens, "tokens.github")?
+            .or_env(&var, "GITHUB_TOKEN");
+        let config = Self {
+            found: document.is_some(),
+            cache_dir,
+            jobs,
+            curseforge: setting(&var, Credentials::CURSEFORGE_VAR)
+                .or_file(&mut tokens, "tokens.curseforge")?,
+            github,
+            modrinth: setting(&var, Credentials::MODRINTH_VAR)
+                .or_file(&mut tokens, "tokens.modrinth")?,
+            http_proxy: proxy_setting(&var, "HTTP_PROXY").or_file(&mut proxies, "proxy.http")?,
+            https_proxy: proxy_setting(&var, "HTTPS_PROXY").or_file(&mut proxies, "proxy.https")?,
+            all_proxy: proxy_setting(&var, "ALL_PROXY").or_file(&mut proxies, "proxy.all")?,
+            no_proxy: proxy_setting(&var, "NO_PROXY").or_file(&mut proxies, "proxy.no-proxy")?,
+            proxy: ProxyConfig::default(),
+            warnings: [("", file), ("tokens.", tokens), ("proxy.", proxies)]
+                .into_iter()
+                .flat_map(|(prefix, table)| {
+                    let path = path.display().to_string();
+                    table
+                        .into_keys()
+                        .map(move |key| format!("Unknown setting {prefix}{key} in {path}"))
+                })
+                .collect(),
+            path,
+        };
+        let proxy = config.proxy_config()?;
+        Ok(Self { proxy, ..config })
+    }
+
+    /// Builds the proxy configuration from the proxy settings
+    fn proxy_config(&self) -> Result<ProxyConfig, ConfigError> {
+        let mut config = ProxyConfig::default();
+        let proxies: [(_, _, ProxyField); 3] = [
+            ("proxy.http", &self.http_proxy, ProxyConfig::with_http),
+            ("proxy.https", &self.https_proxy, ProxyConfig::with_https),
+            ("proxy.all", &self.all_proxy, ProxyConfig::with_all),
+        ];
+        for (name, setting, with) in proxies {
+            if let Some(value) = &setting.value {
+                let proxy = Proxy::parse(value).context(ProxySnafu {
+                    setting: name,
+                    origin: setting.origin,
+                })?;
+                config = with(config, proxy);
+            }
+        }
+        if let Some(list) = &self.no_proxy.value {
+            config = config.with_no_proxy(list);
+        }
+        Ok(config)
+    }
+
+    /// Returns the credentials to send with requests
+    pub fn credentials(&self) -> Credentials {
+        Credentials {
+            curseforge: self.curseforge.value.clone(),
+            github: self.github.value.clone(),
+            modrinth: self.modrinth.value.clone(),
+        }
+    }
+
+    /// Lists every setting by its name in the file, with its value as printed and what set it
+    ///
+    /// Tokens are only said to be set, so the listing can be shared.
+    fn settings(&self) -> Vec<(&'static str, String, Origin)> {
+        let shown = |setting: &Setting<Option<String>>| {
+            setting.value.clone().unwrap_or_else(|| "(not set)".into())
+        };
+        let hidden = |setting: &Setting<Option<String>>| {
+            let value = if setting.value.is_some() {
+                "(set)"
+            } else {
+                "(not set)"
+            };
+            value.to_string()
+        };
+        vec![
+            (
+                "cache-dir",
+                self.cache_dir.value.display().to_string(),
+                self.cache_dir.origin,
+            ),
+            ("jobs", self.jobs.value.to_string(), self.jobs.origin),
+            (
+                "tokens.curseforge",
+                hidden(&self.curseforge),
+                self.curseforge.origin,
+            ),
+            ("tokens.github", hidden(&self.github), self.github.origin),
+            (
+                "tokens.modrinth",
+                hidden(&self.modrinth),
+                self.modrinth.origin,
+            ),
+            (
+                "proxy.http",
+                shown(&self.http_proxy),
+                self.http_proxy.origin,
+            ),
+            (
+                "proxy.https",
+                shown(&self.https_proxy),
+                self.https_proxy.origin,
+            ),
+            ("proxy.all", shown(&self.all_proxy), self.all_proxy.origin),
+            (
+                "proxy.no-proxy",
+                shown(&self.no_proxy),
+                self.no_proxy.origin,
+            ),
+        ]
+    }
+}
+
+impl Setting<Option<String>> {
+    /// Takes the value of the setting `name` from its `table` of the file, unless a variable
+    /// already set it
+    fn or_file(mut self, table: &mut Table, name: &'static str) -> Result<Self, ConfigError> {
+        let value = take_string(table, name)?;
+        if self.value.is_none() && value.is_some() {
+            self = Setting {
+                value,
+                origin: Origin::File,
+            };
+        }
+        Ok(self)
+    }
+
+    /// Takes the value from the variable `name`, unless something else already set it
+    fn or_env(self, var: &impl Fn(&str) -> Option<String>, name: &'static str) -> Self {
+        if self.value.is_some() {
+            self
+        } else {
+            setting(var, name)
+        }
+    }
+}
+
+/// Returns the setting the variable `name` holds, which is unset if it doesn't hold one
+fn setting(var: &impl Fn(&str) -> Option<String>, name: &'static str) -> Setting<Option<String>> {
+    match var(name) {
+        Some(value) => Setting {
+            value: Some(value),
+            origin: Origin::Env(name),
+        },
+        None => Setting {
+            value: None,
+            origin: Origin::Default,
+        },
+    }
+}
+
+/// Returns the setting a proxy variable holds, honoring its lowercase form first as most tools
+/// do
+fn proxy_setting(
+    var: &impl Fn(&str) -> Option<String>,
+    name: &'static str,
+) -> Setting<Option<String>> {
+    let lowercase = match name {
+        "HTTP_PROXY" => "http_proxy",
+        "HTTPS_PROXY" => "https_proxy",
+        "ALL_PROXY" => "all_proxy",
+        _ => "no_proxy",
+    };
+    setting(var, lowercase).or_env(var, name)
+}
+
+/// Takes the string setting `name` out of its `table`, which is keyed by the last part of the
+/// name
+fn take_string(table: &mut Table, name: &'static str) -> Result<Option<String>, ConfigError> {
+    let key = name.rsplit('.').next().unwrap_or(name);
+    match table.remove(key) {
+        None => Ok(None),
+        Some(Value::String(value)) => Ok(Some(value)),
+        Some(_) => InvalidSnafu {
+            setting: name,
+            origin: Origin::File,
+            expected: "a string",
+        }
+        .fail(),
+    }
+}
+
+/// Takes the table `key` out of `table`, or an empty one if it isn't there
+fn take_table(table: &mut Table, key: &'static str) -> Result<Table, ConfigError> {
+    match table.remove(key) {
+        None => Ok(Table::new()),
+        Some(Value::Table(table)) => Ok(table),
+        Some(_) => InvalidSnafu {
+            setting: key,
+            origin: Origin::File,
+            expected: "a table",
+        }
+        .fail(),
+    }
+}
+
+/// Returns the user's home directory
+fn home(var: &impl Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    var("HOME")
+        .or_else(|| var("USERPROFILE"))
+        .map(PathBuf::from)
+}
+
+/// Expands a leading `~` in a path from the file to the user's home directory
+fn expand_home(path: &str, var: &impl Fn(&str) -> Option<String>) -> PathBuf {
+    match (path.strip_prefix("~/"), home(var)) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ if path == "~" => home(var).unwrap_or_else(|| path.into()),
+        _ => path.into(),
+    }
+}
+
+/// Returns where the configuration file is: `FFPACK_CONFIG` if it is set, and otherwise
+/// `ffpack/config.toml` in the user's configuration directory
+fn config_path(var: impl Fn(&str) -> Option<String>) -> PathBuf {
+    if let Some(path) = var(CONFIG_VAR).filter(|path| !path.is_empty()) {
+        return path.into();
+    }
+    let base = var("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| var("APPDATA").map(PathBuf::from))
+        .or_else(|| home(&var).map(|home| home.join(".config")))
+        .unwrap_or_else(env::temp_dir);
+    base.join("ffpack").join("config.toml")
+}
+
+/// Returns the cache directory used when no setting names one, `ffpack` in the user's cache
+/// directory
+fn default_cache_dir(var: &impl Fn(&str) -> Option<String>) -> PathBuf {
+    let base = var("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| var("LOCALAPPDATA").map(PathBuf::from))
+        .or_else(|| home(var).map(|home| home.join(".cache")))
+        .unwrap_or_else(env::temp_dir);
+    base.join("ffpack")
+}
+
+/// Reads the configuration, which commands then take from [`get`]
+///
+/// # Errors
+///
+/// Returns an error if the configuration couldn't be read
+pub fn load() -> Result<&'static Config, ConfigError> {
+    let config = Config::from_env()?;
+    Ok(CONFIG.get_or_init(|| config))
+}
+
+/// Returns the configuration read at startup
+///
+/// Commands only run once it is [loaded](load), so the defaults alone are only used by tests.
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(|| {
+        let none = |_: &str| None;
+        Config::from_sources(config_path(none), None, none).expect("The defaults are valid")
+    })
+}
+
+/// Lays the settings out as a table with aligned columns
+fn table(settings: &[(&'static str, String, Origin)]) -> String {
+    let width = |column: fn(&(&'static str, String, Origin)) -> usize| {
+        settings.iter().map(column).max().unwrap_or_default()
+    };
+    let name = width(|(name, _, _)| name.len());
+    let value = width(|(_, value, _)| value.chars().count());
+    let mut table = String::new();
+    for (setting, shown, origin) in settings {
+        let line = format!("{setting:name$}  {shown:value$}  {origin}");
+        table.push_str(line.trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+/// Takes the name of what a token is for, as the keychain stores it
+#[cfg(feature = "keyring")]
+fn account(options: &mut Options) -> Result<String, UsageError> {
+    let account = options.required("SERVICE")?.to_ascii_lowercase();
+    if !keyring::ACCOUNTS.contains(&account.as_str()) {
+        return Err(UsageError::InvalidValue {
+            option: "SERVICE".into(),
+            value: account,
+            expected: "curseforge, github, or modrinth".into(),
+        });
+    }
+    Ok(account)
+}
+
+/// Runs `ffpack config set-token <SERVICE>`, storing a token read from the terminal in the
+/// platform's keychain
+#[cfg(feature = "keyring")]
+fn set_token(mut options: Options) -> Result<(), CliError> {
+    let account = account(&mut options).context(UsageSnafu)?;
+    options.finish().context(UsageSnafu)?;
+    let mut prompter = Prompter::new(io::stdin().lock(), io::stderr(), false);
+    let token = prompter
+        .ask(&format!("The {account} token"), None, |answer| {
+            Ok(answer.to_string())
+        })
+        .context(PromptSnafu)?;
+    keyring::store(&account, &token).context(KeyringSnafu)?;
+    println!("Stored the {account} token in the keychain");
+    Ok(())
+}
+
+/// Runs `ffpack config delete-token <SERVICE>`, removing a token from the platform's keychain
+#[cfg(feature = "keyring")]
+fn delete_token(mut options: Options) -> Result<(), CliError> {
+    let account = account(&mut options).context(UsageSnafu)?;
+    options.finish().context(UsageSnafu)?;
+    keyring::delete(&account).context(KeyringSnafu)?;
+    println!("Removed the {account} token from the keychain");
+    Ok(())
+}
+
+/// Runs `ffpack config`, printing the settings in effect and what set each of them, or with
+/// the `keyring` feature `ffpack config set-token` or `delete-token`
+pub fn run(_global: &Global, mut options: Options) -> Result<(), CliError> {
+    match options.positional() {
+        None => {}
+        #[cfg(feature = "keyring")]
+        Some(action) if action == "set-token" => return set_token(options),
+        #[cfg(feature = "keyring")]
+        Some(action) if action == "delete-token" => return delete_token(options),
+        Some(action) => {
+            let expected = if cfg!(feature = "keyring") {
+                "set-token or delete-token"
+            } else {
+                "nothing, since ffpack was built without the keyring feature"
+            };
+            return Err(UsageError::InvalidValue {
+                option: "ACTION".into(),
+                value: action,
+                expected: expected.into(),
+            })
+            .context(UsageSnafu);
+        }
+    }
+    options.finish().context(UsageSnafu)?;
+    let config = get();
+    let found = if config.found { "" } else { " (not found)" };
+    println!("Configuration file: {}{found}\n", config.path.display());
+    print!("{}", table(&config.settings()));
+    Ok(())
+}
+
+/// Error that occurs while reading the configuration
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// The configuration file couldn't be read
+    #[snafu(display("Failed to read {}: {}", path.display(), source))]
+    Read {
+        /// The file's path
+        path: PathBuf,
+        /// The underlying error
+        source: io::Error,
+    },
+    /// The configuration file isn't valid TOML
+    #[snafu(display("Failed to parse {}: {}", path.display(), source))]
+    Parse {
+        /// The file's path
+        path: PathBuf,
+        /// The underlying error
+        source: TomlError,
+    },
+    /// A setting has a value it doesn't take
+    #[snafu(display("The {} setting from {} should be {}", setting, origin, expected))]
+    Invalid {
+        /// The setting, by its name in the file
+        setting: &'static str,
+        /// What set it
+        origin: Origin,
+        /// What it takes
+        expected: &'static str,
+    },
+    /// A proxy setting isn't a proxy url
+    #[snafu(display("The {} setting from {} is invalid: {}", setting, origin, source))]
+    Proxy {
+        /// The setting, by its name in the file
+        setting: &'static str,
+        /// What set it
+        origin: Origin,
+        /// The underlying error
+        source: ProxyError,
+    },
+}
+
+impl ConfigError {
+    /// Suggests how the user might fix the problem, if there is a likely fix
+    pub fn suggestion(&self) -> Option<String> {
+        match self {
+            ConfigError::Read { .. } => Some(format!(
+                "Make the file readable, or point {CONFIG_VAR} at another one"
+            )),
+            ConfigError::Parse { .. } => Some("Fix the file's syntax".into()),
+            ConfigError::Invalid { origin, .. } | ConfigError::Proxy { origin, .. } => {
+                Some(match origin {
+                    Origin::Env(variable) => format!("Fix or unset {variable}"),
+                    _ => "Fix the setting, or remove it to use the default".into(),
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use std::collections::HashMap;
+
+    use std::path::Path;
+
+    use super::*;
+
+    // Variables win over the file, which wins over the defaults, and unknown settings warn
+    #[test]
+    fn precedence() {
+        let document = r#"
+            cache-dir = "~/packs-cache"
+            jobs = 8
+            colour = true
+
+            [tokens]
+            curseforge = "from-file"
+            github = "from-file"
+
+            [proxy]
+            https = "proxy.corp:3128"
+        "#;
+        let vars = HashMap::from([
+            ("HOME", "/home/steve"),
+            ("FFPACK_JOBS", "2"),
+            ("FFPACK_CURSEFORGE_TOKEN", "from-env"),
+            ("GITHUB_TOKEN", "conventional"),
+            ("no_proxy", "localhost"),
+        ]);
+        let var = |name: &str| vars.get(name).map(|value| value.to_string());
+        let config = Config::from_sources("config.toml".into(), Some(document), var).unwrap();
+        assert_eq!(config.cache_dir.value, Path::new("/home/steve/packs-cache"));
+        assert_eq!(config.cache_dir.origin, Origin::File);
+        assert_eq!(config.jobs.value, 2);
+        assert_eq!(config.curseforge.value.as_deref(), Some("from-env"));
+        assert_eq!(config.github.value.as_deref(), Some("from-file"));
+        assert_eq!(config.modrinth.origin, Origin::Default);
+        assert_eq!(config.no_proxy.origin, Origin::Env("no_proxy"));
+        let url = url::Url::parse("https://api.modrinth.com").unwrap();
+        let proxy = config.proxy.proxy_for(&url).unwrap();
+        assert_eq!(proxy.url().as_str(), "http://proxy.corp:3128/");
+        assert_eq!(config.warnings, ["Unknown setting colour in config.toml"]);
+
+        let invalid = Config::from_sources("config.toml".into(), Some("jobs = 0"), |_| None);
+        assert!(matches!(
+            invalid,
+            Err(ConfigError::Invalid {
+                setting: "jobs",
+                origin: Origin::File,
+                ..
+            })
+        ));
+    }
+}
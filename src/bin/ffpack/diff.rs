@@ -0,0 +1,480 @@
+//! `ffpack diff`: describing how one version of a pack differs from another
+//!
+//! Rather than the lines of the manifests, the packs themselves are compared: their metadata,
+//! their versions, and their files, which are matched by path, or by project when a file moved
+//! to another path along with a new version. Files are at the versions their lockfiles locked
+//! them to, where the manifests have lockfiles, and otherwise named by their filenames. The
+//! changes print as text, as Markdown for changelogs and pull requests, or as JSON.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use ffpack::{
+    lock::Lockfile,
+    types::{ManagedFile, Source},
+    workspace::MANIFEST_NAME,
+    Pack,
+};
+use relative_path::RelativePath;
+use serde_json::json;
+use snafu::ResultExt;
+
+use crate::{
+    args::{Global, Options, UsageError},
+    list, load, lock, CliError, LockSnafu, UsageSnafu,
+};
+
+/// What kind of change a [`Change`] is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Kind {
+    /// A field of the pack's metadata changed
+    Metadata,
+    /// The minecraft, loader, or java version changed
+    Versions,
+    /// A file was added
+    Added,
+    /// A file was removed
+    Removed,
+    /// A file is at another version
+    Updated,
+    /// A file is installed on other sides
+    Side,
+    /// A file comes from another project or kind of source
+    Source,
+    /// A file was enabled or disabled
+    Toggled,
+}
+
+impl Kind {
+    /// Returns the word changes of this kind are introduced with in text
+    fn label(self) -> &'static str {
+        match self {
+            Kind::Metadata => "Metadata",
+            Kind::Versions => "Versions",
+            Kind::Added => "Added",
+            Kind::Removed => "Removed",
+            Kind::Updated => "Updated",
+            Kind::Side => "Side",
+            Kind::Source => "Source",
+            Kind::Toggled => "Toggled",
+        }
+    }
+
+    /// Returns the heading of the Markdown section listing changes of this kind
+    fn heading(self) -> &'static str {
+        match self {
+            Kind::Metadata => "Metadata",
+            Kind::Versions => "Versions",
+            Kind::Added => "Added",
+            Kind::Removed => "Removed",
+            Kind::Updated => "Updated",
+            Kind::Side => "Changed sides",
+            Kind::Source => "Changed sources",
+            Kind::Toggled => "Enabled and disabled",
+        }
+    }
+}
+
+/// A difference between two versions of a pack
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Change {
+    /// What kind of change it is
+    kind: Kind,
+    /// What changed: a field, a version, or the path of a file
+    subject: String,
+    /// What it was, if it was anything
+    old: Option<String>,
+    /// What it is, if it is anything
+    new: Option<String>,
+}
+
+impl Change {
+    /// Describes what the change went from and to, or the one side of it there is
+    fn values(&self) -> Option<String> {
+        match (&self.old, &self.new) {
+            (Some(old), Some(new)) => Some(format!("{old} -> {new}")),
+            (Some(value), None) | (None, Some(value)) => Some(value.clone()),
+            (None, None) => None,
+        }
+    }
+}
+
+/// The ways changes can be printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    /// A line for each change
+    Text,
+    /// A section for each kind of change
+    Markdown,
+    /// An array of objects
+    Json,
+}
+
+/// A version of a pack, and its lockfile if it has one
+struct Revision<'a> {
+    /// The pack
+    pack: &'a Pack,
+    /// The pack's lockfile
+    lock: Option<&'a Lockfile>,
+}
+
+impl Revision<'_> {
+    /// Names the version a file is at, as it was locked or by its filename
+    fn version(&self, file: &ManagedFile) -> Option<String> {
+        let locked = self
+            .lock
+            .and_then(|lock| lock.get(&file.path))
+            .filter(|locked| locked.source == file.source);
+        match locked {
+            Some(locked) => Some(lock::label(locked)),
+            None => Some(file.filename.clone()).filter(|filename| !filename.is_empty()),
+        }
+    }
+}
+
+/// Names the project a file comes from, for sources that name one
+fn project(source: &Source) -> Option<String> {
+    let slug = list::slug(source)?;
+    Some(format!("{} {slug}", list::source_kind(source)))
+}
+
+/// Describes a source in a few words
+fn describe(source: &Source) -> String {
+    project(source).unwrap_or_else(|| list::source_kind(source))
+}
+
+/// Returns the name of a file's side, as options take it
+fn side_name(file: &ManagedFile) -> String {
+    serde_json::to_value(&file.side)
+        .ok()
+        .and_then(|side| side.as_str().map(str::to_lowercase))
+        .unwrap_or_default()
+}
+
+/// Compares the fields of the packs' metadata and their versions
+fn pack_changes(old: &Pack, new: &Pack) -> Vec<Change> {
+    let mut changes = Vec::new();
+    let fields = |pack: &Pack| match serde_json::to_value(&pack.metadata) {
+        Ok(serde_json::Value::Object(fields)) => fields,
+        _ => serde_json::Map::new(),
+    };
+    // Strings read better without their quotes, and everything else as JSON
+    let value = |value: &serde_json::Value| {
+        value
+            .as_str()
+            .map_or_else(|| value.to_string(), String::from)
+    };
+    let (old_fields, new_fields) = (fields(old), fields(new));
+    let mut keys: Vec<_> = old_fields.keys().chain(new_fields.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    for key in keys {
+        let (before, after) = (old_fields.get(key), new_fields.get(key));
+        if before != after {
+            changes.push(Change {
+                kind: Kind::Metadata,
+                subject: key.clone(),
+                old: before.map(value),
+                new: after.map(value),
+            });
+        }
+    }
+    let versions = |pack: &Pack| {
+        let versions = &pack.versions;
+        [
+            ("minecraft", Some(versions.minecraft.to_string())),
+            (
+                "loader",
+                Some(format!(
+                    "{} {}",
+                    versions.loader.name(),
+                    versions.loader.version()
+                )),
+            ),
+            (
+                "java",
+                versions.java.as_ref().map(|java| {
+                    serde_json::to_string(java).expect("Java requirements serialize to JSON")
+                }),
+            ),
+        ]
+    };
+    for ((subject, before), (_, after)) in versions(old).into_iter().zip(versions(new)) {
+        if before != after {
+            changes.push(Change {
+                kind: Kind::Versions,
+                subject: subject.into(),
+                old: before,
+                new: after,
+            });
+        }
+    }
+    changes
+}
+
+/// Compares a file in both versions of the pack
+fn file_changes(old: (&Revision, &ManagedFile), new: (&Revision, &ManagedFile)) -> Vec<Change> {
+    let (old_revision, before) = old;
+    let (new_revision, after) = new;
+    let subject = if before.path == after.path {
+        after.path.to_string()
+    } else {
+        format!("{} -> {}", before.path, after.path)
+    };
+    let change = |kind, old, new| Change {
+        kind,
+        subject: subject.clone(),
+        old: Some(old),
+        new: Some(new),
+    };
+    let mut changes = Vec::new();
+    if project(&before.source) != project(&after.source)
+        || list::source_kind(&before.source) != list::source_kind(&after.source)
+    {
+        changes.push(change(
+            Kind::Source,
+            describe(&before.source),
+            describe(&after.source),
+        ));
+    }
+    let (old_version, new_version) = (old_revision.version(before), new_revision.version(after));
+    if old_version != new_version || before.path != after.path {
+        changes.push(Change {
+            kind: Kind::Updated,
+            subject: subject.clone(),
+            old: old_version,
+            new: new_version,
+        });
+    }
+    if before.side != after.side {
+        changes.push(change(Kind::Side, side_name(before), side_name(after)));
+    }
+    if before.enabled != after.enabled {
+        let state = |enabled| if enabled { "enabled" } else { "disabled" }.to_string();
+        changes.push(change(
+            Kind::Toggled,
+            state(before.enabled),
+            state(after.enabled),
+        ));
+    }
+    changes
+}
+
+/// Indexes the files of a pack by their paths
+fn by_path(pack: &Pack) -> BTreeMap<&RelativePath, &ManagedFile> {
+    pack.managed_files
+        .iter()
+        .map(|file| (file.path.as_relative_path(), file))
+        .collect()
+}
+
+/// Lists every change from `old` to `new`, sorted by kind and then subject
+fn diff(old: &Revision, new: &Revision) -> Vec<Change> {
+    let mut changes = pack_changes(old.pack, new.pack);
+    let (before, after) = (by_path(old.pack), by_path(new.pack));
+    let mut removed: Vec<_> = before
+        .values()
+        .filter(|file| !after.contains_key(file.path.as_relative_path()))
+        .collect();
+    let mut added: Vec<_> = after
+        .values()
+        .filter(|file| !before.contains_key(file.path.as_relative_path()))
+        .collect();
+    for (path, file) in &after {
+        if let Some(previous) = before.get(path) {
+            changes.extend(file_changes((old, previous), (new, file)));
+        }
+    }
+    // A file that moved is the same project at a new path, usually named after a new version
+    removed.retain(|file| {
+        let name = project(&file.source);
+        let moved = name.as_ref().and_then(|name| {
+            added
+                .iter()
+                .position(|added| project(&added.source).as_ref() == Some(name))
+        });
+        match moved {
+            Some(index) => {
+                let moved = added.remove(index);
+                changes.extend(file_changes((old, file), (new, moved)));
+                false
+            }
+            None => true,
+        }
+    });
+    for file in removed {
+        changes.push(Change {
+            kind: Kind::Removed,
+            subject: file.path.to_string(),
+            old: old.version(file),
+            new: None,
+        });
+    }
+    for file in added {
+        changes.push(Change {
+            kind: Kind::Added,
+            subject: file.path.to_string(),
+            old: None,
+            new: new.version(file),
+        });
+    }
+    changes.sort();
+    changes
+}
+
+/// Renders the changes in `format`
+fn render(changes: &[Change], format: Format) -> String {
+    let mut output = String::new();
+    match format {
+        Format::Text => {
+            for change in changes {
+                output.push_str(&format!("{} {}", change.kind.label(), change.subject));
+                if let Some(values) = change.values() {
+                    let separator = if change.old.is_some() && change.new.is_some() {
+                        ": "
+                    } else {
+                        " "
+                    };
+                    output.push_str(&format!("{separator}{values}"));
+                }
+                output.push('\n');
+            }
+        }
+        Format::Markdown => {
+            let mut kind = None;
+            for change in changes {
+                if kind != Some(change.kind) {
+                    if kind.is_some() {
+                        output.push('\n');
+                    }
+                    output.push_str(&format!("## {}\n\n", change.kind.heading()));
+                    kind = Some(change.kind);
+                }
+                output.push_str(&format!("- `{}`", change.subject));
+                if let Some(values) = change.values() {
+                    output.push_str(&format!(": {values}"));
+                }
+                output.push('\n');
+            }
+        }
+        Format::Json => {
+            let changes: Vec<_> = changes
+                .iter()
+                .map(|change| {
+                    json!({
+                        "change": change.kind.label().to_lowercase(),
+                        "subject": change.subject,
+                        "old": change.old,
+                        "new": change.new,
+                    })
+                })
+                .collect();
+            output = serde_json::to_string_pretty(&changes).expect("Values serialize to JSON");
+            output.push('\n');
+        }
+    }
+    output
+}
+
+/// Loads a pack given as its manifest or the directory holding it, along with its lockfile
+fn load_side(given: &str) -> Result<(Pack, Option<Lockfile>), CliError> {
+    let mut path = PathBuf::from(given);
+    if path.is_dir() {
+        path = path.join(MANIFEST_NAME);
+    }
+    let pack = load(&path)?;
+    let lock = Lockfile::load(&Lockfile::path_for(Path::new(&path))).context(LockSnafu)?;
+    Ok((pack, lock))
+}
+
+/// Runs `ffpack diff <OLD> <NEW>`, printing how the second pack differs from the first
+pub fn run(_global: &Global, mut options: Options) -> Result<(), CliError> {
+    let format = match options.value("--format", None).context(UsageSnafu)? {
+        None => Format::Text,
+        Some(format) => match format.to_ascii_lowercase().as_str() {
+            "text" => Format::Text,
+            "markdown" | "md" => Format::Markdown,
+            "json" => Format::Json,
+            _ => {
+                return Err(UsageError::InvalidValue {
+                    option: "--format".into(),
+                    value: format,
+                    expected: "text, markdown, or json".into(),
+                })
+                .context(UsageSnafu)
+            }
+        },
+    };
+    let old = options.required("OLD").context(UsageSnafu)?;
+    let new = options.required("NEW").context(UsageSnafu)?;
+    options.finish().context(UsageSnafu)?;
+    let (old_pack, old_lock) = load_side(&old)?;
+    let (new_pack, new_lock) = load_side(&new)?;
+    let old = Revision {
+        pack: &old_pack,
+        lock: old_lock.as_ref(),
+    };
+    let new = Revision {
+        pack: &new_pack,
+        lock: new_lock.as_ref(),
+    };
+    let changes = diff(&old, &new);
+    if changes.is_empty() && format != Format::Json {
+        println!("The packs are the same");
+    } else {
+        print!("{}", render(&changes, format));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use ffpack::types::Side;
+
+    use super::*;
+
+    // Moved files are matched by project, and the rest are added, removed, or changed in place
+    #[test]
+    fn diff() {
+        let sodium = |path: &str| ManagedFile {
+            path: path.into(),
+            filename: path.trim_start_matches("mods/").into(),
+            source: Source::Modrinth {
+                slug: "sodium".into(),
+                version_id: None,
+            },
+            ..ManagedFile::default()
+        };
+        let old = Pack {
+            managed_files: [sodium("mods/sodium-0.4.jar"), ManagedFile::default()]
+                .into_iter()
+                .collect(),
+            ..Pack::default()
+        };
+        let mut new = Pack {
+            managed_files: [sodium("mods/sodium-0.5.jar")].into_iter().collect(),
+            ..Pack::default()
+        };
+        new.managed_files.insert(ManagedFile {
+            path: "config/sodium.json".into(),
+            filename: "sodium.json".into(),
+            side: Side::Client,
+            ..ManagedFile::default()
+        });
+        let revision = |pack| Revision { pack, lock: None };
+        let changes = super::diff(&revision(&old), &revision(&new));
+        let text = render(&changes, Format::Text);
+        assert_eq!(
+            text,
+            "Added config/sodium.json sodium.json\n\
+             Removed mods/MyAwesomeMod.jar My Awesome Mod.jar\n\
+             Updated mods/sodium-0.4.jar -> mods/sodium-0.5.jar: sodium-0.4.jar -> \
+             sodium-0.5.jar\n"
+        );
+        let markdown = render(&changes, Format::Markdown);
+        assert!(markdown.starts_with("## Added\n\n- `config/sodium.json`: sodium.json\n\n"));
+        assert!(super::diff(&revision(&old), &revision(&old)).is_empty());
+    }
+}
@@ -0,0 +1,376 @@
+//! `ffpack doctor`: checking that the pack and the environment are ready to work with
+//!
+//! Each check prints whether it passed, with a suggestion for the ones that didn't: the manifest
+//! loads without warnings, the lockfile is up to date, `curl` runs, the credentials that are set
+//! are accepted by their APIs, the pack's loader version is published, and the cache directory
+//! is writable. Checks that couldn't reach the network only warn. The command fails if any check
+//! found an error. In CI mode, the checks that didn't pass annotate the manifest, and every check
+//! is summarized on the job's page.
+
+use std::{
+    fmt::{self, Display},
+    fs,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use ffpack::{
+    http::{HttpClient, Request},
+    lock::Lockfile,
+    resolve::{user_agent, Credentials, Endpoints},
+    types::{Loader, Source, Versions},
+    Pack,
+};
+use snafu::{ensure, ResultExt};
+use url::Url;
+
+use crate::{
+    args::{Global, Options},
+    ci::{self, Level},
+    config,
+    net::{self, block_on, CurlClient},
+    CliError, ReadSnafu, UnhealthySnafu, UsageSnafu,
+};
+
+/// How a check turned out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    /// Everything is as it should be
+    Ok,
+    /// Something is worth looking at, but works
+    Warning,
+    /// Something won't work until it is fixed
+    Error,
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Status::Ok => "ok",
+            Status::Warning => "warning",
+            Status::Error => "error",
+        })
+    }
+}
+
+/// The outcome of one check
+#[derive(Debug, PartialEq, Eq)]
+struct Check {
+    /// How it turned out
+    status: Status,
+    /// What was found
+    message: String,
+    /// How to fix it, where there is something to fix
+    suggestion: Option<String>,
+}
+
+impl Check {
+    /// A check that passed
+    fn ok(message: impl Into<String>) -> Self {
+        Self {
+            status: Status::Ok,
+            message: message.into(),
+            suggestion: None,
+        }
+    }
+
+    /// A check that found something worth looking at
+    fn warning(message: impl Into<String>, suggestion: impl Into<String>) -> Self {
+        Self {
+            status: Status::Warning,
+            message: message.into(),
+            suggestion: Some(suggestion.into()),
+        }
+    }
+
+    /// A check that found something that won't work
+    fn error(message: impl Into<String>, suggestion: Option<String>) -> Self {
+        Self {
+            status: Status::Error,
+            message: message.into(),
+            suggestion,
+        }
+    }
+}
+
+/// Checks that the lockfile next to `manifest` matches the pack
+fn check_lockfile(manifest: &Path, pack: &Pack) -> Check {
+    let path = Lockfile::path_for(manifest);
+    match Lockfile::load(&path) {
+        Ok(None) => Check::warning(
+            format!("{} doesn't exist yet", path.display()),
+            "Run ffpack lock, or any command that installs the pack",
+        ),
+        Ok(Some(lock)) => {
+            let mismatches = lock.verify_against(pack);
+            match mismatches.first() {
+                None => Check::ok(format!("{} is up to date", path.display())),
+                Some(first) => Check::warning(
+                    format!(
+                        "{} is out of date in {} ways, such as: {first}",
+                        path.display(),
+                        mismatches.len()
+                    ),
+                    "Run ffpack lock",
+                ),
+            }
+        }
+        Err(error) => Check::error(error.to_string(), error.suggestion()),
+    }
+}
+
+/// Checks that the program requests are made with runs
+fn check_curl() -> Check {
+    let program = std::env::var("FFPACK_CURL").unwrap_or_else(|_| "curl".into());
+    let output = Command::new(&program)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .output();
+    match output {
+        Ok(output) if output.status.success() => {
+            let version = String::from_utf8_lossy(&output.stdout);
+            Check::ok(version.lines().next().unwrap_or(&program).to_string())
+        }
+        Ok(_) | Err(_) => Check::error(
+            format!("{program} doesn't run"),
+            Some("Install curl, or point FFPACK_CURL at it".into()),
+        ),
+    }
+}
+
+/// The service a credential is for, and a request that only succeeds with it
+fn credential_requests(credentials: &Credentials) -> Vec<(&'static str, Request)> {
+    let endpoints = Endpoints::default();
+    let join = |base: &Url, path| base.join(path).expect("API paths are valid urls");
+    let mut requests = Vec::new();
+    if let Some(key) = &credentials.curseforge {
+        let request =
+            Request::new(join(&endpoints.curseforge, "games/432")).with_header("x-api-key", key);
+        requests.push(("CurseForge", request));
+    }
+    if let Some(token) = &credentials.github {
+        let request = Request::new(join(&endpoints.github, "rate_limit"))
+            .with_header("Authorization", format!("Bearer {token}"));
+        requests.push(("GitHub", request));
+    }
+    if let Some(token) = &credentials.modrinth {
+        let request =
+            Request::new(join(&endpoints.modrinth, "user")).with_header("Authorization", token);
+        requests.push(("Modrinth", request));
+    }
+    requests
+}
+
+/// Checks that the credentials that are set are accepted, and that packs with CurseForge
+/// sources have a key
+fn check_credentials(client: &CurlClient, pack: &Pack) -> Vec<Check> {
+    let credentials = config::get().credentials();
+    let mut checks = Vec::new();
+    let curseforge = pack
+        .managed_files
+        .iter()
+        .any(|file| matches!(file.source, Source::Curseforge { .. }));
+    if curseforge && credentials.curseforge.is_none() {
+        checks.push(Check::error(
+            "No CurseForge key is set, which the pack's CurseForge files need",
+            Some(format!(
+                "Set tokens.curseforge in {}, or {}, to a key from console.curseforge.com",
+                config::get().path.display(),
+                Credentials::CURSEFORGE_VAR
+            )),
+        ));
+    }
+    let agent = user_agent(&pack.metadata);
+    for (service, request) in credential_requests(&credentials) {
+        let request = request.with_header("User-Agent", agent.clone());
+        checks.push(match block_on(client.get(request)) {
+            Ok(response) if (200..300).contains(&response.status) => {
+                Check::ok(format!("The {service} credentials are accepted"))
+            }
+            Ok(response) if [401, 403].contains(&response.status) => Check::error(
+                format!("The {service} credentials are rejected"),
+                Some(format!("Replace the {service} credentials with valid ones")),
+            ),
+            Ok(response) => Check::warning(
+                format!(
+                    "{service} answered with status {} when checking its credentials",
+                    response.status
+                ),
+                "Try again later",
+            ),
+            Err(error) => Check::warning(
+                format!("Couldn't check the {service} credentials: {error}"),
+                "Check the network connection, and any proxy in between",
+            ),
+        });
+    }
+    checks
+}
+
+/// Returns the maven metadata listing a loader's versions, and the version it lists the pack's
+/// loader as
+fn loader_metadata(versions: &Versions) -> (Url, String) {
+    let (url, version) = match &versions.loader {
+        Loader::Quilt(version) => (
+            "https://maven.quiltmc.org/repository/release/org/quiltmc/quilt-loader",
+            version.to_string(),
+        ),
+        Loader::Fabric(version) => (
+            "https://maven.fabricmc.net/net/fabricmc/fabric-loader",
+            version.to_string(),
+        ),
+        Loader::Forge(version) => (
+            "https://maven.minecraftforge.net/net/minecraftforge/forge",
+            format!("{}-{version}", versions.minecraft),
+        ),
+    };
+    let url = Url::parse(&format!("{url}/maven-metadata.xml")).expect("Maven urls are valid");
+    (url, version)
+}
+
+/// Checks that the pack's loader version is published
+fn check_loader(client: &CurlClient, versions: &Versions) -> Check {
+    let (url, version) = loader_metadata(versions);
+    let loader = versions.loader.name();
+    match block_on(client.get(Request::new(url))) {
+        Ok(response) if response.status == 200 => {
+            let metadata = String::from_utf8_lossy(&response.body.0);
+            if metadata.contains(&format!("<version>{version}</version>")) {
+                Check::ok(format!("{loader} {version} is published"))
+            } else {
+                Check::error(
+                    format!("{loader} {version} isn't published"),
+                    Some(format!(
+                        "Set the pack's loader to a version {loader} has released"
+                    )),
+                )
+            }
+        }
+        Ok(response) => Check::warning(
+            format!(
+                "Couldn't check {loader} {version}, the maven answered with status {}",
+                response.status
+            ),
+            "Try again later",
+        ),
+        Err(error) => Check::warning(
+            format!("Couldn't check {loader} {version}: {error}"),
+            "Check the network connection, and any proxy in between",
+        ),
+    }
+}
+
+/// Checks that files can be written into the cache directory
+fn check_cache() -> Check {
+    let dir = net::cache_dir();
+    let probe = dir.join(format!(".doctor-{}", std::process::id()));
+    let written = fs::create_dir_all(&dir).and_then(|()| fs::write(&probe, b"ffpack"));
+    let _ = fs::remove_file(&probe);
+    match written {
+        Ok(()) => Check::ok(format!("{} is writable", dir.display())),
+        Err(error) => Check::error(
+            format!("{} isn't writable: {error}", dir.display()),
+            Some(format!(
+                "Set cache-dir in {}, or {}, to a writable directory",
+                config::get().path.display(),
+                config::CACHE_DIR_VAR
+            )),
+        ),
+    }
+}
+
+/// Summarizes the checks in Markdown, for the job's page
+fn summary(checks: &[Check]) -> String {
+    let rows: Vec<_> = checks
+        .iter()
+        .map(|check| {
+            [
+                check.status.to_string(),
+                check.message.clone(),
+                check.suggestion.clone().unwrap_or_default(),
+            ]
+        })
+        .collect();
+    format!(
+        "## ffpack doctor\n\n{}",
+        ci::table(["Status", "Check", "Help"], &rows)
+    )
+}
+
+/// Runs `ffpack doctor`, checking the manifest in use and the environment
+pub fn run(global: &Global, options: Options) -> Result<(), CliError> {
+    options.finish().context(UsageSnafu)?;
+    let manifest = global.manifest()?;
+    let json = fs::read_to_string(&manifest).context(ReadSnafu { path: &manifest })?;
+    let mut checks = Vec::new();
+    let pack = match Pack::from_json_with_warnings(&json) {
+        Ok((pack, warnings)) => {
+            checks.push(Check::ok(format!("{} loads", manifest.display())));
+            checks.extend(warnings.iter().map(|warning| {
+                Check::warning(warning.to_string(), "Update the manifest as described")
+            }));
+            Some(pack)
+        }
+        Err(error) => {
+            checks.push(Check::error(
+                format!("{} doesn't load: {error}", manifest.display()),
+                error.suggestion(),
+            ));
+            None
+        }
+    };
+    let client = CurlClient::new();
+    checks.push(check_curl());
+    if let Some(pack) = &pack {
+        checks.push(check_lockfile(&manifest, pack));
+        checks.extend(check_credentials(&client, pack));
+        checks.push(check_loader(&client, &pack.versions));
+    }
+    checks.push(check_cache());
+
+    for check in &checks {
+        println!("{:<8} {}", check.status, check.message);
+        if let Some(suggestion) = &check.suggestion {
+            println!("{:<8} help: {suggestion}", "");
+        }
+        let level = match check.status {
+            Status::Ok => continue,
+            Status::Warning => Level::Warning,
+            Status::Error => Level::Error,
+        };
+        let message = match &check.suggestion {
+            Some(suggestion) => format!("{}\n{suggestion}", check.message),
+            None => check.message.clone(),
+        };
+        ci::annotate(level, Some(&manifest), "ffpack doctor", &message);
+    }
+    ci::summarize(&summary(&checks))?;
+    let errors = checks
+        .iter()
+        .filter(|check| check.status == Status::Error)
+        .count();
+    ensure!(errors == 0, UnhealthySnafu { count: errors });
+    Ok(())
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use semver::Version;
+
+    use super::*;
+
+    // Forge versions are listed with the minecraft version they are for
+    #[test]
+    fn loader_metadata() {
+        let versions = Versions {
+            loader: Loader::new_forge(Version::new(47, 1, 0)),
+            ..Versions::default()
+        };
+        let (url, version) = super::loader_metadata(&versions);
+        assert_eq!(
+            url.as_str(),
+            "https://maven.minecraftforge.net/net/minecraftforge/forge/maven-metadata.xml"
+        );
+        assert_eq!(version, format!("{}-47.1.0", versions.minecraft));
+    }
+}
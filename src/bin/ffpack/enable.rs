@@ -0,0 +1,72 @@
+//! `ffpack disable` and `ffpack enable`: keeping a file in the pack without installing it
+//!
+//! Files are named the way `ffpack remove` takes them. A disabled file stays in the manifest and
+//! lockfile, but is installed with `.disabled` appended to its path, so loaders skip it until it
+//! is enabled again.
+
+use ffpack::Pack;
+use relative_path::RelativePathBuf;
+use snafu::{ensure, ResultExt};
+
+use crate::{
+    args::{Global, Options},
+    remove::find,
+    save, AmbiguousSnafu, CliError, NotFoundSnafu, UsageSnafu,
+};
+
+/// Enables or disables the one file going by `given`, returning its path
+fn set(pack: &mut Pack, given: &str, enabled: bool) -> Result<RelativePathBuf, CliError> {
+    let matched = find(&pack.managed_files, given);
+    ensure!(!matched.is_empty(), NotFoundSnafu { given });
+    let paths: Vec<_> = matched.iter().map(|file| file.path.to_string()).collect();
+    ensure!(paths.len() == 1, AmbiguousSnafu { given, paths });
+    let path = matched[0].path.clone();
+    pack.set_enabled(&path, enabled);
+    Ok(path)
+}
+
+/// Runs `ffpack enable <FILE>...` or, without `enabled`, `ffpack disable <FILE>...`, on the
+/// manifest in use
+pub fn run(global: &Global, mut options: Options, enabled: bool) -> Result<(), CliError> {
+    let mut given = Vec::new();
+    while let Some(file) = options.positional() {
+        given.push(file);
+    }
+    if given.is_empty() {
+        options.required("FILE").context(UsageSnafu)?;
+    }
+    options.finish().context(UsageSnafu)?;
+    let (manifest, mut pack) = global.load()?;
+    for given in given {
+        let path = set(&mut pack, &given, enabled)?;
+        if enabled {
+            println!("Enabled {path}");
+        } else {
+            println!("Disabled {path}");
+        }
+    }
+    save(&manifest, &pack)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use relative_path::RelativePath;
+
+    use super::*;
+
+    // Files are disabled and enabled by name, and installed out of the loader's way meanwhile
+    #[test]
+    fn set() {
+        let mut pack = Pack::default();
+        let path = RelativePath::new("mods/MyAwesomeMod.jar");
+        let installed = |pack: &Pack| pack.managed_files.first().unwrap().install_path();
+        assert_eq!(
+            super::set(&mut pack, "my totally awesome mode", false).unwrap(),
+            path
+        );
+        assert_eq!(installed(&pack), "mods/MyAwesomeMod.jar.disabled");
+        super::set(&mut pack, "mods/MyAwesomeMod.jar", true).unwrap();
+        assert_eq!(installed(&pack), path);
+        assert!(super::set(&mut pack, "lithium", false).is_err());
+    }
+}
@@ -0,0 +1,385 @@
+//! `ffpack export`: writing the pack in the format of a launcher or platform
+//!
+//! Every format is exported from the lockfile, locking the pack first if it changed. Formats
+//! that carry files rather than links to them are given an install of the pack to copy from,
+//! which comes out of the download cache. `--side` leaves out the files the other side needs,
+//! and the summary tells how many files were embedded and how many are referenced by url, to be
+//! downloaded by whoever installs the export. Exports made for publishing take `--record`,
+//! which records the lockfile in the pack's history as what its version was published with.
+//! A version already recorded with another lockfile is only recorded again with `--force`, so
+//! exporting to try something out never rewrites what a published version shipped.
+
+use std::{
+    env,
+    fs::{self, File},
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+use ffpack::{
+    download::DownloadJob,
+    export::{curseforge, mrpack, multimc, packwiz, server, Loss},
+    lock::{history::History, Lockfile},
+    resolve::{ProjectDetails, ResolvedArtifact, UpstreamIds},
+    types::{Hashes, ManagedFile, Side, Source},
+    Pack,
+};
+use snafu::ResultExt;
+
+use crate::{
+    args::{self, Global, Options, UsageError},
+    cache,
+    install::{jobs, Selection},
+    lock, scratch, CliError, ExportSnafu, LockSnafu, RecordedSnafu, UsageSnafu, WriteSnafu,
+};
+
+/// The formats packs can be exported in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// A Modrinth `.mrpack`
+    Mrpack,
+    /// A CurseForge modpack zip
+    Curseforge,
+    /// A server directory, or tarball with `--tar`
+    Server,
+    /// A MultiMC or Prism Launcher instance zip
+    Multimc,
+    /// A packwiz project directory
+    Packwiz,
+}
+
+impl Format {
+    /// Every format, by the name it is chosen with
+    const ALL: [(&'static str, Format); 5] = [
+        ("mrpack", Format::Mrpack),
+        ("curseforge", Format::Curseforge),
+        ("server", Format::Server),
+        ("multimc", Format::Multimc),
+        ("packwiz", Format::Packwiz),
+    ];
+
+    /// Parses the name of a format
+    fn parse(name: &str) -> Result<Self, UsageError> {
+        Self::ALL
+            .into_iter()
+            .find(|(known, _)| name.eq_ignore_ascii_case(known))
+            .map(|(_, format)| format)
+            .ok_or_else(|| UsageError::InvalidValue {
+                option: "FORMAT".into(),
+                value: name.into(),
+                expected: "mrpack, curseforge, server, multimc, or packwiz".into(),
+            })
+    }
+
+    /// Returns true if the format copies files from an install of the pack
+    fn needs_install(self) -> bool {
+        matches!(self, Format::Curseforge | Format::Server | Format::Multimc)
+    }
+
+    /// Returns true if the format leaves the file out entirely
+    fn excludes(self, file: &ManagedFile) -> bool {
+        match self {
+            Format::Server => file.side == Side::Client || file.devel,
+            Format::Multimc => file.side == Side::Server,
+            _ => false,
+        }
+    }
+
+    /// Returns true if the export links to the file instead of carrying it
+    fn references(self, file: &ManagedFile) -> bool {
+        match self {
+            Format::Mrpack | Format::Packwiz => !matches!(file.source, Source::Path { .. }),
+            Format::Curseforge => matches!(file.source, Source::Curseforge { .. }),
+            Format::Server | Format::Multimc => false,
+        }
+    }
+
+    /// Returns where the export goes unless `--output` says otherwise
+    pub fn default_output(self, pack: &Pack, tar: bool) -> PathBuf {
+        let name: String = pack
+            .metadata
+            .name(None)
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '-' })
+            .collect();
+        let stem = format!("{name}-{}", pack.metadata.version());
+        PathBuf::from(match self {
+            Format::Mrpack => format!("{stem}.mrpack"),
+            Format::Curseforge => format!("{stem}.zip"),
+            Format::Server if tar => format!("{stem}-server.tar"),
+            Format::Server => format!("{stem}-server"),
+            Format::Multimc => format!("{stem}-multimc.zip"),
+            Format::Packwiz => format!("{stem}-packwiz"),
+        })
+    }
+}
+
+/// What the server format is given beyond the pack
+#[derive(Debug, Default)]
+pub struct ServerFlags {
+    /// Write a tarball instead of a directory
+    tar: bool,
+    /// Carry the loader's server launcher
+    launcher: bool,
+    /// Generate start scripts passing these arguments to java
+    start_scripts: Option<String>,
+}
+
+/// Downloads the loader's server launcher into `staging`, if the loader publishes one
+fn fetch_launcher(pack: &Pack, staging: &Path) -> Result<Option<PathBuf>, CliError> {
+    let Some(url) = server::launcher_url(&pack.versions) else {
+        eprintln!(
+            "warning: {} publishes no server launcher, so the server needs its installer run \
+             by hand",
+            pack.versions.loader.name()
+        );
+        return Ok(None);
+    };
+    let job = DownloadJob {
+        artifact: ResolvedArtifact {
+            download_url: url,
+            filename: server::LAUNCHER_JAR.into(),
+            size: None,
+            hashes: Hashes::default(),
+            mirrors: Vec::new(),
+            extract: None,
+            ids: UpstreamIds::default(),
+            details: ProjectDetails::default(),
+        },
+        path: server::LAUNCHER_JAR.into(),
+    };
+    cache::fetch(&[job], staging)?;
+    Ok(Some(staging.join(server::LAUNCHER_JAR)))
+}
+
+/// Creates the file an archive is written to
+fn create(output: &Path) -> Result<BufWriter<File>, CliError> {
+    if let Some(parent) = output
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent).context(WriteSnafu { path: parent })?;
+    }
+    let file = File::create(output).context(WriteSnafu { path: output })?;
+    Ok(BufWriter::new(file))
+}
+
+/// Exports `pack` as `format` to `output`, copying files from the install in `staging`
+fn export(
+    format: Format,
+    pack: &Pack,
+    lock: &Lockfile,
+    root: &Path,
+    staging: &Path,
+    flags: &ServerFlags,
+    output: &Path,
+) -> Result<Vec<Loss>, CliError> {
+    let losses = match format {
+        Format::Mrpack => mrpack::export(pack, lock, root, create(output)?),
+        Format::Curseforge => {
+            let report = curseforge::export(pack, lock, root, staging, create(output)?)
+                .context(ExportSnafu)?;
+            for path in &report.redistributed {
+                eprintln!(
+                    "warning: {path} isn't on CurseForge, so check that its license allows \
+                     redistributing it"
+                );
+            }
+            Ok(report.losses)
+        }
+        Format::Server => {
+            let mut options = server::ServerOptions::default();
+            if flags.launcher {
+                if let Some(jar) = fetch_launcher(pack, staging)? {
+                    options = options.with_launcher(jar);
+                }
+            }
+            if let Some(java_args) = &flags.start_scripts {
+                options = options.with_start_scripts(java_args);
+            }
+            if flags.tar {
+                server::export_tar(pack, root, staging, &options, create(output)?)
+            } else {
+                server::export_dir(pack, root, staging, &options, output)
+            }
+        }
+        Format::Multimc => multimc::export(
+            pack,
+            multimc::GameFiles::Installed(staging),
+            create(output)?,
+        ),
+        Format::Packwiz => packwiz::export(pack, lock, root, output),
+    };
+    losses.context(ExportSnafu)
+}
+
+/// Runs `ffpack export <FORMAT>`, exporting the manifest in use
+pub fn run(global: &Global, mut options: Options) -> Result<(), CliError> {
+    let output = options.value("--output", Some('o')).context(UsageSnafu)?;
+    let side = options
+        .value("--side", None)
+        .context(UsageSnafu)?
+        .map(|side| args::side("--side", &side))
+        .transpose()
+        .context(UsageSnafu)?;
+    let flags = ServerFlags {
+        tar: options.flag("--tar", None),
+        launcher: options.flag("--launcher", None),
+        start_scripts: options.value("--start-scripts", None).context(UsageSnafu)?,
+    };
+    let record = options.flag("--record", None);
+    let force = options.flag("--force", None);
+    let format = options.required("FORMAT").context(UsageSnafu)?;
+    let format = Format::parse(&format).context(UsageSnafu)?;
+    options.finish().context(UsageSnafu)?;
+
+    let (manifest, mut pack) = global.load()?;
+    let lock = lock::ensure(&manifest, &pack)?;
+    if let Some(side) = side {
+        pack.managed_files
+            .retain(|file| file.side == Side::Both || file.side == side);
+    }
+    let output = output.map_or_else(|| format.default_output(&pack, flags.tar), PathBuf::from);
+    write(&manifest, &pack, &lock, format, &flags, &output)?;
+    if record {
+        self::record(&manifest, &pack, &lock, force)?;
+    }
+    Ok(())
+}
+
+/// Records `lock` in the pack's history as what the pack's version was published with
+///
+/// A version recorded with another lockfile before is only recorded again with `force`.
+fn record(manifest: &Path, pack: &Pack, lock: &Lockfile, force: bool) -> Result<(), CliError> {
+    let history = History::for_manifest(manifest);
+    let version = pack.metadata.version();
+    match history.load(version).context(LockSnafu)? {
+        Some(recorded) if recorded == *lock => {
+            println!("The lockfile of {version} was already recorded");
+            return Ok(());
+        }
+        Some(_) if !force => {
+            return RecordedSnafu {
+                version: version.clone(),
+                path: history.path_of(version),
+            }
+            .fail()
+        }
+        _ => {}
+    }
+    let recorded = history.record(version, lock).context(LockSnafu)?;
+    println!(
+        "Recorded the lockfile of {version} in {}",
+        recorded.display()
+    );
+    Ok(())
+}
+
+/// Exports `pack`, locked as `lock`, as `format` to `output`
+pub fn write(
+    manifest: &Path,
+    pack: &Pack,
+    lock: &Lockfile,
+    format: Format,
+    flags: &ServerFlags,
+    output: &Path,
+) -> Result<(), CliError> {
+    let root = manifest
+        .canonicalize()
+        .context(WriteSnafu { path: manifest })?
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+
+    // A fresh directory of the user's own, which nobody else can have prepared
+    let staging = scratch::private_dir("ffpack-export").context(WriteSnafu {
+        path: env::temp_dir(),
+    })?;
+    let staged = if format.needs_install() {
+        // Only the files the export carries are installed
+        let mut carried = pack.clone();
+        carried
+            .managed_files
+            .retain(|file| !format.excludes(file) && !format.references(file));
+        let everything = Selection {
+            side: Side::Both,
+            no_devel: false,
+        };
+        jobs(&carried, lock, &everything).and_then(|jobs| cache::fetch(&jobs, &staging))
+    } else {
+        Ok(cache::Fetched::default())
+    };
+    let exported = staged.and_then(|_| export(format, pack, lock, &root, &staging, flags, output));
+    let _ = fs::remove_dir_all(&staging);
+    let losses = exported?;
+
+    let files: Vec<_> = pack
+        .managed_files
+        .iter()
+        .filter(|file| !format.excludes(file))
+        .collect();
+    let referenced = files.iter().filter(|file| format.references(file)).count();
+    println!(
+        "Exported {}: {} files embedded, {referenced} referenced by url",
+        output.display(),
+        files.len() - referenced
+    );
+    if !losses.is_empty() {
+        println!(
+            "{} things the format can't represent were left out, as warned above",
+            losses.len()
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use ffpack::types::Loader;
+
+    use super::*;
+
+    // Formats are named loosely, and each carries or links to files as its platform expects
+    #[test]
+    fn format() {
+        assert_eq!(Format::parse("MrPack").unwrap(), Format::Mrpack);
+        assert!(Format::parse("zip").is_err());
+        let curseforge = ManagedFile {
+            side: Side::Client,
+            source: Source::Curseforge {
+                slug: "jei".into(),
+                file_id: None,
+            },
+            ..ManagedFile::default()
+        };
+        assert!(Format::Curseforge.references(&curseforge));
+        assert!(Format::Mrpack.references(&curseforge));
+        assert!(!Format::Multimc.references(&curseforge));
+        assert!(Format::Server.excludes(&curseforge));
+        assert!(!Format::Multimc.excludes(&curseforge));
+    }
+
+    // Recording again changes nothing, and only replaces another lockfile when forced
+    #[test]
+    fn record() {
+        let dir = scratch::private_dir("ffpack-record").unwrap();
+        let manifest = dir.join("ffpack.json");
+        let pack = Pack::default();
+        let lock = Lockfile::default();
+        super::record(&manifest, &pack, &lock, false).unwrap();
+        super::record(&manifest, &pack, &lock, false).unwrap();
+        let mut relocked = lock.clone();
+        relocked.versions.loader = Loader::new_fabric(semver::Version::new(0, 15, 0));
+        assert!(matches!(
+            super::record(&manifest, &pack, &relocked, false),
+            Err(CliError::Recorded { .. })
+        ));
+        let history = History::for_manifest(&manifest);
+        let version = pack.metadata.version();
+        assert_eq!(history.load(version).unwrap(), Some(lock));
+        super::record(&manifest, &pack, &relocked, true).unwrap();
+        assert_eq!(history.load(version).unwrap(), Some(relocked));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,303 @@
+//! `ffpack import`: writing a manifest for a pack made with another tool
+//!
+//! What is imported is worked out from what is given: an `.mrpack` or CurseForge modpack zip by
+//! the index it carries, a packwiz project by its `pack.toml`, and anything else with a `mods`
+//! folder as a game directory whose jars are looked up by their hashes. Archives have their
+//! overrides extracted next to the new manifest, while directories get the manifest written
+//! into them, where their path sources point. Url sources the format doesn't pin are downloaded
+//! once for their digests, and when several files turn out to be the same project, which one to
+//! keep is asked.
+
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+};
+
+use ffpack::{
+    archive::ZipArchive,
+    http::{RetryPolicy, RetryingClient},
+    import::{curseforge, instance, packwiz, Imported},
+    rehash::Rehasher,
+    types::{HashAlgorithm, Source},
+    workspace::{MANIFEST_NAME, PACKS_DIR},
+    Pack,
+};
+use relative_path::RelativePathBuf;
+use snafu::{ensure, ResultExt};
+
+use crate::{
+    args::{Global, Options, UsageError},
+    config,
+    init::{self, Answers},
+    list,
+    net::{self, block_on, CurlClient},
+    prompt::Prompter,
+    save, CliError, ExistsSnafu, ImportSnafu, PinSnafu, PromptSnafu, ReadSnafu, UnknownFormatSnafu,
+    UsageSnafu, WriteSnafu,
+};
+
+/// The index every `.mrpack` carries
+#[cfg(feature = "modrinth")]
+const MRPACK_INDEX: &str = "modrinth.index.json";
+
+/// The manifest every CurseForge modpack carries
+const CURSEFORGE_MANIFEST: &str = "manifest.json";
+
+/// The file at the root of every packwiz project
+const PACKWIZ_PACK: &str = "pack.toml";
+
+/// The folder of a game directory holding its mods
+const MODS: &str = "mods";
+
+/// The kinds of packs that can be imported
+#[derive(Debug, PartialEq, Eq)]
+enum Kind {
+    /// A Modrinth `.mrpack`
+    #[cfg(feature = "modrinth")]
+    Mrpack,
+    /// A CurseForge modpack zip
+    Curseforge,
+    /// A packwiz project directory
+    Packwiz(PathBuf),
+    /// A game directory, whose `mods` folder is scanned
+    Instance(PathBuf),
+}
+
+impl Kind {
+    /// Returns the directory of a pack that is one, which the manifest goes into
+    fn directory(&self) -> Option<&Path> {
+        match self {
+            Kind::Packwiz(dir) | Kind::Instance(dir) => Some(dir),
+            _ => None,
+        }
+    }
+}
+
+/// Works out what kind of pack is at `path`
+fn detect(path: &Path) -> Result<Kind, CliError> {
+    if path.is_dir() {
+        if path.join(PACKWIZ_PACK).is_file() {
+            return Ok(Kind::Packwiz(path.to_path_buf()));
+        }
+        if path.join(MODS).is_dir() {
+            return Ok(Kind::Instance(path.to_path_buf()));
+        }
+        // The mods folder itself stands for the game directory holding it
+        let is_mods = path.file_name().is_some_and(|name| name == MODS);
+        ensure!(is_mods, UnknownFormatSnafu { path });
+        let parent = path.parent().unwrap_or(Path::new(""));
+        let parent = if parent.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            parent
+        };
+        return Ok(Kind::Instance(parent.to_path_buf()));
+    }
+    let file = File::open(path).context(ReadSnafu { path })?;
+    let Ok(archive) = ZipArchive::new(file) else {
+        return UnknownFormatSnafu { path }.fail();
+    };
+    let has = |name: &str| archive.entries().iter().any(|entry| entry.name() == name);
+    #[cfg(feature = "modrinth")]
+    if has(MRPACK_INDEX) {
+        return Ok(Kind::Mrpack);
+    }
+    ensure!(has(CURSEFORGE_MANIFEST), UnknownFormatSnafu { path });
+    Ok(Kind::Curseforge)
+}
+
+/// Groups the paths of files that were identified as the same project, which are likely several
+/// versions of it
+fn conflicts(pack: &Pack) -> Vec<(String, Vec<RelativePathBuf>)> {
+    let mut projects: BTreeMap<_, Vec<_>> = BTreeMap::new();
+    for file in &pack.managed_files {
+        if let Some(slug) = list::slug(&file.source) {
+            let project = format!("{} {slug}", list::source_kind(&file.source));
+            projects.entry(project).or_default().push(file.path.clone());
+        }
+    }
+    projects
+        .into_iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .collect()
+}
+
+/// Asks which of the files identified as the same project to keep, removing the others
+///
+/// Keeping them all is the default, so `--yes` changes nothing.
+fn resolve_conflicts<R: BufRead, W: Write>(
+    prompter: &mut Prompter<R, W>,
+    pack: &mut Pack,
+) -> io::Result<()> {
+    for (project, paths) in conflicts(pack) {
+        let mut question = format!("These files are all {project}:\n");
+        for (number, path) in paths.iter().enumerate() {
+            question.push_str(&format!("  {}) {path}\n", number + 1));
+        }
+        question.push_str("Keep which, by number or all");
+        let keep = prompter.ask(&question, Some("all"), |answer| {
+            if answer.eq_ignore_ascii_case("all") {
+                return Ok(None);
+            }
+            match answer.parse::<usize>() {
+                Ok(number) if (1..=paths.len()).contains(&number) => Ok(Some(number - 1)),
+                _ => Err(format!(
+                    "{answer} isn't all or a number up to {}",
+                    paths.len()
+                )),
+            }
+        })?;
+        if let Some(keep) = keep {
+            pack.managed_files
+                .retain(|file| !paths.contains(&file.path) || file.path == paths[keep]);
+        }
+    }
+    Ok(())
+}
+
+/// Pins the url sources the format left without a blake3 hash, by downloading them once
+fn pin(imported: &mut Imported) -> Result<(), CliError> {
+    if imported.unpinned.is_empty() {
+        return Ok(());
+    }
+    let client = RetryingClient::new(CurlClient::new(), RetryPolicy::default());
+    let rehasher = Rehasher::new(&client, net::cache_dir().join("pins"))
+        .with_credentials(config::get().credentials());
+    let algorithms = [HashAlgorithm::Sha1, HashAlgorithm::Sha512];
+    for path in &imported.unpinned {
+        let pack = &mut imported.pack;
+        let Some(Source::Url { url, .. }) = pack
+            .managed_files
+            .iter()
+            .find(|file| file.path == *path)
+            .map(|file| file.source.clone())
+        else {
+            // Another file of the same project was kept instead
+            continue;
+        };
+        let input = url.to_string();
+        eprintln!("Pinning {path} from {input}");
+        let pinned = block_on(rehasher.pin_url(pack, path.clone(), url, algorithms))
+            .context(PinSnafu { input })?;
+        pack.managed_files.replace(pinned);
+    }
+    Ok(())
+}
+
+/// Runs `ffpack import <FROM> [DIR]`, writing a manifest for the pack at `FROM`
+///
+/// Archives are imported into `DIR`, or the named pack's directory under it, and directories
+/// into themselves.
+pub fn run(global: &Global, mut options: Options) -> Result<(), CliError> {
+    let yes = options.flag("--yes", Some('y'));
+    let from = PathBuf::from(options.required("FROM").context(UsageSnafu)?);
+    let dir = options.positional();
+    options.finish().context(UsageSnafu)?;
+
+    let kind = detect(&from)?;
+    let root = match (kind.directory(), dir) {
+        (Some(directory), None) => directory.to_path_buf(),
+        (Some(_), Some(dir)) => {
+            return Err(UsageError::InvalidValue {
+                option: "DIR".into(),
+                value: dir,
+                expected: "nothing for a directory, whose manifest goes inside it".into(),
+            })
+            .context(UsageSnafu)
+        }
+        (None, dir) => {
+            let dir = dir.map_or_else(|| PathBuf::from("."), PathBuf::from);
+            match &global.pack {
+                Some(pack) => dir.join(PACKS_DIR).join(pack),
+                None => dir,
+            }
+        }
+    };
+    let manifest = root.join(MANIFEST_NAME);
+    ensure!(!manifest.exists(), ExistsSnafu { path: manifest });
+    fs::create_dir_all(&root).context(WriteSnafu { path: &root })?;
+
+    let client = net::api_client();
+    let resolver = net::resolver(&client, &manifest, &Pack::default());
+    let mut prompter = Prompter::new(io::stdin().lock(), io::stderr(), yes);
+    let open = || File::open(&from).context(ReadSnafu { path: &from });
+    let mut imported = match &kind {
+        #[cfg(feature = "modrinth")]
+        Kind::Mrpack => block_on(ffpack::import::mrpack::import(open()?, &root, &resolver)),
+        Kind::Curseforge => block_on(curseforge::import(open()?, &root, &resolver)),
+        Kind::Packwiz(dir) => packwiz::import(dir),
+        Kind::Instance(instance) => {
+            let files = block_on(instance::scan(instance, &resolver)).context(ImportSnafu)?;
+            // Game directories don't record what they are for, so that is asked
+            let name = init::directory_name(instance);
+            let pack =
+                init::wizard(&mut prompter, Answers::default(), &name).context(PromptSnafu)?;
+            Ok(Imported {
+                pack: Pack {
+                    managed_files: files,
+                    ..pack
+                },
+                unpinned: Vec::new(),
+                unnamed: Vec::new(),
+            })
+        }
+    }
+    .context(ImportSnafu)?;
+
+    resolve_conflicts(&mut prompter, &mut imported.pack).context(PromptSnafu)?;
+    pin(&mut imported)?;
+    for path in &imported.unnamed {
+        eprintln!(
+            "warning: {path} is only known to CurseForge by its project id, which stands in for \
+             its slug until it is filled in"
+        );
+    }
+    save(&manifest, &imported.pack)?;
+    println!(
+        "Imported {} files into {}",
+        imported.pack.managed_files.len(),
+        manifest.display()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use ffpack::types::ManagedFile;
+
+    use super::*;
+
+    // Files identified as the same project are grouped, and only the chosen one is kept
+    #[test]
+    fn resolve_conflicts() {
+        let sodium = |path: &str| ManagedFile {
+            path: path.into(),
+            source: Source::Modrinth {
+                slug: "sodium".into(),
+                version_id: None,
+            },
+            ..ManagedFile::default()
+        };
+        let mut pack = Pack::default();
+        pack.managed_files.insert(sodium("mods/sodium-0.4.jar"));
+        pack.managed_files.insert(sodium("mods/sodium-0.5.jar"));
+        pack.managed_files.insert(ManagedFile::default());
+        let conflicts = conflicts(&pack);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].0, "Modrinth sodium");
+
+        let mut defaults = Prompter::new(&b""[..], Vec::new(), true);
+        super::resolve_conflicts(&mut defaults, &mut pack).unwrap();
+        assert_eq!(pack.managed_files.len(), 3);
+        let mut output = Vec::new();
+        let mut prompter = Prompter::new(&b"3\n2\n"[..], &mut output, false);
+        super::resolve_conflicts(&mut prompter, &mut pack).unwrap();
+        let paths: Vec<_> = pack.managed_files.iter().map(|file| &file.path).collect();
+        assert_eq!(paths, ["mods/MyAwesomeMod.jar", "mods/sodium-0.5.jar"]);
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("  2) mods/sodium-0.5.jar\n"));
+        assert!(output.contains("3 isn't all or a number up to 2"));
+    }
+}
@@ -0,0 +1,220 @@
+//! `ffpack init`: creating the manifest of a new pack
+//!
+//! The wizard asks for the pack's name, author, minecraft version, and loader, offering
+//! defaults that `--yes` takes without asking. Answers can also be given up front as options,
+//! which skips their questions.
+
+use std::{
+    collections::BTreeSet,
+    env, fs,
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+};
+
+use ffpack::{
+    types::{Loader, Metadata, Minecraft, Versions},
+    workspace::{MANIFEST_NAME, PACKS_DIR},
+    Pack,
+};
+use semver::Version;
+use snafu::{ensure, ResultExt};
+
+use crate::{
+    args::{Global, Options},
+    prompt::Prompter,
+    save, CliError, ExistsSnafu, PromptSnafu, UsageSnafu, WriteSnafu,
+};
+
+/// Creates a loader of one kind at a version
+type LoaderFn = fn(Version) -> Loader;
+
+/// The loaders a pack can be made for, by the name they are chosen with
+const LOADERS: [(&str, LoaderFn); 3] = [
+    ("quilt", Loader::new_quilt),
+    ("fabric", Loader::new_fabric),
+    ("forge", Loader::new_forge),
+];
+
+/// Answers given as options, which skip their questions
+#[derive(Debug, Default)]
+pub struct Answers {
+    /// The pack's name
+    name: Option<String>,
+    /// The pack's author
+    author: Option<String>,
+    /// The minecraft version
+    minecraft: Option<String>,
+    /// The loader's name
+    loader: Option<String>,
+    /// The loader's version
+    loader_version: Option<String>,
+}
+
+/// Takes an answer given as an option, or asks for it
+fn answer<T, R: BufRead, W: Write>(
+    prompter: &mut Prompter<R, W>,
+    given: Option<String>,
+    question: &str,
+    default: Option<&str>,
+    parse: impl Fn(&str) -> Result<T, String>,
+) -> io::Result<T> {
+    match given {
+        Some(given) => {
+            parse(&given).map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))
+        }
+        None => prompter.ask(question, default, parse),
+    }
+}
+
+/// Takes an answer as it is
+#[allow(clippy::unnecessary_wraps)]
+pub fn text(answer: &str) -> Result<String, String> {
+    Ok(answer.trim().to_string())
+}
+
+/// Asks the wizard's questions, building a pack without any files
+pub fn wizard<R: BufRead, W: Write>(
+    prompter: &mut Prompter<R, W>,
+    answers: Answers,
+    default_name: &str,
+) -> io::Result<Pack> {
+    let name = answer(
+        prompter,
+        answers.name,
+        "Pack name",
+        Some(default_name),
+        text,
+    )?;
+    let user = env::var("USER").or_else(|_| env::var("USERNAME")).ok();
+    let author = answer(prompter, answers.author, "Author", user.as_deref(), text)?;
+    let default_minecraft = Versions::default().minecraft.to_string();
+    let minecraft = answer(
+        prompter,
+        answers.minecraft,
+        "Minecraft version",
+        Some(&default_minecraft),
+        |answer| Minecraft::new(answer).map_err(|error| error.to_string()),
+    )?;
+    let (kind, loader) = answer(
+        prompter,
+        answers.loader,
+        "Loader (quilt, fabric, or forge)",
+        Some("quilt"),
+        |answer| {
+            LOADERS
+                .into_iter()
+                .find(|(name, _)| answer.eq_ignore_ascii_case(name))
+                .ok_or_else(|| format!("{answer} isn't quilt, fabric, or forge"))
+        },
+    )?;
+    // Only the default loader has a version known to work
+    let default_loader = Loader::default();
+    let default_version = default_loader.version().to_string();
+    let default_version =
+        (kind.eq_ignore_ascii_case(default_loader.name())).then_some(default_version.as_str());
+    let version = answer(
+        prompter,
+        answers.loader_version,
+        "Loader version",
+        default_version,
+        |answer| {
+            Version::parse(answer).map_err(|error| format!("{answer} isn't a version: {error}"))
+        },
+    )?;
+    Ok(Pack {
+        metadata: Metadata::new(name, author, Version::new(0, 1, 0)),
+        versions: Versions {
+            minecraft,
+            loader: loader(version),
+            java: None,
+        },
+        managed_files: BTreeSet::new(),
+    })
+}
+
+/// Runs `ffpack init [DIR]`, writing the manifest into `DIR`, or the named pack's directory
+/// under it
+pub fn run(global: &Global, mut options: Options) -> Result<(), CliError> {
+    let yes = options.flag("--yes", Some('y'));
+    let answers = Answers {
+        name: options.value("--name", None).context(UsageSnafu)?,
+        author: options.value("--author", None).context(UsageSnafu)?,
+        minecraft: options.value("--minecraft", None).context(UsageSnafu)?,
+        loader: options.value("--loader", None).context(UsageSnafu)?,
+        loader_version: options
+            .value("--loader-version", None)
+            .context(UsageSnafu)?,
+    };
+    let dir = options
+        .positional()
+        .map_or_else(|| PathBuf::from("."), PathBuf::from);
+    options.finish().context(UsageSnafu)?;
+    let mut prompter = Prompter::new(io::stdin().lock(), io::stderr(), yes);
+    let (path, _) = create(global, &dir, &mut prompter, answers)?;
+    println!("Created {}", path.display());
+    Ok(())
+}
+
+/// Asks the wizard's questions and writes the new manifest into `dir`, or the named pack's
+/// directory under it, returning its path and the pack
+pub fn create<R: BufRead, W: Write>(
+    global: &Global,
+    dir: &Path,
+    prompter: &mut Prompter<R, W>,
+    answers: Answers,
+) -> Result<(PathBuf, Pack), CliError> {
+    let dir = match &global.pack {
+        Some(pack) => dir.join(PACKS_DIR).join(pack),
+        None => dir.to_path_buf(),
+    };
+    let path = dir.join(MANIFEST_NAME);
+    ensure!(!path.exists(), ExistsSnafu { path });
+
+    let default_name = global.pack.clone().unwrap_or_else(|| directory_name(&dir));
+    let pack = wizard(prompter, answers, &default_name).context(PromptSnafu)?;
+    fs::create_dir_all(&dir).context(WriteSnafu { path: &dir })?;
+    save(&path, &pack)?;
+    Ok((path, pack))
+}
+
+/// Returns the name of a directory, which packs are named after by default
+pub fn directory_name(dir: &Path) -> String {
+    dir.canonicalize()
+        .ok()
+        .as_deref()
+        .unwrap_or(dir)
+        .file_name()
+        .map_or_else(
+            || "My pack".to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        )
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    // Answers given up front skip their questions, and invalid versions are asked for again
+    #[test]
+    fn wizard() {
+        let input = b"\nAlex\nlatest\n1.20.1\nfabric\n\n0.14.21\n";
+        let mut prompter = Prompter::new(&input[..], Vec::new(), false);
+        let pack = super::wizard(&mut prompter, Answers::default(), "skyblock").unwrap();
+        assert_eq!(pack.metadata.name(None), "skyblock");
+        assert_eq!(pack.metadata.authors()[0].name, "Alex");
+        assert_eq!(pack.versions.minecraft, Minecraft::new("1.20.1").unwrap());
+        assert_eq!(
+            pack.versions.loader,
+            Loader::new_fabric(Version::new(0, 14, 21))
+        );
+        assert!(pack.managed_files.is_empty());
+
+        let answers = Answers {
+            author: Some("Sam".into()),
+            ..Answers::default()
+        };
+        let mut prompter = Prompter::new(&b""[..], Vec::new(), true);
+        let pack = super::wizard(&mut prompter, answers, "skyblock").unwrap();
+        assert_eq!(pack.versions, Versions::default());
+    }
+}
@@ -0,0 +1,164 @@
+//! `ffpack install`: installing the pack into an instance directory
+//!
+//! Files come from the lockfile, locking the pack first if it changed, and through the download
+//! cache. Installs are for the client and take every file of that side unless told otherwise,
+//! with `--no-devel` leaving out the files whose `devel` flag is set. Files an earlier install put
+//! in the instance that the pack no longer has are removed, which leaves anything the player
+//! added alone.
+
+use std::path::{Path, PathBuf};
+
+use ffpack::{
+    download::DownloadJob,
+    install::{self, InstanceState, PathRules},
+    lock::Lockfile,
+    types::{ManagedFile, Side},
+    Pack,
+};
+use relative_path::RelativePathBuf;
+use snafu::{OptionExt, ResultExt};
+
+use crate::{
+    args::{self, Global, Options},
+    cache::{self, Fetched},
+    lock, CliError, InstallSnafu, UnlockedSnafu, UsageSnafu,
+};
+
+/// Which of the pack's files an install gets
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selection {
+    /// The side being installed, where `Both` takes every file
+    pub side: Side,
+    /// Whether to leave out the files with `devel` set
+    pub no_devel: bool,
+}
+
+impl Selection {
+    /// Takes the selection from `--side` and `--no-devel`, which default to every file for
+    /// the client
+    pub fn parse(options: &mut Options) -> Result<Self, CliError> {
+        let side = options
+            .value("--side", None)
+            .context(UsageSnafu)?
+            .map(|side| args::side("--side", &side))
+            .transpose()
+            .context(UsageSnafu)?
+            .unwrap_or(Side::Client);
+        let no_devel = options.flag("--no-devel", None);
+        Ok(Self { side, no_devel })
+    }
+
+    /// Returns true if the install gets `file`
+    fn includes(&self, file: &ManagedFile) -> bool {
+        let side = self.side == Side::Both || file.side == Side::Both || file.side == self.side;
+        side && !(self.no_devel && file.devel)
+    }
+}
+
+/// Returns the downloads installing the selected files, as they were locked, with paths not yet
+/// prepared for the platform
+pub fn jobs(
+    pack: &Pack,
+    lock: &Lockfile,
+    selection: &Selection,
+) -> Result<Vec<DownloadJob>, CliError> {
+    pack.managed_files
+        .iter()
+        .filter(|file| selection.includes(file))
+        .map(|file| {
+            let locked = lock
+                .get(&file.path)
+                .context(UnlockedSnafu { path: &file.path })?;
+            Ok(DownloadJob {
+                artifact: locked.artifact.clone(),
+                path: file.install_path(),
+            })
+        })
+        .collect()
+}
+
+/// What installing into an instance did
+#[derive(Debug)]
+pub struct Applied {
+    /// How many files the instance has from the pack
+    pub files: usize,
+    /// How those files were come by
+    pub fetched: Fetched,
+    /// The files earlier installs left behind that were removed
+    pub removed: Vec<RelativePathBuf>,
+}
+
+/// Installs the selected files into `instance`, removing the ones earlier installs left behind,
+/// without printing anything but failed downloads
+pub fn apply(instance: &Path, mut jobs: Vec<DownloadJob>) -> Result<Applied, CliError> {
+    let previous = InstanceState::load(instance).context(InstallSnafu)?;
+    let state = install::prepare(&mut jobs, &PathRules::default()).context(InstallSnafu)?;
+    let fetched = cache::fetch(&jobs, instance)?;
+    let removed = state
+        .remove_stale(&previous, instance)
+        .context(InstallSnafu)?;
+    state.save(instance).context(InstallSnafu)?;
+    Ok(Applied {
+        files: jobs.len(),
+        fetched,
+        removed,
+    })
+}
+
+/// Installs the selected files into `instance`, printing what was installed and removed
+fn install(instance: &Path, jobs: Vec<DownloadJob>) -> Result<(), CliError> {
+    let applied = apply(instance, jobs)?;
+    for path in &applied.removed {
+        println!("Removed {path}");
+    }
+    println!(
+        "Installed {} files into {} ({} downloaded, {} from the cache)",
+        applied.files,
+        instance.display(),
+        applied.fetched.downloaded,
+        applied.fetched.cached
+    );
+    Ok(())
+}
+
+/// Runs `ffpack install <DIR>`, installing the manifest in use into the instance at `DIR`
+pub fn run(global: &Global, mut options: Options) -> Result<(), CliError> {
+    let selection = Selection::parse(&mut options)?;
+    let instance = PathBuf::from(options.required("DIR").context(UsageSnafu)?);
+    options.finish().context(UsageSnafu)?;
+    let (manifest, pack) = global.load()?;
+    let lock = lock::ensure(&manifest, &pack)?;
+    let jobs = jobs(&pack, &lock, &selection)?;
+    install(&instance, jobs)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    // Both-sided files go everywhere, and only `--no-devel` leaves out the development files
+    #[test]
+    fn selection() {
+        let server = ManagedFile {
+            side: Side::Server,
+            devel: false,
+            ..ManagedFile::default()
+        };
+        let both = ManagedFile::default();
+        let client = Selection {
+            side: Side::Client,
+            no_devel: false,
+        };
+        assert!(client.includes(&both) && !client.includes(&server));
+        let everything = Selection {
+            side: Side::Both,
+            no_devel: false,
+        };
+        assert!(everything.includes(&server) && everything.includes(&both));
+        let release = Selection {
+            side: Side::Server,
+            no_devel: true,
+        };
+        assert!(release.includes(&server) && !release.includes(&both));
+    }
+}
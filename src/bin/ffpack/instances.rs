@@ -0,0 +1,318 @@
+//! `ffpack instances`: keeping several installs of the pack, such as a fleet of servers, in sync
+//!
+//! Instances are registered with the pack in [`REGISTRY`] next to the manifest, each by a name
+//! and with the selection it installs, so `ffpack instances sync` can bring them all up to date
+//! at once. Syncing locks the pack once and fills the download cache once with every file any of
+//! them needs, then installs into each instance on its own thread, copying from the shared cache.
+//! One instance failing doesn't stop the others, and a table reports how each one went.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::{Path, PathBuf},
+    thread,
+};
+
+use ffpack::{download::DownloadJob, types::Side};
+use serde::{Deserialize, Serialize};
+use snafu::{ensure, OptionExt, ResultExt};
+
+use crate::{
+    args::{Global, Options, UsageError},
+    cache,
+    install::{self, Applied, Selection},
+    list, lock, CliError, InstanceExistsSnafu, InstancesSnafu, UnknownInstanceSnafu, UnsyncedSnafu,
+    UsageSnafu, WorkingDirectorySnafu, WriteSnafu,
+};
+
+/// Where instances are registered, relative to the directory of the manifest
+pub const REGISTRY: &str = ".ffpack/instances.json";
+
+/// The headings of the table of sync results
+const HEADINGS: [&str; 5] = ["INSTANCE", "FILES", "DOWNLOADED", "REMOVED", "RESULT"];
+
+/// An instance registered with the pack
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Instance {
+    /// Where the instance is
+    pub path: PathBuf,
+    /// The side it is installed for
+    #[serde(default)]
+    pub side: Side,
+    /// Whether it leaves out the files with `devel` set
+    #[serde(default)]
+    pub no_devel: bool,
+}
+
+impl Instance {
+    /// Returns which of the pack's files the instance gets
+    fn selection(&self) -> Selection {
+        Selection {
+            side: self.side.clone(),
+            no_devel: self.no_devel,
+        }
+    }
+}
+
+/// Returns where the instances of the pack whose manifest is at `manifest` are registered
+fn registry_path(manifest: &Path) -> PathBuf {
+    manifest.with_file_name(REGISTRY)
+}
+
+/// Reads the instances registered with the pack, by their names
+fn load(manifest: &Path) -> Result<BTreeMap<String, Instance>, CliError> {
+    let path = registry_path(manifest);
+    let json = match fs::read_to_string(&path) {
+        Ok(json) => json,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(source) => return Err(CliError::Read { path, source }),
+    };
+    serde_json::from_str(&json).context(InstancesSnafu { path })
+}
+
+/// Registers `instances` with the pack, replacing the ones registered before
+fn save(manifest: &Path, instances: &BTreeMap<String, Instance>) -> Result<(), CliError> {
+    let path = registry_path(manifest);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).context(WriteSnafu { path: dir })?;
+    }
+    let json = serde_json::to_string_pretty(instances).expect("Instances serialize to JSON");
+    fs::write(&path, json + "\n").context(WriteSnafu { path })
+}
+
+/// Lays out the outcome of syncing each instance as a table with aligned columns
+fn table(results: &[(String, Result<Applied, CliError>)]) -> String {
+    let rows: Vec<[String; 5]> = results
+        .iter()
+        .map(|(name, result)| match result {
+            Ok(applied) => [
+                name.clone(),
+                applied.files.to_string(),
+                applied.fetched.downloaded.to_string(),
+                applied.removed.len().to_string(),
+                "ok".into(),
+            ],
+            Err(error) => [
+                name.clone(),
+                "-".into(),
+                "-".into(),
+                "-".into(),
+                format!("error: {error}"),
+            ],
+        })
+        .collect();
+    let mut widths = HEADINGS.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let headings = HEADINGS.map(String::from);
+    let mut table = String::new();
+    for row in std::iter::once(&headings).chain(&rows) {
+        let line: Vec<_> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect();
+        table.push_str(line.join("  ").trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+/// Runs `ffpack instances add <DIR>`, registering the instance at `DIR`
+fn add(manifest: &Path, mut options: Options) -> Result<(), CliError> {
+    let selection = Selection::parse(&mut options)?;
+    let name = options.value("--name", None).context(UsageSnafu)?;
+    let dir = PathBuf::from(options.required("DIR").context(UsageSnafu)?);
+    options.finish().context(UsageSnafu)?;
+    let path = std::path::absolute(&dir).context(WorkingDirectorySnafu)?;
+    let name = name.unwrap_or_else(|| {
+        path.file_name().map_or_else(
+            || path.display().to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        )
+    });
+    let mut instances = load(manifest)?;
+    ensure!(!instances.contains_key(&name), InstanceExistsSnafu { name });
+    println!("Registered {name} at {}", path.display());
+    instances.insert(
+        name,
+        Instance {
+            path,
+            side: selection.side,
+            no_devel: selection.no_devel,
+        },
+    );
+    save(manifest, &instances)
+}
+
+/// Runs `ffpack instances remove <NAME>...`, forgetting the instances named without touching
+/// their files
+fn remove(manifest: &Path, mut options: Options) -> Result<(), CliError> {
+    let mut names = vec![options.required("NAME").context(UsageSnafu)?];
+    names.extend(std::iter::from_fn(|| options.positional()));
+    options.finish().context(UsageSnafu)?;
+    let mut instances = load(manifest)?;
+    for name in names {
+        instances
+            .remove(&name)
+            .context(UnknownInstanceSnafu { name: &name })?;
+        println!("Forgot {name}");
+    }
+    save(manifest, &instances)
+}
+
+/// Runs `ffpack instances list`, printing every registered instance
+fn list(manifest: &Path, options: Options) -> Result<(), CliError> {
+    options.finish().context(UsageSnafu)?;
+    let instances = load(manifest)?;
+    if instances.is_empty() {
+        println!("No instances are registered, so register one with ffpack instances add");
+    }
+    let width = instances.keys().map(String::len).max().unwrap_or_default();
+    for (name, instance) in &instances {
+        let devel = if instance.no_devel { ", no devel" } else { "" };
+        println!(
+            "{name:width$}  {} ({}{devel})",
+            instance.path.display(),
+            list::side_name(&instance.side)
+        );
+    }
+    Ok(())
+}
+
+/// Runs `ffpack instances sync [NAME]...`, installing the pack into the instances named, or
+/// every one with `--all`
+fn sync(manifest: &Path, mut options: Options) -> Result<(), CliError> {
+    let all = options.flag("--all", None);
+    let names: Vec<_> = std::iter::from_fn(|| options.positional()).collect();
+    options.finish().context(UsageSnafu)?;
+    let mut instances = load(manifest)?;
+    if let (true, Some(name)) = (all, names.first()) {
+        return Err(UsageError::Unexpected { arg: name.clone() }).context(UsageSnafu);
+    }
+    if !all {
+        if names.is_empty() {
+            return Err(UsageError::MissingArgument {
+                name: "NAME, or --all".into(),
+            })
+            .context(UsageSnafu);
+        }
+        for name in &names {
+            ensure!(instances.contains_key(name), UnknownInstanceSnafu { name });
+        }
+        instances.retain(|name, _| names.contains(name));
+    }
+    if instances.is_empty() {
+        println!("No instances are registered, so register one with ffpack instances add");
+        return Ok(());
+    }
+
+    let pack = crate::load(manifest)?;
+    let lock = lock::ensure(manifest, &pack)?;
+    let mut jobs = Vec::new();
+    for (name, instance) in &instances {
+        jobs.push((
+            name,
+            instance,
+            install::jobs(&pack, &lock, &instance.selection())?,
+        ));
+    }
+    // Instances mostly share their files, which are only fetched into the cache once
+    let mut seen = BTreeSet::new();
+    let shared: Vec<DownloadJob> = jobs
+        .iter()
+        .flat_map(|(_, _, jobs)| jobs)
+        .filter(|job| seen.insert(job.path.clone()))
+        .cloned()
+        .collect();
+    let filled = cache::fill(&shared)?;
+    println!(
+        "Cached the files of {} instances ({} downloaded, {} already cached)",
+        instances.len(),
+        filled.downloaded,
+        filled.cached
+    );
+
+    let results: Vec<_> = thread::scope(|scope| {
+        let handles: Vec<_> = jobs
+            .into_iter()
+            .map(|(name, instance, jobs)| {
+                let handle = scope.spawn(move || install::apply(&instance.path, jobs));
+                (name.clone(), handle)
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|(name, handle)| {
+                let result = handle.join().expect("Syncing an instance doesn't panic");
+                (name, result)
+            })
+            .collect()
+    });
+    print!("{}", table(&results));
+    let failed = results.iter().filter(|(_, result)| result.is_err()).count();
+    ensure!(failed == 0, UnsyncedSnafu { count: failed });
+    Ok(())
+}
+
+/// Runs `ffpack instances <ACTION>`, managing the instances registered with the pack and
+/// syncing them
+pub fn run(global: &Global, mut options: Options) -> Result<(), CliError> {
+    let action = options.required("ACTION").context(UsageSnafu)?;
+    let manifest = global.manifest()?;
+    match action.as_str() {
+        "add" => add(&manifest, options),
+        "list" => list(&manifest, options),
+        "remove" => remove(&manifest, options),
+        "sync" => sync(&manifest, options),
+        _ => Err(UsageError::InvalidValue {
+            option: "ACTION".into(),
+            value: action,
+            expected: "add, list, remove, or sync".into(),
+        })
+        .context(UsageSnafu),
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use ffpack::install::InstallError;
+
+    use super::*;
+    use crate::cache::Fetched;
+
+    // Each instance gets a row, with failures reported in place of their counts
+    #[test]
+    fn table() {
+        let results = [
+            (
+                "survival".to_string(),
+                Ok(Applied {
+                    files: 120,
+                    fetched: Fetched {
+                        downloaded: 0,
+                        cached: 120,
+                    },
+                    removed: vec!["mods/old.jar".into()],
+                }),
+            ),
+            (
+                "creative".to_string(),
+                Err(CliError::Install {
+                    source: InstallError::Io {
+                        path: "creative".into(),
+                        source: io::Error::new(io::ErrorKind::PermissionDenied, "denied"),
+                    },
+                }),
+            ),
+        ];
+        let table = super::table(&results);
+        let lines: Vec<_> = table.lines().collect();
+        assert_eq!(lines[0], "INSTANCE  FILES  DOWNLOADED  REMOVED  RESULT");
+        assert_eq!(lines[1], "survival  120    0           1        ok");
+        assert!(lines[2].starts_with("creative  -      -           -        error: "));
+    }
+}
@@ -0,0 +1,185 @@
+//! Keeping API tokens in the platform's keychain, out of the configuration file
+//!
+//! Tokens are stored under the service `ffpack`, with the account naming what the token is for,
+//! through the keychain's own command line tool: `secret-tool` for the Secret Service on Linux
+//! and the BSDs, and `security` for the macOS keychain. Windows has no tool that reads
+//! credentials back, so it isn't supported. Tokens the configuration doesn't otherwise set are
+//! looked up here when it is read, so every command finds them without asking.
+
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+use snafu::{ensure, ResultExt, Snafu};
+
+/// The service tokens are stored under
+const SERVICE: &str = "ffpack";
+
+/// What tokens are kept for, by their account names
+pub const ACCOUNTS: [&str; 3] = ["curseforge", "github", "modrinth"];
+
+/// Returns the program the platform's keychain is reached through
+fn program() -> Result<&'static str, KeyringError> {
+    if cfg!(target_os = "macos") {
+        Ok("security")
+    } else if cfg!(windows) {
+        UnsupportedSnafu.fail()
+    } else {
+        Ok("secret-tool")
+    }
+}
+
+/// Runs `program` with `args`, writing `input` to it, and checks that it succeeded
+fn run(program: &'static str, args: &[&str], input: Option<&str>) -> Result<Output, KeyringError> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context(RunSnafu { program })?;
+    if let (Some(mut stdin), Some(input)) = (child.stdin.take(), input) {
+        stdin
+            .write_all(input.as_bytes())
+            .context(RunSnafu { program })?;
+    }
+    let output = child.wait_with_output().context(RunSnafu { program })?;
+    ensure!(
+        output.status.success(),
+        FailedSnafu {
+            program,
+            message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        }
+    );
+    Ok(output)
+}
+
+/// Stores the token for `account`, replacing any already stored
+///
+/// # Errors
+///
+/// Returns an error if the keychain couldn't be reached, or refused the token
+pub fn store(account: &str, token: &str) -> Result<(), KeyringError> {
+    let program = program()?;
+    if program == "security" {
+        // With `-w` last and no value, `security` asks for the password twice, which keeps it out
+        // of the arguments other users can see
+        run(
+            program,
+            &[
+                "add-generic-password",
+                "-U",
+                "-s",
+                SERVICE,
+                "-a",
+                account,
+                "-w",
+            ],
+            Some(&format!("{token}\n{token}\n")),
+        )?;
+    } else {
+        let label = format!("ffpack {account} token");
+        run(
+            program,
+            &[
+                "store", "--label", &label, "service", SERVICE, "account", account,
+            ],
+            Some(token),
+        )?;
+    }
+    Ok(())
+}
+
+/// Removes the token for `account`
+///
+/// # Errors
+///
+/// Returns an error if the keychain couldn't be reached, or had no token to remove
+pub fn delete(account: &str) -> Result<(), KeyringError> {
+    match program()? {
+        "security" => run(
+            "security",
+            &["delete-generic-password", "-s", SERVICE, "-a", account],
+            None,
+        ),
+        program => run(
+            program,
+            &["clear", "service", SERVICE, "account", account],
+            None,
+        ),
+    }?;
+    Ok(())
+}
+
+/// Returns the token stored for `account`, or `None` if there is none or the keychain can't be
+/// reached
+pub fn fetch(account: &str) -> Option<String> {
+    let output = match program().ok()? {
+        "security" => run(
+            "security",
+            &["find-generic-password", "-s", SERVICE, "-a", account, "-w"],
+            None,
+        ),
+        program => run(
+            program,
+            &["lookup", "service", SERVICE, "account", account],
+            None,
+        ),
+    };
+    let output = match output {
+        Ok(output) => output,
+        Err(error) => {
+            tracing::debug!("No {account} token in the keychain: {error}");
+            return None;
+        }
+    };
+    let token = String::from_utf8(output.stdout).ok()?;
+    let token = token.trim_end_matches(['\r', '\n']);
+    (!token.is_empty()).then(|| token.to_string())
+}
+
+/// Error that occurs while using the platform's keychain
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum KeyringError {
+    /// The platform has no keychain ffpack can reach
+    #[snafu(display("ffpack can't use the keychain on this platform"))]
+    Unsupported,
+    /// The keychain's tool couldn't be run
+    #[snafu(display("Couldn't run {}: {}", program, source))]
+    Run {
+        /// The program
+        program: &'static str,
+        /// The underlying error
+        source: std::io::Error,
+    },
+    /// The keychain's tool reported a failure
+    #[snafu(display("{} failed: {}", program, message))]
+    Failed {
+        /// The program
+        program: &'static str,
+        /// What it printed
+        message: String,
+    },
+}
+
+impl KeyringError {
+    /// Suggests how the user might fix the problem, if there is a likely fix
+    pub fn suggestion(&self) -> Option<String> {
+        match self {
+            KeyringError::Unsupported => {
+                Some("Set the token in the configuration file or its variable instead".into())
+            }
+            KeyringError::Run { program, .. } => Some(match *program {
+                "secret-tool" => "Install secret-tool, which comes with libsecret".into(),
+                program => format!("Check that {program} is installed"),
+            }),
+            KeyringError::Failed { .. } => None,
+        }
+    }
+}
@@ -0,0 +1,216 @@
+//! `ffpack list`: printing the pack's files
+//!
+//! Files can be narrowed down by side, by the kind of source they come from, and to the ones in
+//! the development profile. They print as an aligned table, or with `--json` as the manifest's
+//! own entries, for scripts to read. With `--verbose`, the table has each file's notes under its
+//! row.
+
+use ffpack::types::{ManagedFile, Side, Source};
+use snafu::ResultExt;
+
+use crate::{
+    args::{self, Global, Options},
+    CliError, UsageSnafu,
+};
+
+/// The headings of the table's columns
+const HEADINGS: [&str; 4] = ["PATH", "NAME", "SIDE", "SOURCE"];
+
+/// Which files are listed
+#[derive(Debug, Default)]
+struct Filter {
+    /// Only files installed on this side, counting files for both sides
+    side: Option<Side>,
+    /// Only files from this kind of source
+    source: Option<String>,
+    /// Only files in the development profile
+    devel: bool,
+}
+
+impl Filter {
+    /// Returns true if the file is listed
+    fn matches(&self, file: &ManagedFile) -> bool {
+        let side = match &self.side {
+            Some(Side::Both) => file.side == Side::Both,
+            Some(side) => file.side == *side || file.side == Side::Both,
+            None => true,
+        };
+        let source = self
+            .source
+            .as_deref()
+            .is_none_or(|kind| normalize(kind) == normalize(&source_kind(&file.source)));
+        side && source && (file.devel || !self.devel)
+    }
+}
+
+/// Lowercases a source kind and drops its separators, so `slug-releases` names `SlugReleases`
+fn normalize(kind: &str) -> String {
+    kind.chars()
+        .filter(|c| !matches!(c, '-' | '_'))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Returns the kind of a source, as the manifest names it
+pub fn source_kind(source: &Source) -> String {
+    match serde_json::to_value(source) {
+        Ok(serde_json::Value::Object(object)) => object.keys().next().cloned().unwrap_or_default(),
+        _ => String::new(),
+    }
+}
+
+/// Returns the slug of a source that has one
+pub fn slug(source: &Source) -> Option<&str> {
+    match source {
+        Source::Modrinth { slug, .. }
+        | Source::Curseforge { slug, .. }
+        | Source::Slug { slug, .. }
+        | Source::SlugReleases { slug, .. } => Some(slug),
+        _ => None,
+    }
+}
+
+/// Returns the name of a side, as options take it
+pub fn side_name(side: &Side) -> &'static str {
+    match side {
+        Side::Client => "client",
+        Side::Server => "server",
+        Side::Both => "both",
+    }
+}
+
+/// Lays the files out as a table with aligned columns, with their notes indented under their rows
+/// if `verbose` is set
+fn table<'a>(files: impl IntoIterator<Item = &'a ManagedFile>, verbose: bool) -> String {
+    let files: Vec<_> = files.into_iter().collect();
+    let rows: Vec<[String; 4]> = files
+        .iter()
+        .map(|file| {
+            let mut path = file.path.to_string();
+            if !file.enabled {
+                path.push_str(" (disabled)");
+            }
+            let kind = source_kind(&file.source);
+            let source = match slug(&file.source) {
+                Some(slug) => format!("{kind} {slug}"),
+                None => kind,
+            };
+            [
+                path,
+                file.name.clone().unwrap_or_default(),
+                side_name(&file.side).to_string(),
+                source,
+            ]
+        })
+        .collect();
+    let mut widths = HEADINGS.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let headings = HEADINGS.map(String::from);
+    let notes = files
+        .iter()
+        .map(|file| file.notes.as_deref().filter(|_| verbose));
+    let mut table = String::new();
+    for (row, notes) in std::iter::once((&headings, None)).chain(rows.iter().zip(notes)) {
+        let line: Vec<_> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect();
+        table.push_str(line.join("  ").trim_end());
+        table.push('\n');
+        for note in notes.into_iter().flat_map(str::lines) {
+            table.push_str(format!("    {note}").trim_end());
+            table.push('\n');
+        }
+    }
+    table
+}
+
+/// Runs `ffpack list`, printing the files of the manifest in use
+pub fn run(global: &Global, mut options: Options) -> Result<(), CliError> {
+    let json = options.flag("--json", None);
+    let verbose = options.flag("--verbose", None);
+    let filter = Filter {
+        side: options
+            .value("--side", None)
+            .context(UsageSnafu)?
+            .map(|side| args::side("--side", &side))
+            .transpose()
+            .context(UsageSnafu)?,
+        source: options.value("--source", None).context(UsageSnafu)?,
+        devel: options.flag("--devel", None),
+    };
+    options.finish().context(UsageSnafu)?;
+    let (_, pack) = global.load()?;
+    let files: Vec<_> = pack
+        .managed_files
+        .iter()
+        .filter(|file| filter.matches(file))
+        .collect();
+    if json {
+        let json = serde_json::to_string_pretty(&files).expect("Files serialize to JSON");
+        println!("{json}");
+    } else {
+        print!("{}", table(files, verbose));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    // Files for both sides count for either, source kinds are matched loosely, and notes are only
+    // listed when asked for
+    #[test]
+    fn filter() {
+        let both = ManagedFile::default();
+        let client = ManagedFile {
+            side: Side::Client,
+            source: Source::SlugReleases {
+                slug: "github:owner/repo".into(),
+                artifact_regex: r"\.jar$".into(),
+                release_regex: None,
+            },
+            devel: false,
+            ..ManagedFile::default()
+        };
+        let filter = Filter {
+            side: Some(Side::Server),
+            ..Filter::default()
+        };
+        assert!(filter.matches(&both) && !filter.matches(&client));
+        let filter = Filter {
+            source: Some("slug-releases".into()),
+            ..Filter::default()
+        };
+        assert!(filter.matches(&client) && !filter.matches(&both));
+        let filter = Filter {
+            devel: true,
+            ..Filter::default()
+        };
+        assert!(filter.matches(&both) && !filter.matches(&client));
+
+        let table = table([&client], true);
+        let lines: Vec<_> = table.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].find("NAME"), Some("mods/MyAwesomeMod.jar  ".len()));
+        assert!(lines[1].ends_with("client  SlugReleases github:owner/repo"));
+
+        let noted = ManagedFile {
+            notes: Some("Waiting on the 1.20 port\nAsk upstream".into()),
+            ..client
+        };
+        assert_eq!(super::table([&noted], false).lines().count(), 2);
+        let table = super::table([&noted], true);
+        let lines: Vec<_> = table.lines().collect();
+        assert_eq!(
+            lines[2..],
+            ["    Waiting on the 1.20 port", "    Ask upstream"]
+        );
+    }
+}
@@ -0,0 +1,200 @@
+//! `ffpack lock`: keeping the lockfile next to the manifest up to date
+//!
+//! Commands that need concrete files go through the lockfile, so that every install of a pack
+//! gets the same ones. Locking keeps what was locked for files whose sources didn't change, and
+//! `ffpack update` resolves floating sources again. Both print what changed. Other commands lock
+//! the pack themselves when the lockfile is missing or out of date.
+
+use std::path::Path;
+
+use ffpack::{
+    lock::{LockedFile, Lockfile},
+    types::ManagedFile,
+    Pack,
+};
+use relative_path::RelativePathBuf;
+use snafu::ResultExt;
+
+use crate::{
+    args::{Global, Options},
+    net::{self, block_on},
+    CliError, LockSnafu, UsageSnafu,
+};
+
+/// Returns the pack's lockfile, locking the pack first if the lockfile is missing or out of date
+pub fn ensure(manifest: &Path, pack: &Pack) -> Result<Lockfile, CliError> {
+    let path = Lockfile::path_for(manifest);
+    let previous = match Lockfile::load(&path).context(LockSnafu)? {
+        Some(previous) if previous.verify_against(pack).is_empty() => return Ok(previous),
+        previous => previous.unwrap_or_default(),
+    };
+    let client = net::api_client();
+    let resolver = net::resolver(&client, manifest, pack);
+    let lock = block_on(Lockfile::lock(&resolver, pack, &previous)).context(LockSnafu)?;
+    lock.save(&path).context(LockSnafu)?;
+    eprintln!("Locked {} files into {}", lock.files.len(), path.display());
+    Ok(lock)
+}
+
+/// Names the version a file was locked at
+pub fn label(locked: &LockedFile) -> String {
+    locked
+        .artifact
+        .version_label()
+        .unwrap_or_else(|| locked.artifact.filename.clone())
+}
+
+/// A file that changed from one lockfile to the next
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FileChange<'a> {
+    /// The file's path
+    pub path: &'a RelativePathBuf,
+    /// What it was locked to before, unless it was added
+    pub old: Option<&'a LockedFile>,
+    /// What it is locked to now, unless it was removed
+    pub new: Option<&'a LockedFile>,
+}
+
+/// Returns the files added, locked to another artifact, or removed from `previous` to `next`,
+/// removed files last
+pub fn file_changes<'a>(previous: &'a Lockfile, next: &'a Lockfile) -> Vec<FileChange<'a>> {
+    let mut changes: Vec<_> = next
+        .files
+        .iter()
+        .filter(|(path, locked)| {
+            previous
+                .files
+                .get(*path)
+                .is_none_or(|old| old.artifact != locked.artifact)
+        })
+        .map(|(path, locked)| FileChange {
+            path,
+            old: previous.files.get(path),
+            new: Some(locked),
+        })
+        .collect();
+    changes.extend(
+        previous
+            .files
+            .iter()
+            .filter(|(path, _)| !next.files.contains_key(*path))
+            .map(|(path, old)| FileChange {
+                path,
+                old: Some(old),
+                new: None,
+            }),
+    );
+    changes
+}
+
+/// Describes what changed from `previous` to `next`, a line for each file added, removed, or
+/// locked to another artifact
+fn changes(previous: &Lockfile, next: &Lockfile) -> Vec<String> {
+    let mut changes = Vec::new();
+    if previous.versions != next.versions && !previous.files.is_empty() {
+        changes.push(format!(
+            "Versions: minecraft {}, {} {}",
+            next.versions.minecraft,
+            next.versions.loader.name(),
+            next.versions.loader.version()
+        ));
+    }
+    for change in file_changes(previous, next) {
+        let path = change.path;
+        changes.push(match (change.old.map(label), change.new.map(label)) {
+            (None, Some(new)) => format!("Added {path} {new}"),
+            (Some(old), Some(new)) if old == new => format!("Changed {path} {new}"),
+            (Some(old), Some(new)) => format!("Updated {path} {old} -> {new}"),
+            _ => format!("Removed {path}"),
+        });
+    }
+    changes
+}
+
+/// Locks the pack, resolving again the files `update` selects, and prints what changed
+///
+/// Returns the lockfile from before and the one saved.
+pub fn relock(
+    manifest: &Path,
+    pack: &Pack,
+    update: impl Fn(&ManagedFile) -> bool,
+) -> Result<(Lockfile, Lockfile), CliError> {
+    let path = Lockfile::path_for(manifest);
+    let previous = Lockfile::load(&path)
+        .context(LockSnafu)?
+        .unwrap_or_default();
+    let client = net::api_client();
+    let resolver = net::resolver(&client, manifest, pack);
+    let next =
+        block_on(Lockfile::update_only(&resolver, pack, &previous, update)).context(LockSnafu)?;
+    let changes = changes(&previous, &next);
+    if changes.is_empty() {
+        println!("{} is up to date", path.display());
+    }
+    for change in changes {
+        println!("{change}");
+    }
+    next.save(&path).context(LockSnafu)?;
+    Ok((previous, next))
+}
+
+/// Runs `ffpack lock`, locking what isn't locked yet and keeping everything else
+pub fn run(global: &Global, options: Options) -> Result<(), CliError> {
+    options.finish().context(UsageSnafu)?;
+    let (manifest, pack) = global.load()?;
+    relock(&manifest, &pack, |_| false)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use ffpack::{
+        resolve::{ProjectDetails, ResolvedArtifact, UpstreamIds},
+        types::{Hashes, Source},
+    };
+    use url::Url;
+
+    use super::*;
+
+    /// Locks a file at a version
+    fn locked(version: &str) -> LockedFile {
+        LockedFile {
+            source: Source::default(),
+            artifact: ResolvedArtifact {
+                download_url: Url::parse("https://example.org/mod.jar").unwrap(),
+                filename: "mod.jar".into(),
+                size: None,
+                hashes: Hashes::default(),
+                mirrors: Vec::new(),
+                extract: None,
+                ids: UpstreamIds {
+                    version_id: Some(version.into()),
+                    ..UpstreamIds::default()
+                },
+                details: ProjectDetails::default(),
+            },
+        }
+    }
+
+    // Files added, removed, and updated are each described, and the untouched ones aren't
+    #[test]
+    fn changes() {
+        let mut previous = Lockfile::default();
+        let mut next = Lockfile::default();
+        let path = RelativePathBuf::from;
+        previous.files.insert(path("mods/a.jar"), locked("1.0"));
+        previous.files.insert(path("mods/b.jar"), locked("1.0"));
+        previous.files.insert(path("mods/c.jar"), locked("1.0"));
+        next.files.insert(path("mods/a.jar"), locked("1.0"));
+        next.files.insert(path("mods/b.jar"), locked("1.1"));
+        next.files.insert(path("mods/d.jar"), locked("2.0"));
+        assert_eq!(
+            super::changes(&previous, &next),
+            [
+                "Updated mods/b.jar 1.0 -> 1.1",
+                "Added mods/d.jar 2.0",
+                "Removed mods/c.jar"
+            ]
+        );
+    }
+}
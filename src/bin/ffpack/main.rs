@@ -0,0 +1,509 @@
+//! The ffpack command line application
+//!
+//! Every command operates on a manifest found the way [`ffpack::workspace::locate`] describes,
+//! which `--manifest-path` and `-p` override.
+
+mod add;
+mod args;
+mod cache;
+mod ci;
+mod collection;
+mod commands;
+mod completions;
+mod config;
+mod diff;
+mod doctor;
+mod enable;
+mod export;
+mod import;
+mod init;
+mod install;
+mod instances;
+#[cfg(feature = "keyring")]
+mod keyring;
+mod list;
+mod lock;
+mod man;
+mod net;
+mod outdated;
+mod prompt;
+mod quickstart;
+mod rehash;
+mod remove;
+mod scratch;
+mod search;
+mod serve;
+mod update;
+mod verify;
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use ffpack::{
+    export::ExportError,
+    import::ImportError,
+    install::InstallError,
+    lock::LockError,
+    rehash::RehashError,
+    resolve::ResolveError,
+    workspace::{self, LocateError},
+    Pack, PackError,
+};
+use relative_path::RelativePathBuf;
+use snafu::{ResultExt, Snafu};
+
+use crate::{
+    args::{Global, Options, UsageError},
+    config::ConfigError,
+};
+
+pub fn main() -> ExitCode {
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .init();
+    match run(Options::new(env::args().skip(1))) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error @ CliError::Usage { .. }) => {
+            eprintln!("error: {error}\n\n{}", commands::usage());
+            ExitCode::from(2)
+        }
+        Err(error @ CliError::Updated { .. }) => {
+            eprintln!("{error}");
+            ExitCode::from(update::UPDATED_STATUS)
+        }
+        Err(error) => {
+            eprintln!("error: {error}");
+            let suggestion = error.suggestion();
+            if let Some(suggestion) = &suggestion {
+                eprintln!("help: {suggestion}");
+            }
+            let message = match suggestion {
+                Some(suggestion) => format!("{error}\n{suggestion}"),
+                None => error.to_string(),
+            };
+            ci::annotate(ci::Level::Error, None, "ffpack", &message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Runs the command line
+fn run(mut options: Options) -> Result<(), CliError> {
+    let global = Global::parse(&mut options).context(UsageSnafu)?;
+    let Some(command) = options.positional() else {
+        print!("{}", commands::usage());
+        return Ok(());
+    };
+    if global.help {
+        print!("{}", commands::usage());
+        return Ok(());
+    }
+    if global.allow_build {
+        net::allow_builds();
+    }
+    if global.ci || ci::detected() {
+        ci::enable();
+    }
+    let config = config::load().context(ConfigSnafu)?;
+    for warning in &config.warnings {
+        eprintln!("warning: {warning}");
+    }
+    match command.as_str() {
+        "add" => add::run(&global, options)?,
+        "collection" => collection::run(&global, options)?,
+        "completions" => completions::run(options)?,
+        "config" => config::run(&global, options)?,
+        "diff" => diff::run(&global, options)?,
+        "disable" => enable::run(&global, options, false)?,
+        "doctor" => doctor::run(&global, options)?,
+        "enable" => enable::run(&global, options, true)?,
+        "export" => export::run(&global, options)?,
+        "import" => import::run(&global, options)?,
+        "init" => init::run(&global, options)?,
+        "install" => install::run(&global, options)?,
+        "instances" => instances::run(&global, options)?,
+        "list" => list::run(&global, options)?,
+        "lock" => lock::run(&global, options)?,
+        "man" => man::run(options)?,
+        "update" => update::run(&global, options)?,
+        "outdated" => outdated::run(&global, options)?,
+        "quickstart" => quickstart::run(&global, options)?,
+        "rehash" => rehash::run(&global, options)?,
+        "remove" => remove::run(&global, options)?,
+        "search" => search::run(&global, options)?,
+        "serve" => serve::run(&global, options)?,
+        "locate" => {
+            options.finish().context(UsageSnafu)?;
+            println!("{}", global.manifest()?.display());
+        }
+        "show" => {
+            options.finish().context(UsageSnafu)?;
+            let (_, pack) = global.load()?;
+            println!("{}", to_json(&pack));
+        }
+        "verify" => verify::run(&global, options)?,
+        "template" => {
+            options.finish().context(UsageSnafu)?;
+            println!("{}", to_json(&Pack::default()));
+        }
+        _ => return Err(UsageError::UnknownCommand { command }).context(UsageSnafu),
+    }
+    Ok(())
+}
+
+/// Renders a pack as a manifest
+fn to_json(pack: &Pack) -> String {
+    serde_json::to_string_pretty(pack).expect("Packs serialize to JSON")
+}
+
+impl Global {
+    /// Finds the manifest in use
+    fn manifest(&self) -> Result<PathBuf, CliError> {
+        let cwd = env::current_dir().context(WorkingDirectorySnafu)?;
+        workspace::locate(&cwd, self.manifest_path.as_deref(), self.pack.as_deref())
+            .context(LocateSnafu)
+    }
+
+    /// Finds and loads the manifest in use, returning its path and the pack
+    fn load(&self) -> Result<(PathBuf, Pack), CliError> {
+        let path = self.manifest()?;
+        let pack = load(&path)?;
+        Ok((path, pack))
+    }
+}
+
+/// Writes `pack` to the manifest at `path`
+fn save(path: &Path, pack: &Pack) -> Result<(), CliError> {
+    fs::write(path, to_json(pack) + "\n").context(WriteSnafu { path })
+}
+
+/// Loads the manifest at `path`
+fn load(path: &Path) -> Result<Pack, CliError> {
+    let json = fs::read_to_string(path).context(ReadSnafu { path })?;
+    Pack::from_json(&json).context(LoadSnafu { path })
+}
+
+impl CliError {
+    /// Suggests how the user might fix the problem, if there is a likely fix
+    fn suggestion(&self) -> Option<String> {
+        match self {
+            CliError::Config { source } => source.suggestion(),
+            CliError::Locate { source } => source.suggestion(),
+            CliError::Load { source, .. } => source.suggestion(),
+            CliError::Exists { .. } => {
+                Some("Edit the existing manifest, or initialize another directory".into())
+            }
+            CliError::Unrecognized { .. } => Some(
+                "Give a Modrinth or CurseForge page or slug, a GitHub repository, or a url".into(),
+            ),
+            CliError::Duplicate { .. } => {
+                Some("Remove the existing file first, or choose another path with --path".into())
+            }
+            CliError::NotFound { .. } => Some("Run ffpack list to see the pack's files".into()),
+            CliError::Ambiguous { .. } => Some("Name the file by its path instead".into()),
+            CliError::Resolve { source, .. } | CliError::Search { source, .. } => {
+                source.suggestion()
+            }
+            CliError::Lock { source } => source.suggestion(),
+            CliError::Unlocked { .. } => Some("Lock the pack again".into()),
+            CliError::Install { source } => source.suggestion(),
+            CliError::Download { .. } => None,
+            CliError::Export { source } => source.suggestion(),
+            CliError::Recorded { .. } => Some(
+                "Raise the pack's version before publishing it again, or replace the record \
+                 with --force"
+                    .into(),
+            ),
+            CliError::UnknownFormat { .. } => Some(
+                "Give an .mrpack, a CurseForge modpack zip, a packwiz project, or a game \
+                 directory with a mods folder"
+                    .into(),
+            ),
+            CliError::Import { source } => source.suggestion(),
+            CliError::Unchecked { .. } | CliError::Unhealthy { .. } => None,
+            CliError::Outdated { .. } => Some("Run ffpack update to take the updates".into()),
+            CliError::Updated { .. } => None,
+            CliError::Problems { .. } => Some(
+                "Run ffpack verify --fix to restore the pack's files, and remove files that \
+                 aren't part of it"
+                    .into(),
+            ),
+            CliError::Pin { source, .. } | CliError::Rehash { source, .. } => source.suggestion(),
+            CliError::Serve { .. } => Some("Choose another address with --bind".into()),
+            CliError::Instances { .. } => {
+                Some("Fix the file, or remove it and register the instances again".into())
+            }
+            CliError::InstanceExists { .. } => {
+                Some("Choose another name with --name, or remove the instance first".into())
+            }
+            CliError::UnknownInstance { .. } => {
+                Some("Run ffpack instances list to see the registered instances".into())
+            }
+            CliError::Unsynced { .. } => None,
+            #[cfg(feature = "keyring")]
+            CliError::Keyring { source } => source.suggestion(),
+            CliError::Usage { .. }
+            | CliError::WorkingDirectory { .. }
+            | CliError::Read { .. }
+            | CliError::Write { .. }
+            | CliError::Prompt { .. } => None,
+        }
+    }
+}
+
+/// Error that ends a command
+#[derive(Debug, Snafu)]
+enum CliError {
+    /// The command line couldn't be understood
+    #[snafu(display("{}", source))]
+    Usage {
+        /// The underlying error
+        source: UsageError,
+    },
+    /// The working directory couldn't be determined
+    #[snafu(display("Could not determine the working directory: {}", source))]
+    WorkingDirectory {
+        /// The underlying error
+        source: std::io::Error,
+    },
+    /// The configuration couldn't be read
+    #[snafu(display("{}", source))]
+    Config {
+        /// The underlying error
+        source: ConfigError,
+    },
+    /// No manifest could be found
+    #[snafu(display("{}", source))]
+    Locate {
+        /// The underlying error
+        source: LocateError,
+    },
+    /// The manifest couldn't be read
+    #[snafu(display("Failed to read {}: {}", path.display(), source))]
+    Read {
+        /// The manifest's path
+        path: PathBuf,
+        /// The underlying error
+        source: std::io::Error,
+    },
+    /// A file couldn't be written
+    #[snafu(display("Failed to write {}: {}", path.display(), source))]
+    Write {
+        /// The file's path
+        path: PathBuf,
+        /// The underlying error
+        source: std::io::Error,
+    },
+    /// A manifest is already where a new one would go
+    #[snafu(display("{} already exists", path.display()))]
+    Exists {
+        /// The manifest's path
+        path: PathBuf,
+    },
+    /// A question couldn't be asked or answered
+    #[snafu(display("{}", source))]
+    Prompt {
+        /// The underlying error
+        source: std::io::Error,
+    },
+    /// The manifest couldn't be loaded
+    #[snafu(display("Failed to load {}: {}", path.display(), source))]
+    Load {
+        /// The manifest's path
+        path: PathBuf,
+        /// The underlying error
+        source: PackError,
+    },
+    /// A source given on the command line wasn't recognized
+    #[snafu(display("Could not tell what {} is", input))]
+    Unrecognized {
+        /// The source as given
+        input: String,
+    },
+    /// The pack already has a file at a path
+    #[snafu(display("The pack already has a file at {}", path))]
+    Duplicate {
+        /// The path
+        path: RelativePathBuf,
+    },
+    /// A source couldn't be resolved
+    #[snafu(display("Failed to resolve {}: {}", input, source))]
+    Resolve {
+        /// The source as given
+        input: String,
+        /// The underlying error, boxed since it is much larger than the others
+        #[snafu(source(from(ResolveError, Box::new)))]
+        source: Box<ResolveError>,
+    },
+    /// A platform couldn't be searched
+    #[snafu(display("Failed to search {}: {}", platform, source))]
+    Search {
+        /// The platform being searched
+        platform: &'static str,
+        /// The underlying error, boxed since it is much larger than the others
+        #[snafu(source(from(ResolveError, Box::new)))]
+        source: Box<ResolveError>,
+    },
+    /// A url couldn't be downloaded for its digests
+    #[snafu(display("Failed to pin {}: {}", input, source))]
+    Pin {
+        /// The url as given
+        input: String,
+        /// The underlying error, boxed since it is much larger than the others
+        #[snafu(source(from(RehashError, Box::new)))]
+        source: Box<RehashError>,
+    },
+    /// A file's digest couldn't be computed under another algorithm
+    #[snafu(display("Failed to rehash {}: {}", path, source))]
+    Rehash {
+        /// The file's path
+        path: RelativePathBuf,
+        /// The underlying error, boxed since it is much larger than the others
+        #[snafu(source(from(RehashError, Box::new)))]
+        source: Box<RehashError>,
+    },
+    /// No file goes by what was given
+    #[snafu(display("The pack has no file {}", given))]
+    NotFound {
+        /// The path, name, or slug given
+        given: String,
+    },
+    /// More than one file goes by what was given
+    #[snafu(display("{} could be any of {}", given, paths.join(", ")))]
+    Ambiguous {
+        /// The name or slug given
+        given: String,
+        /// The paths of the files it matched
+        paths: Vec<String>,
+    },
+    /// The pack couldn't be locked, or its lockfile couldn't be read or written
+    #[snafu(display("{}", source))]
+    Lock {
+        /// The underlying error
+        source: LockError,
+    },
+    /// A file the pack has isn't in its lockfile
+    #[snafu(display("{} isn't locked", path))]
+    Unlocked {
+        /// The file's path
+        path: RelativePathBuf,
+    },
+    /// The instance couldn't be prepared or its state recorded
+    #[snafu(display("{}", source))]
+    Install {
+        /// The underlying error
+        source: InstallError,
+    },
+    /// Some files couldn't be downloaded, each of which was reported
+    #[snafu(display("{} files failed to download", failed))]
+    Download {
+        /// How many files failed
+        failed: usize,
+    },
+    /// An instance doesn't match the pack, in ways that were each reported
+    #[snafu(display("{} problems found", count))]
+    Problems {
+        /// How many problems were found
+        count: usize,
+    },
+    /// A published version was recorded with another lockfile than the one exported
+    #[snafu(display(
+        "{} was recorded in {} with another lockfile",
+        version,
+        path.display()
+    ))]
+    Recorded {
+        /// The pack's version
+        version: semver::Version,
+        /// Where its lockfile is recorded
+        path: PathBuf,
+    },
+    /// The pack couldn't be exported
+    #[snafu(display("Failed to export: {}", source))]
+    Export {
+        /// The underlying error
+        source: ExportError,
+    },
+    /// What was given to import isn't a pack of any known format
+    #[snafu(display("{} isn't a pack ffpack can import", path.display()))]
+    UnknownFormat {
+        /// What was given
+        path: PathBuf,
+    },
+    /// A pack couldn't be imported
+    #[snafu(display("Failed to import: {}", source))]
+    Import {
+        /// The underlying error
+        source: ImportError,
+    },
+    /// Some files couldn't be compared with their newest versions, each of which was reported
+    #[snafu(display("{} files couldn't be checked for updates", count))]
+    Unchecked {
+        /// How many files couldn't be checked
+        count: usize,
+    },
+    /// Some files have newer compatible versions
+    #[snafu(display("{} files have updates", count))]
+    Outdated {
+        /// How many files have updates
+        count: usize,
+    },
+    /// Updating changed the lockfile, as the branch report written describes
+    #[snafu(display("Updated {} files, as {} reports", count, path))]
+    Updated {
+        /// How many files changed
+        count: usize,
+        /// Where the report was written
+        path: String,
+    },
+    /// Some checks of the pack or the environment found errors, each of which was reported
+    #[snafu(display("{} checks found errors", count))]
+    Unhealthy {
+        /// How many checks found errors
+        count: usize,
+    },
+    /// The registered instances couldn't be read
+    #[snafu(display("Failed to parse {}: {}", path.display(), source))]
+    Instances {
+        /// The registry's path
+        path: PathBuf,
+        /// The underlying error
+        source: serde_json::Error,
+    },
+    /// An instance was registered under a name already taken
+    #[snafu(display("An instance named {} is already registered", name))]
+    InstanceExists {
+        /// The name
+        name: String,
+    },
+    /// No instance is registered under a name
+    #[snafu(display("No instance named {} is registered", name))]
+    UnknownInstance {
+        /// The name
+        name: String,
+    },
+    /// Some instances failed to sync, as the table of results reported
+    #[snafu(display("{} instances failed to sync", count))]
+    Unsynced {
+        /// How many failed
+        count: usize,
+    },
+    /// The server couldn't listen on its address
+    #[snafu(display("Failed to listen on {}: {}", address, source))]
+    Serve {
+        /// The address
+        address: String,
+        /// The underlying error
+        source: std::io::Error,
+    },
+    /// The platform's keychain couldn't store or remove a token
+    #[cfg(feature = "keyring")]
+    #[snafu(display("{}", source))]
+    Keyring {
+        /// The underlying error
+        source: keyring::KeyringError,
+    },
+}
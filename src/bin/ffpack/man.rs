@@ -0,0 +1,148 @@
+//! `ffpack man`: the manual page, generated from the same descriptions as `--help`
+//!
+//! The page is printed as roff for `man` to render, so distribution packages can install it as
+//! `ffpack.1` while building, after the binary itself is built.
+
+use std::fmt::Write;
+
+use ffpack::resolve::Credentials;
+use snafu::ResultExt;
+
+use crate::{
+    args::Options,
+    commands::{Opt, COMMANDS, GLOBAL},
+    config, CliError, UsageSnafu,
+};
+
+/// The environment variables ffpack reads, and what they are for
+const ENVIRONMENT: [(&str, &str); 9] = [
+    (
+        config::CONFIG_VAR,
+        "The configuration file to read instead of the usual one",
+    ),
+    (
+        Credentials::CURSEFORGE_VAR,
+        "The CurseForge API key, which CurseForge sources need",
+    ),
+    (
+        Credentials::GITHUB_VAR,
+        "A GitHub personal access token, for higher rate limits, with GITHUB_TOKEN read when it \
+         isn't set",
+    ),
+    (
+        Credentials::MODRINTH_VAR,
+        "A Modrinth personal access token",
+    ),
+    (
+        config::CACHE_DIR_VAR,
+        "The directory downloads are cached in, instead of ffpack under the user's cache \
+         directory",
+    ),
+    (config::JOBS_VAR, "How many files are downloaded at once"),
+    ("FFPACK_CURL", "The curl program requests are made with"),
+    (
+        "HTTPS_PROXY, HTTP_PROXY, ALL_PROXY",
+        "The proxy requests go through, by the scheme they are for",
+    ),
+    ("NO_PROXY", "The hosts requests reach without a proxy"),
+];
+
+/// Escapes `text` for roff, so it is printed as written
+fn escape(text: &str) -> String {
+    let escaped = text.replace('\\', r"\e").replace('-', r"\-");
+    // Lines starting with these would be read as requests
+    if escaped.starts_with(['.', '\'']) {
+        format!(r"\&{escaped}")
+    } else {
+        escaped
+    }
+}
+
+/// Appends the paragraphs describing `options` to `page`
+fn options(page: &mut String, options: &[Opt]) {
+    for option in options {
+        let synopsis = escape(option.synopsis().trim_start());
+        writeln!(page, ".TP\n\\fB{synopsis}\\fR").expect("Strings can be written to");
+        let mut about = escape(option.about);
+        if !option.choices.is_empty() {
+            write!(about, ": {}", escape(&option.choices.join(", ")))
+                .expect("Strings can be written to");
+        }
+        writeln!(page, "{about}").expect("Strings can be written to");
+    }
+}
+
+/// Returns the manual page
+fn page() -> String {
+    let mut page = format!(
+        ".TH FFPACK 1 \"\" \"ffpack {}\" \"User Commands\"\n.SH NAME\nffpack \\- {}\n",
+        escape(env!("CARGO_PKG_VERSION")),
+        escape(env!("CARGO_PKG_DESCRIPTION"))
+    );
+    page.push_str(
+        ".SH SYNOPSIS\n\
+         .B ffpack\n\
+         [\\fIOPTIONS\\fR] \\fICOMMAND\\fR [\\fIARGS\\fR]\n\
+         .SH DESCRIPTION\n\
+         Every command operates on a pack manifest, ffpack.json, found by searching upward from \
+         the working directory.\n\
+         A repository holds either a single pack, with its manifest at the root, or several \
+         under packs/, each chosen with \\fB\\-\\-pack\\fR.\n\
+         .SH OPTIONS\n",
+    );
+    options(&mut page, GLOBAL);
+    page.push_str(".SH COMMANDS\n");
+    for command in COMMANDS {
+        let heading = format!("ffpack {} {}", command.name, command.args);
+        writeln!(page, ".SS \"{}\"", escape(heading.trim_end()))
+            .expect("Strings can be written to");
+        let mut about = escape(command.about);
+        if !command.choices.is_empty() {
+            write!(about, ", one of: {}", escape(&command.choices.join(", ")))
+                .expect("Strings can be written to");
+        }
+        writeln!(page, "{about}").expect("Strings can be written to");
+        options(&mut page, command.options);
+    }
+    page.push_str(
+        ".SH FILES\n\
+         .TP\n\
+         ~/.config/ffpack/config.toml\n\
+         Settings for every run, under the user's configuration directory.\n\
+         It takes cache\\-dir, jobs, a [tokens] table of curseforge, github, and modrinth, and \
+         a [proxy] table of http, https, all, and no\\-proxy.\n\
+         The variables below override the settings they correspond to.\n",
+    );
+    page.push_str(".SH ENVIRONMENT\n");
+    for (name, about) in ENVIRONMENT {
+        writeln!(page, ".TP\n\\fB{}\\fR\n{}", escape(name), escape(about))
+            .expect("Strings can be written to");
+    }
+    page.push_str(
+        ".SH EXIT STATUS\n\
+         .TP\n0\nThe command succeeded.\n\
+         .TP\n1\nThe command failed, or found problems it was checking for.\n\
+         .TP\n2\nThe command line couldn't be understood.\n",
+    );
+    page
+}
+
+/// Runs `ffpack man`, printing the manual page
+pub fn run(options: Options) -> Result<(), CliError> {
+    options.finish().context(UsageSnafu)?;
+    print!("{}", page());
+    Ok(())
+}
+
+#[cfg(test)]
+mod unit_tests {
+    // Options are escaped, and text that roff would read as a request isn't left at a line start
+    #[test]
+    fn escape() {
+        assert_eq!(super::escape("--side <SIDE>"), r"\-\-side <SIDE>");
+        assert_eq!(super::escape(".hidden"), r"\&.hidden");
+        let page = super::page();
+        assert!(page.contains(".SS \"ffpack export <FORMAT>\""));
+        assert!(page.contains(".TP\n\\fB\\-o, \\-\\-output <PATH>\\fR\nWrite the export here\n"));
+    }
+}
@@ -0,0 +1,445 @@
+//! Talking to the network through the `curl` program
+//!
+//! The library leaves the HTTP stack to whoever embeds it. Rather than bundle one, the command
+//! line application runs the `curl` on the `PATH`, which brings its own TLS, proxy support, and
+//! certificate store. `FFPACK_CURL` names another program to run instead. Which proxy each
+//! request goes through is decided from the [configured](crate::config) proxies and passed to
+//! curl, and API requests are spaced out to keep to the rate limits the APIs document. Each
+//! request runs curl on a thread of its own, so the downloads a command polls at once run at
+//! once, as many as the `jobs` setting allows, and commands drive them with [`block_on`].
+//!
+//! Git sources are checked out under the cache, but their builds only run once `--allow-build`
+//! [allows](allow_builds) them, as they run whatever the manifest says to.
+
+use std::{
+    env,
+    error::Error,
+    fs,
+    future::{poll_fn, Future},
+    io::Write,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    pin::pin,
+    process::{Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    task::{Context, Poll, Wake, Waker},
+    thread::{self, Thread},
+};
+
+use ffpack::{
+    git::Git,
+    http::{
+        CachingClient, FullBody, HttpClient, HttpError, ProxyConfig, RateLimitedClient, RateLimits,
+        Request, Response, RetryPolicy, RetryingClient,
+    },
+    resolve::{user_agent, Resolver},
+    Pack,
+};
+use url::Url;
+
+use crate::{config, scratch};
+
+/// Whether git sources may run their builds in this run
+static ALLOW_BUILD: AtomicBool = AtomicBool::new(false);
+
+/// Lets git sources run their builds for the rest of the run
+pub fn allow_builds() {
+    ALLOW_BUILD.store(true, Ordering::Relaxed);
+}
+
+/// What curl prints once it is done, the final url and the status on their own lines
+const WRITE_OUT: &str = "%{url_effective}\n%{response_code}";
+
+/// The client API requests go through, keeping to the APIs' rate limits, retrying failures, and
+/// revalidating against a cache
+pub type ApiClient = CachingClient<RetryingClient<RateLimitedClient<CurlClient>>>;
+
+/// An [`HttpClient`] running `curl` for every request
+#[derive(Debug, Clone)]
+pub struct CurlClient {
+    /// The program to run
+    program: PathBuf,
+    /// Which proxies requests go through
+    proxy: ProxyConfig,
+}
+
+impl CurlClient {
+    /// Creates a client running `FFPACK_CURL`, or `curl` if it isn't set, through the configured
+    /// proxies
+    pub fn new() -> Self {
+        Self {
+            program: env::var_os("FFPACK_CURL").map_or_else(|| "curl".into(), PathBuf::from),
+            proxy: config::get().proxy.clone(),
+        }
+    }
+
+    /// Runs curl for `request`, with the headers and body written to the files `scratch` starts,
+    /// which is in a directory only the user can enter
+    ///
+    /// The request's headers reach curl through a file only the user can read, as they can hold
+    /// tokens that other users would see in its arguments.
+    fn send(
+        &self,
+        request: &Request,
+        scratch: &Path,
+    ) -> Result<Response<FullBody>, Box<dyn Error + Send + Sync>> {
+        let headers = scratch.with_extension("headers");
+        let body = scratch.with_extension("body");
+        let sent = scratch.with_extension("sent");
+        let written = write_headers(&sent, &request.headers);
+        let response = written.and_then(|()| self.run(request, &sent, &headers, &body));
+        let _ = fs::remove_file(&sent);
+        let _ = fs::remove_file(&headers);
+        let _ = fs::remove_file(&body);
+        response
+    }
+
+    /// Runs curl for `request` with its headers in the file `sent`, having it write the
+    /// response's headers to `headers` and its body to `body`
+    fn run(
+        &self,
+        request: &Request,
+        sent: &Path,
+        headers: &Path,
+        body: &Path,
+    ) -> Result<Response<FullBody>, Box<dyn Error + Send + Sync>> {
+        let mut command = Command::new(&self.program);
+        command
+            .args(["--silent", "--show-error", "--location", "--globoff"])
+            .arg("--dump-header")
+            .arg(headers)
+            .arg("--output")
+            .arg(body)
+            .args(["--write-out", WRITE_OUT]);
+        match self.proxy.proxy_for(&request.url) {
+            Some(proxy) => command.arg("--proxy").arg(proxy.url().as_str()),
+            // Curl would otherwise pick its own from the environment
+            None => command.args(["--noproxy", "*"]),
+        };
+        if !request.headers.is_empty() {
+            let mut file = std::ffi::OsString::from("@");
+            file.push(sent);
+            command.arg("--header").arg(file);
+        }
+        if request.body.is_some() {
+            command.args(["--data-binary", "@-"]);
+        }
+        command
+            .arg("--url")
+            .arg(request.url.as_str())
+            .stdin(if request.body.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let mut child = command
+            .spawn()
+            .map_err(|error| format!("Couldn't run {}: {error}", self.program.display()))?;
+        if let (Some(mut stdin), Some(data)) = (child.stdin.take(), &request.body) {
+            stdin.write_all(data)?;
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(String::from_utf8_lossy(&output.stderr).trim().into());
+        }
+        let written = String::from_utf8(output.stdout)?;
+        let (url, status) = written
+            .rsplit_once('\n')
+            .ok_or("curl didn't report the response")?;
+        Ok(Response {
+            url: Url::parse(url)?,
+            status: status.trim().parse()?,
+            headers: parse_headers(&fs::read_to_string(headers)?),
+            body: FullBody(fs::read(body).unwrap_or_default()),
+        })
+    }
+}
+
+/// Writes `headers` to `path` one to a line, as curl reads them with `--header @<file>`, in a
+/// new file only the user can read
+fn write_headers(
+    path: &Path,
+    headers: &[(String, String)],
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if headers.is_empty() {
+        return Ok(());
+    }
+    let mut lines = String::new();
+    for (name, value) in headers {
+        if [name, value].iter().any(|part| part.contains(['\r', '\n'])) {
+            return Err(format!("The {name} header holds a line break").into());
+        }
+        lines.push_str(&format!("{name}: {value}\n"));
+    }
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(lines.as_bytes())?;
+    Ok(())
+}
+
+impl Default for CurlClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HttpClient for CurlClient {
+    type Body = FullBody;
+
+    async fn get(&self, request: Request) -> Result<Response<FullBody>, HttpError> {
+        let client = self.clone();
+        let url = request.url.clone();
+        let response = on_thread(move || {
+            let dir = scratch::private_dir("ffpack-curl")?;
+            let response = client.send(&request, &dir.join("request"));
+            let _ = fs::remove_dir_all(&dir);
+            response
+        })
+        .await;
+        response.map_err(|source| HttpError::Transport { url, source })
+    }
+}
+
+/// The result of work on another thread, along with the waker of the future waiting for it
+type Slot<T> = Arc<Mutex<(Option<thread::Result<T>>, Option<Waker>)>>;
+
+/// Runs `work` on a thread of its own, returning a future that completes with its result
+///
+/// Curl runs to completion, which would keep the thread polling a request from making any other
+/// request meanwhile. On threads of their own, as many requests run at once as are polled at
+/// once. A panic in `work` is resumed by the future.
+fn on_thread<T: Send + 'static>(
+    work: impl FnOnce() -> T + Send + 'static,
+) -> impl Future<Output = T> + Send {
+    let slot: Slot<T> = Arc::new(Mutex::new((None, None)));
+    let shared = Arc::clone(&slot);
+    thread::spawn(move || {
+        let output = panic::catch_unwind(AssertUnwindSafe(work));
+        let mut slot = shared.lock().unwrap_or_else(PoisonError::into_inner);
+        slot.0 = Some(output);
+        if let Some(waker) = slot.1.take() {
+            waker.wake();
+        }
+    });
+    poll_fn(move |context| {
+        let mut slot = slot.lock().unwrap_or_else(PoisonError::into_inner);
+        match slot.0.take() {
+            Some(output) => Poll::Ready(output.unwrap_or_else(|panic| panic::resume_unwind(panic))),
+            None => {
+                slot.1 = Some(context.waker().clone());
+                Poll::Pending
+            }
+        }
+    })
+}
+
+/// Parses the headers of the last response curl dumped, after any redirects it followed
+fn parse_headers(dump: &str) -> Vec<(String, String)> {
+    let last = dump
+        .split("\r\n\r\n")
+        .filter(|block| !block.trim().is_empty())
+        .last()
+        .unwrap_or_default();
+    last.lines()
+        .skip(1)
+        .filter_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            Some((name.trim().to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Runs a future to completion on the current thread
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    /// Wakes the blocked thread
+    struct ThreadWaker(Thread);
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut context = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+/// Returns where downloads and API responses are cached
+pub fn cache_dir() -> PathBuf {
+    config::get().cache_dir.value.clone()
+}
+
+/// Creates the client API requests go through
+///
+/// Retries are rate limited like any other request, so they can't push a bulk lock over the
+/// limit either, while answers from the cache don't count against it.
+pub fn api_client() -> ApiClient {
+    let limited = RateLimitedClient::new(CurlClient::new(), RateLimits::default());
+    let retrying = RetryingClient::new(limited, RetryPolicy::default());
+    CachingClient::new(retrying, cache_dir().join("http"))
+}
+
+/// Creates a resolver for the pack whose manifest is at `manifest`, with the configured
+/// credentials, checking git sources out under the cache
+pub fn resolver<'a>(
+    client: &'a ApiClient,
+    manifest: &Path,
+    pack: &Pack,
+) -> Resolver<&'a ApiClient> {
+    let manifest = manifest
+        .canonicalize()
+        .unwrap_or_else(|_| manifest.to_path_buf());
+    let root = manifest.parent().unwrap_or(Path::new(".")).to_path_buf();
+    Resolver::new(client)
+        .with_root(root)
+        .with_credentials(config::get().credentials())
+        .with_user_agent(user_agent(&pack.metadata))
+        .with_builds(ALLOW_BUILD.load(Ordering::Relaxed))
+        .with_git(Git::new(cache_dir().join("git")))
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use std::fs;
+
+    use ffpack::{
+        download::{DownloadJob, DownloadOptions, Downloader},
+        http::ProxyConfig,
+        resolve::{ProjectDetails, ResolvedArtifact, UpstreamIds},
+        types::Hashes,
+    };
+    use url::Url;
+
+    use super::{block_on, CurlClient};
+
+    /// How many downloads the concurrency test runs at once
+    const IN_FLIGHT: usize = 3;
+
+    /// A stand-in for curl that marks itself started in `dir`, then waits for
+    /// [`IN_FLIGHT`] requests to have started and responds with how many did
+    fn fake_curl(dir: &std::path::Path) -> String {
+        format!(
+            r#"#!/bin/sh
+while [ $# -gt 0 ]; do
+    case "$1" in
+        --dump-header) headers=$2; shift ;;
+        --output) body=$2; shift ;;
+        --url) url=$2; shift ;;
+    esac
+    shift
+done
+touch "{dir}/started.$$"
+tries=0
+while [ "$(ls "{dir}" | grep -c started)" -lt {IN_FLIGHT} ] && [ $tries -lt 100 ]; do
+    sleep 0.1
+    tries=$((tries + 1))
+done
+printf 'HTTP/1.1 200 OK
+
+' > "$headers"
+ls "{dir}" | grep -c started | tr -d '
+' > "$body"
+printf '%s
+200' "$url"
+"#,
+            dir = dir.display()
+        )
+    }
+
+    // Downloads run at once, up to the parallelism, rather than one curl after another
+    #[cfg(unix)]
+    #[test]
+    fn concurrent() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = crate::scratch::private_dir("ffpack-concurrent").unwrap();
+        let started = dir.join("started");
+        fs::create_dir(&started).unwrap();
+        let program = dir.join("curl");
+        fs::write(&program, fake_curl(&started)).unwrap();
+        fs::set_permissions(&program, fs::Permissions::from_mode(0o755)).unwrap();
+        let client = CurlClient {
+            program,
+            proxy: ProxyConfig::default(),
+        };
+        let jobs: Vec<_> = (0..IN_FLIGHT)
+            .map(|index| DownloadJob {
+                artifact: ResolvedArtifact {
+                    download_url: Url::parse(&format!("https://example.org/{index}.jar")).unwrap(),
+                    filename: format!("{index}.jar"),
+                    size: None,
+                    hashes: Hashes::default(),
+                    mirrors: Vec::new(),
+                    extract: None,
+                    ids: UpstreamIds::default(),
+                    details: ProjectDetails::default(),
+                },
+                path: format!("mods/{index}.jar").into(),
+            })
+            .collect();
+        let options = DownloadOptions::default().with_parallelism(IN_FLIGHT);
+        let downloader = Downloader::new(client, options);
+        let target = dir.join("instance");
+        for result in block_on(downloader.download(&jobs, &target)) {
+            result.unwrap();
+        }
+        for job in &jobs {
+            let seen = fs::read_to_string(job.path.to_path(&target)).unwrap();
+            assert_eq!(seen, IN_FLIGHT.to_string());
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    // Only the headers of the response redirects ended at are kept
+    #[test]
+    fn parse_headers() {
+        let dump = "HTTP/1.1 302 Found\r\nLocation: /next\r\n\r\n\
+                    HTTP/2 200\r\nETag: \"abc\"\r\nContent-Type: text/plain\r\n\r\n";
+        assert_eq!(
+            super::parse_headers(dump),
+            [
+                ("ETag".to_string(), "\"abc\"".to_string()),
+                ("Content-Type".to_string(), "text/plain".to_string()),
+            ]
+        );
+    }
+
+    // Headers are written one to a line for curl, refusing ones that would split into more
+    #[test]
+    fn write_headers() {
+        let dir = crate::scratch::private_dir("ffpack-headers").unwrap();
+        let path = dir.join("sent");
+        let headers = [("Authorization".to_string(), "Bearer secret".to_string())];
+        super::write_headers(&path, &headers).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "Authorization: Bearer secret\n"
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        std::fs::remove_file(&path).unwrap();
+        let split = [("X-Test".to_string(), "a\r\nInjected: b".to_string())];
+        assert!(super::write_headers(&path, &split).is_err());
+        assert!(!path.exists());
+        std::fs::remove_dir(dir).unwrap();
+    }
+}
@@ -0,0 +1,216 @@
+//! `ffpack outdated`: listing the files that have newer compatible versions
+//!
+//! Files are compared at what the lockfile locked them to, without locking or changing
+//! anything. The ones with updates print as a table, or with `--json` as an object scripts can
+//! read, and files that couldn't be checked are reported too. The command fails when there are
+//! updates, so CI can hold a pack to its newest versions, and `ffpack update` takes them. In CI
+//! mode, each update and failure annotates the manifest, except with `--json`, which keeps the
+//! output to the object alone, and the updates are summarized on the job's page.
+
+use ffpack::{
+    lock::Lockfile,
+    outdated::{self, Outdated},
+};
+use relative_path::RelativePathBuf;
+use serde_json::json;
+use snafu::{ensure, ResultExt};
+
+use crate::{
+    args::{Global, Options},
+    ci::{self, Level},
+    net::{self, block_on},
+    CliError, LockSnafu, OutdatedSnafu, UncheckedSnafu, UsageSnafu,
+};
+
+/// The headings of the table's columns
+const HEADINGS: [&str; 3] = ["NAME", "LOCKED", "LATEST"];
+
+/// A file with a newer compatible version
+#[derive(Debug)]
+struct Update {
+    /// The file's path
+    path: RelativePathBuf,
+    /// The file's name, or its path if it has none
+    name: String,
+    /// The version the file is at
+    current: Option<String>,
+    /// The newest compatible version
+    available: Option<String>,
+    /// The page describing what changed in the newest version
+    changelog: Option<String>,
+}
+
+/// Lays the updates out as a table with aligned columns
+fn table(updates: &[Update]) -> String {
+    let unknown = || "?".to_string();
+    let rows: Vec<[String; 3]> = updates
+        .iter()
+        .map(|update| {
+            [
+                update.name.clone(),
+                update.current.clone().unwrap_or_else(unknown),
+                update.available.clone().unwrap_or_else(unknown),
+            ]
+        })
+        .collect();
+    let mut widths = HEADINGS.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let headings = HEADINGS.map(String::from);
+    let mut table = String::new();
+    for row in std::iter::once(&headings).chain(&rows) {
+        let line: Vec<_> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect();
+        table.push_str(line.join("  ").trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+/// Summarizes the updates in Markdown, for the job's page
+fn summary(updates: &[Update], failures: usize) -> String {
+    let mut summary = String::from("## ffpack outdated\n\n");
+    if updates.is_empty() {
+        summary.push_str("Every file is at its newest compatible version\n");
+    } else {
+        let unknown = || "?".to_string();
+        let rows: Vec<_> = updates
+            .iter()
+            .map(|update| {
+                [
+                    update.name.clone(),
+                    update.current.clone().unwrap_or_else(unknown),
+                    update.available.clone().unwrap_or_else(unknown),
+                    update
+                        .changelog
+                        .as_ref()
+                        .map(|changelog| format!("[changelog]({changelog})"))
+                        .unwrap_or_default(),
+                ]
+            })
+            .collect();
+        summary.push_str(&ci::table(["Name", "Locked", "Latest", "Changes"], &rows));
+    }
+    if failures > 0 {
+        summary.push_str(&format!("\n{failures} files couldn't be checked\n"));
+    }
+    summary
+}
+
+/// Runs `ffpack outdated`, failing if any file of the manifest in use has an update
+pub fn run(global: &Global, mut options: Options) -> Result<(), CliError> {
+    let json = options.flag("--json", None);
+    options.finish().context(UsageSnafu)?;
+    let (manifest, pack) = global.load()?;
+    let lock = Lockfile::load(&Lockfile::path_for(&manifest)).context(LockSnafu)?;
+    let client = net::api_client();
+    let resolver = net::resolver(&client, &manifest, &pack);
+    let results = block_on(outdated::outdated(&resolver, &pack, lock.as_ref()));
+
+    let mut updates = Vec::new();
+    let mut failures = Vec::new();
+    for (path, outcome) in results {
+        match outcome {
+            Ok(Outdated::Available {
+                current,
+                available,
+                changelog,
+            }) => {
+                let file = pack.managed_files.iter().find(|file| file.path == path);
+                updates.push(Update {
+                    name: file
+                        .and_then(|file| file.name.clone())
+                        .unwrap_or_else(|| path.to_string()),
+                    path,
+                    current,
+                    available,
+                    changelog: changelog.map(String::from),
+                });
+            }
+            Ok(_) => {}
+            Err(error) => failures.push((path, error)),
+        }
+    }
+
+    if json {
+        let updates: Vec<_> = updates
+            .iter()
+            .map(|update| {
+                json!({
+                    "path": update.path,
+                    "name": update.name,
+                    "current": update.current,
+                    "available": update.available,
+                    "changelog": update.changelog,
+                })
+            })
+            .collect();
+        let failures: Vec<_> = failures
+            .iter()
+            .map(|(path, error)| json!({ "path": path, "error": error.to_string() }))
+            .collect();
+        let report = json!({ "updates": updates, "failures": failures });
+        let report = serde_json::to_string_pretty(&report).expect("Values serialize to JSON");
+        println!("{report}");
+    } else {
+        for (path, error) in &failures {
+            eprintln!("warning: Couldn't check {path}: {error}");
+            let message = format!("Couldn't check {path}: {error}");
+            ci::annotate(Level::Warning, Some(&manifest), "ffpack outdated", &message);
+        }
+        if updates.is_empty() {
+            println!("Every file is at its newest compatible version");
+        } else {
+            print!("{}", table(&updates));
+        }
+        for update in &updates {
+            let unknown = "?";
+            let message = format!(
+                "{} can be updated from {} to {}",
+                update.name,
+                update.current.as_deref().unwrap_or(unknown),
+                update.available.as_deref().unwrap_or(unknown)
+            );
+            ci::annotate(Level::Warning, Some(&manifest), "ffpack outdated", &message);
+        }
+    }
+    ci::summarize(&summary(&updates, failures.len()))?;
+    ensure!(
+        failures.is_empty(),
+        UncheckedSnafu {
+            count: failures.len()
+        }
+    );
+    ensure!(
+        updates.is_empty(),
+        OutdatedSnafu {
+            count: updates.len()
+        }
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    // Versions the source doesn't label show as unknown, and the columns line up
+    #[test]
+    fn table() {
+        let updates = [Update {
+            path: "mods/sodium.jar".into(),
+            name: "Sodium".into(),
+            current: Some("0.4.10".into()),
+            available: None,
+            changelog: None,
+        }];
+        let table = super::table(&updates);
+        assert_eq!(table, "NAME    LOCKED  LATEST\nSodium  0.4.10  ?\n");
+    }
+}
@@ -0,0 +1,103 @@
+//! Asking the user questions on the terminal
+//!
+//! Every question has an answer it falls back to, if one makes sense, which an empty answer
+//! takes. Commands run with `--yes`, or in CI mode, take those defaults without asking at all,
+//! and answers that don't parse are asked for again.
+
+use std::io::{self, BufRead, Write};
+
+use crate::ci;
+
+/// Asks questions on `output` and reads the answers from `input`
+#[derive(Debug)]
+pub struct Prompter<R, W> {
+    /// Where answers are read from
+    input: R,
+    /// Where questions are written to
+    output: W,
+    /// Take every default without asking
+    assume_defaults: bool,
+}
+
+impl<R: BufRead, W: Write> Prompter<R, W> {
+    /// Creates a prompter, which with `assume_defaults` or in CI mode never asks anything
+    pub fn new(input: R, output: W, assume_defaults: bool) -> Self {
+        Self {
+            input,
+            output,
+            assume_defaults: assume_defaults || ci::enabled(),
+        }
+    }
+
+    /// Asks `question` until `parse` accepts the answer, an empty answer taking `default`
+    ///
+    /// Without a default, empty answers are asked for again, and running with `--yes` is an
+    /// error, as it is in CI mode. The end of the input takes the default too.
+    pub fn ask<T>(
+        &mut self,
+        question: &str,
+        default: Option<&str>,
+        parse: impl Fn(&str) -> Result<T, String>,
+    ) -> io::Result<T> {
+        if self.assume_defaults {
+            let default = default.ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("\"{question}\" has no default to take without asking"),
+                )
+            })?;
+            return parse(default)
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error));
+        }
+        loop {
+            match default {
+                Some(default) => write!(self.output, "{question} [{default}]: ")?,
+                None => write!(self.output, "{question}: ")?,
+            }
+            self.output.flush()?;
+            let mut answer = String::new();
+            let ended = self.input.read_line(&mut answer)? == 0;
+            let answer = match (answer.trim(), default) {
+                ("", Some(default)) => default,
+                ("", None) if ended => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        format!("No answer to \"{question}\""),
+                    ))
+                }
+                ("", None) => continue,
+                (answer, _) => answer,
+            };
+            match parse(answer) {
+                Ok(value) => return Ok(value),
+                Err(error) if ended => {
+                    return Err(io::Error::new(io::ErrorKind::InvalidInput, error))
+                }
+                Err(error) => writeln!(self.output, "{error}")?,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    // Invalid answers are asked for again, and empty ones take the default
+    #[test]
+    fn ask() {
+        let mut output = Vec::new();
+        let mut prompter = Prompter::new(&b"twelve\n12\n\n"[..], &mut output, false);
+        let parse = |answer: &str| answer.parse::<u32>().map_err(|error| error.to_string());
+        assert_eq!(prompter.ask("Count", None, parse).unwrap(), 12);
+        assert_eq!(prompter.ask("Count", Some("3"), parse).unwrap(), 3);
+        assert!(prompter.ask("Count", None, parse).is_err());
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("Count: invalid digit"));
+        assert!(output.contains("Count [3]: "));
+
+        let mut prompter = Prompter::new(&b""[..], Vec::new(), true);
+        assert_eq!(prompter.ask("Count", Some("3"), parse).unwrap(), 3);
+        assert!(prompter.ask("Count", None, parse).is_err());
+    }
+}
@@ -0,0 +1,142 @@
+//! `ffpack quickstart`: a guided tour from an empty directory to an exported pack
+//!
+//! The tour takes a new pack through the steps of its life, naming the command that takes each
+//! one on its own: it creates the manifest the way `ffpack init` does, then searches for mods to
+//! add the way `ffpack search --add` does, locks the pack, and exports it as an `.mrpack`. Mods
+//! can be named up front with `--mod`, which together with `--yes` adds the best match for each
+//! without asking anything.
+
+use std::{
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+};
+
+use ffpack::Pack;
+use snafu::ResultExt;
+
+use crate::{
+    args::{Global, Options},
+    export::{self, Format, ServerFlags},
+    init::{self, Answers},
+    lock,
+    prompt::Prompter,
+    save, search, CliError, PromptSnafu, UsageSnafu,
+};
+
+/// How many results each search offers
+const SEARCH_LIMIT: usize = 5;
+
+/// The answer that moves on from adding mods
+const DONE: &str = "done";
+
+/// How many steps the tour has
+const STEPS: usize = 4;
+
+/// Announces a step of the tour, with the command that takes it on its own
+fn step(number: usize, what: &str, command: &str) {
+    println!("\n[{number}/{STEPS}] {what} (ffpack {command})");
+}
+
+/// Searches for mods to add to `pack`, first the ones in `given` and then whatever is asked for
+/// until the answer is [`DONE`], saving the manifest after each one added
+fn add_mods<R: BufRead, W: Write>(
+    prompter: &mut Prompter<R, W>,
+    manifest: &Path,
+    pack: &mut Pack,
+    given: Vec<String>,
+) -> Result<(), CliError> {
+    let mut given = given.into_iter();
+    loop {
+        let query = match given.next() {
+            Some(query) => query,
+            None => prompter
+                .ask(
+                    &format!("Search for a mod to add, or {DONE}"),
+                    Some(DONE),
+                    init::text,
+                )
+                .context(PromptSnafu)?,
+        };
+        if query.eq_ignore_ascii_case(DONE) {
+            return Ok(());
+        }
+        let hits = search::find(manifest, pack, &query, None, SEARCH_LIMIT)?;
+        if hits.is_empty() {
+            println!(
+                "No mods for minecraft {} on {} match {query}",
+                pack.versions.minecraft,
+                pack.versions.loader.name()
+            );
+            continue;
+        }
+        print!("{}", search::table(&hits));
+        let file = search::pick(prompter, manifest, pack, hits)?;
+        if pack
+            .managed_files
+            .iter()
+            .any(|added| added.path == file.path)
+        {
+            println!("The pack already has {}", file.path);
+            continue;
+        }
+        println!("Added {}", file.path);
+        pack.managed_files.replace(file);
+        save(manifest, pack)?;
+    }
+}
+
+/// Runs `ffpack quickstart [DIR]`, touring the creation of a pack in `DIR`
+pub fn run(global: &Global, mut options: Options) -> Result<(), CliError> {
+    let yes = options.flag("--yes", Some('y'));
+    let mods = options.values("--mod", None).context(UsageSnafu)?;
+    let dir = options
+        .positional()
+        .map_or_else(|| PathBuf::from("."), PathBuf::from);
+    options.finish().context(UsageSnafu)?;
+    let mut prompter = Prompter::new(io::stdin().lock(), io::stderr(), yes);
+
+    step(1, "Creating the manifest, which describes the pack", "init");
+    let (manifest, mut pack) = init::create(global, &dir, &mut prompter, Answers::default())?;
+    println!("Created {}", manifest.display());
+
+    step(2, "Adding mods found by searching for them", "search --add");
+    add_mods(&mut prompter, &manifest, &mut pack, mods)?;
+
+    step(3, "Locking the exact files every install gets", "lock");
+    let lock = lock::ensure(&manifest, &pack)?;
+
+    step(4, "Exporting the pack for Modrinth", "export mrpack");
+    let output = manifest.with_file_name(Format::Mrpack.default_output(&pack, false));
+    export::write(
+        &manifest,
+        &pack,
+        &lock,
+        Format::Mrpack,
+        &ServerFlags::default(),
+        &output,
+    )?;
+
+    println!(
+        "\nThe pack is ready. From here, ffpack add takes mods from anywhere they are published, \
+         ffpack install puts the pack in an instance, and ffpack update takes newer versions."
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    // Taking the defaults moves on without searching for anything
+    #[test]
+    fn add_mods() {
+        let mut pack = Pack::default();
+        let before = pack.clone();
+        let manifest = Path::new("ffpack.json");
+        for (input, yes) in [(&b"DONE\n"[..], false), (b"", false), (b"", true)] {
+            let mut prompter = Prompter::new(input, Vec::new(), yes);
+            super::add_mods(&mut prompter, manifest, &mut pack, Vec::new()).unwrap();
+        }
+        assert_eq!(pack, before);
+    }
+}
@@ -0,0 +1,73 @@
+//! `ffpack rehash`: recording the digests of the pack's files under another algorithm
+//!
+//! Url and path files get a digest under `--to` in the manifest, read from an install of the
+//! pack when one is named and verifies, and downloaded into the cache otherwise. Files whose
+//! sources only resolve when the pack is locked have no digests of their own, and are skipped.
+//! The lockfile picks the new digests up the next time the pack is locked.
+
+use std::path::{Path, PathBuf};
+
+use ffpack::{
+    http::{RetryPolicy, RetryingClient},
+    rehash::{Rehashed, Rehasher},
+};
+use snafu::ResultExt;
+
+use crate::{
+    args::{self, Global, Options, UsageError},
+    config,
+    net::{self, block_on, CurlClient},
+    save, CliError, ReadSnafu, RehashSnafu, UsageSnafu,
+};
+
+/// Runs `ffpack rehash --to <ALGORITHM> [DIR]`, rehashing the manifest in use from the install
+/// at `DIR` or the cache
+pub fn run(global: &Global, mut options: Options) -> Result<(), CliError> {
+    let algorithm = options
+        .value("--to", None)
+        .context(UsageSnafu)?
+        .ok_or_else(|| UsageError::MissingValue {
+            option: "--to".into(),
+        })
+        .and_then(|value| args::algorithm("--to", &value))
+        .context(UsageSnafu)?;
+    let installed = options.positional().map(PathBuf::from);
+    options.finish().context(UsageSnafu)?;
+    let (manifest, mut pack) = global.load()?;
+    let root = manifest
+        .canonicalize()
+        .context(ReadSnafu { path: &manifest })?
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+
+    let cache = installed.unwrap_or_else(|| net::cache_dir().join("rehash"));
+    let client = RetryingClient::new(CurlClient::new(), RetryPolicy::default());
+    let rehasher = Rehasher::new(&client, cache)
+        .with_root(root)
+        .with_credentials(config::get().credentials());
+    let results = block_on(rehasher.rehash(&mut pack, algorithm));
+    let (mut updated, mut failed) = (0, None);
+    for (path, result) in results {
+        match result {
+            Ok(Rehashed::Updated) => {
+                println!("Rehashed {path}");
+                updated += 1;
+            }
+            Ok(Rehashed::Unchanged | Rehashed::Skipped) => {}
+            Err(source) => {
+                eprintln!("error: Failed to rehash {path}: {source}");
+                failed.get_or_insert((path, source));
+            }
+        }
+    }
+    // Digests that were computed are kept even if others failed
+    if updated > 0 {
+        save(&manifest, &pack)?;
+    }
+    if let Some((path, source)) = failed {
+        return Err(source).context(RehashSnafu { path });
+    }
+    println!("Recorded {algorithm} digests for {updated} files");
+    Ok(())
+}
@@ -0,0 +1,97 @@
+//! `ffpack remove`: taking a file out of the pack
+//!
+//! Files are named by their path in the manifest, their name, or the slug of their source, as
+//! whichever is handiest. A name or slug that more than one file shares removes nothing, and the
+//! files it matched are listed so one can be picked by path.
+
+use ffpack::types::ManagedFile;
+use snafu::{ensure, ResultExt};
+
+use crate::{
+    args::{Global, Options},
+    list::slug,
+    save, AmbiguousSnafu, CliError, NotFoundSnafu, UsageSnafu,
+};
+
+/// Returns true if `file` goes by `given`
+fn goes_by(file: &ManagedFile, given: &str) -> bool {
+    file.path == given
+        || file
+            .name
+            .as_deref()
+            .is_some_and(|name| name.eq_ignore_ascii_case(given))
+        || slug(&file.source).is_some_and(|slug| slug.eq_ignore_ascii_case(given))
+}
+
+/// Finds the one file that goes by `given`, with a path matching before anything else
+pub fn find<'a>(
+    files: impl IntoIterator<Item = &'a ManagedFile>,
+    given: &str,
+) -> Vec<&'a ManagedFile> {
+    let matched: Vec<_> = files
+        .into_iter()
+        .filter(|file| goes_by(file, given))
+        .collect();
+    match matched.iter().find(|file| file.path == given) {
+        Some(file) => vec![*file],
+        None => matched,
+    }
+}
+
+/// Runs `ffpack remove <FILE>...`, removing files from the manifest in use
+pub fn run(global: &Global, mut options: Options) -> Result<(), CliError> {
+    let mut given = Vec::new();
+    while let Some(file) = options.positional() {
+        given.push(file);
+    }
+    if given.is_empty() {
+        options.required("FILE").context(UsageSnafu)?;
+    }
+    options.finish().context(UsageSnafu)?;
+    let (manifest, mut pack) = global.load()?;
+    for given in given {
+        let matched = find(&pack.managed_files, &given);
+        ensure!(!matched.is_empty(), NotFoundSnafu { given });
+        let paths: Vec<_> = matched.iter().map(|file| file.path.to_string()).collect();
+        ensure!(paths.len() == 1, AmbiguousSnafu { given, paths });
+        let file = matched[0].clone();
+        pack.managed_files.remove(&file);
+        println!("Removed {}", file.path);
+    }
+    save(&manifest, &pack)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use ffpack::types::Source;
+    use relative_path::RelativePathBuf;
+
+    use super::*;
+
+    // Files are found by path, name, or slug, and a path wins over a name another file has
+    #[test]
+    fn find() {
+        let sodium = ManagedFile {
+            name: Some("Sodium".into()),
+            path: RelativePathBuf::from("mods/sodium.jar"),
+            source: Source::Modrinth {
+                slug: "sodium".into(),
+                version_id: None,
+            },
+            ..ManagedFile::default()
+        };
+        let named = ManagedFile {
+            name: Some("mods/sodium.jar".into()),
+            path: RelativePathBuf::from("mods/other.jar"),
+            source: Source::Curseforge {
+                slug: "sodium".into(),
+                file_id: None,
+            },
+            ..ManagedFile::default()
+        };
+        let files = [sodium.clone(), named];
+        assert_eq!(super::find(&files, "mods/sodium.jar"), [&sodium]);
+        assert_eq!(super::find(&files, "SODIUM").len(), 2);
+        assert!(super::find(&files, "lithium").is_empty());
+    }
+}
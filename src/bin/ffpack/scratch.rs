@@ -0,0 +1,86 @@
+//! Private directories for the intermediate files of a command
+//!
+//! The system's temporary directory is shared with every other user, who can guess names made
+//! from the process id and plant links there before ffpack writes through them. Scratch files
+//! therefore go in a directory ffpack creates itself, under a random name and readable only by
+//! the user, which is never one that existed before.
+
+use std::{
+    collections::hash_map::RandomState,
+    env, fs,
+    hash::{BuildHasher, Hasher},
+    io,
+    path::PathBuf,
+    process,
+    time::SystemTime,
+};
+
+/// How many names are tried before giving up on creating a directory
+const ATTEMPTS: usize = 16;
+
+/// Returns a name that can't be guessed ahead of time
+fn random_name(prefix: &str) -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos());
+    hasher.write_u128(nanos);
+    hasher.write_u32(process::id());
+    format!("{prefix}-{:016x}", hasher.finish())
+}
+
+/// Creates a new directory in the system's temporary directory, named `prefix` and a random
+/// suffix, that only the user can enter
+///
+/// The directory is the caller's to remove once its files are no longer needed.
+pub fn private_dir(prefix: &str) -> io::Result<PathBuf> {
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    let parent = env::temp_dir();
+    for _ in 0..ATTEMPTS {
+        let dir = parent.join(random_name(prefix));
+        // Creating fails on anything already there, links included, rather than reusing it
+        match builder.create(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(error) => return Err(error),
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        format!(
+            "Couldn't find an unused name for a directory in {}",
+            parent.display()
+        ),
+    ))
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    // Each directory is new, differently named, and closed to other users
+    #[test]
+    fn private_dir() {
+        let first = super::private_dir("ffpack-scratch").unwrap();
+        let second = super::private_dir("ffpack-scratch").unwrap();
+        assert_ne!(first, second);
+        assert!(first
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .starts_with("ffpack-scratch-"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&first).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o700);
+        }
+        fs::remove_dir(first).unwrap();
+        fs::remove_dir(second).unwrap();
+    }
+}
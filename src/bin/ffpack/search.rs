@@ -0,0 +1,241 @@
+//! `ffpack search`: finding mods on Modrinth and CurseForge for the pack
+//!
+//! Both platforms are searched for mods with builds for the pack's minecraft version and loader,
+//! CurseForge only when there is a key for it. The results print numbered, with their sources,
+//! downloads, and descriptions, and `--add` asks which one to add to the pack, resolving it the
+//! way `ffpack add` does.
+
+use std::{
+    io::{self, BufRead, Write},
+    path::Path,
+};
+
+use ffpack::{resolve::SearchHit, types::ManagedFile, Pack};
+use snafu::ResultExt;
+
+use crate::{
+    add::{self, Placement},
+    args::{Global, Options, UsageError},
+    config, list,
+    net::{self, block_on},
+    prompt::Prompter,
+    save, CliError, PromptSnafu, SearchSnafu, UsageSnafu,
+};
+
+/// How many results are asked of each platform unless `--limit` says otherwise
+const DEFAULT_LIMIT: usize = 10;
+
+/// The widest descriptions are printed, in characters
+const DESCRIPTION_WIDTH: usize = 60;
+
+/// The platforms that can be searched
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    /// Modrinth
+    Modrinth,
+    /// CurseForge
+    Curseforge,
+}
+
+/// Abbreviates a download count, like `1.2M`
+fn downloads(count: u64) -> String {
+    #[allow(clippy::cast_precision_loss)]
+    let scaled = |divisor: u64, suffix| format!("{:.1}{suffix}", count as f64 / divisor as f64);
+    match count {
+        0..=999 => count.to_string(),
+        1_000..=999_999 => scaled(1_000, "k"),
+        1_000_000..=999_999_999 => scaled(1_000_000, "M"),
+        _ => scaled(1_000_000_000, "B"),
+    }
+}
+
+/// Shortens a description to fit its column, on one line
+fn shorten(description: &str) -> String {
+    let line = description.split_whitespace().collect::<Vec<_>>().join(" ");
+    if line.chars().count() <= DESCRIPTION_WIDTH {
+        return line;
+    }
+    let mut short: String = line.chars().take(DESCRIPTION_WIDTH - 3).collect();
+    short.push_str("...");
+    short
+}
+
+/// Names a result the way `ffpack add` takes it, like `modrinth:sodium`
+fn name(hit: &SearchHit) -> String {
+    let kind = list::source_kind(&hit.source).to_lowercase();
+    let slug = list::slug(&hit.source).unwrap_or_default();
+    format!("{kind}:{slug}")
+}
+
+/// Lays the results out as a numbered table with aligned columns
+pub fn table(hits: &[SearchHit]) -> String {
+    let headings = ["#", "NAME", "SOURCE", "DOWNLOADS", "DESCRIPTION"].map(String::from);
+    let rows: Vec<[String; 5]> = hits
+        .iter()
+        .enumerate()
+        .map(|(index, hit)| {
+            [
+                (index + 1).to_string(),
+                hit.name.clone(),
+                name(hit),
+                downloads(hit.downloads),
+                hit.description.as_deref().map(shorten).unwrap_or_default(),
+            ]
+        })
+        .collect();
+    let mut widths = headings.clone().map(|heading| heading.len());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let mut table = String::new();
+    for row in std::iter::once(&headings).chain(&rows) {
+        let line: Vec<_> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect();
+        table.push_str(line.join("  ").trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+/// Runs `ffpack search <QUERY>`, printing the mods found for the manifest in use and optionally
+/// adding one
+pub fn run(global: &Global, mut options: Options) -> Result<(), CliError> {
+    let add = options.flag("--add", None);
+    let yes = options.flag("--yes", Some('y'));
+    let platform = match options.value("--platform", None).context(UsageSnafu)? {
+        None => None,
+        Some(platform) => Some(match platform.to_ascii_lowercase().as_str() {
+            "modrinth" => Platform::Modrinth,
+            "curseforge" => Platform::Curseforge,
+            _ => {
+                return Err(UsageError::InvalidValue {
+                    option: "--platform".into(),
+                    value: platform,
+                    expected: "modrinth or curseforge".into(),
+                })
+                .context(UsageSnafu)
+            }
+        }),
+    };
+    let limit = match options.value("--limit", None).context(UsageSnafu)? {
+        None => DEFAULT_LIMIT,
+        Some(limit) => limit
+            .parse()
+            .ok()
+            .filter(|limit| *limit > 0)
+            .ok_or(UsageError::InvalidValue {
+                option: "--limit".into(),
+                value: limit,
+                expected: "a positive number".into(),
+            })
+            .context(UsageSnafu)?,
+    };
+    let query = options.required("QUERY").context(UsageSnafu)?;
+    options.finish().context(UsageSnafu)?;
+
+    let (manifest, mut pack) = global.load()?;
+    let hits = find(&manifest, &pack, &query, platform, limit)?;
+    if hits.is_empty() {
+        println!(
+            "No mods for minecraft {} on {} match {query}",
+            pack.versions.minecraft,
+            pack.versions.loader.name()
+        );
+        return Ok(());
+    }
+    print!("{}", table(&hits));
+    if !add {
+        return Ok(());
+    }
+
+    let mut prompter = Prompter::new(io::stdin().lock(), io::stderr(), yes);
+    let file = pick(&mut prompter, &manifest, &pack, hits)?;
+    println!("Added {}", file.path);
+    pack.managed_files.replace(file);
+    save(&manifest, &pack)
+}
+
+/// Searches `platform`, or every platform there is a key for, for mods matching `query` with
+/// builds for the pack whose manifest is at `manifest`
+pub fn find(
+    manifest: &Path,
+    pack: &Pack,
+    query: &str,
+    platform: Option<Platform>,
+    limit: usize,
+) -> Result<Vec<SearchHit>, CliError> {
+    let client = net::api_client();
+    let resolver = net::resolver(&client, manifest, pack);
+    let mut hits = Vec::new();
+    if platform.is_none_or(|platform| platform == Platform::Modrinth) {
+        #[cfg(feature = "modrinth")]
+        hits.extend(
+            block_on(resolver.search_modrinth(query, &pack.versions, limit)).context(
+                SearchSnafu {
+                    platform: "Modrinth",
+                },
+            )?,
+        );
+    }
+    // Without a key, CurseForge is only searched when asked for, to report the missing key
+    let curseforge_key = config::get().curseforge.value.is_some();
+    if platform == Some(Platform::Curseforge) || (platform.is_none() && curseforge_key) {
+        hits.extend(
+            block_on(resolver.search_curseforge(query, &pack.versions, limit)).context(
+                SearchSnafu {
+                    platform: "CurseForge",
+                },
+            )?,
+        );
+    }
+    Ok(hits)
+}
+
+/// Asks which of `hits` to add, returning it resolved the way `ffpack add` resolves files
+pub fn pick<R: BufRead, W: Write>(
+    prompter: &mut Prompter<R, W>,
+    manifest: &Path,
+    pack: &Pack,
+    mut hits: Vec<SearchHit>,
+) -> Result<ManagedFile, CliError> {
+    let count = hits.len();
+    let chosen = prompter
+        .ask("Add which, by number", Some("1"), |answer| {
+            match answer.parse::<usize>() {
+                Ok(number) if (1..=count).contains(&number) => Ok(number - 1),
+                _ => Err(format!("{answer} isn't a number from 1 to {count}")),
+            }
+        })
+        .context(PromptSnafu)?;
+    let hit = hits.swap_remove(chosen);
+    let input = name(&hit);
+    add::resolve(
+        manifest,
+        pack,
+        hit.source,
+        hit.folder,
+        Placement::default(),
+        &input,
+    )
+}
+
+#[cfg(test)]
+mod unit_tests {
+    // Counts are abbreviated, and long descriptions cut to fit their column
+    #[test]
+    fn abbreviations() {
+        assert_eq!(super::downloads(999), "999");
+        assert_eq!(super::downloads(1_250), "1.2k");
+        assert_eq!(super::downloads(48_000_000), "48.0M");
+        let long = "word ".repeat(20);
+        let short = super::shorten(&long);
+        assert_eq!(short.chars().count(), super::DESCRIPTION_WIDTH);
+        assert!(short.ends_with("..."));
+        assert_eq!(super::shorten("Two\nlines"), "Two lines");
+    }
+}
@@ -0,0 +1,544 @@
+//! `ffpack serve`: serving the pack over HTTP, for launchers to install from
+//!
+//! The manifest and lockfile are served at `/ffpack.json` and `/ffpack.lock`, and every locked
+//! artifact both at `/files/<path>`, by its path in the pack, and at `/blobs/<algorithm>/<hex>`,
+//! by its digest, which never changes what it serves. Artifacts are served from the download
+//! cache, which is filled before serving starts, and carry their digests as `X-Checksum-*`
+//! headers and their strongest one as the `ETag`. Artifacts without a digest can't be cached, so
+//! they aren't served.
+//!
+//! The server only answers `GET` and `HEAD`, closing each connection after its response. Each
+//! connection gets its own thread, up to [`MAX_CONNECTIONS`] at once, after which clients are told
+//! to retry; a client that stalls for [`TIMEOUT`] is dropped, and request heads with lines or
+//! headers beyond the limits below are refused. It listens on the loopback address unless
+//! `--bind` names another, such as `0.0.0.0:8080` to serve friends on the network.
+
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use ffpack::{
+    download::DownloadJob,
+    hash::blake3,
+    lock::{Lockfile, LOCK_NAME},
+    types::{HashAlgorithm, Hashes},
+    workspace::MANIFEST_NAME,
+};
+use snafu::{OptionExt, ResultExt};
+
+use crate::{
+    args::{Global, Options},
+    cache, lock, CliError, ReadSnafu, ServeSnafu, UnlockedSnafu, UsageSnafu,
+};
+
+/// The address listened on unless `--bind` says otherwise
+const DEFAULT_ADDRESS: &str = "127.0.0.1:8080";
+
+/// The longest request head read, in bytes
+const MAX_HEAD: usize = 16 * 1024;
+
+/// The longest line of a request head read, in bytes
+const MAX_LINE: usize = 8 * 1024;
+
+/// The most headers a request can have
+const MAX_HEADERS: usize = 64;
+
+/// The most connections answered at once
+const MAX_CONNECTIONS: usize = 64;
+
+/// How long a connection can wait on its client before it is dropped
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// An artifact being served
+#[derive(Debug)]
+struct Artifact {
+    /// Where its cached copy is
+    cached: PathBuf,
+    /// Its digests
+    hashes: Hashes,
+}
+
+/// Everything being served
+#[derive(Debug, Default)]
+struct Served {
+    /// The manifest, as it is on disk
+    manifest: Vec<u8>,
+    /// The lockfile, as it is on disk
+    lockfile: Vec<u8>,
+    /// The artifacts, by their paths in the pack
+    files: BTreeMap<String, Arc<Artifact>>,
+    /// The artifacts, by their digests as `<algorithm>/<hex>`
+    blobs: BTreeMap<String, Arc<Artifact>>,
+}
+
+/// What a response carries
+#[derive(Debug, PartialEq, Eq)]
+enum Body {
+    /// These bytes
+    Bytes(Vec<u8>),
+    /// The contents of this file
+    File(PathBuf),
+}
+
+/// A response to a request
+#[derive(Debug, PartialEq, Eq)]
+struct Response {
+    /// The status code
+    status: u16,
+    /// The headers, besides those every response has
+    headers: Vec<(String, String)>,
+    /// What it carries
+    body: Body,
+}
+
+impl Response {
+    /// A response with only a short explanation
+    fn status(status: u16) -> Self {
+        Self {
+            status,
+            headers: vec![("Content-Type".into(), "text/plain".into())],
+            body: Body::Bytes(format!("{}\n", reason(status)).into_bytes()),
+        }
+    }
+}
+
+/// Returns the reason phrase of a status code
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        304 => "Not Modified",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        431 => "Request Header Fields Too Large",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
+
+/// Returns the `Content-Type` of a file by its extension
+fn content_type(path: &str) -> &'static str {
+    match path.rsplit_once('.').map(|(_, extension)| extension) {
+        Some("jar") => "application/java-archive",
+        Some("zip") => "application/zip",
+        Some("json") => "application/json",
+        Some("toml" | "txt" | "cfg" | "properties") => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Decodes the `%XX` escapes of a url path, returning `None` if they don't decode to UTF-8
+fn percent_decode(path: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut rest = path.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' && tail.len() >= 2 {
+            let hex = std::str::from_utf8(&tail[..2]).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Returns true if `if_none_match`, the tags a client already has, includes `etag`
+///
+/// The header is `*` or a comma-separated list of tags, which are compared weakly, ignoring any
+/// `W/` in front of them.
+fn not_modified(if_none_match: Option<&str>, etag: Option<&str>) -> bool {
+    let (Some(if_none_match), Some(etag)) = (if_none_match, etag) else {
+        return false;
+    };
+    let weak = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let etag = weak(etag);
+    if_none_match.trim() == "*" || if_none_match.split(',').any(|tag| weak(tag) == etag)
+}
+
+/// Returns the response for serving `body`, revalidated against `if_none_match`
+fn document(body: &[u8], content_type: &str, if_none_match: Option<&str>) -> Response {
+    let etag = format!("\"{}\"", hex::encode(blake3(body)));
+    let mut headers = vec![
+        ("ETag".to_string(), etag.clone()),
+        ("Cache-Control".to_string(), "no-cache".to_string()),
+    ];
+    if not_modified(if_none_match, Some(&etag)) {
+        return Response {
+            status: 304,
+            headers,
+            body: Body::Bytes(Vec::new()),
+        };
+    }
+    headers.push(("Content-Type".into(), content_type.into()));
+    Response {
+        status: 200,
+        headers,
+        body: Body::Bytes(body.to_vec()),
+    }
+}
+
+/// Returns the response for serving an artifact, which is cached for good when it is asked for
+/// by its digest
+fn artifact(
+    artifact: &Artifact,
+    name: &str,
+    immutable: bool,
+    if_none_match: Option<&str>,
+) -> Response {
+    let mut headers = Vec::new();
+    if let Some((algorithm, digest)) = artifact.hashes.preferred(&HashAlgorithm::ALL) {
+        let etag = format!("\"{}:{}\"", algorithm.name(), hex::encode(digest));
+        headers.push(("ETag".into(), etag));
+    }
+    let cache_control = if immutable {
+        "public, max-age=31536000, immutable"
+    } else {
+        "no-cache"
+    };
+    headers.push(("Cache-Control".into(), cache_control.into()));
+    let etag = headers
+        .iter()
+        .find(|(name, _)| name == "ETag")
+        .map(|(_, etag)| etag.as_str());
+    if not_modified(if_none_match, etag) {
+        return Response {
+            status: 304,
+            headers,
+            body: Body::Bytes(Vec::new()),
+        };
+    }
+    for algorithm in artifact.hashes.algorithms() {
+        let name = algorithm.name();
+        let name = name[..1].to_uppercase() + &name[1..];
+        let digest = artifact.hashes.hex(algorithm).unwrap_or_default();
+        headers.push((format!("X-Checksum-{name}"), digest));
+    }
+    headers.push(("Content-Type".into(), content_type(name).into()));
+    Response {
+        status: 200,
+        headers,
+        body: Body::File(artifact.cached.clone()),
+    }
+}
+
+/// Lists what is served, one path a line
+fn index(served: &Served) -> Vec<u8> {
+    let mut index = format!("/{MANIFEST_NAME}\n/{LOCK_NAME}\n");
+    for path in served.files.keys() {
+        index.push_str(&format!("/files/{path}\n"));
+    }
+    index.into_bytes()
+}
+
+/// Works out the response to a request for `target`
+fn respond(served: &Served, method: &str, target: &str, if_none_match: Option<&str>) -> Response {
+    if method != "GET" && method != "HEAD" {
+        let mut response = Response::status(405);
+        response.headers.push(("Allow".into(), "GET, HEAD".into()));
+        return response;
+    }
+    let path = target.split(['?', '#']).next().unwrap_or_default();
+    let Some(path) = percent_decode(path) else {
+        return Response::status(400);
+    };
+    match path.as_str() {
+        "/" => return document(&index(served), "text/plain", if_none_match),
+        "/ffpack.json" => return document(&served.manifest, "application/json", if_none_match),
+        "/ffpack.lock" => return document(&served.lockfile, "application/json", if_none_match),
+        _ => {}
+    }
+    if let Some(file) = path.strip_prefix("/files/") {
+        if let Some(found) = served.files.get(file) {
+            return artifact(found, file, false, if_none_match);
+        }
+    }
+    if let Some(blob) = path.strip_prefix("/blobs/") {
+        if let Some(found) = served.blobs.get(blob) {
+            return artifact(found, blob, true, if_none_match);
+        }
+    }
+    Response::status(404)
+}
+
+/// The parts of a request head that serving it needs
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Head {
+    /// The request line, as `<method> <target> <version>`
+    request_line: String,
+    /// The `If-None-Match` header, if there is one
+    if_none_match: Option<String>,
+}
+
+/// Reads a line of a request head, returning `None` if it is longer than [`MAX_LINE`]
+fn read_line(reader: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut line = String::new();
+    reader.take(MAX_LINE as u64).read_line(&mut line)?;
+    Ok((line.len() < MAX_LINE || line.ends_with('\n')).then_some(line))
+}
+
+/// Reads a request head from `reader`, returning `None` if it is beyond the limits on its lines,
+/// headers, or length
+fn read_head(reader: &mut impl BufRead) -> io::Result<Option<Head>> {
+    let Some(request_line) = read_line(reader)? else {
+        return Ok(None);
+    };
+    let mut head = Head {
+        request_line,
+        if_none_match: None,
+    };
+    let mut length = head.request_line.len();
+    for _ in 0..=MAX_HEADERS {
+        let Some(line) = read_line(reader)? else {
+            return Ok(None);
+        };
+        length += line.len();
+        if length > MAX_HEAD {
+            return Ok(None);
+        }
+        if line.trim().is_empty() {
+            return Ok(Some(head));
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            // Repeating the header is the same as listing its tags in one
+            if name.trim().eq_ignore_ascii_case("if-none-match") {
+                let tags = match head.if_none_match.take() {
+                    Some(tags) => format!("{tags}, {}", value.trim()),
+                    None => value.trim().to_string(),
+                };
+                head.if_none_match = Some(tags);
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Reads a request from `stream` and writes the response
+fn handle(served: &Served, stream: TcpStream) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let Some(head) = read_head(&mut reader)? else {
+        tracing::info!("Refused a request with too large a head");
+        return write_response(stream, false, &Response::status(431));
+    };
+    let mut parts = head.request_line.split_whitespace();
+    let (head_only, response) = match (parts.next(), parts.next()) {
+        (Some(method), Some(target)) => {
+            let response = respond(served, method, target, head.if_none_match.as_deref());
+            tracing::info!("{method} {target} {}", response.status);
+            (method == "HEAD", response)
+        }
+        _ => (false, Response::status(400)),
+    };
+    write_response(stream, head_only, &response)
+}
+
+/// Writes `response` to `stream`, leaving its body out if `head_only` is set
+fn write_response(stream: TcpStream, head_only: bool, response: &Response) -> io::Result<()> {
+    let length = match &response.body {
+        Body::Bytes(bytes) => bytes.len() as u64,
+        Body::File(path) => fs::metadata(path)?.len(),
+    };
+    let mut stream = io::BufWriter::new(stream);
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Length: {length}\r\nConnection: close\r\n",
+        response.status,
+        reason(response.status)
+    )?;
+    for (name, value) in &response.headers {
+        write!(stream, "{name}: {value}\r\n")?;
+    }
+    stream.write_all(b"\r\n")?;
+    if !head_only && response.status != 304 {
+        match &response.body {
+            Body::Bytes(bytes) => stream.write_all(bytes)?,
+            Body::File(path) => {
+                io::copy(&mut File::open(path)?, &mut stream)?;
+            }
+        }
+    }
+    stream.flush()
+}
+
+/// Answers the connection `stream` on a thread of its own, or turns it away if
+/// [`MAX_CONNECTIONS`] are already open, counting them in `open`
+fn accept(served: &Arc<Served>, open: &Arc<AtomicUsize>, stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    if open.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+        open.fetch_sub(1, Ordering::SeqCst);
+        tracing::warn!("Turned a connection away, with {MAX_CONNECTIONS} already open");
+        let mut response = Response::status(503);
+        response.headers.push(("Retry-After".into(), "1".into()));
+        return write_response(stream, false, &response);
+    }
+    let (served, open) = (Arc::clone(served), Arc::clone(open));
+    thread::spawn(move || {
+        if let Err(error) = handle(&served, stream) {
+            tracing::warn!("Failed to answer a request: {error}");
+        }
+        open.fetch_sub(1, Ordering::SeqCst);
+    });
+    Ok(())
+}
+
+/// Runs `ffpack serve`, serving the manifest in use until the process is stopped
+pub fn run(global: &Global, mut options: Options) -> Result<(), CliError> {
+    let address = options
+        .value("--bind", None)
+        .context(UsageSnafu)?
+        .unwrap_or_else(|| DEFAULT_ADDRESS.into());
+    options.finish().context(UsageSnafu)?;
+    let (manifest, pack) = global.load()?;
+    let lock = lock::ensure(&manifest, &pack)?;
+
+    let mut served = Served {
+        manifest: fs::read(&manifest).context(ReadSnafu { path: &manifest })?,
+        ..Served::default()
+    };
+    let lock_path = Lockfile::path_for(&manifest);
+    served.lockfile = fs::read(&lock_path).context(ReadSnafu { path: &lock_path })?;
+    let mut jobs = Vec::new();
+    for file in &pack.managed_files {
+        let locked = lock
+            .get(&file.path)
+            .context(UnlockedSnafu { path: &file.path })?;
+        let Some(cached) = cache::path(&locked.artifact) else {
+            eprintln!(
+                "warning: {} isn't served, having no digest to be cached by",
+                file.path
+            );
+            continue;
+        };
+        let found = Arc::new(Artifact {
+            cached,
+            hashes: locked.artifact.hashes.clone(),
+        });
+        for algorithm in found.hashes.algorithms() {
+            let digest = found.hashes.hex(algorithm).unwrap_or_default();
+            let key = format!("{}/{digest}", algorithm.name());
+            served.blobs.insert(key, Arc::clone(&found));
+        }
+        served.files.insert(file.path.to_string(), found);
+        jobs.push(DownloadJob {
+            artifact: locked.artifact.clone(),
+            path: file.install_path(),
+        });
+    }
+    let fetched = cache::fill(&jobs)?;
+    eprintln!(
+        "Cached {} files, {} of which were already cached",
+        jobs.len(),
+        fetched.cached
+    );
+
+    let listener = TcpListener::bind(&address).context(ServeSnafu { address: &address })?;
+    let bound = listener
+        .local_addr()
+        .context(ServeSnafu { address: &address })?;
+    println!("Serving {} files on http://{bound}/", served.files.len());
+    let served = Arc::new(served);
+    let open = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let accepted = stream.and_then(|stream| accept(&served, &open, stream));
+        if let Err(error) = accepted {
+            tracing::warn!("Failed to accept a connection: {error}");
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    // Artifacts are found by path and by digest, with their digests as headers, and revalidate
+    #[test]
+    fn respond() {
+        let mut hashes = Hashes::default();
+        hashes.set(HashAlgorithm::Sha1, &[0xab; 20]);
+        let found = Arc::new(Artifact {
+            cached: "cached/sha1/abab".into(),
+            hashes,
+        });
+        let mut served = Served {
+            manifest: b"{}".to_vec(),
+            ..Served::default()
+        };
+        served
+            .files
+            .insert("mods/Sodium Extra.jar".into(), Arc::clone(&found));
+        served
+            .blobs
+            .insert(format!("sha1/{}", "ab".repeat(20)), found);
+
+        let response = super::respond(&served, "GET", "/files/mods/Sodium%20Extra.jar", None);
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, Body::File("cached/sha1/abab".into()));
+        let etag = format!("\"sha1:{}\"", "ab".repeat(20));
+        assert!(response.headers.contains(&("ETag".into(), etag.clone())));
+        assert!(response
+            .headers
+            .contains(&("X-Checksum-Sha1".into(), "ab".repeat(20))));
+        let blob = format!("/blobs/sha1/{}", "ab".repeat(20));
+        let response = super::respond(&served, "HEAD", &blob, Some(&etag));
+        assert_eq!(response.status, 304);
+        let listed = format!("\"other\", W/{etag}");
+        let response = super::respond(&served, "GET", &blob, Some(&listed));
+        assert_eq!(response.status, 304);
+        let response = super::respond(&served, "GET", &blob, Some("*"));
+        assert_eq!(response.status, 304);
+        let response = super::respond(&served, "GET", &blob, Some("\"other\""));
+        assert_eq!(response.status, 200);
+
+        assert_eq!(
+            super::respond(&served, "GET", "/ffpack.json", None).status,
+            200
+        );
+        assert_eq!(
+            super::respond(&served, "GET", "/files/other.jar", None).status,
+            404
+        );
+        assert_eq!(
+            super::respond(&served, "PUT", "/ffpack.json", None).status,
+            405
+        );
+    }
+
+    // Request heads are read up to a blank line, refusing long lines and too many headers
+    #[test]
+    fn read_head() {
+        let mut request = io::Cursor::new(
+            "GET / HTTP/1.1\r\nIf-None-Match: \"a\"\r\nif-none-match: \"b\"\r\n\r\n",
+        );
+        assert_eq!(
+            super::read_head(&mut request).unwrap(),
+            Some(Head {
+                request_line: "GET / HTTP/1.1\r\n".into(),
+                if_none_match: Some("\"a\", \"b\"".into()),
+            })
+        );
+        let long = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_LINE));
+        assert_eq!(super::read_head(&mut io::Cursor::new(long)).unwrap(), None);
+        let many = format!(
+            "GET / HTTP/1.1\r\n{}\r\n",
+            "X: y\r\n".repeat(MAX_HEADERS + 1)
+        );
+        assert_eq!(super::read_head(&mut io::Cursor::new(many)).unwrap(), None);
+        let enough = format!("GET / HTTP/1.1\r\n{}\r\n", "X: y\r\n".repeat(MAX_HEADERS));
+        assert!(super::read_head(&mut io::Cursor::new(enough))
+            .unwrap()
+            .is_some());
+    }
+}
@@ -0,0 +1,241 @@
+//! `ffpack update`: resolving floating sources again, and reporting what changed for update bots
+//!
+//! Every file is resolved again, or only the files named, and the lockfile is saved as `ffpack
+//! lock` saves it. `--write-branch-report <PATH>` also writes what changed to `PATH` as JSON,
+//! shaped for bots that open a pull request with the update: a title and Markdown body for the
+//! pull request, and each changed file with its old and new versions, its project's page, and
+//! the changelog of its new version. The report is written even when nothing changed, and the
+//! exit status tells the two apart: 0 when the pack was up to date, and [`UPDATED_STATUS`] when
+//! the lockfile changed. Failures keep the statuses every command fails with.
+
+use std::fs;
+
+use ffpack::{
+    lock::{selecting, Lockfile},
+    Pack,
+};
+use serde_json::{json, Value};
+use snafu::{ensure, ResultExt};
+
+use crate::{
+    args::{Global, Options},
+    ci,
+    lock::{self, FileChange},
+    CliError, NotFoundSnafu, UpdatedSnafu, UsageSnafu, WriteSnafu,
+};
+
+/// The exit status of `ffpack update --write-branch-report` when the lockfile changed
+pub const UPDATED_STATUS: u8 = 3;
+
+/// Returns what kind of change `change` is, as the report names it
+fn kind(change: &FileChange<'_>) -> &'static str {
+    match (change.old.map(lock::label), change.new.map(lock::label)) {
+        (None, _) => "added",
+        (_, None) => "removed",
+        (old, new) if old == new => "changed",
+        _ => "updated",
+    }
+}
+
+/// Returns the name of the file `change` is about, from its project, the manifest, or its path
+fn name(pack: &Pack, change: &FileChange<'_>) -> String {
+    let locked = change.new.or(change.old);
+    locked
+        .and_then(|locked| locked.artifact.details.name.clone())
+        .or_else(|| {
+            pack.managed_files
+                .iter()
+                .find(|file| file.path == *change.path)
+                .and_then(|file| file.name.clone())
+        })
+        .unwrap_or_else(|| change.path.to_string())
+}
+
+/// Builds the branch report of updating `pack` from `previous` to `next`
+fn report(pack: &Pack, previous: &Lockfile, next: &Lockfile) -> Value {
+    let changes = lock::file_changes(previous, next);
+    let files: Vec<_> = changes
+        .iter()
+        .map(|change| {
+            let locked = change.new.or(change.old);
+            json!({
+                "path": change.path,
+                "name": name(pack, change),
+                "change": kind(change),
+                "old": change.old.map(lock::label),
+                "new": change.new.map(lock::label),
+                "url": locked.and_then(|locked| locked.artifact.details.url.as_ref()),
+                "changelog": change.new.and_then(|new| new.artifact.details.changelog.as_ref()),
+            })
+        })
+        .collect();
+    let versions_changed = previous.versions != next.versions && !previous.files.is_empty();
+    let pack_name = pack.metadata.name(None);
+    let title = match changes.as_slice() {
+        [] if !versions_changed => format!("{pack_name} is up to date"),
+        [] => format!(
+            "Update {pack_name} to minecraft {}",
+            next.versions.minecraft
+        ),
+        [change] if kind(change) == "updated" => format!(
+            "Update {} to {}",
+            name(pack, change),
+            change.new.map(lock::label).unwrap_or_default()
+        ),
+        changes => format!("Update {} files of {pack_name}", changes.len()),
+    };
+    let rows: Vec<_> = changes
+        .iter()
+        .map(|change| {
+            [
+                name(pack, change),
+                change.old.map(lock::label).unwrap_or_default(),
+                change.new.map(lock::label).unwrap_or_default(),
+                change
+                    .new
+                    .and_then(|new| new.artifact.details.changelog.as_ref())
+                    .map(|changelog| format!("[changelog]({changelog})"))
+                    .unwrap_or_default(),
+            ]
+        })
+        .collect();
+    let mut body = format!("Updates {pack_name}, as `ffpack update` resolved it.\n");
+    if versions_changed {
+        body.push_str(&format!(
+            "\nThe pack now runs on minecraft {} with {} {}.\n",
+            next.versions.minecraft,
+            next.versions.loader.name(),
+            next.versions.loader.version()
+        ));
+    }
+    if !rows.is_empty() {
+        body.push('\n');
+        body.push_str(&ci::table(["File", "From", "To", "Changes"], &rows));
+    }
+    json!({
+        "updated": !changes.is_empty() || versions_changed,
+        "title": title,
+        "body": body,
+        "versions": versions_changed.then_some(&next.versions),
+        "files": files,
+    })
+}
+
+/// Runs `ffpack update [FILE]...`, resolving floating sources again, everywhere or only for the
+/// files named by name, path, or directory
+///
+/// Files are named as arguments rather than with `-p <name>`, which is the global `--pack`.
+pub fn run(global: &Global, mut options: Options) -> Result<(), CliError> {
+    let report_path = options
+        .value("--write-branch-report", None)
+        .context(UsageSnafu)?;
+    let mut names = Vec::new();
+    while let Some(name) = options.positional() {
+        names.push(name);
+    }
+    options.finish().context(UsageSnafu)?;
+    let (manifest, pack) = global.load()?;
+    let (previous, next) = if names.is_empty() {
+        lock::relock(&manifest, &pack, |_| true)?
+    } else {
+        for name in &names {
+            let name = [name.as_str()];
+            let selects = selecting(&name);
+            ensure!(
+                pack.managed_files.iter().any(selects),
+                NotFoundSnafu { given: name[0] }
+            );
+        }
+        let names: Vec<_> = names.iter().map(String::as_str).collect();
+        lock::relock(&manifest, &pack, selecting(&names))?
+    };
+    let Some(path) = report_path else {
+        return Ok(());
+    };
+    let report = report(&pack, &previous, &next);
+    let json = serde_json::to_string_pretty(&report).expect("Values serialize to JSON");
+    fs::write(&path, json + "\n").context(WriteSnafu { path: &path })?;
+    ensure!(
+        report["updated"] == false,
+        UpdatedSnafu {
+            count: report["files"].as_array().map_or(0, Vec::len),
+            path,
+        }
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use ffpack::{
+        lock::LockedFile,
+        resolve::{ProjectDetails, ResolvedArtifact, UpstreamIds},
+        types::{Hashes, Source},
+    };
+    use relative_path::RelativePathBuf;
+    use url::Url;
+
+    use super::*;
+
+    /// Locks Sodium at a version, with the changelog of that version
+    fn sodium(version: &str) -> LockedFile {
+        LockedFile {
+            source: Source::default(),
+            artifact: ResolvedArtifact {
+                download_url: Url::parse("https://example.org/sodium.jar").unwrap(),
+                filename: "sodium.jar".into(),
+                details: ProjectDetails {
+                    name: Some("Sodium".into()),
+                    version: Some(version.into()),
+                    url: Some(Url::parse("https://modrinth.com/mod/sodium").unwrap()),
+                    changelog: Some(
+                        Url::parse(&format!(
+                            "https://modrinth.com/mod/sodium/version/{version}"
+                        ))
+                        .unwrap(),
+                    ),
+                    ..ProjectDetails::default()
+                },
+                size: None,
+                hashes: Hashes::default(),
+                mirrors: Vec::new(),
+                extract: None,
+                ids: UpstreamIds::default(),
+            },
+        }
+    }
+
+    // Each changed file is reported with both versions and its links, under a pull request title
+    #[test]
+    fn report() {
+        let pack = Pack::default();
+        let path = RelativePathBuf::from("mods/sodium.jar");
+        let mut previous = Lockfile::default();
+        previous.files.insert(path.clone(), sodium("0.5.0"));
+        let report = super::report(&pack, &previous, &previous);
+        assert_eq!(report["updated"], false);
+        assert_eq!(report["files"], json!([]));
+
+        let mut next = previous.clone();
+        next.files.insert(path, sodium("0.5.3"));
+        let report = super::report(&pack, &previous, &next);
+        assert_eq!(report["updated"], true);
+        assert_eq!(report["title"], "Update Sodium to 0.5.3");
+        assert_eq!(
+            report["files"],
+            json!([{
+                "path": "mods/sodium.jar",
+                "name": "Sodium",
+                "change": "updated",
+                "old": "0.5.0",
+                "new": "0.5.3",
+                "url": "https://modrinth.com/mod/sodium",
+                "changelog": "https://modrinth.com/mod/sodium/version/0.5.3",
+            }])
+        );
+        assert!(report["body"]
+            .as_str()
+            .unwrap()
+            .contains("| Sodium | 0.5.0 | 0.5.3 | [changelog]("));
+    }
+}
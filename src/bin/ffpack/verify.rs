@@ -0,0 +1,95 @@
+//! `ffpack verify`: checking an installed instance against the lockfile
+//!
+//! Every file the install selects is hashed against what was locked for it, and the folders
+//! they go in are checked for files the pack doesn't have, which is usually where "it crashes
+//! for me" comes from. With `--fix`, missing and modified files are put back, from the cache
+//! where it still has them. Orphans are only reported, since they may be the player's own. In CI
+//! mode, the problems left annotate the job, and the counts are summarized on its page.
+
+use std::path::PathBuf;
+
+use ffpack::install::{self, PathRules, Problem};
+use snafu::{ensure, ResultExt};
+
+use crate::{
+    args::{Global, Options},
+    cache,
+    ci::{self, Level},
+    install::{jobs, Selection},
+    lock, CliError, InstallSnafu, ProblemsSnafu, UsageSnafu, WriteSnafu,
+};
+
+/// Runs `ffpack verify <DIR>`, checking the instance at `DIR` against the manifest in use
+pub fn run(global: &Global, mut options: Options) -> Result<(), CliError> {
+    let selection = Selection::parse(&mut options)?;
+    let fix = options.flag("--fix", None);
+    let instance = PathBuf::from(options.required("DIR").context(UsageSnafu)?);
+    options.finish().context(UsageSnafu)?;
+    let (manifest, pack) = global.load()?;
+    let lock = lock::ensure(&manifest, &pack)?;
+    let mut jobs = jobs(&pack, &lock, &selection)?;
+    install::prepare(&mut jobs, &PathRules::default()).context(InstallSnafu)?;
+
+    let mut problems = install::verify(&instance, &jobs).context(InstallSnafu)?;
+    for problem in &problems {
+        println!("{problem}");
+    }
+    if fix {
+        let broken: Vec<_> = jobs
+            .iter()
+            .filter(|job| {
+                problems.iter().any(|problem| {
+                    matches!(problem, Problem::Missing { .. } | Problem::Modified { .. })
+                        && problem.path() == job.path
+                })
+            })
+            .cloned()
+            .collect();
+        for job in &broken {
+            let local = job.path.to_path(&instance);
+            if local.exists() {
+                std::fs::remove_file(&local).context(WriteSnafu { path: &local })?;
+            }
+        }
+        let fetched = cache::fetch(&broken, &instance)?;
+        println!(
+            "Fixed {} files ({} downloaded, {} from the cache)",
+            broken.len(),
+            fetched.downloaded,
+            fetched.cached
+        );
+        problems = install::verify(&instance, &jobs).context(InstallSnafu)?;
+    }
+    let missing = problems
+        .iter()
+        .filter(|problem| matches!(problem, Problem::Missing { .. }))
+        .count();
+    let modified = problems
+        .iter()
+        .filter(|problem| matches!(problem, Problem::Modified { .. }))
+        .count();
+    let orphaned = problems.len() - missing - modified;
+    let counts = format!(
+        "{} files checked: {missing} missing, {modified} modified, {orphaned} orphaned",
+        jobs.len()
+    );
+    println!("{counts}");
+    for problem in &problems {
+        let level = match problem {
+            Problem::Missing { .. } | Problem::Modified { .. } => Level::Error,
+            _ => Level::Warning,
+        };
+        ci::annotate(level, None, "ffpack verify", &problem.to_string());
+    }
+    ci::summarize(&format!(
+        "## ffpack verify\n\n{counts} in {}\n",
+        instance.display()
+    ))?;
+    ensure!(
+        problems.is_empty(),
+        ProblemsSnafu {
+            count: problems.len()
+        }
+    );
+    Ok(())
+}
@@ -0,0 +1,187 @@
+//! Following Modrinth collections, which are curated lists of projects, as sources of files
+//!
+//! A pack following a collection carries a [`Source::Modrinth`] file for each of the collection's
+//! projects, marked with the collection's id in [`ManagedFile::collection`]. Syncing compares the
+//! collection as [`Resolver::modrinth_collection`](crate::resolve::Resolver::modrinth_collection)
+//! finds it with the pack: projects added upstream are added to the pack, and files from the
+//! collection whose projects left it are removed. Projects the pack already has are left as they
+//! are, however they were added, and files added by hand are never removed.
+
+use std::collections::BTreeMap;
+
+use relative_path::RelativePathBuf;
+use url::Url;
+
+use crate::{
+    resolve::SearchHit,
+    types::{ManagedFile, Source},
+    Pack,
+};
+
+/// A Modrinth collection, as [`Resolver::modrinth_collection`] finds it
+///
+/// [`Resolver::modrinth_collection`]: crate::resolve::Resolver::modrinth_collection
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Collection {
+    /// The collection's id
+    pub id: String,
+    /// The collection's display name
+    pub name: String,
+    /// The projects in it that packs can carry, by their project ids
+    pub projects: BTreeMap<String, SearchHit>,
+}
+
+/// How syncing a collection changes a pack
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct CollectionChanges {
+    /// The projects to add, by their project ids
+    pub added: BTreeMap<String, SearchHit>,
+    /// The paths of the files to remove
+    pub removed: Vec<RelativePathBuf>,
+}
+
+impl CollectionChanges {
+    /// Returns true if the pack already follows the collection as it is
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Returns the id of the collection `input` names, given either as its id or its page
+///
+/// Returns `None` if `input` is neither
+pub fn collection_id(input: &str) -> Option<String> {
+    let Ok(url) = Url::parse(input) else {
+        let valid = !input.is_empty() && input.chars().all(|c| c.is_ascii_alphanumeric());
+        return valid.then(|| input.to_string());
+    };
+    let host = url.host_str()?;
+    let host = host.strip_prefix("www.").unwrap_or(host);
+    let mut segments = url.path_segments()?;
+    if host != "modrinth.com" || segments.next() != Some("collection") {
+        return None;
+    }
+    segments
+        .next()
+        .filter(|id| !id.is_empty())
+        .map(String::from)
+}
+
+/// Returns the Modrinth project `file` comes from, by the slug or id its source names
+fn project(file: &ManagedFile) -> Option<&str> {
+    match &file.source {
+        Source::Modrinth { slug, .. } => Some(slug),
+        _ => None,
+    }
+}
+
+/// Returns true if `project`, a slug or id, names the project with the id `id` found as `hit`
+fn is_project(id: &str, hit: &SearchHit, project: &str) -> bool {
+    id == project || matches!(&hit.source, Source::Modrinth { slug, .. } if slug == project)
+}
+
+impl Collection {
+    /// Returns what syncing the collection would add to and remove from `pack`
+    pub fn changes(&self, pack: &Pack) -> CollectionChanges {
+        let added = self
+            .projects
+            .iter()
+            .filter(|(id, hit)| {
+                !pack
+                    .managed_files
+                    .iter()
+                    .filter_map(project)
+                    .any(|project| is_project(id, hit, project))
+            })
+            .map(|(id, hit)| (id.clone(), hit.clone()))
+            .collect();
+        let listed = |project: &str| {
+            self.projects
+                .iter()
+                .any(|(id, hit)| is_project(id, hit, project))
+        };
+        let removed = pack
+            .managed_files
+            .iter()
+            .filter(|file| file.collection.as_deref() == Some(self.id.as_str()))
+            .filter(|file| !project(file).is_some_and(listed))
+            .map(|file| file.path.clone())
+            .collect();
+        CollectionChanges { added, removed }
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    /// A project in a collection
+    fn hit(slug: &str) -> SearchHit {
+        SearchHit {
+            source: Source::Modrinth {
+                slug: slug.into(),
+                version_id: None,
+            },
+            name: slug.into(),
+            description: None,
+            downloads: 0,
+            folder: "mods",
+        }
+    }
+
+    /// A file from a Modrinth project, added from `collection` if there is one
+    fn file(slug: &str, collection: Option<&str>) -> ManagedFile {
+        ManagedFile {
+            path: RelativePathBuf::from(format!("mods/{slug}.jar")),
+            source: Source::Modrinth {
+                slug: slug.into(),
+                version_id: None,
+            },
+            collection: collection.map(String::from),
+            ..ManagedFile::default()
+        }
+    }
+
+    // New projects are added, departed ones removed, and files added by hand left alone
+    #[test]
+    fn changes() {
+        let collection = Collection {
+            id: "abc123".into(),
+            name: "Performance".into(),
+            projects: [
+                ("AANobbMI".into(), hit("sodium")),
+                ("gvQqBUqZ".into(), hit("lithium")),
+                ("H8CaAYZC".into(), hit("starlight")),
+            ]
+            .into_iter()
+            .collect(),
+        };
+        let pack = Pack {
+            managed_files: [
+                file("sodium", Some("abc123")),
+                file("gvQqBUqZ", None),
+                file("phosphor", Some("abc123")),
+                file("phosphor-fork", Some("other")),
+                file("modmenu", None),
+            ]
+            .into_iter()
+            .collect(),
+            ..Pack::default()
+        };
+        let changes = collection.changes(&pack);
+        assert_eq!(changes.added.keys().collect::<Vec<_>>(), ["H8CaAYZC"]);
+        assert_eq!(
+            changes.removed,
+            [RelativePathBuf::from("mods/phosphor.jar")]
+        );
+        assert!(!changes.is_empty());
+
+        assert_eq!(collection_id("abc123").as_deref(), Some("abc123"));
+        assert_eq!(
+            collection_id("https://modrinth.com/collection/abc123").as_deref(),
+            Some("abc123")
+        );
+        assert_eq!(collection_id("https://modrinth.com/mod/sodium"), None);
+        assert_eq!(collection_id("not an id"), None);
+    }
+}
@@ -0,0 +1,1380 @@
+//! Fetching resolved artifacts into a directory
+//!
+//! The [`Downloader`] runs a bounded number of downloads at once on whatever executor polls it,
+//! reporting progress through a callback and stopping early when cancelled. Files are written
+//! to a `.part` file next to their destination, hashed as they arrive, and only renamed into
+//! place once complete and verified. A `.part` file left by an interrupted transfer is picked
+//! up where it stopped, when the server supports range requests. Artifacts that come inside a
+//! zip archive, like CI artifacts, are verified as the archive and then taken out of it.
+//! Files that are gone from everywhere they were published can be fetched from the Internet
+//! Archive instead, when their hash was pinned to check the archived copy against.
+
+use std::{
+    ffi::OsString,
+    fmt::Debug,
+    fs::{self, File, OpenOptions},
+    future::{poll_fn, Future},
+    io::{self, Read, Write},
+    iter,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    task::Poll,
+    time::{Duration, Instant},
+};
+
+use regex::Regex;
+use relative_path::RelativePathBuf;
+use snafu::{ensure, OptionExt, ResultExt, Snafu};
+use tracing::{debug, instrument, warn};
+use url::Url;
+
+use crate::{
+    archive::{ZipArchive, ZipError},
+    hash::Hasher,
+    http::{
+        Body, Bucket, HttpClient, HttpError, NetworkPolicy, Request, Response, Sleep, ThreadSleep,
+    },
+    install::is_contained,
+    resolve::{Credentials, ResolvedArtifact},
+    types::{HashAlgorithm, Hashes},
+};
+
+/// The host whose downloads are sent the GitHub token
+const GITHUB_API_HOST: &str = "api.github.com";
+
+/// Where the Wayback Machine serves archived files from
+const WAYBACK: &str = "https://web.archive.org/web/";
+
+/// The timestamp archived copies are asked for at
+///
+/// A partial timestamp is padded out to the latest possible one, which picks the newest
+/// capture; `id_` asks for the file as captured rather than wrapped in the archive's page.
+const WAYBACK_TIMESTAMP: &str = "2id_";
+
+/// Size of the chunks local files are copied in
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// An artifact to download, and where to put it
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DownloadJob {
+    /// The artifact to download
+    pub artifact: ResolvedArtifact,
+    /// Where to put it, relative to the target directory
+    pub path: RelativePathBuf,
+}
+
+/// A snapshot of download progress
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Progress {
+    /// The index of the job this update is for
+    pub job: usize,
+    /// Bytes downloaded so far for this job
+    pub file_done: u64,
+    /// The size of this job's file, if known
+    pub file_total: Option<u64>,
+    /// Bytes downloaded so far across all jobs
+    pub total_done: u64,
+    /// The size of all files together, if every size is known
+    pub total: Option<u64>,
+}
+
+/// A callback receiving progress updates
+pub type ProgressCallback = Arc<dyn Fn(Progress) + Send + Sync>;
+
+/// A handle for cancelling downloads from elsewhere
+///
+/// Clones share the same state, so one clone can be handed to the downloader and another kept
+/// to cancel with.
+#[derive(Debug, Clone, Default)]
+pub struct Cancellation(Arc<AtomicBool>);
+
+impl Cancellation {
+    /// Creates a handle that hasn't been cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the downloads using this handle
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns true if [`Cancellation::cancel`] has been called
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Options controlling a [`Downloader`]
+#[derive(Clone)]
+pub struct DownloadOptions {
+    /// How many files to download at once
+    parallelism: usize,
+    /// Called as bytes arrive
+    progress: Option<ProgressCallback>,
+    /// Checked between chunks to stop early
+    cancellation: Cancellation,
+    /// Whether downloads may use the network
+    network: NetworkPolicy,
+    /// Whether interrupted downloads are continued where they left off
+    resume: bool,
+    /// The most bytes a second fetched across all downloads of a run
+    max_bytes_per_second: Option<u64>,
+    /// The tokens sent with downloads that need them
+    credentials: Credentials,
+    /// Whether files that are gone are looked for in the Wayback Machine
+    wayback: bool,
+}
+
+impl DownloadOptions {
+    /// Sets how many files are downloaded at once, with values below 1 treated as 1
+    #[must_use]
+    pub fn with_parallelism(mut self, parallelism: usize) -> Self {
+        self.parallelism = parallelism.max(1);
+        self
+    }
+
+    /// Sets the callback receiving progress updates
+    #[must_use]
+    pub fn with_progress(mut self, progress: impl Fn(Progress) + Send + Sync + 'static) -> Self {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    /// Sets the handle that cancels the downloads
+    #[must_use]
+    pub fn with_cancellation(mut self, cancellation: Cancellation) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    /// Sets whether downloads may use the network
+    ///
+    /// Offline, files are copied from `file://` urls and served from the client's cache, and
+    /// anything else fails.
+    #[must_use]
+    pub fn with_network(mut self, network: NetworkPolicy) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// Returns whether downloads may use the network
+    pub fn network(&self) -> NetworkPolicy {
+        self.network
+    }
+
+    /// Sets whether interrupted downloads are continued where they left off, which is on by
+    /// default
+    ///
+    /// Only files with known digests are resumed, with HTTP range requests, so that the joined
+    /// file can be verified like any other. A file that fails verification is removed, and
+    /// downloaded in full the next time.
+    #[must_use]
+    pub fn with_resume(mut self, resume: bool) -> Self {
+        self.resume = resume;
+        self
+    }
+
+    /// Caps how fast files are fetched, in bytes a second shared between all the downloads
+    /// running at once, with values below 1 treated as 1
+    ///
+    /// Copies of local files aren't limited. Waiting uses the downloader's [`Sleep`], which
+    /// should be the runtime's own timer under an async runtime.
+    #[must_use]
+    pub fn with_max_bytes_per_second(mut self, bytes: u64) -> Self {
+        self.max_bytes_per_second = Some(bytes.max(1));
+        self
+    }
+
+    /// Returns the cap on how fast files are fetched, if there is one
+    pub fn max_bytes_per_second(&self) -> Option<u64> {
+        self.max_bytes_per_second
+    }
+
+    /// Sets the tokens sent with downloads that need them
+    ///
+    /// Only the GitHub token is used, for CI artifacts downloaded from the GitHub API, which
+    /// can't be downloaded without one.
+    #[must_use]
+    pub fn with_credentials(mut self, credentials: Credentials) -> Self {
+        self.credentials = credentials;
+        self
+    }
+
+    /// Sets whether files that are gone upstream are fetched from the Wayback Machine, which is
+    /// off by default
+    ///
+    /// Only files with a pinned blake3 hash are looked for, once the download url and every
+    /// mirror failed with the last answering 404 or 410. The archived copy has to match the
+    /// hash like any other download.
+    #[must_use]
+    pub fn with_wayback(mut self, wayback: bool) -> Self {
+        self.wayback = wayback;
+        self
+    }
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        Self {
+            parallelism: 4,
+            progress: None,
+            cancellation: Cancellation::default(),
+            network: NetworkPolicy::default(),
+            resume: true,
+            max_bytes_per_second: None,
+            credentials: Credentials::default(),
+            wayback: false,
+        }
+    }
+}
+
+impl Debug for DownloadOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DownloadOptions")
+            .field("parallelism", &self.parallelism)
+            .field("progress", &self.progress.is_some())
+            .field("cancellation", &self.cancellation)
+            .field("network", &self.network)
+            .field("resume", &self.resume)
+            .field("max_bytes_per_second", &self.max_bytes_per_second)
+            .field("credentials", &self.credentials)
+            .field("wayback", &self.wayback)
+            .finish()
+    }
+}
+
+/// Downloads artifacts using an [`HttpClient`]
+#[derive(Debug)]
+pub struct Downloader<C, S = ThreadSleep> {
+    /// The client used for downloads
+    client: C,
+    /// How downloads are run
+    options: DownloadOptions,
+    /// How to wait when over the bandwidth cap
+    sleep: S,
+}
+
+/// Tracks progress shared between the jobs of one run
+struct Tracker<'a> {
+    /// The options of the run
+    options: &'a DownloadOptions,
+    /// Bytes downloaded across all jobs
+    total_done: AtomicU64,
+    /// Bytes reported so far for each job
+    file_done: Vec<AtomicU64>,
+    /// The size of all files together, if known
+    total: Option<u64>,
+    /// Bytes allowed through under the bandwidth cap, if there is one
+    throttle: Option<Mutex<Bucket>>,
+}
+
+impl Tracker<'_> {
+    /// Records bytes arriving for a job, and reports them
+    fn advance(&self, job: usize, file_done: u64, file_total: Option<u64>, bytes: u64) {
+        let total_done = self.total_done.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        self.file_done[job].store(file_done, Ordering::Relaxed);
+        if let Some(progress) = &self.options.progress {
+            progress(Progress {
+                job,
+                file_done,
+                file_total,
+                total_done,
+                total: self.total,
+            });
+        }
+    }
+
+    /// Takes back the bytes reported for a job that is about to be fetched again
+    fn rewind(&self, job: usize) {
+        let done = self.file_done[job].swap(0, Ordering::Relaxed);
+        self.total_done.fetch_sub(done, Ordering::Relaxed);
+    }
+
+    /// Counts fetched bytes against the bandwidth cap, returning how long to wait before
+    /// fetching more
+    fn throttle(&self, bytes: usize) -> Duration {
+        self.throttle.as_ref().map_or(Duration::ZERO, |bucket| {
+            let mut bucket = bucket.lock().expect("Throttle isn't poisoned");
+            #[allow(clippy::cast_precision_loss)]
+            bucket.take(bytes as f64, Instant::now())
+        })
+    }
+}
+
+impl<C: HttpClient> Downloader<C> {
+    /// Creates a downloader, waiting when over the bandwidth cap by blocking the thread
+    pub fn new(client: C, options: DownloadOptions) -> Self {
+        Self::with_sleep(client, options, ThreadSleep)
+    }
+}
+
+impl<C: HttpClient, S: Sleep> Downloader<C, S> {
+    /// Creates a downloader, waiting when over the bandwidth cap with `sleep`
+    pub fn with_sleep(client: C, options: DownloadOptions, sleep: S) -> Self {
+        Self {
+            client,
+            options,
+            sleep,
+        }
+    }
+
+    /// Returns the client used for downloads
+    pub fn client(&self) -> &C {
+        &self.client
+    }
+
+    /// Downloads every job into `target`, returning the path each file was written to
+    ///
+    /// A failing job doesn't stop the others; results are in the same order as `jobs`.
+    #[instrument(skip(self, jobs), fields(jobs = jobs.len(), target = %target.display()))]
+    pub async fn download(
+        &self,
+        jobs: &[DownloadJob],
+        target: &Path,
+    ) -> Vec<Result<PathBuf, DownloadError>> {
+        let tracker = Tracker {
+            options: &self.options,
+            total_done: AtomicU64::new(0),
+            file_done: jobs.iter().map(|_| AtomicU64::new(0)).collect(),
+            total: jobs.iter().map(|job| job.artifact.size).sum(),
+            // Up to a second's worth goes through at once, after which fetching settles at the
+            // cap
+            #[allow(clippy::cast_precision_loss)]
+            throttle: self.options.max_bytes_per_second.map(|rate| {
+                let rate = rate as f64;
+                Mutex::new(Bucket::new(rate, rate))
+            }),
+        };
+        let tracker = &tracker;
+        // Collected up front, as holding the lazy iterator across awaits would make this future
+        // not Send
+        let downloads: Vec<_> = jobs
+            .iter()
+            .enumerate()
+            .map(|(index, job)| self.download_one(index, job, target, tracker))
+            .collect();
+        limit_concurrency(downloads, self.options.parallelism).await
+    }
+
+    /// Downloads a single job, falling back to its mirrors in order if it fails
+    async fn download_one(
+        &self,
+        index: usize,
+        job: &DownloadJob,
+        target: &Path,
+        tracker: &Tracker<'_>,
+    ) -> Result<PathBuf, DownloadError> {
+        // Jobs are meant to have been checked by `install::prepare`, but a path escaping the
+        // target would write anywhere, so it is checked again here
+        ensure!(
+            is_contained(&job.path),
+            OutsideSnafu {
+                path: job.path.clone()
+            }
+        );
+        let destination = job.path.to_path(target);
+        let mut partial = OsString::from(destination.clone());
+        partial.push(".part");
+        let partial = PathBuf::from(partial);
+        let artifact = &job.artifact;
+        let mut urls: Vec<Url> = iter::once(&artifact.download_url)
+            .chain(&artifact.mirrors)
+            .cloned()
+            .collect();
+        for attempt in 0.. {
+            let url = urls[attempt].clone();
+            let result = self
+                .fetch(index, job, &url, &partial, tracker)
+                .await
+                .and_then(|(written, actual)| verify(job, written, &actual))
+                .and_then(|()| match &artifact.extract {
+                    Some(pattern) => extract(job, pattern, &partial, &destination),
+                    None => fs::rename(&partial, &destination).context(IoSnafu {
+                        path: destination.clone(),
+                    }),
+                });
+            let error = match result {
+                Ok(()) => {
+                    debug!(path = %job.path, %url, "Downloaded");
+                    return Ok(destination);
+                }
+                Err(error) => error,
+            };
+            // An interrupted transfer is kept to resume from, but anything else left is
+            // useless on its own, and may not exist at all
+            let interrupted = matches!(
+                error,
+                DownloadError::Http {
+                    source: HttpError::Transport { .. }
+                }
+            );
+            if !(interrupted && self.resumable(job)) {
+                let _ = fs::remove_file(&partial);
+            }
+            // The archived copy is the last resort, tried once every url is exhausted
+            let exhausted = attempt == artifact.mirrors.len();
+            if exhausted && self.options.wayback && is_gone(&error) {
+                if let Some(archived) = wayback_url(artifact) {
+                    urls.push(archived);
+                }
+            }
+            match urls.get(attempt + 1) {
+                Some(next) if error.is_url_specific() => {
+                    warn!(path = %job.path, %url, %next, %error, "Download failed, trying a mirror");
+                    // Whatever the failed attempt reported is fetched again, or counted again
+                    // when resumed
+                    tracker.rewind(index);
+                }
+                _ => return Err(error),
+            }
+        }
+        unreachable!("Every attempt either returns or moves on to another url")
+    }
+
+    /// Writes a job's artifact from `url` to `partial`, returning the number of bytes written
+    /// and their digests under every algorithm the artifact has a known digest for
+    ///
+    /// A transfer left in `partial` by an earlier run is continued rather than started over
+    /// when resuming is allowed and the server supports it.
+    async fn fetch(
+        &self,
+        index: usize,
+        job: &DownloadJob,
+        url: &Url,
+        partial: &Path,
+        tracker: &Tracker<'_>,
+    ) -> Result<(u64, Hashes), DownloadError> {
+        let cancellation = &self.options.cancellation;
+        ensure!(!cancellation.is_cancelled(), CancelledSnafu);
+        if let Some(parent) = partial.parent() {
+            fs::create_dir_all(parent).context(IoSnafu { path: parent })?;
+        }
+        let (mut input, offset) = match url.scheme() {
+            "file" => {
+                let source = url
+                    .to_file_path()
+                    .ok()
+                    .filter(|path| path.is_absolute())
+                    .context(UnsupportedUrlSnafu { url: url.clone() })?;
+                let file = File::open(&source).context(IoSnafu { path: &source })?;
+                (Input::File(file, source), 0)
+            }
+            "http" | "https" => {
+                let (response, offset) = self.request(job, url, partial).await?;
+                (Input::Http(response), offset)
+            }
+            _ => return UnsupportedUrlSnafu { url: url.clone() }.fail(),
+        };
+        let file_total = job.artifact.size;
+        let mut hasher = Hasher::new(job.artifact.hashes.algorithms());
+        let mut output = if offset > 0 {
+            // The bytes already there count towards the digests too
+            let mut existing = File::open(partial).context(IoSnafu { path: partial })?;
+            let mut buffer = vec![0; CHUNK_SIZE];
+            loop {
+                let read = existing
+                    .read(&mut buffer)
+                    .context(IoSnafu { path: partial })?;
+                if read == 0 {
+                    break;
+                }
+                hasher.update(&buffer[..read]);
+            }
+            tracker.advance(index, offset, file_total, offset);
+            OpenOptions::new().append(true).open(partial)
+        } else {
+            File::create(partial)
+        }
+        .context(IoSnafu { path: partial })?;
+        let mut written = offset;
+        while let Some(chunk) = input.chunk().await? {
+            ensure!(!cancellation.is_cancelled(), CancelledSnafu);
+            hasher.update(&chunk);
+            output
+                .write_all(&chunk)
+                .context(IoSnafu { path: partial })?;
+            written += chunk.len() as u64;
+            tracker.advance(index, written, file_total, chunk.len() as u64);
+            if matches!(input, Input::Http(_)) {
+                let wait = tracker.throttle(chunk.len());
+                if !wait.is_zero() {
+                    self.sleep.sleep(wait).await;
+                }
+            }
+        }
+        output.sync_all().context(IoSnafu { path: partial })?;
+        Ok((written, hasher.finalize()))
+    }
+
+    /// Requests a job's artifact from `url`, asking for only the rest of it if `partial` holds
+    /// the start of it, and returns the response along with the offset its body starts at
+    async fn request(
+        &self,
+        job: &DownloadJob,
+        url: &Url,
+        partial: &Path,
+    ) -> Result<(Response<C::Body>, u64), DownloadError> {
+        let get = |start: Option<u64>| async move {
+            let mut request = Request::new(url.clone());
+            if let Some(start) = start {
+                request = request.with_header("Range", format!("bytes={start}-"));
+            }
+            if let Some(token) = &self.options.credentials.github {
+                if url.host_str() == Some(GITHUB_API_HOST) {
+                    request = request.with_header("Authorization", format!("Bearer {token}"));
+                }
+            }
+            let request = self.options.network.prepare(&self.client, request)?;
+            self.client.get(request).await
+        };
+        let existing = if self.resumable(job) {
+            fs::metadata(partial).map_or(0, |metadata| metadata.len())
+        } else {
+            0
+        };
+        if existing > 0 {
+            let response = get(Some(existing)).await.context(HttpSnafu)?;
+            if response.status == 206 && content_range_start(&response) == Some(existing) {
+                debug!(path = %job.path, offset = existing, "Resuming download");
+                return Ok((response, existing));
+            }
+            if response.status == 200 {
+                debug!(path = %job.path, "Server can't resume, starting over");
+                return Ok((response, 0));
+            }
+            // Most likely the file changed upstream and no longer fits what we have, so ask
+            // for all of it instead
+            debug!(path = %job.path, status = response.status, "Can't resume, starting over");
+        }
+        let response = get(None)
+            .await
+            .and_then(Response::error_for_status)
+            .context(HttpSnafu)?;
+        Ok((response, 0))
+    }
+
+    /// Returns true if an interrupted download of `job` may be continued later, which needs
+    /// digests to check the joined file against
+    fn resumable(&self, job: &DownloadJob) -> bool {
+        self.options.resume && !job.artifact.hashes.is_empty()
+    }
+}
+
+/// Where a download reads from
+enum Input<B> {
+    /// A local file, and its path
+    File(File, PathBuf),
+    /// A response being received
+    Http(Response<B>),
+}
+
+impl<B: Body> Input<B> {
+    /// Reads the next chunk, or `None` at the end
+    async fn chunk(&mut self) -> Result<Option<Vec<u8>>, DownloadError> {
+        match self {
+            Input::File(file, path) => {
+                let mut buffer = vec![0; CHUNK_SIZE];
+                let read = file.read(&mut buffer).context(IoSnafu { path: &*path })?;
+                buffer.truncate(read);
+                Ok((read > 0).then_some(buffer))
+            }
+            Input::Http(response) => response.body.chunk().await.context(HttpSnafu),
+        }
+    }
+}
+
+/// Returns where the body of a `206 Partial Content` response starts, from its `Content-Range`
+fn content_range_start<B: Body>(response: &Response<B>) -> Option<u64> {
+    let range = response
+        .header("Content-Range")?
+        .trim()
+        .strip_prefix("bytes ")?;
+    range.split_once('-')?.0.trim().parse().ok()
+}
+
+/// Checks a downloaded file against the size and digests its artifact was resolved with
+fn verify(job: &DownloadJob, written: u64, actual: &Hashes) -> Result<(), DownloadError> {
+    if let Some(expected) = job.artifact.size {
+        ensure!(
+            written == expected,
+            SizeMismatchSnafu {
+                path: job.path.clone(),
+                expected,
+                actual: written,
+            }
+        );
+    }
+    let expected = &job.artifact.hashes;
+    match expected.mismatch(actual) {
+        Some(algorithm) => HashMismatchSnafu {
+            path: job.path.clone(),
+            algorithm,
+            expected: expected.hex(algorithm).unwrap_or_default(),
+            actual: actual.hex(algorithm).unwrap_or_default(),
+        }
+        .fail(),
+        None => Ok(()),
+    }
+}
+
+/// Takes the single file whose name matches `pattern` out of the zip archive at `partial`,
+/// writing it to `destination` and removing the archive
+fn extract(
+    job: &DownloadJob,
+    pattern: &str,
+    partial: &Path,
+    destination: &Path,
+) -> Result<(), DownloadError> {
+    let file = File::open(partial).context(IoSnafu { path: partial })?;
+    let mut archive = ZipArchive::new(io::BufReader::new(file)).context(ArchiveSnafu {
+        path: job.path.clone(),
+    })?;
+    let regex = Regex::new(pattern).ok();
+    let matches: Vec<usize> = archive
+        .entries()
+        .iter()
+        .enumerate()
+        .filter(|(_, entry)| {
+            let name = entry.name().rsplit('/').next().unwrap_or_default();
+            !entry.is_directory() && regex.as_ref().is_some_and(|regex| regex.is_match(name))
+        })
+        .map(|(index, _)| index)
+        .collect();
+    let [index] = matches[..] else {
+        return ExtractSnafu {
+            path: job.path.clone(),
+            pattern,
+            matches: matches
+                .iter()
+                .map(|&index| archive.entries()[index].name().to_string())
+                .collect::<Vec<_>>(),
+        }
+        .fail();
+    };
+    let mut extracted = OsString::from(destination);
+    extracted.push(".extract");
+    let extracted = PathBuf::from(extracted);
+    let mut output = File::create(&extracted).context(IoSnafu { path: &extracted })?;
+    let mut entry = archive.open(index).context(ArchiveSnafu {
+        path: job.path.clone(),
+    })?;
+    // Reading fails if the entry doesn't match its checksum
+    io::copy(&mut entry, &mut output).context(IoSnafu { path: &extracted })?;
+    output.sync_all().context(IoSnafu { path: &extracted })?;
+    fs::rename(&extracted, destination).context(IoSnafu { path: destination })?;
+    fs::remove_file(partial).context(IoSnafu { path: partial })
+}
+
+/// Returns true if a download failed because the file isn't there anymore
+fn is_gone(error: &DownloadError) -> bool {
+    matches!(
+        error,
+        DownloadError::Http {
+            source: HttpError::Status {
+                status: 404 | 410,
+                ..
+            }
+        }
+    )
+}
+
+/// Returns the url of an artifact's newest copy in the Wayback Machine, if it was published
+/// over http and has a pinned hash to check the copy against
+fn wayback_url(artifact: &ResolvedArtifact) -> Option<Url> {
+    let url = &artifact.download_url;
+    let pinned = artifact
+        .hashes
+        .blake3
+        .is_some_and(|blake3| blake3 != [0; 32]);
+    if !pinned || !matches!(url.scheme(), "http" | "https") {
+        return None;
+    }
+    Url::parse(&format!("{WAYBACK}{WAYBACK_TIMESTAMP}/{url}")).ok()
+}
+
+/// Runs futures with at most `limit` in flight at once, returning their outputs in order
+async fn limit_concurrency<F: Future>(
+    futures: impl IntoIterator<Item = F>,
+    limit: usize,
+) -> Vec<F::Output> {
+    let mut queue = futures.into_iter().enumerate();
+    let mut exhausted = false;
+    let mut running: Vec<(usize, Pin<Box<F>>)> = Vec::new();
+    let mut outputs: Vec<Option<F::Output>> = Vec::new();
+    poll_fn(|context| loop {
+        while !exhausted && running.len() < limit.max(1) {
+            match queue.next() {
+                Some((index, future)) => {
+                    outputs.push(None);
+                    running.push((index, Box::pin(future)));
+                }
+                None => exhausted = true,
+            }
+        }
+        let before = running.len();
+        running.retain_mut(|(index, future)| match future.as_mut().poll(context) {
+            Poll::Ready(output) => {
+                outputs[*index] = Some(output);
+                false
+            }
+            Poll::Pending => true,
+        });
+        if running.is_empty() && exhausted {
+            return Poll::Ready(());
+        }
+        // Only go around again if slots were freed for queued futures
+        if running.len() == before {
+            return Poll::Pending;
+        }
+    })
+    .await;
+    outputs
+        .into_iter()
+        .map(|output| output.expect("Every future ran to completion"))
+        .collect()
+}
+
+/// Error that occurs while downloading a file
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum DownloadError {
+    /// The download request failed
+    #[snafu(display("Download failed: {}", source))]
+    Http {
+        /// The underlying error
+        source: HttpError,
+    },
+    /// A file couldn't be read or written
+    #[snafu(display("Failed to access {}: {}", path.display(), source))]
+    Io {
+        /// The file being accessed
+        path: PathBuf,
+        /// The underlying error
+        source: io::Error,
+    },
+    /// The file was a different size than expected
+    #[snafu(display("{} is {} bytes, expected {}", path, actual, expected))]
+    SizeMismatch {
+        /// The file's path
+        path: RelativePathBuf,
+        /// The size the artifact was resolved with
+        expected: u64,
+        /// The size it actually was
+        actual: u64,
+    },
+    /// The file's contents didn't match the hash it was resolved with
+    #[snafu(display("{} has {} hash {}, expected {}", path, algorithm, actual, expected))]
+    HashMismatch {
+        /// The file's path
+        path: RelativePathBuf,
+        /// The hash algorithm that was checked
+        algorithm: HashAlgorithm,
+        /// The expected hash, in hex
+        expected: String,
+        /// The hash of the downloaded contents, in hex
+        actual: String,
+    },
+    /// The download was cancelled
+    #[snafu(display("Download cancelled"))]
+    Cancelled,
+    /// The url can't be downloaded from
+    #[snafu(display("Can't download from {}", url))]
+    UnsupportedUrl {
+        /// The url
+        url: Url,
+    },
+    /// The archive holding the file couldn't be read
+    #[snafu(display("Failed to read the archive holding {}: {}", path, source))]
+    Archive {
+        /// The file's path
+        path: RelativePathBuf,
+        /// The underlying error
+        source: ZipError,
+    },
+    /// The file's path leads outside the directory it is downloaded into
+    #[snafu(display("{} leads outside the directory it is downloaded into", path))]
+    Outside {
+        /// The file's path
+        path: RelativePathBuf,
+    },
+    /// The archive holding the file didn't have exactly one file matching its pattern
+    #[snafu(display(
+        "{} files in the archive holding {} match {}: {}",
+        matches.len(),
+        path,
+        pattern,
+        matches.join(", ")
+    ))]
+    Extract {
+        /// The file's path
+        path: RelativePathBuf,
+        /// The pattern files were matched against
+        pattern: String,
+        /// The files in the archive that matched
+        matches: Vec<String>,
+    },
+}
+
+impl DownloadError {
+    /// Returns true if the error has to do with where the file was downloaded from, so that
+    /// another copy of it might do better
+    fn is_url_specific(&self) -> bool {
+        matches!(
+            self,
+            DownloadError::Http { .. }
+                | DownloadError::SizeMismatch { .. }
+                | DownloadError::HashMismatch { .. }
+                | DownloadError::UnsupportedUrl { .. }
+                | DownloadError::Archive { .. }
+        )
+    }
+
+    /// Suggests how the user might fix the problem, if there is a likely fix
+    pub fn suggestion(&self) -> Option<String> {
+        match self {
+            DownloadError::Http { source } => source.suggestion(),
+            DownloadError::Io { path, .. } => Some(format!(
+                "Check that {} is writable, and that there is space left on the disk",
+                path.display()
+            )),
+            DownloadError::SizeMismatch { .. } | DownloadError::HashMismatch { .. } => Some(
+                "If the file was meant to change upstream, update the hashes recorded for it; \
+                 otherwise the server may be sending a corrupted or tampered file"
+                    .into(),
+            ),
+            DownloadError::UnsupportedUrl { .. } => {
+                Some("Only http, https, and file urls can be downloaded".into())
+            }
+            DownloadError::Archive { .. } => Some(
+                "The archive may have been corrupted on the way; try downloading it again".into(),
+            ),
+            DownloadError::Extract { .. } => Some(
+                "Make the source's file regex specific enough to match only one file in the \
+                 archive"
+                    .into(),
+            ),
+            DownloadError::Outside { .. } => {
+                Some("Give the file a path without `..` in the manifest".into())
+            }
+            DownloadError::Cancelled => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::{
+        archive::{FileOptions, ZipWriter},
+        http::testing::{block_on, MockClient},
+        resolve::{ProjectDetails, UpstreamIds},
+        types::Hashes,
+    };
+
+    /// A job downloading `url` to `path`
+    fn job(url: &str, path: &str, size: Option<u64>) -> DownloadJob {
+        DownloadJob {
+            artifact: ResolvedArtifact {
+                download_url: Url::parse(url).unwrap(),
+                filename: path.to_string(),
+                size,
+                hashes: Hashes::default(),
+                mirrors: Vec::new(),
+                extract: None,
+                ids: UpstreamIds::default(),
+                details: ProjectDetails::default(),
+            },
+            path: RelativePathBuf::from(path),
+        }
+    }
+
+    /// A fresh directory for a test
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ffpack-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    // Jobs download independently, with progress summed across them
+    #[test]
+    fn download() {
+        let dir = scratch("download");
+        let local = dir.join("local.txt");
+        fs::write(&local, b"local!").unwrap();
+        let client = MockClient::default()
+            .with("https://example.org/a.jar", "aaaa")
+            .with("https://example.org/b.jar", "bb");
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let options = DownloadOptions::default()
+            .with_parallelism(2)
+            .with_progress({
+                let events = events.clone();
+                move |progress| events.lock().unwrap().push(progress)
+            });
+        let downloader = Downloader::new(client, options);
+        let jobs = [
+            job("https://example.org/a.jar", "mods/a.jar", Some(4)),
+            job("https://example.org/b.jar", "mods/b.jar", Some(3)),
+            job("https://example.org/missing.jar", "mods/c.jar", Some(1)),
+            job(
+                Url::from_file_path(&local).unwrap().as_str(),
+                "config/local.txt",
+                Some(6),
+            ),
+        ];
+        let target = dir.join("instance");
+        let results = block_on(downloader.download(&jobs, &target));
+        assert_eq!(fs::read(results[0].as_ref().unwrap()).unwrap(), b"aaaa");
+        assert!(matches!(
+            results[1],
+            Err(DownloadError::SizeMismatch { .. })
+        ));
+        assert!(matches!(results[2], Err(DownloadError::Http { .. })));
+        assert_eq!(
+            fs::read(target.join("config/local.txt")).unwrap(),
+            b"local!"
+        );
+        assert!(!target.join("mods/b.jar").exists());
+        assert!(!target.join("mods/b.jar.part").exists());
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 3);
+        assert!(events.iter().all(|event| event.total == Some(14)));
+        assert_eq!(events.last().unwrap().total_done, 12);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    // Offline, local files are still copied, but nothing is fetched from a client without a cache
+    #[test]
+    fn offline() {
+        let dir = scratch("offline");
+        let local = dir.join("local.txt");
+        fs::write(&local, b"local!").unwrap();
+        let client = MockClient::default().with("https://example.org/a.jar", "aaaa");
+        let options = DownloadOptions::default().with_network(NetworkPolicy::Offline);
+        let downloader = Downloader::new(client, options);
+        let jobs = [
+            job("https://example.org/a.jar", "mods/a.jar", Some(4)),
+            job(
+                Url::from_file_path(&local).unwrap().as_str(),
+                "local.txt",
+                None,
+            ),
+        ];
+        let results = block_on(downloader.download(&jobs, &dir.join("instance")));
+        assert!(matches!(
+            &results[0],
+            Err(DownloadError::Http {
+                source: HttpError::WouldRequireNetwork { .. }
+            })
+        ));
+        assert!(results[1].is_ok());
+        assert!(downloader.client().requests.lock().unwrap().is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    // Contents are checked against every known digest, and corrupted files are never put in place
+    #[test]
+    fn hash_mismatch() {
+        let dir = scratch("hash-mismatch");
+        let client = MockClient::default().with("https://example.org/a.jar", "aaaa");
+        let downloader = Downloader::new(client, DownloadOptions::default());
+        let with_hashes = |path, hashes| {
+            let mut job = job("https://example.org/a.jar", path, None);
+            job.artifact.hashes = hashes;
+            job
+        };
+        let blake3 = crate::hash::blake3(b"aaaa");
+        let mut bad_md5 = Hashes::blake3(blake3);
+        bad_md5.md5 = Some([0; 16]);
+        let jobs = [
+            with_hashes("good.jar", Hashes::blake3(blake3)),
+            with_hashes("bad.jar", Hashes::blake3([0; 32])),
+            with_hashes("bad-md5.jar", bad_md5),
+        ];
+        let results = block_on(downloader.download(&jobs, &dir));
+        assert_eq!(fs::read(results[0].as_ref().unwrap()).unwrap(), b"aaaa");
+        match &results[1] {
+            Err(DownloadError::HashMismatch {
+                algorithm,
+                expected,
+                actual,
+                ..
+            }) => {
+                assert_eq!(*algorithm, HashAlgorithm::Blake3);
+                assert_eq!(expected, &"00".repeat(32));
+                assert_eq!(actual, &hex::encode(blake3));
+            }
+            other => panic!("Expected a hash mismatch, got {other:?}"),
+        }
+        assert!(matches!(
+            results[2],
+            Err(DownloadError::HashMismatch {
+                algorithm: HashAlgorithm::Md5,
+                ..
+            })
+        ));
+        assert!(!dir.join("bad.jar").exists());
+        assert!(!dir.join("bad.jar.part").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    // Mirrors are tried in order when the download url fails, each checked against the same
+    // digests
+    #[test]
+    fn mirrors() {
+        let dir = scratch("mirrors");
+        let client = MockClient::default()
+            .with("https://corrupt.example.org/a.jar", "oops")
+            .with("https://good.example.org/a.jar", "aaaa");
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let options = DownloadOptions::default().with_progress({
+            let events = events.clone();
+            move |progress| events.lock().unwrap().push(progress)
+        });
+        let downloader = Downloader::new(client, options);
+        let mut mirrored = job("https://gone.example.org/a.jar", "a.jar", Some(4));
+        mirrored.artifact.hashes = Hashes::blake3(crate::hash::blake3(b"aaaa"));
+        mirrored.artifact.mirrors = ["corrupt", "good", "unused"]
+            .iter()
+            .map(|host| Url::parse(&format!("https://{host}.example.org/a.jar")).unwrap())
+            .collect();
+        let mut exhausted = mirrored.clone();
+        exhausted.path = RelativePathBuf::from("b.jar");
+        exhausted.artifact.mirrors.truncate(1);
+        let results = block_on(downloader.download(&[mirrored], &dir));
+        assert_eq!(fs::read(results[0].as_ref().unwrap()).unwrap(), b"aaaa");
+        // Bytes from failed attempts don't count towards the total
+        assert_eq!(events.lock().unwrap().last().unwrap().total_done, 4);
+        let results = block_on(downloader.download(&[exhausted], &dir));
+        assert!(matches!(
+            results[0],
+            Err(DownloadError::HashMismatch { .. })
+        ));
+        // Once a mirror works, the rest are left alone
+        let requests = downloader.client().requests.lock().unwrap();
+        assert!(!requests
+            .iter()
+            .any(|request| request.url.host_str() == Some("unused.example.org")));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// A client serving one file with range requests, breaking off the next response after
+    /// a number of bytes when told to
+    #[derive(Default)]
+    struct Flaky {
+        /// The file served
+        contents: Vec<u8>,
+        /// Where to break off the next response
+        cut: Mutex<Option<usize>>,
+        /// The requests made so far
+        requests: Mutex<Vec<Request>>,
+    }
+
+    /// A body that fails after its chunks run out, if cut short
+    struct FlakyBody(Option<Vec<u8>>, bool);
+
+    impl Body for FlakyBody {
+        async fn chunk(&mut self) -> Result<Option<Vec<u8>>, HttpError> {
+            match self.0.take() {
+                Some(chunk) => Ok(Some(chunk)),
+                None if self.1 => Err(HttpError::Transport {
+                    url: Url::parse("https://example.org/big.zip").unwrap(),
+                    source: Box::new(io::Error::other("Connection reset")),
+                }),
+                None => Ok(None),
+            }
+        }
+    }
+
+    impl HttpClient for Flaky {
+        type Body = FlakyBody;
+
+        async fn get(&self, request: Request) -> Result<Response<FlakyBody>, HttpError> {
+            let start = request
+                .headers
+                .iter()
+                .find(|(name, _)| name == "Range")
+                .and_then(|(_, range)| {
+                    range
+                        .strip_prefix("bytes=")?
+                        .strip_suffix('-')?
+                        .parse()
+                        .ok()
+                });
+            let mut headers = Vec::new();
+            let (status, mut body) = match start {
+                Some(start) => {
+                    let total = self.contents.len();
+                    headers.push((
+                        "Content-Range".to_string(),
+                        format!("bytes {start}-{}/{total}", total - 1),
+                    ));
+                    (206, self.contents[start..].to_vec())
+                }
+                None => (200, self.contents.clone()),
+            };
+            let cut = self.cut.lock().unwrap().take();
+            if let Some(cut) = cut {
+                body.truncate(cut);
+            }
+            let url = request.url.clone();
+            self.requests.lock().unwrap().push(request);
+            Ok(Response {
+                url,
+                status,
+                headers,
+                body: FlakyBody(Some(body), cut.is_some()),
+            })
+        }
+    }
+
+    // Interrupted downloads keep what arrived and continue from there, as long as the result
+    // can be verified
+    #[test]
+    fn resume() {
+        let dir = scratch("resume");
+        let contents = b"0123456789".repeat(10);
+        let client = Flaky {
+            contents: contents.clone(),
+            ..Flaky::default()
+        };
+        let downloader = Downloader::new(&client, DownloadOptions::default());
+        let mut big = job("https://example.org/big.zip", "big.zip", Some(100));
+        big.artifact.hashes = Hashes::blake3(crate::hash::blake3(&contents));
+        let partial = dir.join("big.zip.part");
+
+        *client.cut.lock().unwrap() = Some(40);
+        let results = block_on(downloader.download(&[big.clone()], &dir));
+        assert!(matches!(
+            results[0],
+            Err(DownloadError::Http {
+                source: HttpError::Transport { .. }
+            })
+        ));
+        assert_eq!(fs::read(&partial).unwrap(), &contents[..40]);
+        let results = block_on(downloader.download(&[big.clone()], &dir));
+        assert_eq!(fs::read(results[0].as_ref().unwrap()).unwrap(), contents);
+        let range = |index: usize| {
+            let requests = client.requests.lock().unwrap();
+            let headers = &requests[index].headers;
+            headers
+                .iter()
+                .find(|(name, _)| name == "Range")
+                .map(|(_, value)| value.clone())
+        };
+        assert_eq!(range(0), None);
+        assert_eq!(range(1).as_deref(), Some("bytes=40-"));
+
+        // Leftovers that don't belong to the file fail verification, and are dropped
+        fs::remove_file(dir.join("big.zip")).unwrap();
+        fs::write(&partial, b"garbage").unwrap();
+        let results = block_on(downloader.download(&[big.clone()], &dir));
+        assert!(matches!(
+            results[0],
+            Err(DownloadError::HashMismatch { .. })
+        ));
+        assert!(!partial.exists());
+        let results = block_on(downloader.download(&[big.clone()], &dir));
+        assert!(results[0].is_ok());
+
+        // Files without digests, or with resuming turned off, always start over
+        let mut unverified = big.clone();
+        unverified.artifact.hashes = Hashes::default();
+        let no_resume = Downloader::new(&client, DownloadOptions::default().with_resume(false));
+        for (downloader, job) in [(&downloader, unverified), (&no_resume, big)] {
+            *client.cut.lock().unwrap() = Some(40);
+            let results = block_on(downloader.download(&[job], &dir));
+            assert!(results[0].is_err());
+            assert!(!partial.exists());
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    // The bandwidth cap is shared between every download running at once
+    #[test]
+    fn throttle() {
+        /// Records the delays it is asked to sleep for
+        #[derive(Default)]
+        struct Recorder(Mutex<Vec<Duration>>);
+        impl Sleep for &Recorder {
+            async fn sleep(&self, duration: Duration) {
+                self.0.lock().unwrap().push(duration);
+            }
+        }
+
+        let dir = scratch("throttle");
+        let local = dir.join("local.txt");
+        fs::write(&local, "l".repeat(500)).unwrap();
+        let client = MockClient::default()
+            .with("https://example.org/a.jar", "a".repeat(100))
+            .with("https://example.org/b.jar", "b".repeat(100))
+            .with("https://example.org/c.jar", "c".repeat(100));
+        let recorder = Recorder::default();
+        let options = DownloadOptions::default()
+            .with_parallelism(4)
+            .with_max_bytes_per_second(100);
+        let downloader = Downloader::with_sleep(client, options, &recorder);
+        let jobs = [
+            job("https://example.org/a.jar", "a.jar", Some(100)),
+            job("https://example.org/b.jar", "b.jar", Some(100)),
+            job("https://example.org/c.jar", "c.jar", Some(100)),
+            job(
+                Url::from_file_path(&local).unwrap().as_str(),
+                "local.txt",
+                None,
+            ),
+        ];
+        let results = block_on(downloader.download(&jobs, &dir.join("instance")));
+        assert!(results.iter().all(Result::is_ok));
+        // The first second's worth goes straight through, the rest waits its turn, and local
+        // copies don't count
+        let waits = recorder.0.lock().unwrap().clone();
+        assert_eq!(waits.len(), 2);
+        let longest = waits.into_iter().max().unwrap();
+        assert!(
+            longest > Duration::from_millis(1900) && longest <= Duration::from_secs(2),
+            "{longest:?}"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    // Cancelled downloads stop without leaving files behind
+    #[test]
+    fn cancelled() {
+        let dir = scratch("cancelled");
+        let cancellation = Cancellation::new();
+        let client = MockClient::default().with("https://example.org/a.jar", "aaaa");
+        let options = DownloadOptions::default().with_cancellation(cancellation.clone());
+        let downloader = Downloader::new(client, options);
+        cancellation.cancel();
+        let results =
+            block_on(downloader.download(&[job("https://example.org/a.jar", "a.jar", None)], &dir));
+        assert!(matches!(results[0], Err(DownloadError::Cancelled)));
+        assert!(fs::read_dir(&dir).unwrap().next().is_none());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    // Files are taken out of archived artifacts, which are fetched from GitHub with the token
+    #[test]
+    fn extract_artifact() {
+        let dir = scratch("extract-artifact");
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        for (name, contents) in [("build/mod.jar", "jar!"), ("sources.txt", "src")] {
+            writer
+                .start_file(name, FileOptions::for_path(name))
+                .unwrap();
+            io::Write::write_all(&mut writer, contents.as_bytes()).unwrap();
+        }
+        let archive = writer.finish().unwrap().into_inner();
+        let url = "https://api.github.com/repos/o/r/actions/artifacts/1/zip";
+        let client = MockClient::default().with(url, archive);
+        let options = DownloadOptions::default().with_credentials(Credentials {
+            github: Some("token".into()),
+            ..Credentials::default()
+        });
+        let downloader = Downloader::new(client, options);
+        let extracting = |path, pattern: &str| {
+            let mut job = job(url, path, None);
+            job.artifact.extract = Some(pattern.to_string());
+            job
+        };
+        let jobs = [
+            extracting("mods/mod.jar", r"\.jar$"),
+            extracting("mods/any.jar", "."),
+        ];
+        let results = block_on(downloader.download(&jobs, &dir));
+        assert_eq!(fs::read(results[0].as_ref().unwrap()).unwrap(), b"jar!");
+        assert!(matches!(
+            &results[1],
+            Err(DownloadError::Extract { matches, .. }) if matches.len() == 2
+        ));
+        assert!(!dir.join("mods/any.jar").exists());
+        assert!(!dir.join("mods/any.jar.part").exists());
+        let requests = downloader.client().requests.lock().unwrap();
+        assert!(requests[0]
+            .headers
+            .contains(&("Authorization".into(), "Bearer token".into())));
+        drop(requests);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    // Files gone upstream come from the Wayback Machine, if their hash is pinned to check
+    #[test]
+    fn wayback() {
+        let dir = scratch("wayback");
+        let gone = "https://example.org/old/mod.jar";
+        let archived = format!("https://web.archive.org/web/2id_/{gone}");
+        let client = MockClient::default().with(&archived, "old mod");
+        let mut pinned = job(gone, "pinned.jar", None);
+        pinned.artifact.hashes = Hashes::blake3(crate::hash::blake3(b"old mod"));
+        let mut changed = job(gone, "changed.jar", None);
+        changed.artifact.hashes = Hashes::blake3([1; 32]);
+        let unpinned = job(gone, "unpinned.jar", None);
+        let jobs = [pinned, changed, unpinned];
+
+        let downloader = Downloader::new(&client, DownloadOptions::default());
+        let results = block_on(downloader.download(&jobs, &dir));
+        assert!(results.iter().all(Result::is_err));
+        let options = DownloadOptions::default().with_wayback(true);
+        let downloader = Downloader::new(&client, options);
+        let results = block_on(downloader.download(&jobs, &dir));
+        assert_eq!(fs::read(results[0].as_ref().unwrap()).unwrap(), b"old mod");
+        assert!(matches!(
+            results[1],
+            Err(DownloadError::HashMismatch { .. })
+        ));
+        assert!(matches!(
+            &results[2],
+            Err(DownloadError::Http {
+                source: HttpError::Status { status: 404, .. }
+            })
+        ));
+        let requests = client.requests.lock().unwrap();
+        let archive_requests = requests
+            .iter()
+            .filter(|request| request.url.as_str() == archived)
+            .count();
+        assert_eq!(archive_requests, 2);
+        drop(requests);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    // No more than the limit run at once, and outputs keep their order
+    #[test]
+    fn concurrency_limit() {
+        let running = AtomicU64::new(0);
+        let peak = AtomicU64::new(0);
+        let futures = (0..10).map(|index| {
+            let (running, peak) = (&running, &peak);
+            async move {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                // Yield once so that other futures get a chance to start
+                let mut yielded = false;
+                poll_fn(|context| {
+                    if yielded {
+                        Poll::Ready(())
+                    } else {
+                        yielded = true;
+                        context.waker().wake_by_ref();
+                        Poll::Pending
+                    }
+                })
+                .await;
+                running.fetch_sub(1, Ordering::SeqCst);
+                index
+            }
+        });
+        let outputs = block_on(limit_concurrency(futures, 3));
+        assert_eq!(outputs, (0..10).collect::<Vec<_>>());
+        assert_eq!(peak.load(Ordering::SeqCst), 3);
+    }
+}
@@ -0,0 +1,214 @@
+//! Telling Java Edition content apart from Bedrock Edition content
+//!
+//! ffpack only handles Java Edition packs. Bedrock add-ons and worlds are zips too, and one of
+//! them even has a `manifest.json` at its root like a CurseForge pack, so they are recognized up
+//! front and rejected with an error saying as much, rather than failing to parse as something
+//! else. Should Bedrock support ever be added, [`Edition`] is where it would hook in.
+
+use std::{
+    fmt::Display,
+    io::{Read, Seek},
+    path::Path,
+};
+
+use crate::archive::ZipArchive;
+
+/// An edition of minecraft
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum Edition {
+    /// Java Edition, which ffpack is built for
+    #[default]
+    Java,
+    /// Bedrock Edition
+    Bedrock,
+}
+
+impl Edition {
+    /// Returns true if ffpack can work with content for this edition
+    pub fn is_supported(self) -> bool {
+        matches!(self, Edition::Java)
+    }
+}
+
+impl Display for Edition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Edition::Java => write!(f, "Java Edition"),
+            Edition::Bedrock => write!(f, "Bedrock Edition"),
+        }
+    }
+}
+
+/// The kinds of Bedrock Edition archive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BedrockFormat {
+    /// A single resource or behavior pack (`.mcpack`)
+    Pack,
+    /// A bundle of packs (`.mcaddon`)
+    Addon,
+    /// A world (`.mcworld`)
+    World,
+    /// A world template (`.mctemplate`)
+    Template,
+}
+
+impl BedrockFormat {
+    /// Recognizes a Bedrock archive by its file extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "mcpack" => Some(BedrockFormat::Pack),
+            "mcaddon" => Some(BedrockFormat::Addon),
+            "mcworld" => Some(BedrockFormat::World),
+            "mctemplate" => Some(BedrockFormat::Template),
+            _ => None,
+        }
+    }
+
+    /// Recognizes a Bedrock archive by its contents
+    ///
+    /// Packs have a Bedrock manifest at their root or in a single directory, add-ons hold
+    /// several packs, and worlds have a `level.dat` next to a leveldb database.
+    pub fn detect<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Option<Self> {
+        let mut manifests = Vec::new();
+        let mut nested_packs = false;
+        for (index, entry) in archive.entries().iter().enumerate() {
+            let name = entry.name().to_ascii_lowercase();
+            if name.ends_with(".mcpack") {
+                nested_packs = true;
+            }
+            let depth = name.matches('/').count();
+            if depth <= 1 && (name == "manifest.json" || name.ends_with("/manifest.json")) {
+                manifests.push((index, depth));
+            }
+        }
+        // Java Edition worlds have a level.dat too, but keep their chunks in region files
+        // rather than a leveldb database
+        let world = archive.by_name("level.dat").is_some()
+            && archive
+                .entries()
+                .iter()
+                .any(|entry| entry.name() == "levelname.txt" || entry.name().starts_with("db/"));
+        let bedrock_manifests: Vec<_> = manifests
+            .into_iter()
+            .filter(|&(index, _)| {
+                let mut json = Vec::new();
+                archive
+                    .open(index)
+                    .ok()
+                    .and_then(|mut reader| reader.read_to_end(&mut json).ok())
+                    .is_some_and(|_| is_bedrock_manifest(&json))
+            })
+            .collect();
+        if world {
+            // Templates describe themselves with a manifest at the root, worlds don't
+            let root_manifest = bedrock_manifests.iter().any(|&(_, depth)| depth == 0);
+            return Some(if root_manifest {
+                BedrockFormat::Template
+            } else {
+                BedrockFormat::World
+            });
+        }
+        match bedrock_manifests.as_slice() {
+            [] if nested_packs => Some(BedrockFormat::Addon),
+            [] => None,
+            [_] if !nested_packs => Some(BedrockFormat::Pack),
+            _ => Some(BedrockFormat::Addon),
+        }
+    }
+
+    /// The usual file extension of the format
+    pub fn extension(self) -> &'static str {
+        match self {
+            BedrockFormat::Pack => "mcpack",
+            BedrockFormat::Addon => "mcaddon",
+            BedrockFormat::World => "mcworld",
+            BedrockFormat::Template => "mctemplate",
+        }
+    }
+}
+
+impl Display for BedrockFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BedrockFormat::Pack => write!(f, "pack"),
+            BedrockFormat::Addon => write!(f, "add-on"),
+            BedrockFormat::World => write!(f, "world"),
+            BedrockFormat::Template => write!(f, "world template"),
+        }
+    }
+}
+
+/// Returns true if `json` is a Bedrock pack manifest, which always has a header with a uuid and
+/// a list of modules
+pub fn is_bedrock_manifest(json: &[u8]) -> bool {
+    serde_json::from_slice::<serde_json::Value>(json).is_ok_and(|manifest| {
+        manifest["header"]["uuid"].is_string() && manifest["modules"].is_array()
+    })
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::archive::{FileOptions, ZipWriter};
+
+    /// Builds a zip out of names and contents
+    fn zip(entries: &[(&str, &str)]) -> ZipArchive<Cursor<Vec<u8>>> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, contents) in entries {
+            writer
+                .write_file(name, FileOptions::for_path(name), &mut contents.as_bytes())
+                .unwrap();
+        }
+        let mut data = writer.finish().unwrap();
+        data.set_position(0);
+        ZipArchive::new(data).unwrap()
+    }
+
+    // Bedrock archives are told apart from each other, and from CurseForge packs
+    #[test]
+    fn detect() {
+        let manifest = r#"{"format_version": 2, "header": {"uuid": "1234", "version": [1, 0, 0], "name": "Pack"}, "modules": []}"#;
+        let detect = |entries: &[(&str, &str)]| BedrockFormat::detect(&mut zip(entries));
+        assert_eq!(
+            detect(&[("manifest.json", manifest)]),
+            Some(BedrockFormat::Pack)
+        );
+        assert_eq!(
+            detect(&[
+                ("resources/manifest.json", manifest),
+                ("behaviors/manifest.json", manifest)
+            ]),
+            Some(BedrockFormat::Addon)
+        );
+        assert_eq!(
+            detect(&[("a.mcpack", ""), ("b.mcpack", "")]),
+            Some(BedrockFormat::Addon)
+        );
+        assert_eq!(
+            detect(&[
+                ("level.dat", ""),
+                ("levelname.txt", "World"),
+                ("manifest.json", manifest),
+                ("resource_packs/x/manifest.json", manifest)
+            ]),
+            Some(BedrockFormat::Template)
+        );
+        assert_eq!(
+            detect(&[("level.dat", ""), ("db/CURRENT", "")]),
+            Some(BedrockFormat::World)
+        );
+        // Java Edition worlds aren't mistaken for Bedrock ones
+        assert_eq!(detect(&[("level.dat", ""), ("region/r.0.0.mca", "")]), None);
+        let curseforge = r#"{"manifestType": "minecraftModpack", "minecraft": {"version": "1.19"}, "files": []}"#;
+        assert_eq!(detect(&[("manifest.json", curseforge)]), None);
+        assert_eq!(
+            BedrockFormat::from_path(Path::new("x/Pack.McAddon")),
+            Some(BedrockFormat::Addon)
+        );
+        assert!(!Edition::Bedrock.is_supported());
+    }
+}
@@ -0,0 +1,472 @@
+//! Exporting packs into the formats used by launchers and platforms
+//!
+//! No export format can represent everything a [`Pack`] can. Each exporter declares what its
+//! target supports as a set of [`Capabilities`], which can be checked against a pack up front to
+//! warn about exactly what will be lost, rather than having it silently dropped.
+
+#[cfg(feature = "atlauncher")]
+pub mod atlauncher;
+pub mod curseforge;
+pub mod modlist;
+pub mod mrpack;
+pub mod multimc;
+pub mod packwiz;
+pub mod server;
+#[cfg(feature = "technic")]
+pub mod technic;
+
+use std::{
+    fmt::Display,
+    fs::File,
+    io,
+    path::{Path, PathBuf},
+};
+
+use relative_path::{RelativePath, RelativePathBuf};
+use snafu::{ensure, ResultExt, Snafu};
+use tracing::warn;
+
+use crate::{
+    archive::ZipError,
+    types::{FileKind, HashAlgorithm, ManagedFile, Side, Source},
+    Pack,
+};
+
+/// The features an export target can represent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct Capabilities {
+    /// The target can mark files as only needed on the client
+    pub client_only_files: bool,
+    /// The target can mark files as only needed on the server
+    pub server_only_files: bool,
+    /// The target can keep files out of some installs, as the development profile does for files
+    /// with `devel` unset
+    pub development_profile: bool,
+    /// The target knows how to install datapacks
+    pub datapacks: bool,
+    /// The target can carry files from the repository inside the export
+    pub embedded_files: bool,
+    /// The target keeps the names and descriptions of files
+    pub file_descriptions: bool,
+    /// The target can carry the pack icon
+    pub icon: bool,
+    /// The target can carry the pack gallery
+    pub gallery: bool,
+    /// The target can patch jarmods into the minecraft jar
+    pub jarmods: bool,
+}
+
+impl Capabilities {
+    /// A target that can represent everything
+    pub const FULL: Self = Self {
+        client_only_files: true,
+        server_only_files: true,
+        development_profile: true,
+        datapacks: true,
+        embedded_files: true,
+        file_descriptions: true,
+        icon: true,
+        gallery: true,
+        jarmods: true,
+    };
+
+    /// A target that can only represent a flat list of files
+    pub const NONE: Self = Self {
+        client_only_files: false,
+        server_only_files: false,
+        development_profile: false,
+        datapacks: false,
+        embedded_files: false,
+        file_descriptions: false,
+        icon: false,
+        gallery: false,
+        jarmods: false,
+    };
+
+    /// Lists everything in `pack` that this target can't represent
+    pub fn losses(&self, pack: &Pack) -> Vec<Loss> {
+        let mut losses = Vec::new();
+        if !self.icon && pack.metadata.icon().is_some() {
+            losses.push(Loss::Icon);
+        }
+        if !self.gallery && !pack.metadata.gallery().is_empty() {
+            losses.push(Loss::Gallery {
+                images: pack.metadata.gallery().len(),
+            });
+        }
+        losses.extend(
+            pack.managed_files
+                .iter()
+                .flat_map(|file| self.file_losses(file)),
+        );
+        losses
+    }
+
+    /// Logs a warning for everything in `pack` that this target can't represent, returning the
+    /// losses
+    pub fn warn_losses(&self, pack: &Pack, target: &str) -> Vec<Loss> {
+        let losses = self.losses(pack);
+        for loss in &losses {
+            warn!(target_format = target, "{}", loss);
+        }
+        losses
+    }
+
+    /// Lists everything about a single file that this target can't represent
+    fn file_losses(self, file: &ManagedFile) -> Vec<Loss> {
+        let path = || file.path.clone();
+        let mut losses = Vec::new();
+        match file.side {
+            Side::Client if !self.client_only_files => losses.push(Loss::SideFlag {
+                path: path(),
+                side: Side::Client,
+            }),
+            Side::Server if !self.server_only_files => losses.push(Loss::SideFlag {
+                path: path(),
+                side: Side::Server,
+            }),
+            _ => {}
+        }
+        if !file.devel && !self.development_profile {
+            losses.push(Loss::DevelopmentProfile { path: path() });
+        }
+        if !self.datapacks && is_datapack(file) {
+            losses.push(Loss::Datapack { path: path() });
+        }
+        if !self.embedded_files && matches!(file.source, Source::Path { .. }) {
+            losses.push(Loss::EmbeddedFile { path: path() });
+        }
+        if !self.file_descriptions && (file.name.is_some() || file.description.is_some()) {
+            losses.push(Loss::FileDescription { path: path() });
+        }
+        if !self.jarmods && file.kind == FileKind::Jarmod {
+            losses.push(Loss::Jarmod { path: path() });
+        }
+        losses
+    }
+}
+
+// Targets start out representing nothing, so that what they support is spelled out rather than
+// assumed
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self::NONE
+    }
+}
+
+/// Returns where the file of a path source is, which has to be inside `root`, the directory the
+/// manifest is in
+fn path_source(root: &Path, path: &RelativePath) -> Result<PathBuf, ExportError> {
+    let normalized = path.normalize();
+    ensure!(
+        !normalized.starts_with(".."),
+        PathOutsideRootSnafu {
+            path: path.to_owned()
+        }
+    );
+    Ok(normalized.to_path(root))
+}
+
+/// Opens the file of a path source, which has to be inside `root`, the directory the manifest
+/// is in
+fn open_path_source(root: &Path, path: &RelativePath) -> Result<File, ExportError> {
+    let local = path_source(root, path)?;
+    File::open(&local).context(IoSnafu { path: local })
+}
+
+/// Returns true if the file is installed as a datapack
+fn is_datapack(file: &ManagedFile) -> bool {
+    file.path
+        .components()
+        .any(|component| component.as_str() == "datapacks")
+}
+
+/// A piece of information that an export target can't represent
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Loss {
+    /// The file is limited to one side, but will be installed on both
+    SideFlag {
+        /// The file's path
+        path: RelativePathBuf,
+        /// The side the file is limited to
+        side: Side,
+    },
+    /// The file is excluded from the development profile, but will be installed everywhere
+    DevelopmentProfile {
+        /// The file's path
+        path: RelativePathBuf,
+    },
+    /// The file is a datapack, which the target will treat as a plain file
+    Datapack {
+        /// The file's path
+        path: RelativePathBuf,
+    },
+    /// The file comes from the repository, and can't be carried by the target
+    EmbeddedFile {
+        /// The file's path
+        path: RelativePathBuf,
+    },
+    /// The file's name and description will be dropped
+    FileDescription {
+        /// The file's path
+        path: RelativePathBuf,
+    },
+    /// The file is a jarmod, which the target will treat as a plain file
+    Jarmod {
+        /// The file's path
+        path: RelativePathBuf,
+    },
+    /// The pack icon will be dropped
+    Icon,
+    /// The pack gallery will be dropped
+    Gallery {
+        /// The number of images in the gallery
+        images: usize,
+    },
+}
+
+impl Loss {
+    /// The path of the file the loss applies to, if it applies to a single file
+    pub fn path(&self) -> Option<&RelativePathBuf> {
+        match self {
+            Loss::SideFlag { path, .. }
+            | Loss::DevelopmentProfile { path }
+            | Loss::Datapack { path }
+            | Loss::EmbeddedFile { path }
+            | Loss::FileDescription { path }
+            | Loss::Jarmod { path } => Some(path),
+            Loss::Icon | Loss::Gallery { .. } => None,
+        }
+    }
+}
+
+impl Display for Loss {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Loss::SideFlag { path, side } => write!(
+                f,
+                "{path} is {side:?} only, but will be installed on both sides"
+            ),
+            Loss::DevelopmentProfile { path } => write!(
+                f,
+                "{path} is excluded from the development profile, but will always be installed"
+            ),
+            Loss::Datapack { path } => write!(
+                f,
+                "{path} is a datapack, but will be installed as a plain file"
+            ),
+            Loss::EmbeddedFile { path } => write!(
+                f,
+                "{path} comes from the repository and can't be included, so it will be skipped"
+            ),
+            Loss::FileDescription { path } => {
+                write!(f, "{path}'s name and description will be dropped")
+            }
+            Loss::Jarmod { path } => write!(
+                f,
+                "{path} is a jarmod, but will be installed as a plain file"
+            ),
+            Loss::Icon => write!(f, "The pack icon will be dropped"),
+            Loss::Gallery { images } => {
+                write!(f, "The pack gallery ({images} images) will be dropped")
+            }
+        }
+    }
+}
+
+/// Error that occurs while exporting a pack
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub(crate)))]
+#[non_exhaustive]
+pub enum ExportError {
+    /// A file that has to be downloaded isn't in the lockfile
+    #[snafu(display("{} isn't locked", path))]
+    Unlocked {
+        /// The file's path
+        path: RelativePathBuf,
+    },
+    /// A file lacks a digest the format requires
+    #[snafu(display("{} has no {} digest, which {} needs", path, algorithm, format))]
+    MissingHash {
+        /// The file's path
+        path: RelativePathBuf,
+        /// The missing digest's algorithm
+        algorithm: HashAlgorithm,
+        /// The format being exported to
+        format: &'static str,
+    },
+    /// A file was locked without the upstream ids the format references it by
+    #[snafu(display("{} was locked without the ids {} references it by", path, format))]
+    MissingId {
+        /// The file's path
+        path: RelativePathBuf,
+        /// The format being exported to
+        format: &'static str,
+    },
+    /// A file's size isn't known, and the format requires it
+    #[snafu(display("{}'s size isn't known, which {} needs", path, format))]
+    MissingSize {
+        /// The file's path
+        path: RelativePathBuf,
+        /// The format being exported to
+        format: &'static str,
+    },
+    /// A file can't be downloaded as is by launchers, like one taken out of an archive
+    #[snafu(display("{} can't be downloaded directly, which {} needs", path, format))]
+    NotDownloadable {
+        /// The file's path
+        path: RelativePathBuf,
+        /// The format being exported to
+        format: &'static str,
+    },
+    /// A path source leads out of the directory the manifest is in
+    #[snafu(display("{} is outside the pack's directory", path))]
+    PathOutsideRoot {
+        /// The path of the source
+        path: RelativePathBuf,
+    },
+    /// A file to embed couldn't be read
+    #[snafu(display("Failed to read {}: {}", path.display(), source))]
+    Io {
+        /// The file being read
+        path: PathBuf,
+        /// The underlying error
+        source: io::Error,
+    },
+    /// An exported file couldn't be written
+    #[snafu(display("Failed to write {}: {}", path.display(), source))]
+    Write {
+        /// The file being written
+        path: PathBuf,
+        /// The underlying error
+        source: io::Error,
+    },
+    /// A mod list couldn't be written
+    #[snafu(display("Failed to write mod list: {}", source))]
+    Output {
+        /// The underlying error
+        source: io::Error,
+    },
+    /// A mod list template is malformed
+    #[snafu(display("Invalid template: {}", message))]
+    Template {
+        /// What is wrong with the template
+        message: String,
+    },
+    /// The tarball couldn't be written
+    #[snafu(display("Failed to write tarball: {}", source))]
+    Tar {
+        /// The underlying error
+        source: io::Error,
+    },
+    /// The archive couldn't be written
+    #[snafu(display("Failed to write archive: {}", source))]
+    Zip {
+        /// The underlying error
+        source: ZipError,
+    },
+    /// An index file couldn't be written
+    #[snafu(display("Failed to write index: {}", source))]
+    Json {
+        /// The underlying error
+        source: serde_json::Error,
+    },
+}
+
+impl ExportError {
+    /// Suggests how the user might fix the problem, if there is a likely fix
+    pub fn suggestion(&self) -> Option<String> {
+        match self {
+            ExportError::Unlocked { .. } => Some("Lock the pack again before exporting".into()),
+            ExportError::MissingHash { algorithm, .. } => Some(format!(
+                "Record the file's {algorithm} digest in the manifest, or use a source whose \
+                 API lists it"
+            )),
+            ExportError::MissingId { .. } => {
+                Some("Update the file's entry in the lockfile by locking it again".into())
+            }
+            ExportError::MissingSize { .. } => {
+                Some("Use a source whose API lists the file's size".into())
+            }
+            ExportError::NotDownloadable { .. } => Some(
+                "Use a source the file can be downloaded from directly, or include it in the \
+                 repository as a path source"
+                    .into(),
+            ),
+            ExportError::PathOutsideRoot { .. } => {
+                Some("Move the file into the pack's directory".into())
+            }
+            ExportError::Template { .. } => Some(
+                "Write fields as `{{field}}`, and close sections like `{{#files}}` with \
+                 `{{/files}}`"
+                    .into(),
+            ),
+            ExportError::Io { .. }
+            | ExportError::Write { .. }
+            | ExportError::Output { .. }
+            | ExportError::Tar { .. }
+            | ExportError::Zip { .. }
+            | ExportError::Json { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::types::{Asset, Hashes};
+
+    // Only the features a target lacks are reported
+    #[test]
+    fn losses() {
+        let mut pack = Pack::default();
+        let template = pack.managed_files.iter().next().unwrap().clone();
+        pack.managed_files.insert(ManagedFile {
+            path: RelativePathBuf::from("mods/client.jar"),
+            side: Side::Client,
+            name: None,
+            description: None,
+            ..template.clone()
+        });
+        pack.managed_files.insert(ManagedFile {
+            path: RelativePathBuf::from("world/datapacks/recipes.zip"),
+            devel: false,
+            kind: FileKind::Jarmod,
+            collection: None,
+            source: Source::Path {
+                path: RelativePathBuf::from("datapacks/recipes.zip"),
+                blake3: [0; 32],
+                hashes: Hashes::default(),
+            },
+            name: None,
+            description: None,
+            ..template
+        });
+        pack.metadata.set_icon(Some(Asset::Path {
+            path: RelativePathBuf::from("icon.png"),
+        }));
+        assert!(Capabilities::FULL.losses(&pack).is_empty());
+        assert_eq!(Capabilities::default(), Capabilities::NONE);
+        let losses = Capabilities::NONE.losses(&pack);
+        assert_eq!(losses.len(), 7);
+        assert_eq!(losses[0], Loss::Icon);
+        let client_only = Capabilities {
+            server_only_files: false,
+            ..Capabilities::FULL
+        };
+        assert!(client_only.losses(&pack).is_empty());
+        let no_sides = Capabilities {
+            client_only_files: false,
+            ..Capabilities::FULL
+        };
+        assert_eq!(
+            no_sides.losses(&pack),
+            [Loss::SideFlag {
+                path: RelativePathBuf::from("mods/client.jar"),
+                side: Side::Client
+            }]
+        );
+    }
+}
@@ -0,0 +1,312 @@
+//! Exporting packs as ATLauncher packs
+//!
+//! An ATLauncher pack is described by the `version.json` of each of its versions, which names
+//! the game and loader and lists every mod with where to download it, its checksums, and the
+//! sides and options ATLauncher offers it with. ATLauncher hosts configuration separately, so
+//! files from the repository aren't part of it.
+
+use std::io::Write;
+
+use serde::Serialize;
+use snafu::{ensure, OptionExt, ResultExt};
+use tracing::instrument;
+use url::Url;
+
+use super::{Capabilities, ExportError, JsonSnafu, Loss, NotDownloadableSnafu, UnlockedSnafu};
+use crate::{
+    lock::Lockfile,
+    types::{FileKind, HashAlgorithm, Loader, ManagedFile, Side, Source},
+    Pack,
+};
+
+/// The name of the format, as it appears in errors and warnings
+const FORMAT: &str = "ATLauncher";
+
+/// What an ATLauncher pack can represent
+pub const CAPABILITIES: Capabilities = Capabilities {
+    client_only_files: true,
+    server_only_files: true,
+    file_descriptions: true,
+    jarmods: true,
+    ..Capabilities::NONE
+};
+
+/// The description of a version of a pack
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PackVersion<'a> {
+    /// The version of the pack
+    version: String,
+    /// The version of the game
+    minecraft: String,
+    /// The loader the pack is installed with
+    loader: VersionLoader,
+    /// The mods of the pack
+    mods: Vec<Mod<'a>>,
+}
+
+/// The loader of a version
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VersionLoader {
+    /// The launcher's class installing the loader
+    class_name: &'static str,
+    /// The loader's settings
+    metadata: LoaderMetadata,
+}
+
+/// The settings of a loader
+#[derive(Serialize)]
+struct LoaderMetadata {
+    /// The version of the loader
+    loader: String,
+    /// The version of the game it is for
+    minecraft: String,
+}
+
+/// A mod of a version
+///
+/// The flags are ATLauncher's own fields, so they can't be folded into enums.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Mod<'a> {
+    /// The name of the mod
+    name: &'a str,
+    /// The version of the mod
+    version: String,
+    /// Where to download the mod
+    url: Url,
+    /// The name the mod is saved under
+    file: &'a str,
+    /// The folder the mod goes in, for mods installed at the root of the instance
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<&'a str>,
+    /// How the mod is installed
+    #[serde(rename = "type")]
+    kind: &'static str,
+    /// How the mod is downloaded
+    download: &'static str,
+    /// The MD5 digest of the mod
+    #[serde(skip_serializing_if = "Option::is_none")]
+    md5: Option<String>,
+    /// The SHA-1 digest of the mod
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sha1: Option<String>,
+    /// The size of the mod in bytes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    filesize: Option<u64>,
+    /// Whether the mod is installed on clients
+    client: bool,
+    /// Whether the mod is installed on servers
+    server: bool,
+    /// Whether users can leave the mod out
+    optional: bool,
+    /// Whether the mod is selected when it is optional
+    selected: bool,
+    /// What the mod does
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'a str>,
+    /// The CurseForge project the mod is from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    curse_forge_project: Option<u64>,
+    /// The CurseForge file of the mod
+    #[serde(skip_serializing_if = "Option::is_none")]
+    curse_forge_file: Option<u64>,
+    /// The Modrinth version of the mod
+    #[serde(skip_serializing_if = "Option::is_none")]
+    modrinth_version: Option<String>,
+}
+
+/// Returns the launcher's class installing a loader
+fn loader_class(loader: &Loader) -> &'static str {
+    match loader {
+        Loader::Quilt(_) => "com.atlauncher.data.minecraft.loaders.quilt.QuiltLoader",
+        Loader::Fabric(_) => "com.atlauncher.data.minecraft.loaders.fabric.FabricLoader",
+        Loader::Forge(_) => "com.atlauncher.data.minecraft.loaders.forge.ForgeLoader",
+    }
+}
+
+/// Returns how a file is installed, and the folder it goes in if that isn't implied
+fn install_type(file: &ManagedFile) -> (&'static str, Option<&str>) {
+    if file.kind == FileKind::Jarmod {
+        return ("jar", None);
+    }
+    let folder = file.path.parent().map_or("", |parent| parent.as_str());
+    match folder {
+        "mods" => ("mods", None),
+        "resourcepacks" => ("resourcepack", None),
+        "shaderpacks" => ("shaderpack", None),
+        "" => ("root", None),
+        folder => ("root", Some(folder)),
+    }
+}
+
+/// Lists a managed file as a mod, from what it was locked to
+fn mod_entry<'a>(file: &'a ManagedFile, lock: &Lockfile) -> Result<Mod<'a>, ExportError> {
+    let path = || file.path.clone();
+    let locked = lock
+        .get(&file.path)
+        .context(UnlockedSnafu { path: path() })?;
+    let artifact = &locked.artifact;
+    let downloadable =
+        artifact.extract.is_none() && matches!(artifact.download_url.scheme(), "http" | "https");
+    ensure!(
+        downloadable,
+        NotDownloadableSnafu {
+            path: path(),
+            format: FORMAT
+        }
+    );
+    let filename = file.path.file_name().unwrap_or(&file.filename);
+    let (kind, folder) = install_type(file);
+    let id = |id: &Option<String>| id.as_ref().and_then(|id| id.parse().ok());
+    let curseforge = matches!(file.source, Source::Curseforge { .. });
+    Ok(Mod {
+        name: file.name.as_deref().unwrap_or(filename),
+        version: artifact.version_label().unwrap_or_else(|| "unknown".into()),
+        url: artifact.download_url.clone(),
+        file: filename,
+        path: folder,
+        kind,
+        download: "direct",
+        md5: artifact.hashes.hex(HashAlgorithm::Md5),
+        sha1: artifact.hashes.hex(HashAlgorithm::Sha1),
+        filesize: artifact.size,
+        client: file.side != Side::Server,
+        server: file.side != Side::Client,
+        optional: !file.enabled,
+        selected: file.enabled,
+        description: file.description.as_deref(),
+        curse_forge_project: curseforge.then(|| id(&artifact.ids.project_id)).flatten(),
+        curse_forge_file: curseforge.then(|| id(&artifact.ids.file_id)).flatten(),
+        modrinth_version: matches!(file.source, Source::Modrinth { .. })
+            .then(|| artifact.ids.version_id.clone())
+            .flatten(),
+    })
+}
+
+/// Writes the `version.json` of a pack to `writer`, returning what it couldn't represent
+///
+/// Files are downloaded from what `lock` locked them to. Path sources can't be downloaded by
+/// ATLauncher, so they are left out, and disabled files are offered as optional mods that
+/// aren't selected. Everything lost in the export is logged as well as returned.
+///
+/// # Errors
+///
+/// Returns an error if a file isn't locked or can't be downloaded directly, or `writer` fails
+#[instrument(skip_all)]
+pub fn export(pack: &Pack, lock: &Lockfile, writer: impl Write) -> Result<Vec<Loss>, ExportError> {
+    let losses = CAPABILITIES.warn_losses(pack, FORMAT);
+    let mods = pack
+        .managed_files
+        .iter()
+        .filter(|file| !matches!(file.source, Source::Path { .. }))
+        .map(|file| mod_entry(file, lock))
+        .collect::<Result<_, _>>()?;
+    let minecraft = pack.versions.minecraft.to_string();
+    let loader = &pack.versions.loader;
+    let version = PackVersion {
+        version: pack.metadata.version().to_string(),
+        minecraft: minecraft.clone(),
+        loader: VersionLoader {
+            class_name: loader_class(loader),
+            metadata: LoaderMetadata {
+                loader: loader.version().to_string(),
+                minecraft,
+            },
+        },
+        mods,
+    };
+    serde_json::to_writer_pretty(writer, &version).context(JsonSnafu)?;
+    Ok(losses)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use relative_path::RelativePathBuf;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::{
+        lock::LockedFile,
+        resolve::{ProjectDetails, ResolvedArtifact, UpstreamIds},
+        types::Hashes,
+    };
+
+    // Locked files are listed as mods with their sides and options, and path sources left out
+    #[test]
+    fn export() {
+        let file = ManagedFile {
+            path: RelativePathBuf::from("mods/jei.jar"),
+            side: Side::Client,
+            enabled: false,
+            source: Source::Curseforge {
+                slug: "jei".into(),
+                file_id: None,
+            },
+            ..ManagedFile::default()
+        };
+        let config = ManagedFile {
+            path: RelativePathBuf::from("config/jei.toml"),
+            source: Source::Path {
+                path: RelativePathBuf::from("config/jei.toml"),
+                blake3: [0; 32],
+                hashes: Hashes::default(),
+            },
+            ..ManagedFile::default()
+        };
+        let pack = Pack {
+            managed_files: [file.clone(), config].into_iter().collect(),
+            ..Pack::default()
+        };
+        let mut lock = Lockfile::default();
+        let artifact = ResolvedArtifact {
+            download_url: Url::parse("https://edge.forgecdn.net/files/jei.jar").unwrap(),
+            filename: "jei.jar".into(),
+            size: Some(10),
+            hashes: Hashes {
+                md5: Some([5; 16]),
+                ..Hashes::default()
+            },
+            mirrors: Vec::new(),
+            extract: None,
+            ids: UpstreamIds {
+                project_id: Some("238222".into()),
+                file_id: Some("4712866".into()),
+                ..UpstreamIds::default()
+            },
+            details: ProjectDetails::default(),
+        };
+        lock.files.insert(
+            file.path.clone(),
+            LockedFile {
+                source: file.source.clone(),
+                artifact,
+            },
+        );
+
+        let mut json = Vec::new();
+        let losses = super::export(&pack, &lock, &mut json).unwrap();
+        assert!(losses.contains(&Loss::EmbeddedFile {
+            path: RelativePathBuf::from("config/jei.toml")
+        }));
+        let version: Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(version["loader"]["metadata"]["loader"], "0.17.1-beta.3");
+        let mods = version["mods"].as_array().unwrap();
+        assert_eq!(mods.len(), 1);
+        assert_eq!(mods[0]["type"], "mods");
+        assert_eq!(mods[0]["version"], "4712866");
+        assert_eq!(mods[0]["md5"], "05".repeat(16));
+        assert_eq!(mods[0]["server"], false);
+        assert_eq!(mods[0]["optional"], true);
+        assert_eq!(mods[0]["selected"], false);
+        assert_eq!(mods[0]["curseForgeProject"], 238_222);
+
+        lock.files.clear();
+        assert!(matches!(
+            super::export(&pack, &lock, Vec::new()),
+            Err(ExportError::Unlocked { .. })
+        ));
+    }
+}
@@ -0,0 +1,309 @@
+//! Exporting packs as CurseForge modpacks
+//!
+//! A CurseForge modpack is a zip holding a `manifest.json`, which references files on
+//! CurseForge by project and file id, and an `overrides` folder copied into the instance as it
+//! is. Files from anywhere else have to be carried in the overrides, which CurseForge's rules
+//! only allow for files the pack's authors may redistribute, so the export reports them.
+
+use std::{io::Write, path::Path};
+
+use relative_path::RelativePathBuf;
+use serde::Serialize;
+use snafu::{OptionExt, ResultExt};
+use tracing::{debug, instrument};
+
+use super::{
+    path_source, Capabilities, ExportError, IoSnafu, JsonSnafu, Loss, MissingIdSnafu,
+    UnlockedSnafu, ZipSnafu,
+};
+use crate::{
+    archive::DeterministicZipWriter,
+    lock::Lockfile,
+    types::{Loader, ManagedFile, Source},
+    Pack,
+};
+
+/// The name of the format, as it appears in errors and warnings
+const FORMAT: &str = "CurseForge";
+
+/// The name of the manifest inside the archive
+pub(crate) const MANIFEST: &str = "manifest.json";
+
+/// The folder inside the archive that is copied into the instance
+pub(crate) const OVERRIDES: &str = "overrides";
+
+/// What a CurseForge modpack can represent
+pub const CAPABILITIES: Capabilities = Capabilities {
+    embedded_files: true,
+    ..Capabilities::NONE
+};
+
+/// What was lost in a CurseForge export, and what in it needs checking against CurseForge's
+/// distribution rules
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    /// Everything the format couldn't represent
+    pub losses: Vec<Loss>,
+    /// Files from other platforms carried in the overrides, which CurseForge only accepts if
+    /// their licenses or authors allow redistributing them
+    pub redistributed: Vec<RelativePathBuf>,
+}
+
+/// The manifest of a CurseForge modpack
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ModpackManifest<'a> {
+    /// The game and loader versions
+    minecraft: Game,
+    /// The kind of manifest
+    manifest_type: &'static str,
+    /// The version of the manifest format
+    manifest_version: u32,
+    /// The name of the pack
+    name: &'a str,
+    /// The version of the pack
+    version: String,
+    /// The pack's authors
+    author: String,
+    /// The files on CurseForge
+    files: Vec<ManifestFile>,
+    /// The folder inside the archive that is copied into the instance
+    overrides: &'static str,
+}
+
+/// The game and loader versions of a modpack
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Game {
+    /// The version of minecraft
+    version: String,
+    /// The loaders, one of which is primary
+    mod_loaders: Vec<ModLoader>,
+}
+
+/// A loader in a modpack's manifest
+#[derive(Serialize)]
+struct ModLoader {
+    /// The loader and its version, like `forge-47.1.0`
+    id: String,
+    /// Whether this is the loader instances are created with
+    primary: bool,
+}
+
+/// A file on CurseForge in a modpack's manifest
+#[derive(Serialize)]
+struct ManifestFile {
+    /// The id of the project
+    #[serde(rename = "projectID")]
+    project_id: u64,
+    /// The id of the file
+    #[serde(rename = "fileID")]
+    file_id: u64,
+    /// Whether the file is installed, rather than only offered
+    required: bool,
+}
+
+/// Returns the id CurseForge gives a loader and its version
+fn loader_id(loader: &Loader) -> String {
+    let name = match loader {
+        Loader::Quilt(_) => "quilt",
+        Loader::Fabric(_) => "fabric",
+        Loader::Forge(_) => "forge",
+    };
+    format!("{name}-{}", loader.version())
+}
+
+/// Lists a CurseForge file in the manifest, from what it was locked to
+fn manifest_file(file: &ManagedFile, lock: &Lockfile) -> Result<ManifestFile, ExportError> {
+    let path = || file.path.clone();
+    let locked = lock
+        .get(&file.path)
+        .context(UnlockedSnafu { path: path() })?;
+    let ids = &locked.artifact.ids;
+    let id = |id: Option<&String>| id.and_then(|id| id.parse().ok());
+    let missing = || MissingIdSnafu {
+        path: path(),
+        format: FORMAT,
+    };
+    Ok(ManifestFile {
+        project_id: id(ids.project_id.as_ref()).with_context(missing)?,
+        file_id: id(ids.file_id.as_ref()).with_context(missing)?,
+        required: file.enabled,
+    })
+}
+
+/// Writes a pack as a CurseForge modpack to `writer`, returning what it couldn't represent and
+/// what it redistributes
+///
+/// CurseForge files are referenced by the ids `lock` locked them to. Path sources are read
+/// from under `root`, the directory the manifest is in, and every other file from where it is
+/// installed under `installed`, which should be an install of the same lockfile. Everything
+/// lost in the export is logged as well as returned.
+///
+/// # Errors
+///
+/// Returns an error if a CurseForge file isn't locked, a file to embed can't be read, or the
+/// archive can't be written
+#[instrument(skip_all)]
+pub fn export(
+    pack: &Pack,
+    lock: &Lockfile,
+    root: &Path,
+    installed: &Path,
+    writer: impl Write,
+) -> Result<Report, ExportError> {
+    let mut report = Report {
+        losses: CAPABILITIES.warn_losses(pack, FORMAT),
+        redistributed: Vec::new(),
+    };
+    let mut zip = DeterministicZipWriter::new(writer);
+    let mut files = Vec::new();
+    for file in &pack.managed_files {
+        let local = match &file.source {
+            Source::Curseforge { .. } => {
+                files.push(manifest_file(file, lock)?);
+                continue;
+            }
+            Source::Path { path, .. } => path_source(root, path)?,
+            _ => {
+                report.redistributed.push(file.path.clone());
+                file.install_path().to_path(installed)
+            }
+        };
+        let name = format!("{OVERRIDES}/{}", file.install_path());
+        debug!(%name, "Embedding file");
+        zip.add_file(name, &local)
+            .context(IoSnafu { path: local })?;
+    }
+
+    let authors: Vec<_> = pack
+        .metadata
+        .authors()
+        .iter()
+        .map(|author| author.name.as_str())
+        .collect();
+    let manifest = ModpackManifest {
+        minecraft: Game {
+            version: pack.versions.minecraft.to_string(),
+            mod_loaders: vec![ModLoader {
+                id: loader_id(&pack.versions.loader),
+                primary: true,
+            }],
+        },
+        manifest_type: "minecraftModpack",
+        manifest_version: 1,
+        name: pack.metadata.name(None),
+        version: pack.metadata.version().to_string(),
+        author: authors.join(", "),
+        files,
+        overrides: OVERRIDES,
+    };
+    let json = serde_json::to_vec_pretty(&manifest).context(JsonSnafu)?;
+    zip.add_bytes(MANIFEST, json);
+    zip.finish().context(ZipSnafu)?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use std::{
+        fs,
+        io::{Cursor, Read},
+    };
+
+    use serde_json::Value;
+    use url::Url;
+
+    use super::*;
+    use crate::{
+        archive::ZipArchive,
+        lock::LockedFile,
+        resolve::{ProjectDetails, ResolvedArtifact, UpstreamIds},
+        types::Hashes,
+    };
+
+    // CurseForge files are referenced by id, and everything else is embedded and reported
+    #[test]
+    fn export() {
+        let dir = std::env::temp_dir().join(format!("ffpack-cf-export-{}", std::process::id()));
+        fs::create_dir_all(dir.join("instance/mods")).unwrap();
+        fs::write(dir.join("instance/mods/sodium.jar"), b"sodium").unwrap();
+        let jei = ManagedFile {
+            path: RelativePathBuf::from("mods/jei.jar"),
+            source: Source::Curseforge {
+                slug: "jei".into(),
+                file_id: None,
+            },
+            ..ManagedFile::default()
+        };
+        let sodium = ManagedFile {
+            path: RelativePathBuf::from("mods/sodium.jar"),
+            source: Source::Modrinth {
+                slug: "sodium".into(),
+                version_id: None,
+            },
+            ..ManagedFile::default()
+        };
+        let mut pack = Pack {
+            managed_files: [jei.clone(), sodium].into_iter().collect(),
+            ..Pack::default()
+        };
+        let mut lock = Lockfile::default();
+        let locked = |project_id: Option<&str>| LockedFile {
+            source: jei.source.clone(),
+            artifact: ResolvedArtifact {
+                download_url: Url::parse("https://edge.forgecdn.net/files/jei.jar").unwrap(),
+                filename: "jei.jar".into(),
+                size: None,
+                hashes: Hashes::default(),
+                mirrors: Vec::new(),
+                extract: None,
+                ids: UpstreamIds {
+                    project_id: project_id.map(Into::into),
+                    file_id: Some("4712866".into()),
+                    ..UpstreamIds::default()
+                },
+                details: ProjectDetails::default(),
+            },
+        };
+        lock.files.insert(jei.path.clone(), locked(Some("238222")));
+
+        let mut data = Cursor::new(Vec::new());
+        let report = super::export(&pack, &lock, &dir, &dir.join("instance"), &mut data).unwrap();
+        assert_eq!(report.redistributed, ["mods/sodium.jar"]);
+        data.set_position(0);
+        let mut archive = ZipArchive::new(data).unwrap();
+        assert!(archive.by_name("overrides/mods/sodium.jar").is_some());
+        let index = archive
+            .entries()
+            .iter()
+            .position(|entry| entry.name() == MANIFEST)
+            .unwrap();
+        let mut json = String::new();
+        archive
+            .open(index)
+            .unwrap()
+            .read_to_string(&mut json)
+            .unwrap();
+        let manifest: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            manifest["minecraft"]["modLoaders"][0]["id"],
+            "quilt-0.17.1-beta.3"
+        );
+        assert_eq!(manifest["files"][0]["projectID"], 238_222);
+        assert_eq!(manifest["files"][0]["fileID"], 4_712_866);
+
+        // Files locked before project ids were recorded have to be locked again
+        lock.files.insert(jei.path.clone(), locked(None));
+        assert!(matches!(
+            super::export(&pack, &lock, &dir, &dir, Cursor::new(Vec::new())),
+            Err(ExportError::MissingId { .. })
+        ));
+        pack.managed_files.remove(&jei);
+        assert!(matches!(
+            super::export(&pack, &lock, &dir, &dir, Cursor::new(Vec::new())),
+            Err(ExportError::Io { .. })
+        ));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
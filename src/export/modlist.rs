@@ -0,0 +1,389 @@
+//! Exporting human readable lists of a pack's files
+//!
+//! Mod lists are rendered from a template, as Markdown to embed in a README or as a standalone
+//! HTML page, and list every file with what's known about it from the manifest and the
+//! lockfile. Templates are text with fields in double braces:
+//!
+//! - `{{field}}` is replaced with the field's value, escaped for the output's format
+//! - `{{#files}}…{{/files}}` repeats its contents for each file
+//! - `{{#field}}…{{/field}}` keeps its contents only if the field isn't empty, and
+//!   `{{^field}}…{{/field}}` only if it is
+//!
+//! The pack has the fields `name`, `description`, `version`, `minecraft`, and `loader`. Each file
+//! has `name`, `description`, `version`, `side`, `url`, `license`, `authors`, and `path`, which
+//! hide the pack's fields of the same name inside `{{#files}}`.
+
+use std::{collections::BTreeMap, io::Write};
+
+use snafu::{ensure, OptionExt, ResultExt};
+use tracing::instrument;
+
+use super::{ExportError, OutputSnafu, TemplateSnafu};
+use crate::{
+    lock::Lockfile,
+    resolve::ResolvedArtifact,
+    types::{ManagedFile, Side, Source},
+    Pack,
+};
+
+/// The default template for Markdown lists
+pub const MARKDOWN_TEMPLATE: &str = "\
+# {{name}}
+
+{{#description}}{{description}}
+
+{{/description}}For Minecraft {{minecraft}} with {{loader}}.
+
+| Name | Version | Side | License | Description |
+| --- | --- | --- | --- | --- |
+{{#files}}| {{#url}}[{{name}}]({{url}}){{/url}}{{^url}}{{name}}{{/url}} | {{version}} | {{side}} | \
+{{license}} | {{description}} |
+{{/files}}";
+
+/// The default template for HTML pages
+pub const HTML_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{{name}}</title>
+<style>
+body { font-family: sans-serif; margin: 2em auto; max-width: 60em; }
+table { border-collapse: collapse; width: 100%; }
+th, td { border-bottom: 1px solid #ccc; padding: 0.4em; text-align: left; }
+</style>
+</head>
+<body>
+<h1>{{name}}</h1>
+{{#description}}<p>{{description}}</p>
+{{/description}}<p>For Minecraft {{minecraft}} with {{loader}}.</p>
+<table>
+<tr><th>Name</th><th>Version</th><th>Side</th><th>License</th><th>Description</th></tr>
+{{#files}}<tr><td>{{#url}}<a href="{{url}}">{{name}}</a>{{/url}}{{^url}}{{name}}{{/url}}</td><td>{{version}}</td><td>{{side}}</td><td>{{license}}</td><td>{{description}}</td></tr>
+{{/files}}</table>
+</body>
+</html>
+"#;
+
+/// The format a mod list is written in, which decides how values are escaped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModListFormat {
+    /// Markdown, for READMEs
+    Markdown,
+    /// HTML, for websites
+    Html,
+}
+
+impl ModListFormat {
+    /// Returns the template lists are rendered from unless another is given
+    pub fn default_template(self) -> &'static str {
+        match self {
+            ModListFormat::Markdown => MARKDOWN_TEMPLATE,
+            ModListFormat::Html => HTML_TEMPLATE,
+        }
+    }
+
+    /// Escapes a value so it shows up as is in the format
+    fn escape(self, value: &str) -> String {
+        let mut escaped = String::with_capacity(value.len());
+        for c in value.chars() {
+            match (self, c) {
+                // Table rows are a single line
+                (ModListFormat::Markdown, '\n') => escaped.push(' '),
+                (
+                    ModListFormat::Markdown,
+                    '\\' | '`' | '*' | '_' | '[' | ']' | '(' | ')' | '<' | '>' | '|',
+                ) => {
+                    escaped.push('\\');
+                    escaped.push(c);
+                }
+                (ModListFormat::Html, '&') => escaped.push_str("&amp;"),
+                (ModListFormat::Html, '<') => escaped.push_str("&lt;"),
+                (ModListFormat::Html, '>') => escaped.push_str("&gt;"),
+                (ModListFormat::Html, '"') => escaped.push_str("&quot;"),
+                (ModListFormat::Html, '\'') => escaped.push_str("&#39;"),
+                _ => escaped.push(c),
+            }
+        }
+        escaped
+    }
+}
+
+/// The value of a template field
+enum Value {
+    /// Text, empty when nothing is known
+    Text(String),
+    /// A list of items, each with fields of its own
+    List(Vec<Fields>),
+}
+
+/// The fields of the pack or of a file
+type Fields = BTreeMap<&'static str, Value>;
+
+/// A piece of a parsed template
+#[derive(Debug)]
+enum Node<'a> {
+    /// Text written out as is
+    Text(&'a str),
+    /// A field replaced with its value
+    Field(&'a str),
+    /// A section kept, repeated, or left out depending on a field
+    Section {
+        /// The field the section depends on
+        name: &'a str,
+        /// Whether the section is kept when the field is empty, instead of when it isn't
+        inverted: bool,
+        /// The contents of the section
+        children: Vec<Node<'a>>,
+    },
+}
+
+/// Parses a template, up to the end of the section `closing` if it is given
+fn parse<'a>(template: &mut &'a str, closing: Option<&str>) -> Result<Vec<Node<'a>>, ExportError> {
+    let mut nodes = Vec::new();
+    while let Some(start) = template.find("{{") {
+        if start > 0 {
+            nodes.push(Node::Text(&template[..start]));
+        }
+        let tag_start = &template[start + 2..];
+        let end = tag_start.find("}}").context(TemplateSnafu {
+            message: "a field isn't closed with `}}`",
+        })?;
+        let tag = tag_start[..end].trim();
+        *template = &tag_start[end + 2..];
+        if let Some(name) = tag.strip_prefix('/') {
+            ensure!(
+                closing == Some(name.trim()),
+                TemplateSnafu {
+                    message: format!("`{{{{/{}}}}}` doesn't close a section", name.trim()),
+                }
+            );
+            return Ok(nodes);
+        }
+        let (inverted, name) = match tag.strip_prefix('#') {
+            Some(name) => (false, Some(name)),
+            None => (true, tag.strip_prefix('^')),
+        };
+        nodes.push(match name {
+            Some(name) => {
+                let name = name.trim();
+                let children = parse(template, Some(name))?;
+                Node::Section {
+                    name,
+                    inverted,
+                    children,
+                }
+            }
+            None => Node::Field(tag),
+        });
+    }
+    if !template.is_empty() {
+        nodes.push(Node::Text(template));
+    }
+    match closing {
+        Some(name) => TemplateSnafu {
+            message: format!("`{{{{#{name}}}}}` isn't closed"),
+        }
+        .fail(),
+        None => Ok(nodes),
+    }
+}
+
+/// Looks a field up, in the innermost item that has it
+fn lookup<'a>(scopes: &[&'a Fields], name: &str) -> Result<&'a Value, ExportError> {
+    scopes
+        .iter()
+        .rev()
+        .find_map(|fields| fields.get(name))
+        .context(TemplateSnafu {
+            message: format!("there is no field `{name}`"),
+        })
+}
+
+/// Renders parsed template nodes into `out`
+fn render(
+    nodes: &[Node<'_>],
+    scopes: &mut Vec<&Fields>,
+    format: ModListFormat,
+    out: &mut String,
+) -> Result<(), ExportError> {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Field(name) => match lookup(scopes, name)? {
+                Value::Text(text) => out.push_str(&format.escape(text)),
+                Value::List(_) => {
+                    return TemplateSnafu {
+                        message: format!("`{name}` is a list, which only a section can show"),
+                    }
+                    .fail()
+                }
+            },
+            Node::Section {
+                name,
+                inverted,
+                children,
+            } => match lookup(scopes, name)? {
+                Value::List(items) if !inverted => {
+                    for item in items {
+                        scopes.push(item);
+                        render(children, scopes, format, out)?;
+                        scopes.pop();
+                    }
+                }
+                Value::List(items) if items.is_empty() => {
+                    render(children, scopes, format, out)?;
+                }
+                Value::Text(text) if text.is_empty() == *inverted => {
+                    render(children, scopes, format, out)?;
+                }
+                _ => {}
+            },
+        }
+    }
+    Ok(())
+}
+
+/// Collects the fields of a file, from the manifest and what the file was locked to
+fn file_fields(file: &ManagedFile, lock: &Lockfile) -> Fields {
+    let artifact = lock.get(&file.path).map(|locked| &locked.artifact);
+    let details = artifact.map(|artifact| &artifact.details);
+    let filename = file.path.file_name().unwrap_or(&file.filename);
+    let name = file
+        .name
+        .clone()
+        .or_else(|| details.and_then(|details| details.name.clone()))
+        .unwrap_or_else(|| filename.to_owned());
+    let url = details
+        .and_then(|details| details.url.clone())
+        .or_else(|| match &file.source {
+            Source::Url { url, .. } => Some(url.clone()),
+            _ => None,
+        });
+    let side = match file.side {
+        Side::Client => "client",
+        Side::Server => "server",
+        Side::Both => "both",
+    };
+    let text = |value: Option<String>| Value::Text(value.unwrap_or_default());
+    Fields::from([
+        ("name", Value::Text(name)),
+        ("description", text(file.description.clone())),
+        (
+            "version",
+            text(artifact.and_then(ResolvedArtifact::version_label)),
+        ),
+        ("side", Value::Text(side.into())),
+        ("url", text(url.map(String::from))),
+        (
+            "license",
+            text(details.and_then(|details| details.license.clone())),
+        ),
+        (
+            "authors",
+            text(details.map(|details| details.authors.join(", "))),
+        ),
+        ("path", Value::Text(file.path.to_string())),
+    ])
+}
+
+/// Renders a mod list of a pack from `template`, falling back on the format's default
+///
+/// Files are described from the manifest first, then from what `lock` locked them to, so files
+/// that aren't locked are listed with what the manifest says about them alone.
+///
+/// # Errors
+///
+/// Returns an error if the template is malformed or names a field that doesn't exist
+pub fn render_list(
+    pack: &Pack,
+    lock: &Lockfile,
+    format: ModListFormat,
+    template: Option<&str>,
+) -> Result<String, ExportError> {
+    let mut template = template.unwrap_or(format.default_template());
+    let nodes = parse(&mut template, None)?;
+    let loader = &pack.versions.loader;
+    let files = pack
+        .managed_files
+        .iter()
+        .map(|file| file_fields(file, lock))
+        .collect();
+    let fields = Fields::from([
+        ("name", Value::Text(pack.metadata.name(None).to_owned())),
+        (
+            "description",
+            Value::Text(pack.metadata.description(None).unwrap_or_default().into()),
+        ),
+        ("version", Value::Text(pack.metadata.version().to_string())),
+        (
+            "minecraft",
+            Value::Text(pack.versions.minecraft.to_string()),
+        ),
+        (
+            "loader",
+            Value::Text(format!("{} {}", loader.name(), loader.version())),
+        ),
+        ("files", Value::List(files)),
+    ]);
+    let mut out = String::new();
+    render(&nodes, &mut vec![&fields], format, &mut out)?;
+    Ok(out)
+}
+
+/// Writes a mod list of a pack to `writer`, rendered as by [`render_list`]
+///
+/// # Errors
+///
+/// Returns an error if the template is malformed, or `writer` fails
+#[instrument(skip_all, fields(?format))]
+pub fn export(
+    pack: &Pack,
+    lock: &Lockfile,
+    format: ModListFormat,
+    template: Option<&str>,
+    mut writer: impl Write,
+) -> Result<(), ExportError> {
+    let list = render_list(pack, lock, format, template)?;
+    writer.write_all(list.as_bytes()).context(OutputSnafu)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    // Files are listed through the default templates, with values escaped for the format
+    #[test]
+    fn default_templates() {
+        let mut pack = Pack::default();
+        pack.managed_files.insert(ManagedFile {
+            name: Some("Fish & <Chips>".into()),
+            path: "mods/fish.jar".into(),
+            side: Side::Client,
+            ..ManagedFile::default()
+        });
+        let lock = Lockfile::default();
+        let markdown = render_list(&pack, &lock, ModListFormat::Markdown, None).unwrap();
+        assert!(markdown.contains(
+            "| [Fish & \\<Chips\\>](https://example.org/mods/MyAwesomeMod-1.2.3.jar) |  | client |"
+        ));
+        let html = render_list(&pack, &lock, ModListFormat::Html, None).unwrap();
+        assert!(html.contains(">Fish &amp; &lt;Chips&gt;</a></td>"));
+    }
+
+    // Sections repeat over files and depend on fields, and broken templates are rejected
+    #[test]
+    fn templates() {
+        let pack = Pack::default();
+        let lock = Lockfile::default();
+        let render = |template| render_list(&pack, &lock, ModListFormat::Markdown, Some(template));
+        assert_eq!(
+            render("{{#files}}{{path}}{{^version}} (unlocked){{/version}};{{/files}}").unwrap(),
+            "mods/MyAwesomeMod.jar (unlocked);"
+        );
+        assert_eq!(render("{{ minecraft }}").unwrap(), "1.19");
+        assert!(render("{{#files}}").is_err());
+        assert!(render("{{#files}}{{/description}}").is_err());
+        assert!(render("{{nope}}").is_err());
+        assert!(render("{{files}}").is_err());
+    }
+}
@@ -0,0 +1,337 @@
+//! Exporting packs as Modrinth modpacks (`.mrpack`)
+//!
+//! An mrpack is a zip holding a `modrinth.index.json`, which lists the files launchers download
+//! along with their hashes and the sides they are for, and override folders copied into the
+//! instance as they are. Downloads come from the lockfile, so the export installs exactly what
+//! was locked; path sources are carried in the override folder for their side.
+
+use std::{collections::BTreeMap, io::Write, path::Path};
+
+use serde::Serialize;
+use snafu::{ensure, OptionExt, ResultExt};
+use tracing::{debug, instrument};
+use url::Url;
+
+use super::{
+    path_source, Capabilities, ExportError, IoSnafu, JsonSnafu, Loss, MissingHashSnafu,
+    MissingSizeSnafu, NotDownloadableSnafu, UnlockedSnafu, ZipSnafu,
+};
+use crate::{
+    archive::DeterministicZipWriter,
+    lock::Lockfile,
+    types::{HashAlgorithm, Loader, ManagedFile, Side, Source},
+    Pack,
+};
+
+/// The name of the format, as it appears in errors and warnings
+const FORMAT: &str = "mrpack";
+
+/// The name of the index inside the archive
+pub(crate) const INDEX: &str = "modrinth.index.json";
+
+/// The version of the index format written
+const FORMAT_VERSION: u32 = 1;
+
+/// The digests every file in the index needs
+const REQUIRED_HASHES: [HashAlgorithm; 2] = [HashAlgorithm::Sha1, HashAlgorithm::Sha512];
+
+/// What an mrpack can represent
+pub const CAPABILITIES: Capabilities = Capabilities {
+    client_only_files: true,
+    server_only_files: true,
+    embedded_files: true,
+    ..Capabilities::NONE
+};
+
+/// The index of an mrpack
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Index<'a> {
+    /// The version of the index format
+    format_version: u32,
+    /// The game the pack is for
+    game: &'static str,
+    /// The version of the pack
+    version_id: String,
+    /// The name of the pack
+    name: &'a str,
+    /// A short description of the pack
+    #[serde(skip_serializing_if = "Option::is_none")]
+    summary: Option<&'a str>,
+    /// The files launchers download
+    files: Vec<IndexFile>,
+    /// The versions of the game and loader, by their Modrinth ids
+    dependencies: BTreeMap<&'static str, String>,
+}
+
+/// A file listed in the index
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct IndexFile {
+    /// Where the file is installed in the instance
+    path: String,
+    /// The file's digests, by algorithm
+    hashes: BTreeMap<&'static str, String>,
+    /// Which sides the file is needed on
+    env: Env,
+    /// Where to download the file, in the order to try them
+    downloads: Vec<Url>,
+    /// The size of the file in bytes
+    file_size: u64,
+}
+
+/// Which sides a file is needed on
+#[derive(Serialize)]
+struct Env {
+    /// `required` or `unsupported` on the client
+    client: &'static str,
+    /// `required` or `unsupported` on the server
+    server: &'static str,
+}
+
+impl Env {
+    /// Returns the sides a file for `side` is needed on
+    fn new(side: &Side) -> Self {
+        let needed = |needed: bool| if needed { "required" } else { "unsupported" };
+        Self {
+            client: needed(side != &Side::Server),
+            server: needed(side != &Side::Client),
+        }
+    }
+}
+
+/// Returns the override folder files for `side` are copied from
+fn overrides(side: &Side) -> &'static str {
+    match side {
+        Side::Both => "overrides",
+        Side::Client => "client-overrides",
+        Side::Server => "server-overrides",
+    }
+}
+
+/// Returns the id Modrinth gives a loader in an index's dependencies
+fn loader_id(loader: &Loader) -> &'static str {
+    match loader {
+        Loader::Quilt(_) => "quilt-loader",
+        Loader::Fabric(_) => "fabric-loader",
+        Loader::Forge(_) => "forge",
+    }
+}
+
+/// Lists a managed file in the index, from what it was locked to
+fn index_file(file: &ManagedFile, lock: &Lockfile) -> Result<IndexFile, ExportError> {
+    let path = || file.path.clone();
+    let locked = lock
+        .get(&file.path)
+        .context(UnlockedSnafu { path: path() })?;
+    let artifact = &locked.artifact;
+    let downloadable =
+        artifact.extract.is_none() && matches!(artifact.download_url.scheme(), "http" | "https");
+    ensure!(
+        downloadable,
+        NotDownloadableSnafu {
+            path: path(),
+            format: FORMAT
+        }
+    );
+    let mut hashes = BTreeMap::new();
+    for algorithm in REQUIRED_HASHES {
+        let digest = artifact.hashes.hex(algorithm).context(MissingHashSnafu {
+            path: path(),
+            algorithm,
+            format: FORMAT,
+        })?;
+        hashes.insert(algorithm.name(), digest);
+    }
+    let file_size = artifact.size.context(MissingSizeSnafu {
+        path: path(),
+        format: FORMAT,
+    })?;
+    Ok(IndexFile {
+        path: file.install_path().to_string(),
+        hashes,
+        env: Env::new(&file.side),
+        downloads: [artifact.download_url.clone()]
+            .into_iter()
+            .chain(artifact.mirrors.iter().cloned())
+            .collect(),
+        file_size,
+    })
+}
+
+/// Writes a pack as an mrpack to `writer`, returning what it couldn't represent
+///
+/// Files are downloaded from what `lock` locked them to, which has to cover every file that
+/// isn't a path source, with their SHA-1 and SHA-512 digests and sizes. Path sources are read
+/// from under `root`, the directory the manifest is in. Everything lost in the export is logged
+/// as well as returned.
+///
+/// # Errors
+///
+/// Returns an error if a file can't be listed in the index or embedded, or the archive can't be
+/// written
+#[instrument(skip_all)]
+pub fn export(
+    pack: &Pack,
+    lock: &Lockfile,
+    root: &Path,
+    writer: impl Write,
+) -> Result<Vec<Loss>, ExportError> {
+    let losses = CAPABILITIES.warn_losses(pack, FORMAT);
+    let mut zip = DeterministicZipWriter::new(writer);
+    let mut files = Vec::new();
+    for file in &pack.managed_files {
+        let Source::Path { path, .. } = &file.source else {
+            files.push(index_file(file, lock)?);
+            continue;
+        };
+        let local = path_source(root, path)?;
+        let name = format!("{}/{}", overrides(&file.side), file.install_path());
+        debug!(%name, "Embedding file");
+        zip.add_file(name, &local)
+            .context(IoSnafu { path: local })?;
+    }
+
+    let mut dependencies = BTreeMap::from([("minecraft", pack.versions.minecraft.to_string())]);
+    let loader = &pack.versions.loader;
+    dependencies.insert(loader_id(loader), loader.version().to_string());
+    let index = Index {
+        format_version: FORMAT_VERSION,
+        game: "minecraft",
+        version_id: pack.metadata.version().to_string(),
+        name: pack.metadata.name(None),
+        summary: pack.metadata.description(None),
+        files,
+        dependencies,
+    };
+    let json = serde_json::to_vec_pretty(&index).context(JsonSnafu)?;
+    zip.add_bytes(INDEX, json);
+    zip.finish().context(ZipSnafu)?;
+    Ok(losses)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use std::{
+        fs,
+        io::{Cursor, Read},
+    };
+
+    use relative_path::RelativePathBuf;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::{
+        archive::ZipArchive,
+        hash::blake3,
+        lock::LockedFile,
+        resolve::{ProjectDetails, ResolvedArtifact, UpstreamIds},
+        types::Hashes,
+    };
+
+    // Locked files are listed in the index and path sources embedded as overrides
+    #[test]
+    fn export() {
+        let dir = std::env::temp_dir().join(format!("ffpack-mrpack-{}", std::process::id()));
+        fs::create_dir_all(dir.join("config")).unwrap();
+        fs::write(dir.join("config/client.toml"), b"fov = 90").unwrap();
+        let modrinth = ManagedFile {
+            path: RelativePathBuf::from("mods/sodium.jar"),
+            side: Side::Client,
+            source: Source::Modrinth {
+                slug: "sodium".into(),
+                version_id: None,
+            },
+            ..ManagedFile::default()
+        };
+        let config = ManagedFile {
+            path: RelativePathBuf::from("config/client.toml"),
+            side: Side::Client,
+            source: Source::Path {
+                path: RelativePathBuf::from("config/client.toml"),
+                blake3: blake3(b"fov = 90"),
+                hashes: Hashes::default(),
+            },
+            ..ManagedFile::default()
+        };
+        let mut pack = Pack {
+            managed_files: [modrinth.clone(), config].into_iter().collect(),
+            ..Pack::default()
+        };
+        let artifact = ResolvedArtifact {
+            download_url: Url::parse("https://cdn.modrinth.com/sodium.jar").unwrap(),
+            filename: "sodium.jar".into(),
+            size: Some(100),
+            hashes: Hashes {
+                sha1: Some([1; 20]),
+                sha512: Some([2; 64]),
+                ..Hashes::default()
+            },
+            mirrors: Vec::new(),
+            extract: None,
+            ids: UpstreamIds::default(),
+            details: ProjectDetails::default(),
+        };
+        let mut lock = Lockfile::default();
+        lock.files.insert(
+            modrinth.path.clone(),
+            LockedFile {
+                source: modrinth.source.clone(),
+                artifact: artifact.clone(),
+            },
+        );
+
+        let mut data = Cursor::new(Vec::new());
+        let losses = super::export(&pack, &lock, &dir, &mut data).unwrap();
+        assert!(losses
+            .iter()
+            .all(|loss| matches!(loss, Loss::FileDescription { .. })));
+        data.set_position(0);
+        let mut archive = ZipArchive::new(data).unwrap();
+        assert!(archive
+            .by_name("client-overrides/config/client.toml")
+            .is_some());
+        let index = archive
+            .entries()
+            .iter()
+            .position(|entry| entry.name() == INDEX)
+            .unwrap();
+        let mut json = String::new();
+        archive
+            .open(index)
+            .unwrap()
+            .read_to_string(&mut json)
+            .unwrap();
+        let index: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(index["formatVersion"], 1);
+        assert_eq!(index["dependencies"]["quilt-loader"], "0.17.1-beta.3");
+        let file = &index["files"][0];
+        assert_eq!(file["path"], "mods/sodium.jar");
+        assert_eq!(file["hashes"]["sha1"], "01".repeat(20));
+        assert_eq!(file["env"]["server"], "unsupported");
+        assert_eq!(file["fileSize"], 100);
+
+        // Files launchers couldn't install exactly as locked are refused
+        let mut incomplete = artifact;
+        incomplete.hashes.sha512 = None;
+        lock.files.get_mut(&modrinth.path).unwrap().artifact = incomplete;
+        let error = super::export(&pack, &lock, &dir, Cursor::new(Vec::new())).unwrap_err();
+        assert!(matches!(
+            error,
+            ExportError::MissingHash {
+                algorithm: HashAlgorithm::Sha512,
+                ..
+            }
+        ));
+        pack.managed_files.insert(ManagedFile {
+            path: RelativePathBuf::from("mods/new.jar"),
+            ..modrinth
+        });
+        lock.files.clear();
+        assert!(matches!(
+            super::export(&pack, &lock, &dir, Cursor::new(Vec::new())),
+            Err(ExportError::Unlocked { .. })
+        ));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,429 @@
+//! Exporting packs as MultiMC and Prism Launcher instances
+//!
+//! An instance zip holds `instance.cfg`, naming the instance and holding its settings,
+//! `mmc-pack.json`, listing the components the launcher sets the game up from, and the game
+//! directory under `.minecraft`. The game directory is either filled with the pack's client
+//! files, taken from an install of its lockfile, or left to packwiz-installer, which a
+//! pre-launch command runs to download them from a published packwiz project.
+//!
+//! Jarmods are kept in `jarmods/`, next to the game directory, each with a patch in `patches/`
+//! and a component after the loader's, in the order they are applied. They are named by a
+//! digest of their path in the pack, so that exporting a pack again names them the same.
+//! packwiz-installer only downloads into the game directory, so bootstrapped instances carry
+//! jarmods as plain files.
+
+use std::{io::Write, path::Path};
+
+use serde::Serialize;
+use snafu::ResultExt;
+use tracing::{debug, instrument};
+use url::Url;
+
+use super::{Capabilities, ExportError, IoSnafu, JsonSnafu, Loss, ZipSnafu};
+use crate::{
+    archive::DeterministicZipWriter,
+    hash::blake3,
+    types::{FileKind, Loader, ManagedFile, Side},
+    Pack,
+};
+
+/// The name of the format, as it appears in errors and warnings
+const FORMAT: &str = "MultiMC";
+
+/// The name of the instance settings inside the archive
+const INSTANCE_CFG: &str = "instance.cfg";
+
+/// The name of the component list inside the archive
+const MMC_PACK: &str = "mmc-pack.json";
+
+/// The game directory inside the archive
+const GAME_DIR: &str = ".minecraft";
+
+/// The folder of the instance jarmods are kept in
+const JARMODS_DIR: &str = "jarmods";
+
+/// The folder of the instance holding the patches of components the launcher has no metadata for
+const PATCHES_DIR: &str = "patches";
+
+/// The name the packwiz-installer bootstrap is given in the game directory
+pub const BOOTSTRAP_JAR: &str = "packwiz-installer-bootstrap.jar";
+
+/// What an instance can represent
+///
+/// Instances are for the client, so server only files are left out of them.
+pub const CAPABILITIES: Capabilities = Capabilities {
+    client_only_files: true,
+    server_only_files: true,
+    embedded_files: true,
+    jarmods: true,
+    ..Capabilities::NONE
+};
+
+/// Where the files of an instance's game directory come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameFiles<'a> {
+    /// The pack's client files are copied from where they are installed under this directory,
+    /// which should be an install of the pack's lockfile
+    Installed(&'a Path),
+    /// packwiz-installer downloads the files before every launch, keeping the instance up to
+    /// date with a published packwiz project
+    Bootstrap {
+        /// The url of the project's `pack.toml`
+        pack_url: &'a Url,
+        /// The packwiz-installer bootstrap jar to carry in the instance
+        bootstrap_jar: &'a Path,
+    },
+}
+
+/// The component list of an instance
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MmcPack {
+    /// The components, the game first
+    components: Vec<Component>,
+    /// The version of the list's format
+    format_version: u32,
+}
+
+/// A component of an instance, like the game, a loader, or a jarmod
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Component {
+    /// The component's id in the launcher's metadata, or of its patch
+    uid: String,
+    /// The version of the component, which jarmods don't have
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    /// The name the launcher shows for a component it has no metadata for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cached_name: Option<String>,
+    /// Whether the component can't be removed, as the game can't
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    important: bool,
+}
+
+/// A jarmod of the pack, as an instance holds it
+#[derive(Debug, PartialEq, Eq)]
+struct Jarmod {
+    /// The id it is known by in the instance, shaped like a UUID as the launcher makes them
+    id: String,
+    /// The name the launcher shows for it
+    name: String,
+}
+
+impl Jarmod {
+    /// Names the jarmod `file` by a digest of its path
+    fn new(file: &ManagedFile) -> Self {
+        let digest = hex::encode(blake3(file.path.as_str().as_bytes()));
+        let id = format!(
+            "{}-{}-{}-{}-{}",
+            &digest[..8],
+            &digest[8..12],
+            &digest[12..16],
+            &digest[16..20],
+            &digest[20..32]
+        );
+        let name = file.path.file_name().unwrap_or(file.path.as_str()).into();
+        Self { id, name }
+    }
+
+    /// Returns the id of its component
+    fn uid(&self) -> String {
+        format!("org.multimc.jarmod.{}", self.id)
+    }
+
+    /// Returns where it is kept in the instance
+    fn path(&self) -> String {
+        format!("{JARMODS_DIR}/{}.jar", self.id)
+    }
+}
+
+/// The patch a jarmod's component is set up from
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Patch {
+    /// The version of the patch's format
+    format_version: u32,
+    /// The jarmods the patch applies, which is only this one
+    jar_mods: [PatchJarmod; 1],
+    /// The name the launcher shows for it
+    name: String,
+    /// The id of its component
+    uid: String,
+}
+
+/// A jarmod a patch applies
+#[derive(Serialize)]
+struct PatchJarmod {
+    /// The name the launcher shows for it
+    #[serde(rename = "MMC-displayname")]
+    display_name: String,
+    /// Its file name in `jarmods/`
+    #[serde(rename = "MMC-filename")]
+    filename: String,
+    /// Where the launcher finds it, which is always the instance
+    #[serde(rename = "MMC-hint")]
+    hint: &'static str,
+    /// Its maven coordinate
+    name: String,
+}
+
+impl From<&Jarmod> for Patch {
+    fn from(jarmod: &Jarmod) -> Self {
+        Self {
+            format_version: 1,
+            jar_mods: [PatchJarmod {
+                display_name: jarmod.name.clone(),
+                filename: format!("{}.jar", jarmod.id),
+                hint: "local",
+                name: format!("org.multimc.jarmods:{}:1", jarmod.id),
+            }],
+            name: jarmod.name.clone(),
+            uid: jarmod.uid(),
+        }
+    }
+}
+
+/// Lists the components an instance of the pack is set up from, with `jarmods` last
+fn components(pack: &Pack, jarmods: &[Jarmod]) -> Vec<Component> {
+    let component = |uid: &str, version: String| Component {
+        uid: uid.into(),
+        version: Some(version),
+        cached_name: None,
+        important: false,
+    };
+    let minecraft = pack.versions.minecraft.to_string();
+    let mut components = vec![Component {
+        important: true,
+        ..component("net.minecraft", minecraft.clone())
+    }];
+    let loader = &pack.versions.loader;
+    let version = loader.version().to_string();
+    match loader {
+        Loader::Quilt(_) | Loader::Fabric(_) => {
+            // Both loaders map the game with Fabric's intermediary names
+            components.push(component("net.fabricmc.intermediary", minecraft));
+            let uid = if let Loader::Quilt(_) = loader {
+                "org.quiltmc.quilt-loader"
+            } else {
+                "net.fabricmc.fabric-loader"
+            };
+            components.push(component(uid, version));
+        }
+        Loader::Forge(_) => components.push(component("net.minecraftforge", version)),
+    }
+    for jarmod in jarmods {
+        components.push(Component {
+            uid: jarmod.uid(),
+            version: None,
+            cached_name: Some(jarmod.name.clone()),
+            important: false,
+        });
+    }
+    components
+}
+
+/// Writes the instance settings, which are `key=value` lines
+fn instance_cfg(pack: &Pack, files: GameFiles<'_>) -> String {
+    let mut lines = vec![
+        "InstanceType=OneSix".to_owned(),
+        format!("name={}", pack.metadata.name(None)),
+    ];
+    if let Some(description) = pack.metadata.description(None) {
+        lines.push(format!("notes={}", description.replace('\n', "\\n")));
+    }
+    if let GameFiles::Bootstrap { pack_url, .. } = files {
+        lines.push("OverrideCommands=true".into());
+        lines.push(format!(
+            "PreLaunchCommand=\"$INST_JAVA\" -jar {BOOTSTRAP_JAR} {pack_url}"
+        ));
+    }
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+/// Writes a pack as a MultiMC or Prism Launcher instance zip to `writer`, returning what it
+/// couldn't represent
+///
+/// Everything lost in the export is logged as well as returned.
+///
+/// # Errors
+///
+/// Returns an error if a file for the game directory can't be read, or the archive can't be
+/// written
+#[instrument(skip_all)]
+pub fn export(
+    pack: &Pack,
+    files: GameFiles<'_>,
+    writer: impl Write,
+) -> Result<Vec<Loss>, ExportError> {
+    let capabilities = match files {
+        GameFiles::Installed(_) => CAPABILITIES,
+        GameFiles::Bootstrap { .. } => Capabilities {
+            jarmods: false,
+            ..CAPABILITIES
+        },
+    };
+    let losses = capabilities.warn_losses(pack, FORMAT);
+    let mut zip = DeterministicZipWriter::new(writer);
+    let mut embed = |name: String, local: &Path| {
+        debug!(%name, "Embedding file");
+        zip.add_file(name, local).context(IoSnafu { path: local })
+    };
+    let mut jarmods = Vec::new();
+    match files {
+        GameFiles::Installed(installed) => {
+            let client = pack
+                .managed_files
+                .iter()
+                .filter(|file| file.side != Side::Server);
+            for file in client {
+                let path = file.install_path();
+                let local = path.to_path(installed);
+                if file.kind == FileKind::Jarmod {
+                    let jarmod = Jarmod::new(file);
+                    embed(jarmod.path(), &local)?;
+                    jarmods.push(jarmod);
+                } else {
+                    embed(format!("{GAME_DIR}/{path}"), &local)?;
+                }
+            }
+        }
+        GameFiles::Bootstrap { bootstrap_jar, .. } => {
+            embed(format!("{GAME_DIR}/{BOOTSTRAP_JAR}"), bootstrap_jar)?;
+        }
+    }
+
+    for jarmod in &jarmods {
+        let json = serde_json::to_vec_pretty(&Patch::from(jarmod)).context(JsonSnafu)?;
+        zip.add_bytes(format!("{PATCHES_DIR}/{}.json", jarmod.uid()), json);
+    }
+    let mmc_pack = MmcPack {
+        components: components(pack, &jarmods),
+        format_version: 1,
+    };
+    let json = serde_json::to_vec_pretty(&mmc_pack).context(JsonSnafu)?;
+    zip.add_bytes(MMC_PACK, json);
+    zip.add_bytes(INSTANCE_CFG, instance_cfg(pack, files));
+    zip.finish().context(ZipSnafu)?;
+    Ok(losses)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use std::{
+        fs,
+        io::{Cursor, Read},
+    };
+
+    use relative_path::RelativePathBuf;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::{archive::ZipArchive, types::ManagedFile};
+
+    /// Reads an entry of an archive as text
+    fn read(archive: &mut ZipArchive<Cursor<Vec<u8>>>, name: &str) -> String {
+        let index = archive
+            .entries()
+            .iter()
+            .position(|entry| entry.name() == name)
+            .unwrap();
+        let mut text = String::new();
+        archive
+            .open(index)
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        text
+    }
+
+    // Instances carry their client files and jarmods, or the installer that downloads them
+    #[test]
+    fn export() {
+        let dir = std::env::temp_dir().join(format!("ffpack-multimc-{}", std::process::id()));
+        fs::create_dir_all(dir.join("mods")).unwrap();
+        fs::create_dir_all(dir.join("jarmods")).unwrap();
+        fs::write(dir.join("mods/MyAwesomeMod.jar"), b"mod").unwrap();
+        fs::write(dir.join("jarmods/Patch.zip"), b"patch").unwrap();
+        fs::write(dir.join(BOOTSTRAP_JAR), b"bootstrap").unwrap();
+        let mut pack = Pack::default();
+        pack.managed_files.insert(ManagedFile {
+            path: RelativePathBuf::from("mods/server.jar"),
+            side: Side::Server,
+            ..ManagedFile::default()
+        });
+        let patch = ManagedFile {
+            path: RelativePathBuf::from("jarmods/Patch.zip"),
+            kind: FileKind::Jarmod,
+            collection: None,
+            ..ManagedFile::default()
+        };
+        let jarmod = Jarmod::new(&patch);
+        pack.managed_files.insert(patch);
+
+        let mut data = Cursor::new(Vec::new());
+        let losses = super::export(&pack, GameFiles::Installed(&dir), &mut data).unwrap();
+        assert!(!losses
+            .iter()
+            .any(|loss| matches!(loss, Loss::Jarmod { .. })));
+        data.set_position(0);
+        let mut archive = ZipArchive::new(data).unwrap();
+        assert!(archive
+            .by_name(".minecraft/mods/MyAwesomeMod.jar")
+            .is_some());
+        assert!(archive.by_name(".minecraft/mods/server.jar").is_none());
+        assert!(archive.by_name(".minecraft/jarmods/Patch.zip").is_none());
+        assert!(archive.by_name(&jarmod.path()).is_some());
+        let patch_name = format!("patches/{}.json", jarmod.uid());
+        let patch: Value = serde_json::from_str(&read(&mut archive, &patch_name)).unwrap();
+        assert_eq!(
+            patch["jarMods"][0]["MMC-filename"],
+            format!("{}.jar", jarmod.id)
+        );
+        assert_eq!(patch["name"], "Patch.zip");
+        let mmc_pack: Value = serde_json::from_str(&read(&mut archive, MMC_PACK)).unwrap();
+        let uids: Vec<_> = mmc_pack["components"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|component| component["uid"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            uids,
+            [
+                "net.minecraft",
+                "net.fabricmc.intermediary",
+                "org.quiltmc.quilt-loader",
+                &jarmod.uid(),
+            ]
+        );
+        assert_eq!(mmc_pack["components"][0]["important"], true);
+        assert!(!read(&mut archive, INSTANCE_CFG).contains("PreLaunchCommand"));
+
+        let pack_url = Url::parse("https://example.com/pack.toml").unwrap();
+        let files = GameFiles::Bootstrap {
+            pack_url: &pack_url,
+            bootstrap_jar: &dir.join(BOOTSTRAP_JAR),
+        };
+        let mut data = Cursor::new(Vec::new());
+        let losses = super::export(&pack, files, &mut data).unwrap();
+        assert!(losses.contains(&Loss::Jarmod {
+            path: RelativePathBuf::from("jarmods/Patch.zip")
+        }));
+        data.set_position(0);
+        let mut archive = ZipArchive::new(data).unwrap();
+        assert!(archive
+            .by_name(".minecraft/mods/MyAwesomeMod.jar")
+            .is_none());
+        assert!(archive
+            .by_name(".minecraft/packwiz-installer-bootstrap.jar")
+            .is_some());
+        let cfg = read(&mut archive, INSTANCE_CFG);
+        assert!(cfg.contains(
+            "PreLaunchCommand=\"$INST_JAVA\" -jar packwiz-installer-bootstrap.jar \
+             https://example.com/pack.toml"
+        ));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
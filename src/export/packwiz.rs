@@ -0,0 +1,300 @@
+//! Exporting packs as packwiz projects
+//!
+//! A packwiz project is a directory tree: `pack.toml` names the pack and the versions it is for,
+//! `index.toml` lists every other file with its hash, and each download is described by a
+//! `.pw.toml` metafile next to where it is installed. Files that aren't downloaded are copied
+//! into the tree as they are. [`import::packwiz`](crate::import::packwiz) reads projects back.
+
+use std::{fs, io::Read, path::Path};
+
+use relative_path::{RelativePath, RelativePathBuf};
+use snafu::{ensure, OptionExt, ResultExt};
+use tracing::{debug, instrument, warn};
+
+use super::{
+    open_path_source, Capabilities, ExportError, IoSnafu, Loss, MissingHashSnafu, MissingIdSnafu,
+    NotDownloadableSnafu, UnlockedSnafu, WriteSnafu,
+};
+use crate::{
+    hash::Sha256,
+    lock::Lockfile,
+    toml::{self, Table, Value},
+    types::{HashAlgorithm, Loader, ManagedFile, Side, Source},
+    Pack,
+};
+
+/// The name of the format, as it appears in errors and warnings
+const FORMAT: &str = "packwiz";
+
+/// The version of the project format written
+pub(crate) const PACK_FORMAT: &str = "packwiz:1.1.0";
+
+/// The name of the file describing the pack
+pub(crate) const PACK_FILE: &str = "pack.toml";
+
+/// The name of the index written next to `pack.toml`
+const INDEX_FILE: &str = "index.toml";
+
+/// The suffix of the metafiles describing downloads
+pub(crate) const METAFILE_SUFFIX: &str = ".pw.toml";
+
+/// The digests packwiz can check downloads against, in order of preference
+const HASHES: [HashAlgorithm; 4] = [
+    HashAlgorithm::Sha256,
+    HashAlgorithm::Sha512,
+    HashAlgorithm::Sha1,
+    HashAlgorithm::Md5,
+];
+
+/// What a packwiz project can represent
+///
+/// Only downloads have a side in packwiz, so path sources limited to one side are reported
+/// separately by [`export`].
+pub const CAPABILITIES: Capabilities = Capabilities {
+    client_only_files: true,
+    server_only_files: true,
+    embedded_files: true,
+    file_descriptions: true,
+    ..Capabilities::NONE
+};
+
+/// Builds a table from its keys and values
+fn table(pairs: impl IntoIterator<Item = (&'static str, Value)>) -> Table {
+    pairs
+        .into_iter()
+        .map(|(key, value)| (key.to_owned(), value))
+        .collect()
+}
+
+/// Returns packwiz's name for a side
+fn side_name(side: &Side) -> &'static str {
+    match side {
+        Side::Both => "both",
+        Side::Client => "client",
+        Side::Server => "server",
+    }
+}
+
+/// Returns the key of a loader's version in `pack.toml`
+fn loader_key(loader: &Loader) -> &'static str {
+    match loader {
+        Loader::Quilt(_) => "quilt",
+        Loader::Fabric(_) => "fabric",
+        Loader::Forge(_) => "forge",
+    }
+}
+
+/// Returns the SHA-256 digest of some data as hex
+fn sha256_hex(data: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(data);
+    hex::encode(hasher.finalize())
+}
+
+/// Returns where the metafile for a file installed at `path` goes
+pub(crate) fn metafile_path(path: &RelativePath) -> RelativePathBuf {
+    let stem = path.file_stem().unwrap_or_default();
+    path.with_file_name(format!("{stem}{METAFILE_SUFFIX}"))
+}
+
+/// Writes a file at `path` under `out`, creating its directory, and returns its SHA-256 digest
+/// as hex
+fn write_file(out: &Path, path: &RelativePath, contents: &[u8]) -> Result<String, ExportError> {
+    let local = path.to_path(out);
+    if let Some(parent) = local.parent() {
+        fs::create_dir_all(parent).context(WriteSnafu { path: parent })?;
+    }
+    fs::write(&local, contents).context(WriteSnafu { path: local })?;
+    Ok(sha256_hex(contents))
+}
+
+/// Builds the options of a file, which packwiz uses for files users can turn off, if it needs
+/// any
+///
+/// Disabled files become optional files that are off by default, and descriptions are kept
+/// with the options wherever there is one.
+fn options(file: &ManagedFile) -> Option<Table> {
+    if file.enabled && file.description.is_none() {
+        return None;
+    }
+    let mut options = table([("optional", (!file.enabled).into())]);
+    if !file.enabled {
+        options.insert("default".into(), false.into());
+    }
+    if let Some(description) = &file.description {
+        options.insert("description".into(), description.as_str().into());
+    }
+    Some(options)
+}
+
+/// Builds the metafile of a downloaded file, from what it was locked to
+fn metafile(file: &ManagedFile, lock: &Lockfile) -> Result<Table, ExportError> {
+    let path = || file.path.clone();
+    let locked = lock
+        .get(&file.path)
+        .context(UnlockedSnafu { path: path() })?;
+    let artifact = &locked.artifact;
+    let ids = &artifact.ids;
+    let mut download = Table::new();
+    let mut update = Table::new();
+    let accepted = if let Source::Curseforge { .. } = &file.source {
+        // packwiz looks CurseForge downloads up itself, since their urls aren't stable
+        let id = |id: Option<&String>| id.and_then(|id| id.parse::<i64>().ok());
+        let missing = || MissingIdSnafu {
+            path: path(),
+            format: FORMAT,
+        };
+        let project_id = id(ids.project_id.as_ref()).with_context(missing)?;
+        let file_id = id(ids.file_id.as_ref()).with_context(missing)?;
+        download.insert("mode".into(), "metadata:curseforge".into());
+        let ids = table([
+            ("file-id", file_id.into()),
+            ("project-id", project_id.into()),
+        ]);
+        update.insert("curseforge".into(), ids.into());
+        &[HashAlgorithm::Sha1][..]
+    } else {
+        let downloadable = artifact.extract.is_none()
+            && matches!(artifact.download_url.scheme(), "http" | "https");
+        ensure!(
+            downloadable,
+            NotDownloadableSnafu {
+                path: path(),
+                format: FORMAT
+            }
+        );
+        download.insert("url".into(), artifact.download_url.to_string().into());
+        if let (Source::Modrinth { slug, .. }, Some(version)) = (&file.source, &ids.version_id) {
+            let project = ids.project_id.as_ref().unwrap_or(slug);
+            let ids = table([
+                ("mod-id", project.as_str().into()),
+                ("version", version.as_str().into()),
+            ]);
+            update.insert("modrinth".into(), ids.into());
+        }
+        &HASHES[..]
+    };
+    let (algorithm, digest) = artifact
+        .hashes
+        .preferred(accepted)
+        .context(MissingHashSnafu {
+            path: path(),
+            algorithm: accepted[0],
+            format: FORMAT,
+        })?;
+    download.insert("hash-format".into(), algorithm.name().into());
+    download.insert("hash".into(), hex::encode(digest).into());
+
+    let filename = file.path.file_name().unwrap_or(&file.filename);
+    let name = file.name.as_deref().unwrap_or(filename);
+    let mut metafile = table([
+        ("name", name.into()),
+        ("filename", filename.into()),
+        ("side", side_name(&file.side).into()),
+        ("download", download.into()),
+    ]);
+    if !update.is_empty() {
+        metafile.insert("update".into(), update.into());
+    }
+    if let Some(options) = options(file) {
+        metafile.insert("option".into(), options.into());
+    }
+    Ok(metafile)
+}
+
+/// Builds `pack.toml`, pointing at an index with the given digest
+fn pack_file(pack: &Pack, index_hash: String) -> Table {
+    let metadata = &pack.metadata;
+    let authors: Vec<_> = metadata
+        .authors()
+        .iter()
+        .map(|author| author.name.as_str())
+        .collect();
+    let mut file = table([
+        ("name", metadata.name(None).into()),
+        ("author", authors.join(", ").into()),
+        ("version", metadata.version().to_string().into()),
+        ("pack-format", PACK_FORMAT.into()),
+    ]);
+    if let Some(description) = metadata.description(None) {
+        file.insert("description".into(), description.into());
+    }
+    let index = table([
+        ("file", INDEX_FILE.into()),
+        ("hash-format", "sha256".into()),
+        ("hash", index_hash.into()),
+    ]);
+    file.insert("index".into(), index.into());
+    let loader = &pack.versions.loader;
+    let versions = table([
+        ("minecraft", pack.versions.minecraft.to_string().into()),
+        (loader_key(loader), loader.version().to_string().into()),
+    ]);
+    file.insert("versions".into(), versions.into());
+    file
+}
+
+/// Writes a pack as a packwiz project into the directory `out`, returning what it couldn't
+/// represent
+///
+/// Downloads are described from what `lock` locked them to, which has to cover every file that
+/// isn't a path source. Path sources are read from under `root`, the directory the manifest is
+/// in, and copied into the project. Everything lost in the export is logged as well as
+/// returned.
+///
+/// # Errors
+///
+/// Returns an error if a download can't be described, a path source can't be read, or the
+/// project can't be written
+#[instrument(skip_all, fields(out = %out.display()))]
+pub fn export(
+    pack: &Pack,
+    lock: &Lockfile,
+    root: &Path,
+    out: &Path,
+) -> Result<Vec<Loss>, ExportError> {
+    let mut losses = CAPABILITIES.warn_losses(pack, FORMAT);
+    let mut files = Vec::new();
+    for file in &pack.managed_files {
+        let (path, contents, is_metafile) = if let Source::Path { path, .. } = &file.source {
+            if file.side != Side::Both {
+                let loss = Loss::SideFlag {
+                    path: file.path.clone(),
+                    side: file.side.clone(),
+                };
+                warn!(target_format = FORMAT, "{}", loss);
+                losses.push(loss);
+            }
+            let mut contents = Vec::new();
+            open_path_source(root, path)?
+                .read_to_end(&mut contents)
+                .context(IoSnafu {
+                    path: path.to_path(root),
+                })?;
+            (file.install_path(), contents, false)
+        } else {
+            let metafile = toml::to_string(&metafile(file, lock)?);
+            (metafile_path(&file.path), metafile.into_bytes(), true)
+        };
+        debug!(%path, "Writing file");
+        let hash = write_file(out, &path, &contents)?;
+        let mut entry = table([("file", path.as_str().into()), ("hash", hash.into())]);
+        if is_metafile {
+            entry.insert("metafile".into(), true.into());
+        }
+        files.push(Value::Table(entry));
+    }
+
+    let index = table([
+        ("hash-format", "sha256".into()),
+        ("files", Value::Array(files)),
+    ]);
+    let index_hash = write_file(
+        out,
+        RelativePath::new(INDEX_FILE),
+        toml::to_string(&index).as_bytes(),
+    )?;
+    let pack_file = toml::to_string(&pack_file(pack, index_hash));
+    write_file(out, RelativePath::new(PACK_FILE), pack_file.as_bytes())?;
+    Ok(losses)
+}
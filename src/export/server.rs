@@ -0,0 +1,306 @@
+//! Exporting the files a dedicated server needs
+//!
+//! A server pack is the game directory of a server: every file of the pack that isn't client
+//! only, written into a directory or a tarball to unpack next to the server. It can carry the
+//! loader's server launcher and scripts starting it too, so that admins can deploy it as is.
+//!
+//! Files with `devel` set are only used in development, so they are left out as well.
+
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use relative_path::RelativePathBuf;
+use snafu::ResultExt;
+use tracing::{debug, instrument};
+use url::Url;
+
+use super::{path_source, Capabilities, ExportError, IoSnafu, Loss, TarSnafu, WriteSnafu};
+use crate::{
+    archive::TarWriter,
+    types::{Loader, Side, Source, Versions},
+    Pack,
+};
+
+/// The name of the format, as it appears in errors and warnings
+const FORMAT: &str = "server pack";
+
+/// The name the loader's server launcher is given in the pack
+pub const LAUNCHER_JAR: &str = "server-launch.jar";
+
+/// The version of Fabric's installer that server launchers are built with
+const FABRIC_INSTALLER: &str = "1.0.1";
+
+/// What a server pack can represent
+///
+/// Server packs are for the server, so client only and development files are left out of them.
+pub const CAPABILITIES: Capabilities = Capabilities {
+    file_descriptions: false,
+    icon: false,
+    gallery: false,
+    jarmods: false,
+    ..Capabilities::FULL
+};
+
+/// What a server pack carries beyond the pack's files
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ServerOptions {
+    /// The loader's server launcher jar to carry
+    launcher: Option<PathBuf>,
+    /// The arguments start scripts pass to java, if they are generated
+    start_scripts: Option<String>,
+}
+
+impl ServerOptions {
+    /// Carries the loader's server launcher jar at `jar` as [`LAUNCHER_JAR`], such as one
+    /// downloaded from [`launcher_url`]
+    #[must_use]
+    pub fn with_launcher(mut self, jar: impl Into<PathBuf>) -> Self {
+        self.launcher = Some(jar.into());
+        self
+    }
+
+    /// Generates `start.sh` and `start.bat`, which run [`LAUNCHER_JAR`] with `java_args`
+    /// passed to java, like `-Xmx4G`
+    #[must_use]
+    pub fn with_start_scripts(mut self, java_args: impl Into<String>) -> Self {
+        self.start_scripts = Some(java_args.into());
+        self
+    }
+}
+
+/// Returns where the server launcher for a pack's versions can be downloaded, where the loader
+/// publishes one
+///
+/// Only Fabric serves launchers directly. Quilt and Forge build theirs with their installers,
+/// which have to be run by hand.
+pub fn launcher_url(versions: &Versions) -> Option<Url> {
+    let Loader::Fabric(loader) = &versions.loader else {
+        return None;
+    };
+    let minecraft = &versions.minecraft;
+    Url::parse(&format!(
+        "https://meta.fabricmc.net/v2/versions/loader/{minecraft}/{loader}/{FABRIC_INSTALLER}/server/jar"
+    ))
+    .ok()
+}
+
+/// What an entry of a server pack holds
+enum Contents {
+    /// The file at a local path
+    Local(PathBuf),
+    /// Generated contents
+    Generated(String),
+}
+
+/// A file of a server pack
+struct Entry {
+    /// Where the file goes in the pack
+    name: RelativePathBuf,
+    /// What the file holds
+    contents: Contents,
+    /// Whether the file is a script to run
+    executable: bool,
+}
+
+/// Lists the files of a server pack
+fn entries(
+    pack: &Pack,
+    root: &Path,
+    installed: &Path,
+    options: &ServerOptions,
+) -> Result<Vec<Entry>, ExportError> {
+    let mut entries = Vec::new();
+    let server = pack
+        .managed_files
+        .iter()
+        .filter(|file| file.side != Side::Client && !file.devel);
+    for file in server {
+        let name = file.install_path();
+        let local = match &file.source {
+            Source::Path { path, .. } => path_source(root, path)?,
+            _ => name.to_path(installed),
+        };
+        entries.push(Entry {
+            name,
+            contents: Contents::Local(local),
+            executable: false,
+        });
+    }
+    if let Some(launcher) = &options.launcher {
+        entries.push(Entry {
+            name: LAUNCHER_JAR.into(),
+            contents: Contents::Local(launcher.clone()),
+            executable: false,
+        });
+    }
+    if let Some(java_args) = &options.start_scripts {
+        let sh = format!(
+            "#!/bin/sh\ncd \"$(dirname \"$0\")\"\nexec java {java_args} -jar {LAUNCHER_JAR} nogui \"$@\"\n"
+        );
+        let bat = format!(
+            "@echo off\r\ncd /d \"%~dp0\"\r\njava {java_args} -jar {LAUNCHER_JAR} nogui %*\r\n"
+        );
+        for (name, script) in [("start.sh", sh), ("start.bat", bat)] {
+            entries.push(Entry {
+                name: name.into(),
+                contents: Contents::Generated(script),
+                executable: true,
+            });
+        }
+    }
+    Ok(entries)
+}
+
+/// Writes a server pack into the directory `out`, returning what it couldn't represent
+///
+/// Path sources are read from under `root`, the directory the manifest is in, and every other
+/// file from where it is installed under `installed`, which should be an install of the pack's
+/// lockfile. Everything lost in the export is logged as well as returned.
+///
+/// # Errors
+///
+/// Returns an error if a file can't be copied into `out`, or a path source leads out of
+/// `root`
+#[instrument(skip_all, fields(out = %out.display()))]
+pub fn export_dir(
+    pack: &Pack,
+    root: &Path,
+    installed: &Path,
+    options: &ServerOptions,
+    out: &Path,
+) -> Result<Vec<Loss>, ExportError> {
+    let losses = CAPABILITIES.warn_losses(pack, FORMAT);
+    for entry in entries(pack, root, installed, options)? {
+        let destination = entry.name.to_path(out);
+        debug!(name = %entry.name, "Writing file");
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent).context(WriteSnafu { path: parent })?;
+        }
+        let written = match &entry.contents {
+            Contents::Local(local) => fs::copy(local, &destination).map(drop),
+            Contents::Generated(contents) => fs::write(&destination, contents),
+        };
+        written.context(WriteSnafu { path: &destination })?;
+        #[cfg(unix)]
+        if entry.executable {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&destination, fs::Permissions::from_mode(0o755))
+                .context(WriteSnafu { path: destination })?;
+        }
+    }
+    Ok(losses)
+}
+
+/// Writes a server pack as a tarball to `writer`, returning what it couldn't represent
+///
+/// Files are found as by [`export_dir`].
+///
+/// # Errors
+///
+/// Returns an error if a file can't be read, a path source leads out of `root`, or the
+/// tarball can't be written
+#[instrument(skip_all)]
+pub fn export_tar(
+    pack: &Pack,
+    root: &Path,
+    installed: &Path,
+    options: &ServerOptions,
+    writer: impl Write,
+) -> Result<Vec<Loss>, ExportError> {
+    let losses = CAPABILITIES.warn_losses(pack, FORMAT);
+    let mut tar = TarWriter::new(writer);
+    for entry in entries(pack, root, installed, options)? {
+        let name = entry.name.as_str();
+        let mode = if entry.executable { 0o755 } else { 0o644 };
+        match &entry.contents {
+            Contents::Local(local) => {
+                let mut file = File::open(local).context(IoSnafu { path: local })?;
+                let size = file.metadata().context(IoSnafu { path: local })?.len();
+                tar.append(name, mode, size, &mut file)
+            }
+            Contents::Generated(contents) => {
+                tar.append(name, mode, contents.len() as u64, &mut contents.as_bytes())
+            }
+        }
+        .context(TarSnafu)?;
+    }
+    tar.finish().context(TarSnafu)?;
+    Ok(losses)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::{hash::blake3, types::Hashes, types::ManagedFile};
+
+    // Only files the server needs are exported, along with the launcher and scripts, leaving out
+    // client and development files
+    #[test]
+    fn export() {
+        let dir = std::env::temp_dir().join(format!("ffpack-server-{}", std::process::id()));
+        fs::create_dir_all(dir.join("installed/mods")).unwrap();
+        fs::create_dir_all(dir.join("repo/config")).unwrap();
+        fs::write(dir.join("installed/mods/MyAwesomeMod.jar"), b"mod").unwrap();
+        fs::write(dir.join("repo/config/server.properties"), b"motd=hi").unwrap();
+        fs::write(dir.join("launcher.jar"), b"launcher").unwrap();
+        let mut pack = Pack::default();
+        let template = pack.managed_files.pop_first().unwrap();
+        pack.managed_files.insert(ManagedFile {
+            devel: false,
+            ..template
+        });
+        pack.managed_files.insert(ManagedFile {
+            path: RelativePathBuf::from("mods/debugger.jar"),
+            ..ManagedFile::default()
+        });
+        pack.managed_files.insert(ManagedFile {
+            path: RelativePathBuf::from("mods/sodium.jar"),
+            side: Side::Client,
+            ..ManagedFile::default()
+        });
+        pack.managed_files.insert(ManagedFile {
+            path: RelativePathBuf::from("server.properties"),
+            devel: false,
+            source: Source::Path {
+                path: RelativePathBuf::from("config/server.properties"),
+                blake3: blake3(b"motd=hi"),
+                hashes: Hashes::default(),
+            },
+            ..ManagedFile::default()
+        });
+        let options = ServerOptions::default()
+            .with_launcher(dir.join("launcher.jar"))
+            .with_start_scripts("-Xmx4G");
+
+        let out = dir.join("out");
+        let (root, installed) = (dir.join("repo"), dir.join("installed"));
+        export_dir(&pack, &root, &installed, &options, &out).unwrap();
+        assert_eq!(fs::read(out.join("server.properties")).unwrap(), b"motd=hi");
+        assert!(out.join("mods/MyAwesomeMod.jar").exists());
+        assert!(!out.join("mods/sodium.jar").exists());
+        assert!(!out.join("mods/debugger.jar").exists());
+        assert_eq!(fs::read(out.join(LAUNCHER_JAR)).unwrap(), b"launcher");
+        let script = fs::read_to_string(out.join("start.sh")).unwrap();
+        assert!(script.contains("exec java -Xmx4G -jar server-launch.jar nogui"));
+
+        let mut data = Vec::new();
+        export_tar(&pack, &root, &installed, &options, &mut data).unwrap();
+        assert_eq!(&data[..20], b"mods/MyAwesomeMod.ja");
+        // Five entries of one header and one block each, then the end of the archive
+        assert_eq!(data.len(), 512 * (5 * 2 + 2));
+        fs::remove_dir_all(dir).unwrap();
+
+        let versions = Versions {
+            loader: Loader::Fabric(semver::Version::new(0, 15, 0)),
+            ..Versions::default()
+        };
+        assert!(launcher_url(&versions)
+            .unwrap()
+            .as_str()
+            .ends_with("/server/jar"));
+        assert!(launcher_url(&Versions::default()).is_none());
+    }
+}
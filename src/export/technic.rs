@@ -0,0 +1,279 @@
+//! Exporting packs as Technic Solder repositories
+//!
+//! Solder serves a pack's builds as lists of mods, each a zip that the Technic launcher
+//! extracts into the instance. The exported repository holds those zips at
+//! `mods/{slug}/{slug}-{version}.zip`, the layout Solder reads its mirror from, and a
+//! `build.json` shaped like Solder's build API, to import the build from or serve as is.
+//!
+//! The Technic launcher installs loaders from a mod holding `bin/modpack.jar`, which has to be
+//! added to the build by hand.
+
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+use snafu::{OptionExt, ResultExt};
+use tracing::{debug, instrument};
+use url::Url;
+
+use super::{
+    path_source, Capabilities, ExportError, IoSnafu, JsonSnafu, Loss, UnlockedSnafu, WriteSnafu,
+    ZipSnafu,
+};
+use crate::{
+    archive::DeterministicZipWriter,
+    hash::hash_file,
+    lock::Lockfile,
+    types::{HashAlgorithm, ManagedFile, Side, Source},
+    Pack,
+};
+
+/// The name of the format, as it appears in errors and warnings
+const FORMAT: &str = "Technic";
+
+/// The name of the build description in the repository
+pub const BUILD_JSON: &str = "build.json";
+
+/// What a Technic pack can represent
+///
+/// The Technic launcher is a client, so server only files are left out of builds.
+pub const CAPABILITIES: Capabilities = Capabilities {
+    client_only_files: true,
+    server_only_files: true,
+    embedded_files: true,
+    ..Capabilities::NONE
+};
+
+/// A build of a pack, as Solder's API describes it
+#[derive(Serialize)]
+struct Build {
+    /// The version of the game
+    minecraft: String,
+    /// The mods of the build
+    mods: Vec<BuildMod>,
+}
+
+/// A mod of a build
+#[derive(Serialize)]
+struct BuildMod {
+    /// The mod's slug in Solder
+    name: String,
+    /// The version of the mod
+    version: String,
+    /// Where the mod's zip is served
+    url: String,
+    /// The MD5 digest of the mod's zip
+    md5: String,
+    /// The size of the mod's zip in bytes
+    filesize: u64,
+}
+
+/// Turns text into a Solder slug or version, which are lowercase letters, digits, dots, and
+/// dashes
+fn slugify(text: &str) -> String {
+    let slug: String = text
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    slug.trim_matches('-').to_owned()
+}
+
+/// Picks the slug and version a file is published under, and where its contents are read from
+fn identify(
+    file: &ManagedFile,
+    lock: &Lockfile,
+    root: &Path,
+    installed: &Path,
+) -> Result<(String, String, PathBuf), ExportError> {
+    if let Source::Path { path, blake3, .. } = &file.source {
+        let stem = file.path.file_stem().unwrap_or_default();
+        let version = hex::encode(&blake3[..4]);
+        return Ok((slugify(stem), version, path_source(root, path)?));
+    }
+    let locked = lock.get(&file.path).context(UnlockedSnafu {
+        path: file.path.clone(),
+    })?;
+    let slug = match &file.source {
+        Source::Modrinth { slug, .. } | Source::Curseforge { slug, .. } => slug.as_str(),
+        _ => file.path.file_stem().unwrap_or_default(),
+    };
+    // Files without a version are told apart by their digest
+    let artifact = &locked.artifact;
+    let version = artifact
+        .version_label()
+        .or_else(|| {
+            artifact
+                .hashes
+                .blake3
+                .map(|blake3| hex::encode(&blake3[..4]))
+        })
+        .unwrap_or_else(|| "0".into());
+    Ok((
+        slugify(slug),
+        slugify(&version),
+        file.install_path().to_path(installed),
+    ))
+}
+
+/// Writes a pack as a Technic Solder repository into the directory `out`, returning what it
+/// couldn't represent
+///
+/// Each client file becomes a mod zip holding it at its install path. Path sources are read
+/// from under `root`, the directory the manifest is in, and every other file from where it is
+/// installed under `installed`, which should be an install of `lock`. `repository` is where
+/// the repository will be served from, which the urls in `build.json` point under. Everything
+/// lost in the export is logged as well as returned.
+///
+/// # Errors
+///
+/// Returns an error if a file isn't locked or can't be read, a path source leads out of
+/// `root`, or the repository can't be written
+#[instrument(skip_all, fields(out = %out.display()))]
+pub fn export(
+    pack: &Pack,
+    lock: &Lockfile,
+    root: &Path,
+    installed: &Path,
+    repository: &Url,
+    out: &Path,
+) -> Result<Vec<Loss>, ExportError> {
+    let losses = CAPABILITIES.warn_losses(pack, FORMAT);
+    let base = repository.as_str().trim_end_matches('/');
+    let mut mods = Vec::new();
+    let client = pack
+        .managed_files
+        .iter()
+        .filter(|file| file.side != Side::Server);
+    for file in client {
+        let (slug, version, local) = identify(file, lock, root, installed)?;
+        let name = format!("mods/{slug}/{slug}-{version}.zip");
+        debug!(%name, path = %file.path, "Writing mod");
+        let destination = out.join(&name);
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent).context(WriteSnafu { path: parent })?;
+        }
+        let writer = File::create(&destination).context(WriteSnafu { path: &destination })?;
+        let mut zip = DeterministicZipWriter::new(writer);
+        zip.add_file(file.install_path().as_str(), &local)
+            .context(IoSnafu { path: &local })?;
+        zip.finish().context(ZipSnafu)?;
+
+        let hashes = hash_file(&destination, [HashAlgorithm::Md5])
+            .context(IoSnafu { path: &destination })?;
+        let filesize = fs::metadata(&destination)
+            .context(IoSnafu { path: &destination })?
+            .len();
+        mods.push(BuildMod {
+            url: format!("{base}/{name}"),
+            name: slug,
+            version,
+            md5: hashes.hex(HashAlgorithm::Md5).unwrap_or_default(),
+            filesize,
+        });
+    }
+
+    let build = Build {
+        minecraft: pack.versions.minecraft.to_string(),
+        mods,
+    };
+    let json = serde_json::to_vec_pretty(&build).context(JsonSnafu)?;
+    let path = out.join(BUILD_JSON);
+    fs::write(&path, json).context(WriteSnafu { path })?;
+    Ok(losses)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use relative_path::RelativePathBuf;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::{
+        hash::blake3,
+        lock::LockedFile,
+        resolve::{ProjectDetails, ResolvedArtifact, UpstreamIds},
+        types::Hashes,
+    };
+
+    // Client files become mod zips, listed in the build with their digests
+    #[test]
+    fn export() {
+        let dir = std::env::temp_dir().join(format!("ffpack-technic-{}", std::process::id()));
+        fs::create_dir_all(dir.join("installed/mods")).unwrap();
+        fs::create_dir_all(dir.join("repo")).unwrap();
+        fs::write(dir.join("installed/mods/MyAwesomeMod.jar"), b"mod").unwrap();
+        fs::write(dir.join("repo/options.txt"), b"fov:90").unwrap();
+        let mut pack = Pack::default();
+        pack.managed_files.insert(ManagedFile {
+            path: RelativePathBuf::from("options.txt"),
+            source: Source::Path {
+                path: RelativePathBuf::from("options.txt"),
+                blake3: blake3(b"fov:90"),
+                hashes: Hashes::default(),
+            },
+            ..ManagedFile::default()
+        });
+        pack.managed_files.insert(ManagedFile {
+            path: RelativePathBuf::from("mods/server.jar"),
+            side: Side::Server,
+            ..ManagedFile::default()
+        });
+        let mut lock = Lockfile::default();
+        let file = ManagedFile::default();
+        let mut artifact = ResolvedArtifact {
+            download_url: Url::parse("https://example.com/MyAwesomeMod.jar").unwrap(),
+            filename: file.filename.clone(),
+            size: None,
+            hashes: Hashes::default(),
+            mirrors: Vec::new(),
+            extract: None,
+            ids: UpstreamIds::default(),
+            details: ProjectDetails::default(),
+        };
+        artifact.details.version = Some("1.2.0+mc1.20".into());
+        lock.files.insert(
+            file.path.clone(),
+            LockedFile {
+                source: file.source,
+                artifact,
+            },
+        );
+
+        let out = dir.join("out");
+        let repository = Url::parse("https://solder.example.com/repo/").unwrap();
+        let repo = dir.join("repo");
+        super::export(
+            &pack,
+            &lock,
+            &repo,
+            &dir.join("installed"),
+            &repository,
+            &out,
+        )
+        .unwrap();
+        let build: Value =
+            serde_json::from_slice(&fs::read(out.join(BUILD_JSON)).unwrap()).unwrap();
+        let mods = build["mods"].as_array().unwrap();
+        assert_eq!(mods.len(), 2);
+        assert_eq!(mods[0]["name"], "myawesomemod");
+        assert_eq!(mods[0]["version"], "1.2.0-mc1.20");
+        assert_eq!(
+            mods[0]["url"],
+            "https://solder.example.com/repo/mods/myawesomemod/myawesomemod-1.2.0-mc1.20.zip"
+        );
+        assert!(out
+            .join("mods/myawesomemod/myawesomemod-1.2.0-mc1.20.zip")
+            .exists());
+        assert_eq!(mods[1]["name"], "options");
+        assert_eq!(mods[1]["md5"].as_str().unwrap().len(), 32);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
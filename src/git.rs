@@ -0,0 +1,469 @@
+//! Checking out the repositories git sources point at
+//!
+//! None of ffpack's dependencies implement git, so checkouts shell out to the `git` command
+//! line. Each repository and [`GitRef`] gets a directory of its own under a cache root, filled
+//! by a shallow fetch so a long history doesn't come along with it. The [`Checkout`] hands back
+//! the working tree and the commit it is at, for [`Git::build`] or path extraction to take the
+//! artifact from, and for a lockfile to pin. A checkout pinned to a commit that is already in
+//! the cache is reused without touching the network.
+
+mod build;
+
+pub use self::build::Built;
+
+use std::{
+    ffi::OsStr,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use snafu::{ensure, ResultExt, Snafu};
+use tracing::{debug, instrument};
+use url::Url;
+
+use crate::{hash::blake3, http::NetworkPolicy};
+
+/// What to check out of a repository
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GitRef {
+    /// The tip of the repository's default branch
+    Default,
+    /// The tip of a branch
+    Branch(String),
+    /// A tag
+    Tag(String),
+    /// A commit, by its id
+    Rev(String),
+}
+
+impl GitRef {
+    /// Picks the most specific of the references a git source gives: a commit, then a tag, then
+    /// a branch
+    pub fn new(branch: Option<&str>, tag: Option<&str>, rev: Option<&str>) -> Self {
+        match (rev, tag, branch) {
+            (Some(rev), _, _) => GitRef::Rev(rev.to_string()),
+            (None, Some(tag), _) => GitRef::Tag(tag.to_string()),
+            (None, None, Some(branch)) => GitRef::Branch(branch.to_string()),
+            (None, None, None) => GitRef::Default,
+        }
+    }
+
+    /// The refspec to fetch for this reference
+    fn refspec(&self) -> String {
+        match self {
+            GitRef::Default => "HEAD".to_string(),
+            GitRef::Branch(branch) => format!("refs/heads/{branch}"),
+            GitRef::Tag(tag) => format!("refs/tags/{tag}"),
+            GitRef::Rev(rev) => rev.clone(),
+        }
+    }
+}
+
+impl Display for GitRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GitRef::Default => write!(f, "the default branch"),
+            GitRef::Branch(branch) => write!(f, "branch {branch}"),
+            GitRef::Tag(tag) => write!(f, "tag {tag}"),
+            GitRef::Rev(rev) => write!(f, "commit {rev}"),
+        }
+    }
+}
+
+/// A working tree checked out of a repository
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Checkout {
+    /// The directory holding the working tree
+    pub path: PathBuf,
+    /// The full id of the commit that is checked out
+    pub commit: String,
+}
+
+/// Checks out repositories into a cache directory
+#[derive(Debug, Clone)]
+pub struct Git {
+    /// The git executable
+    program: PathBuf,
+    /// The directory checkouts are kept in
+    cache: PathBuf,
+    /// Whether checkouts may fetch
+    network: NetworkPolicy,
+    /// Whether build commands may run
+    builds: bool,
+}
+
+impl Git {
+    /// Creates a checkout cache under `cache`, running `git` from the
+    /// `PATH`
+    pub fn new(cache: impl Into<PathBuf>) -> Self {
+        Self {
+            program: PathBuf::from("git"),
+            cache: cache.into(),
+            network: NetworkPolicy::default(),
+            builds: false,
+        }
+    }
+
+    /// Sets the git executable to run
+    #[must_use]
+    pub fn with_program(mut self, program: impl Into<PathBuf>) -> Self {
+        self.program = program.into();
+        self
+    }
+
+    /// Sets whether checkouts may fetch
+    ///
+    /// Offline, only commits that were checked out before are available, so sources have to be
+    /// pinned with `rev`.
+    #[must_use]
+    pub fn with_network(mut self, network: NetworkPolicy) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// Sets whether build commands may run, which they may not by default
+    ///
+    /// A build runs whatever command the manifest names, in a repository the manifest names, so
+    /// it is only allowed when whoever runs ffpack chooses to trust the pack. Builds cached
+    /// earlier are used either way, as using them runs nothing.
+    #[must_use]
+    pub fn with_builds(mut self, builds: bool) -> Self {
+        self.builds = builds;
+        self
+    }
+
+    /// Checks out `reference` of the repository at `url`, returning the working tree
+    ///
+    /// The tree belongs to the cache, and is reset by the next checkout of the same reference.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if git can't be run, the repository or reference can't be fetched, or a
+    /// fetch would be needed while offline
+    #[instrument(skip(self), fields(url = %url, reference = %reference))]
+    pub fn checkout(&self, url: &Url, reference: &GitRef) -> Result<Checkout, GitError> {
+        let key = blake3(format!("{url}\n{}", reference.refspec()).as_bytes());
+        let path = self.cache.join(hex::encode(&key[..8]));
+        let initialized = path.join(".git").is_dir();
+        if let (GitRef::Rev(rev), true) = (reference, initialized) {
+            let pinned = self.run(
+                &path,
+                ["rev-parse", "--verify", &format!("{rev}^{{commit}}")],
+            );
+            let head = self.run(&path, ["rev-parse", "HEAD"]);
+            if let (Ok(pinned), Ok(head)) = (pinned, head) {
+                if pinned == head {
+                    debug!("Reusing cached checkout");
+                    return Ok(Checkout { path, commit: head });
+                }
+            }
+        }
+        ensure!(
+            self.network == NetworkPolicy::Online,
+            WouldRequireNetworkSnafu {
+                url: url.clone(),
+                reference: reference.clone(),
+            }
+        );
+        if !initialized {
+            fs::create_dir_all(&path).context(IoSnafu { path: &path })?;
+            self.run(&path, ["init", "--quiet"])?;
+        }
+
+        let refspec = reference.refspec();
+        let shallow = self.run(
+            &path,
+            ["fetch", "--quiet", "--depth", "1", url.as_str(), &refspec],
+        );
+        let target = match (shallow, reference) {
+            (Ok(_), _) => "FETCH_HEAD",
+            // Servers may refuse to hand out a commit by id, but will send the whole history
+            (Err(error), GitRef::Rev(rev)) => {
+                debug!(%error, "Fetching the commit failed, fetching everything instead");
+                self.run(
+                    &path,
+                    [
+                        "fetch",
+                        "--quiet",
+                        url.as_str(),
+                        "+refs/heads/*:refs/remotes/origin/*",
+                        "+refs/tags/*:refs/tags/*",
+                    ],
+                )?;
+                rev
+            }
+            (Err(error), _) => return Err(error),
+        };
+        self.run(
+            &path,
+            ["checkout", "--quiet", "--force", "--detach", target],
+        )?;
+        let commit = self.run(&path, ["rev-parse", "HEAD"])?;
+        debug!(%commit, "Checked out");
+        Ok(Checkout { path, commit })
+    }
+
+    /// Runs git in `dir`, returning what it printed with surrounding whitespace removed
+    fn run<'a>(
+        &self,
+        dir: &Path,
+        args: impl IntoIterator<Item = &'a str> + Clone,
+    ) -> Result<String, GitError> {
+        let output = Command::new(&self.program)
+            .arg("-C")
+            .arg(dir)
+            .args(args.clone().into_iter().map(OsStr::new))
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .stdin(Stdio::null())
+            .output()
+            .context(SpawnSnafu {
+                program: &self.program,
+            })?;
+        ensure!(
+            output.status.success(),
+            FailedSnafu {
+                command: args.into_iter().collect::<Vec<_>>().join(" "),
+                stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            }
+        );
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+}
+
+/// Error that occurs while checking out a repository
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum GitError {
+    /// Git couldn't be run at all
+    #[snafu(display("Failed to run {}: {}", program.display(), source))]
+    Spawn {
+        /// The git executable
+        program: PathBuf,
+        /// The underlying error
+        source: std::io::Error,
+    },
+    /// A git command failed
+    #[snafu(display("`git {}` failed: {}", command, stderr))]
+    Failed {
+        /// The arguments git was run with
+        command: String,
+        /// What git printed about the failure
+        stderr: String,
+    },
+    /// The checkout directory couldn't be created
+    #[snafu(display("Failed to access {}: {}", path.display(), source))]
+    Io {
+        /// The directory being created
+        path: PathBuf,
+        /// The underlying error
+        source: std::io::Error,
+    },
+    /// A build was needed, but builds aren't allowed
+    #[snafu(display("Building {} would run `{}`, and builds aren't allowed", url, command))]
+    BuildNotAllowed {
+        /// The repository
+        url: Url,
+        /// The build command
+        command: String,
+    },
+    /// A build command couldn't be run
+    #[snafu(display("Failed to run `{}`: {}", command, source))]
+    BuildSpawn {
+        /// The build command
+        command: String,
+        /// The underlying error
+        source: std::io::Error,
+    },
+    /// A build command failed
+    #[snafu(display("`{}` failed:\n{}", command, output))]
+    BuildFailed {
+        /// The build command
+        command: String,
+        /// The last lines the build printed
+        output: String,
+    },
+    /// No built file matched the artifact glob
+    #[snafu(display("The build left no file matching {}", artifact))]
+    NoBuildOutput {
+        /// The artifact glob
+        artifact: String,
+    },
+    /// More than one built file matched the artifact glob
+    #[snafu(display(
+        "The build left {} files matching {}: {}",
+        matches.len(),
+        artifact,
+        matches.join(", ")
+    ))]
+    AmbiguousBuild {
+        /// The artifact glob
+        artifact: String,
+        /// The matching files, relative to the checkout
+        matches: Vec<String>,
+    },
+    /// A fetch was needed while offline
+    #[snafu(display("Checking out {} of {} requires the network", reference, url))]
+    WouldRequireNetwork {
+        /// The repository
+        url: Url,
+        /// What was to be checked out
+        reference: GitRef,
+    },
+}
+
+impl GitError {
+    /// Suggests how the user might fix the problem, if there is a likely fix
+    pub fn suggestion(&self) -> Option<String> {
+        match self {
+            GitError::Spawn { .. } => {
+                Some("Install git and make sure it is on the PATH to use git sources".into())
+            }
+            GitError::WouldRequireNetwork { reference, .. } => Some(match reference {
+                GitRef::Rev(_) => "Check the commit out once while online".into(),
+                _ => "Pin the source to a commit with `rev`, and check it out once while online"
+                    .into(),
+            }),
+            GitError::BuildNotAllowed { .. } => Some(
+                "Allow builds, such as with `ffpack --allow-build`, if you trust the pack and the \
+                 repository, or use a url source for a built file instead"
+                    .into(),
+            ),
+            GitError::BuildSpawn { .. } => Some(
+                "Check that the build command exists in the repository, or is installed".into(),
+            ),
+            GitError::NoBuildOutput { .. } => Some(
+                "Check the artifact glob against where the build puts its output, relative to \
+                 the repository root"
+                    .into(),
+            ),
+            GitError::AmbiguousBuild { .. } => {
+                Some("Make the artifact glob specific enough to match only one of these".into())
+            }
+            GitError::Failed { .. } | GitError::BuildFailed { .. } | GitError::Io { .. } => None,
+        }
+    }
+}
+
+/// Helpers for exercising checkouts against a local repository
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::{fs, path::PathBuf, process::Command};
+
+    use url::Url;
+
+    /// A repository in a scratch directory, removed again when dropped
+    pub(crate) struct Upstream {
+        /// The scratch directory, holding the repository and a cache to check it out into
+        pub(crate) root: PathBuf,
+        /// The url of the repository
+        pub(crate) url: Url,
+    }
+
+    impl Upstream {
+        /// Creates an empty repository on `main`, or nothing where git isn't installed
+        pub(crate) fn new(name: &str) -> Option<Self> {
+            Command::new("git").arg("--version").output().ok()?;
+            let root =
+                std::env::temp_dir().join(format!("ffpack-git-{name}-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&root);
+            fs::create_dir_all(root.join("upstream")).unwrap();
+            let url = Url::from_directory_path(root.join("upstream")).unwrap();
+            let upstream = Self { root, url };
+            upstream.git(&["init", "--quiet", "--initial-branch", "main"]);
+            Some(upstream)
+        }
+
+        /// The directory to keep checkouts in
+        pub(crate) fn cache(&self) -> PathBuf {
+            self.root.join("cache")
+        }
+
+        /// Runs git in the repository, with an identity to commit as
+        pub(crate) fn git(&self, args: &[&str]) -> String {
+            let output = Command::new("git")
+                .args([
+                    "-c",
+                    "user.name=Tester",
+                    "-c",
+                    "user.email=tester@example.org",
+                ])
+                .arg("-C")
+                .arg(self.root.join("upstream"))
+                .args(args)
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "git {args:?} failed");
+            String::from_utf8(output.stdout).unwrap().trim().to_string()
+        }
+
+        /// Writes and commits files, returning the id of the commit
+        pub(crate) fn commit(&self, files: &[(&str, &str)]) -> String {
+            for (path, contents) in files {
+                fs::write(self.root.join("upstream").join(path), contents).unwrap();
+                self.git(&["add", path]);
+            }
+            self.git(&["commit", "--quiet", "-m", "Change things"]);
+            self.git(&["rev-parse", "HEAD"])
+        }
+    }
+
+    impl Drop for Upstream {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::{testing::Upstream, *};
+
+    // Branches, tags, and commits check out, and pinned commits are reused offline
+    #[test]
+    fn checkout() {
+        let Some(upstream) = Upstream::new("checkout") else {
+            return;
+        };
+        let first = upstream.commit(&[("mod.txt", "first")]);
+        upstream.git(&["tag", "-a", "v1", "-m", "v1"]);
+        let second = upstream.commit(&[("mod.txt", "second")]);
+        let url = &upstream.url;
+
+        let git = Git::new(upstream.cache());
+        let read = |checkout: &Checkout| fs::read_to_string(checkout.path.join("mod.txt")).unwrap();
+        let tip = git.checkout(url, &GitRef::Default).unwrap();
+        assert_eq!(
+            (read(&tip).as_str(), tip.commit.as_str()),
+            ("second", &*second)
+        );
+        let tagged = git.checkout(url, &GitRef::new(Some("main"), Some("v1"), None));
+        assert_eq!(tagged.unwrap().commit, first);
+        let branch = git.checkout(url, &GitRef::Branch("main".into())).unwrap();
+        assert_eq!(branch.commit, second);
+        // Abbreviated ids can't be fetched directly, so this takes the full fetch
+        let pinned = GitRef::Rev(first[..12].to_string());
+        let checkout = git.checkout(url, &pinned).unwrap();
+        assert_eq!(
+            (read(&checkout).as_str(), checkout.commit.as_str()),
+            ("first", &*first)
+        );
+
+        let offline = git.clone().with_network(NetworkPolicy::Offline);
+        assert_eq!(offline.checkout(url, &pinned).unwrap(), checkout);
+        assert!(matches!(
+            offline.checkout(url, &GitRef::Default),
+            Err(GitError::WouldRequireNetwork { .. })
+        ));
+        assert!(matches!(
+            git.checkout(url, &GitRef::Branch("missing".into())),
+            Err(GitError::Failed { .. })
+        ));
+        let missing = git.clone().with_program(upstream.root.join("no-git"));
+        assert!(matches!(
+            missing.checkout(url, &GitRef::Default),
+            Err(GitError::Spawn { .. })
+        ));
+    }
+}
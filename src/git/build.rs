@@ -0,0 +1,319 @@
+//! Building the file a git source provides out of its checkout
+//!
+//! Built files are copied out of the working tree into `builds/` in the cache, under the commit
+//! they were built from and the build they were built with. A later resolution of the same
+//! commit takes the copy, without checking out or building again.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use regex::Regex;
+use snafu::{ensure, ResultExt};
+use tracing::{debug, info, instrument};
+use url::Url;
+
+use super::{
+    AmbiguousBuildSnafu, BuildFailedSnafu, BuildNotAllowedSnafu, BuildSpawnSnafu, Checkout, Git,
+    GitError, GitRef, IoSnafu, NoBuildOutputSnafu,
+};
+use crate::{
+    hash::{blake3, hash_file},
+    types::{Build, HashAlgorithm, Hashes},
+};
+
+/// How many lines of a failed build's output are kept for the error
+const OUTPUT_LINES: usize = 20;
+
+/// A file built out of a repository
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Built {
+    /// Where the built file is kept in the cache, as an absolute path
+    pub path: PathBuf,
+    /// The full id of the commit it was built from
+    pub commit: String,
+    /// The size of the file
+    pub size: u64,
+    /// The digests of the file, under every algorithm
+    pub hashes: Hashes,
+}
+
+impl Built {
+    /// Reads the size and digests of a built file
+    fn new(path: PathBuf, commit: String) -> Result<Self, GitError> {
+        let path = fs::canonicalize(&path).context(IoSnafu { path })?;
+        let size = fs::metadata(&path).context(IoSnafu { path: &path })?.len();
+        let hashes = hash_file(&path, HashAlgorithm::ALL).context(IoSnafu { path: &path })?;
+        Ok(Self {
+            path,
+            commit,
+            size,
+            hashes,
+        })
+    }
+}
+
+impl Git {
+    /// Checks out `reference` of the repository at `url` and builds it, returning the built file
+    ///
+    /// Building runs the source's own code, so it only happens once
+    /// [allowed](Git::with_builds). Until then only builds cached earlier are returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the checkout fails, the build isn't cached and builds aren't allowed,
+    /// the build command can't be run or fails, or the build didn't leave exactly one file
+    /// matching the artifact glob behind
+    #[instrument(skip(self, build), fields(url = %url, reference = %reference))]
+    pub fn build(&self, url: &Url, reference: &GitRef, build: &Build) -> Result<Built, GitError> {
+        // A commit that was built before needs neither a fetch nor a build
+        if let GitRef::Rev(rev) = reference {
+            if matches!(rev.len(), 40 | 64) {
+                if let Some(path) = self.cached_build(rev, build) {
+                    return Built::new(path, rev.clone());
+                }
+            }
+        }
+        let Checkout { path, commit } = self.checkout(url, reference)?;
+        if let Some(path) = self.cached_build(&commit, build) {
+            return Built::new(path, commit);
+        }
+
+        let command = command(build);
+        ensure!(
+            self.builds,
+            BuildNotAllowedSnafu {
+                url: url.clone(),
+                command: command.join(" ")
+            }
+        );
+        // Outputs of an earlier build of another commit would be picked up with the new one
+        self.run(&path, ["clean", "--quiet", "-d", "-x", "--force"])?;
+        let (program, args) = command.split_first().expect("Commands aren't empty");
+        // Programs given as a path are found in the checkout rather than where ffpack runs
+        let program = if program.contains('/') {
+            path.join(program)
+        } else {
+            PathBuf::from(program)
+        };
+        let command = command.join(" ");
+        info!(%command, "Building");
+        let output = Command::new(program)
+            .args(args)
+            .current_dir(&path)
+            .stdin(Stdio::null())
+            .output()
+            .context(BuildSpawnSnafu { command: &command })?;
+        if !output.status.success() {
+            let printed = String::from_utf8_lossy(&output.stdout).into_owned()
+                + &String::from_utf8_lossy(&output.stderr);
+            let lines: Vec<_> = printed.lines().collect();
+            let output = lines[lines.len().saturating_sub(OUTPUT_LINES)..].join("\n");
+            return BuildFailedSnafu { command, output }.fail();
+        }
+
+        let matches = find(&path, &build.artifact);
+        let artifact = match <[PathBuf; 1]>::try_from(matches) {
+            Ok([artifact]) => artifact,
+            Err(matches) if matches.is_empty() => {
+                return NoBuildOutputSnafu {
+                    artifact: &build.artifact,
+                }
+                .fail()
+            }
+            Err(matches) => {
+                return AmbiguousBuildSnafu {
+                    artifact: &build.artifact,
+                    matches: matches
+                        .iter()
+                        .map(|path| path.display().to_string())
+                        .collect::<Vec<_>>(),
+                }
+                .fail()
+            }
+        };
+        let dir = self.build_dir(&commit, build);
+        fs::create_dir_all(&dir).context(IoSnafu { path: &dir })?;
+        let name = artifact.file_name().unwrap_or_default();
+        let partial = dir.join(".partial");
+        fs::copy(path.join(&artifact), &partial).context(IoSnafu { path: &partial })?;
+        let built = dir.join(name);
+        fs::rename(&partial, &built).context(IoSnafu { path: &built })?;
+        debug!(path = %built.display(), "Built");
+        Built::new(built, commit)
+    }
+
+    /// The directory builds of `commit` with `build` are kept in
+    fn build_dir(&self, commit: &str, build: &Build) -> PathBuf {
+        let key = blake3(format!("{}\n{}", command(build).join("\0"), build.artifact).as_bytes());
+        self.cache
+            .join("builds")
+            .join(format!("{commit}-{}", hex::encode(&key[..4])))
+    }
+
+    /// Returns the file an earlier build of `commit` left, if there is one
+    fn cached_build(&self, commit: &str, build: &Build) -> Option<PathBuf> {
+        let mut files = fs::read_dir(self.build_dir(commit, build))
+            .ok()?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.file_name().is_some_and(|name| name != ".partial"));
+        let path = files.next()?;
+        debug!(path = %path.display(), "Reusing cached build");
+        Some(path)
+    }
+}
+
+/// The command a build runs, which is the default for an empty one
+fn command(build: &Build) -> Vec<String> {
+    if build.command.is_empty() {
+        Build::gradle()
+    } else {
+        build.command.clone()
+    }
+}
+
+/// Translates a glob into a regex matching whole `/`-separated paths
+fn glob_regex(glob: &str) -> Regex {
+    let mut regex = String::from("^");
+    let mut rest = glob;
+    while let Some(c) = rest.chars().next() {
+        if let Some(after) = rest.strip_prefix("**/") {
+            regex.push_str("(?:[^/]*/)*");
+            rest = after;
+            continue;
+        }
+        if let Some(after) = rest.strip_prefix("**") {
+            regex.push_str(".*");
+            rest = after;
+            continue;
+        }
+        match c {
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    regex.push('$');
+    Regex::new(&regex).expect("Everything but wildcards is escaped")
+}
+
+/// Finds the files under `root` matching `glob`, as paths relative to it, leaving out `.git`
+fn find(root: &Path, glob: &str) -> Vec<PathBuf> {
+    let regex = glob_regex(glob);
+    let mut matches = Vec::new();
+    let mut pending = vec![PathBuf::new()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(root.join(&dir)) else {
+            continue;
+        };
+        for entry in entries.filter_map(Result::ok) {
+            let path = dir.join(entry.file_name());
+            let Ok(kind) = entry.file_type() else {
+                continue;
+            };
+            if kind.is_dir() {
+                if entry.file_name() != ".git" {
+                    pending.push(path);
+                }
+            } else {
+                let relative = path
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                if regex.is_match(&relative) {
+                    matches.push(path);
+                }
+            }
+        }
+    }
+    matches.sort();
+    matches
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::{git::testing::Upstream, http::NetworkPolicy};
+
+    /// A build script leaving a file behind whose name differs on every run
+    const SCRIPT: &str = "mkdir -p out && echo \"built $(cat mod.txt)\" > out/mod-$$.jar";
+
+    // Builds run once per commit, only when allowed, and are reused for pinned commits offline
+    #[cfg(unix)]
+    #[test]
+    fn build() {
+        let Some(upstream) = Upstream::new("build") else {
+            return;
+        };
+        let first = upstream.commit(&[("build.sh", SCRIPT), ("mod.txt", "one")]);
+        let build = Build {
+            command: vec!["sh".into(), "build.sh".into()],
+            artifact: "out/*.jar".into(),
+            filename: None,
+        };
+        let refused = Git::new(upstream.cache());
+        assert!(matches!(
+            refused.build(&upstream.url, &GitRef::Default, &build),
+            Err(GitError::BuildNotAllowed { .. })
+        ));
+        let git = refused.clone().with_builds(true);
+        let output = git.build(&upstream.url, &GitRef::Default, &build).unwrap();
+        // Once built, the build is used without running anything
+        let cached = refused.build(&upstream.url, &GitRef::Rev(first.clone()), &build);
+        assert_eq!(cached.unwrap(), output);
+        assert_eq!(output.commit, first);
+        assert_eq!(fs::read(&output.path).unwrap(), b"built one\n");
+        assert_eq!(output.hashes.blake3, Some(blake3(b"built one\n")));
+        let again = git.build(&upstream.url, &GitRef::Default, &build).unwrap();
+        assert_eq!(again, output);
+
+        // Outputs of the earlier commit are cleaned away before building the next
+        upstream.commit(&[("mod.txt", "two")]);
+        let next = git.build(&upstream.url, &GitRef::Default, &build).unwrap();
+        assert_eq!(fs::read(&next.path).unwrap(), b"built two\n");
+        let offline = git.clone().with_network(NetworkPolicy::Offline);
+        let pinned = offline.build(&upstream.url, &GitRef::Rev(first), &build);
+        assert_eq!(pinned.unwrap(), output);
+
+        let glob = |artifact: &str| Build {
+            artifact: artifact.into(),
+            ..build.clone()
+        };
+        assert!(matches!(
+            git.build(&upstream.url, &GitRef::Default, &glob("**")),
+            Err(GitError::AmbiguousBuild { .. })
+        ));
+        assert!(matches!(
+            git.build(&upstream.url, &GitRef::Default, &glob("libs/*.jar")),
+            Err(GitError::NoBuildOutput { .. })
+        ));
+        let broken = Build {
+            command: vec!["sh".into(), "-c".into(), "echo broken; exit 1".into()],
+            ..build
+        };
+        match git.build(&upstream.url, &GitRef::Default, &broken) {
+            Err(GitError::BuildFailed { output, .. }) => assert_eq!(output, "broken"),
+            other => panic!("Expected the build to fail, got {other:?}"),
+        }
+    }
+
+    // Globs match within components, or across them with **
+    #[test]
+    fn globs() {
+        let regex = glob_regex("build/libs/*-?.jar");
+        assert!(regex.is_match("build/libs/mod-1.jar"));
+        assert!(!regex.is_match("build/libs/mod-10.jar"));
+        assert!(!regex.is_match("build/libs/sub/mod-1.jar"));
+        assert!(!regex.is_match("xbuild/libs/mod-1.jar"));
+        let regex = glob_regex("**/libs/*.jar");
+        assert!(regex.is_match("libs/mod.jar"));
+        assert!(regex.is_match("fabric/build/libs/mod.jar"));
+        assert!(glob_regex("a.b").is_match("a.b"));
+        assert!(!glob_regex("a.b").is_match("axb"));
+    }
+}
@@ -0,0 +1,208 @@
+//! Hash functions used to verify files
+//!
+//! These are implemented on the standard library alone, and all follow the same shape: create a
+//! hasher, feed it with `update`, and read the digest with `finalize`. [`Hasher`] runs several of
+//! them over the same input at once. The odd one out is [`curseforge_fingerprint`], which
+//! can't be computed incrementally.
+
+mod blake3;
+mod md5;
+mod murmur2;
+mod sha1;
+mod sha2;
+
+pub use self::{
+    blake3::Blake3,
+    md5::Md5,
+    murmur2::curseforge_fingerprint,
+    sha1::Sha1,
+    sha2::{Sha256, Sha512},
+};
+use std::{fs::File, io::Read, path::Path};
+
+use crate::types::{HashAlgorithm, Hashes};
+
+/// Hashes a whole buffer with BLAKE3
+pub fn blake3(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Blake3::new();
+    hasher.update(data);
+    hasher.finalize()
+}
+
+/// Hashes a file under the given algorithms, reading it in chunks
+///
+/// # Errors
+///
+/// Returns an error if the file can't be read
+pub fn hash_file(
+    path: &Path,
+    algorithms: impl IntoIterator<Item = HashAlgorithm>,
+) -> std::io::Result<Hashes> {
+    let mut hasher = Hasher::new(algorithms);
+    let mut file = File::open(path)?;
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(hasher.finalize());
+        }
+        hasher.update(&buffer[..read]);
+    }
+}
+
+/// Collects input into the fixed size blocks of a Merkle–Damgård hash
+#[derive(Debug, Clone)]
+struct BlockBuffer<const N: usize> {
+    /// The block being filled
+    block: [u8; N],
+    /// How much of the block is filled
+    filled: usize,
+    /// The number of bytes fed in so far
+    length: u128,
+}
+
+impl<const N: usize> Default for BlockBuffer<N> {
+    fn default() -> Self {
+        Self {
+            block: [0; N],
+            filled: 0,
+            length: 0,
+        }
+    }
+}
+
+impl<const N: usize> BlockBuffer<N> {
+    /// Adds input, calling `compress` for every block that fills up
+    fn update(&mut self, mut input: &[u8], mut compress: impl FnMut(&[u8; N])) {
+        self.length += input.len() as u128;
+        if self.filled > 0 {
+            let take = (N - self.filled).min(input.len());
+            self.block[self.filled..self.filled + take].copy_from_slice(&input[..take]);
+            self.filled += take;
+            input = &input[take..];
+            if self.filled < N {
+                return;
+            }
+            compress(&self.block);
+            self.filled = 0;
+        }
+        let mut blocks = input.chunks_exact(N);
+        for block in &mut blocks {
+            compress(block.try_into().unwrap());
+        }
+        let rest = blocks.remainder();
+        self.block[..rest.len()].copy_from_slice(rest);
+        self.filled = rest.len();
+    }
+
+    /// The number of bits fed in so far
+    fn bits(&self) -> u128 {
+        self.length.wrapping_mul(8)
+    }
+
+    /// Pads the buffered input with a one bit, zeros, and the encoded `length`, calling
+    /// `compress` for the final one or two blocks
+    fn pad(&self, length: &[u8], mut compress: impl FnMut(&[u8; N])) {
+        let mut block = self.block;
+        block[self.filled] = 0x80;
+        block[self.filled + 1..].fill(0);
+        if self.filled + 1 + length.len() > N {
+            compress(&block);
+            block = [0; N];
+        }
+        block[N - length.len()..].copy_from_slice(length);
+        compress(&block);
+    }
+}
+
+/// Computes digests under several algorithms in one pass over the input
+#[derive(Debug, Clone, Default)]
+pub struct Hasher {
+    /// The BLAKE3 hasher, if requested
+    blake3: Option<Blake3>,
+    /// The SHA-1 hasher, if requested
+    sha1: Option<Sha1>,
+    /// The SHA-256 hasher, if requested
+    sha256: Option<Sha256>,
+    /// The SHA-512 hasher, if requested
+    sha512: Option<Sha512>,
+    /// The MD5 hasher, if requested
+    md5: Option<Md5>,
+}
+
+impl Hasher {
+    /// Creates a hasher computing the given algorithms
+    pub fn new(algorithms: impl IntoIterator<Item = HashAlgorithm>) -> Self {
+        let mut hasher = Self::default();
+        for algorithm in algorithms {
+            match algorithm {
+                HashAlgorithm::Blake3 => hasher.blake3 = Some(Blake3::new()),
+                HashAlgorithm::Sha1 => hasher.sha1 = Some(Sha1::new()),
+                HashAlgorithm::Sha256 => hasher.sha256 = Some(Sha256::new()),
+                HashAlgorithm::Sha512 => hasher.sha512 = Some(Sha512::new()),
+                HashAlgorithm::Md5 => hasher.md5 = Some(Md5::new()),
+            }
+        }
+        hasher
+    }
+
+    /// Returns true if no algorithms are being computed
+    pub fn is_empty(&self) -> bool {
+        self.blake3.is_none()
+            && self.sha1.is_none()
+            && self.sha256.is_none()
+            && self.sha512.is_none()
+            && self.md5.is_none()
+    }
+
+    /// Adds input to every hash
+    pub fn update(&mut self, input: &[u8]) {
+        if let Some(hasher) = &mut self.blake3 {
+            hasher.update(input);
+        }
+        if let Some(hasher) = &mut self.sha1 {
+            hasher.update(input);
+        }
+        if let Some(hasher) = &mut self.sha256 {
+            hasher.update(input);
+        }
+        if let Some(hasher) = &mut self.sha512 {
+            hasher.update(input);
+        }
+        if let Some(hasher) = &mut self.md5 {
+            hasher.update(input);
+        }
+    }
+
+    /// Returns the digests of everything added so far
+    pub fn finalize(&self) -> Hashes {
+        Hashes {
+            blake3: self.blake3.as_ref().map(Blake3::finalize),
+            sha1: self.sha1.as_ref().map(Sha1::finalize),
+            sha256: self.sha256.as_ref().map(Sha256::finalize),
+            sha512: self.sha512.as_ref().map(Sha512::finalize),
+            md5: self.md5.as_ref().map(Md5::finalize),
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    // Only the requested algorithms are computed, each matching its own hasher
+    #[test]
+    fn hasher() {
+        let mut hasher = Hasher::new([HashAlgorithm::Blake3, HashAlgorithm::Md5]);
+        hasher.update(b"a");
+        hasher.update(b"bc");
+        let digests = hasher.finalize();
+        assert_eq!(digests.blake3, Some(blake3(b"abc")));
+        assert_eq!(
+            digests.hex(HashAlgorithm::Md5).unwrap(),
+            "900150983cd24fb0d6963f7d28e17f72"
+        );
+        assert_eq!(digests.sha1, None);
+        assert!(Hasher::new([]).is_empty());
+    }
+}
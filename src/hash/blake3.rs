@@ -0,0 +1,382 @@
+//! Incremental [BLAKE3](https://github.com/BLAKE3-team/BLAKE3-specs) hashing
+//!
+//! A straightforward single-threaded implementation of the default hash mode, following the
+//! structure of the reference implementation.
+
+#![allow(clippy::cast_possible_truncation)]
+
+/// The size of a chunk, the unit of the hash tree's leaves
+const CHUNK_LEN: usize = 1024;
+
+/// The size of a block, the unit of the compression function
+const BLOCK_LEN: usize = 64;
+
+/// Flag for the first block of a chunk
+const CHUNK_START: u32 = 1 << 0;
+
+/// Flag for the last block of a chunk
+const CHUNK_END: u32 = 1 << 1;
+
+/// Flag for parent nodes
+const PARENT: u32 = 1 << 2;
+
+/// Flag for the root node
+const ROOT: u32 = 1 << 3;
+
+/// The initialization vector, shared with SHA-256
+const IV: [u32; 8] = [
+    0x6A09_E667,
+    0xBB67_AE85,
+    0x3C6E_F372,
+    0xA54F_F53A,
+    0x510E_527F,
+    0x9B05_688C,
+    0x1F83_D9AB,
+    0x5BE0_CD19,
+];
+
+/// How message words are permuted between rounds
+const MESSAGE_PERMUTATION: [usize; 16] = [2, 6, 3, 10, 7, 0, 4, 13, 1, 11, 12, 5, 9, 14, 15, 8];
+
+/// The quarter-round mixing function
+#[allow(clippy::many_single_char_names)]
+fn g(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize, x: u32, y: u32) {
+    state[a] = state[a].wrapping_add(state[b]).wrapping_add(x);
+    state[d] = (state[d] ^ state[a]).rotate_right(16);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_right(12);
+    state[a] = state[a].wrapping_add(state[b]).wrapping_add(y);
+    state[d] = (state[d] ^ state[a]).rotate_right(8);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_right(7);
+}
+
+/// Mixes the columns, then the diagonals, of the state
+fn round(state: &mut [u32; 16], m: &[u32; 16]) {
+    g(state, 0, 4, 8, 12, m[0], m[1]);
+    g(state, 1, 5, 9, 13, m[2], m[3]);
+    g(state, 2, 6, 10, 14, m[4], m[5]);
+    g(state, 3, 7, 11, 15, m[6], m[7]);
+    g(state, 0, 5, 10, 15, m[8], m[9]);
+    g(state, 1, 6, 11, 12, m[10], m[11]);
+    g(state, 2, 7, 8, 13, m[12], m[13]);
+    g(state, 3, 4, 9, 14, m[14], m[15]);
+}
+
+/// The compression function
+fn compress(
+    chaining_value: &[u32; 8],
+    block: &[u32; 16],
+    counter: u64,
+    block_len: u32,
+    flags: u32,
+) -> [u32; 16] {
+    let mut state = [
+        chaining_value[0],
+        chaining_value[1],
+        chaining_value[2],
+        chaining_value[3],
+        chaining_value[4],
+        chaining_value[5],
+        chaining_value[6],
+        chaining_value[7],
+        IV[0],
+        IV[1],
+        IV[2],
+        IV[3],
+        counter as u32,
+        (counter >> 32) as u32,
+        block_len,
+        flags,
+    ];
+    let mut block = *block;
+    for index in 0..7 {
+        round(&mut state, &block);
+        if index < 6 {
+            block = MESSAGE_PERMUTATION.map(|source| block[source]);
+        }
+    }
+    for i in 0..8 {
+        state[i] ^= state[i + 8];
+        state[i + 8] ^= chaining_value[i];
+    }
+    state
+}
+
+/// Returns the first eight words of a compression output
+fn first_eight(words: [u32; 16]) -> [u32; 8] {
+    let mut out = [0; 8];
+    out.copy_from_slice(&words[..8]);
+    out
+}
+
+/// Reads a block as little-endian words
+fn words(bytes: &[u8; BLOCK_LEN]) -> [u32; 16] {
+    let mut words = [0; 16];
+    for (word, bytes) in words.iter_mut().zip(bytes.chunks_exact(4)) {
+        *word = u32::from_le_bytes(bytes.try_into().unwrap());
+    }
+    words
+}
+
+/// The inputs to a compression that hasn't been performed yet, as it may turn out to be the root
+struct Output {
+    /// The chaining value going in
+    chaining_value: [u32; 8],
+    /// The block
+    block: [u32; 16],
+    /// The chunk counter
+    counter: u64,
+    /// The number of bytes in the block
+    block_len: u32,
+    /// The domain flags
+    flags: u32,
+}
+
+impl Output {
+    /// Compresses as a non-root node
+    fn chaining_value(&self) -> [u32; 8] {
+        first_eight(compress(
+            &self.chaining_value,
+            &self.block,
+            self.counter,
+            self.block_len,
+            self.flags,
+        ))
+    }
+
+    /// Compresses as the root node, producing the default 32 byte hash
+    fn root_hash(&self) -> [u8; 32] {
+        let words = compress(
+            &self.chaining_value,
+            &self.block,
+            0,
+            self.block_len,
+            self.flags | ROOT,
+        );
+        let mut hash = [0; 32];
+        for (bytes, word) in hash.chunks_exact_mut(4).zip(words) {
+            bytes.copy_from_slice(&word.to_le_bytes());
+        }
+        hash
+    }
+}
+
+/// Returns the output of a parent node
+fn parent_output(left: [u32; 8], right: [u32; 8]) -> Output {
+    let mut block = [0; 16];
+    block[..8].copy_from_slice(&left);
+    block[8..].copy_from_slice(&right);
+    Output {
+        chaining_value: IV,
+        block,
+        counter: 0,
+        block_len: BLOCK_LEN as u32,
+        flags: PARENT,
+    }
+}
+
+/// The state of the chunk currently being hashed
+#[derive(Clone)]
+struct ChunkState {
+    /// The chaining value so far
+    chaining_value: [u32; 8],
+    /// The index of this chunk
+    counter: u64,
+    /// The block being filled
+    block: [u8; BLOCK_LEN],
+    /// How much of the block is filled
+    block_len: usize,
+    /// How many blocks have been compressed
+    blocks_compressed: usize,
+}
+
+impl ChunkState {
+    /// Starts the chunk at `counter`
+    fn new(counter: u64) -> Self {
+        Self {
+            chaining_value: IV,
+            counter,
+            block: [0; BLOCK_LEN],
+            block_len: 0,
+            blocks_compressed: 0,
+        }
+    }
+
+    /// The number of bytes in the chunk so far
+    fn len(&self) -> usize {
+        BLOCK_LEN * self.blocks_compressed + self.block_len
+    }
+
+    /// The start flag, if the first block hasn't been compressed yet
+    fn start_flag(&self) -> u32 {
+        if self.blocks_compressed == 0 {
+            CHUNK_START
+        } else {
+            0
+        }
+    }
+
+    /// Adds input to the chunk, which must fit
+    fn update(&mut self, mut input: &[u8]) {
+        while !input.is_empty() {
+            // Only compress a full block once more input arrives, as the last block is special
+            if self.block_len == BLOCK_LEN {
+                self.chaining_value = first_eight(compress(
+                    &self.chaining_value,
+                    &words(&self.block),
+                    self.counter,
+                    BLOCK_LEN as u32,
+                    self.start_flag(),
+                ));
+                self.blocks_compressed += 1;
+                self.block = [0; BLOCK_LEN];
+                self.block_len = 0;
+            }
+            let take = (BLOCK_LEN - self.block_len).min(input.len());
+            self.block[self.block_len..self.block_len + take].copy_from_slice(&input[..take]);
+            self.block_len += take;
+            input = &input[take..];
+        }
+    }
+
+    /// Returns the output of the chunk's last block
+    fn output(&self) -> Output {
+        Output {
+            chaining_value: self.chaining_value,
+            block: words(&self.block),
+            counter: self.counter,
+            block_len: self.block_len as u32,
+            flags: self.start_flag() | CHUNK_END,
+        }
+    }
+}
+
+/// An incremental BLAKE3 hasher
+#[derive(Clone)]
+pub struct Blake3 {
+    /// The chunk being hashed
+    chunk: ChunkState,
+    /// Chaining values of completed subtrees, which can be at most 54 deep for 2^64 bytes
+    stack: Vec<[u32; 8]>,
+}
+
+impl Blake3 {
+    /// Creates a hasher
+    pub fn new() -> Self {
+        Self {
+            chunk: ChunkState::new(0),
+            stack: Vec::new(),
+        }
+    }
+
+    /// Adds a completed chunk, merging completed subtrees as the chunk count allows
+    fn push_chunk(&mut self, mut chaining_value: [u32; 8], mut total_chunks: u64) {
+        // Each trailing zero bit in the chunk count is a subtree that is now complete
+        while total_chunks & 1 == 0 {
+            let left = self
+                .stack
+                .pop()
+                .expect("Completed subtrees are on the stack");
+            chaining_value = parent_output(left, chaining_value).chaining_value();
+            total_chunks >>= 1;
+        }
+        self.stack.push(chaining_value);
+    }
+
+    /// Adds input to the hash
+    pub fn update(&mut self, mut input: &[u8]) {
+        while !input.is_empty() {
+            // Only finish a full chunk once more input arrives, as the last chunk is special
+            if self.chunk.len() == CHUNK_LEN {
+                let chaining_value = self.chunk.output().chaining_value();
+                let total_chunks = self.chunk.counter + 1;
+                self.push_chunk(chaining_value, total_chunks);
+                self.chunk = ChunkState::new(total_chunks);
+            }
+            let take = (CHUNK_LEN - self.chunk.len()).min(input.len());
+            self.chunk.update(&input[..take]);
+            input = &input[take..];
+        }
+    }
+
+    /// Returns the hash of everything added so far
+    pub fn finalize(&self) -> [u8; 32] {
+        let mut output = self.chunk.output();
+        for left in self.stack.iter().rev() {
+            output = parent_output(*left, output.chaining_value());
+        }
+        output.root_hash()
+    }
+}
+
+impl Default for Blake3 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Debug for Blake3 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Blake3").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    /// The input of the official test vectors: bytes counting up modulo 251
+    fn input(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    // Official test vectors, covering single blocks, chunk boundaries, and deeper trees
+    #[test]
+    fn vectors() {
+        let vectors = [
+            (
+                0,
+                "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262",
+            ),
+            (
+                1024,
+                "42214739f095a406f3fc83deb889744ac00df831c10daa55189b5d121c855af7",
+            ),
+            (
+                1025,
+                "d00278ae47eb27b34faecf67b4fe263f82d5412916c1ffd97c8cb7fb814b8444",
+            ),
+            (
+                2048,
+                "e776b6028c7cd22a4d0ba182a8bf62205d2ef576467e838ed6f2529b85fba24a",
+            ),
+        ];
+        for (len, expected) in vectors {
+            let mut hasher = Blake3::new();
+            hasher.update(&input(len));
+            assert_eq!(hex::encode(hasher.finalize()), expected, "length {len}");
+        }
+        let mut hasher = Blake3::new();
+        hasher.update(b"abc");
+        assert_eq!(
+            hex::encode(hasher.finalize()),
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+        );
+    }
+
+    // Splitting the input doesn't change the hash
+    #[test]
+    fn incremental() {
+        let data = input(10_000);
+        let mut whole = Blake3::new();
+        whole.update(&data);
+        for split in [1, 63, 64, 65, 1023, 1024, 1025, 4096, 7777] {
+            let mut hasher = Blake3::new();
+            for piece in data.chunks(split) {
+                hasher.update(piece);
+            }
+            assert_eq!(hasher.finalize(), whole.finalize(), "split {split}");
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! Incremental [MD5](https://www.rfc-editor.org/rfc/rfc1321) hashing
+//!
+//! MD5 offers no security against deliberate collisions; it is only here because CurseForge
+//! publishes it for every file.
+
+use super::BlockBuffer;
+
+/// The per-round shift amounts
+const SHIFTS: [u32; 64] = [
+    7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9,
+    14, 20, 5, 9, 14, 20, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 6, 10, 15,
+    21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21,
+];
+
+/// The per-round constants, from the sines of integers
+const K: [u32; 64] = [
+    0xd76a_a478,
+    0xe8c7_b756,
+    0x2420_70db,
+    0xc1bd_ceee,
+    0xf57c_0faf,
+    0x4787_c62a,
+    0xa830_4613,
+    0xfd46_9501,
+    0x6980_98d8,
+    0x8b44_f7af,
+    0xffff_5bb1,
+    0x895c_d7be,
+    0x6b90_1122,
+    0xfd98_7193,
+    0xa679_438e,
+    0x49b4_0821,
+    0xf61e_2562,
+    0xc040_b340,
+    0x265e_5a51,
+    0xe9b6_c7aa,
+    0xd62f_105d,
+    0x0244_1453,
+    0xd8a1_e681,
+    0xe7d3_fbc8,
+    0x21e1_cde6,
+    0xc337_07d6,
+    0xf4d5_0d87,
+    0x455a_14ed,
+    0xa9e3_e905,
+    0xfcef_a3f8,
+    0x676f_02d9,
+    0x8d2a_4c8a,
+    0xfffa_3942,
+    0x8771_f681,
+    0x6d9d_6122,
+    0xfde5_380c,
+    0xa4be_ea44,
+    0x4bde_cfa9,
+    0xf6bb_4b60,
+    0xbebf_bc70,
+    0x289b_7ec6,
+    0xeaa1_27fa,
+    0xd4ef_3085,
+    0x0488_1d05,
+    0xd9d4_d039,
+    0xe6db_99e5,
+    0x1fa2_7cf8,
+    0xc4ac_5665,
+    0xf429_2244,
+    0x432a_ff97,
+    0xab94_23a7,
+    0xfc93_a039,
+    0x655b_59c3,
+    0x8f0c_cc92,
+    0xffef_f47d,
+    0x8584_5dd1,
+    0x6fa8_7e4f,
+    0xfe2c_e6e0,
+    0xa301_4314,
+    0x4e08_11a1,
+    0xf753_7e82,
+    0xbd3a_f235,
+    0x2ad7_d2bb,
+    0xeb86_d391,
+];
+
+/// The initial hash state
+const INITIAL: [u32; 4] = [0x6745_2301, 0xefcd_ab89, 0x98ba_dcfe, 0x1032_5476];
+
+/// Processes one block
+#[allow(clippy::many_single_char_names)]
+fn compress(state: &mut [u32; 4], block: &[u8; 64]) {
+    let mut words = [0_u32; 16];
+    for (word, bytes) in words.iter_mut().zip(block.chunks_exact(4)) {
+        *word = u32::from_le_bytes(bytes.try_into().unwrap());
+    }
+    let [mut a, mut b, mut c, mut d] = *state;
+    for i in 0..64 {
+        let (f, g) = match i {
+            0..=15 => ((b & c) | (!b & d), i),
+            16..=31 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+            32..=47 => (b ^ c ^ d, (3 * i + 5) % 16),
+            _ => (c ^ (b | !d), (7 * i) % 16),
+        };
+        let f = f.wrapping_add(a).wrapping_add(K[i]).wrapping_add(words[g]);
+        a = d;
+        d = c;
+        c = b;
+        b = b.wrapping_add(f.rotate_left(SHIFTS[i]));
+    }
+    for (state, value) in state.iter_mut().zip([a, b, c, d]) {
+        *state = state.wrapping_add(value);
+    }
+}
+
+/// An incremental MD5 hasher
+#[derive(Debug, Clone)]
+pub struct Md5 {
+    /// The hash state
+    state: [u32; 4],
+    /// Input waiting for a full block
+    buffer: BlockBuffer<64>,
+}
+
+impl Md5 {
+    /// Creates a hasher
+    pub fn new() -> Self {
+        Self {
+            state: INITIAL,
+            buffer: BlockBuffer::default(),
+        }
+    }
+
+    /// Adds input to the hash
+    pub fn update(&mut self, input: &[u8]) {
+        let state = &mut self.state;
+        self.buffer.update(input, |block| compress(state, block));
+    }
+
+    /// Returns the hash of everything added so far
+    pub fn finalize(&self) -> [u8; 16] {
+        let mut state = self.state;
+        // Unlike SHA, the length is appended as a little-endian 64 bit number of bits
+        let length = self.buffer.bits().to_le_bytes();
+        self.buffer
+            .pad(&length[..8], |block| compress(&mut state, block));
+        let mut hash = [0; 16];
+        for (bytes, word) in hash.chunks_exact_mut(4).zip(state) {
+            bytes.copy_from_slice(&word.to_le_bytes());
+        }
+        hash
+    }
+}
+
+impl Default for Md5 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    // Test vectors from RFC 1321
+    #[test]
+    fn vectors() {
+        let hash = |input: &[u8]| {
+            let mut hasher = Md5::new();
+            for piece in input.chunks(11) {
+                hasher.update(piece);
+            }
+            hex::encode(hasher.finalize())
+        };
+        assert_eq!(hash(b""), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(hash(b"abc"), "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(
+            hash(b"abcdefghijklmnopqrstuvwxyz"),
+            "c3fcd3d76192e4007dfb496cca67e13b"
+        );
+        assert_eq!(
+            hash(
+                b"12345678901234567890123456789012345678901234567890123456789012345678901234567890"
+            ),
+            "57edf4a22be3c955ac49da2e2107b67a"
+        );
+    }
+}
@@ -0,0 +1,71 @@
+//! The [MurmurHash2](https://github.com/aappleby/smhasher) fingerprints CurseForge identifies
+//! files by
+//!
+//! CurseForge hashes a file with its whitespace removed, so the same jar is found again after a
+//! launcher or editor changed line endings in it. The length goes into the seed, which means the
+//! whole input has to be at hand before hashing starts.
+
+/// The multiplication constant
+const M: u32 = 0x5bd1_e995;
+
+/// The shift applied to each block
+const R: u32 = 24;
+
+/// The seed CurseForge uses
+const SEED: u32 = 1;
+
+/// Returns true for the bytes CurseForge leaves out: tab, newline, carriage return, and space
+fn is_skipped(byte: u8) -> bool {
+    matches!(byte, b'\t' | b'\n' | b'\r' | b' ')
+}
+
+/// Computes the CurseForge fingerprint of a file's contents
+pub fn curseforge_fingerprint(data: &[u8]) -> u32 {
+    let data: Vec<u8> = data
+        .iter()
+        .copied()
+        .filter(|&byte| !is_skipped(byte))
+        .collect();
+    // Fingerprints are only defined for 32 bit lengths, which a file list never gets near
+    #[allow(clippy::cast_possible_truncation)]
+    let mut hash = SEED ^ data.len() as u32;
+    let mut blocks = data.chunks_exact(4);
+    for block in &mut blocks {
+        let mut k = u32::from_le_bytes([block[0], block[1], block[2], block[3]]);
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        hash = hash.wrapping_mul(M) ^ k;
+    }
+    let tail = blocks.remainder();
+    if !tail.is_empty() {
+        for (index, &byte) in tail.iter().enumerate() {
+            hash ^= u32::from(byte) << (8 * index);
+        }
+        hash = hash.wrapping_mul(M);
+    }
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(M);
+    hash ^ (hash >> 15)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    // Fingerprints cover every tail length, and ignore whitespace
+    #[test]
+    fn vectors() {
+        assert_eq!(curseforge_fingerprint(b""), 1_540_447_798);
+        assert_eq!(curseforge_fingerprint(b"a"), 626_045_324);
+        assert_eq!(curseforge_fingerprint(b"abc"), 1_621_425_345);
+        assert_eq!(
+            curseforge_fingerprint(b"The quick brown fox jumps over the lazy dog"),
+            3_751_777_527
+        );
+        assert_eq!(
+            curseforge_fingerprint(b"hello world"),
+            curseforge_fingerprint(b"hello\r\n\tworld")
+        );
+    }
+}
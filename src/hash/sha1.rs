@@ -0,0 +1,123 @@
+//! Incremental [SHA-1](https://www.rfc-editor.org/rfc/rfc3174) hashing
+//!
+//! SHA-1 is broken for signatures, but several platforms still publish it, so it remains useful
+//! for checking downloads against them.
+
+use super::BlockBuffer;
+
+/// The initial hash state
+const INITIAL: [u32; 5] = [
+    0x6745_2301,
+    0xEFCD_AB89,
+    0x98BA_DCFE,
+    0x1032_5476,
+    0xC3D2_E1F0,
+];
+
+/// Processes one block
+#[allow(clippy::many_single_char_names)]
+fn compress(state: &mut [u32; 5], block: &[u8; 64]) {
+    let mut schedule = [0_u32; 80];
+    for (word, bytes) in schedule.iter_mut().zip(block.chunks_exact(4)) {
+        *word = u32::from_be_bytes(bytes.try_into().unwrap());
+    }
+    for i in 16..80 {
+        schedule[i] = (schedule[i - 3] ^ schedule[i - 8] ^ schedule[i - 14] ^ schedule[i - 16])
+            .rotate_left(1);
+    }
+    let [mut a, mut b, mut c, mut d, mut e] = *state;
+    for (i, word) in schedule.into_iter().enumerate() {
+        let (f, k) = match i {
+            0..=19 => ((b & c) | (!b & d), 0x5A82_7999),
+            20..=39 => (b ^ c ^ d, 0x6ED9_EBA1),
+            40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1B_BCDC),
+            _ => (b ^ c ^ d, 0xCA62_C1D6),
+        };
+        let temp = a
+            .rotate_left(5)
+            .wrapping_add(f)
+            .wrapping_add(e)
+            .wrapping_add(k)
+            .wrapping_add(word);
+        e = d;
+        d = c;
+        c = b.rotate_left(30);
+        b = a;
+        a = temp;
+    }
+    for (state, value) in state.iter_mut().zip([a, b, c, d, e]) {
+        *state = state.wrapping_add(value);
+    }
+}
+
+/// An incremental SHA-1 hasher
+#[derive(Debug, Clone)]
+pub struct Sha1 {
+    /// The hash state
+    state: [u32; 5],
+    /// Input waiting for a full block
+    buffer: BlockBuffer<64>,
+}
+
+impl Sha1 {
+    /// Creates a hasher
+    pub fn new() -> Self {
+        Self {
+            state: INITIAL,
+            buffer: BlockBuffer::default(),
+        }
+    }
+
+    /// Adds input to the hash
+    pub fn update(&mut self, input: &[u8]) {
+        let state = &mut self.state;
+        self.buffer.update(input, |block| compress(state, block));
+    }
+
+    /// Returns the hash of everything added so far
+    pub fn finalize(&self) -> [u8; 20] {
+        let mut state = self.state;
+        // The length is appended as a 64 bit big-endian number of bits
+        let length = self.buffer.bits().to_be_bytes();
+        self.buffer
+            .pad(&length[8..], |block| compress(&mut state, block));
+        let mut hash = [0; 20];
+        for (bytes, word) in hash.chunks_exact_mut(4).zip(state) {
+            bytes.copy_from_slice(&word.to_be_bytes());
+        }
+        hash
+    }
+}
+
+impl Default for Sha1 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    // Test vectors from RFC 3174, split across block boundaries
+    #[test]
+    fn vectors() {
+        let hash = |input: &[u8]| {
+            let mut hasher = Sha1::new();
+            for piece in input.chunks(7) {
+                hasher.update(piece);
+            }
+            hex::encode(hasher.finalize())
+        };
+        assert_eq!(hash(b""), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+        assert_eq!(hash(b"abc"), "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert_eq!(
+            hash(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            "84983e441c3bd26ebaae4aa1f95129e5e54670f1"
+        );
+        assert_eq!(
+            hash(&vec![b'a'; 1_000_000]),
+            "34aa973cd4c4daa4f61eeb2bdbad27316534016f"
+        );
+    }
+}
@@ -0,0 +1,351 @@
+//! Incremental [SHA-256 and SHA-512](https://www.rfc-editor.org/rfc/rfc6234) hashing
+
+use super::BlockBuffer;
+
+/// The SHA-256 round constants
+const K256: [u32; 64] = [
+    0x428a_2f98,
+    0x7137_4491,
+    0xb5c0_fbcf,
+    0xe9b5_dba5,
+    0x3956_c25b,
+    0x59f1_11f1,
+    0x923f_82a4,
+    0xab1c_5ed5,
+    0xd807_aa98,
+    0x1283_5b01,
+    0x2431_85be,
+    0x550c_7dc3,
+    0x72be_5d74,
+    0x80de_b1fe,
+    0x9bdc_06a7,
+    0xc19b_f174,
+    0xe49b_69c1,
+    0xefbe_4786,
+    0x0fc1_9dc6,
+    0x240c_a1cc,
+    0x2de9_2c6f,
+    0x4a74_84aa,
+    0x5cb0_a9dc,
+    0x76f9_88da,
+    0x983e_5152,
+    0xa831_c66d,
+    0xb003_27c8,
+    0xbf59_7fc7,
+    0xc6e0_0bf3,
+    0xd5a7_9147,
+    0x06ca_6351,
+    0x1429_2967,
+    0x27b7_0a85,
+    0x2e1b_2138,
+    0x4d2c_6dfc,
+    0x5338_0d13,
+    0x650a_7354,
+    0x766a_0abb,
+    0x81c2_c92e,
+    0x9272_2c85,
+    0xa2bf_e8a1,
+    0xa81a_664b,
+    0xc24b_8b70,
+    0xc76c_51a3,
+    0xd192_e819,
+    0xd699_0624,
+    0xf40e_3585,
+    0x106a_a070,
+    0x19a4_c116,
+    0x1e37_6c08,
+    0x2748_774c,
+    0x34b0_bcb5,
+    0x391c_0cb3,
+    0x4ed8_aa4a,
+    0x5b9c_ca4f,
+    0x682e_6ff3,
+    0x748f_82ee,
+    0x78a5_636f,
+    0x84c8_7814,
+    0x8cc7_0208,
+    0x90be_fffa,
+    0xa450_6ceb,
+    0xbef9_a3f7,
+    0xc671_78f2,
+];
+
+/// The SHA-512 round constants
+const K512: [u64; 80] = [
+    0x428a_2f98_d728_ae22,
+    0x7137_4491_23ef_65cd,
+    0xb5c0_fbcf_ec4d_3b2f,
+    0xe9b5_dba5_8189_dbbc,
+    0x3956_c25b_f348_b538,
+    0x59f1_11f1_b605_d019,
+    0x923f_82a4_af19_4f9b,
+    0xab1c_5ed5_da6d_8118,
+    0xd807_aa98_a303_0242,
+    0x1283_5b01_4570_6fbe,
+    0x2431_85be_4ee4_b28c,
+    0x550c_7dc3_d5ff_b4e2,
+    0x72be_5d74_f27b_896f,
+    0x80de_b1fe_3b16_96b1,
+    0x9bdc_06a7_25c7_1235,
+    0xc19b_f174_cf69_2694,
+    0xe49b_69c1_9ef1_4ad2,
+    0xefbe_4786_384f_25e3,
+    0x0fc1_9dc6_8b8c_d5b5,
+    0x240c_a1cc_77ac_9c65,
+    0x2de9_2c6f_592b_0275,
+    0x4a74_84aa_6ea6_e483,
+    0x5cb0_a9dc_bd41_fbd4,
+    0x76f9_88da_8311_53b5,
+    0x983e_5152_ee66_dfab,
+    0xa831_c66d_2db4_3210,
+    0xb003_27c8_98fb_213f,
+    0xbf59_7fc7_beef_0ee4,
+    0xc6e0_0bf3_3da8_8fc2,
+    0xd5a7_9147_930a_a725,
+    0x06ca_6351_e003_826f,
+    0x1429_2967_0a0e_6e70,
+    0x27b7_0a85_46d2_2ffc,
+    0x2e1b_2138_5c26_c926,
+    0x4d2c_6dfc_5ac4_2aed,
+    0x5338_0d13_9d95_b3df,
+    0x650a_7354_8baf_63de,
+    0x766a_0abb_3c77_b2a8,
+    0x81c2_c92e_47ed_aee6,
+    0x9272_2c85_1482_353b,
+    0xa2bf_e8a1_4cf1_0364,
+    0xa81a_664b_bc42_3001,
+    0xc24b_8b70_d0f8_9791,
+    0xc76c_51a3_0654_be30,
+    0xd192_e819_d6ef_5218,
+    0xd699_0624_5565_a910,
+    0xf40e_3585_5771_202a,
+    0x106a_a070_32bb_d1b8,
+    0x19a4_c116_b8d2_d0c8,
+    0x1e37_6c08_5141_ab53,
+    0x2748_774c_df8e_eb99,
+    0x34b0_bcb5_e19b_48a8,
+    0x391c_0cb3_c5c9_5a63,
+    0x4ed8_aa4a_e341_8acb,
+    0x5b9c_ca4f_7763_e373,
+    0x682e_6ff3_d6b2_b8a3,
+    0x748f_82ee_5def_b2fc,
+    0x78a5_636f_4317_2f60,
+    0x84c8_7814_a1f0_ab72,
+    0x8cc7_0208_1a64_39ec,
+    0x90be_fffa_2363_1e28,
+    0xa450_6ceb_de82_bde9,
+    0xbef9_a3f7_b2c6_7915,
+    0xc671_78f2_e372_532b,
+    0xca27_3ece_ea26_619c,
+    0xd186_b8c7_21c0_c207,
+    0xeada_7dd6_cde0_eb1e,
+    0xf57d_4f7f_ee6e_d178,
+    0x06f0_67aa_7217_6fba,
+    0x0a63_7dc5_a2c8_98a6,
+    0x113f_9804_bef9_0dae,
+    0x1b71_0b35_131c_471b,
+    0x28db_77f5_2304_7d84,
+    0x32ca_ab7b_40c7_2493,
+    0x3c9e_be0a_15c9_bebc,
+    0x431d_67c4_9c10_0d4c,
+    0x4cc5_d4be_cb3e_42b6,
+    0x597f_299c_fc65_7e2a,
+    0x5fcb_6fab_3ad6_faec,
+    0x6c44_198c_4a47_5817,
+];
+
+/// Defines a SHA-2 hasher over words of type `$word`
+///
+/// SHA-256 and SHA-512 only differ in their word size, constants, and rotation amounts.
+macro_rules! sha2 {
+    (
+        $(#[$meta:meta])*
+        $name:ident, $word:ty, $block:literal, $output:literal, $k:ident,
+        initial: $initial:expr,
+        sigma0: ($s00:literal, $s01:literal, $s02:literal),
+        sigma1: ($s10:literal, $s11:literal, $s12:literal),
+        big_sigma0: ($b00:literal, $b01:literal, $b02:literal),
+        big_sigma1: ($b10:literal, $b11:literal, $b12:literal),
+        length_bytes: $length:literal $(,)?
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone)]
+        pub struct $name {
+            /// The hash state
+            state: [$word; 8],
+            /// Input waiting for a full block
+            buffer: BlockBuffer<$block>,
+        }
+
+        impl $name {
+            /// Creates a hasher
+            pub fn new() -> Self {
+                Self {
+                    state: $initial,
+                    buffer: BlockBuffer::default(),
+                }
+            }
+
+            /// Processes one block
+            #[allow(clippy::many_single_char_names)]
+            fn compress(state: &mut [$word; 8], block: &[u8; $block]) {
+                const WORD: usize = std::mem::size_of::<$word>();
+                let mut schedule = [0; $k.len()];
+                for (word, bytes) in schedule.iter_mut().zip(block.chunks_exact(WORD)) {
+                    *word = <$word>::from_be_bytes(bytes.try_into().unwrap());
+                }
+                for i in 16..schedule.len() {
+                    let (w15, w2) = (schedule[i - 15], schedule[i - 2]);
+                    let s0 = w15.rotate_right($s00) ^ w15.rotate_right($s01) ^ (w15 >> $s02);
+                    let s1 = w2.rotate_right($s10) ^ w2.rotate_right($s11) ^ (w2 >> $s12);
+                    schedule[i] = schedule[i - 16]
+                        .wrapping_add(s0)
+                        .wrapping_add(schedule[i - 7])
+                        .wrapping_add(s1);
+                }
+                let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+                for (k, word) in $k.into_iter().zip(schedule) {
+                    let s1 = e.rotate_right($b10) ^ e.rotate_right($b11) ^ e.rotate_right($b12);
+                    let choice = (e & f) ^ (!e & g);
+                    let temp1 = h
+                        .wrapping_add(s1)
+                        .wrapping_add(choice)
+                        .wrapping_add(k)
+                        .wrapping_add(word);
+                    let s0 = a.rotate_right($b00) ^ a.rotate_right($b01) ^ a.rotate_right($b02);
+                    let majority = (a & b) ^ (a & c) ^ (b & c);
+                    let temp2 = s0.wrapping_add(majority);
+                    h = g;
+                    g = f;
+                    f = e;
+                    e = d.wrapping_add(temp1);
+                    d = c;
+                    c = b;
+                    b = a;
+                    a = temp1.wrapping_add(temp2);
+                }
+                for (state, value) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+                    *state = state.wrapping_add(value);
+                }
+            }
+
+            /// Adds input to the hash
+            pub fn update(&mut self, input: &[u8]) {
+                let state = &mut self.state;
+                self.buffer.update(input, |block| Self::compress(state, block));
+            }
+
+            /// Returns the hash of everything added so far
+            pub fn finalize(&self) -> [u8; $output] {
+                let mut state = self.state;
+                // The length is appended as a big-endian number of bits
+                let length = self.buffer.bits().to_be_bytes();
+                self.buffer
+                    .pad(&length[16 - $length..], |block| Self::compress(&mut state, block));
+                let mut hash = [0; $output];
+                for (bytes, word) in hash.chunks_exact_mut(std::mem::size_of::<$word>()).zip(state) {
+                    bytes.copy_from_slice(&word.to_be_bytes());
+                }
+                hash
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+    };
+}
+
+sha2! {
+    /// An incremental SHA-256 hasher
+    Sha256, u32, 64, 32, K256,
+    initial: [
+        0x6a09_e667, 0xbb67_ae85, 0x3c6e_f372, 0xa54f_f53a, 0x510e_527f, 0x9b05_688c,
+        0x1f83_d9ab, 0x5be0_cd19,
+    ],
+    sigma0: (7, 18, 3),
+    sigma1: (17, 19, 10),
+    big_sigma0: (2, 13, 22),
+    big_sigma1: (6, 11, 25),
+    length_bytes: 8,
+}
+
+sha2! {
+    /// An incremental SHA-512 hasher
+    Sha512, u64, 128, 64, K512,
+    initial: [
+        0x6a09_e667_f3bc_c908, 0xbb67_ae85_84ca_a73b, 0x3c6e_f372_fe94_f82b,
+        0xa54f_f53a_5f1d_36f1, 0x510e_527f_ade6_82d1, 0x9b05_688c_2b3e_6c1f,
+        0x1f83_d9ab_fb41_bd6b, 0x5be0_cd19_137e_2179,
+    ],
+    sigma0: (1, 8, 7),
+    sigma1: (19, 61, 6),
+    big_sigma0: (28, 34, 39),
+    big_sigma1: (14, 18, 41),
+    length_bytes: 16,
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    /// The two block test message from FIPS 180-2
+    const TWO_BLOCKS: &[u8] = b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq";
+
+    /// The long test message for SHA-512 from FIPS 180-2
+    const LONG: &[u8] = b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmn\
+        hijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu";
+
+    // Test vectors from FIPS 180-2
+    #[test]
+    fn sha256() {
+        let hash = |input: &[u8]| {
+            let mut hasher = Sha256::new();
+            for piece in input.chunks(13) {
+                hasher.update(piece);
+            }
+            hex::encode(hasher.finalize())
+        };
+        assert_eq!(
+            hash(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            hash(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            hash(TWO_BLOCKS),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+    }
+
+    // Test vectors from FIPS 180-2
+    #[test]
+    fn sha512() {
+        let hash = |input: &[u8]| {
+            let mut hasher = Sha512::new();
+            for piece in input.chunks(29) {
+                hasher.update(piece);
+            }
+            hex::encode(hasher.finalize())
+        };
+        assert_eq!(
+            hash(b""),
+            "cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce\
+             47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e"
+        );
+        assert_eq!(
+            hash(b"abc"),
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+             2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"
+        );
+        assert_eq!(
+            hash(LONG),
+            "8e959b75dae313da8cf4f72814fc143f8f7779c6eb9f7fa17299aeadb6889018\
+             501d289e4900f7e4331b99dec4b5433ac7d329eeb6dd26545e96e55b874be909"
+        );
+    }
+}
@@ -0,0 +1,459 @@
+//! The minimal HTTP interface the network-facing parts of the library are written against
+//!
+//! The library doesn't bundle an HTTP stack or async runtime. Embedders implement
+//! [`HttpClient`] over whatever they already use, and the resolver and downloader drive it.
+//! Wrapping a client in a [`CachingClient`] revalidates repeated requests instead of repeating
+//! them, a [`RetryingClient`] retries the ones that fail for transient reasons, and a
+//! [`RateLimitedClient`] keeps to the request rates APIs allow. Under
+//! [`NetworkPolicy::Offline`], requests are only answered from a cache. Backends can pick the
+//! proxy for each request from a [`ProxyConfig`].
+
+mod cache;
+mod proxy;
+mod rate_limit;
+mod retry;
+
+use std::{future::Future, mem, sync::Arc};
+
+use serde::de::DeserializeOwned;
+use snafu::{ensure, ResultExt, Snafu};
+use url::Url;
+
+pub(crate) use self::rate_limit::Bucket;
+pub use self::{
+    cache::{CachedBody, CachingClient},
+    proxy::{Proxy, ProxyConfig, ProxyError, ProxyProtocol},
+    rate_limit::{Rate, RateLimitedClient, RateLimits},
+    retry::{RetryOn, RetryPolicy, RetryingClient, Sleep, ThreadSleep},
+};
+
+/// A GET request, or a POST if it carries a body
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    /// The url to fetch
+    pub url: Url,
+    /// Extra headers to send, as name and value pairs
+    pub headers: Vec<(String, String)>,
+    /// The body to post, for the few API lookups that can't be made with a GET
+    pub body: Option<Vec<u8>>,
+}
+
+impl Request {
+    /// Creates a request for the given url with no extra headers
+    pub fn new(url: Url) -> Self {
+        Self {
+            url,
+            headers: Vec::new(),
+            body: None,
+        }
+    }
+
+    /// Turns the request into a POST of `body`, sent as JSON
+    #[must_use]
+    pub fn with_json_body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = Some(body.into());
+        self.with_header("Content-Type", "application/json")
+    }
+
+    /// Adds a header, consuming and returning `self` for chaining
+    #[must_use]
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Asks for the request to be answered from a cache only, never from the network
+    #[must_use]
+    pub fn only_if_cached(self) -> Self {
+        self.with_header("Cache-Control", "only-if-cached")
+    }
+
+    /// Returns true if the request may only be answered from a cache
+    pub fn is_only_if_cached(&self) -> bool {
+        self.headers.iter().any(|(name, value)| {
+            name.eq_ignore_ascii_case("Cache-Control")
+                && value
+                    .split(',')
+                    .any(|directive| directive.trim().eq_ignore_ascii_case("only-if-cached"))
+        })
+    }
+}
+
+/// Whether operations may use the network
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum NetworkPolicy {
+    /// Requests go out as usual
+    #[default]
+    Online,
+    /// Requests are only answered from a local cache, failing with
+    /// [`HttpError::WouldRequireNetwork`] when they can't be
+    Offline,
+}
+
+impl NetworkPolicy {
+    /// Prepares a request to `client` under this policy
+    ///
+    /// # Errors
+    ///
+    /// Offline, requests to a client that can't answer from a cache are refused
+    pub fn prepare<C: HttpClient + ?Sized>(
+        self,
+        client: &C,
+        request: Request,
+    ) -> Result<Request, HttpError> {
+        match self {
+            NetworkPolicy::Online => Ok(request),
+            NetworkPolicy::Offline => {
+                ensure!(
+                    client.serves_offline(),
+                    WouldRequireNetworkSnafu { url: request.url }
+                );
+                Ok(request.only_if_cached())
+            }
+        }
+    }
+}
+
+/// A response, with a body that may still be streaming in
+#[derive(Debug)]
+pub struct Response<B> {
+    /// The url the response came from, after any redirects
+    pub url: Url,
+    /// The status code
+    pub status: u16,
+    /// The response headers, as name and value pairs
+    pub headers: Vec<(String, String)>,
+    /// The body
+    pub body: B,
+}
+
+impl<B: Body> Response<B> {
+    /// Returns the first value of a header, ignoring the case of its name
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns true for a 2xx status
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// Fails with [`HttpError::Status`] unless this is a 2xx response
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for non-2xx statuses
+    pub fn error_for_status(self) -> Result<Self, HttpError> {
+        ensure!(
+            self.is_success(),
+            StatusSnafu {
+                url: self.url.clone(),
+                status: self.status
+            }
+        );
+        Ok(self)
+    }
+
+    /// Reads the whole body
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the body fails partway through
+    pub async fn bytes(mut self) -> Result<Vec<u8>, HttpError> {
+        let mut bytes = Vec::new();
+        while let Some(chunk) = self.body.chunk().await? {
+            bytes.extend_from_slice(&chunk);
+        }
+        Ok(bytes)
+    }
+
+    /// Reads the whole body as JSON
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the body fails partway through or isn't the expected JSON
+    pub async fn json<T: DeserializeOwned>(self) -> Result<T, HttpError> {
+        let url = self.url.clone();
+        let bytes = self.bytes().await?;
+        serde_json::from_slice(&bytes).context(JsonSnafu { url })
+    }
+}
+
+/// A response body, read in chunks
+pub trait Body: Send {
+    /// Reads the next chunk of the body, or `None` at the end
+    fn chunk(&mut self) -> impl Future<Output = Result<Option<Vec<u8>>, HttpError>> + Send;
+}
+
+/// A body that is already fully in memory
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FullBody(pub Vec<u8>);
+
+impl Body for FullBody {
+    async fn chunk(&mut self) -> Result<Option<Vec<u8>>, HttpError> {
+        Ok(if self.0.is_empty() {
+            None
+        } else {
+            Some(mem::take(&mut self.0))
+        })
+    }
+}
+
+/// Something that can perform HTTP GET requests
+///
+/// Implementations should follow redirects, and return non-2xx responses as responses rather
+/// than errors. Anything a backend needs, like a proxy, custom TLS roots, or a browser's
+/// `fetch`, stays on the embedder's side of this trait.
+///
+/// ```
+/// use ffpack::http::{FullBody, HttpClient, HttpError, Request, Response};
+///
+/// /// A backend with nothing behind it, where a real one would send the request
+/// struct Unreachable;
+///
+/// impl HttpClient for Unreachable {
+///     type Body = FullBody;
+///
+///     async fn get(&self, request: Request) -> Result<Response<FullBody>, HttpError> {
+///         Ok(Response {
+///             url: request.url,
+///             status: 503,
+///             headers: Vec::new(),
+///             body: FullBody::default(),
+///         })
+///     }
+/// }
+/// ```
+pub trait HttpClient: Send + Sync {
+    /// The body type of responses
+    type Body: Body;
+
+    /// Performs a GET request, or a POST if the request has a [`Request::body`]
+    ///
+    /// Requests marked [`Request::only_if_cached`] must be answered without the network, or fail
+    /// with [`HttpError::WouldRequireNetwork`].
+    fn get(
+        &self,
+        request: Request,
+    ) -> impl Future<Output = Result<Response<Self::Body>, HttpError>> + Send;
+
+    /// Returns true if the client can answer some requests without the network, as a cache can
+    fn serves_offline(&self) -> bool {
+        false
+    }
+}
+
+/// Lets one client be shared, such as between a resolver and a downloader
+impl<T: HttpClient + ?Sized> HttpClient for &T {
+    type Body = T::Body;
+
+    fn get(
+        &self,
+        request: Request,
+    ) -> impl Future<Output = Result<Response<Self::Body>, HttpError>> + Send {
+        (**self).get(request)
+    }
+
+    fn serves_offline(&self) -> bool {
+        (**self).serves_offline()
+    }
+}
+
+/// Lets one client be shared between tasks
+impl<T: HttpClient + ?Sized> HttpClient for Arc<T> {
+    type Body = T::Body;
+
+    fn get(
+        &self,
+        request: Request,
+    ) -> impl Future<Output = Result<Response<Self::Body>, HttpError>> + Send {
+        (**self).get(request)
+    }
+
+    fn serves_offline(&self) -> bool {
+        (**self).serves_offline()
+    }
+}
+
+/// Error that occurs while making a request
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub))]
+#[non_exhaustive]
+pub enum HttpError {
+    /// The request couldn't be completed
+    #[snafu(display("Request to {} failed: {}", url, source))]
+    Transport {
+        /// The url being fetched
+        url: Url,
+        /// The backend's error
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    /// The server responded with an error status
+    #[snafu(display("Request to {} failed with status {}", url, status))]
+    Status {
+        /// The url being fetched
+        url: Url,
+        /// The status code
+        status: u16,
+    },
+    /// The response wasn't the expected JSON
+    #[snafu(display("Invalid response from {}: {}", url, source))]
+    Json {
+        /// The url being fetched
+        url: Url,
+        /// The underlying error
+        source: serde_json::Error,
+    },
+    /// The request can't be answered without the network, which is off limits
+    #[snafu(display("Request to {} needs the network, but ffpack is offline", url))]
+    WouldRequireNetwork {
+        /// The url being fetched
+        url: Url,
+    },
+}
+
+impl HttpError {
+    /// Suggests how the user might fix the problem, if there is a likely fix
+    pub fn suggestion(&self) -> Option<String> {
+        match self {
+            HttpError::Transport { .. } => {
+                Some("Check the network connection, and any proxy in between".into())
+            }
+            HttpError::Status { status, .. } => match status {
+                401 | 403 => Some("Check that the API credentials in use are valid".into()),
+                404 => Some("Check that the project or url still exists upstream".into()),
+                429 => Some("Wait a while before trying again, or lower the request rate".into()),
+                500..=599 => Some("The server is having trouble, try again later".into()),
+                _ => None,
+            },
+            HttpError::Json { .. } => {
+                Some("The API may have changed, check for a newer version of ffpack".into())
+            }
+            HttpError::WouldRequireNetwork { .. } => Some(
+                "Run the same operation online once to fill the cache, or allow network access"
+                    .into(),
+            ),
+        }
+    }
+}
+
+/// Helpers for exercising network code without a network
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::{
+        collections::HashMap,
+        future::Future,
+        pin::pin,
+        sync::{Arc, Mutex},
+        task::{Context, Poll, Wake, Waker},
+        thread::{self, Thread},
+    };
+
+    use super::*;
+
+    /// Runs a future to completion on the current thread
+    pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+        /// Wakes the blocked thread
+        struct ThreadWaker(Thread);
+        impl Wake for ThreadWaker {
+            fn wake(self: Arc<Self>) {
+                self.0.unpark();
+            }
+        }
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut context = Context::from_waker(&waker);
+        let mut future = pin!(future);
+        loop {
+            match future.as_mut().poll(&mut context) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    /// A client serving canned responses, recording the requests it sees
+    #[derive(Debug, Default)]
+    pub(crate) struct MockClient {
+        /// Status and body by url
+        responses: HashMap<String, (u16, Vec<u8>)>,
+        /// Extra headers sent for a url
+        headers: HashMap<String, Vec<(String, String)>>,
+        /// The ETag sent for a url, which is answered with a 304 when it comes back
+        etags: HashMap<String, String>,
+        /// The requests made so far
+        pub(crate) requests: Mutex<Vec<Request>>,
+    }
+
+    impl MockClient {
+        /// Serves `body` with a 200 status for `url`
+        pub(crate) fn with(mut self, url: &str, body: impl Into<Vec<u8>>) -> Self {
+            self.responses.insert(url.to_string(), (200, body.into()));
+            self
+        }
+
+        /// Serves `body` with another status for `url`
+        pub(crate) fn with_status(
+            mut self,
+            url: &str,
+            status: u16,
+            body: impl Into<Vec<u8>>,
+        ) -> Self {
+            self.responses
+                .insert(url.to_string(), (status, body.into()));
+            self
+        }
+
+        /// Sends a header along with the response for `url`
+        pub(crate) fn with_header(mut self, url: &str, name: &str, value: &str) -> Self {
+            self.headers
+                .entry(url.to_string())
+                .or_default()
+                .push((name.to_string(), value.to_string()));
+            self
+        }
+
+        /// Serves `body` for `url` like [`MockClient::with`], tagged with `etag`
+        pub(crate) fn with_etag(self, url: &str, body: impl Into<Vec<u8>>, etag: &str) -> Self {
+            let mut client = self.with(url, body);
+            client.etags.insert(url.to_string(), etag.to_string());
+            client
+        }
+    }
+
+    impl HttpClient for MockClient {
+        type Body = FullBody;
+
+        async fn get(&self, request: Request) -> Result<Response<FullBody>, HttpError> {
+            let (mut status, mut body) = self
+                .responses
+                .get(request.url.as_str())
+                .cloned()
+                .unwrap_or((404, Vec::new()));
+            let mut headers = self
+                .headers
+                .get(request.url.as_str())
+                .cloned()
+                .unwrap_or_default();
+            if let Some(etag) = self.etags.get(request.url.as_str()) {
+                let matches = request.headers.iter().any(|(name, value)| {
+                    name.eq_ignore_ascii_case("If-None-Match") && value == etag
+                });
+                if matches {
+                    (status, body) = (304, Vec::new());
+                }
+                headers.push(("ETag".to_string(), etag.clone()));
+            }
+            let url = request.url.clone();
+            self.requests.lock().unwrap().push(request);
+            Ok(Response {
+                url,
+                status,
+                headers,
+                body: FullBody(body),
+            })
+        }
+    }
+}
@@ -0,0 +1,272 @@
+//! Revalidating responses against a cache on disk instead of fetching them again
+//!
+//! Responses that carry an `ETag` or `Last-Modified` validator are stored by url. The next
+//! request for the same url asks the server whether it changed, and a `304 Not Modified` is
+//! answered from the cache, so packs that rebuild often only transfer what actually changed.
+//! Requests marked [`Request::only_if_cached`] are answered from the cache alone, which is how
+//! [`NetworkPolicy::Offline`](super::NetworkPolicy::Offline) works. Requests with a body are
+//! posts, which always go to the network.
+
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use url::Url;
+
+use super::{Body, FullBody, HttpClient, HttpError, Request, Response, WouldRequireNetworkSnafu};
+
+/// What is remembered about a cached response
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    /// The url the response is for, to rule out key collisions
+    url: String,
+    /// The `ETag` the server sent
+    etag: Option<String>,
+    /// The `Last-Modified` date the server sent
+    last_modified: Option<String>,
+}
+
+/// A body served either from the cache or straight from the wrapped client
+#[derive(Debug)]
+pub enum CachedBody<B> {
+    /// A body read from, or just written to, the cache
+    Cached(FullBody),
+    /// A body that isn't cached, streamed from the wrapped client
+    Live(B),
+}
+
+impl<B: Body> Body for CachedBody<B> {
+    async fn chunk(&mut self) -> Result<Option<Vec<u8>>, HttpError> {
+        match self {
+            CachedBody::Cached(body) => body.chunk().await,
+            CachedBody::Live(body) => body.chunk().await,
+        }
+    }
+}
+
+/// An [`HttpClient`] that revalidates responses with conditional requests
+///
+/// Cacheable responses are read into memory in full before being stored, so this is meant for
+/// API queries and unpinned files rather than large downloads that already have known hashes.
+#[derive(Debug)]
+pub struct CachingClient<C> {
+    /// The client doing the actual requests
+    inner: C,
+    /// Where responses are stored
+    dir: PathBuf,
+}
+
+impl<C: HttpClient> CachingClient<C> {
+    /// Wraps `inner`, storing responses in `dir`
+    pub fn new(inner: C, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            inner,
+            dir: dir.into(),
+        }
+    }
+
+    /// Returns the wrapped client
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// Returns the paths of the entry and body stored for a url
+    fn paths(&self, request: &Request) -> (PathBuf, PathBuf) {
+        let key = hex::encode(crate::hash::blake3(request.url.as_str().as_bytes()));
+        (
+            self.dir.join(format!("{key}.json")),
+            self.dir.join(format!("{key}.body")),
+        )
+    }
+
+    /// Stores a response, logging rather than failing if the cache can't be written
+    fn store(&self, request: &Request, entry: &Entry, body: &[u8]) {
+        let (entry_path, body_path) = self.paths(request);
+        let result = fs::create_dir_all(&self.dir)
+            .and_then(|()| write_atomically(&body_path, body))
+            .and_then(|()| {
+                let entry = serde_json::to_vec(entry).expect("Entries are always serializable");
+                write_atomically(&entry_path, &entry)
+            });
+        if let Err(error) = result {
+            warn!(url = %request.url, %error, "Failed to cache response");
+        }
+    }
+}
+
+/// Writes a file by renaming a temporary file into place, so readers never see half of it
+fn write_atomically(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let partial = path.with_extension("part");
+    let mut file = fs::File::create(&partial)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(partial, path)
+}
+
+impl<C: HttpClient> HttpClient for CachingClient<C> {
+    type Body = CachedBody<C::Body>;
+
+    async fn get(&self, request: Request) -> Result<Response<Self::Body>, HttpError> {
+        // Posts aren't answered the same way twice, so they are never cached
+        if request.body.is_some() {
+            if request.is_only_if_cached() {
+                return WouldRequireNetworkSnafu { url: request.url }.fail();
+            }
+            return Ok(live(self.inner.get(request).await?));
+        }
+        let (entry_path, body_path) = self.paths(&request);
+        let entry = fs::read(&entry_path)
+            .ok()
+            .and_then(|entry| serde_json::from_slice::<Entry>(&entry).ok())
+            .filter(|entry| entry.url == request.url.as_str());
+        if request.is_only_if_cached() {
+            return match (&entry, fs::read(&body_path)) {
+                (Some(entry), Ok(body)) => {
+                    debug!(url = %request.url, "Offline, using cached response");
+                    Ok(cached(request.url, entry, body))
+                }
+                _ => WouldRequireNetworkSnafu { url: request.url }.fail(),
+            };
+        }
+        // Only whole bodies are stored, so partial requests go straight through
+        let ranged = request
+            .headers
+            .iter()
+            .any(|(name, _)| name.eq_ignore_ascii_case("Range"));
+        if ranged {
+            return Ok(live(self.inner.get(request).await?));
+        }
+        let mut conditional = request.clone();
+        if let Some(entry) = &entry {
+            if let Some(etag) = &entry.etag {
+                conditional = conditional.with_header("If-None-Match", etag);
+            }
+            if let Some(last_modified) = &entry.last_modified {
+                conditional = conditional.with_header("If-Modified-Since", last_modified);
+            }
+        }
+        let response = self.inner.get(conditional).await?;
+        if response.status == 304 {
+            if let (Some(entry), Ok(body)) = (&entry, fs::read(&body_path)) {
+                debug!(url = %request.url, "Not modified, using cached response");
+                return Ok(cached(response.url, entry, body));
+            }
+            // The cached body went missing, so ask again without validators
+            warn!(url = %request.url, "Cached response is gone, fetching it again");
+            let response = self.inner.get(request).await?;
+            return Ok(live(response));
+        }
+        let etag = response.header("ETag").map(str::to_string);
+        let last_modified = response.header("Last-Modified").map(str::to_string);
+        if !response.is_success() || (etag.is_none() && last_modified.is_none()) {
+            return Ok(live(response));
+        }
+        let (url, status, headers) = (
+            response.url.clone(),
+            response.status,
+            response.headers.clone(),
+        );
+        let body = response.bytes().await?;
+        let entry = Entry {
+            url: request.url.to_string(),
+            etag,
+            last_modified,
+        };
+        self.store(&request, &entry, &body);
+        Ok(Response {
+            url,
+            status,
+            headers,
+            body: CachedBody::Cached(FullBody(body)),
+        })
+    }
+
+    fn serves_offline(&self) -> bool {
+        true
+    }
+}
+
+/// Builds a successful response out of a cached body
+fn cached<B>(url: Url, entry: &Entry, body: Vec<u8>) -> Response<CachedBody<B>> {
+    let headers = [
+        ("ETag", &entry.etag),
+        ("Last-Modified", &entry.last_modified),
+    ]
+    .into_iter()
+    .filter_map(|(name, value)| Some((name.to_string(), value.clone()?)))
+    .collect();
+    Response {
+        url,
+        status: 200,
+        headers,
+        body: CachedBody::Cached(FullBody(body)),
+    }
+}
+
+/// Passes a response from the wrapped client through untouched
+fn live<B>(response: Response<B>) -> Response<CachedBody<B>> {
+    Response {
+        url: response.url,
+        status: response.status,
+        headers: response.headers,
+        body: CachedBody::Live(response.body),
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::http::{
+        testing::{block_on, MockClient},
+        NetworkPolicy,
+    };
+
+    // Responses with validators are revalidated, and a 304 is served from the cache
+    #[test]
+    fn revalidate() {
+        let dir = std::env::temp_dir().join(format!("ffpack-http-cache-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let inner = MockClient::default()
+            .with_etag("https://example.org/versions", "[1, 2]", "\"v1\"")
+            .with("https://example.org/plain", "plain");
+        let client = CachingClient::new(inner, &dir);
+        let get = |url: &str| {
+            let request = Request::new(url::Url::parse(url).unwrap());
+            block_on(async { client.get(request).await.unwrap().bytes().await.unwrap() })
+        };
+        assert_eq!(get("https://example.org/versions"), b"[1, 2]");
+        assert_eq!(get("https://example.org/versions"), b"[1, 2]");
+        assert_eq!(get("https://example.org/plain"), b"plain");
+        assert_eq!(get("https://example.org/plain"), b"plain");
+        let requests = client.inner().requests.lock().unwrap();
+        let conditional = |index: usize| {
+            requests[index]
+                .headers
+                .iter()
+                .any(|(name, value)| name == "If-None-Match" && value == "\"v1\"")
+        };
+        assert!(!conditional(0));
+        assert!(conditional(1));
+        assert!(!conditional(3));
+        drop(requests);
+
+        // Offline, only what is cached can be served, and nothing reaches the wrapped client
+        let offline = |url: &str| {
+            let request = Request::new(url::Url::parse(url).unwrap());
+            let request = NetworkPolicy::Offline.prepare(&client, request).unwrap();
+            block_on(async { client.get(request).await?.bytes().await })
+        };
+        assert_eq!(offline("https://example.org/versions").unwrap(), b"[1, 2]");
+        assert!(matches!(
+            offline("https://example.org/plain"),
+            Err(HttpError::WouldRequireNetwork { .. })
+        ));
+        assert_eq!(client.inner().requests.lock().unwrap().len(), 4);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
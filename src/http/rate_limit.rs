@@ -0,0 +1,264 @@
+//! Keeping request rates under the limits APIs enforce
+//!
+//! Each limit is a token bucket: requests spend a token, tokens refill at a steady rate, and a
+//! request finding the bucket empty waits for its token. Bursts up to the bucket's size go out at
+//! once, so small packs resolve as fast as before, while bulk operations settle at the limit
+//! instead of getting an API key banned.
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use tracing::debug;
+
+use super::{HttpClient, HttpError, Request, Response, Sleep, ThreadSleep};
+
+/// A request rate, as a number of requests over a period
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rate {
+    /// How many requests may be made in each period, which is also the largest burst
+    pub requests: u32,
+    /// The length of the period
+    pub per: Duration,
+}
+
+impl Rate {
+    /// Allows `requests` requests a minute
+    pub fn per_minute(requests: u32) -> Self {
+        Self {
+            requests,
+            per: Duration::from_mins(1),
+        }
+    }
+
+    /// Allows `requests` requests a second
+    pub fn per_second(requests: u32) -> Self {
+        Self {
+            requests,
+            per: Duration::from_secs(1),
+        }
+    }
+
+    /// The number of tokens regained each second
+    fn refill(self) -> f64 {
+        f64::from(self.requests) / self.per.as_secs_f64()
+    }
+}
+
+/// The limits to keep to, overall and for individual hosts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimits {
+    /// The limit across all hosts
+    global: Option<Rate>,
+    /// The limits for individual hosts, by host name
+    hosts: HashMap<String, Rate>,
+}
+
+impl Default for RateLimits {
+    /// Limits for the public APIs the resolver uses
+    ///
+    /// Modrinth documents a limit of 300 requests a minute per IP.
+    fn default() -> Self {
+        Self::none().with_host("api.modrinth.com", Rate::per_minute(300))
+    }
+}
+
+impl RateLimits {
+    /// No limits at all
+    pub fn none() -> Self {
+        Self {
+            global: None,
+            hosts: HashMap::new(),
+        }
+    }
+
+    /// Sets the limit across all hosts
+    #[must_use]
+    pub fn with_global(mut self, rate: Rate) -> Self {
+        self.global = Some(rate);
+        self
+    }
+
+    /// Sets the limit for requests to `host`
+    #[must_use]
+    pub fn with_host(mut self, host: impl Into<String>, rate: Rate) -> Self {
+        self.hosts.insert(host.into(), rate);
+        self
+    }
+
+    /// Returns the limit across all hosts
+    pub fn global(&self) -> Option<Rate> {
+        self.global
+    }
+
+    /// Returns the limit for requests to `host`
+    pub fn host(&self, host: &str) -> Option<Rate> {
+        self.hosts.get(host).copied()
+    }
+}
+
+/// The state of one limit, also used by the downloader to cap bandwidth in bytes
+#[derive(Debug)]
+pub(crate) struct Bucket {
+    /// The most tokens the bucket holds
+    capacity: f64,
+    /// The number of tokens regained each second
+    refill: f64,
+    /// The tokens available, negative when requests are already waiting on future tokens
+    tokens: f64,
+    /// When the tokens were last counted
+    updated: Instant,
+}
+
+impl Bucket {
+    /// Creates a full bucket holding `capacity` tokens, regaining `refill` tokens a second
+    pub(crate) fn new(capacity: f64, refill: f64) -> Self {
+        Self {
+            capacity,
+            refill,
+            tokens: capacity,
+            updated: Instant::now(),
+        }
+    }
+
+    /// Creates a full bucket for a rate of requests
+    fn for_rate(rate: Rate) -> Self {
+        Self::new(f64::from(rate.requests), rate.refill())
+    }
+
+    /// Takes `amount` tokens, returning how long to wait before they are actually there
+    pub(crate) fn take(&mut self, amount: f64, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill).min(self.capacity);
+        self.updated = now;
+        self.tokens -= amount;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.refill)
+        }
+    }
+}
+
+/// An [`HttpClient`] that delays requests to stay within [`RateLimits`]
+///
+/// Waiting requests reserve their tokens up front, so concurrent requests are spaced out rather
+/// than all waking at the same moment.
+#[derive(Debug)]
+pub struct RateLimitedClient<C, S = ThreadSleep> {
+    /// The client doing the actual requests
+    inner: C,
+    /// The limits to keep to
+    limits: RateLimits,
+    /// The global bucket, and the bucket of each host requested so far
+    buckets: Mutex<(Option<Bucket>, HashMap<String, Bucket>)>,
+    /// How to wait for tokens
+    sleep: S,
+}
+
+impl<C: HttpClient> RateLimitedClient<C> {
+    /// Wraps `inner`, waiting for tokens by blocking the thread
+    pub fn new(inner: C, limits: RateLimits) -> Self {
+        Self::with_sleep(inner, limits, ThreadSleep)
+    }
+}
+
+impl<C: HttpClient, S: Sleep> RateLimitedClient<C, S> {
+    /// Wraps `inner`, waiting for tokens with `sleep`
+    pub fn with_sleep(inner: C, limits: RateLimits, sleep: S) -> Self {
+        Self {
+            inner,
+            limits,
+            buckets: Mutex::new((None, HashMap::new())),
+            sleep,
+        }
+    }
+
+    /// Returns the wrapped client
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// Takes a token from every limit that applies to `host`, returning the longest wait
+    fn take(&self, host: &str) -> Duration {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().expect("Buckets aren't poisoned");
+        let (global, hosts) = &mut *buckets;
+        let mut wait = Duration::ZERO;
+        if let Some(rate) = self.limits.global {
+            let bucket = global.get_or_insert_with(|| Bucket::for_rate(rate));
+            wait = wait.max(bucket.take(1.0, now));
+        }
+        if let Some(rate) = self.limits.host(host) {
+            let bucket = hosts
+                .entry(host.to_string())
+                .or_insert_with(|| Bucket::for_rate(rate));
+            wait = wait.max(bucket.take(1.0, now));
+        }
+        wait
+    }
+}
+
+impl<C: HttpClient, S: Sleep> HttpClient for RateLimitedClient<C, S> {
+    type Body = C::Body;
+
+    async fn get(&self, request: Request) -> Result<Response<Self::Body>, HttpError> {
+        // Requests answered from a cache never reach the host, so don't count against it
+        if !request.is_only_if_cached() {
+            let wait = self.take(request.url.host_str().unwrap_or_default());
+            if !wait.is_zero() {
+                debug!(url = %request.url, ?wait, "Waiting for the rate limit");
+                self.sleep.sleep(wait).await;
+            }
+        }
+        self.inner.get(request).await
+    }
+
+    fn serves_offline(&self) -> bool {
+        self.inner.serves_offline()
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::http::testing::{block_on, MockClient};
+
+    /// Records the delays it is asked to sleep for
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<Duration>>);
+
+    impl Sleep for &Recorder {
+        async fn sleep(&self, duration: Duration) {
+            self.0.lock().unwrap().push(duration);
+        }
+    }
+
+    // Bursts go straight through, after which requests are spaced out, per host and overall
+    #[test]
+    fn rate_limit() {
+        let recorder = Recorder::default();
+        let limits = RateLimits::none()
+            .with_host("api.example.org", Rate::per_second(2))
+            .with_global(Rate::per_minute(4));
+        let client = RateLimitedClient::with_sleep(MockClient::default(), limits, &recorder);
+        let get = |url: &str| {
+            block_on(client.get(Request::new(url::Url::parse(url).unwrap()))).unwrap();
+        };
+        get("https://api.example.org/a");
+        get("https://api.example.org/b");
+        get("https://cdn.example.org/c");
+        assert!(recorder.0.lock().unwrap().is_empty());
+        // The host's bucket is empty, and its third token is half a second away
+        get("https://api.example.org/d");
+        let waits = recorder.0.lock().unwrap().clone();
+        assert_eq!(waits.len(), 1);
+        assert!(waits[0] > Duration::from_millis(400) && waits[0] <= Duration::from_millis(500));
+        // The global bucket is empty too, and refills far slower
+        get("https://cdn.example.org/e");
+        let waits = recorder.0.lock().unwrap().clone();
+        assert!(waits[1] > Duration::from_secs(14));
+    }
+}
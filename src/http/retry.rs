@@ -0,0 +1,349 @@
+//! Retrying requests that fail in ways that are likely to pass if tried again
+//!
+//! Connection failures, timeouts, rate limits, and server errors are retried with exponential
+//! backoff. Delays are randomized ("full jitter") so that many clients failing at once don't
+//! retry in lockstep. As the resolver and downloader both go through an [`HttpClient`], wrapping
+//! it in a [`RetryingClient`] applies the same policy to every request either makes.
+
+use std::{
+    collections::hash_map::RandomState,
+    future::Future,
+    hash::{BuildHasher, Hasher},
+    time::Duration,
+};
+
+use tracing::{debug, debug_span, Instrument};
+
+use super::{HttpClient, HttpError, Request, Response};
+
+/// Waits for a while, on whatever timer the embedding application has
+pub trait Sleep: Send + Sync {
+    /// Completes after `duration`
+    fn sleep(&self, duration: Duration) -> impl Future<Output = ()> + Send;
+}
+
+/// Sleeps by blocking the current thread
+///
+/// This only suits executors that run one future per thread, such as a plain `block_on`; async
+/// runtimes should supply their own timer instead.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ThreadSleep;
+
+impl Sleep for ThreadSleep {
+    async fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+/// The kinds of failure that are retried
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct RetryOn {
+    /// Requests that couldn't be completed at all
+    pub transport: bool,
+    /// Responses saying the request timed out (408)
+    pub timeouts: bool,
+    /// Responses saying too many requests were made (429)
+    pub rate_limits: bool,
+    /// Server errors (5xx)
+    pub server_errors: bool,
+}
+
+impl RetryOn {
+    /// Retry every kind of transient failure
+    pub const ALL: Self = Self {
+        transport: true,
+        timeouts: true,
+        rate_limits: true,
+        server_errors: true,
+    };
+
+    /// Returns true if a response with `status` should be retried
+    pub fn status(&self, status: u16) -> bool {
+        match status {
+            408 => self.timeouts,
+            429 => self.rate_limits,
+            500..=599 => self.server_errors,
+            _ => false,
+        }
+    }
+}
+
+impl Default for RetryOn {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+/// How often, and how patiently, requests are retried
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// How many times a request is tried in total
+    max_attempts: u32,
+    /// The delay before the first retry, doubled for each one after
+    base_delay: Duration,
+    /// The longest delay between tries
+    max_delay: Duration,
+    /// Whether delays are randomized
+    jitter: bool,
+    /// Which failures are retried
+    retry_on: RetryOn,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: true,
+            retry_on: RetryOn::ALL,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries
+    pub fn never() -> Self {
+        Self::default().with_max_attempts(1)
+    }
+
+    /// Sets how many times a request is tried in total, with values below 1 treated as 1
+    #[must_use]
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Sets the delay before the first retry
+    #[must_use]
+    pub fn with_base_delay(mut self, base_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    /// Sets the longest delay between tries
+    #[must_use]
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Sets whether delays are randomized, which is mostly useful to turn off in tests
+    #[must_use]
+    pub fn with_jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Sets which failures are retried
+    #[must_use]
+    pub fn with_retry_on(mut self, retry_on: RetryOn) -> Self {
+        self.retry_on = retry_on;
+        self
+    }
+
+    /// Returns how many times a request is tried in total
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Returns the delay before retry number `retry`, counting from 0
+    ///
+    /// Without jitter this is the capped exponential delay; with it, a random delay between
+    /// zero and that.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let ceiling = self
+            .base_delay
+            .saturating_mul(2_u32.saturating_pow(retry))
+            .min(self.max_delay);
+        if self.jitter {
+            ceiling.mul_f64(random_fraction())
+        } else {
+            ceiling
+        }
+    }
+
+    /// Returns which failures are retried
+    pub fn retry_on(&self) -> RetryOn {
+        self.retry_on
+    }
+}
+
+/// Returns a random number in `[0, 1)`, using the randomly seeded std hasher as the source
+#[allow(clippy::cast_precision_loss)]
+fn random_fraction() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u32(std::process::id());
+    (hasher.finish() >> 11) as f64 / (1_u64 << 53) as f64
+}
+
+/// Parses a `Retry-After` header given in seconds, ignoring the rarer HTTP date form
+fn retry_after(header: Option<&str>) -> Option<Duration> {
+    header?.trim().parse().ok().map(Duration::from_secs)
+}
+
+/// An [`HttpClient`] that retries failed requests according to a [`RetryPolicy`]
+///
+/// Only getting a response is retried. A body that fails partway through is reported to the
+/// caller, since part of it may already have been consumed.
+#[derive(Debug)]
+pub struct RetryingClient<C, S = ThreadSleep> {
+    /// The client doing the actual requests
+    inner: C,
+    /// When to retry
+    policy: RetryPolicy,
+    /// How to wait between tries
+    sleep: S,
+}
+
+impl<C: HttpClient> RetryingClient<C> {
+    /// Wraps `inner`, waiting between tries by blocking the thread
+    pub fn new(inner: C, policy: RetryPolicy) -> Self {
+        Self::with_sleep(inner, policy, ThreadSleep)
+    }
+}
+
+impl<C: HttpClient, S: Sleep> RetryingClient<C, S> {
+    /// Wraps `inner`, waiting between tries with `sleep`
+    pub fn with_sleep(inner: C, policy: RetryPolicy, sleep: S) -> Self {
+        Self {
+            inner,
+            policy,
+            sleep,
+        }
+    }
+
+    /// Returns the wrapped client
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+}
+
+impl<C: HttpClient, S: Sleep> HttpClient for RetryingClient<C, S> {
+    type Body = C::Body;
+
+    async fn get(&self, request: Request) -> Result<Response<Self::Body>, HttpError> {
+        let retry_on = self.policy.retry_on;
+        let mut retry = 0;
+        loop {
+            let attempt = retry + 1;
+            let span = debug_span!("attempt", url = %request.url, attempt);
+            let result = self
+                .inner
+                .get(request.clone())
+                .instrument(span.clone())
+                .await;
+            let server_delay = match &result {
+                _ if attempt >= self.policy.max_attempts => return result,
+                Ok(response) if retry_on.status(response.status) => {
+                    retry_after(response.header("Retry-After"))
+                }
+                Err(HttpError::Transport { .. }) if retry_on.transport => None,
+                _ => return result,
+            };
+            let delay = server_delay
+                .unwrap_or_else(|| self.policy.delay(retry))
+                .min(self.policy.max_delay);
+            span.in_scope(|| debug!(?delay, "Retrying request"));
+            self.sleep.sleep(delay).await;
+            retry += 1;
+        }
+    }
+
+    fn serves_offline(&self) -> bool {
+        self.inner.serves_offline()
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::http::{testing::block_on, FullBody};
+
+    /// A client failing a set number of times before succeeding
+    #[derive(Default)]
+    struct Flaky {
+        /// The statuses to respond with, in order, before a 200
+        failures: Mutex<Vec<u16>>,
+    }
+
+    impl HttpClient for Flaky {
+        type Body = FullBody;
+
+        async fn get(&self, request: Request) -> Result<Response<FullBody>, HttpError> {
+            let status = self.failures.lock().unwrap().pop().unwrap_or(200);
+            let headers = if status == 429 {
+                vec![("Retry-After".to_string(), "7".to_string())]
+            } else {
+                Vec::new()
+            };
+            Ok(Response {
+                url: request.url,
+                status,
+                headers,
+                body: FullBody(Vec::new()),
+            })
+        }
+    }
+
+    /// Records the delays it is asked to sleep for
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<Duration>>);
+
+    impl Sleep for &Recorder {
+        async fn sleep(&self, duration: Duration) {
+            self.0.lock().unwrap().push(duration);
+        }
+    }
+
+    // Retryable failures back off exponentially, honoring Retry-After, up to the attempt limit
+    #[test]
+    fn retry() {
+        let policy = RetryPolicy::default().with_jitter(false);
+        assert_eq!(policy.delay(0), Duration::from_millis(500));
+        assert_eq!(policy.delay(2), Duration::from_secs(2));
+        assert_eq!(policy.delay(20), Duration::from_secs(30));
+        let jittered = RetryPolicy::default().delay(3);
+        assert!(jittered <= Duration::from_secs(4));
+
+        let request = || Request::new(url::Url::parse("https://example.org/").unwrap());
+        let recorder = Recorder::default();
+        let flaky = Flaky {
+            failures: Mutex::new(vec![503, 429, 500]),
+        };
+        let client = RetryingClient::with_sleep(flaky, policy, &recorder);
+        assert_eq!(block_on(client.get(request())).unwrap().status, 200);
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            [
+                Duration::from_millis(500),
+                Duration::from_secs(7),
+                Duration::from_secs(2)
+            ]
+        );
+
+        let flaky = Flaky {
+            failures: Mutex::new(vec![404, 503, 503]),
+        };
+        let client = RetryingClient::with_sleep(flaky, policy.with_max_attempts(2), &recorder);
+        assert_eq!(block_on(client.get(request())).unwrap().status, 503);
+        let client = RetryingClient::with_sleep(client.inner, policy, &recorder);
+        assert_eq!(block_on(client.get(request())).unwrap().status, 404);
+
+        // Failures outside `retry_on` come straight back
+        let flaky = Flaky {
+            failures: Mutex::new(vec![503]),
+        };
+        let no_server_errors = RetryOn {
+            server_errors: false,
+            ..RetryOn::ALL
+        };
+        let client =
+            RetryingClient::with_sleep(flaky, policy.with_retry_on(no_server_errors), &recorder);
+        assert_eq!(block_on(client.get(request())).unwrap().status, 503);
+        assert_eq!(recorder.0.lock().unwrap().len(), 4);
+    }
+}
@@ -0,0 +1,273 @@
+//! Importing packs from the formats of other tools
+//!
+//! Other formats rarely record everything a [`Pack`] needs; most importantly, they don't know
+//! the blake3 hashes url sources are pinned by. Importers fill in what they can and report the
+//! rest in an [`Imported`], so it can be finished by hand or with the rehasher.
+
+pub mod curseforge;
+pub mod instance;
+#[cfg(feature = "modrinth")]
+pub mod mrpack;
+pub mod packwiz;
+
+use std::{
+    collections::BTreeSet,
+    fs::{self, File},
+    io::{self, Read, Seek, Write},
+    path::{Path, PathBuf},
+};
+
+use relative_path::{RelativePath, RelativePathBuf};
+use semver::Version;
+use serde::de::DeserializeOwned;
+use snafu::{ensure, OptionExt, ResultExt, Snafu};
+use tracing::{debug, warn};
+
+use crate::{
+    archive::{ZipArchive, ZipError},
+    hash::Blake3,
+    resolve::ResolveError,
+    toml::TomlError,
+    types::{FileKind, Hashes, Loader, ManagedFile, Side, Source},
+    Pack,
+};
+
+/// A pack read from another format, and what about it still needs finishing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Imported {
+    /// The pack
+    pub pack: Pack,
+    /// Url sources with a placeholder blake3 hash, which have to be pinned, like with
+    /// [`Rehasher::pin_url`](crate::rehash::Rehasher::pin_url), before they can be installed
+    pub unpinned: Vec<RelativePathBuf>,
+    /// CurseForge sources whose slug isn't known, so their project id stands in for it until
+    /// the slug is filled in
+    pub unnamed: Vec<RelativePathBuf>,
+}
+
+/// Error that occurs while importing a pack
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub(crate)))]
+#[non_exhaustive]
+pub enum ImportError {
+    /// A file of the pack couldn't be read
+    #[snafu(display("Failed to read {}: {}", path.display(), source))]
+    Io {
+        /// The file being read
+        path: PathBuf,
+        /// The underlying error
+        source: io::Error,
+    },
+    /// A file of the pack isn't valid TOML
+    #[snafu(display("Failed to parse {}: {}", path.display(), source))]
+    Toml {
+        /// The file being parsed
+        path: PathBuf,
+        /// The underlying error
+        source: TomlError,
+    },
+    /// A file couldn't be written into the pack's directory
+    #[snafu(display("Failed to write {}: {}", path.display(), source))]
+    Write {
+        /// The file being written
+        path: PathBuf,
+        /// The underlying error
+        source: io::Error,
+    },
+    /// The pack lacks a file every pack of its format has
+    #[snafu(display("The pack has no {}", path.display()))]
+    MissingFile {
+        /// The missing file
+        path: PathBuf,
+    },
+    /// A file of the pack isn't the JSON it should be
+    #[snafu(display("Failed to parse {}: {}", path.display(), source))]
+    Json {
+        /// The file being parsed
+        path: PathBuf,
+        /// The underlying error
+        source: serde_json::Error,
+    },
+    /// The pack's archive can't be read
+    #[snafu(display("Failed to read archive: {}", source))]
+    Zip {
+        /// The underlying error
+        source: ZipError,
+    },
+    /// A file of the pack couldn't be looked up on the platform it might come from
+    #[snafu(display("Failed to look up {}: {}", path, source))]
+    Lookup {
+        /// The file being looked up
+        path: RelativePathBuf,
+        /// The underlying error, boxed since it is much larger than the others
+        #[snafu(source(from(ResolveError, Box::new)))]
+        source: Box<ResolveError>,
+    },
+    /// A file of the pack lacks a field it needs
+    #[snafu(display("{} has no `{}`", path.display(), field))]
+    MissingField {
+        /// The file missing the field
+        path: PathBuf,
+        /// The name of the field
+        field: &'static str,
+    },
+    /// A field of the pack has a value that can't be used
+    #[snafu(display("{} has an invalid `{}`: {}", path.display(), field, value))]
+    InvalidField {
+        /// The file with the field
+        path: PathBuf,
+        /// The name of the field
+        field: &'static str,
+        /// The value of the field
+        value: String,
+    },
+    /// The pack uses no loader this library supports
+    #[snafu(display("{} names no supported loader", path.display()))]
+    UnsupportedLoader {
+        /// The file naming the loaders
+        path: PathBuf,
+    },
+    /// The pack lists a file outside of its own directory
+    #[snafu(display("{} is outside the pack's directory", path))]
+    PathOutsideRoot {
+        /// The path of the file
+        path: RelativePathBuf,
+    },
+}
+
+impl ImportError {
+    /// Suggests how the user might fix the problem, if there is a likely fix
+    pub fn suggestion(&self) -> Option<String> {
+        match self {
+            ImportError::MissingField { field, .. } => Some(format!(
+                "Add `{field}` to the file, or export the pack again"
+            )),
+            ImportError::UnsupportedLoader { .. } => {
+                Some("Switch the pack to Quilt, Fabric, or Forge before importing it".into())
+            }
+            ImportError::PathOutsideRoot { .. } => {
+                Some("Move the file into the pack's directory".into())
+            }
+            ImportError::MissingFile { .. } => {
+                Some("Check that the file is a pack of the format being imported".into())
+            }
+            ImportError::Lookup { source, .. } => source.suggestion(),
+            ImportError::Io { .. }
+            | ImportError::Write { .. }
+            | ImportError::Toml { .. }
+            | ImportError::Json { .. }
+            | ImportError::Zip { .. }
+            | ImportError::InvalidField { .. } => None,
+        }
+    }
+}
+
+/// Creates a loader of one kind at a version
+type LoaderFn = fn(Version) -> Loader;
+
+/// Parses a version, padding out versions like `1.0` that leave off components
+fn lenient_version(raw: &str) -> Option<Version> {
+    let mut padded = raw.to_owned();
+    for _ in 0..3 {
+        if let Ok(version) = Version::parse(&padded) {
+            return Some(version);
+        }
+        padded.push_str(".0");
+    }
+    None
+}
+
+/// Resolves a path listed in a pack against `base`, which it can't lead out of
+fn resolve_path(base: &RelativePath, path: &str) -> Result<RelativePathBuf, ImportError> {
+    let resolved = base.join_normalized(path);
+    ensure!(!resolved.starts_with(".."), PathOutsideRootSnafu { path });
+    Ok(resolved)
+}
+
+/// Reads the JSON file at `name` in an archive
+fn read_json<T: DeserializeOwned, R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    name: &str,
+) -> Result<T, ImportError> {
+    let position = archive
+        .entries()
+        .iter()
+        .position(|entry| entry.name() == name)
+        .context(MissingFileSnafu { path: name })?;
+    serde_json::from_reader(archive.open(position).context(ZipSnafu)?)
+        .context(JsonSnafu { path: name })
+}
+
+/// Writes what `reader` holds to `local`, returning its blake3 hash
+fn extract(reader: &mut impl Read, local: &Path) -> io::Result<[u8; 32]> {
+    if let Some(parent) = local.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = File::create(local)?;
+    let mut hasher = Blake3::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(hasher.finalize());
+        }
+        hasher.update(&buffer[..read]);
+        file.write_all(&buffer[..read])?;
+    }
+}
+
+/// Extracts override folders of an archive into `root`, returning the path sources they become
+///
+/// The folders are applied in order, each with the side its files are for, so files in a later
+/// folder replace those of the same path in earlier ones. Extracted files keep their path in
+/// the archive, which their sources point at.
+fn extract_overrides<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    root: &Path,
+    folders: &[(&str, Side)],
+) -> Result<BTreeSet<ManagedFile>, ImportError> {
+    let names: Vec<_> = archive
+        .entries()
+        .iter()
+        .enumerate()
+        .filter(|(_, entry)| !entry.is_directory())
+        .filter_map(|(index, entry)| {
+            let name = entry.enclosed_name();
+            if name.is_none() {
+                warn!(name = entry.name(), "Skipping entry outside the pack");
+            }
+            Some((index, name?))
+        })
+        .collect();
+    let mut files = BTreeSet::new();
+    for (folder, side) in folders {
+        for (index, name) in &names {
+            let Ok(installed) = name.strip_prefix(folder) else {
+                continue;
+            };
+            debug!(%name, "Extracting override");
+            let local = name.to_path(root);
+            let mut reader = archive.open(*index).context(ZipSnafu)?;
+            let blake3 = extract(&mut reader, &local).context(WriteSnafu { path: &local })?;
+            let installed = RelativePathBuf::from(installed.as_str());
+            files.replace(ManagedFile {
+                name: None,
+                description: None,
+                filename: installed.file_name().unwrap_or_default().into(),
+                devel: true,
+                path: installed,
+                side: side.clone(),
+                source: Source::Path {
+                    path: name.clone(),
+                    blake3,
+                    hashes: Hashes::default(),
+                },
+                enabled: true,
+                notes: None,
+                kind: FileKind::Regular,
+                collection: None,
+            });
+        }
+    }
+    Ok(files)
+}
@@ -0,0 +1,314 @@
+//! Importing CurseForge modpacks
+//!
+//! A modpack's manifest only lists files by the ids of their project and themselves, so each is
+//! looked up on CurseForge for its project's slug, the file's name, and the folder the project's
+//! class installs into, which needs a CurseForge key. Files that aren't required become disabled
+//! files, and the overrides are extracted into the pack's directory as path sources. Written as
+//! [`export::curseforge`](crate::export::curseforge) writes them, packs round trip.
+
+use std::{
+    io::{Read, Seek},
+    path::Path,
+};
+
+use relative_path::RelativePath;
+use semver::Version;
+use serde::Deserialize;
+use snafu::{OptionExt, ResultExt};
+use tracing::{debug, instrument, warn};
+
+use super::{
+    extract_overrides, lenient_version, read_json, resolve_path, ImportError, Imported,
+    InvalidFieldSnafu, LoaderFn, LookupSnafu, UnsupportedLoaderSnafu, ZipSnafu,
+};
+use crate::{
+    archive::ZipArchive,
+    export::curseforge::{MANIFEST, OVERRIDES},
+    http::HttpClient,
+    resolve::Resolver,
+    types::{FileKind, Loader, ManagedFile, Metadata, Minecraft, Side, Versions},
+    Pack,
+};
+
+/// The names CurseForge gives loaders in their ids, and the loaders they are
+const LOADERS: [(&str, LoaderFn); 3] = [
+    ("quilt", Loader::Quilt),
+    ("fabric", Loader::Fabric),
+    ("forge", Loader::Forge),
+];
+
+/// The manifest of a CurseForge modpack
+#[derive(Deserialize)]
+struct ModpackManifest {
+    /// The game and loader versions
+    minecraft: Game,
+    /// The name of the pack
+    name: String,
+    /// The version of the pack
+    #[serde(default)]
+    version: Option<String>,
+    /// The pack's authors, separated by commas
+    #[serde(default)]
+    author: Option<String>,
+    /// The files on CurseForge
+    files: Vec<ManifestFile>,
+    /// The folder inside the archive that is copied into the instance
+    #[serde(default)]
+    overrides: Option<String>,
+}
+
+/// The game and loader versions of a modpack
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Game {
+    /// The version of minecraft
+    version: String,
+    /// The loaders, one of which is primary
+    mod_loaders: Vec<ModLoader>,
+}
+
+/// A loader in a modpack's manifest
+#[derive(Deserialize)]
+struct ModLoader {
+    /// The loader and its version, like `forge-47.1.0`
+    id: String,
+    /// Whether this is the loader instances are created with
+    #[serde(default)]
+    primary: bool,
+}
+
+/// A file on CurseForge in a modpack's manifest
+#[derive(Deserialize)]
+struct ManifestFile {
+    /// The id of the project
+    #[serde(rename = "projectID")]
+    project_id: u64,
+    /// The id of the file
+    #[serde(rename = "fileID")]
+    file_id: u64,
+    /// Whether the file is installed, rather than only offered
+    #[serde(default = "required")]
+    required: bool,
+}
+
+/// Files are required unless the manifest says otherwise
+fn required() -> bool {
+    true
+}
+
+/// Reads the game and loader versions from the manifest, going by its primary loader
+fn versions(game: &Game) -> Result<Versions, ImportError> {
+    let path = Path::new(MANIFEST);
+    let minecraft = Minecraft::new(&game.version)
+        .ok()
+        .context(InvalidFieldSnafu {
+            path,
+            field: "minecraft.version",
+            value: &game.version,
+        })?;
+    let primary = game
+        .mod_loaders
+        .iter()
+        .find(|loader| loader.primary)
+        .or_else(|| game.mod_loaders.first())
+        .context(UnsupportedLoaderSnafu { path })?;
+    let (name, version) = primary.id.split_once('-').unwrap_or((&primary.id, ""));
+    let (_, loader) = LOADERS
+        .into_iter()
+        .find(|(id, _)| *id == name)
+        .context(UnsupportedLoaderSnafu { path })?;
+    let version = lenient_version(version).context(InvalidFieldSnafu {
+        path,
+        field: "minecraft.modLoaders",
+        value: &primary.id,
+    })?;
+    Ok(Versions {
+        minecraft,
+        loader: loader(version),
+        java: None,
+    })
+}
+
+/// Reads the pack's metadata from the manifest
+fn metadata(manifest: &ModpackManifest) -> Metadata {
+    let raw = manifest.version.as_deref().unwrap_or_default();
+    let version = lenient_version(raw).unwrap_or_else(|| {
+        warn!(version = raw, "Pack version isn't semver, using 1.0.0");
+        Version::new(1, 0, 0)
+    });
+    let mut authors = manifest
+        .author
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|author| !author.is_empty());
+    let metadata = Metadata::new(
+        manifest.name.as_str(),
+        authors.next().unwrap_or_default(),
+        version,
+    );
+    authors.fold(metadata, Metadata::with_author)
+}
+
+/// Turns a file listed in the manifest into a managed file, looking it up on CurseForge
+async fn manifest_file<C: HttpClient>(
+    file: &ManifestFile,
+    resolver: &Resolver<C>,
+) -> Result<ManagedFile, ImportError> {
+    let identified = resolver
+        .identify_curseforge(file.project_id, file.file_id)
+        .await
+        .context(LookupSnafu {
+            path: format!("{}/{}", file.project_id, file.file_id),
+        })?;
+    let path = resolve_path(RelativePath::new(""), identified.path.as_str())?;
+    debug!(%path, "Importing file");
+    Ok(ManagedFile {
+        name: identified.name,
+        description: None,
+        filename: identified.filename,
+        devel: true,
+        path,
+        side: Side::Both,
+        source: identified.source,
+        enabled: file.required,
+        notes: None,
+        kind: FileKind::Regular,
+        collection: None,
+    })
+}
+
+/// Reads the CurseForge modpack `reader` holds into a pack, extracting its overrides into `root`
+///
+/// Path sources point at the extracted overrides, so the pack's manifest belongs in `root`.
+/// Every file is on CurseForge, so nothing comes back unpinned or unnamed.
+///
+/// # Errors
+///
+/// Returns an error if the archive or its manifest can't be read, a file can't be looked up on
+/// CurseForge, or an override can't be extracted
+#[instrument(skip_all, fields(root = %root.display()))]
+pub async fn import<C: HttpClient>(
+    reader: impl Read + Seek,
+    root: &Path,
+    resolver: &Resolver<C>,
+) -> Result<Imported, ImportError> {
+    let mut archive = ZipArchive::new(reader).context(ZipSnafu)?;
+    let manifest: ModpackManifest = read_json(&mut archive, MANIFEST)?;
+    let overrides = manifest.overrides.as_deref().unwrap_or(OVERRIDES);
+    let mut imported = Imported {
+        pack: Pack {
+            metadata: metadata(&manifest),
+            versions: versions(&manifest.minecraft)?,
+            managed_files: extract_overrides(&mut archive, root, &[(overrides, Side::Both)])?,
+        },
+        unpinned: Vec::new(),
+        unnamed: Vec::new(),
+    };
+    for file in &manifest.files {
+        let file = manifest_file(file, resolver).await?;
+        imported.pack.managed_files.replace(file);
+    }
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use std::{fs, io::Cursor};
+
+    use relative_path::RelativePathBuf;
+    use url::Url;
+
+    use super::*;
+    use crate::{
+        export,
+        hash::blake3,
+        http::testing::{block_on, MockClient},
+        lock::{LockedFile, Lockfile},
+        resolve::{ProjectDetails, ResolvedArtifact, UpstreamIds},
+        types::{Hashes, Source},
+    };
+
+    // Exported packs come back with their files looked up by id and overrides extracted
+    #[test]
+    fn round_trip() {
+        let dir = std::env::temp_dir().join(format!("ffpack-cf-import-{}", std::process::id()));
+        fs::create_dir_all(dir.join("config")).unwrap();
+        fs::write(dir.join("config/jei.toml"), b"cheats = false").unwrap();
+        let jei = ManagedFile {
+            name: Some("Just Enough Items".into()),
+            description: None,
+            path: RelativePathBuf::from("mods/jei-1.20.1-forge.jar"),
+            filename: "jei-1.20.1-forge.jar".into(),
+            source: Source::Curseforge {
+                slug: "jei".into(),
+                file_id: Some(4_712_866),
+            },
+            enabled: false,
+            ..ManagedFile::default()
+        };
+        let config = ManagedFile {
+            path: RelativePathBuf::from("config/jei.toml"),
+            filename: "jei.toml".into(),
+            source: Source::Path {
+                path: RelativePathBuf::from("config/jei.toml"),
+                blake3: blake3(b"cheats = false"),
+                hashes: Hashes::default(),
+            },
+            ..ManagedFile::default()
+        };
+        let pack = Pack {
+            managed_files: [jei.clone(), config].into_iter().collect(),
+            ..Pack::default()
+        };
+        let mut lock = Lockfile::default();
+        let artifact = ResolvedArtifact {
+            download_url: Url::parse("https://edge.forgecdn.net/files/jei.jar").unwrap(),
+            filename: jei.filename.clone(),
+            size: None,
+            hashes: Hashes::default(),
+            mirrors: Vec::new(),
+            extract: None,
+            ids: UpstreamIds {
+                project_id: Some("238222".into()),
+                file_id: Some("4712866".into()),
+                ..UpstreamIds::default()
+            },
+            details: ProjectDetails::default(),
+        };
+        let source = jei.source.clone();
+        lock.files
+            .insert(jei.path.clone(), LockedFile { source, artifact });
+        let mut data = Cursor::new(Vec::new());
+        export::curseforge::export(&pack, &lock, &dir, &dir, &mut data).unwrap();
+
+        let client = MockClient::default()
+            .with(
+                "https://api.curseforge.com/v1/mods/238222",
+                r#"{"data": {"id": 238222, "slug": "jei", "classId": 6,
+                    "name": "Just Enough Items"}}"#,
+            )
+            .with(
+                "https://api.curseforge.com/v1/mods/238222/files/4712866",
+                r#"{"data": {"id": 4712866, "fileName": "jei-1.20.1-forge.jar",
+                    "fileLength": 1000}}"#,
+            );
+        let resolver = Resolver::new(client).with_curseforge_key("key");
+        let out = dir.join("imported");
+        data.set_position(0);
+        let imported = block_on(import(data, &out, &resolver)).unwrap();
+        assert_eq!(imported.pack.versions, pack.versions);
+        assert_eq!(imported.pack.metadata.name(None), pack.metadata.name(None));
+        let files: Vec<_> = imported.pack.managed_files.iter().collect();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].path, "config/jei.toml");
+        assert_eq!(
+            fs::read(out.join("overrides/config/jei.toml")).unwrap(),
+            b"cheats = false"
+        );
+        assert_eq!(files[1], &jei);
+        assert!(imported.unpinned.is_empty() && imported.unnamed.is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
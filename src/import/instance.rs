@@ -0,0 +1,266 @@
+//! Importing the mods of an existing game directory
+//!
+//! Most players start from a `.minecraft` folder they assembled by hand, which records nothing
+//! about where its jars came from. Each jar in `mods` is hashed and looked up on Modrinth by its
+//! SHA-1 digest, and the ones Modrinth doesn't know on CurseForge by their fingerprints, so
+//! published jars come back as sources pinned to their versions. The rest stay where they are as
+//! path sources. Names, descriptions, and sides the platforms don't give are read from the
+//! loader metadata in the jars. Jars disabled the way launchers disable them, with a `.disabled`
+//! suffix, become disabled files.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+
+use relative_path::RelativePathBuf;
+use snafu::ResultExt;
+use tracing::{debug, instrument, warn};
+
+use super::{ImportError, IoSnafu, LookupSnafu};
+use crate::{
+    hash::{curseforge_fingerprint, Hasher},
+    http::HttpClient,
+    jar::{self, ModMetadata},
+    resolve::{ResolveError, Resolver},
+    types::{FileKind, HashAlgorithm, Hashes, ManagedFile, Side, Source},
+};
+
+/// The folder of the game directory that is scanned
+const MODS: &str = "mods";
+
+/// The suffix launchers add to the names of jars they shouldn't load
+const DISABLED: &str = ".disabled";
+
+/// A jar found in the folder, and what is known about it so far
+struct Jar {
+    /// Where the jar is, relative to the game directory
+    local: RelativePathBuf,
+    /// The name of the jar, without any `.disabled` suffix
+    filename: String,
+    /// Whether the jar is loaded
+    enabled: bool,
+    /// The jar's blake3 and SHA-1 digests
+    hashes: Hashes,
+    /// The jar's CurseForge fingerprint
+    fingerprint: u32,
+    /// What the jar declares about itself, if it declares anything readable
+    metadata: Option<ModMetadata>,
+}
+
+/// Lists and hashes the jars in the game directory's `mods` folder, sorted by name
+fn jars(instance: &Path) -> Result<Vec<Jar>, ImportError> {
+    let folder = instance.join(MODS);
+    let mut names: Vec<_> = fs::read_dir(&folder)
+        .context(IoSnafu { path: &folder })?
+        .map(|entry| Ok(entry?.file_name()))
+        .collect::<Result<_, std::io::Error>>()
+        .context(IoSnafu { path: &folder })?;
+    names.sort();
+    let mut jars = Vec::new();
+    for name in names {
+        let Some(name) = name.to_str() else {
+            warn!(?name, "Skipping file whose name isn't valid UTF-8");
+            continue;
+        };
+        let (filename, enabled) = match name.strip_suffix(DISABLED) {
+            Some(filename) => (filename, false),
+            None => (name, true),
+        };
+        let path: PathBuf = folder.join(name);
+        let is_jar = Path::new(filename)
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("jar"));
+        if !is_jar || !path.is_file() {
+            continue;
+        }
+        let contents = fs::read(&path).context(IoSnafu { path: &path })?;
+        let mut hasher = Hasher::new([HashAlgorithm::Blake3, HashAlgorithm::Sha1]);
+        hasher.update(&contents);
+        let metadata = jar::read(Cursor::new(&contents)).unwrap_or_else(|error| {
+            warn!(name, %error, "Skipping unreadable mod metadata");
+            None
+        });
+        jars.push(Jar {
+            local: RelativePathBuf::from(MODS).join(name),
+            filename: filename.to_string(),
+            enabled,
+            hashes: hasher.finalize(),
+            fingerprint: curseforge_fingerprint(&contents),
+            metadata,
+        });
+    }
+    Ok(jars)
+}
+
+/// Looks a jar up on Modrinth by its SHA-1 digest
+#[cfg(feature = "modrinth")]
+async fn modrinth<C: HttpClient>(
+    jar: &Jar,
+    resolver: &Resolver<C>,
+) -> Result<Option<Source>, ImportError> {
+    let Some(sha1) = &jar.hashes.sha1 else {
+        return Ok(None);
+    };
+    resolver
+        .identify_modrinth(sha1)
+        .await
+        .context(LookupSnafu { path: &jar.local })
+}
+
+/// Modrinth lookups need the `modrinth` feature, so without it no jar is found there
+#[cfg(not(feature = "modrinth"))]
+#[allow(clippy::unused_async)]
+async fn modrinth<C: HttpClient>(
+    _jar: &Jar,
+    _resolver: &Resolver<C>,
+) -> Result<Option<Source>, ImportError> {
+    Ok(None)
+}
+
+/// Scans the `mods` folder of the game directory `instance` into managed files
+///
+/// Path sources are relative to `instance`, so the pack's manifest belongs there. Without a
+/// CurseForge key, jars Modrinth doesn't know aren't looked up on CurseForge, and a warning is
+/// logged.
+///
+/// # Errors
+///
+/// Returns an error if the folder or a jar can't be read, or a lookup fails
+#[instrument(skip_all, fields(instance = %instance.display()))]
+pub async fn scan<C: HttpClient>(
+    instance: &Path,
+    resolver: &Resolver<C>,
+) -> Result<BTreeSet<ManagedFile>, ImportError> {
+    let mut found = Vec::new();
+    for jar in jars(instance)? {
+        let source = modrinth(&jar, resolver).await?;
+        found.push((jar, source.map(|source| (source, None))));
+    }
+
+    let fingerprints: Vec<_> = found
+        .iter()
+        .filter(|(_, source)| source.is_none())
+        .map(|(jar, _)| jar.fingerprint)
+        .collect();
+    let mut identified = match resolver
+        .identify_curseforge_fingerprints(&fingerprints)
+        .await
+    {
+        Ok(identified) => identified,
+        Err(ResolveError::MissingCredentials { .. }) => {
+            warn!("No CurseForge key, so jars are only looked up on Modrinth");
+            BTreeMap::new()
+        }
+        Err(source) => {
+            return Err(source).context(LookupSnafu {
+                path: RelativePathBuf::from(MODS),
+            })
+        }
+    };
+
+    let mut files = BTreeSet::new();
+    for (jar, source) in found {
+        let source = source.or_else(|| {
+            let file = identified.remove(&jar.fingerprint)?;
+            Some((file.source, file.name))
+        });
+        debug!(path = %jar.local, identified = source.is_some(), "Importing jar");
+        let (source, name) = source.unwrap_or_else(|| {
+            let source = Source::Path {
+                path: jar.local.clone(),
+                blake3: jar.hashes.blake3.unwrap_or_default(),
+                hashes: Hashes::default(),
+            };
+            (source, None)
+        });
+        let mut file = ManagedFile {
+            name,
+            description: None,
+            path: RelativePathBuf::from(MODS).join(&jar.filename),
+            filename: jar.filename,
+            devel: true,
+            side: Side::Both,
+            source,
+            enabled: jar.enabled,
+            notes: None,
+            kind: FileKind::Regular,
+            collection: None,
+        };
+        if let Some(metadata) = &jar.metadata {
+            metadata.fill(&mut file);
+        }
+        files.insert(file);
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::{
+        archive::DeterministicZipWriter,
+        hash::blake3,
+        http::testing::{block_on, MockClient},
+    };
+
+    // Jars are found on CurseForge by fingerprint, and unknown ones stay path sources described
+    // by their metadata
+    #[test]
+    fn scan() {
+        let dir = std::env::temp_dir().join(format!("ffpack-instance-{}", std::process::id()));
+        fs::create_dir_all(dir.join("mods")).unwrap();
+        fs::write(dir.join("mods/jei.jar.disabled"), b"jei").unwrap();
+        let mut homemade = DeterministicZipWriter::new(Vec::new());
+        homemade.add_bytes(
+            "fabric.mod.json",
+            r#"{"id": "homemade", "name": "Homemade", "environment": "client"}"#,
+        );
+        let homemade = homemade.finish().unwrap();
+        fs::write(dir.join("mods/homemade.jar"), &homemade).unwrap();
+        fs::write(dir.join("mods/notes.txt"), b"not a mod").unwrap();
+
+        let matches = format!(
+            r#"{{"data": {{"exactMatches": [{{"id": 238222, "file": {{"id": 4712866,
+                "fileName": "jei-1.20.1-forge.jar", "fileLength": 3,
+                "fileFingerprint": {}}}}}]}}}}"#,
+            curseforge_fingerprint(b"jei")
+        );
+        let client = MockClient::default()
+            .with("https://api.curseforge.com/v1/fingerprints/432", matches)
+            .with(
+                "https://api.curseforge.com/v1/mods/238222",
+                r#"{"data": {"id": 238222, "slug": "jei", "name": "Just Enough Items"}}"#,
+            );
+        let resolver = Resolver::new(client).with_curseforge_key("key");
+        let files = block_on(super::scan(&dir, &resolver)).unwrap();
+        let files: Vec<_> = files.into_iter().collect();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].path, "mods/homemade.jar");
+        assert_eq!(
+            files[0].source,
+            Source::Path {
+                path: RelativePathBuf::from("mods/homemade.jar"),
+                blake3: blake3(&homemade),
+                hashes: Hashes::default(),
+            }
+        );
+        assert_eq!(files[0].name.as_deref(), Some("Homemade"));
+        assert_eq!(files[0].side, Side::Client);
+        assert_eq!(files[1].path, "mods/jei.jar");
+        assert_eq!(files[1].name.as_deref(), Some("Just Enough Items"));
+        assert!(!files[1].enabled);
+        assert_eq!(
+            files[1].source,
+            Source::Curseforge {
+                slug: "jei".into(),
+                file_id: Some(4_712_866),
+            }
+        );
+        let requests = resolver.client().requests.lock().unwrap();
+        assert!(requests.iter().any(|request| request.body.is_some()));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,331 @@
+//! Importing Modrinth modpacks (`.mrpack`)
+//!
+//! Every file the index lists is looked up on Modrinth by its SHA-1 digest, so files published
+//! there come back as Modrinth sources pinned to their version, and the rest as url sources
+//! keeping the index's digests and mirrors. Env flags become sides, and the override folders are
+//! extracted into the pack's directory as path sources. Written as
+//! [`export::mrpack`](crate::export::mrpack) writes them, packs round trip.
+
+use std::{
+    collections::BTreeMap,
+    io::{Read, Seek},
+    path::Path,
+};
+
+use relative_path::RelativePath;
+use semver::Version;
+use serde::Deserialize;
+use snafu::{OptionExt, ResultExt};
+use tracing::{debug, instrument, warn};
+use url::Url;
+
+use super::{
+    extract_overrides, lenient_version, read_json, resolve_path, ImportError, Imported,
+    InvalidFieldSnafu, LoaderFn, LookupSnafu, MissingFieldSnafu, UnsupportedLoaderSnafu, ZipSnafu,
+};
+use crate::{
+    archive::ZipArchive,
+    export::mrpack::INDEX,
+    http::HttpClient,
+    resolve::Resolver,
+    types::{FileKind, Hashes, Loader, ManagedFile, Metadata, Minecraft, Side, Source, Versions},
+    Pack,
+};
+
+/// The ids loaders are listed under in an index's dependencies, and the loaders they are for
+const LOADERS: [(&str, LoaderFn); 3] = [
+    ("quilt-loader", Loader::Quilt),
+    ("fabric-loader", Loader::Fabric),
+    ("forge", Loader::Forge),
+];
+
+/// The override folders, in the order they are applied, and the sides they are for
+///
+/// Files in a side's folder replace those of the same path in `overrides`, as they do when
+/// the pack is installed on that side.
+const OVERRIDES: [(&str, Side); 3] = [
+    ("overrides", Side::Both),
+    ("client-overrides", Side::Client),
+    ("server-overrides", Side::Server),
+];
+
+/// The index of an mrpack
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Index {
+    /// The version of the pack
+    version_id: String,
+    /// The name of the pack
+    name: String,
+    /// A short description of the pack
+    #[serde(default)]
+    summary: Option<String>,
+    /// The files launchers download
+    files: Vec<IndexFile>,
+    /// The versions of the game and loader, by their Modrinth ids
+    dependencies: BTreeMap<String, String>,
+}
+
+/// A file listed in the index
+#[derive(Deserialize)]
+struct IndexFile {
+    /// Where the file is installed in the instance
+    path: String,
+    /// The file's digests, by algorithm
+    hashes: BTreeMap<String, String>,
+    /// Which sides the file is needed on, or both if it is left out
+    #[serde(default)]
+    env: Option<Env>,
+    /// Where to download the file, in the order to try them
+    downloads: Vec<Url>,
+}
+
+/// Which sides a file is needed on
+#[derive(Deserialize)]
+struct Env {
+    /// `required`, `optional`, or `unsupported` on the client
+    client: String,
+    /// `required`, `optional`, or `unsupported` on the server
+    server: String,
+}
+
+impl Env {
+    /// Returns the side a file needed on these sides is for
+    fn side(&self) -> Side {
+        match (self.client == "unsupported", self.server == "unsupported") {
+            (false, true) => Side::Client,
+            (true, false) => Side::Server,
+            _ => Side::Both,
+        }
+    }
+}
+
+/// Reads the game and loader versions from the index's dependencies
+fn versions(dependencies: &BTreeMap<String, String>) -> Result<Versions, ImportError> {
+    let path = Path::new(INDEX);
+    let field = "dependencies.minecraft";
+    let minecraft = dependencies
+        .get("minecraft")
+        .context(MissingFieldSnafu { path, field })?;
+    let minecraft = Minecraft::new(minecraft).ok().context(InvalidFieldSnafu {
+        path,
+        field,
+        value: minecraft,
+    })?;
+    let (version, loader) = LOADERS
+        .into_iter()
+        .find_map(|(id, loader)| Some((dependencies.get(id)?, loader)))
+        .context(UnsupportedLoaderSnafu { path })?;
+    let version = lenient_version(version).context(InvalidFieldSnafu {
+        path,
+        field: "dependencies",
+        value: version,
+    })?;
+    Ok(Versions {
+        minecraft,
+        loader: loader(version),
+        java: None,
+    })
+}
+
+/// Turns a file listed in the index into a managed file, looking it up on Modrinth
+async fn index_file<C: HttpClient>(
+    file: IndexFile,
+    resolver: &Resolver<C>,
+) -> Result<ManagedFile, ImportError> {
+    let path = resolve_path(RelativePath::new(""), &file.path)?;
+    let hashes = Hashes::from_hex(
+        file.hashes
+            .iter()
+            .map(|(algorithm, digest)| (algorithm.as_str(), digest.as_str())),
+    );
+    let identified = match &hashes.sha1 {
+        Some(sha1) => resolver
+            .identify_modrinth(sha1)
+            .await
+            .context(LookupSnafu { path: &path })?,
+        None => None,
+    };
+    debug!(%path, on_modrinth = identified.is_some(), "Importing file");
+    let source = if let Some(source) = identified {
+        source
+    } else {
+        let mut downloads = file.downloads.into_iter();
+        let url = downloads.next().context(MissingFieldSnafu {
+            path: INDEX,
+            field: "files.downloads",
+        })?;
+        Source::Url {
+            url,
+            blake3: [0; 32],
+            hashes,
+            mirrors: downloads.collect(),
+        }
+    };
+    Ok(ManagedFile {
+        name: None,
+        description: None,
+        filename: path.file_name().unwrap_or_default().into(),
+        devel: true,
+        side: file.env.as_ref().map_or(Side::Both, Env::side),
+        path,
+        source,
+        enabled: true,
+        notes: None,
+        kind: FileKind::Regular,
+        collection: None,
+    })
+}
+
+/// Reads the mrpack `reader` holds into a pack, extracting its overrides into `root`
+///
+/// Path sources point at the extracted overrides, so the pack's manifest belongs in `root`.
+/// mrpacks don't know blake3 hashes, so files that aren't on Modrinth come back as unpinned url
+/// sources, listed in the returned [`Imported`].
+///
+/// # Errors
+///
+/// Returns an error if the archive or its index can't be read, a file can't be looked up on
+/// Modrinth, or an override can't be extracted
+#[instrument(skip_all, fields(root = %root.display()))]
+pub async fn import<C: HttpClient>(
+    reader: impl Read + Seek,
+    root: &Path,
+    resolver: &Resolver<C>,
+) -> Result<Imported, ImportError> {
+    let mut archive = ZipArchive::new(reader).context(ZipSnafu)?;
+    let index: Index = read_json(&mut archive, INDEX)?;
+
+    let version = lenient_version(&index.version_id).unwrap_or_else(|| {
+        warn!(
+            version = %index.version_id,
+            "Pack version isn't semver, using 1.0.0"
+        );
+        Version::new(1, 0, 0)
+    });
+    let metadata = Metadata::new(index.name, "", version);
+    let mut imported = Imported {
+        pack: Pack {
+            metadata: match index.summary {
+                Some(summary) => metadata.with_description(summary),
+                None => metadata,
+            },
+            versions: versions(&index.dependencies)?,
+            managed_files: extract_overrides(&mut archive, root, &OVERRIDES)?,
+        },
+        unpinned: Vec::new(),
+        unnamed: Vec::new(),
+    };
+    for file in index.files {
+        let file = index_file(file, resolver).await?;
+        if let Source::Url { .. } = file.source {
+            imported.unpinned.push(file.path.clone());
+        }
+        imported.pack.managed_files.replace(file);
+    }
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use std::{fs, io::Cursor};
+
+    use relative_path::RelativePathBuf;
+
+    use super::*;
+    use crate::{
+        export,
+        hash::blake3,
+        http::testing::{block_on, MockClient},
+        lock::{LockedFile, Lockfile},
+        resolve::{ProjectDetails, ResolvedArtifact, UpstreamIds},
+    };
+
+    // Exported packs come back with their Modrinth files identified and overrides extracted
+    #[test]
+    fn round_trip() {
+        let dir = std::env::temp_dir().join(format!("ffpack-mrpack-import-{}", std::process::id()));
+        fs::create_dir_all(dir.join("config")).unwrap();
+        fs::write(dir.join("config/client.toml"), b"fov = 90").unwrap();
+        let sodium = ManagedFile {
+            path: RelativePathBuf::from("mods/sodium.jar"),
+            side: Side::Client,
+            source: Source::Modrinth {
+                slug: "sodium".into(),
+                version_id: Some("rAfhHfow".into()),
+            },
+            ..ManagedFile::default()
+        };
+        let config = ManagedFile {
+            path: RelativePathBuf::from("config/client.toml"),
+            side: Side::Client,
+            source: Source::Path {
+                path: RelativePathBuf::from("config/client.toml"),
+                blake3: blake3(b"fov = 90"),
+                hashes: Hashes::default(),
+            },
+            ..ManagedFile::default()
+        };
+        let pack = Pack {
+            managed_files: [sodium.clone(), config, ManagedFile::default()]
+                .into_iter()
+                .collect(),
+            ..Pack::default()
+        };
+        let mut lock = Lockfile::default();
+        for (file, byte) in [(&sodium, 1), (&ManagedFile::default(), 3)] {
+            let artifact = ResolvedArtifact {
+                download_url: Url::parse("https://cdn.example.com/file.jar").unwrap(),
+                filename: file.filename.clone(),
+                size: Some(100),
+                hashes: Hashes {
+                    sha1: Some([byte; 20]),
+                    sha512: Some([byte; 64]),
+                    ..Hashes::default()
+                },
+                mirrors: Vec::new(),
+                extract: None,
+                ids: UpstreamIds::default(),
+                details: ProjectDetails::default(),
+            };
+            let source = file.source.clone();
+            lock.files
+                .insert(file.path.clone(), LockedFile { source, artifact });
+        }
+        let mut data = Cursor::new(Vec::new());
+        export::mrpack::export(&pack, &lock, &dir, &mut data).unwrap();
+
+        let lookup = format!(
+            "https://api.modrinth.com/v2/version_file/{}?algorithm=sha1",
+            "01".repeat(20)
+        );
+        let client = MockClient::default()
+            .with(&lookup, r#"{"id": "rAfhHfow", "project_id": "AANobbMI"}"#)
+            .with(
+                "https://api.modrinth.com/v2/project/AANobbMI",
+                r#"{"slug": "sodium"}"#,
+            );
+        let resolver = Resolver::new(client);
+        let out = dir.join("imported");
+        data.set_position(0);
+        let imported = block_on(import(data, &out, &resolver)).unwrap();
+        assert_eq!(imported.pack.versions, pack.versions);
+        let files: Vec<_> = imported.pack.managed_files.iter().collect();
+        assert_eq!(files.len(), 3);
+        assert_eq!(files[0].path, "config/client.toml");
+        assert_eq!(files[0].side, Side::Client);
+        assert_eq!(
+            fs::read(out.join("client-overrides/config/client.toml")).unwrap(),
+            b"fov = 90"
+        );
+        let Source::Path { blake3: hash, .. } = &files[0].source else {
+            panic!("overrides should become path sources");
+        };
+        assert_eq!(*hash, blake3(b"fov = 90"));
+        assert!(matches!(files[1].source, Source::Url { .. }));
+        assert_eq!(imported.unpinned, ["mods/MyAwesomeMod.jar"]);
+        assert_eq!(files[2].source, sodium.source);
+        assert_eq!(files[2].side, Side::Client);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
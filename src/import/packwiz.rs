@@ -0,0 +1,423 @@
+//! Importing packwiz projects
+//!
+//! Metafiles become managed files with the sides, options, and hashes they declare: Modrinth
+//! and CurseForge metafiles are taken back to their platforms through their `update` tables,
+//! other downloads become url sources, and the rest of the project's files become path sources.
+//! Written as [`export::packwiz`](crate::export::packwiz) writes them, projects round trip.
+
+use std::{collections::BTreeSet, fs, path::Path};
+
+use relative_path::{RelativePath, RelativePathBuf};
+use semver::Version;
+use snafu::{OptionExt, ResultExt};
+use tracing::{debug, instrument};
+use url::Url;
+
+use super::{
+    lenient_version, resolve_path, ImportError, Imported, InvalidFieldSnafu, IoSnafu, LoaderFn,
+    MissingFieldSnafu, TomlSnafu, UnsupportedLoaderSnafu,
+};
+use crate::{
+    export::packwiz::{METAFILE_SUFFIX, PACK_FILE},
+    hash::blake3,
+    toml::{self, Table, Value},
+    types::{
+        FileKind, Hashes, Loader, ManagedFile, Metadata, Minecraft, Side, Source, Versions,
+        DISABLED_SUFFIX,
+    },
+    Pack,
+};
+
+/// The keys loader versions are listed under in `pack.toml`, and the loaders they are for
+const LOADERS: [(&str, LoaderFn); 3] = [
+    ("versions.quilt", Loader::Quilt),
+    ("versions.fabric", Loader::Fabric),
+    ("versions.forge", Loader::Forge),
+];
+
+/// Reads a TOML file
+fn read_toml(path: &Path) -> Result<Table, ImportError> {
+    let text = fs::read_to_string(path).context(IoSnafu { path })?;
+    toml::parse(&text).context(TomlSnafu { path })
+}
+
+/// Returns a required string field of a table read from `path`
+fn string<'a>(table: &'a Table, path: &Path, field: &'static str) -> Result<&'a str, ImportError> {
+    toml::get(table, field)
+        .and_then(Value::as_str)
+        .context(MissingFieldSnafu { path, field })
+}
+
+/// Returns an optional string field of a table
+fn optional<'a>(table: &'a Table, field: &str) -> Option<&'a str> {
+    toml::get(table, field).and_then(Value::as_str)
+}
+
+/// Reads the game and loader versions from `pack.toml`
+fn versions(pack: &Table, path: &Path) -> Result<Versions, ImportError> {
+    let field = "versions.minecraft";
+    let minecraft = string(pack, path, field)?;
+    let minecraft = Minecraft::new(minecraft).ok().context(InvalidFieldSnafu {
+        path,
+        field,
+        value: minecraft,
+    })?;
+    let (field, loader) = LOADERS
+        .into_iter()
+        .find(|(field, _)| toml::get(pack, field).is_some())
+        .context(UnsupportedLoaderSnafu { path })?;
+    let version = string(pack, path, field)?;
+    let version = lenient_version(version).context(InvalidFieldSnafu {
+        path,
+        field,
+        value: version,
+    })?;
+    Ok(Versions {
+        minecraft,
+        loader: loader(version),
+        java: None,
+    })
+}
+
+/// Reads the pack's metadata from `pack.toml`
+fn metadata(pack: &Table, path: &Path) -> Result<Metadata, ImportError> {
+    let name = string(pack, path, "name")?;
+    let author = optional(pack, "author").unwrap_or_default();
+    let version = match optional(pack, "version") {
+        Some(version) => lenient_version(version).context(InvalidFieldSnafu {
+            path,
+            field: "version",
+            value: version,
+        })?,
+        None => Version::new(1, 0, 0),
+    };
+    let metadata = Metadata::new(name, author, version);
+    Ok(match optional(pack, "description") {
+        Some(description) => metadata.with_description(description),
+        None => metadata,
+    })
+}
+
+/// Reads a metafile at `path` in the project into the managed file it describes
+fn metafile(dir: &Path, path: &RelativePath) -> Result<ManagedFile, ImportError> {
+    let local = path.to_path(dir);
+    let table = read_toml(&local)?;
+    let filename = string(&table, &local, "filename")?;
+    let side = match optional(&table, "side") {
+        None | Some("both" | "") => Side::Both,
+        Some("client") => Side::Client,
+        Some("server") => Side::Server,
+        Some(side) => {
+            return InvalidFieldSnafu {
+                path: local,
+                field: "side",
+                value: side,
+            }
+            .fail()
+        }
+    };
+    let hashes = match (
+        optional(&table, "download.hash-format"),
+        optional(&table, "download.hash"),
+    ) {
+        (Some(format), Some(hash)) => Hashes::from_hex([(format, hash)]),
+        _ => Hashes::default(),
+    };
+
+    let integer = |field| toml::get(&table, field).and_then(Value::as_integer);
+    let source = if let Some(project_id) = integer("update.curseforge.project-id") {
+        Source::Curseforge {
+            slug: project_id.to_string(),
+            file_id: integer("update.curseforge.file-id").and_then(|id| id.try_into().ok()),
+        }
+    } else if let Some(project_id) = optional(&table, "update.modrinth.mod-id") {
+        // Modrinth takes project ids anywhere it takes slugs
+        Source::Modrinth {
+            slug: project_id.into(),
+            version_id: optional(&table, "update.modrinth.version").map(Into::into),
+        }
+    } else {
+        let field = "download.url";
+        let url = string(&table, &local, field)?;
+        Source::Url {
+            url: Url::parse(url).ok().context(InvalidFieldSnafu {
+                path: &local,
+                field,
+                value: url,
+            })?,
+            blake3: [0; 32],
+            hashes,
+            mirrors: Vec::new(),
+        }
+    };
+
+    let flag = |field| toml::get(&table, field).and_then(Value::as_bool);
+    let optional_off =
+        flag("option.optional") == Some(true) && flag("option.default") != Some(true);
+    Ok(ManagedFile {
+        name: optional(&table, "name").map(Into::into),
+        description: optional(&table, "option.description").map(Into::into),
+        filename: filename.into(),
+        devel: true,
+        path: path.with_file_name(filename),
+        side,
+        source,
+        enabled: !optional_off,
+        notes: None,
+        kind: FileKind::Regular,
+        collection: None,
+    })
+}
+
+/// Reads a file of the project that isn't a metafile into a path source
+fn plain_file(
+    dir: &Path,
+    path: RelativePathBuf,
+    hash_format: Option<&str>,
+    hash: Option<&str>,
+) -> Result<ManagedFile, ImportError> {
+    let local = path.to_path(dir);
+    let contents = fs::read(&local).context(IoSnafu { path: local })?;
+    let hashes = match (hash_format, hash) {
+        (Some(format), Some(hash)) => Hashes::from_hex([(format, hash)]),
+        _ => Hashes::default(),
+    };
+    let installed = path.as_str();
+    let (installed, enabled) = match installed.strip_suffix(DISABLED_SUFFIX) {
+        Some(installed) => (installed, false),
+        None => (installed, true),
+    };
+    let installed = RelativePathBuf::from(installed);
+    Ok(ManagedFile {
+        name: None,
+        description: None,
+        filename: installed.file_name().unwrap_or_default().into(),
+        devel: true,
+        path: installed.clone(),
+        side: Side::Both,
+        source: Source::Path {
+            path,
+            blake3: blake3(&contents),
+            hashes,
+        },
+        enabled,
+        notes: None,
+        kind: FileKind::Regular,
+        collection: None,
+    })
+}
+
+/// Reads the packwiz project in the directory `dir` into a pack
+///
+/// Path sources point at the files in the project, so the pack's manifest belongs in `dir`
+/// too. packwiz doesn't know blake3 hashes, so url sources come back unpinned, and it only
+/// knows CurseForge projects by id, so their sources name the id where a slug would go. Both
+/// are listed in the returned [`Imported`].
+///
+/// # Errors
+///
+/// Returns an error if a file of the project can't be read, lacks a field it needs, or lists a
+/// file outside of `dir`
+#[instrument(skip_all, fields(dir = %dir.display()))]
+pub fn import(dir: &Path) -> Result<Imported, ImportError> {
+    let pack_path = dir.join(PACK_FILE);
+    let pack_file = read_toml(&pack_path)?;
+    let root = RelativePath::new("");
+    let index = resolve_path(root, string(&pack_file, &pack_path, "index.file")?)?;
+    let index_dir = index.parent().unwrap_or(root);
+    let index_path = index.to_path(dir);
+    let index = read_toml(&index_path)?;
+    let hash_format = optional(&index, "hash-format");
+
+    let mut imported = Imported {
+        pack: Pack {
+            metadata: metadata(&pack_file, &pack_path)?,
+            versions: versions(&pack_file, &pack_path)?,
+            managed_files: BTreeSet::new(),
+        },
+        unpinned: Vec::new(),
+        unnamed: Vec::new(),
+    };
+    let entries = index
+        .get("files")
+        .and_then(Value::as_array)
+        .unwrap_or_default();
+    for entry in entries.iter().filter_map(Value::as_table) {
+        let path = resolve_path(index_dir, string(entry, &index_path, "file")?)?;
+        let is_metafile = toml::get(entry, "metafile").and_then(Value::as_bool) == Some(true)
+            || path.as_str().ends_with(METAFILE_SUFFIX);
+        debug!(%path, is_metafile, "Importing file");
+        let file = if is_metafile {
+            metafile(dir, &path)?
+        } else {
+            let hash_format = optional(entry, "hash-format").or(hash_format);
+            plain_file(dir, path, hash_format, optional(entry, "hash"))?
+        };
+        match &file.source {
+            Source::Url { .. } => imported.unpinned.push(file.path.clone()),
+            Source::Curseforge { .. } => imported.unnamed.push(file.path.clone()),
+            _ => {}
+        }
+        imported.pack.managed_files.replace(file);
+    }
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::{
+        export,
+        lock::{LockedFile, Lockfile},
+        resolve::{ProjectDetails, ResolvedArtifact, UpstreamIds},
+    };
+
+    /// Locks a file to a download with the given digests and ids
+    fn locked(file: &ManagedFile, url: &str, hashes: Hashes, ids: UpstreamIds) -> LockedFile {
+        LockedFile {
+            source: file.source.clone(),
+            artifact: ResolvedArtifact {
+                download_url: Url::parse(url).unwrap(),
+                filename: file.filename.clone(),
+                size: None,
+                hashes,
+                mirrors: Vec::new(),
+                extract: None,
+                ids,
+                details: ProjectDetails::default(),
+            },
+        }
+    }
+
+    /// A pack with a Modrinth, a disabled CurseForge, a url, and a path file, and its lockfile
+    fn locked_pack() -> (Pack, Lockfile) {
+        let file = |path: &str, side, source| ManagedFile {
+            name: None,
+            description: None,
+            filename: RelativePath::new(path).file_name().unwrap().into(),
+            path: RelativePathBuf::from(path),
+            side,
+            source,
+            ..ManagedFile::default()
+        };
+        let sodium = file(
+            "mods/sodium.jar",
+            Side::Client,
+            Source::Modrinth {
+                slug: "AANobbMI".into(),
+                version_id: Some("rAfhHfow".into()),
+            },
+        );
+        let jei = ManagedFile {
+            description: Some("Shows recipes".into()),
+            enabled: false,
+            ..file(
+                "mods/jei.jar",
+                Side::Both,
+                Source::Curseforge {
+                    slug: "238222".into(),
+                    file_id: Some(4_712_866),
+                },
+            )
+        };
+        let extra = file(
+            "mods/extra.jar",
+            Side::Server,
+            Source::Url {
+                url: Url::parse("https://example.com/extra.jar").unwrap(),
+                blake3: [0; 32],
+                hashes: Hashes {
+                    sha256: Some([3; 32]),
+                    ..Hashes::default()
+                },
+                mirrors: Vec::new(),
+            },
+        );
+        let config = file(
+            "config/options.txt",
+            Side::Both,
+            Source::Path {
+                path: RelativePathBuf::from("config/options.txt"),
+                blake3: blake3(b"fov:90"),
+                hashes: Hashes::default(),
+            },
+        );
+
+        let sha1 = Hashes {
+            sha1: Some([1; 20]),
+            ..Hashes::default()
+        };
+        let curseforge = UpstreamIds {
+            project_id: Some("238222".into()),
+            file_id: Some("4712866".into()),
+            ..UpstreamIds::default()
+        };
+        let modrinth = UpstreamIds {
+            version_id: Some("rAfhHfow".into()),
+            ..UpstreamIds::default()
+        };
+        let mut lock = Lockfile::default();
+        let entries = [
+            (
+                &sodium,
+                "https://cdn.modrinth.com/s.jar",
+                sha1.clone(),
+                modrinth,
+            ),
+            (&jei, "https://edge.forgecdn.net/j.jar", sha1, curseforge),
+            (
+                &extra,
+                "https://example.com/extra.jar",
+                extra.source.hashes().unwrap(),
+                UpstreamIds::default(),
+            ),
+        ];
+        for (file, url, hashes, ids) in entries {
+            let locked = locked(file, url, hashes, ids);
+            lock.files.insert(file.path.clone(), locked);
+        }
+        let pack = Pack {
+            managed_files: [sodium, jei, extra, config].into_iter().collect(),
+            ..Pack::default()
+        };
+        (pack, lock)
+    }
+
+    // Exported projects come back with their sides, options, and hashes
+    #[test]
+    fn round_trip() {
+        let dir = std::env::temp_dir().join(format!("ffpack-packwiz-{}", std::process::id()));
+        fs::create_dir_all(dir.join("repo/config")).unwrap();
+        fs::write(dir.join("repo/config/options.txt"), b"fov:90").unwrap();
+        let (pack, lock) = locked_pack();
+        let out = dir.join("packwiz");
+        let losses = export::packwiz::export(&pack, &lock, &dir.join("repo"), &out).unwrap();
+        assert!(losses.is_empty());
+        let metafile = fs::read_to_string(out.join("mods/jei.pw.toml")).unwrap();
+        assert!(metafile.contains("mode = \"metadata:curseforge\""));
+
+        let imported = super::import(&out).unwrap();
+        assert_eq!(imported.unpinned, ["mods/extra.jar"]);
+        assert_eq!(imported.unnamed, ["mods/jei.jar"]);
+        assert_eq!(imported.pack.versions, pack.versions);
+        for (file, original) in imported.pack.managed_files.iter().zip(&pack.managed_files) {
+            assert_eq!(
+                (&file.path, &file.side, file.enabled, &file.description),
+                (
+                    &original.path,
+                    &original.side,
+                    original.enabled,
+                    &original.description
+                )
+            );
+            // Path sources pick up the digest in the index
+            if let Source::Path { hashes, .. } = &file.source {
+                assert!(hashes.sha256.is_some());
+            } else {
+                assert_eq!(file.source, original.source);
+            }
+        }
+        assert_eq!(imported.pack.managed_files.len(), 4);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,751 @@
+//! Read-only inspection of exported pack archives
+//!
+//! Lists what an `.mrpack` or CurseForge zip contains, and compares it against a manifest or
+//! another archive, without importing anything. This is meant for reviewing third-party pack
+//! updates before adopting them.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{Read, Seek},
+};
+
+use relative_path::RelativePathBuf;
+use serde::Deserialize;
+use snafu::{OptionExt, ResultExt, Snafu};
+use tracing::instrument;
+use url::Url;
+
+use crate::{
+    archive::{ZipArchive, ZipError},
+    edition::BedrockFormat,
+    types::{Hashes, Side, Source},
+    Pack,
+};
+
+/// The name of the index file in an `.mrpack`
+const MRPACK_INDEX: &str = "modrinth.index.json";
+
+/// The name of the manifest file in a CurseForge zip
+const CURSEFORGE_MANIFEST: &str = "manifest.json";
+
+/// The kinds of archive that can be inspected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ArchiveFormat {
+    /// A Modrinth `.mrpack`
+    Mrpack,
+    /// A CurseForge modpack zip
+    Curseforge,
+}
+
+/// Identifies a file across archives and manifests
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum FileKey {
+    /// A file with a known install path
+    Path(RelativePathBuf),
+    /// A CurseForge project, whose install path is only known once it is resolved
+    CurseforgeProject(u64),
+}
+
+/// Where a file in an archive comes from
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FileOrigin {
+    /// Downloaded from one of a list of urls
+    Download {
+        /// The urls the file can be downloaded from
+        urls: Vec<Url>,
+        /// The hashes listed for the file, by algorithm name (`sha1`, `sha512`, ...)
+        hashes: BTreeMap<String, String>,
+        /// The size of the file, if listed
+        size: Option<u64>,
+        /// Where the file is needed
+        side: Side,
+    },
+    /// A file from CurseForge
+    Curseforge {
+        /// The project id
+        project_id: u64,
+        /// The id of the project's file
+        file_id: u64,
+        /// Whether the file must be installed
+        required: bool,
+    },
+    /// Bundled inside the archive as an override
+    Override {
+        /// Where the file is needed
+        side: Side,
+        /// The uncompressed size of the file
+        size: u64,
+        /// The CRC-32 of the file
+        crc32: u32,
+    },
+}
+
+/// The contents of an exported pack archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveSummary {
+    /// The format of the archive
+    pub format: ArchiveFormat,
+    /// The name of the pack, if listed
+    pub name: Option<String>,
+    /// The version of the pack, if listed
+    pub version: Option<String>,
+    /// The minecraft version, if listed
+    pub minecraft: Option<String>,
+    /// Loader versions, by lowercase loader name (`fabric`, `quilt`, `forge`, `neoforge`)
+    pub loaders: BTreeMap<String, String>,
+    /// The files, sorted by key
+    pub files: BTreeMap<FileKey, FileOrigin>,
+}
+
+impl ArchiveSummary {
+    /// Reads the summary of an `.mrpack` or CurseForge zip
+    #[instrument(skip(reader), err)]
+    pub fn read<R: Read + Seek>(reader: R) -> Result<Self, InspectError> {
+        let mut archive = ZipArchive::new(reader).context(ZipSnafu)?;
+        if let Some(index) = read_entry(&mut archive, MRPACK_INDEX)? {
+            let index: MrpackIndex =
+                serde_json::from_slice(&index).context(JsonSnafu { name: MRPACK_INDEX })?;
+            let mut summary = Self {
+                format: ArchiveFormat::Mrpack,
+                name: index.name,
+                version: index.version_id,
+                minecraft: None,
+                loaders: BTreeMap::new(),
+                files: BTreeMap::new(),
+            };
+            for (id, version) in index.dependencies {
+                if id == "minecraft" {
+                    summary.minecraft = Some(version);
+                } else {
+                    let name = id.strip_suffix("-loader").unwrap_or(&id);
+                    summary.loaders.insert(name.to_string(), version);
+                }
+            }
+            for file in index.files {
+                let path = safe_path(&file.path).context(UnsafePathSnafu { path: &file.path })?;
+                let side = match file.env {
+                    Some(env) if env.client == "unsupported" => Side::Server,
+                    Some(env) if env.server == "unsupported" => Side::Client,
+                    _ => Side::Both,
+                };
+                summary.files.insert(
+                    FileKey::Path(path),
+                    FileOrigin::Download {
+                        urls: file.downloads,
+                        hashes: file.hashes,
+                        size: file.file_size,
+                        side,
+                    },
+                );
+            }
+            summary.add_overrides(
+                &archive,
+                &[
+                    ("overrides", Side::Both),
+                    ("client-overrides", Side::Client),
+                    ("server-overrides", Side::Server),
+                ],
+            );
+            Ok(summary)
+        } else if let Some(format) = BedrockFormat::detect(&mut archive) {
+            BedrockSnafu { format }.fail()
+        } else if let Some(manifest) = read_entry(&mut archive, CURSEFORGE_MANIFEST)? {
+            let manifest: CurseforgeManifest =
+                serde_json::from_slice(&manifest).context(JsonSnafu {
+                    name: CURSEFORGE_MANIFEST,
+                })?;
+            let mut summary = Self {
+                format: ArchiveFormat::Curseforge,
+                name: manifest.name,
+                version: manifest.version,
+                minecraft: Some(manifest.minecraft.version),
+                loaders: manifest
+                    .minecraft
+                    .mod_loaders
+                    .into_iter()
+                    .filter_map(|loader| {
+                        let (name, version) = loader.id.split_once('-')?;
+                        Some((name.to_lowercase(), version.to_string()))
+                    })
+                    .collect(),
+                files: BTreeMap::new(),
+            };
+            for file in manifest.files {
+                summary.files.insert(
+                    FileKey::CurseforgeProject(file.project_id),
+                    FileOrigin::Curseforge {
+                        project_id: file.project_id,
+                        file_id: file.file_id,
+                        required: file.required,
+                    },
+                );
+            }
+            let overrides = manifest
+                .overrides
+                .unwrap_or_else(|| "overrides".to_string());
+            summary.add_overrides(&archive, &[(overrides.as_str(), Side::Both)]);
+            Ok(summary)
+        } else {
+            UnknownFormatSnafu.fail()
+        }
+    }
+
+    /// Adds the files under each of the given override directories
+    fn add_overrides<R: Read + Seek>(
+        &mut self,
+        archive: &ZipArchive<R>,
+        directories: &[(&str, Side)],
+    ) {
+        for entry in archive.entries() {
+            let Some(name) = entry.enclosed_name().filter(|_| !entry.is_directory()) else {
+                continue;
+            };
+            for (directory, side) in directories {
+                if let Ok(path) = name.strip_prefix(directory) {
+                    self.files.insert(
+                        FileKey::Path(path.to_relative_path_buf()),
+                        FileOrigin::Override {
+                            side: side.clone(),
+                            size: entry.size(),
+                            crc32: entry.crc32(),
+                        },
+                    );
+                }
+            }
+        }
+    }
+
+    /// Lists what changed going from this archive to `newer`
+    pub fn diff(&self, newer: &ArchiveSummary) -> Vec<Change> {
+        let mut changes = version_changes(
+            self.minecraft.as_deref(),
+            newer.minecraft.as_deref(),
+            &self.loaders,
+            &newer.loaders,
+        );
+        for (key, old) in &self.files {
+            match newer.files.get(key) {
+                None => changes.push(Change::Removed { key: key.clone() }),
+                Some(new) if new != old => changes.push(Change::Changed {
+                    key: key.clone(),
+                    new: new.clone(),
+                }),
+                Some(_) => {}
+            }
+        }
+        for (key, new) in &newer.files {
+            if !self.files.contains_key(key) {
+                changes.push(Change::Added {
+                    key: key.clone(),
+                    origin: new.clone(),
+                });
+            }
+        }
+        changes
+    }
+
+    /// Lists what would change in `pack` if it were replaced with this archive
+    ///
+    /// Files are matched up by install path. CurseForge files have no path until they are
+    /// resolved, so they are always reported as added. Files present in both are only reported
+    /// as changed when the difference is visible without downloading anything: a different side,
+    /// or a url source that isn't among the archive's download urls.
+    pub fn diff_manifest(&self, pack: &Pack) -> Vec<Change> {
+        let current_loaders = [(
+            pack.versions.loader.name().to_lowercase(),
+            pack.versions.loader.version().to_string(),
+        )]
+        .into_iter()
+        .collect();
+        let mut changes = version_changes(
+            Some(&pack.versions.minecraft.to_string()),
+            self.minecraft.as_deref(),
+            &current_loaders,
+            &self.loaders,
+        );
+        for file in &pack.managed_files {
+            let key = FileKey::Path(file.path.clone());
+            let Some(new) = self.files.get(&key) else {
+                changes.push(Change::Removed { key });
+                continue;
+            };
+            let differs = match new {
+                FileOrigin::Download {
+                    urls, hashes, side, ..
+                } => {
+                    let listed =
+                        Hashes::from_hex(hashes.iter().map(|(name, hex)| (&**name, &**hex)));
+                    side != &file.side
+                        || matches!(&file.source, Source::Url { url, .. } if !urls.contains(url))
+                        || file
+                            .source
+                            .hashes()
+                            .is_some_and(|known| known.mismatch(&listed).is_some())
+                }
+                FileOrigin::Override { side, .. } => side != &file.side,
+                FileOrigin::Curseforge { .. } => false,
+            };
+            if differs {
+                changes.push(Change::Changed {
+                    key,
+                    new: new.clone(),
+                });
+            }
+        }
+        for (key, origin) in &self.files {
+            let known = match key {
+                FileKey::Path(path) => pack.managed_files.iter().any(|file| &file.path == path),
+                FileKey::CurseforgeProject(_) => false,
+            };
+            if !known {
+                changes.push(Change::Added {
+                    key: key.clone(),
+                    origin: origin.clone(),
+                });
+            }
+        }
+        changes
+    }
+}
+
+/// Lists the differences between two sets of minecraft and loader versions
+fn version_changes(
+    old_minecraft: Option<&str>,
+    new_minecraft: Option<&str>,
+    old_loaders: &BTreeMap<String, String>,
+    new_loaders: &BTreeMap<String, String>,
+) -> Vec<Change> {
+    let mut changes = Vec::new();
+    if old_minecraft != new_minecraft {
+        changes.push(Change::Minecraft {
+            old: old_minecraft.map(str::to_string),
+            new: new_minecraft.map(str::to_string),
+        });
+    }
+    let names: BTreeSet<_> = old_loaders.keys().chain(new_loaders.keys()).collect();
+    for name in names {
+        let (old, new) = (old_loaders.get(name), new_loaders.get(name));
+        if old != new {
+            changes.push(Change::Loader {
+                name: name.clone(),
+                old: old.cloned(),
+                new: new.cloned(),
+            });
+        }
+    }
+    changes
+}
+
+/// A difference between two packs
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Change {
+    /// The minecraft version changed
+    Minecraft {
+        /// The old version
+        old: Option<String>,
+        /// The new version
+        new: Option<String>,
+    },
+    /// A loader was added, removed, or changed version
+    Loader {
+        /// The lowercase loader name
+        name: String,
+        /// The old version
+        old: Option<String>,
+        /// The new version
+        new: Option<String>,
+    },
+    /// A file was added
+    Added {
+        /// The file
+        key: FileKey,
+        /// Where it comes from
+        origin: FileOrigin,
+    },
+    /// A file was removed
+    Removed {
+        /// The file
+        key: FileKey,
+    },
+    /// A file changed
+    Changed {
+        /// The file
+        key: FileKey,
+        /// Where it comes from now
+        new: FileOrigin,
+    },
+}
+
+/// Reads a whole entry by name, if it exists
+fn read_entry<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    name: &str,
+) -> Result<Option<Vec<u8>>, InspectError> {
+    let Some(index) = archive
+        .entries()
+        .iter()
+        .position(|entry| entry.name() == name)
+    else {
+        return Ok(None);
+    };
+    let mut data = Vec::new();
+    archive
+        .open(index)
+        .context(ZipSnafu)?
+        .read_to_end(&mut data)
+        .context(IoSnafu { name })?;
+    Ok(Some(data))
+}
+
+/// Checks that an install path listed in an index stays inside the instance
+fn safe_path(path: &str) -> Option<RelativePathBuf> {
+    let valid = !path.is_empty()
+        && !path.starts_with('/')
+        && !path.contains('\\')
+        && !path.contains(':')
+        && path.split('/').all(|component| component != "..");
+    valid.then(|| RelativePathBuf::from(path).normalize())
+}
+
+/// The index of an `.mrpack`
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MrpackIndex {
+    /// The pack name
+    name: Option<String>,
+    /// The pack version
+    version_id: Option<String>,
+    /// The files to download
+    #[serde(default)]
+    files: Vec<MrpackFile>,
+    /// Minecraft and loader versions
+    #[serde(default)]
+    dependencies: BTreeMap<String, String>,
+}
+
+/// A downloaded file in an `.mrpack`
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MrpackFile {
+    /// The install path
+    path: String,
+    /// Hashes by algorithm name
+    #[serde(default)]
+    hashes: BTreeMap<String, String>,
+    /// Which sides need the file
+    env: Option<MrpackEnv>,
+    /// The download urls
+    #[serde(default)]
+    downloads: Vec<Url>,
+    /// The size of the file
+    file_size: Option<u64>,
+}
+
+/// The environments an `.mrpack` file is for
+#[derive(Deserialize)]
+struct MrpackEnv {
+    /// `required`, `optional`, or `unsupported` on the client
+    client: String,
+    /// `required`, `optional`, or `unsupported` on the server
+    server: String,
+}
+
+/// The manifest of a CurseForge zip
+#[derive(Deserialize)]
+struct CurseforgeManifest {
+    /// Minecraft and loader versions
+    minecraft: CurseforgeMinecraft,
+    /// The pack name
+    name: Option<String>,
+    /// The pack version
+    version: Option<String>,
+    /// The files to download
+    #[serde(default)]
+    files: Vec<CurseforgeFile>,
+    /// The name of the overrides directory
+    overrides: Option<String>,
+}
+
+/// The versions in a CurseForge manifest
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CurseforgeMinecraft {
+    /// The minecraft version
+    version: String,
+    /// The loaders
+    #[serde(default)]
+    mod_loaders: Vec<CurseforgeLoader>,
+}
+
+/// A loader in a CurseForge manifest
+#[derive(Deserialize)]
+struct CurseforgeLoader {
+    /// The loader and version, e.g. `forge-47.1.0`
+    id: String,
+}
+
+/// A file in a CurseForge manifest
+#[derive(Deserialize)]
+struct CurseforgeFile {
+    /// The project id
+    #[serde(rename = "projectID")]
+    project_id: u64,
+    /// The file id
+    #[serde(rename = "fileID")]
+    file_id: u64,
+    /// Whether the file must be installed
+    #[serde(default = "required_default")]
+    required: bool,
+}
+
+/// CurseForge files are required unless stated otherwise
+fn required_default() -> bool {
+    true
+}
+
+/// Error that occurs while inspecting an archive
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum InspectError {
+    /// The archive couldn't be read
+    #[snafu(display("Failed to read archive: {}", source))]
+    Zip {
+        /// The underlying error
+        source: ZipError,
+    },
+    /// An entry couldn't be read
+    #[snafu(display("Failed to read {}: {}", name, source))]
+    Io {
+        /// The entry's name
+        name: String,
+        /// The underlying error
+        source: std::io::Error,
+    },
+    /// The index or manifest was malformed
+    #[snafu(display("Invalid {}: {}", name, source))]
+    Json {
+        /// The entry's name
+        name: String,
+        /// The underlying error
+        source: serde_json::Error,
+    },
+    /// The archive is for Bedrock Edition
+    #[snafu(display(
+        "This is a Bedrock Edition {}, but ffpack only supports Java Edition",
+        format
+    ))]
+    Bedrock {
+        /// What kind of Bedrock archive it is
+        format: BedrockFormat,
+    },
+    /// The archive has neither an `.mrpack` index nor a CurseForge manifest
+    #[snafu(display("Archive is neither an mrpack nor a CurseForge pack"))]
+    UnknownFormat,
+    /// A file would be installed outside of the instance
+    #[snafu(display("Unsafe install path in index: {}", path))]
+    UnsafePath {
+        /// The offending path
+        path: String,
+    },
+}
+
+impl InspectError {
+    /// Suggests how the user might fix the problem, if there is a likely fix
+    pub fn suggestion(&self) -> Option<String> {
+        match self {
+            InspectError::Bedrock { format } => Some(format!(
+                "Bedrock {format}s are installed by opening them with the game; ffpack works with \
+                 Java Edition modpacks"
+            )),
+            InspectError::UnknownFormat => {
+                Some("Only Modrinth .mrpack files and CurseForge pack zips can be read".into())
+            }
+            InspectError::Zip { .. }
+            | InspectError::Io { .. }
+            | InspectError::Json { .. }
+            | InspectError::UnsafePath { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use std::io::{Cursor, Write};
+
+    use super::*;
+    use crate::{
+        archive::{FileOptions, ZipWriter},
+        types::{ManagedFile, Minecraft},
+    };
+
+    /// Builds an archive from name and content pairs
+    fn archive(files: &[(&str, &str)]) -> Cursor<Vec<u8>> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in files {
+            writer
+                .start_file(name, FileOptions::for_path(name))
+                .unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        let mut cursor = writer.finish().unwrap();
+        cursor.set_position(0);
+        cursor
+    }
+
+    /// An mrpack index with one downloaded mod
+    const INDEX: &str = r#"{
+        "formatVersion": 1,
+        "game": "minecraft",
+        "name": "Pack",
+        "versionId": "1.0.0",
+        "files": [
+            {
+                "path": "mods/sodium.jar",
+                "hashes": {"sha1": "aa", "sha512": "bb"},
+                "env": {"client": "required", "server": "unsupported"},
+                "downloads": ["https://cdn.modrinth.com/sodium.jar"],
+                "fileSize": 10
+            }
+        ],
+        "dependencies": {"minecraft": "1.20.1", "fabric-loader": "0.14.21"}
+    }"#;
+
+    // mrpack contents are listed, including overrides
+    #[test]
+    fn mrpack() {
+        let summary = ArchiveSummary::read(archive(&[
+            (MRPACK_INDEX, INDEX),
+            ("overrides/config/a.toml", "a"),
+            ("server-overrides/server.properties", "b"),
+        ]))
+        .unwrap();
+        assert_eq!(summary.format, ArchiveFormat::Mrpack);
+        assert_eq!(summary.minecraft.as_deref(), Some("1.20.1"));
+        assert_eq!(summary.loaders["fabric"], "0.14.21");
+        let keys: Vec<_> = summary.files.keys().collect();
+        assert_eq!(
+            keys,
+            [
+                &FileKey::Path("config/a.toml".into()),
+                &FileKey::Path("mods/sodium.jar".into()),
+                &FileKey::Path("server.properties".into()),
+            ]
+        );
+        assert!(matches!(
+            summary.files[&FileKey::Path("mods/sodium.jar".into())],
+            FileOrigin::Download {
+                side: Side::Client,
+                ..
+            }
+        ));
+        let unsafe_index = INDEX.replace("mods/sodium.jar", "../sodium.jar");
+        assert!(matches!(
+            ArchiveSummary::read(archive(&[(MRPACK_INDEX, &unsafe_index)])),
+            Err(InspectError::UnsafePath { .. })
+        ));
+        assert!(matches!(
+            ArchiveSummary::read(archive(&[("readme.txt", "hi")])),
+            Err(InspectError::UnknownFormat)
+        ));
+        let bedrock = r#"{"format_version": 2, "header": {"uuid": "1234"}, "modules": []}"#;
+        assert!(matches!(
+            ArchiveSummary::read(archive(&[(CURSEFORGE_MANIFEST, bedrock)])),
+            Err(InspectError::Bedrock {
+                format: BedrockFormat::Pack
+            })
+        ));
+    }
+
+    // Two archives can be compared directly
+    #[test]
+    fn diff() {
+        let old = ArchiveSummary::read(archive(&[
+            (
+                CURSEFORGE_MANIFEST,
+                r#"{
+                    "minecraft": {"version": "1.20.1", "modLoaders": [{"id": "forge-47.1.0"}]},
+                    "files": [
+                        {"projectID": 1, "fileID": 10},
+                        {"projectID": 2, "fileID": 20}
+                    ]
+                }"#,
+            ),
+            ("overrides/config/a.toml", "a"),
+        ]))
+        .unwrap();
+        let new = ArchiveSummary::read(archive(&[
+            (
+                CURSEFORGE_MANIFEST,
+                r#"{
+                    "minecraft": {"version": "1.20.1", "modLoaders": [{"id": "forge-47.2.0"}]},
+                    "files": [
+                        {"projectID": 1, "fileID": 11},
+                        {"projectID": 3, "fileID": 30, "required": false}
+                    ],
+                    "overrides": "overrides"
+                }"#,
+            ),
+            ("overrides/config/a.toml", "a"),
+        ]))
+        .unwrap();
+        assert_eq!(
+            old.diff(&new),
+            [
+                Change::Loader {
+                    name: "forge".to_string(),
+                    old: Some("47.1.0".to_string()),
+                    new: Some("47.2.0".to_string()),
+                },
+                Change::Changed {
+                    key: FileKey::CurseforgeProject(1),
+                    new: FileOrigin::Curseforge {
+                        project_id: 1,
+                        file_id: 11,
+                        required: true
+                    },
+                },
+                Change::Removed {
+                    key: FileKey::CurseforgeProject(2)
+                },
+                Change::Added {
+                    key: FileKey::CurseforgeProject(3),
+                    origin: FileOrigin::Curseforge {
+                        project_id: 3,
+                        file_id: 30,
+                        required: false
+                    },
+                },
+            ]
+        );
+        assert!(new.diff(&new).is_empty());
+    }
+
+    // An archive can be compared against the current manifest
+    #[test]
+    fn diff_manifest() {
+        let summary = ArchiveSummary::read(archive(&[(MRPACK_INDEX, INDEX)])).unwrap();
+        let mut pack = Pack::default();
+        pack.versions.minecraft = Minecraft::new("1.20.1").unwrap();
+        pack.versions.loader = crate::types::Loader::new_fabric("0.14.21".parse().unwrap());
+        pack.managed_files = [ManagedFile {
+            path: "mods/sodium.jar".into(),
+            side: Side::Client,
+            source: Source::Url {
+                url: Url::parse("https://cdn.modrinth.com/sodium.jar").unwrap(),
+                blake3: [0; 32],
+                hashes: Hashes::default(),
+                mirrors: Vec::new(),
+            },
+            ..ManagedFile::default()
+        }]
+        .into_iter()
+        .collect();
+        assert!(summary.diff_manifest(&pack).is_empty());
+        pack.managed_files = [ManagedFile::default()].into_iter().collect();
+        let changes = summary.diff_manifest(&pack);
+        assert_eq!(changes.len(), 2);
+        assert!(matches!(changes[0], Change::Removed { .. }));
+        assert!(matches!(changes[1], Change::Added { .. }));
+    }
+}
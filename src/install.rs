@@ -0,0 +1,625 @@
+//! Preparing a pack's files for the filesystem of the instance they are installed into
+//!
+//! Manifests are written on one system and installed on others. A file name that is fine on
+//! Linux can be illegal on Windows, and the same name can arrive in different unicode forms
+//! depending on the editor that wrote it. Before downloading, [`prepare`] rewrites each path
+//! into a form the target accepts, following [`PathRules`], and returns the [`InstanceState`]
+//! mapping manifest paths to where files actually went, so that verifying the instance later
+//! still finds them. The state also lists every path it installed, so the next install can
+//! remove the files dropped from the manifest since, without touching files the player added.
+//! [`verify`] checks an installed instance against the downloads that installed it.
+//!
+//! Paths come from manifests and lockfiles written by others, so every path is checked to stay
+//! inside the instance, with [`is_contained`], before anything is written or deleted there.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::{self, Display},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use relative_path::{Component, RelativePath, RelativePathBuf};
+use serde::{Deserialize, Serialize};
+use snafu::{ensure, ResultExt, Snafu};
+use tracing::{debug, warn};
+use unicode_normalization::UnicodeNormalization;
+
+use crate::{download::DownloadJob, hash::hash_file};
+
+/// Where the instance state is kept, relative to the instance directory
+pub const STATE_PATH: &str = ".ffpack/instance.json";
+
+/// Names Windows reserves for devices, with or without an extension
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// The filesystem conventions an instance is installed under
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Platform {
+    /// Windows, which forbids a number of characters and names, and ignores case
+    Windows,
+    /// Linux, macOS and the other Unix-likes, which only forbid NUL
+    Unix,
+}
+
+impl Platform {
+    /// The platform ffpack is running on
+    pub fn current() -> Self {
+        if cfg!(windows) {
+            Platform::Windows
+        } else {
+            Platform::Unix
+        }
+    }
+
+    /// Returns true if the platform's filesystems usually ignore case
+    pub fn ignores_case(self) -> bool {
+        matches!(self, Platform::Windows)
+    }
+
+    /// Returns true if `c` may not appear in a file name
+    fn forbids(self, c: char) -> bool {
+        match self {
+            Platform::Windows => {
+                c < ' ' || matches!(c, '<' | '>' | ':' | '"' | '\\' | '|' | '?' | '*')
+            }
+            Platform::Unix => c == '\0',
+        }
+    }
+}
+
+/// How paths are rewritten before installing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PathRules {
+    /// The platform whose rules apply
+    platform: Platform,
+    /// What forbidden characters are replaced with
+    replacement: char,
+}
+
+impl Default for PathRules {
+    /// The rules of the current platform, replacing forbidden characters with `_`
+    fn default() -> Self {
+        Self {
+            platform: Platform::current(),
+            replacement: '_',
+        }
+    }
+}
+
+impl PathRules {
+    /// Sets the platform whose rules apply, for installing into an instance meant for another
+    /// system
+    #[must_use]
+    pub fn with_platform(mut self, platform: Platform) -> Self {
+        self.platform = platform;
+        self
+    }
+
+    /// Sets what forbidden characters are replaced with, which is ignored if the platform
+    /// forbids the replacement too
+    #[must_use]
+    pub fn with_replacement(mut self, replacement: char) -> Self {
+        if !self.platform.forbids(replacement) && replacement != '/' {
+            self.replacement = replacement;
+        }
+        self
+    }
+
+    /// Returns the platform whose rules apply
+    pub fn platform(self) -> Platform {
+        self.platform
+    }
+
+    /// Rewrites a path into a form the platform accepts, composing unicode into NFC and
+    /// replacing whatever can't appear in a name
+    pub fn normalize(self, path: &RelativePath) -> RelativePathBuf {
+        let components: Vec<_> = path
+            .components()
+            .map(|component| self.normalize_name(component.as_str()))
+            .collect();
+        RelativePathBuf::from(components.join("/"))
+    }
+
+    /// Rewrites a single file or directory name
+    fn normalize_name(self, name: &str) -> String {
+        let mut name: String = name
+            .nfc()
+            .map(|c| {
+                if self.platform.forbids(c) {
+                    self.replacement
+                } else {
+                    c
+                }
+            })
+            .collect();
+        if self.platform == Platform::Windows {
+            // Windows drops trailing dots and spaces, so names differing in them would collide
+            let trimmed = name.trim_end_matches(['.', ' ']).len();
+            if trimmed < name.len() && name != "." && name != ".." {
+                let removed = name[trimmed..].chars().count();
+                name.truncate(trimmed);
+                name.extend(std::iter::repeat_n(self.replacement, removed));
+            }
+            let stem = name.split('.').next().unwrap_or_default();
+            if RESERVED_NAMES
+                .iter()
+                .any(|reserved| stem.eq_ignore_ascii_case(reserved))
+            {
+                name.insert(stem.len(), self.replacement);
+            }
+        }
+        name
+    }
+}
+
+/// Returns true if `path` names a file inside the directory it is relative to
+///
+/// Only plain names are allowed, so `..` and `.` are rejected wherever they appear, as are paths
+/// with no name at all
+pub fn is_contained(path: &RelativePath) -> bool {
+    let mut components = path.components().peekable();
+    components.peek().is_some()
+        && components.all(|component| matches!(component, Component::Normal(_)))
+}
+
+/// What is remembered about an installed instance
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstanceState {
+    /// Where files went, by the path the manifest gives them, for those that had to be renamed
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    renamed: BTreeMap<RelativePathBuf, RelativePathBuf>,
+    /// The paths files were installed at
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    installed: BTreeSet<RelativePathBuf>,
+}
+
+impl InstanceState {
+    /// Reads the state of the instance at `instance`, which is empty if nothing was installed
+    /// there yet
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the state exists but can't be read
+    pub fn load(instance: &Path) -> Result<Self, InstallError> {
+        let path = instance.join(STATE_PATH);
+        match fs::read(&path) {
+            Ok(json) => serde_json::from_slice(&json).context(StateSnafu { path }),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(source) => Err(InstallError::Io { path, source }),
+        }
+    }
+
+    /// Writes the state into the instance at `instance`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the state can't be written
+    pub fn save(&self, instance: &Path) -> Result<(), InstallError> {
+        let path = instance.join(STATE_PATH);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context(IoSnafu { path: parent })?;
+        }
+        let json = serde_json::to_vec_pretty(self).context(StateSnafu { path: &path })?;
+        fs::write(&path, json).context(IoSnafu { path })
+    }
+
+    /// Returns where the file the manifest puts at `path` was installed
+    pub fn installed_path(&self, path: &RelativePath) -> RelativePathBuf {
+        self.renamed
+            .get(path)
+            .cloned()
+            .unwrap_or_else(|| path.to_relative_path_buf())
+    }
+
+    /// Returns the files that were renamed, as their manifest path and installed path
+    pub fn renamed(&self) -> impl Iterator<Item = (&RelativePath, &RelativePath)> {
+        self.renamed
+            .iter()
+            .map(|(from, to)| (from.as_relative_path(), to.as_relative_path()))
+    }
+
+    /// Returns the paths `previous` installed files at that this state doesn't, for files
+    /// dropped from the manifest or moved since
+    pub fn stale<'a>(
+        &'a self,
+        previous: &'a InstanceState,
+    ) -> impl Iterator<Item = &'a RelativePath> {
+        previous
+            .installed
+            .difference(&self.installed)
+            .map(RelativePathBuf::as_relative_path)
+    }
+
+    /// Deletes the [`stale`](Self::stale) files from the instance at `instance`, returning the
+    /// ones that were still there
+    ///
+    /// The previous state is read from the instance, where anything could have written it, so
+    /// paths in it leading outside the instance are skipped rather than deleted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a file can't be deleted
+    pub fn remove_stale(
+        &self,
+        previous: &InstanceState,
+        instance: &Path,
+    ) -> Result<Vec<RelativePathBuf>, InstallError> {
+        let mut removed = Vec::new();
+        for path in self.stale(previous) {
+            if !is_contained(path) {
+                warn!(%path, "Not removing a recorded file outside the instance");
+                continue;
+            }
+            let local = path.to_path(instance);
+            match fs::remove_file(&local) {
+                Ok(()) => {
+                    debug!(%path, "Removed stale file");
+                    removed.push(path.to_relative_path_buf());
+                }
+                Err(error) if error.kind() == std::io::ErrorKind::NotFound => {}
+                Err(source) => {
+                    return Err(InstallError::Io {
+                        path: local,
+                        source,
+                    })
+                }
+            }
+        }
+        Ok(removed)
+    }
+}
+
+/// Rewrites the paths of `jobs` following `rules`, returning the state recording what was
+/// renamed
+///
+/// # Errors
+///
+/// Returns an error if a path leads outside the instance, or if two files end up at the same
+/// path, counting paths that only differ in case on platforms that ignore it
+pub fn prepare(jobs: &mut [DownloadJob], rules: &PathRules) -> Result<InstanceState, InstallError> {
+    let mut state = InstanceState::default();
+    let mut taken: HashMap<String, RelativePathBuf> = HashMap::new();
+    for job in jobs {
+        let normalized = rules.normalize(&job.path);
+        ensure!(
+            is_contained(&normalized),
+            OutsideSnafu {
+                path: job.path.clone()
+            }
+        );
+        let key = if rules.platform.ignores_case() {
+            normalized.as_str().to_lowercase()
+        } else {
+            normalized.to_string()
+        };
+        if let Some(first) = taken.get(&key) {
+            return CollisionSnafu {
+                first: first.clone(),
+                second: job.path.clone(),
+                path: normalized,
+            }
+            .fail();
+        }
+        taken.insert(key, job.path.clone());
+        if normalized != job.path {
+            debug!(from = %job.path, to = %normalized, "Renaming for the target platform");
+            state.renamed.insert(job.path.clone(), normalized.clone());
+            job.path = normalized;
+        }
+        state.installed.insert(job.path.clone());
+    }
+    Ok(state)
+}
+
+/// A way an installed instance doesn't match the downloads that installed it
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Problem {
+    /// A file isn't in the instance
+    Missing {
+        /// Where the file belongs
+        path: RelativePathBuf,
+    },
+    /// A file isn't the artifact it was installed from
+    Modified {
+        /// Where the file is
+        path: RelativePathBuf,
+    },
+    /// A file in one of the folders the pack installs into isn't one of its files
+    Orphaned {
+        /// Where the file is
+        path: RelativePathBuf,
+    },
+}
+
+impl Problem {
+    /// Returns the path of the file with the problem
+    pub fn path(&self) -> &RelativePath {
+        match self {
+            Problem::Missing { path } | Problem::Modified { path } | Problem::Orphaned { path } => {
+                path
+            }
+        }
+    }
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::Missing { path } => write!(f, "{path} is missing"),
+            Problem::Modified { path } => write!(f, "{path} was modified"),
+            Problem::Orphaned { path } => write!(f, "{path} isn't part of the pack"),
+        }
+    }
+}
+
+/// Checks the instance at `instance` against the prepared `jobs` that installed it, returning
+/// every problem found, ordered by path
+///
+/// Files are hashed under every algorithm their artifact has a digest for, and compared on size
+/// if they have none. Files taken out of an archive can only be checked for being there, since
+/// their digests are the archive's. Orphans are only looked for in the folders the jobs install
+/// into, not below them, so the player's worlds and settings aren't reported.
+///
+/// # Errors
+///
+/// Returns an error if a file or folder exists but can't be read
+pub fn verify(instance: &Path, jobs: &[DownloadJob]) -> Result<Vec<Problem>, InstallError> {
+    let mut problems = Vec::new();
+    let mut folders = BTreeSet::new();
+    for job in jobs {
+        let path = job.path.clone();
+        let local = job.path.to_path(instance);
+        if let Some(parent) = job
+            .path
+            .parent()
+            .filter(|parent| !parent.as_str().is_empty())
+        {
+            folders.insert(parent.to_relative_path_buf());
+        }
+        let artifact = &job.artifact;
+        let metadata = match fs::metadata(&local) {
+            Ok(metadata) if metadata.is_file() => metadata,
+            Ok(_) => {
+                problems.push(Problem::Missing { path });
+                continue;
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                problems.push(Problem::Missing { path });
+                continue;
+            }
+            Err(source) => {
+                return Err(InstallError::Io {
+                    path: local,
+                    source,
+                })
+            }
+        };
+        if artifact.extract.is_some() {
+            continue;
+        }
+        let modified = if artifact.hashes.is_empty() {
+            artifact.size.is_some_and(|size| size != metadata.len())
+        } else {
+            let actual = hash_file(&local, artifact.hashes.algorithms())
+                .context(IoSnafu { path: &local })?;
+            artifact.hashes.mismatch(&actual).is_some()
+        };
+        if modified {
+            problems.push(Problem::Modified { path });
+        }
+    }
+
+    let expected: BTreeSet<_> = jobs.iter().map(|job| job.path.as_relative_path()).collect();
+    for folder in folders {
+        let local = folder.to_path(instance);
+        let entries = match fs::read_dir(&local) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => continue,
+            Err(source) => {
+                return Err(InstallError::Io {
+                    path: local,
+                    source,
+                })
+            }
+        };
+        for entry in entries {
+            let entry = entry.context(IoSnafu { path: &local })?;
+            let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+            let path = folder.join(name);
+            if entry.path().is_file() && !expected.contains(path.as_relative_path()) {
+                problems.push(Problem::Orphaned { path });
+            }
+        }
+    }
+    problems.sort_by(|a, b| a.path().cmp(b.path()));
+    Ok(problems)
+}
+
+/// Error that occurs while preparing or recording an install
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum InstallError {
+    /// Two files would be installed at the same path
+    #[snafu(display("{} and {} would both be installed at {}", first, second, path))]
+    Collision {
+        /// The first file's path in the manifest
+        first: RelativePathBuf,
+        /// The second file's path in the manifest
+        second: RelativePathBuf,
+        /// The path both end up at
+        path: RelativePathBuf,
+    },
+    /// A file would be installed outside the instance
+    #[snafu(display("{} leads outside the instance", path))]
+    Outside {
+        /// The file's path
+        path: RelativePathBuf,
+    },
+    /// The instance state couldn't be read or written
+    #[snafu(display("Failed to access {}: {}", path.display(), source))]
+    Io {
+        /// The file being accessed
+        path: PathBuf,
+        /// The underlying error
+        source: std::io::Error,
+    },
+    /// The instance state isn't valid
+    #[snafu(display("Invalid instance state in {}: {}", path.display(), source))]
+    State {
+        /// The state file
+        path: PathBuf,
+        /// The underlying error
+        source: serde_json::Error,
+    },
+}
+
+impl InstallError {
+    /// Suggests how the user might fix the problem, if there is a likely fix
+    pub fn suggestion(&self) -> Option<String> {
+        match self {
+            InstallError::Collision { second, .. } => Some(format!(
+                "Rename {second} in the manifest so that it stays distinct on every platform"
+            )),
+            InstallError::Outside { path } => Some(format!(
+                "Give {path} a path inside the instance, without `..`, in the manifest"
+            )),
+            InstallError::Io { .. } => None,
+            InstallError::State { path, .. } => Some(format!(
+                "Delete {} and install again to recreate it",
+                path.display()
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use url::Url;
+
+    use super::*;
+    use crate::resolve::ResolvedArtifact;
+
+    // Names are made legal for the target platform, and renames are remembered
+    #[test]
+    fn prepare() {
+        let windows = PathRules::default().with_platform(Platform::Windows);
+        let normalize =
+            |rules: &PathRules, path: &str| rules.normalize(RelativePath::new(path)).to_string();
+        assert_eq!(normalize(&windows, "config/a:b?.toml"), "config/a_b_.toml");
+        assert_eq!(normalize(&windows, "mods/con.jar"), "mods/con_.jar");
+        assert_eq!(normalize(&windows, "dots.../x"), "dots___/x");
+        assert_eq!(normalize(&windows, "mods/console.jar"), "mods/console.jar");
+        // Decomposed characters are composed
+        assert_eq!(normalize(&windows, "e\u{301}.txt"), "\u{e9}.txt");
+        let unix = PathRules::default().with_platform(Platform::Unix);
+        assert_eq!(normalize(&unix, "config/a:b?.toml"), "config/a:b?.toml");
+        let dashes = windows.with_replacement('-').with_replacement('*');
+        assert_eq!(normalize(&dashes, "a|b"), "a-b");
+
+        let job = |path: &str| DownloadJob {
+            artifact: ResolvedArtifact {
+                download_url: Url::parse("https://example.org/file").unwrap(),
+                filename: String::new(),
+                size: None,
+                hashes: crate::types::Hashes::default(),
+                mirrors: Vec::new(),
+                extract: None,
+                ids: crate::resolve::UpstreamIds::default(),
+                details: crate::resolve::ProjectDetails::default(),
+            },
+            path: RelativePathBuf::from(path),
+        };
+        let mut jobs = [job("mods/a.jar"), job("config/what?.json")];
+        let state = super::prepare(&mut jobs, &windows).unwrap();
+        assert_eq!(jobs[1].path, "config/what_.json");
+        assert_eq!(
+            state.installed_path(RelativePath::new("config/what?.json")),
+            "config/what_.json"
+        );
+        assert_eq!(
+            state.installed_path(RelativePath::new("mods/a.jar")),
+            "mods/a.jar"
+        );
+
+        let dir = std::env::temp_dir().join(format!("ffpack-install-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(InstanceState::load(&dir).unwrap(), InstanceState::default());
+        state.save(&dir).unwrap();
+        assert_eq!(InstanceState::load(&dir).unwrap(), state);
+
+        // Files the next install doesn't install are removed, if they are still there
+        fs::create_dir_all(dir.join("mods")).unwrap();
+        fs::write(dir.join("mods/a.jar"), b"a").unwrap();
+        fs::write(dir.join("mods/added.jar"), b"mine").unwrap();
+        let mut jobs = [job("config/what?.json")];
+        let next = super::prepare(&mut jobs, &windows).unwrap();
+        let removed = next.remove_stale(&state, &dir).unwrap();
+        assert_eq!(removed, [RelativePathBuf::from("mods/a.jar")]);
+        assert!(!dir.join("mods/a.jar").exists() && dir.join("mods/added.jar").exists());
+
+        // Recorded paths leading outside the instance are never deleted
+        let outside = dir.with_extension("outside");
+        fs::write(&outside, b"keep").unwrap();
+        let mut hostile = InstanceState::default();
+        let escape = format!("../{}", outside.file_name().unwrap().to_str().unwrap());
+        hostile.installed.insert(RelativePathBuf::from(escape));
+        assert!(next.remove_stale(&hostile, &dir).unwrap().is_empty());
+        assert!(outside.exists());
+        fs::remove_file(outside).unwrap();
+
+        // Missing, modified, and unexpected files are all found
+        let mut sized = job("mods/sized.jar");
+        sized.artifact.size = Some(3);
+        fs::write(dir.join("mods/sized.jar"), b"four").unwrap();
+        let problems = verify(&dir, &[job("mods/a.jar"), sized]).unwrap();
+        assert_eq!(
+            problems,
+            [
+                Problem::Missing {
+                    path: RelativePathBuf::from("mods/a.jar")
+                },
+                Problem::Orphaned {
+                    path: RelativePathBuf::from("mods/added.jar")
+                },
+                Problem::Modified {
+                    path: RelativePathBuf::from("mods/sized.jar")
+                },
+            ]
+        );
+        fs::remove_dir_all(dir).unwrap();
+
+        // Names that only differ by case, or by what was replaced, collide
+        let mut jobs = [job("mods/A.jar"), job("mods/a.jar")];
+        assert!(matches!(
+            super::prepare(&mut jobs, &windows),
+            Err(InstallError::Collision { .. })
+        ));
+        assert!(super::prepare(&mut jobs, &unix).is_ok());
+        let mut jobs = [job("a?"), job("a*")];
+        assert!(super::prepare(&mut jobs, &windows).is_err());
+
+        // Paths leading out of the instance are refused, wherever the `..` is
+        for path in [
+            "mods/../../../etc/evil.jar",
+            "../evil.jar",
+            "mods/./a.jar",
+            "",
+        ] {
+            let mut jobs = [job(path)];
+            assert!(
+                matches!(
+                    super::prepare(&mut jobs, &unix),
+                    Err(InstallError::Outside { .. })
+                ),
+                "{path} was accepted"
+            );
+        }
+    }
+}
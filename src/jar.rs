@@ -0,0 +1,561 @@
+//! Reading the loader metadata mods carry in their jars
+//!
+//! Every loader asks mods to describe themselves in a file inside the jar: `quilt.mod.json`,
+//! `fabric.mod.json`, or Forge's `META-INF/mods.toml` and NeoForge's
+//! `META-INF/neoforge.mods.toml`. They all declare an id, a display name, a description, and the
+//! mods they depend on, and the JSON formats also declare which sides the mod runs on, which
+//! fills in what a jar found on disk otherwise leaves blank.
+
+use std::{
+    collections::BTreeMap,
+    io::{self, Read, Seek},
+};
+
+use serde::{de::DeserializeOwned, Deserialize};
+use snafu::{OptionExt, ResultExt, Snafu};
+use tracing::{debug, instrument};
+
+use crate::{
+    archive::{ZipArchive, ZipError},
+    toml::{self, Table, TomlError, Value},
+    types::{ManagedFile, Side},
+};
+
+/// Ids mods depend on that are the game, the runtime, or a loader rather than other mods
+const PLATFORM_IDS: [&str; 7] = [
+    "minecraft",
+    "java",
+    "fabricloader",
+    "quilt_loader",
+    "forge",
+    "neoforge",
+    "javafml",
+];
+
+/// The metadata files a jar can carry, in the order they are looked for
+///
+/// Quilt loads Fabric mods too, so jars made for both carry both files, and the Quilt one is the
+/// more detailed.
+const FILES: [(&str, MetadataFormat); 4] = [
+    ("quilt.mod.json", MetadataFormat::Quilt),
+    ("fabric.mod.json", MetadataFormat::Fabric),
+    ("META-INF/neoforge.mods.toml", MetadataFormat::NeoForge),
+    ("META-INF/mods.toml", MetadataFormat::Forge),
+];
+
+/// Which loader's metadata file a jar carries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MetadataFormat {
+    /// `quilt.mod.json`
+    Quilt,
+    /// `fabric.mod.json`
+    Fabric,
+    /// `META-INF/mods.toml`
+    Forge,
+    /// `META-INF/neoforge.mods.toml`
+    NeoForge,
+}
+
+/// What a mod declares about itself in its jar
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ModMetadata {
+    /// The file the metadata was read from
+    pub format: MetadataFormat,
+    /// The mod's id, which other mods depend on it by
+    pub id: String,
+    /// The mod's display name
+    pub name: Option<String>,
+    /// A short description of the mod
+    pub description: Option<String>,
+    /// The mod's version, as the mod writes it
+    pub version: Option<String>,
+    /// The other mods it depends on, leaving out the game and loaders
+    pub dependencies: Vec<Dependency>,
+    /// The sides the mod runs on, which only the JSON formats declare
+    pub side: Side,
+}
+
+/// A mod that another depends on
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Dependency {
+    /// The id of the mod depended on
+    pub id: String,
+    /// The versions that work, in the format's own syntax, or nothing if any does
+    pub versions: Option<String>,
+    /// Whether the mod can't run without it, rather than only working better with it
+    pub required: bool,
+}
+
+impl ModMetadata {
+    /// Fills in the blanks of `file` from the metadata: its name, description, and side
+    ///
+    /// What is already set is kept, so metadata from a platform takes precedence.
+    pub fn fill(&self, file: &mut ManagedFile) {
+        if file.name.is_none() {
+            file.name.clone_from(&self.name);
+        }
+        if file.description.is_none() {
+            file.description.clone_from(&self.description);
+        }
+        if file.side == Side::Both {
+            file.side = self.side.clone();
+        }
+    }
+}
+
+/// Error that occurs while reading a jar's metadata
+#[derive(Debug, Snafu)]
+#[snafu(visibility(pub(crate)))]
+#[non_exhaustive]
+pub enum JarError {
+    /// The jar isn't a readable zip archive
+    #[snafu(display("Failed to read jar: {}", source))]
+    Zip {
+        /// The underlying error
+        source: ZipError,
+    },
+    /// The metadata file couldn't be read out of the jar
+    #[snafu(display("Failed to read {}: {}", file, source))]
+    Io {
+        /// The metadata file
+        file: &'static str,
+        /// The underlying error
+        source: io::Error,
+    },
+    /// A JSON metadata file isn't valid
+    #[snafu(display("Failed to parse {}: {}", file, source))]
+    Json {
+        /// The metadata file
+        file: &'static str,
+        /// The underlying error
+        source: serde_json::Error,
+    },
+    /// A TOML metadata file isn't valid
+    #[snafu(display("Failed to parse {}: {}", file, source))]
+    Toml {
+        /// The metadata file
+        file: &'static str,
+        /// The underlying error
+        source: TomlError,
+    },
+    /// A metadata file lacks a field every mod has to declare
+    #[snafu(display("{} has no `{}`", file, field))]
+    MissingField {
+        /// The metadata file
+        file: &'static str,
+        /// The name of the field
+        field: &'static str,
+    },
+}
+
+impl JarError {
+    /// Suggests how the user might fix the problem, if there is a likely fix
+    pub fn suggestion(&self) -> Option<String> {
+        match self {
+            JarError::Json { .. } | JarError::Toml { .. } | JarError::MissingField { .. } => {
+                Some("The jar's metadata is broken; report it to the mod's authors".into())
+            }
+            JarError::Zip { .. } | JarError::Io { .. } => None,
+        }
+    }
+}
+
+/// Versions in a JSON metadata file, given as one or as a list of alternatives
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum VersionList {
+    /// A single range
+    One(String),
+    /// Ranges any of which works
+    Any(Vec<String>),
+    /// A form this module doesn't read, like Quilt's `{"all": [...]}`
+    Other(serde_json::Value),
+}
+
+impl VersionList {
+    /// Returns the ranges as one string, or nothing if any version works
+    fn range(self) -> Option<String> {
+        let range = match self {
+            VersionList::One(range) => range,
+            VersionList::Any(ranges) => ranges.join(" || "),
+            VersionList::Other(versions) => {
+                debug!(%versions, "Treating versions in an unsupported form as any");
+                return None;
+            }
+        };
+        (!range.is_empty() && range != "*").then_some(range)
+    }
+}
+
+/// The parts of `fabric.mod.json` that are read
+#[derive(Deserialize)]
+struct FabricMod {
+    /// The mod id
+    id: String,
+    /// The mod version
+    #[serde(default)]
+    version: Option<String>,
+    /// The display name
+    #[serde(default)]
+    name: Option<String>,
+    /// The description
+    #[serde(default)]
+    description: Option<String>,
+    /// `client`, `server`, or `*`
+    #[serde(default)]
+    environment: Option<String>,
+    /// Required dependencies, with their versions
+    #[serde(default)]
+    depends: BTreeMap<String, VersionList>,
+    /// Dependencies the mod works better with
+    #[serde(default)]
+    recommends: BTreeMap<String, VersionList>,
+}
+
+/// The parts of `quilt.mod.json` that are read
+#[derive(Deserialize)]
+struct QuiltMod {
+    /// What the loader reads
+    quilt_loader: QuiltLoader,
+    /// What the game side of the loader reads
+    #[serde(default)]
+    minecraft: Option<QuiltMinecraft>,
+}
+
+/// The `quilt_loader` section of `quilt.mod.json`
+#[derive(Deserialize)]
+struct QuiltLoader {
+    /// The mod id
+    id: String,
+    /// The mod version
+    #[serde(default)]
+    version: Option<String>,
+    /// The mod's display metadata
+    #[serde(default)]
+    metadata: QuiltDisplay,
+    /// The dependencies
+    #[serde(default)]
+    depends: Vec<QuiltDependency>,
+}
+
+/// The `quilt_loader.metadata` section of `quilt.mod.json`
+#[derive(Deserialize, Default)]
+struct QuiltDisplay {
+    /// The display name
+    #[serde(default)]
+    name: Option<String>,
+    /// The description
+    #[serde(default)]
+    description: Option<String>,
+}
+
+/// A dependency in `quilt.mod.json`, given by id alone or with details
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum QuiltDependency {
+    /// Any version of the mod with this id
+    Id(String),
+    /// A dependency with details
+    Detailed {
+        /// The mod id
+        id: String,
+        /// The versions that work
+        #[serde(default)]
+        versions: Option<VersionList>,
+        /// Whether the mod runs without it
+        #[serde(default)]
+        optional: bool,
+    },
+}
+
+/// The `minecraft` section of `quilt.mod.json`
+#[derive(Deserialize)]
+struct QuiltMinecraft {
+    /// `client`, `dedicated_server`, or `*`
+    #[serde(default)]
+    environment: Option<String>,
+}
+
+/// Returns the side an environment is for, where `server` is what the server side is called
+fn environment_side(environment: Option<&str>, server: &str) -> Side {
+    match environment {
+        Some("client") => Side::Client,
+        Some(environment) if environment == server => Side::Server,
+        _ => Side::Both,
+    }
+}
+
+/// Returns true if a dependency is on another mod, not on the game or a loader
+fn is_mod(id: &str) -> bool {
+    !PLATFORM_IDS.contains(&id)
+}
+
+/// Parses a JSON metadata file
+fn json<T: DeserializeOwned>(file: &'static str, contents: &[u8]) -> Result<T, JarError> {
+    serde_json::from_slice(contents).context(JsonSnafu { file })
+}
+
+/// Reads `fabric.mod.json`
+fn fabric(contents: &[u8]) -> Result<ModMetadata, JarError> {
+    let metadata: FabricMod = json("fabric.mod.json", contents)?;
+    let required = metadata
+        .depends
+        .into_iter()
+        .map(|dependency| (dependency, true));
+    let optional = metadata
+        .recommends
+        .into_iter()
+        .map(|dependency| (dependency, false));
+    let dependencies = required
+        .chain(optional)
+        .filter(|((id, _), _)| is_mod(id))
+        .map(|((id, versions), required)| Dependency {
+            id,
+            versions: versions.range(),
+            required,
+        })
+        .collect();
+    Ok(ModMetadata {
+        format: MetadataFormat::Fabric,
+        id: metadata.id,
+        name: metadata.name,
+        description: metadata.description,
+        version: metadata.version,
+        dependencies,
+        side: environment_side(metadata.environment.as_deref(), "server"),
+    })
+}
+
+/// Reads `quilt.mod.json`
+fn quilt(contents: &[u8]) -> Result<ModMetadata, JarError> {
+    let metadata: QuiltMod = json("quilt.mod.json", contents)?;
+    let loader = metadata.quilt_loader;
+    let dependencies = loader
+        .depends
+        .into_iter()
+        .map(|dependency| match dependency {
+            QuiltDependency::Id(id) => Dependency {
+                id,
+                versions: None,
+                required: true,
+            },
+            QuiltDependency::Detailed {
+                id,
+                versions,
+                optional,
+            } => Dependency {
+                id,
+                versions: versions.and_then(VersionList::range),
+                required: !optional,
+            },
+        })
+        .filter(|dependency| is_mod(&dependency.id))
+        .collect();
+    let environment = metadata
+        .minecraft
+        .and_then(|minecraft| minecraft.environment);
+    Ok(ModMetadata {
+        format: MetadataFormat::Quilt,
+        id: loader.id,
+        name: loader.metadata.name,
+        description: loader.metadata.description,
+        version: loader.version,
+        dependencies,
+        side: environment_side(environment.as_deref(), "dedicated_server"),
+    })
+}
+
+/// Reads a Forge or NeoForge `mods.toml`, taking the first mod it declares
+///
+/// Versions like `${file.jarVersion}` are filled in from the jar's manifest when the mod is
+/// built, so they are left out.
+fn forge(
+    file: &'static str,
+    format: MetadataFormat,
+    contents: &[u8],
+) -> Result<ModMetadata, JarError> {
+    let contents = String::from_utf8_lossy(contents);
+    let table = toml::parse(&contents).context(TomlSnafu { file })?;
+    let first = table
+        .get("mods")
+        .and_then(Value::as_array)
+        .and_then(|mods| mods.first())
+        .and_then(Value::as_table)
+        .context(MissingFieldSnafu {
+            file,
+            field: "mods",
+        })?;
+    let string = |table: &Table, key: &str| {
+        table
+            .get(key)
+            .and_then(Value::as_str)
+            .map(|value| value.trim().to_string())
+    };
+    let id = string(first, "modId").context(MissingFieldSnafu {
+        file,
+        field: "mods.modId",
+    })?;
+    let dependencies = toml::get(&table, &format!("dependencies.{id}"))
+        .and_then(Value::as_array)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(Value::as_table)
+        .filter_map(|dependency| {
+            let id = string(dependency, "modId").filter(|id| is_mod(id))?;
+            // Forge says whether a dependency is mandatory, NeoForge gives its type
+            let required = match dependency.get("type").and_then(Value::as_str) {
+                Some(kind) => kind.eq_ignore_ascii_case("required"),
+                None => dependency
+                    .get("mandatory")
+                    .and_then(Value::as_bool)
+                    .unwrap_or(true),
+            };
+            Some(Dependency {
+                id,
+                versions: string(dependency, "versionRange").filter(|range| !range.is_empty()),
+                required,
+            })
+        })
+        .collect();
+    Ok(ModMetadata {
+        format,
+        name: string(first, "displayName"),
+        description: string(first, "description"),
+        version: string(first, "version").filter(|version| !version.starts_with("${")),
+        id,
+        dependencies,
+        side: Side::Both,
+    })
+}
+
+/// Reads the loader metadata of the jar `reader` holds, or nothing if it carries none
+///
+/// # Errors
+///
+/// Returns an error if the jar isn't a zip archive, or its metadata file can't be read or
+/// parsed
+#[instrument(skip_all, err)]
+pub fn read(reader: impl Read + Seek) -> Result<Option<ModMetadata>, JarError> {
+    let mut archive = ZipArchive::new(reader).context(ZipSnafu)?;
+    let Some((index, file, format)) = FILES.into_iter().find_map(|(file, format)| {
+        let index = archive
+            .entries()
+            .iter()
+            .position(|entry| entry.name() == file)?;
+        Some((index, file, format))
+    }) else {
+        return Ok(None);
+    };
+    let mut contents = Vec::new();
+    archive
+        .open(index)
+        .context(ZipSnafu)?
+        .read_to_end(&mut contents)
+        .context(IoSnafu { file })?;
+    debug!(file, "Reading mod metadata");
+    let metadata = match format {
+        MetadataFormat::Quilt => quilt(&contents)?,
+        MetadataFormat::Fabric => fabric(&contents)?,
+        MetadataFormat::Forge | MetadataFormat::NeoForge => forge(file, format, &contents)?,
+    };
+    Ok(Some(metadata))
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::archive::DeterministicZipWriter;
+
+    /// Builds a jar holding one metadata file
+    fn jar(name: &str, contents: &str) -> Cursor<Vec<u8>> {
+        let mut zip = DeterministicZipWriter::new(Cursor::new(Vec::new()));
+        zip.add_bytes(name, contents);
+        let mut jar = zip.finish().unwrap();
+        jar.set_position(0);
+        jar
+    }
+
+    // Each format's id, name, dependencies, and environment are read, leaving out platforms
+    #[test]
+    fn read() {
+        let fabric = r#"{"schemaVersion": 1, "id": "sodium", "version": "0.5.3",
+            "name": "Sodium", "description": "Fast", "environment": "client",
+            "depends": {"minecraft": "1.20.1", "fabric-api": ["*"], "indium": [">=1.0", "<0.9"]},
+            "recommends": {"modmenu": "*"}}"#;
+        let metadata = super::read(jar("fabric.mod.json", fabric))
+            .unwrap()
+            .unwrap();
+        assert_eq!(metadata.format, MetadataFormat::Fabric);
+        assert_eq!(metadata.id, "sodium");
+        assert_eq!(metadata.side, Side::Client);
+        let dependencies: Vec<_> = metadata
+            .dependencies
+            .iter()
+            .map(|dependency| {
+                (
+                    dependency.id.as_str(),
+                    dependency.versions.as_deref(),
+                    dependency.required,
+                )
+            })
+            .collect();
+        assert_eq!(
+            dependencies,
+            [
+                ("fabric-api", None, true),
+                ("indium", Some(">=1.0 || <0.9"), true),
+                ("modmenu", None, false),
+            ]
+        );
+
+        let quilt = r#"{"schema_version": 1, "quilt_loader": {"id": "qsl", "version": "6.0.0",
+            "metadata": {"name": "QSL"}, "depends": ["quilt_loader",
+            {"id": "minecraft_stuff", "versions": {"any": []}, "optional": true}]},
+            "minecraft": {"environment": "dedicated_server"}}"#;
+        let metadata = super::read(jar("quilt.mod.json", quilt)).unwrap().unwrap();
+        assert_eq!(metadata.name.as_deref(), Some("QSL"));
+        assert_eq!(metadata.side, Side::Server);
+        assert_eq!(metadata.dependencies.len(), 1);
+        assert!(!metadata.dependencies[0].required);
+
+        let forge = r#"
+            modLoader = "javafml"
+            [[mods]]
+            modId = "jei"
+            version = "${file.jarVersion}"
+            displayName = "Just Enough Items"
+            description = '''
+            Shows items and recipes
+            '''
+            [[dependencies.jei]]
+            modId = "forge"
+            mandatory = true
+            [[dependencies.jei]]
+            modId = "jade"
+            mandatory = false
+            versionRange = "[11,)"
+        "#;
+        let metadata = super::read(jar("META-INF/mods.toml", forge))
+            .unwrap()
+            .unwrap();
+        assert_eq!(metadata.format, MetadataFormat::Forge);
+        assert_eq!(metadata.id, "jei");
+        assert_eq!(metadata.version, None);
+        assert_eq!(
+            metadata.description.as_deref(),
+            Some("Shows items and recipes")
+        );
+        assert_eq!(
+            metadata.dependencies,
+            [Dependency {
+                id: "jade".into(),
+                versions: Some("[11,)".into()),
+                required: false,
+            }]
+        );
+
+        assert_eq!(super::read(jar("assets/icon.png", "")).unwrap(), None);
+    }
+}
@@ -11,20 +11,51 @@
     clippy::module_name_repetitions,
     clippy::shadow_unrelated,
     clippy::must_use_candidate,
-    clippy::implicit_hasher
+    clippy::implicit_hasher,
+    clippy::doc_markdown
 )]
 
-use std::collections::BTreeSet;
+use std::{collections::BTreeSet, path::Path};
 
+use relative_path::RelativePath;
+use semver::{Version, VersionReq};
 use serde::{Deserialize, Serialize};
+use snafu::{ensure, ResultExt, Snafu};
+use tracing::{instrument, warn};
 
+pub mod archive;
+pub mod collection;
+pub mod download;
+pub mod edition;
+pub mod export;
+pub mod git;
+pub mod hash;
+pub mod http;
+pub mod import;
+pub mod inspect;
+pub mod install;
+pub mod jar;
+pub mod lock;
+pub mod outdated;
+pub mod rehash;
+pub mod resolve;
+pub mod toml;
 pub mod types;
+pub mod warnings;
+pub mod workspace;
 
-use types::{ManagedFile, Metadata, Versions};
+use lock::{history::History, LockError, Lockfile};
+use types::{FileKind, ManagedFile, Metadata, Versions};
+use warnings::LoadWarning;
 
 /// High level representation of a modpack
 ///
 /// TODO: Document
+///
+/// Like the rest of the library's types, packs are `Send + Sync`, as are lockfiles and the
+/// instance state installs plan with. The resolver, downloader, and rehasher are too whenever
+/// their client is, with futures that are `Send`, and so is locking. Multi-threaded
+/// hosts can share any of them between tasks behind an [`Arc`](std::sync::Arc).
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Hash)]
 pub struct Pack {
     /// The metadata for this pack
@@ -44,3 +75,261 @@ impl Default for Pack {
         }
     }
 }
+
+impl Pack {
+    /// Parses a pack manifest
+    ///
+    /// If the manifest declares [`Metadata::requires`] and this version of ffpack doesn't
+    /// satisfy it, that is reported instead of whatever parsing error newer fields would cause.
+    /// Pre-release builds count as the version they lead up to.
+    ///
+    /// Anything worth updating in the manifest is logged as a warning; use
+    /// [`Pack::from_json_with_warnings`] to handle those some other way.
+    #[instrument(skip(json), err)]
+    pub fn from_json(json: &str) -> Result<Self, PackError> {
+        let (pack, warnings) = Self::from_json_with_warnings(json)?;
+        for warning in warnings {
+            warn!(%warning, "Manifest could use updating");
+        }
+        Ok(pack)
+    }
+
+    /// Parses a pack manifest like [`Pack::from_json`], also returning deprecated forms and
+    /// leftover example content found in it
+    #[instrument(skip(json), err)]
+    pub fn from_json_with_warnings(json: &str) -> Result<(Self, Vec<LoadWarning>), PackError> {
+        /// Just enough of the manifest to find the version requirement
+        #[derive(Deserialize)]
+        struct Probe {
+            /// The metadata section
+            metadata: Option<ProbeMetadata>,
+        }
+        /// Just enough of the metadata to find the version requirement
+        #[derive(Deserialize)]
+        struct ProbeMetadata {
+            /// The raw requirement
+            requires: Option<String>,
+        }
+        let requires = serde_json::from_str::<Probe>(json)
+            .ok()
+            .and_then(|probe| probe.metadata?.requires);
+        if let Some(requires) = requires {
+            let requires = VersionReq::parse(&requires).context(InvalidRequirementSnafu {
+                requires: requires.clone(),
+            })?;
+            let mut current =
+                Version::parse(env!("CARGO_PKG_VERSION")).expect("Crate version is valid semver");
+            current.pre = semver::Prerelease::EMPTY;
+            ensure!(
+                requires.matches(&current),
+                UnsupportedSnafu { requires, current }
+            );
+        }
+        let pack: Self = serde_json::from_str(json).context(ParseSnafu)?;
+        let raw: serde_json::Value = serde_json::from_str(json).context(ParseSnafu)?;
+        let warnings = warnings::check(&raw, &pack);
+        Ok((pack, warnings))
+    }
+
+    /// Returns the lockfile `version` of the pack was published with, reading it from the history
+    /// kept next to the manifest at `manifest`
+    ///
+    /// The pack's own version falls back to the lockfile next to the manifest until it is
+    /// published. Versions that were never recorded, or were pruned, have no lockfile.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the lockfile exists but can't be read
+    pub fn lockfile_at(
+        &self,
+        manifest: &Path,
+        version: &Version,
+    ) -> Result<Option<Lockfile>, LockError> {
+        let recorded = History::for_manifest(manifest).load(version)?;
+        if recorded.is_none() && version == self.metadata.version() {
+            return Lockfile::load(&Lockfile::path_for(manifest));
+        }
+        Ok(recorded)
+    }
+
+    /// Returns the pack's jarmods, in the order they are applied
+    pub fn jarmods(&self) -> impl Iterator<Item = &ManagedFile> {
+        self.managed_files
+            .iter()
+            .filter(|file| file.kind == FileKind::Jarmod)
+    }
+
+    /// Enables or disables the managed file at `path`, returning false if there is none
+    ///
+    /// Disabled files stay in the pack, but are installed under
+    /// [`ManagedFile::install_path`] so loaders skip them.
+    pub fn set_enabled(&mut self, path: &RelativePath, enabled: bool) -> bool {
+        let Some(mut file) = self
+            .managed_files
+            .iter()
+            .find(|file| file.path == path)
+            .cloned()
+        else {
+            return false;
+        };
+        file.enabled = enabled;
+        self.managed_files.replace(file);
+        true
+    }
+}
+
+/// Error that occurs while loading a pack manifest
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum PackError {
+    /// The manifest needs a different version of ffpack
+    #[snafu(display(
+        "Manifest requires ffpack {}, but this is ffpack {}",
+        requires,
+        current
+    ))]
+    Unsupported {
+        /// The versions the manifest requires
+        requires: VersionReq,
+        /// This version of ffpack
+        current: Version,
+    },
+    /// The manifest's version requirement couldn't be parsed
+    #[snafu(display("Invalid ffpack version requirement in manifest: {}", requires))]
+    InvalidRequirement {
+        /// The raw requirement
+        requires: String,
+        /// The underlying error
+        source: semver::Error,
+    },
+    /// The manifest was malformed
+    #[snafu(display("Invalid manifest: {}", source))]
+    Parse {
+        /// The underlying error
+        source: serde_json::Error,
+    },
+}
+
+impl PackError {
+    /// Suggests how the user might fix the problem, if there is a likely fix
+    pub fn suggestion(&self) -> Option<String> {
+        match self {
+            PackError::Unsupported { requires, .. } => {
+                Some(format!("Install a version of ffpack matching {requires}"))
+            }
+            PackError::InvalidRequirement { .. } => {
+                Some("Requirements are semver ranges, like >=0.1.0".into())
+            }
+            PackError::Parse { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::{
+        download::{DownloadJob, DownloadOptions, Downloader},
+        http::{testing::MockClient, CachingClient, RateLimitedClient, RetryingClient},
+        install::{InstanceState, PathRules},
+        lock::{LockError, Lockfile},
+        rehash::Rehasher,
+        resolve::Resolver,
+        types::HashAlgorithm,
+    };
+
+    /// Asserts at compile time that a type can be shared between threads
+    fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+    /// Asserts at compile time that a future can move between threads
+    fn assert_send<T: Send>(_: T) {}
+
+    // Core types can be shared between threads, and their futures run on any of them
+    #[test]
+    fn send_sync() {
+        let mut pack = Pack::default();
+        assert_send_sync(&pack);
+        assert_send_sync(&PackError::Parse {
+            source: serde_json::from_str::<()>("").unwrap_err(),
+        });
+        let client = RetryingClient::new(
+            RateLimitedClient::new(
+                CachingClient::new(MockClient::default(), "cache"),
+                http::RateLimits::none(),
+            ),
+            http::RetryPolicy::never(),
+        );
+        assert_send_sync(&client);
+
+        let resolver = Resolver::new(&client);
+        assert_send_sync(&resolver);
+        let file = pack.managed_files.iter().next().unwrap().clone();
+        assert_send(resolver.resolve(&file, &pack.versions));
+
+        let downloader = Downloader::new(&client, DownloadOptions::default());
+        assert_send_sync(&downloader);
+        let jobs: Vec<DownloadJob> = Vec::new();
+        assert_send(downloader.download(&jobs, std::path::Path::new("target")));
+
+        let lockfile = Lockfile::default();
+        assert_send_sync(&lockfile);
+        assert_send_sync(&LockError::UnsupportedVersion {
+            path: lock::LOCK_NAME.into(),
+            version: 0,
+        });
+        assert_send(Lockfile::lock(&resolver, &pack, &lockfile));
+        let mut jobs = lockfile.jobs();
+        assert_send_sync(&jobs);
+        assert_send_sync(&PathRules::default());
+        let state = install::prepare(&mut jobs, &PathRules::default()).unwrap();
+        assert_send_sync(&state);
+        assert_send_sync(&InstanceState::default());
+
+        let rehasher = Rehasher::new(&client, "cache");
+        assert_send_sync(&rehasher);
+        assert_send(rehasher.rehash(&mut pack, HashAlgorithm::Sha1));
+    }
+
+    // An unsatisfied requirement is reported ahead of parse errors from newer fields
+    #[test]
+    fn requires() {
+        let manifest = |requires: &str| {
+            let mut value = serde_json::to_value(Pack::default()).unwrap();
+            value["metadata"]["requires"] = requires.into();
+            value["versions"] = "something from the future".into();
+            value.to_string()
+        };
+        assert!(matches!(
+            Pack::from_json(&manifest(">=99.0.0")),
+            Err(PackError::Unsupported { .. })
+        ));
+        assert!(matches!(
+            Pack::from_json(&manifest("not a range")),
+            Err(PackError::InvalidRequirement { .. })
+        ));
+        // When the version is fine, the real problem comes through
+        assert!(matches!(
+            Pack::from_json(&manifest(">=0.0.1")),
+            Err(PackError::Parse { .. })
+        ));
+        let pack = Pack::default();
+        let json = serde_json::to_string(&pack).unwrap();
+        assert_eq!(Pack::from_json(&json).unwrap(), pack);
+    }
+
+    // Disabled files keep their entry, and are installed with the suffix loaders skip
+    #[test]
+    fn set_enabled() {
+        let mut pack = Pack::default();
+        let path = RelativePath::new("mods/MyAwesomeMod.jar");
+        assert!(pack.set_enabled(path, false));
+        let file = pack.managed_files.iter().next().unwrap();
+        assert!(!file.enabled);
+        assert_eq!(file.install_path(), "mods/MyAwesomeMod.jar.disabled");
+        let json = serde_json::to_string(&pack).unwrap();
+        assert_eq!(Pack::from_json(&json).unwrap(), pack);
+        assert!(pack.set_enabled(path, true));
+        assert!(!serde_json::to_string(&pack).unwrap().contains("enabled"));
+        assert!(!pack.set_enabled(RelativePath::new("mods/missing.jar"), false));
+    }
+}
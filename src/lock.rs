@@ -0,0 +1,517 @@
+//! Freezing what a pack's sources resolve to, so that installs are reproducible
+//!
+//! Sources like Modrinth projects without a pinned version or git branches resolve to whatever
+//! is newest at the time. The [`Lockfile`], kept as [`LOCK_NAME`] next to the manifest, records
+//! each file's resolved url, upstream ids, size, and hashes, and is installed from instead of
+//! resolving again. Locking again only resolves files whose source changed since, so what was
+//! locked stays frozen until it is explicitly updated. The lockfiles of published versions are
+//! kept in [`history`].
+
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use relative_path::{RelativePath, RelativePathBuf};
+use serde::{Deserialize, Serialize};
+use snafu::{ensure, ResultExt, Snafu};
+use tracing::{debug, instrument};
+
+use crate::{
+    download::DownloadJob,
+    http::HttpClient,
+    resolve::{ResolveError, ResolvedArtifact, Resolver},
+    types::{ManagedFile, Source, Versions},
+    Pack,
+};
+
+pub mod history;
+
+/// The file name of a lockfile, which sits next to the manifest
+pub const LOCK_NAME: &str = "ffpack.lock";
+
+/// The version of the lockfile format written
+const LOCK_VERSION: u32 = 1;
+
+/// What every managed file of a pack resolved to when it was locked
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lockfile {
+    /// The version of the lockfile format
+    version: u32,
+    /// The versions of the game and loader the files were resolved for
+    pub versions: Versions,
+    /// The locked files, by their path in the manifest
+    #[serde(default)]
+    pub files: BTreeMap<RelativePathBuf, LockedFile>,
+}
+
+/// A managed file as it was locked
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedFile {
+    /// The source as the manifest gave it, to tell when it changes
+    pub source: Source,
+    /// What the source resolved to
+    #[serde(flatten)]
+    pub artifact: ResolvedArtifact,
+}
+
+impl Default for Lockfile {
+    fn default() -> Self {
+        Self {
+            version: LOCK_VERSION,
+            versions: Versions::default(),
+            files: BTreeMap::new(),
+        }
+    }
+}
+
+impl Lockfile {
+    /// Returns where the lockfile of the manifest at `manifest` is kept
+    pub fn path_for(manifest: &Path) -> PathBuf {
+        manifest.with_file_name(LOCK_NAME)
+    }
+
+    /// Reads the lockfile at `path`, returning `None` if there is none yet
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the lockfile exists but can't be read, or was written by a newer
+    /// version of ffpack
+    pub fn load(path: &Path) -> Result<Option<Self>, LockError> {
+        let json = match fs::read(path) {
+            Ok(json) => json,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(source) => {
+                return Err(LockError::Io {
+                    path: path.into(),
+                    source,
+                })
+            }
+        };
+        let lockfile: Self = serde_json::from_slice(&json).context(ParseSnafu { path })?;
+        ensure!(
+            lockfile.version <= LOCK_VERSION,
+            UnsupportedVersionSnafu {
+                path,
+                version: lockfile.version
+            }
+        );
+        Ok(Some(lockfile))
+    }
+
+    /// Writes the lockfile to `path`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the lockfile can't be written
+    pub fn save(&self, path: &Path) -> Result<(), LockError> {
+        let mut json = serde_json::to_vec_pretty(self).context(ParseSnafu { path })?;
+        json.push(b'\n');
+        fs::write(path, json).context(IoSnafu { path })
+    }
+
+    /// Locks every managed file of a pack, keeping what `previous` locked for files whose
+    /// source hasn't changed
+    ///
+    /// Files are resolved one after another with `resolver`, which needs whatever the pack's
+    /// sources do, like a root for path sources. Nothing is kept from `previous` if it was locked
+    /// for other versions of the game or loader.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the first file that couldn't be resolved
+    pub async fn lock<C: HttpClient>(
+        resolver: &Resolver<C>,
+        pack: &Pack,
+        previous: &Lockfile,
+    ) -> Result<Self, LockError> {
+        Self::update_only(resolver, pack, previous, |_| false).await
+    }
+
+    /// Locks a pack like [`lock`](Self::lock), but also resolves the files `update` selects
+    /// again, keeping every other file frozen
+    ///
+    /// This is how a single mod, or a group like everything under `mods/` picked with
+    /// [`selecting`], is updated to whatever its source resolves to now.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the first file that couldn't be resolved
+    #[doc(alias = "freeze_except")]
+    #[instrument(skip_all)]
+    pub async fn update_only<C: HttpClient>(
+        resolver: &Resolver<C>,
+        pack: &Pack,
+        previous: &Lockfile,
+        update: impl Fn(&ManagedFile) -> bool,
+    ) -> Result<Self, LockError> {
+        let reusable = previous.versions == pack.versions;
+        let mut files = BTreeMap::new();
+        for file in &pack.managed_files {
+            let kept = previous
+                .files
+                .get(&file.path)
+                .filter(|locked| reusable && locked.source == file.source && !update(file));
+            let locked = match kept {
+                Some(locked) => locked.clone(),
+                None => lock_file(resolver, file, &pack.versions).await?,
+            };
+            files.insert(file.path.clone(), locked);
+        }
+        Ok(Self {
+            version: LOCK_VERSION,
+            versions: pack.versions.clone(),
+            files,
+        })
+    }
+
+    /// Checks that the lockfile still matches a pack, returning every way it doesn't
+    ///
+    /// A lockfile that was locked from the pack as it is now has no mismatches, so CI can fail
+    /// on any of them to catch manifests changed without locking again.
+    pub fn verify_against(&self, pack: &Pack) -> Vec<LockMismatch> {
+        let mut mismatches = Vec::new();
+        if self.versions != pack.versions {
+            mismatches.push(LockMismatch::VersionsChanged);
+        }
+        for file in &pack.managed_files {
+            match self.files.get(&file.path) {
+                None => mismatches.push(LockMismatch::Missing {
+                    path: file.path.clone(),
+                }),
+                Some(locked) if locked.source != file.source => {
+                    mismatches.push(LockMismatch::SourceChanged {
+                        path: file.path.clone(),
+                    });
+                }
+                Some(_) => {}
+            }
+        }
+        for path in self.files.keys() {
+            if !pack.managed_files.iter().any(|file| file.path == *path) {
+                mismatches.push(LockMismatch::Removed { path: path.clone() });
+            }
+        }
+        mismatches
+    }
+
+    /// Returns what the file at `path` was locked to
+    pub fn get(&self, path: &RelativePath) -> Option<&LockedFile> {
+        self.files.get(path)
+    }
+
+    /// Returns the downloads that install the locked files
+    pub fn jobs(&self) -> Vec<DownloadJob> {
+        self.files
+            .iter()
+            .map(|(path, locked)| DownloadJob {
+                artifact: locked.artifact.clone(),
+                path: path.clone(),
+            })
+            .collect()
+    }
+}
+
+/// A way in which a lockfile no longer matches its pack
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum LockMismatch {
+    /// The pack is for other versions of the game or loader than were locked
+    VersionsChanged,
+    /// A managed file isn't locked
+    Missing {
+        /// The file's path
+        path: RelativePathBuf,
+    },
+    /// A locked file is no longer in the pack
+    Removed {
+        /// The file's path
+        path: RelativePathBuf,
+    },
+    /// A managed file's source changed since it was locked
+    SourceChanged {
+        /// The file's path
+        path: RelativePathBuf,
+    },
+}
+
+impl Display for LockMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LockMismatch::VersionsChanged => {
+                write!(f, "The game or loader version changed since locking")
+            }
+            LockMismatch::Missing { path } => write!(f, "{path} isn't locked"),
+            LockMismatch::Removed { path } => write!(f, "{path} is locked but not in the pack"),
+            LockMismatch::SourceChanged { path } => {
+                write!(f, "{path}'s source changed since it was locked")
+            }
+        }
+    }
+}
+
+/// Returns a filter for [`Lockfile::update_only`] selecting the files with any of `names` as
+/// their name or path, or inside a directory of that path
+///
+/// Names are compared ignoring case, so `sodium` selects a file named "Sodium".
+pub fn selecting<'a>(names: &'a [&str]) -> impl Fn(&ManagedFile) -> bool + 'a {
+    move |file| {
+        names.iter().any(|&name| {
+            let directory = name.trim_end_matches('/');
+            file.name
+                .as_deref()
+                .is_some_and(|own| own.eq_ignore_ascii_case(name))
+                || file.path == RelativePath::new(name)
+                || file
+                    .path
+                    .as_str()
+                    .strip_prefix(directory)
+                    .is_some_and(|rest| rest.starts_with('/'))
+        })
+    }
+}
+
+/// Resolves a managed file into its lockfile entry
+async fn lock_file<C: HttpClient>(
+    resolver: &Resolver<C>,
+    file: &ManagedFile,
+    versions: &Versions,
+) -> Result<LockedFile, LockError> {
+    debug!(path = %file.path, "Locking");
+    let mut artifact = resolver
+        .resolve(file, versions)
+        .await
+        .context(ResolveSnafu { path: &file.path })?;
+    // Digests the manifest records are kept alongside what the API reported
+    if let Some(hashes) = file.source.hashes() {
+        artifact.hashes.merge(&hashes);
+    }
+    Ok(LockedFile {
+        source: file.source.clone(),
+        artifact,
+    })
+}
+
+/// Error that occurs while locking a pack or reading its lockfile
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum LockError {
+    /// A file couldn't be resolved
+    #[snafu(display("Failed to lock {}: {}", path, source))]
+    Resolve {
+        /// The file's path in the manifest
+        path: RelativePathBuf,
+        /// The underlying error, boxed since it is much larger than the others
+        #[snafu(source(from(ResolveError, Box::new)))]
+        source: Box<ResolveError>,
+    },
+    /// The lockfile couldn't be read or written
+    #[snafu(display("Failed to access {}: {}", path.display(), source))]
+    Io {
+        /// The lockfile
+        path: PathBuf,
+        /// The underlying error
+        source: io::Error,
+    },
+    /// The lockfile was malformed
+    #[snafu(display("Invalid lockfile {}: {}", path.display(), source))]
+    Parse {
+        /// The lockfile
+        path: PathBuf,
+        /// The underlying error
+        source: serde_json::Error,
+    },
+    /// The lockfile was written in a newer format
+    #[snafu(display(
+        "Lockfile {} has format version {}, newer than this ffpack understands",
+        path.display(),
+        version
+    ))]
+    UnsupportedVersion {
+        /// The lockfile
+        path: PathBuf,
+        /// The format version it has
+        version: u32,
+    },
+}
+
+impl LockError {
+    /// Suggests how the user might fix the problem, if there is a likely fix
+    pub fn suggestion(&self) -> Option<String> {
+        match self {
+            LockError::Resolve { source, .. } => source.suggestion(),
+            LockError::Parse { .. } => Some(format!("Delete {LOCK_NAME} and lock the pack again")),
+            LockError::UnsupportedVersion { .. } => {
+                Some("Update ffpack to the version that wrote the lockfile".into())
+            }
+            LockError::Io { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use url::Url;
+
+    use super::*;
+    use crate::{
+        http::testing::{block_on, MockClient},
+        types::{Minecraft, Source},
+    };
+
+    /// A pack with a maven library at an exact version
+    fn pack(version: &str) -> Pack {
+        let file = ManagedFile {
+            path: RelativePathBuf::from("mods/lib.jar"),
+            source: Source::Maven {
+                repository: Url::parse("https://maven.example.org").unwrap(),
+                group: "net.example".into(),
+                artifact: "lib".into(),
+                version: version.into(),
+                classifier: None,
+            },
+            name: Some("Lib".into()),
+            ..ManagedFile::default()
+        };
+        Pack {
+            managed_files: [file, ManagedFile::default()].into_iter().collect(),
+            ..Pack::default()
+        }
+    }
+
+    // Files are resolved once and stay locked until their source or the pack's versions change
+    #[test]
+    fn lock() {
+        let base = "https://maven.example.org/net/example/lib";
+        let client = MockClient::default()
+            .with(&format!("{base}/1.0/lib-1.0.jar.sha1"), "aa".repeat(20))
+            .with(&format!("{base}/1.1/lib-1.1.jar.sha1"), "bb".repeat(20));
+        let resolver = Resolver::new(&client);
+        let requests = || client.requests.lock().unwrap().len();
+        let locked = block_on(Lockfile::lock(
+            &resolver,
+            &pack("1.0"),
+            &Lockfile::default(),
+        ))
+        .unwrap();
+        let lib = locked.get(RelativePath::new("mods/lib.jar")).unwrap();
+        assert_eq!(
+            lib.artifact.download_url.as_str(),
+            format!("{base}/1.0/lib-1.0.jar")
+        );
+        assert_eq!(lib.artifact.hashes.sha1, Some([0xaa; 20]));
+        assert_eq!(lib.artifact.ids.version_id.as_deref(), Some("1.0"));
+        assert_eq!(locked.jobs().len(), 2);
+        assert_eq!(requests(), 1);
+
+        let again = block_on(Lockfile::lock(&resolver, &pack("1.0"), &locked)).unwrap();
+        assert_eq!(again, locked);
+        assert_eq!(requests(), 1);
+        let bumped = block_on(Lockfile::lock(&resolver, &pack("1.1"), &locked)).unwrap();
+        let lib = bumped.get(RelativePath::new("mods/lib.jar")).unwrap();
+        assert_eq!(lib.artifact.hashes.sha1, Some([0xbb; 20]));
+        assert_eq!(requests(), 2);
+        let mut other = pack("1.0");
+        other.versions.minecraft = Minecraft::new("1.16.5").unwrap();
+        block_on(Lockfile::lock(&resolver, &other, &locked)).unwrap();
+        assert_eq!(requests(), 3);
+
+        // Lockfiles round trip through disk, and a missing one is no lockfile
+        let dir = std::env::temp_dir().join(format!("ffpack-lock-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = Lockfile::path_for(&dir.join("ffpack.json"));
+        assert_eq!(Lockfile::load(&path).unwrap(), None);
+        locked.save(&path).unwrap();
+        assert_eq!(Lockfile::load(&path).unwrap(), Some(locked.clone()));
+        let mut future = serde_json::to_value(&locked).unwrap();
+        future["version"] = 99.into();
+        fs::write(&path, future.to_string()).unwrap();
+        assert!(matches!(
+            Lockfile::load(&path),
+            Err(LockError::UnsupportedVersion { version: 99, .. })
+        ));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    // Selected files are resolved again while the others stay frozen
+    #[test]
+    fn update_only() {
+        let client = MockClient::default();
+        let resolver = Resolver::new(&client);
+        let requests = || client.requests.lock().unwrap().len();
+        let locked = block_on(Lockfile::lock(
+            &resolver,
+            &pack("1.0"),
+            &Lockfile::default(),
+        ))
+        .unwrap();
+        assert_eq!(requests(), 1);
+        block_on(Lockfile::update_only(
+            &resolver,
+            &pack("1.0"),
+            &locked,
+            selecting(&["config"]),
+        ))
+        .unwrap();
+        assert_eq!(requests(), 1);
+        for names in [
+            &["mods/lib.jar"][..],
+            &["mods/"],
+            &["mods"],
+            &["other", "LIB"],
+        ] {
+            block_on(Lockfile::update_only(
+                &resolver,
+                &pack("1.0"),
+                &locked,
+                selecting(names),
+            ))
+            .unwrap();
+        }
+        assert_eq!(requests(), 5);
+        let mods = ManagedFile {
+            path: RelativePathBuf::from("modsextra/a.jar"),
+            ..ManagedFile::default()
+        };
+        assert!(!selecting(&["mods"])(&mods));
+    }
+
+    // Lockfiles report every file added, removed, or changed since they were locked
+    #[test]
+    fn verify() {
+        let client = MockClient::default();
+        let resolver = Resolver::new(&client);
+        let locked = block_on(Lockfile::lock(
+            &resolver,
+            &pack("1.0"),
+            &Lockfile::default(),
+        ))
+        .unwrap();
+        assert_eq!(locked.verify_against(&pack("1.0")), []);
+        let lib = RelativePathBuf::from("mods/lib.jar");
+        assert_eq!(
+            locked.verify_against(&pack("1.1")),
+            [LockMismatch::SourceChanged { path: lib.clone() }]
+        );
+
+        let mut other = pack("1.0");
+        other.versions.minecraft = Minecraft::new("1.16.5").unwrap();
+        other.managed_files.retain(|file| file.path == lib);
+        let added = ManagedFile {
+            path: RelativePathBuf::from("mods/new.jar"),
+            ..ManagedFile::default()
+        };
+        other.managed_files.insert(added.clone());
+        let removed = ManagedFile::default().path;
+        assert_eq!(
+            locked.verify_against(&other),
+            [
+                LockMismatch::VersionsChanged,
+                LockMismatch::Missing { path: added.path },
+                LockMismatch::Removed { path: removed },
+            ]
+        );
+    }
+}
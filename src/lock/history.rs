@@ -0,0 +1,235 @@
+//! The lockfiles of published versions of a pack, kept for looking back at
+//!
+//! Whenever a version of the pack is published, its lockfile is recorded under [`HISTORY_DIR`]
+//! next to the manifest, as `<version>.lock`. Changelogs, diffs, and bisecting between versions
+//! read what each version shipped from there, without needing the git history of the manifest.
+//! A [`Retention`] policy decides which of the recorded versions are kept as more are published.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use semver::Version;
+use snafu::ResultExt;
+use tracing::debug;
+
+use super::{IoSnafu, LockError, Lockfile};
+
+/// Where the history is kept, relative to the directory of the manifest
+pub const HISTORY_DIR: &str = ".ffpack/history";
+
+/// The extension of recorded lockfiles
+const EXTENSION: &str = "lock";
+
+/// Which recorded versions [`History::prune`] keeps
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Retention {
+    /// How many of the newest versions are kept, or `None` to keep any number
+    pub latest: Option<usize>,
+    /// Whether pre-releases, like `1.2.0-beta.1`, are kept once a newer release is recorded
+    pub superseded_prereleases: bool,
+}
+
+impl Default for Retention {
+    /// Keeps every release, along with the pre-releases of versions that aren't released yet
+    fn default() -> Self {
+        Self {
+            latest: None,
+            superseded_prereleases: false,
+        }
+    }
+}
+
+impl Retention {
+    /// Keeps every recorded version
+    pub const ALL: Self = Self {
+        latest: None,
+        superseded_prereleases: true,
+    };
+
+    /// Keeps only the newest `latest` versions, consuming and returning `self`
+    #[must_use]
+    pub fn with_latest(mut self, latest: usize) -> Self {
+        self.latest = Some(latest);
+        self
+    }
+
+    /// Returns the versions the policy drops out of `versions`, which are sorted oldest first
+    fn dropped<'a>(&self, versions: &'a [Version]) -> Vec<&'a Version> {
+        let newest_release = versions
+            .iter()
+            .filter(|version| version.pre.is_empty())
+            .max();
+        let superseded = |version: &Version| {
+            !version.pre.is_empty() && newest_release.is_some_and(|release| release > version)
+        };
+        let kept: Vec<_> = versions
+            .iter()
+            .filter(|version| self.superseded_prereleases || !superseded(version))
+            .collect();
+        let skip = self
+            .latest
+            .map_or(0, |latest| kept.len().saturating_sub(latest));
+        versions
+            .iter()
+            .filter(|version| !kept[skip..].contains(version))
+            .collect()
+    }
+}
+
+/// The recorded lockfiles of a pack
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct History {
+    /// The directory they are recorded in
+    dir: PathBuf,
+}
+
+impl History {
+    /// Creates the history kept in `dir`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Returns the history of the pack whose manifest is at `manifest`
+    pub fn for_manifest(manifest: &Path) -> Self {
+        Self::new(manifest.with_file_name(HISTORY_DIR))
+    }
+
+    /// Returns where the lockfile of `version` is recorded
+    pub fn path_of(&self, version: &Version) -> PathBuf {
+        self.dir.join(format!("{version}.{EXTENSION}"))
+    }
+
+    /// Records `lockfile` as what `version` was published with, replacing whatever was recorded
+    /// for it before, and returns where it was recorded
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the lockfile can't be written
+    pub fn record(&self, version: &Version, lockfile: &Lockfile) -> Result<PathBuf, LockError> {
+        fs::create_dir_all(&self.dir).context(IoSnafu { path: &self.dir })?;
+        let path = self.path_of(version);
+        lockfile.save(&path)?;
+        debug!(%version, path = %path.display(), "Recorded lockfile");
+        Ok(path)
+    }
+
+    /// Reads the lockfile recorded for `version`, returning `None` if there is none
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the recorded lockfile can't be read
+    pub fn load(&self, version: &Version) -> Result<Option<Lockfile>, LockError> {
+        Lockfile::load(&self.path_of(version))
+    }
+
+    /// Returns every version with a recorded lockfile, oldest first
+    ///
+    /// Files in the directory that aren't named after a version are left alone.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory exists but can't be read
+    pub fn versions(&self) -> Result<Vec<Version>, LockError> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(source) => {
+                return Err(LockError::Io {
+                    path: self.dir.clone(),
+                    source,
+                })
+            }
+        };
+        let mut versions = Vec::new();
+        for entry in entries {
+            let path = entry.context(IoSnafu { path: &self.dir })?.path();
+            if path.extension().and_then(|extension| extension.to_str()) != Some(EXTENSION) {
+                continue;
+            }
+            let version = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| Version::parse(stem).ok());
+            versions.extend(version);
+        }
+        versions.sort();
+        Ok(versions)
+    }
+
+    /// Removes the recorded lockfiles `retention` doesn't keep, returning their versions
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the history can't be read, or a lockfile can't be removed
+    pub fn prune(&self, retention: &Retention) -> Result<Vec<Version>, LockError> {
+        let versions = self.versions()?;
+        let dropped: Vec<_> = retention.dropped(&versions).into_iter().cloned().collect();
+        for version in &dropped {
+            let path = self.path_of(version);
+            fs::remove_file(&path).context(IoSnafu { path: &path })?;
+            debug!(%version, "Pruned lockfile from history");
+        }
+        Ok(dropped)
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::Pack;
+
+    // Published versions are recorded and listed in order, and pruning keeps what the policy says
+    #[test]
+    fn history() {
+        let dir = std::env::temp_dir().join(format!("ffpack-history-{}", std::process::id()));
+        let manifest = dir.join("ffpack.json");
+        let history = History::for_manifest(&manifest);
+        assert_eq!(history.versions().unwrap(), []);
+        let version = |version: &str| Version::parse(version).unwrap();
+        let published = ["1.1.0", "1.0.0", "1.1.0-beta.1", "1.2.0-alpha.1"].map(version);
+        for version in &published {
+            history.record(version, &Lockfile::default()).unwrap();
+        }
+        fs::write(dir.join(HISTORY_DIR).join("notes.txt"), "").unwrap();
+        fs::write(dir.join(HISTORY_DIR).join("latest.lock"), "").unwrap();
+        assert_eq!(
+            history.versions().unwrap(),
+            ["1.0.0", "1.1.0-beta.1", "1.1.0", "1.2.0-alpha.1"].map(version)
+        );
+        assert_eq!(
+            history.load(&version("1.0.0")).unwrap(),
+            Some(Lockfile::default())
+        );
+        assert_eq!(history.load(&version("0.9.0")).unwrap(), None);
+
+        assert_eq!(history.prune(&Retention::ALL).unwrap(), []);
+        assert_eq!(
+            history.prune(&Retention::default()).unwrap(),
+            [version("1.1.0-beta.1")]
+        );
+        assert_eq!(
+            history.prune(&Retention::default().with_latest(2)).unwrap(),
+            [version("1.0.0")]
+        );
+        assert_eq!(
+            history.versions().unwrap(),
+            ["1.1.0", "1.2.0-alpha.1"].map(version)
+        );
+
+        // Packs find the lockfile of a past version, or of their own before it is published
+        let pack = Pack::default();
+        assert!(pack
+            .lockfile_at(&manifest, &version("1.1.0"))
+            .unwrap()
+            .is_some());
+        let current = pack.metadata.version().clone();
+        assert_eq!(pack.lockfile_at(&manifest, &current).unwrap(), None);
+        Lockfile::default()
+            .save(&Lockfile::path_for(&manifest))
+            .unwrap();
+        assert!(pack.lockfile_at(&manifest, &current).unwrap().is_some());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
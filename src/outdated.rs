@@ -0,0 +1,180 @@
+//! Finding which of a pack's files have newer compatible versions upstream
+//!
+//! Each file's current version, from the lockfile when there is one, is compared with what its
+//! source resolves to with its pins removed (see [`Source::floating`](crate::types::Source::floating)).
+//! Nothing is changed; [`Lockfile::update_only`] is how the updates are taken.
+
+use relative_path::RelativePathBuf;
+use tracing::instrument;
+use url::Url;
+
+use crate::{
+    http::HttpClient,
+    lock::Lockfile,
+    resolve::{ResolveError, ResolvedArtifact, Resolver},
+    types::{ManagedFile, Versions},
+    Pack,
+};
+
+/// How a file compares with what is available upstream
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Outdated {
+    /// The file is at the newest compatible version
+    UpToDate {
+        /// The version, as the project labels it
+        version: Option<String>,
+    },
+    /// A newer compatible version is available
+    Available {
+        /// The version the file is at
+        current: Option<String>,
+        /// The newest compatible version
+        available: Option<String>,
+        /// The page describing what changed in the newest version, where the source has one
+        changelog: Option<Url>,
+    },
+    /// The file's source names fixed content, which has no newer versions
+    Fixed,
+}
+
+/// Compares every managed file of a pack with the newest compatible version of its source,
+/// sorted by path
+///
+/// Files are taken to be at what `lock` locked them to if their source hasn't changed since,
+/// and at what their source resolves to now otherwise. Each file's source is resolved with
+/// `resolver` once with its pins and once without, so failures are reported per file.
+#[instrument(skip_all)]
+pub async fn outdated<C: HttpClient>(
+    resolver: &Resolver<C>,
+    pack: &Pack,
+    lock: Option<&Lockfile>,
+) -> Vec<(RelativePathBuf, Result<Outdated, ResolveError>)> {
+    let lock = lock.filter(|lock| lock.versions == pack.versions);
+    let mut results = Vec::new();
+    for file in &pack.managed_files {
+        let locked = lock
+            .and_then(|lock| lock.get(&file.path))
+            .filter(|locked| locked.source == file.source)
+            .map(|locked| &locked.artifact);
+        let outcome = compare(resolver, file, locked, &pack.versions).await;
+        results.push((file.path.clone(), outcome));
+    }
+    results.sort_by(|(a, _), (b, _)| a.cmp(b));
+    results
+}
+
+/// Compares a file at `locked`, or at what it resolves to, with its newest compatible version
+async fn compare<C: HttpClient>(
+    resolver: &Resolver<C>,
+    file: &ManagedFile,
+    locked: Option<&ResolvedArtifact>,
+    versions: &Versions,
+) -> Result<Outdated, ResolveError> {
+    let Some(floating) = file.source.floating() else {
+        return Ok(Outdated::Fixed);
+    };
+    let current = match locked {
+        Some(locked) => locked.clone(),
+        None => resolver.resolve(file, versions).await?,
+    };
+    let newest = ManagedFile {
+        source: floating,
+        ..file.clone()
+    };
+    let newest = resolver.resolve(&newest, versions).await?;
+    // Upstream ids tell versions apart best, where both have ids naming a file
+    let named = |artifact: &ResolvedArtifact| {
+        let ids = &artifact.ids;
+        ids.version_id.is_some() || ids.file_id.is_some() || ids.commit.is_some()
+    };
+    let same = if !named(&current) || !named(&newest) {
+        current.download_url == newest.download_url
+    } else {
+        current.ids == newest.ids
+    };
+    Ok(if same {
+        Outdated::UpToDate {
+            version: current.version_label(),
+        }
+    } else {
+        Outdated::Available {
+            current: current.version_label(),
+            available: newest.version_label(),
+            changelog: newest.details.changelog.clone(),
+        }
+    })
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::{
+        http::testing::{block_on, MockClient},
+        types::Source,
+    };
+
+    /// The metadata of the example maven library, listing `versions`
+    fn metadata(versions: &[&str]) -> String {
+        let versions = versions.join("</version><version>");
+        format!(
+            "<metadata><versioning><versions><version>{versions}</version></versions>\
+            </versioning></metadata>"
+        )
+    }
+
+    /// A pack with the example maven library at `version`, and a url file
+    fn pack(version: &str) -> Pack {
+        let file = ManagedFile {
+            path: RelativePathBuf::from("mods/lib.jar"),
+            source: Source::Maven {
+                repository: Url::parse("https://maven.example.org").unwrap(),
+                group: "net.example".into(),
+                artifact: "lib".into(),
+                version: version.into(),
+                classifier: None,
+            },
+            ..ManagedFile::default()
+        };
+        Pack {
+            managed_files: [file, ManagedFile::default()].into_iter().collect(),
+            ..Pack::default()
+        }
+    }
+
+    // Pinned and locked files are compared with the newest version, fixed ones are skipped
+    #[test]
+    fn outdated() {
+        let url = "https://maven.example.org/net/example/lib/maven-metadata.xml";
+        let old = Resolver::new(MockClient::default().with(url, metadata(&["1.0"])));
+        let new = Resolver::new(MockClient::default().with(url, metadata(&["1.0", "1.1"])));
+        let check = |resolver: &Resolver<MockClient>, pack: &Pack, lock: Option<&Lockfile>| {
+            let results = block_on(super::outdated(resolver, pack, lock));
+            assert_eq!(results[0].1.as_ref().unwrap(), &Outdated::Fixed);
+            assert_eq!(results[1].0, "mods/lib.jar");
+            results[1].1.as_ref().unwrap().clone()
+        };
+
+        let up_to_date = Outdated::UpToDate {
+            version: Some("1.1".into()),
+        };
+        let available = Outdated::Available {
+            current: Some("1.0".into()),
+            available: Some("1.1".into()),
+            changelog: None,
+        };
+        assert_eq!(check(&new, &pack("1.1"), None), up_to_date);
+        assert_eq!(check(&new, &pack("1.0"), None), available);
+        // Floating sources are at what they were locked to
+        let floating = pack("release");
+        assert_eq!(check(&new, &floating, None), up_to_date);
+        let lock = block_on(Lockfile::lock(&old, &floating, &Lockfile::default())).unwrap();
+        assert_eq!(check(&new, &floating, Some(&lock)), available);
+
+        let missing = block_on(super::outdated(&old, &pack("[2.0,3.0)"), None));
+        assert!(matches!(
+            missing[1].1,
+            Err(ResolveError::NoMatchingVersion { .. })
+        ));
+    }
+}
@@ -0,0 +1,415 @@
+//! Recomputing the digests of a pack's files under another hash algorithm
+//!
+//! Packs exchanged with ecosystems standardized on a different hash need digests the manifest
+//! doesn't have yet. The [`Rehasher`] reads each url and path file from a cache directory when
+//! a verified copy is there, downloads it otherwise, and records the new digest in the pack.
+//! [`Rehasher::pin_url`] does the same for a url that isn't in the pack yet, so that its
+//! blake3 hash doesn't have to be computed by hand.
+
+use std::path::PathBuf;
+
+use relative_path::RelativePathBuf;
+use snafu::{ResultExt, Snafu};
+use tracing::{debug, instrument};
+use url::Url;
+
+use crate::{
+    download::{DownloadError, DownloadJob, DownloadOptions, Downloader},
+    hash::hash_file,
+    http::HttpClient,
+    resolve::{
+        user_agent, Credentials, ProjectDetails, ResolveError, ResolvedArtifact, Resolver,
+        UpstreamIds,
+    },
+    types::{FileKind, HashAlgorithm, Hashes, ManagedFile, Side, Source},
+    Pack,
+};
+
+/// What happened to a file when rehashing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rehashed {
+    /// The digest was added or changed
+    Updated,
+    /// The manifest already had the same digest
+    Unchanged,
+    /// The file's source resolves to a file later, so it has no digests of its own to update
+    Skipped,
+}
+
+/// Recomputes digests of a pack's files, using an [`HttpClient`] for files that aren't cached
+#[derive(Debug)]
+pub struct Rehasher<C> {
+    /// The client used for downloads
+    client: C,
+    /// Where artifacts are cached, laid out by install path
+    cache: PathBuf,
+    /// The directory the manifest is in, for reading path sources
+    root: Option<PathBuf>,
+    /// How missing artifacts are downloaded
+    options: DownloadOptions,
+    /// The keys and tokens for API-backed sources
+    credentials: Credentials,
+}
+
+impl<C: HttpClient> Rehasher<C> {
+    /// Creates a rehasher that reads and fills the cache at `cache`, which can be an existing
+    /// install of the pack
+    pub fn new(client: C, cache: impl Into<PathBuf>) -> Self {
+        Self {
+            client,
+            cache: cache.into(),
+            root: None,
+            options: DownloadOptions::default(),
+            credentials: Credentials::default(),
+        }
+    }
+
+    /// Sets the directory path sources are relative to, which must be absolute
+    #[must_use]
+    pub fn with_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.root = Some(root.into());
+        self
+    }
+
+    /// Sets how missing artifacts are downloaded
+    #[must_use]
+    pub fn with_options(mut self, options: DownloadOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Sets the keys and tokens used to resolve API-backed sources
+    #[must_use]
+    pub fn with_credentials(mut self, credentials: Credentials) -> Self {
+        self.credentials = credentials;
+        self
+    }
+
+    /// Computes every url and path file's digest under `algorithm` and records it in the pack
+    ///
+    /// Cached copies are only used when they match every digest already in the manifest, and
+    /// downloads are verified the same way, so a digest is never recorded for a different file.
+    /// Results are per file, ordered by path.
+    #[instrument(skip(self, pack), fields(cache = %self.cache.display()))]
+    pub async fn rehash(
+        &self,
+        pack: &mut Pack,
+        algorithm: HashAlgorithm,
+    ) -> Vec<(RelativePathBuf, Result<Rehashed, RehashError>)> {
+        let mut resolver = Resolver::new(&self.client)
+            .with_network(self.options.network())
+            .with_credentials(self.credentials.clone())
+            .with_user_agent(user_agent(&pack.metadata));
+        if let Some(root) = &self.root {
+            resolver = resolver.with_root(root);
+        }
+        let mut results = Vec::new();
+        let mut digests = Vec::new();
+        let mut jobs = Vec::new();
+        for file in &pack.managed_files {
+            let Some(known) = file.source.hashes() else {
+                results.push((file.path.clone(), Ok(Rehashed::Skipped)));
+                continue;
+            };
+            let cached = file.path.to_path(&self.cache);
+            let verified = hash_file(&cached, known.algorithms().chain([algorithm]))
+                .ok()
+                .filter(|actual| known.mismatch(actual).is_none());
+            if let Some(actual) = verified {
+                debug!(path = %file.path, "Using cached copy");
+                digests.push((file.clone(), Ok(actual)));
+                continue;
+            }
+            match resolver.resolve(file, &pack.versions).await {
+                Ok(artifact) => jobs.push((
+                    file.clone(),
+                    DownloadJob {
+                        artifact,
+                        path: file.path.clone(),
+                    },
+                )),
+                Err(source) => {
+                    results.push((file.path.clone(), Err(RehashError::Resolve { source })));
+                }
+            }
+        }
+
+        let downloader = Downloader::new(&self.client, self.options.clone());
+        let downloads: Vec<_> = jobs.iter().map(|(_, job)| job.clone()).collect();
+        let downloaded = downloader.download(&downloads, &self.cache).await;
+        for ((file, _), result) in jobs.into_iter().zip(downloaded) {
+            let digest = result
+                .context(DownloadSnafu)
+                .and_then(|path| hash_file(&path, [algorithm]).context(IoSnafu { path }));
+            digests.push((file, digest));
+        }
+
+        for (mut file, digest) in digests {
+            let path = file.path.clone();
+            let outcome = digest.map(|actual| {
+                let digest = actual.get(algorithm).unwrap_or_default();
+                let known = file.source.hashes().unwrap_or_default();
+                if known.get(algorithm) == Some(digest) {
+                    Rehashed::Unchanged
+                } else {
+                    file.source.set_hash(algorithm, digest);
+                    debug!(%path, %algorithm, "Updated digest");
+                    pack.managed_files.replace(file);
+                    Rehashed::Updated
+                }
+            });
+            results.push((path, outcome));
+        }
+        results.sort_by(|(a, _), (b, _)| a.cmp(b));
+        results
+    }
+
+    /// Downloads the file at `url` into the cache at `path`, and records it in the pack as a url
+    /// source with its blake3 hash and its digests under `algorithms`
+    ///
+    /// A file already at `path` keeps everything but its source; otherwise a file named after
+    /// the url is added. Locking the pack afterwards records the same digests in the lockfile.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be downloaded or read back
+    #[instrument(skip(self, pack, algorithms), fields(%url))]
+    pub async fn pin_url(
+        &self,
+        pack: &mut Pack,
+        path: RelativePathBuf,
+        url: Url,
+        algorithms: impl IntoIterator<Item = HashAlgorithm>,
+    ) -> Result<ManagedFile, RehashError> {
+        let existing = pack.managed_files.iter().find(|file| file.path == path);
+        let filename = match existing {
+            Some(file) => file.filename.clone(),
+            None => path.file_name().unwrap_or_default().to_string(),
+        };
+        let job = DownloadJob {
+            artifact: ResolvedArtifact {
+                download_url: url.clone(),
+                filename: filename.clone(),
+                size: None,
+                hashes: Hashes::default(),
+                mirrors: Vec::new(),
+                extract: None,
+                ids: UpstreamIds::default(),
+                details: ProjectDetails::default(),
+            },
+            path: path.clone(),
+        };
+        let downloader = Downloader::new(&self.client, self.options.clone());
+        let downloaded = downloader.download(&[job], &self.cache).await;
+        let downloaded = downloaded.into_iter().next().unwrap_or_else(|| {
+            unreachable!("The downloader returns a result for every job");
+        });
+        let local = downloaded.context(DownloadSnafu)?;
+        let mut hashes = hash_file(
+            &local,
+            [HashAlgorithm::Blake3].into_iter().chain(algorithms),
+        )
+        .context(IoSnafu { path: local })?;
+        let blake3 = hashes.blake3.take().unwrap_or_default();
+        let source = Source::Url {
+            url,
+            blake3,
+            hashes,
+            mirrors: Vec::new(),
+        };
+        let file = ManagedFile {
+            source,
+            ..existing.cloned().unwrap_or_else(|| ManagedFile {
+                name: None,
+                description: None,
+                filename,
+                devel: false,
+                path,
+                side: Side::default(),
+                source: Source::default(),
+                enabled: true,
+                notes: None,
+                kind: FileKind::default(),
+                collection: None,
+            })
+        };
+        debug!(path = %file.path, "Pinned url");
+        pack.managed_files.replace(file.clone());
+        Ok(file)
+    }
+}
+
+/// Error that occurs while rehashing a file
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum RehashError {
+    /// The file's source couldn't be turned into something to download
+    #[snafu(display("Failed to resolve file: {}", source))]
+    Resolve {
+        /// The underlying error
+        source: ResolveError,
+    },
+    /// The file couldn't be downloaded, or didn't match the digests already known
+    #[snafu(display("{}", source))]
+    Download {
+        /// The underlying error
+        source: DownloadError,
+    },
+    /// The downloaded file couldn't be read back
+    #[snafu(display("Failed to read {}: {}", path.display(), source))]
+    Io {
+        /// The file being read
+        path: PathBuf,
+        /// The underlying error
+        source: std::io::Error,
+    },
+}
+
+impl RehashError {
+    /// Suggests how the user might fix the problem, if there is a likely fix
+    pub fn suggestion(&self) -> Option<String> {
+        match self {
+            RehashError::Resolve { source } => source.suggestion(),
+            RehashError::Download { source } => source.suggestion(),
+            RehashError::Io { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use std::fs;
+
+    use url::Url;
+
+    use super::*;
+    use crate::{
+        hash::blake3,
+        http::testing::{block_on, MockClient},
+        types::{Hashes, ManagedFile, Source},
+    };
+
+    /// A managed file at `path` with the given source
+    fn file(path: &str, source: Source) -> ManagedFile {
+        ManagedFile {
+            path: RelativePathBuf::from(path),
+            source,
+            ..ManagedFile::default()
+        }
+    }
+
+    // Cached copies are used when they verify, and everything else is downloaded first
+    #[test]
+    fn rehash() {
+        let dir = std::env::temp_dir().join(format!("ffpack-rehash-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let (root, cache) = (dir.join("pack"), dir.join("cache"));
+        fs::create_dir_all(root.join("jars")).unwrap();
+        fs::create_dir_all(cache.join("mods")).unwrap();
+        fs::write(root.join("jars/local.jar"), b"local").unwrap();
+        fs::write(cache.join("mods/cached.jar"), b"cached").unwrap();
+        fs::write(cache.join("mods/stale.jar"), b"stale").unwrap();
+        let url = |name: &str| Url::parse(&format!("https://example.org/{name}")).unwrap();
+        let mut pack = Pack {
+            managed_files: [
+                file(
+                    "mods/local.jar",
+                    Source::Path {
+                        path: RelativePathBuf::from("jars/local.jar"),
+                        blake3: blake3(b"local"),
+                        hashes: Hashes::default(),
+                    },
+                ),
+                file(
+                    "mods/cached.jar",
+                    Source::Url {
+                        url: url("cached.jar"),
+                        blake3: blake3(b"cached"),
+                        hashes: Hashes::default(),
+                        mirrors: Vec::new(),
+                    },
+                ),
+                file(
+                    "mods/stale.jar",
+                    Source::Url {
+                        url: url("stale.jar"),
+                        blake3: blake3(b"fresh"),
+                        hashes: Hashes::default(),
+                        mirrors: Vec::new(),
+                    },
+                ),
+                file(
+                    "mods/sodium.jar",
+                    Source::Modrinth {
+                        slug: "sodium".to_string(),
+                        version_id: None,
+                    },
+                ),
+            ]
+            .into_iter()
+            .collect(),
+            ..Pack::default()
+        };
+        let client = MockClient::default().with("https://example.org/stale.jar", "fresh");
+        let rehasher = Rehasher::new(client, &cache).with_root(&root);
+        let results = block_on(rehasher.rehash(&mut pack, HashAlgorithm::Sha1));
+        let outcomes: Vec<_> = results
+            .iter()
+            .map(|(path, result)| (path.as_str(), *result.as_ref().unwrap()))
+            .collect();
+        assert_eq!(
+            outcomes,
+            [
+                ("mods/cached.jar", Rehashed::Updated),
+                ("mods/local.jar", Rehashed::Updated),
+                ("mods/sodium.jar", Rehashed::Skipped),
+                ("mods/stale.jar", Rehashed::Updated),
+            ]
+        );
+        // Only the stale copy needed downloading
+        assert_eq!(rehasher.client.requests.lock().unwrap().len(), 1);
+        let sha1 = |path: &str| {
+            let file = pack.managed_files.iter().find(|file| file.path == path);
+            file.unwrap().source.hashes().unwrap().sha1
+        };
+        let mut expected = crate::hash::Sha1::new();
+        expected.update(b"fresh");
+        assert_eq!(sha1("mods/stale.jar"), Some(expected.finalize()));
+        assert!(sha1("mods/local.jar").is_some());
+
+        let results = block_on(rehasher.rehash(&mut pack, HashAlgorithm::Sha1));
+        assert!(results
+            .iter()
+            .all(|(_, result)| matches!(result, Ok(Rehashed::Unchanged | Rehashed::Skipped))));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    // Pinning downloads the url once and records its digests, keeping a file's other fields
+    #[test]
+    fn pin_url() {
+        let dir = std::env::temp_dir().join(format!("ffpack-pin-{}", std::process::id()));
+        let client = MockClient::default().with("https://example.org/new.jar", "new");
+        let rehasher = Rehasher::new(client, &dir);
+        let mut pack = Pack::default();
+        let url = Url::parse("https://example.org/new.jar").unwrap();
+        let added = block_on(rehasher.pin_url(
+            &mut pack,
+            RelativePathBuf::from("mods/new.jar"),
+            url.clone(),
+            [HashAlgorithm::Sha512],
+        ))
+        .unwrap();
+        assert_eq!(added.filename, "new.jar");
+        let hashes = added.source.hashes().unwrap();
+        assert_eq!(hashes.blake3, Some(blake3(b"new")));
+        assert!(hashes.sha512.is_some());
+        assert_eq!(pack.managed_files.len(), 2);
+
+        let existing = pack.managed_files.iter().next().unwrap().clone();
+        let pinned = block_on(rehasher.pin_url(&mut pack, existing.path.clone(), url, [])).unwrap();
+        assert_eq!(pinned.name, existing.name);
+        assert_eq!(pinned.source.hashes(), Some(Hashes::blake3(blake3(b"new"))));
+        assert_eq!(pack.managed_files.len(), 2);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
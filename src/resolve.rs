@@ -0,0 +1,1045 @@
+//! Turning the [`Source`] of a managed file into a concrete artifact to download
+//!
+//! URL, path, and IPFS sources pass straight through; slug-based sources are looked up through
+//! the Modrinth, CurseForge, and GitHub APIs using the pack's minecraft and loader versions, CI
+//! builds through Jenkins and GitHub Actions, and maven artifacts in their repository's
+//! metadata. Git sources are checked out and built locally, with [`Git`], and custom sources by
+//! the [`SourceResolver`] registered for their scheme.
+
+mod actions;
+mod credentials;
+mod curseforge;
+mod custom;
+mod git;
+mod gitea;
+mod github;
+mod gitlab;
+mod ipfs;
+mod jenkins;
+mod maven;
+#[cfg(feature = "modrinth")]
+mod modrinth;
+mod releases;
+
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use relative_path::RelativePathBuf;
+use serde::{Deserialize, Serialize};
+use snafu::{ensure, OptionExt, Snafu};
+use tracing::{instrument, warn};
+use url::Url;
+
+use self::releases::Matcher;
+pub use self::{
+    credentials::{user_agent, Credentials, CredentialsError},
+    curseforge::{CurseforgeFile, IdentifiedFile},
+    custom::{CustomSource, SourceFuture, SourceRegistry, SourceResolver, SourceResolverError},
+};
+use crate::{
+    collection::Collection,
+    git::{Git, GitError, GitRef},
+    http::{HttpClient, HttpError, NetworkPolicy, Request, Response},
+    types::{Forge, ForgeSlug, Hashes, ManagedFile, SlugError, Source, Versions},
+};
+
+/// A concrete file to download for a managed file
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ResolvedArtifact {
+    /// Where to download the file from
+    ///
+    /// Files from the repository resolve to `file://` urls.
+    pub download_url: Url,
+    /// The name of the file upstream
+    pub filename: String,
+    /// The size of the file, if known ahead of time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// The hashes known for the file
+    #[serde(default, skip_serializing_if = "Hashes::is_empty")]
+    pub hashes: Hashes,
+    /// Other places to download the same file from, tried in order if `download_url` fails
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mirrors: Vec<Url>,
+    /// A regex for the name of the file to take out of the download, when the download is a
+    /// zip archive holding the artifact rather than the artifact itself
+    ///
+    /// The size and hashes are those of the archive, and the regex has to match exactly one
+    /// file in it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extract: Option<String>,
+    /// The ids the file is known by upstream
+    #[serde(default, skip_serializing_if = "UpstreamIds::is_empty")]
+    pub ids: UpstreamIds,
+    /// What the API said about the project the file belongs to
+    #[serde(default, skip_serializing_if = "ProjectDetails::is_empty")]
+    pub details: ProjectDetails,
+}
+
+impl ResolvedArtifact {
+    /// Names the version of the artifact, by its label or else its upstream ids
+    pub fn version_label(&self) -> Option<String> {
+        let ids = &self.ids;
+        self.details
+            .version
+            .clone()
+            .or_else(|| ids.version_id.clone())
+            .or_else(|| ids.file_id.clone())
+            .or_else(|| ids.commit.clone())
+    }
+}
+
+/// The ids that name exactly one file upstream, which a source can be pinned to
+///
+/// Which are known depends on the source: Modrinth has version ids, CurseForge file ids, and
+/// git commits; Jenkins builds, Actions runs, maven versions, and release tags are recorded as
+/// version ids. CurseForge files also record the project they belong to, which its modpacks
+/// reference files by.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct UpstreamIds {
+    /// The id of the project the file belongs to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<String>,
+    /// The id of the version the file belongs to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version_id: Option<String>,
+    /// The id of the file itself
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_id: Option<String>,
+    /// The commit the file was built from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+}
+
+impl UpstreamIds {
+    /// Returns true if no id is known
+    pub fn is_empty(&self) -> bool {
+        self.project_id.is_none()
+            && self.version_id.is_none()
+            && self.file_id.is_none()
+            && self.commit.is_none()
+    }
+}
+
+/// Display information about the project an artifact comes from, for mod lists, changelogs,
+/// and credits
+///
+/// Everything is optional, as sources know more or less about their projects, and lookups that
+/// only fill this in are allowed to fail.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct ProjectDetails {
+    /// The project's display name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The version of the artifact, as the project labels it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// The project's page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<Url>,
+    /// The names of the project's authors
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub authors: Vec<String>,
+    /// The project's license, as an SPDX identifier where the source gives one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    /// The page describing what changed in this version, where the source has one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changelog: Option<Url>,
+}
+
+impl ProjectDetails {
+    /// Returns true if nothing is known about the project
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none()
+            && self.version.is_none()
+            && self.url.is_none()
+            && self.authors.is_empty()
+            && self.license.is_none()
+            && self.changelog.is_none()
+    }
+}
+
+/// A project found by searching a platform, with a source that adds it to a pack
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SearchHit {
+    /// A source following the project's newest compatible version
+    pub source: Source,
+    /// The project's display name
+    pub name: String,
+    /// The project's short description
+    pub description: Option<String>,
+    /// How many times the project has been downloaded
+    pub downloads: u64,
+    /// The folder the project's files are installed in
+    pub folder: &'static str,
+}
+
+/// Decodes a hex hash reported by an API, ignoring it with a warning if it is malformed
+fn decode_hash<const N: usize>(algorithm: &str, hex: &str) -> Option<[u8; N]> {
+    let mut hash = [0; N];
+    match hex::decode_to_slice(hex, &mut hash) {
+        Ok(()) => Some(hash),
+        Err(error) => {
+            warn!(algorithm, hex, %error, "Ignoring malformed hash");
+            None
+        }
+    }
+}
+
+/// Parses the slug of a source
+fn parse_slug(slug: &str) -> Result<ForgeSlug, ResolveError> {
+    ForgeSlug::new(slug).map_err(|error| match error {
+        SlugError::UnknownForge { slug, .. } => ResolveError::UnsupportedForge { slug },
+        SlugError::Format { slug } => ResolveError::InvalidSlug { slug },
+    })
+}
+
+/// The base urls of the APIs the resolver talks to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoints {
+    /// The Modrinth API (`https://api.modrinth.com/v2/`)
+    pub modrinth: Url,
+    /// The CurseForge API (`https://api.curseforge.com/v1/`)
+    pub curseforge: Url,
+    /// The GitHub API (`https://api.github.com/`)
+    pub github: Url,
+    /// The GitLab API (`https://gitlab.com/api/v4/`)
+    pub gitlab: Url,
+    /// The Codeberg API (`https://codeberg.org/api/v1/`)
+    ///
+    /// Other Gitea instances are reached at the host their slugs name.
+    pub codeberg: Url,
+    /// The nightly.link service, for GitHub Actions artifacts (`https://nightly.link/`)
+    pub nightly_link: Url,
+    /// The IPFS gateway for sources that don't name one (`https://ipfs.io/`)
+    pub ipfs: Url,
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Self {
+            modrinth: Url::parse("https://api.modrinth.com/v2/").unwrap(),
+            curseforge: Url::parse("https://api.curseforge.com/v1/").unwrap(),
+            github: Url::parse("https://api.github.com/").unwrap(),
+            gitlab: Url::parse("https://gitlab.com/api/v4/").unwrap(),
+            codeberg: Url::parse("https://codeberg.org/api/v1/").unwrap(),
+            nightly_link: Url::parse("https://nightly.link/").unwrap(),
+            ipfs: Url::parse("https://ipfs.io/").unwrap(),
+        }
+    }
+}
+
+/// What to do with builds made for another loader that the pack's loader can usually run, such
+/// as Fabric builds in a Quilt pack
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CompatibilityPolicy {
+    /// Use them silently
+    Allow,
+    /// Use them, with a warning
+    #[default]
+    Warn,
+    /// Only use builds made for the pack's loader
+    Deny,
+}
+
+/// Appends path segments to an API base url
+fn api_url<'a>(base: &Url, segments: impl IntoIterator<Item = &'a str>) -> Url {
+    let mut url = base.clone();
+    url.path_segments_mut()
+        .expect("API base urls can have paths")
+        .pop_if_empty()
+        .extend(segments);
+    url
+}
+
+/// Resolves managed files into artifacts, using an [`HttpClient`] for API lookups
+///
+/// API requests carry the configured [`Credentials`] for the service they go to.
+/// Resolving many files at once can run into the APIs' rate limits; giving the resolver a
+/// [`RateLimitedClient`](crate::http::RateLimitedClient) keeps bulk operations within them.
+#[derive(Debug)]
+pub struct Resolver<C> {
+    /// The client used for API requests
+    client: C,
+    /// The directory the manifest is in, for resolving path sources
+    root: Option<PathBuf>,
+    /// The APIs to talk to
+    endpoints: Endpoints,
+    /// The keys and tokens for API-backed sources
+    credentials: Credentials,
+    /// The User-Agent sent with API requests
+    user_agent: String,
+    /// Whether builds for compatible loaders are used
+    compatibility: CompatibilityPolicy,
+    /// Whether API requests may use the network
+    network: NetworkPolicy,
+    /// Whether extra requests are made to fill in [`ProjectDetails`]
+    details: bool,
+    /// Where git sources are checked out and built, if anywhere
+    git: Option<Git>,
+    /// Whether git sources may run their builds
+    builds: bool,
+    /// The resolvers for custom sources
+    sources: SourceRegistry,
+}
+
+impl<C: HttpClient> Resolver<C> {
+    /// Creates a resolver using the public APIs, which can't resolve path sources or CurseForge
+    /// sources until given a root and key
+    pub fn new(client: C) -> Self {
+        Self {
+            client,
+            root: None,
+            endpoints: Endpoints::default(),
+            credentials: Credentials::default(),
+            user_agent: concat!("ffpack/", env!("CARGO_PKG_VERSION")).to_string(),
+            compatibility: CompatibilityPolicy::default(),
+            network: NetworkPolicy::default(),
+            details: true,
+            git: None,
+            builds: false,
+            sources: SourceRegistry::default(),
+        }
+    }
+
+    /// Sets the directory path sources are relative to, which must be absolute
+    #[must_use]
+    pub fn with_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.root = Some(root.into());
+        self
+    }
+
+    /// Sets the APIs to talk to, such as a mirror or a mock for tests
+    #[must_use]
+    pub fn with_endpoints(mut self, endpoints: Endpoints) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+
+    /// Sets the key used for the CurseForge API
+    #[must_use]
+    pub fn with_curseforge_key(mut self, key: impl Into<String>) -> Self {
+        self.credentials.curseforge = Some(key.into());
+        self
+    }
+
+    /// Sets the keys and tokens used for API-backed sources
+    #[must_use]
+    pub fn with_credentials(mut self, credentials: Credentials) -> Self {
+        self.credentials = credentials;
+        self
+    }
+
+    /// Sets the User-Agent sent with API requests, usually built with [`user_agent`]
+    #[must_use]
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Sets whether builds for compatible loaders, like Fabric builds in a Quilt pack, are used
+    #[must_use]
+    pub fn with_compatibility(mut self, compatibility: CompatibilityPolicy) -> Self {
+        self.compatibility = compatibility;
+        self
+    }
+
+    /// Sets whether API requests may use the network
+    ///
+    /// Offline, API lookups are answered from the client's cache, such as a
+    /// [`CachingClient`](crate::http::CachingClient), or fail. Url and path sources resolve
+    /// either way.
+    #[must_use]
+    pub fn with_network(mut self, network: NetworkPolicy) -> Self {
+        self.network = network;
+        self.git = self.git.map(|git| git.with_network(network));
+        self
+    }
+
+    /// Sets where git sources are checked out and built, which they can't be resolved without
+    ///
+    /// Checkouts follow the resolver's network and build settings. Resolving a git source
+    /// blocks until its build is done, or was found in the cache.
+    #[must_use]
+    pub fn with_git(mut self, git: Git) -> Self {
+        self.git = Some(git.with_network(self.network).with_builds(self.builds));
+        self
+    }
+
+    /// Sets whether git sources may run their builds, which they may not by default
+    ///
+    /// A build runs the command the manifest gives, so this should only be allowed for packs
+    /// whose authors are trusted. Without it, git sources only resolve to builds already cached.
+    #[must_use]
+    pub fn with_builds(mut self, builds: bool) -> Self {
+        self.builds = builds;
+        self.git = self.git.map(|git| git.with_builds(builds));
+        self
+    }
+
+    /// Sets the resolvers for custom sources, which fail to resolve without one for their
+    /// scheme
+    #[must_use]
+    pub fn with_sources(mut self, sources: SourceRegistry) -> Self {
+        self.sources = sources;
+        self
+    }
+
+    /// Sets whether extra API requests are made just to fill in [`ProjectDetails`], which is on
+    /// by default
+    ///
+    /// Only Modrinth needs them, for the project's name and authors; whatever the lookups
+    /// that find the file return is used either way.
+    #[must_use]
+    pub fn with_details(mut self, details: bool) -> Self {
+        self.details = details;
+        self
+    }
+
+    /// Returns the client used for API requests
+    pub fn client(&self) -> &C {
+        &self.client
+    }
+
+    /// Sends an API request, as the network policy allows
+    async fn get(&self, request: Request) -> Result<Response<C::Body>, HttpError> {
+        let request = self.network.prepare(&self.client, request)?;
+        self.client.get(request).await
+    }
+
+    /// Builds a request to an API, identifying ourselves as the APIs ask
+    fn request(&self, url: Url) -> Request {
+        Request::new(url).with_header("User-Agent", &self.user_agent)
+    }
+
+    /// Looks a file up on Modrinth by its SHA-1 digest, returning a source pinned to the
+    /// version it was published in, or nothing if Modrinth doesn't host it
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the API can't be reached
+    #[cfg(feature = "modrinth")]
+    #[instrument(skip(self, sha1), err)]
+    pub async fn identify_modrinth(&self, sha1: &[u8; 20]) -> Result<Option<Source>, ResolveError> {
+        modrinth::identify(self, &hex::encode(sha1)).await
+    }
+
+    /// Looks a file up on CurseForge by the ids of its project and itself, returning a source
+    /// pinned to it and where it is installed
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no CurseForge key, or the project or file can't be fetched
+    #[instrument(skip(self), err)]
+    pub async fn identify_curseforge(
+        &self,
+        project_id: u64,
+        file_id: u64,
+    ) -> Result<IdentifiedFile, ResolveError> {
+        curseforge::identify(self, project_id, file_id).await
+    }
+
+    /// Looks files up on CurseForge by their [fingerprints](crate::hash::curseforge_fingerprint),
+    /// returning what was found by fingerprint
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no CurseForge key, or the API can't be reached
+    #[instrument(skip_all, fields(files = fingerprints.len()), err)]
+    pub async fn identify_curseforge_fingerprints(
+        &self,
+        fingerprints: &[u32],
+    ) -> Result<BTreeMap<u32, IdentifiedFile>, ResolveError> {
+        curseforge::identify_fingerprints(self, fingerprints).await
+    }
+
+    /// Searches Modrinth for mods matching `query` that have versions for `versions`, most
+    /// relevant first
+    ///
+    /// Builds for other loaders the pack's loader can run are included unless the
+    /// [`CompatibilityPolicy`] denies them.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the API can't be reached
+    #[cfg(feature = "modrinth")]
+    #[instrument(skip(self, versions), err)]
+    pub async fn search_modrinth(
+        &self,
+        query: &str,
+        versions: &Versions,
+        limit: usize,
+    ) -> Result<Vec<SearchHit>, ResolveError> {
+        modrinth::search(self, query, versions, limit).await
+    }
+
+    /// Finds the projects in the Modrinth collection with the id `id`, leaving out the kinds
+    /// packs can't carry, like modpacks
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the collection doesn't exist, or the API can't be reached
+    #[cfg(feature = "modrinth")]
+    #[instrument(skip(self), err)]
+    pub async fn modrinth_collection(&self, id: &str) -> Result<Collection, ResolveError> {
+        modrinth::collection(self, id).await
+    }
+
+    /// Fails to find a Modrinth collection, since ffpack was built without Modrinth support
+    ///
+    /// # Errors
+    ///
+    /// Always returns [`ResolveError::FeatureDisabled`]
+    #[cfg(not(feature = "modrinth"))]
+    #[allow(clippy::unused_async)]
+    pub async fn modrinth_collection(&self, _id: &str) -> Result<Collection, ResolveError> {
+        FeatureDisabledSnafu {
+            kind: "Modrinth",
+            feature: "modrinth",
+        }
+        .fail()
+    }
+
+    /// Searches CurseForge for mods matching `query` that have files for `versions`, most
+    /// popular first
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no CurseForge key, or the API can't be reached
+    #[instrument(skip(self, versions), err)]
+    pub async fn search_curseforge(
+        &self,
+        query: &str,
+        versions: &Versions,
+        limit: usize,
+    ) -> Result<Vec<SearchHit>, ResolveError> {
+        curseforge::search(self, query, versions, limit).await
+    }
+
+    /// Resolves a managed file into a concrete artifact for the given versions of the game and
+    /// loader
+    #[instrument(skip(self, file, versions), fields(path = %file.path), err)]
+    pub async fn resolve(
+        &self,
+        file: &ManagedFile,
+        versions: &Versions,
+    ) -> Result<ResolvedArtifact, ResolveError> {
+        match &file.source {
+            Source::Url { url, mirrors, .. } => Ok(ResolvedArtifact {
+                download_url: url.clone(),
+                mirrors: mirrors.clone(),
+                extract: None,
+                ids: UpstreamIds::default(),
+                filename: file.filename.clone(),
+                size: None,
+                hashes: file.source.hashes().unwrap_or_default(),
+                details: ProjectDetails::default(),
+            }),
+            Source::Path { path, .. } => {
+                let root = self
+                    .root
+                    .as_ref()
+                    .context(NoRootSnafu { path: path.clone() })?;
+                // Packs from elsewhere mustn't be able to copy out whatever is next to them
+                let normalized = path.normalize();
+                ensure!(
+                    !normalized.starts_with(".."),
+                    PathOutsideRootSnafu { path: path.clone() }
+                );
+                let local = normalized.to_path(root);
+                let download_url = Url::from_file_path(&local)
+                    .ok()
+                    .context(InvalidRootSnafu { root: root.clone() })?;
+                Ok(ResolvedArtifact {
+                    download_url,
+                    mirrors: Vec::new(),
+                    extract: None,
+                    ids: UpstreamIds::default(),
+                    filename: file.filename.clone(),
+                    size: fs::metadata(&local).ok().map(|metadata| metadata.len()),
+                    hashes: file.source.hashes().unwrap_or_default(),
+                    details: ProjectDetails::default(),
+                })
+            }
+            Source::Ipfs { cid, gateway, .. } => ipfs::resolve(
+                self,
+                cid,
+                gateway.as_ref(),
+                &file.source.hashes().unwrap_or_default(),
+                &file.filename,
+            ),
+            #[cfg(feature = "modrinth")]
+            Source::Modrinth { slug, version_id } => {
+                modrinth::resolve(self, slug, version_id.as_deref(), versions).await
+            }
+            #[cfg(not(feature = "modrinth"))]
+            Source::Modrinth { .. } => FeatureDisabledSnafu {
+                kind: "Modrinth",
+                feature: "modrinth",
+            }
+            .fail(),
+            Source::Curseforge { slug, file_id } => {
+                curseforge::resolve(self, slug, *file_id, versions).await
+            }
+            Source::GithubActions {
+                slug,
+                workflow,
+                artifact,
+                file_regex,
+                branch,
+                run_id,
+                nightly_link,
+            } => {
+                let slug = parse_slug(slug)?;
+                ensure!(
+                    slug.forge == Forge::Github,
+                    UnsupportedForgeSnafu {
+                        slug: slug.to_string()
+                    }
+                );
+                let run = actions::Run {
+                    workflow,
+                    branch: branch.as_deref(),
+                    id: *run_id,
+                };
+                actions::resolve(
+                    self,
+                    &slug,
+                    &run,
+                    artifact,
+                    file_regex.as_deref(),
+                    *nightly_link,
+                )
+                .await
+            }
+            Source::Jenkins {
+                base_url,
+                job,
+                artifact_regex,
+                build,
+            } => jenkins::resolve(self, base_url, job, artifact_regex, *build).await,
+            Source::Maven {
+                repository,
+                group,
+                artifact,
+                version,
+                classifier,
+            } => {
+                maven::resolve(
+                    self,
+                    repository,
+                    group,
+                    artifact,
+                    version,
+                    classifier.as_deref(),
+                )
+                .await
+            }
+            Source::SlugReleases {
+                slug,
+                artifact_regex,
+                release_regex,
+            } => {
+                let slug = parse_slug(slug)?;
+                let matcher = Matcher::new(&slug, artifact_regex, release_regex.as_deref())?;
+                match slug.forge {
+                    Forge::Github => github::resolve_release(self, &slug, &matcher).await,
+                    Forge::Gitlab => gitlab::resolve_release(self, &slug, &matcher).await,
+                    Forge::Codeberg | Forge::Gitea { .. } => {
+                        gitea::resolve_release(self, &slug, &matcher).await
+                    }
+                }
+            }
+            Source::Git {
+                url,
+                branch,
+                tag,
+                rev,
+                build,
+            } => {
+                let reference = GitRef::new(branch.as_deref(), tag.as_deref(), rev.as_deref());
+                git::resolve(self, url, &reference, build.as_ref())
+            }
+            Source::Custom { url, params } => {
+                let scheme = file.source.custom_scheme().unwrap_or_default();
+                let resolver = self
+                    .sources
+                    .get(scheme)
+                    .context(UnknownSchemeSnafu { scheme })?;
+                let source = CustomSource {
+                    url,
+                    scheme,
+                    params,
+                    file,
+                    versions,
+                };
+                // Resolvers' errors are boxed, which context selectors don't take
+                resolver
+                    .resolve(source)
+                    .await
+                    .map_err(|source| ResolveError::Custom {
+                        scheme: scheme.to_string(),
+                        source,
+                    })
+            }
+            Source::Slug { .. } => UnsupportedSnafu { kind: "repository" }.fail(),
+        }
+    }
+}
+
+/// Error that occurs while resolving a managed file
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum ResolveError {
+    /// An API request failed
+    #[snafu(display("API request failed: {}", source))]
+    Http {
+        /// The underlying error
+        source: HttpError,
+    },
+    /// A path source was resolved without a root directory
+    #[snafu(display("Can't resolve {} without knowing where the manifest is", path))]
+    NoRoot {
+        /// The path being resolved
+        path: RelativePathBuf,
+    },
+    /// A path source leads out of the directory the manifest is in
+    #[snafu(display("{} is outside the pack's directory", path))]
+    PathOutsideRoot {
+        /// The path being resolved
+        path: RelativePathBuf,
+    },
+    /// The root directory couldn't be turned into a url
+    #[snafu(display("Manifest directory {} isn't an absolute path", root.display()))]
+    InvalidRoot {
+        /// The root directory
+        root: PathBuf,
+    },
+    /// No version of a project matches the pack
+    #[snafu(display("No version of {} supports {}", project, versions))]
+    NoMatchingVersion {
+        /// The project being resolved
+        project: String,
+        /// The versions searched for
+        versions: String,
+    },
+    /// A project couldn't be found
+    #[snafu(display("No project found for {}", project))]
+    NoProject {
+        /// The project being resolved
+        project: String,
+    },
+    /// Credentials are needed for an API
+    #[snafu(display("An API key is needed to resolve files from {}", service))]
+    MissingCredentials {
+        /// The service that needs credentials
+        service: &'static str,
+    },
+    /// An API's rate limit ran out
+    #[snafu(display("Ran out of requests to the {} API for now", service))]
+    RateLimited {
+        /// The service whose limit ran out
+        service: &'static str,
+        /// Whether requests were made with credentials, which usually get a higher limit
+        authenticated: bool,
+    },
+    /// The author doesn't allow third party downloads of the file
+    ///
+    /// Exporters can still reference the file by its ids, and a copy downloaded by hand can be
+    /// checked against its fingerprint.
+    #[snafu(display("{} can't be downloaded outside of the official launcher", project))]
+    DistributionDisabled {
+        /// The project being resolved
+        project: String,
+        /// The file that would have been downloaded
+        file: Box<CurseforgeFile>,
+    },
+    /// A slug wasn't of the form `forge:owner/project`
+    #[snafu(display("Invalid slug: {}", slug))]
+    InvalidSlug {
+        /// The slug
+        slug: String,
+    },
+    /// A slug referred to a forge that isn't supported
+    #[snafu(display("Unsupported forge in slug: {}", slug))]
+    UnsupportedForge {
+        /// The slug
+        slug: String,
+    },
+    /// A regex in the source was invalid
+    #[snafu(display("Invalid regex {}: {}", regex, source))]
+    InvalidRegex {
+        /// The regex
+        regex: String,
+        /// The underlying error
+        source: regex::Error,
+    },
+    /// A maven version range couldn't be parsed
+    #[snafu(display("Invalid version range {}", range))]
+    InvalidVersionRange {
+        /// The range
+        range: String,
+    },
+    /// An IPFS source's CID couldn't be parsed
+    #[snafu(display("Invalid CID {}", cid))]
+    InvalidCid {
+        /// The CID
+        cid: String,
+    },
+    /// An IPFS source records a hash that differs from the one its CID names
+    #[snafu(display("The hashes recorded for {} differ from the CID", cid))]
+    CidMismatch {
+        /// The CID
+        cid: String,
+    },
+    /// More than one artifact matched
+    #[snafu(display(
+        "Artifact regex matches {} artifacts of {} {}: {}",
+        matches.len(),
+        project,
+        release,
+        matches.join(", ")
+    ))]
+    AmbiguousArtifact {
+        /// The project being resolved
+        project: String,
+        /// The release with the matches
+        release: String,
+        /// The names of the matching artifacts
+        matches: Vec<String>,
+    },
+    /// Checking out or building a git source failed
+    #[snafu(display("Failed to build from git: {}", source))]
+    Git {
+        /// The underlying error
+        source: GitError,
+    },
+    /// A git source was resolved without anywhere to check it out
+    #[snafu(display("Can't check out {} without a directory to keep checkouts in", url))]
+    NoGit {
+        /// The repository
+        url: Url,
+    },
+    /// A git source doesn't say how to build the file it provides
+    #[snafu(display("Git source {} has no build to take a file from", url))]
+    NoBuild {
+        /// The repository
+        url: Url,
+    },
+    /// The source kind needs a feature ffpack was built without
+    #[snafu(display("{} sources need ffpack built with the {} feature", kind, feature))]
+    FeatureDisabled {
+        /// The kind of source
+        kind: &'static str,
+        /// The cargo feature that would enable it
+        feature: &'static str,
+    },
+    /// No resolver is registered for a custom source's scheme
+    #[snafu(display("No resolver is registered for {} sources", scheme))]
+    UnknownScheme {
+        /// The scheme
+        scheme: String,
+    },
+    /// The resolver of a custom source failed
+    #[snafu(display("Resolving a {} source failed: {}", scheme, source))]
+    Custom {
+        /// The scheme
+        scheme: String,
+        /// The resolver's error
+        source: SourceResolverError,
+    },
+    /// The source kind can't be resolved into a single artifact
+    #[snafu(display("{} sources can't be resolved into an artifact yet", kind))]
+    Unsupported {
+        /// The kind of source
+        kind: &'static str,
+    },
+}
+
+impl ResolveError {
+    /// Suggests how the user might fix the problem, if there is a likely fix
+    pub fn suggestion(&self) -> Option<String> {
+        match self {
+            ResolveError::Http { source } => source.suggestion(),
+            ResolveError::NoMatchingVersion { project, .. } => Some(format!(
+                "Check that {project} supports the pack's minecraft version and loader, or take \
+                 the file from another source"
+            )),
+            ResolveError::NoProject { .. } => {
+                Some("Check the slug against the project's page".into())
+            }
+            ResolveError::MissingCredentials { service } => Some(format!(
+                "Set FFPACK_{}_TOKEN to an API key for {service}, or add one to the credentials \
+                 file",
+                service.to_uppercase()
+            )),
+            ResolveError::RateLimited {
+                service,
+                authenticated: false,
+            } => Some(format!(
+                "Set FFPACK_{}_TOKEN to an API token for {service} to be allowed more requests",
+                service.to_uppercase()
+            )),
+            ResolveError::RateLimited { .. } => {
+                Some("Wait for the limit to reset, usually within the hour".into())
+            }
+            ResolveError::DistributionDisabled { file, .. } => Some(match &file.page {
+                Some(page) => format!(
+                    "Download {} by hand from {page}, and add it to the repository as a path \
+                     source",
+                    file.filename
+                ),
+                None => format!(
+                    "Download {} by hand, and add it to the repository as a path source",
+                    file.filename
+                ),
+            }),
+            ResolveError::InvalidSlug { .. } => {
+                Some("Slugs look like github:owner/repository".into())
+            }
+            ResolveError::UnsupportedForge { .. } => Some(
+                "Slugs can point at github:, gitlab:, codeberg:, or gitea:<host>: repositories"
+                    .into(),
+            ),
+            ResolveError::InvalidVersionRange { .. } => Some(
+                "Version ranges look like [1.0,2.0), or give an exact version, latest, or release"
+                    .into(),
+            ),
+            ResolveError::InvalidCid { .. } => Some(
+                "Copy the CID from the IPFS client or gateway url, like bafy... or Qm...".into(),
+            ),
+            ResolveError::CidMismatch { .. } => {
+                Some("Check that the CID and the hashes are of the same file".into())
+            }
+            ResolveError::AmbiguousArtifact { .. } => {
+                Some("Make the artifact regex specific enough to match only one of these".into())
+            }
+            ResolveError::FeatureDisabled { feature, .. } => Some(format!(
+                "Reinstall ffpack with `--features {feature}`, or use a url source for this file"
+            )),
+            ResolveError::UnknownScheme { scheme } => Some(format!(
+                "Resolve this pack with the program that knows {scheme} sources, or use a url \
+                 source for this file"
+            )),
+            ResolveError::Unsupported { .. } => {
+                Some("Use a url or path source for this file for now".into())
+            }
+            ResolveError::Git { source } => source.suggestion(),
+            ResolveError::NoBuild { .. } => Some(
+                "Add a build to the source, naming the command to run and the artifact it \
+                 produces"
+                    .into(),
+            ),
+            ResolveError::PathOutsideRoot { .. } => {
+                Some("Move the file into the pack's directory, next to the manifest".to_string())
+            }
+            ResolveError::NoRoot { .. }
+            | ResolveError::NoGit { .. }
+            | ResolveError::InvalidRoot { .. }
+            | ResolveError::InvalidRegex { .. }
+            | ResolveError::Custom { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::http::testing::{block_on, MockClient};
+
+    // Url and path sources pass straight through without touching the network
+    #[test]
+    fn passthrough() {
+        let resolver = Resolver::new(MockClient::default());
+        let artifact =
+            block_on(resolver.resolve(&ManagedFile::default(), &Versions::default())).unwrap();
+        assert_eq!(
+            artifact.download_url.as_str(),
+            "https://example.org/mods/MyAwesomeMod-1.2.3.jar"
+        );
+        assert_eq!(artifact.filename, "My Awesome Mod.jar");
+        assert_eq!(artifact.hashes.blake3, Some([0; 32]));
+        let file = ManagedFile {
+            source: Source::Path {
+                path: RelativePathBuf::from("jars/mod.jar"),
+                blake3: [1; 32],
+                hashes: Hashes::default(),
+            },
+            ..ManagedFile::default()
+        };
+        assert!(matches!(
+            block_on(resolver.resolve(&file, &Versions::default())),
+            Err(ResolveError::NoRoot { .. })
+        ));
+        let root = std::env::temp_dir().join("pack");
+        let resolver = resolver.with_root(&root);
+        let artifact = block_on(resolver.resolve(&file, &Versions::default())).unwrap();
+        assert_eq!(
+            artifact.download_url.to_file_path().unwrap(),
+            root.join("jars").join("mod.jar")
+        );
+        assert!(resolver.client().requests.lock().unwrap().is_empty());
+    }
+
+    // Path sources are hashed from the pack's directory, and can't reach outside it
+    #[test]
+    fn paths() {
+        let root = std::env::temp_dir().join(format!("ffpack-paths-{}", std::process::id()));
+        fs::create_dir_all(root.join("local")).unwrap();
+        fs::write(root.join("local/config.jar"), b"private").unwrap();
+        let source = Source::path_with_hash(&root, "local/config.jar").unwrap();
+        assert_eq!(
+            source.hashes().unwrap().blake3,
+            Some(crate::hash::blake3(b"private"))
+        );
+        assert!(Source::path_with_hash(&root, "local/missing.jar").is_err());
+
+        let resolver = Resolver::new(MockClient::default()).with_root(&root);
+        let resolve = |source: Source| {
+            let file = ManagedFile {
+                source,
+                ..ManagedFile::default()
+            };
+            block_on(resolver.resolve(&file, &Versions::default()))
+        };
+        let artifact = resolve(source).unwrap();
+        assert_eq!(artifact.size, Some(7));
+        let sneaky = Source::Path {
+            path: RelativePathBuf::from("local/../../secret.txt"),
+            blake3: [0; 32],
+            hashes: Hashes::default(),
+        };
+        assert!(matches!(
+            resolve(sneaky),
+            Err(ResolveError::PathOutsideRoot { .. })
+        ));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    // Suggestions come through from the request that failed
+    #[cfg(feature = "modrinth")]
+    #[test]
+    fn suggestion() {
+        let file = ManagedFile {
+            source: Source::Modrinth {
+                slug: "sodium".into(),
+                version_id: None,
+            },
+            ..ManagedFile::default()
+        };
+        let resolver = Resolver::new(MockClient::default());
+        let error = block_on(resolver.resolve(&file, &Versions::default())).unwrap_err();
+        assert!(matches!(error, ResolveError::Http { .. }));
+        assert!(error
+            .suggestion()
+            .unwrap()
+            .contains("still exists upstream"));
+        assert_eq!(
+            ResolveError::NoRoot {
+                path: RelativePathBuf::from("jars/mod.jar")
+            }
+            .suggestion(),
+            None
+        );
+    }
+}
@@ -0,0 +1,297 @@
+//! Resolution of [`Source::GithubActions`](crate::types::Source::GithubActions) through the
+//! GitHub Actions API
+//!
+//! The newest successful run of the workflow is used unless the source pins one. Its artifact
+//! is downloaded from GitHub with the GitHub token, or from nightly.link without one, and the
+//! file is taken out of the zip afterwards.
+
+use regex::Regex;
+use serde::Deserialize;
+use snafu::{ensure, OptionExt, ResultExt};
+use url::Url;
+
+use super::{
+    api_url, decode_hash, github::fetch, HttpSnafu, InvalidRegexSnafu, MissingCredentialsSnafu,
+    NoMatchingVersionSnafu, ProjectDetails, ResolveError, ResolvedArtifact, Resolver, UpstreamIds,
+};
+use crate::{
+    http::HttpClient,
+    types::{ForgeSlug, Hashes},
+};
+
+/// The regex files are taken out of artifacts with when a source doesn't give one
+const DEFAULT_FILE_REGEX: &str = r"\.jar$";
+
+/// Which runs of a workflow a source takes its artifact from
+pub(super) struct Run<'a> {
+    /// The file name of the workflow
+    pub(super) workflow: &'a str,
+    /// The branch the run has to be on
+    pub(super) branch: Option<&'a str>,
+    /// The id of a pinned run
+    pub(super) id: Option<u64>,
+}
+
+/// The runs of a workflow, as returned by `/repos/{owner}/{repo}/actions/workflows/{id}/runs`
+#[derive(Deserialize)]
+struct WorkflowRuns {
+    /// The runs, newest first
+    workflow_runs: Vec<WorkflowRun>,
+}
+
+/// A run of a workflow
+#[derive(Deserialize)]
+struct WorkflowRun {
+    /// The id of the run
+    id: u64,
+    /// The number of the run among the workflow's runs
+    run_number: u64,
+    /// The run's page
+    html_url: Url,
+}
+
+/// The artifacts of a run, as returned by `/repos/{owner}/{repo}/actions/runs/{id}/artifacts`
+#[derive(Deserialize)]
+struct Artifacts {
+    /// The artifacts
+    artifacts: Vec<Artifact>,
+}
+
+/// An artifact uploaded by a run
+#[derive(Deserialize)]
+struct Artifact {
+    /// The name it was uploaded under
+    name: String,
+    /// The size of the zip
+    size_in_bytes: u64,
+    /// Whether GitHub deleted the artifact after its retention period
+    #[serde(default)]
+    expired: bool,
+    /// Where to download the zip, with a token
+    archive_download_url: Url,
+    /// The hash of the zip, as `algorithm:hex`
+    digest: Option<String>,
+}
+
+/// Resolves the artifact named `name` of the newest successful run of a workflow, or of the
+/// pinned run
+pub(super) async fn resolve<C: HttpClient>(
+    resolver: &Resolver<C>,
+    slug: &ForgeSlug,
+    run: &Run<'_>,
+    name: &str,
+    file_regex: Option<&str>,
+    nightly_link: bool,
+) -> Result<ResolvedArtifact, ResolveError> {
+    // The regex is checked now rather than once the zip is downloaded
+    let file_regex = file_regex.unwrap_or(DEFAULT_FILE_REGEX);
+    Regex::new(file_regex).context(InvalidRegexSnafu { regex: file_regex })?;
+    ensure!(
+        nightly_link || resolver.credentials.github.is_some(),
+        MissingCredentialsSnafu { service: "GitHub" }
+    );
+    let project = slug.to_string();
+    let repo = ["repos", &slug.owner, &slug.project, "actions"];
+    let versions = match (run.id, run.branch) {
+        (Some(id), _) => format!("run {id} of {}", run.workflow),
+        (None, Some(branch)) => format!("a successful run of {} on {branch}", run.workflow),
+        (None, None) => format!("a successful run of {}", run.workflow),
+    };
+
+    let found = if let Some(id) = run.id {
+        let url = api_url(
+            &resolver.endpoints.github,
+            repo.into_iter().chain(["runs", &id.to_string()]),
+        );
+        let response = fetch(resolver, url).await?;
+        Some(response.json().await.context(HttpSnafu)?)
+    } else {
+        let mut url = api_url(
+            &resolver.endpoints.github,
+            repo.into_iter().chain(["workflows", run.workflow, "runs"]),
+        );
+        url.query_pairs_mut()
+            .append_pair("status", "success")
+            .append_pair("per_page", "1");
+        if let Some(branch) = run.branch {
+            url.query_pairs_mut().append_pair("branch", branch);
+        }
+        let response = fetch(resolver, url).await?;
+        let runs: WorkflowRuns = response.json().await.context(HttpSnafu)?;
+        runs.workflow_runs.into_iter().next()
+    };
+    let found: WorkflowRun = found.context(NoMatchingVersionSnafu {
+        project: &project,
+        versions: &versions,
+    })?;
+
+    let mut url = api_url(
+        &resolver.endpoints.github,
+        repo.into_iter()
+            .chain(["runs", &found.id.to_string(), "artifacts"]),
+    );
+    url.query_pairs_mut().append_pair("name", name);
+    let response = fetch(resolver, url).await?;
+    let artifacts: Artifacts = response.json().await.context(HttpSnafu)?;
+    let artifact = artifacts
+        .artifacts
+        .into_iter()
+        .find(|artifact| artifact.name == name && !artifact.expired)
+        .context(NoMatchingVersionSnafu {
+            project: &project,
+            versions: format!("{versions} with an artifact named {name}"),
+        })?;
+
+    let download_url = if nightly_link {
+        api_url(
+            &resolver.endpoints.nightly_link,
+            [
+                &*slug.owner,
+                &slug.project,
+                "actions",
+                "runs",
+                &found.id.to_string(),
+                &format!("{name}.zip"),
+            ],
+        )
+    } else {
+        artifact.archive_download_url
+    };
+    let sha256 = artifact
+        .digest
+        .as_deref()
+        .and_then(|digest| digest.strip_prefix("sha256:"))
+        .and_then(|hex| decode_hash("sha256", hex));
+    Ok(ResolvedArtifact {
+        download_url,
+        filename: artifact.name,
+        size: Some(artifact.size_in_bytes),
+        hashes: Hashes {
+            sha256,
+            ..Hashes::default()
+        },
+        mirrors: Vec::new(),
+        extract: Some(file_regex.to_string()),
+        ids: UpstreamIds {
+            version_id: Some(found.id.to_string()),
+            ..UpstreamIds::default()
+        },
+        details: ProjectDetails {
+            name: Some(slug.project.clone()),
+            version: Some(format!("run {}", found.run_number)),
+            url: Some(found.html_url),
+            authors: Vec::new(),
+            license: None,
+            changelog: None,
+        },
+    })
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::{
+        http::testing::{block_on, MockClient},
+        resolve::Credentials,
+        types::{ManagedFile, Source, Versions},
+    };
+
+    /// The api of the example repository's actions
+    const API: &str = "https://api.github.com/repos/o/r/actions";
+
+    /// Returns a run of the example workflow
+    fn run(id: u64) -> String {
+        format!(
+            r#"{{"id": {id}, "run_number": {}, "head_sha": "abc",
+            "html_url": "https://github.com/o/r/actions/runs/{id}"}}"#,
+            id / 100
+        )
+    }
+
+    /// Returns the artifacts of a run of the example workflow
+    fn artifacts(id: u64, expired: bool) -> String {
+        format!(
+            r#"{{"total_count": 1, "artifacts": [{{"name": "mod", "size_in_bytes": 42,
+            "expired": {expired},
+            "archive_download_url": "{API}/artifacts/{id}/zip",
+            "digest": "sha256:{}"}}]}}"#,
+            "02".repeat(32)
+        )
+    }
+
+    // The newest successful run's artifact is downloaded from GitHub with a token, or from
+    // nightly.link without one
+    #[test]
+    fn artifacts_of_runs() {
+        let client = MockClient::default()
+            .with(
+                &format!("{API}/workflows/build.yml/runs?status=success&per_page=1"),
+                format!(r#"{{"workflow_runs": [{}]}}"#, run(1200)),
+            )
+            .with(
+                &format!("{API}/workflows/build.yml/runs?status=success&per_page=1&branch=dev"),
+                r#"{"workflow_runs": []}"#,
+            )
+            .with(&format!("{API}/runs/700"), run(700))
+            .with(
+                &format!("{API}/runs/1200/artifacts?name=mod"),
+                artifacts(1200, false),
+            )
+            .with(
+                &format!("{API}/runs/700/artifacts?name=mod"),
+                artifacts(700, true),
+            );
+        let resolver = Resolver::new(client);
+        let resolve = |resolver: &Resolver<MockClient>,
+                       branch: Option<&str>,
+                       run_id: Option<u64>,
+                       nightly_link: bool| {
+            let file = ManagedFile {
+                source: Source::GithubActions {
+                    slug: "github:o/r".into(),
+                    workflow: "build.yml".into(),
+                    artifact: "mod".into(),
+                    file_regex: None,
+                    branch: branch.map(Into::into),
+                    run_id,
+                    nightly_link,
+                },
+                ..ManagedFile::default()
+            };
+            block_on(resolver.resolve(&file, &Versions::default()))
+        };
+
+        let artifact = resolve(&resolver, None, None, true).unwrap();
+        assert_eq!(
+            artifact.download_url.as_str(),
+            "https://nightly.link/o/r/actions/runs/1200/mod.zip"
+        );
+        assert_eq!(artifact.extract.as_deref(), Some(DEFAULT_FILE_REGEX));
+        assert_eq!(artifact.size, Some(42));
+        assert_eq!(artifact.hashes.sha256, Some([2; 32]));
+        assert_eq!(artifact.details.version.as_deref(), Some("run 12"));
+        assert!(matches!(
+            resolve(&resolver, None, None, false),
+            Err(ResolveError::MissingCredentials { .. })
+        ));
+        assert!(matches!(
+            resolve(&resolver, Some("dev"), None, true),
+            Err(ResolveError::NoMatchingVersion { .. })
+        ));
+        // Expired artifacts can't be downloaded anymore
+        assert!(matches!(
+            resolve(&resolver, None, Some(700), true),
+            Err(ResolveError::NoMatchingVersion { .. })
+        ));
+
+        let resolver = resolver.with_credentials(Credentials {
+            github: Some("token".into()),
+            ..Credentials::default()
+        });
+        let artifact = resolve(&resolver, None, None, false).unwrap();
+        assert_eq!(
+            artifact.download_url.as_str(),
+            format!("{API}/artifacts/1200/zip")
+        );
+    }
+}
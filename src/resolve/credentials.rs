@@ -0,0 +1,167 @@
+//! API credentials and the User-Agent the resolver identifies itself with
+
+use std::{
+    fmt::Debug,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use snafu::{ResultExt, Snafu};
+
+use crate::types::Metadata;
+
+/// The keys and tokens used for API-backed sources
+///
+/// None of these are needed for public Modrinth and GitHub lookups, but they raise rate limits,
+/// and CurseForge can't be used at all without a key. Values are never shown by `Debug`.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Credentials {
+    /// The CurseForge API key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub curseforge: Option<String>,
+    /// A GitHub personal access token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub github: Option<String>,
+    /// A Modrinth personal access token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modrinth: Option<String>,
+}
+
+impl Credentials {
+    /// The variable holding the CurseForge API key
+    pub const CURSEFORGE_VAR: &'static str = "FFPACK_CURSEFORGE_TOKEN";
+    /// The variable holding the GitHub token, with the conventional `GITHUB_TOKEN` as a fallback
+    pub const GITHUB_VAR: &'static str = "FFPACK_GITHUB_TOKEN";
+    /// The variable holding the Modrinth token
+    pub const MODRINTH_VAR: &'static str = "FFPACK_MODRINTH_TOKEN";
+
+    /// Reads credentials from the environment
+    pub fn from_env() -> Self {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    /// Reads credentials from variables looked up by `var`
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Self {
+        let lookup = |name: &str| var(name).filter(|value| !value.trim().is_empty());
+        Self {
+            curseforge: lookup(Self::CURSEFORGE_VAR),
+            github: lookup(Self::GITHUB_VAR).or_else(|| lookup("GITHUB_TOKEN")),
+            modrinth: lookup(Self::MODRINTH_VAR),
+        }
+    }
+
+    /// Reads credentials from a JSON file with `curseforge`, `github`, and `modrinth` keys
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be read or is malformed
+    pub fn from_file(path: &Path) -> Result<Self, CredentialsError> {
+        let json = std::fs::read(path).context(IoSnafu { path })?;
+        serde_json::from_slice(&json).context(ParseSnafu { path })
+    }
+
+    /// Fills in whatever is missing here from `fallback`, such as file credentials under
+    /// environment ones
+    #[must_use]
+    pub fn or(self, fallback: Self) -> Self {
+        Self {
+            curseforge: self.curseforge.or(fallback.curseforge),
+            github: self.github.or(fallback.github),
+            modrinth: self.modrinth.or(fallback.modrinth),
+        }
+    }
+}
+
+impl Debug for Credentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        /// Shows whether a credential is set, without its value
+        fn redact(value: Option<&String>) -> &'static str {
+            if value.is_some() {
+                "<redacted>"
+            } else {
+                "<unset>"
+            }
+        }
+        f.debug_struct("Credentials")
+            .field("curseforge", &redact(self.curseforge.as_ref()))
+            .field("github", &redact(self.github.as_ref()))
+            .field("modrinth", &redact(self.modrinth.as_ref()))
+            .finish()
+    }
+}
+
+/// Builds a User-Agent naming both ffpack and the pack, as the Modrinth API asks of clients
+///
+/// The pack's homepage or source link is included as a contact when it has one.
+pub fn user_agent(metadata: &Metadata) -> String {
+    // Keep the name a single token, free of the characters that delimit the comment
+    let name = metadata
+        .name(None)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+        .replace(['(', ')', '/', ';', '\\', '"'], "");
+    let mut agent = format!(
+        "ffpack/{} ({}/{}",
+        env!("CARGO_PKG_VERSION"),
+        name,
+        metadata.version()
+    );
+    let links = metadata.links();
+    if let Some(contact) = links.homepage.as_ref().or(links.source.as_ref()) {
+        agent.push_str("; +");
+        agent.push_str(contact.as_str());
+    }
+    agent.push(')');
+    agent
+}
+
+/// Error that occurs while loading credentials
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum CredentialsError {
+    /// The file couldn't be read
+    #[snafu(display("Failed to read {}: {}", path.display(), source))]
+    Io {
+        /// The file's path
+        path: PathBuf,
+        /// The underlying error
+        source: std::io::Error,
+    },
+    /// The file was malformed
+    #[snafu(display("Invalid credentials in {}: {}", path.display(), source))]
+    Parse {
+        /// The file's path
+        path: PathBuf,
+        /// The underlying error
+        source: serde_json::Error,
+    },
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    // Environment variables win over the file, fallbacks apply, and values stay out of logs
+    #[test]
+    fn credentials() {
+        let env = Credentials::from_vars(|name| match name {
+            "FFPACK_CURSEFORGE_TOKEN" => Some("cf-env".into()),
+            "GITHUB_TOKEN" => Some("gh-env".into()),
+            "FFPACK_MODRINTH_TOKEN" => Some(" ".into()),
+            _ => None,
+        });
+        let file: Credentials =
+            serde_json::from_str(r#"{"curseforge": "cf-file", "modrinth": "mr-file"}"#).unwrap();
+        let credentials = env.or(file);
+        assert_eq!(credentials.curseforge.as_deref(), Some("cf-env"));
+        assert_eq!(credentials.github.as_deref(), Some("gh-env"));
+        assert_eq!(credentials.modrinth.as_deref(), Some("mr-file"));
+        let debug = format!("{credentials:?}");
+        assert!(!debug.contains("env") && !debug.contains("file"));
+
+        let agent = user_agent(&Metadata::default());
+        assert!(agent.starts_with(concat!("ffpack/", env!("CARGO_PKG_VERSION"), " (")));
+        assert!(agent.contains(" (My-super-cool-modpack!/"));
+    }
+}
@@ -0,0 +1,652 @@
+//! Resolution of [`Source::Curseforge`](crate::types::Source::Curseforge) through the
+//! CurseForge API
+
+use std::collections::BTreeMap;
+
+use relative_path::RelativePathBuf;
+use serde::{de::DeserializeOwned, Deserialize};
+use snafu::{OptionExt, ResultExt};
+use tracing::{debug, warn};
+use url::Url;
+
+use super::{
+    api_url, decode_hash, CompatibilityPolicy, DistributionDisabledSnafu, HttpSnafu,
+    MissingCredentialsSnafu, NoMatchingVersionSnafu, NoProjectSnafu, ProjectDetails, ResolveError,
+    ResolvedArtifact, Resolver, SearchHit, UpstreamIds,
+};
+use crate::{
+    hash::curseforge_fingerprint,
+    http::{HttpClient, HttpError, Request},
+    types::{Hashes, Loader, Source, Versions},
+};
+
+/// The CurseForge game id of minecraft
+const MINECRAFT_GAME_ID: &str = "432";
+
+/// CurseForge hash algorithm id for SHA-1
+const ALGORITHM_SHA1: u8 = 1;
+
+/// CurseForge hash algorithm id for MD5
+const ALGORITHM_MD5: u8 = 2;
+
+/// The loader names CurseForge lists among a file's game versions
+const LOADER_NAMES: [&str; 4] = ["Forge", "NeoForge", "Fabric", "Quilt"];
+
+/// The folders files of projects are installed in, by the id of the projects' class
+///
+/// Projects of any other class are taken to be mods.
+const CLASS_FOLDERS: [(u64, &str); 3] = [(6, "mods"), (12, "resourcepacks"), (6552, "shaderpacks")];
+
+/// The id of the class of mods, which searches are limited to
+const MODS_CLASS_ID: u64 = 6;
+
+/// The field `/mods/search` sorts by to put the most downloaded projects first
+const SORT_BY_POPULARITY: &str = "2";
+
+/// Returns the folder files of a project of `class` are installed in
+fn folder(class: Option<u64>) -> &'static str {
+    CLASS_FOLDERS
+        .iter()
+        .find(|(id, _)| Some(*id) == class)
+        .map_or("mods", |(_, folder)| folder)
+}
+
+/// A CurseForge file, as far as it is known to ffpack
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CurseforgeFile {
+    /// The id of the project the file belongs to
+    pub project_id: u64,
+    /// The id of the file
+    pub file_id: u64,
+    /// The name of the file
+    pub filename: String,
+    /// The file's fingerprint, if CurseForge listed it
+    pub fingerprint: Option<u32>,
+    /// The page the file can be downloaded from by hand
+    pub page: Option<Url>,
+}
+
+impl CurseforgeFile {
+    /// Describes a file as the API listed it
+    fn new(project: &Project, file: File) -> Self {
+        let page = project.links.website_url.clone().and_then(|mut page| {
+            page.path_segments_mut()
+                .ok()?
+                .pop_if_empty()
+                .extend(["files", &file.id.to_string()]);
+            Some(page)
+        });
+        Self {
+            project_id: project.id,
+            file_id: file.id,
+            filename: file.file_name,
+            fingerprint: file.file_fingerprint,
+            page,
+        }
+    }
+
+    /// Returns true if `contents` are this file, going by its fingerprint
+    ///
+    /// Files without a known fingerprint never match.
+    pub fn matches(&self, contents: &[u8]) -> bool {
+        self.fingerprint == Some(curseforge_fingerprint(contents))
+    }
+}
+
+/// A CurseForge file looked up by its ids, with what a pack needs to list it
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IdentifiedFile {
+    /// A source pinned to the file
+    pub source: Source,
+    /// Where the file is installed, going by the class of its project
+    pub path: RelativePathBuf,
+    /// The name of the file
+    pub filename: String,
+    /// The display name of the project
+    pub name: Option<String>,
+}
+
+impl IdentifiedFile {
+    /// Describes a file of a project, as the API listed them
+    fn new(project: Project, file: File) -> Self {
+        let folder = folder(project.class_id);
+        debug!(slug = %project.slug, class = project.class_id, "Identified file");
+        Self {
+            source: Source::Curseforge {
+                slug: project.slug,
+                file_id: Some(file.id),
+            },
+            path: RelativePathBuf::from(folder).join(&file.file_name),
+            filename: file.file_name,
+            name: project.name,
+        }
+    }
+}
+
+/// The envelope all CurseForge responses come in
+#[derive(Deserialize)]
+struct Data<T> {
+    /// The payload
+    data: T,
+}
+
+/// A project, as returned by `/mods/search`
+#[derive(Deserialize)]
+struct Project {
+    /// The project id
+    id: u64,
+    /// The project slug
+    slug: String,
+    /// The id of the project's class, like mods or resource packs
+    #[serde(default, rename = "classId")]
+    class_id: Option<u64>,
+    /// The display name
+    #[serde(default)]
+    name: Option<String>,
+    /// The short description
+    #[serde(default)]
+    summary: Option<String>,
+    /// How many times the project has been downloaded, which the API gives as a float
+    #[serde(default, rename = "downloadCount")]
+    download_count: f64,
+    /// The project's authors
+    #[serde(default)]
+    authors: Vec<Author>,
+    /// Links to the project's pages
+    #[serde(default)]
+    links: Links,
+}
+
+/// An author of a project
+#[derive(Deserialize)]
+struct Author {
+    /// The author's name
+    name: String,
+}
+
+/// Links to a project's pages
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct Links {
+    /// The project's page on the CurseForge website
+    website_url: Option<Url>,
+}
+
+/// A file, as returned by `/mods/{id}/files`
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[allow(clippy::struct_field_names)]
+struct File {
+    /// The file id
+    id: u64,
+    /// The name of the file
+    file_name: String,
+    /// The title of the file, which usually includes its version
+    #[serde(default)]
+    display_name: Option<String>,
+    /// The size of the file
+    file_length: u64,
+    /// Where to download the file, unless the author has disabled third party downloads
+    download_url: Option<Url>,
+    /// Hashes of the file
+    #[serde(default)]
+    hashes: Vec<FileHash>,
+    /// The file's fingerprint
+    #[serde(default)]
+    file_fingerprint: Option<u32>,
+    /// The Minecraft versions and loaders the file is for, mixed together
+    #[serde(default)]
+    game_versions: Vec<String>,
+    /// When the file was uploaded, as an RFC 3339 timestamp
+    #[serde(default)]
+    file_date: Option<String>,
+}
+
+impl File {
+    /// Returns true if the file is for `minecraft` and one of `loaders`, counting lists
+    /// without any versions or loaders as matching anything
+    fn supports(&self, minecraft: &str, loaders: &[&str]) -> bool {
+        let (listed_loaders, listed_games): (Vec<_>, Vec<_>) = self
+            .game_versions
+            .iter()
+            .partition(|name| LOADER_NAMES.contains(&name.as_str()));
+        // Besides versions like 1.20.1, the list holds tags like "Client", which never start
+        // with a digit
+        let listed_games: Vec<_> = listed_games
+            .into_iter()
+            .filter(|name| name.starts_with(|c: char| c.is_ascii_digit()))
+            .collect();
+        let game = listed_games.is_empty() || listed_games.iter().any(|name| *name == minecraft);
+        let loader = listed_loaders.is_empty()
+            || listed_loaders.iter().any(|name| {
+                loaders
+                    .iter()
+                    .any(|accepted| name.eq_ignore_ascii_case(accepted))
+            });
+        game && loader
+    }
+
+    /// Returns the digests the API lists for the file
+    fn digests(&self) -> Hashes {
+        let hash = |algorithm| {
+            self.hashes
+                .iter()
+                .find(|hash| hash.algo == algorithm)
+                .map(|hash| hash.value.as_str())
+        };
+        Hashes {
+            sha1: hash(ALGORITHM_SHA1).and_then(|hex| decode_hash("sha1", hex)),
+            md5: hash(ALGORITHM_MD5).and_then(|hex| decode_hash("md5", hex)),
+            ..Hashes::default()
+        }
+    }
+}
+
+/// The files `/fingerprints` found
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FingerprintMatches {
+    /// The files matching a fingerprint exactly
+    #[serde(default)]
+    exact_matches: Vec<FingerprintMatch>,
+}
+
+/// A file matching a fingerprint
+#[derive(Deserialize)]
+struct FingerprintMatch {
+    /// The id of the file's project
+    id: u64,
+    /// The file
+    file: File,
+}
+
+/// A hash of a file
+#[derive(Deserialize)]
+struct FileHash {
+    /// The hash, in hex
+    value: String,
+    /// The algorithm id
+    algo: u8,
+}
+
+/// Returns the CurseForge mod loader id of a loader
+fn loader_type(loader: &Loader) -> &'static str {
+    match loader {
+        Loader::Forge(_) => "1",
+        Loader::Fabric(_) => "4",
+        Loader::Quilt(_) => "5",
+    }
+}
+
+/// Searches for mods with files for the pack's minecraft version and loader, most popular first
+pub(super) async fn search<C: HttpClient>(
+    resolver: &Resolver<C>,
+    query: &str,
+    versions: &Versions,
+    limit: usize,
+) -> Result<Vec<SearchHit>, ResolveError> {
+    let key = api_key(resolver)?;
+    let mut url = api_url(&resolver.endpoints.curseforge, ["mods", "search"]);
+    url.query_pairs_mut()
+        .append_pair("gameId", MINECRAFT_GAME_ID)
+        .append_pair("classId", &MODS_CLASS_ID.to_string())
+        .append_pair("searchFilter", query)
+        .append_pair("gameVersion", &versions.minecraft.to_string())
+        .append_pair("modLoaderType", loader_type(&versions.loader))
+        .append_pair("sortField", SORT_BY_POPULARITY)
+        .append_pair("sortOrder", "desc")
+        .append_pair("pageSize", &limit.to_string());
+    let projects: Vec<Project> = fetch(resolver, key, url).await.context(HttpSnafu)?;
+    debug!(query, hits = projects.len(), "Searched CurseForge");
+    Ok(projects
+        .into_iter()
+        .map(|project| SearchHit {
+            folder: folder(project.class_id),
+            name: project.name.unwrap_or_else(|| project.slug.clone()),
+            description: project.summary,
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            downloads: project.download_count as u64,
+            source: Source::Curseforge {
+                slug: project.slug,
+                file_id: None,
+            },
+        })
+        .collect())
+}
+
+/// Resolves the file of a project to use in the pack: the pinned one if there is one,
+/// otherwise the newest compatible one
+///
+/// The API is asked for files matching the pack, and what it returns is checked again, as
+/// its filters have been known to let files for other loaders through. A pinned file is used
+/// whatever it was made for, with a warning if that doesn't match the pack.
+pub(super) async fn resolve<C: HttpClient>(
+    resolver: &Resolver<C>,
+    slug: &str,
+    file_id: Option<u64>,
+    versions: &Versions,
+) -> Result<ResolvedArtifact, ResolveError> {
+    let key = api_key(resolver)?;
+    let mut url = api_url(&resolver.endpoints.curseforge, ["mods", "search"]);
+    url.query_pairs_mut()
+        .append_pair("gameId", MINECRAFT_GAME_ID)
+        .append_pair("slug", slug);
+    let projects: Vec<Project> = fetch(resolver, key, url).await.context(HttpSnafu)?;
+    let project = projects
+        .into_iter()
+        .find(|project| project.slug == slug)
+        .context(NoProjectSnafu { project: slug })?;
+
+    let minecraft = versions.minecraft.to_string();
+    let compatible = versions.loader.compatible_loaders();
+    let accepted = if resolver.compatibility == CompatibilityPolicy::Deny {
+        &compatible[..1]
+    } else {
+        compatible
+    };
+    let id = project.id.to_string();
+    let file = if let Some(file_id) = file_id {
+        let file_id = file_id.to_string();
+        let url = api_url(
+            &resolver.endpoints.curseforge,
+            ["mods", &id, "files", &file_id],
+        );
+        let file: File = fetch(resolver, key, url).await.context(HttpSnafu)?;
+        if !file.supports(&minecraft, accepted) {
+            warn!(
+                project = slug,
+                file = %file_id,
+                "Pinned file isn't made for minecraft {minecraft} on {}",
+                versions.loader.name()
+            );
+        }
+        file
+    } else {
+        let mut url = api_url(&resolver.endpoints.curseforge, ["mods", &id, "files"]);
+        url.query_pairs_mut()
+            .append_pair("gameVersion", &minecraft)
+            .append_pair("modLoaderType", loader_type(&versions.loader));
+        let files: Vec<File> = fetch(resolver, key, url).await.context(HttpSnafu)?;
+        // Files come newest first, but upload dates are more trustworthy where they are given
+        files
+            .into_iter()
+            .filter(|file| file.supports(&minecraft, accepted))
+            .reduce(|newest, file| match (&newest.file_date, &file.file_date) {
+                (Some(current), Some(candidate)) if candidate > current => file,
+                _ => newest,
+            })
+            .context(NoMatchingVersionSnafu {
+                project: slug,
+                versions: format!("minecraft {minecraft} on {}", versions.loader.name()),
+            })?
+    };
+    let hashes = file.digests();
+    let Some(download_url) = file.download_url else {
+        return DistributionDisabledSnafu {
+            project: slug,
+            file: Box::new(CurseforgeFile::new(&project, file)),
+        }
+        .fail();
+    };
+    let changelog = project.links.website_url.as_ref().map(|page| {
+        let id = file.id.to_string();
+        api_url(page, ["files", &id])
+    });
+    Ok(ResolvedArtifact {
+        download_url,
+        filename: file.file_name,
+        size: Some(file.file_length),
+        hashes,
+        mirrors: Vec::new(),
+        extract: None,
+        ids: UpstreamIds {
+            project_id: Some(project.id.to_string()),
+            file_id: Some(file.id.to_string()),
+            ..UpstreamIds::default()
+        },
+        details: ProjectDetails {
+            name: project.name,
+            version: file.display_name,
+            url: project.links.website_url,
+            authors: project
+                .authors
+                .into_iter()
+                .map(|author| author.name)
+                .collect(),
+            license: None,
+            changelog,
+        },
+    })
+}
+
+/// Looks a file up by the ids of its project and itself, as modpack manifests list files
+pub(super) async fn identify<C: HttpClient>(
+    resolver: &Resolver<C>,
+    project_id: u64,
+    file_id: u64,
+) -> Result<IdentifiedFile, ResolveError> {
+    let key = api_key(resolver)?;
+    let (project_id, file_id) = (project_id.to_string(), file_id.to_string());
+    let url = api_url(&resolver.endpoints.curseforge, ["mods", &project_id]);
+    let project: Project = fetch(resolver, key, url).await.context(HttpSnafu)?;
+    let url = api_url(
+        &resolver.endpoints.curseforge,
+        ["mods", &project_id, "files", &file_id],
+    );
+    let file: File = fetch(resolver, key, url).await.context(HttpSnafu)?;
+    Ok(IdentifiedFile::new(project, file))
+}
+
+/// Looks files up by their fingerprints, returning the ones CurseForge knows by fingerprint
+///
+/// The fingerprints are matched in a single request, and then each project found is fetched
+/// for its slug.
+pub(super) async fn identify_fingerprints<C: HttpClient>(
+    resolver: &Resolver<C>,
+    fingerprints: &[u32],
+) -> Result<BTreeMap<u32, IdentifiedFile>, ResolveError> {
+    if fingerprints.is_empty() {
+        return Ok(BTreeMap::new());
+    }
+    let key = api_key(resolver)?;
+    let url = api_url(
+        &resolver.endpoints.curseforge,
+        ["fingerprints", MINECRAFT_GAME_ID],
+    );
+    let body = serde_json::json!({ "fingerprints": fingerprints }).to_string();
+    let request = resolver.request(url).with_json_body(body);
+    let found: FingerprintMatches = send(resolver, key, request).await.context(HttpSnafu)?;
+    let mut identified = BTreeMap::new();
+    for found in found.exact_matches {
+        let Some(fingerprint) = found.file.file_fingerprint else {
+            continue;
+        };
+        let id = found.id.to_string();
+        let url = api_url(&resolver.endpoints.curseforge, ["mods", &id]);
+        let project: Project = fetch(resolver, key, url).await.context(HttpSnafu)?;
+        identified.insert(fingerprint, IdentifiedFile::new(project, found.file));
+    }
+    Ok(identified)
+}
+
+/// Returns the API key, which every CurseForge request needs
+fn api_key<C>(resolver: &Resolver<C>) -> Result<&str, ResolveError> {
+    resolver
+        .credentials
+        .curseforge
+        .as_deref()
+        .context(MissingCredentialsSnafu {
+            service: "CurseForge",
+        })
+}
+
+/// Sends an API request for `url` with the key, and unwraps the response from its envelope
+async fn fetch<C: HttpClient, T: DeserializeOwned>(
+    resolver: &Resolver<C>,
+    key: &str,
+    url: Url,
+) -> Result<T, HttpError> {
+    send(resolver, key, resolver.request(url)).await
+}
+
+/// Sends an API request with the key, and unwraps the response from its envelope
+async fn send<C: HttpClient, T: DeserializeOwned>(
+    resolver: &Resolver<C>,
+    key: &str,
+    request: Request,
+) -> Result<T, HttpError> {
+    let request = request.with_header("x-api-key", key);
+    let response: Data<T> = resolver
+        .get(request)
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(response.data)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use crate::{
+        http::testing::{block_on, MockClient},
+        resolve::{ResolveError, Resolver},
+        types::{Loader, ManagedFile, Minecraft, Source, Versions},
+    };
+
+    /// The project search for jei
+    const SEARCH: &str = "https://api.curseforge.com/v1/mods/search?gameId=432&slug=jei";
+
+    /// The file listing for jei on forge 1.20.1
+    const FILES: &str =
+        "https://api.curseforge.com/v1/mods/238222/files?gameVersion=1.20.1&modLoaderType=1";
+
+    // Projects are found by slug, and need a key and third party downloads enabled, and files
+    // for other loaders are skipped even when the API lists them
+    #[test]
+    fn resolve() {
+        let versions = Versions {
+            minecraft: Minecraft::new("1.20.1").unwrap(),
+            loader: Loader::new_forge("47.1.0".parse().unwrap()),
+            java: None,
+        };
+        let file = ManagedFile {
+            source: Source::Curseforge {
+                slug: "jei".to_string(),
+                file_id: None,
+            },
+            ..ManagedFile::default()
+        };
+        let search = r#"{"data": [{"id": 1, "slug": "jei-addon"}, {"id": 238222, "slug": "jei",
+            "name": "Just Enough Items", "authors": [{"name": "mezz"}],
+            "links": {"websiteUrl": "https://www.curseforge.com/minecraft/mc-mods/jei"}}]}"#;
+        let files = r#"{"data": [{
+            "id": 4712868,
+            "fileName": "jei-1.20.1-fabric.jar",
+            "fileLength": 1000,
+            "downloadUrl": "https://edge.forgecdn.net/files/jei-1.20.1-fabric.jar",
+            "gameVersions": ["Fabric", "1.20.1", "Client"],
+            "fileDate": "2023-09-01T00:00:00Z"
+        }, {
+            "id": 4712866,
+            "fileName": "jei-1.20.1-forge.jar",
+            "displayName": "jei-1.20.1-forge-15.2.0.27",
+            "fileLength": 1000,
+            "downloadUrl": "https://edge.forgecdn.net/files/jei-1.20.1-forge.jar",
+            "hashes": [{"value": "00112233445566778899aabbccddeeff", "algo": 2}],
+            "fileFingerprint": 3751777527,
+            "gameVersions": ["Forge", "NeoForge", "1.20.1"],
+            "fileDate": "2023-08-30T00:00:00Z"
+        }]}"#;
+        let client = || MockClient::default().with(SEARCH, search);
+
+        assert!(matches!(
+            block_on(Resolver::new(client()).resolve(&file, &versions)),
+            Err(ResolveError::MissingCredentials { .. })
+        ));
+
+        let resolver = Resolver::new(client().with(FILES, files)).with_curseforge_key("key");
+        let artifact = block_on(resolver.resolve(&file, &versions)).unwrap();
+        assert_eq!(artifact.filename, "jei-1.20.1-forge.jar");
+        assert_eq!(artifact.hashes.md5.unwrap()[15], 0xff);
+        assert_eq!(artifact.details.name.as_deref(), Some("Just Enough Items"));
+        assert_eq!(
+            artifact.details.version.as_deref(),
+            Some("jei-1.20.1-forge-15.2.0.27")
+        );
+        assert_eq!(artifact.details.authors, ["mezz"]);
+        assert_eq!(artifact.ids.project_id.as_deref(), Some("238222"));
+        assert_eq!(artifact.ids.file_id.as_deref(), Some("4712866"));
+        assert!(resolver.client().requests.lock().unwrap()[0]
+            .headers
+            .contains(&("x-api-key".to_string(), "key".to_string())));
+
+        // A pinned file is fetched by its id instead
+        let pinned = ManagedFile {
+            source: Source::Curseforge {
+                slug: "jei".to_string(),
+                file_id: Some(4_712_868),
+            },
+            ..ManagedFile::default()
+        };
+        let fabric = r#"{"data": {
+            "id": 4712868,
+            "fileName": "jei-1.20.1-fabric.jar",
+            "fileLength": 1000,
+            "downloadUrl": "https://edge.forgecdn.net/files/jei-1.20.1-fabric.jar",
+            "gameVersions": ["Fabric", "1.20.1"]
+        }}"#;
+        let pinned_client = client().with(
+            "https://api.curseforge.com/v1/mods/238222/files/4712868",
+            fabric,
+        );
+        let resolver = Resolver::new(pinned_client).with_curseforge_key("key");
+        let artifact = block_on(resolver.resolve(&pinned, &versions)).unwrap();
+        assert_eq!(artifact.filename, "jei-1.20.1-fabric.jar");
+
+        let disabled = files.replace(
+            r#""https://edge.forgecdn.net/files/jei-1.20.1-forge.jar""#,
+            "null",
+        );
+        let resolver = Resolver::new(client().with(FILES, disabled)).with_curseforge_key("key");
+        let error = block_on(resolver.resolve(&file, &versions)).unwrap_err();
+        let ResolveError::DistributionDisabled { file, .. } = &error else {
+            panic!("Expected distribution to be disabled, got {error:?}");
+        };
+        assert_eq!((file.project_id, file.file_id), (238_222, 4_712_866));
+        assert_eq!(
+            file.page.as_ref().unwrap().as_str(),
+            "https://www.curseforge.com/minecraft/mc-mods/jei/files/4712866"
+        );
+        // A copy downloaded by hand is recognized, whitespace changes and all
+        assert!(file.matches(b"The quick brown fox\r\njumps over the lazy dog"));
+        assert!(!file.matches(b"Something else"));
+        assert!(error.suggestion().unwrap().contains("jei-1.20.1-forge.jar"));
+    }
+
+    // Searches are limited to mods for the pack's versions, and find where their files go
+    #[test]
+    fn search() {
+        let versions = Versions {
+            minecraft: Minecraft::new("1.20.1").unwrap(),
+            loader: Loader::new_forge("47.1.0".parse().unwrap()),
+            java: None,
+        };
+        let url = "https://api.curseforge.com/v1/mods/search?gameId=432&classId=6\
+            &searchFilter=just+enough&gameVersion=1.20.1&modLoaderType=1&sortField=2\
+            &sortOrder=desc&pageSize=10";
+        let body = r#"{"data": [{"id": 238222, "slug": "jei", "classId": 6,
+            "name": "Just Enough Items", "summary": "View items and recipes",
+            "downloadCount": 250000000.0}]}"#;
+        let client = || MockClient::default().with(url, body);
+        let resolver = Resolver::new(client());
+        assert!(matches!(
+            block_on(resolver.search_curseforge("just enough", &versions, 10)),
+            Err(ResolveError::MissingCredentials { .. })
+        ));
+        let resolver = Resolver::new(client()).with_curseforge_key("key");
+        let hits = block_on(resolver.search_curseforge("just enough", &versions, 10)).unwrap();
+        assert_eq!(hits[0].name, "Just Enough Items");
+        assert_eq!(hits[0].downloads, 250_000_000);
+        assert_eq!(hits[0].folder, "mods");
+    }
+}
@@ -0,0 +1,192 @@
+//! Resolution of [`Source::Custom`](crate::types::Source::Custom) by resolvers embedders plug in
+//!
+//! Proprietary artifact stores are reached through a [`SourceResolver`] registered for the
+//! custom scheme their sources use, so that resolving from them doesn't take a fork.
+
+use std::{collections::BTreeMap, error::Error, fmt, future::Future, pin::Pin, sync::Arc};
+
+use url::Url;
+
+use super::ResolvedArtifact;
+use crate::types::{ManagedFile, Versions};
+
+/// The error a [`SourceResolver`] fails with, whatever it is
+pub type SourceResolverError = Box<dyn Error + Send + Sync>;
+
+/// The future a [`SourceResolver`] returns
+pub type SourceFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<ResolvedArtifact, SourceResolverError>> + Send + 'a>>;
+
+/// A custom source being resolved
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct CustomSource<'a> {
+    /// The url of the source, custom scheme and all
+    pub url: &'a Url,
+    /// The scheme the resolver was picked by
+    pub scheme: &'a str,
+    /// The source's extra parameters
+    pub params: &'a BTreeMap<String, String>,
+    /// The managed file the source belongs to
+    pub file: &'a ManagedFile,
+    /// The versions of the game and loader being resolved for
+    pub versions: &'a Versions,
+}
+
+impl CustomSource<'_> {
+    /// Returns the url after the custom part of its scheme, like `https://…` for
+    /// `mycompany+https://…`, if that is a url
+    pub fn inner_url(&self) -> Option<Url> {
+        let rest = self.url.as_str().strip_prefix(self.scheme)?;
+        Url::parse(rest.strip_prefix('+')?).ok()
+    }
+}
+
+/// Resolves sources of a custom scheme into artifacts
+///
+/// Resolvers are shared between every resolution, so they are called through `&self` and
+/// return boxed futures.
+///
+/// ```
+/// use ffpack::resolve::{CustomSource, ResolvedArtifact, SourceFuture, SourceResolver};
+///
+/// /// A store serving every file from under the url in the source
+/// struct Store;
+///
+/// impl SourceResolver for Store {
+///     fn resolve<'a>(&'a self, source: CustomSource<'a>) -> SourceFuture<'a> {
+///         Box::pin(async move {
+///             let download_url = source.inner_url().ok_or("Not a url")?;
+///             Ok(ResolvedArtifact {
+///                 download_url,
+///                 filename: source.file.filename.clone(),
+///                 size: None,
+///                 hashes: Default::default(),
+///                 mirrors: Vec::new(),
+///                 extract: None,
+///                 ids: Default::default(),
+///                 details: Default::default(),
+///             })
+///         })
+///     }
+/// }
+/// ```
+pub trait SourceResolver: Send + Sync {
+    /// Resolves a source into an artifact to download
+    fn resolve<'a>(&'a self, source: CustomSource<'a>) -> SourceFuture<'a>;
+}
+
+/// The [`SourceResolver`]s for custom schemes, given to a
+/// [`Resolver`](super::Resolver) with [`with_sources`](super::Resolver::with_sources)
+#[derive(Clone, Default)]
+pub struct SourceRegistry {
+    /// The resolvers by scheme
+    resolvers: BTreeMap<String, Arc<dyn SourceResolver>>,
+}
+
+impl SourceRegistry {
+    /// Registers the resolver for sources whose scheme starts with `scheme`, followed by a `+`
+    /// or nothing, replacing any resolver it had
+    #[must_use]
+    pub fn with(
+        mut self,
+        scheme: impl Into<String>,
+        resolver: impl SourceResolver + 'static,
+    ) -> Self {
+        self.resolvers.insert(scheme.into(), Arc::new(resolver));
+        self
+    }
+
+    /// Returns the resolver registered for `scheme`
+    pub fn get(&self, scheme: &str) -> Option<&dyn SourceResolver> {
+        self.resolvers.get(scheme).map(|resolver| &**resolver)
+    }
+
+    /// Returns the schemes with a registered resolver
+    pub fn schemes(&self) -> impl Iterator<Item = &str> {
+        self.resolvers.keys().map(String::as_str)
+    }
+}
+
+impl fmt::Debug for SourceRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.schemes()).finish()
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::{
+        http::testing::{block_on, MockClient},
+        resolve::{ProjectDetails, ResolveError, Resolver, UpstreamIds},
+        types::{Hashes, Source},
+    };
+
+    /// Resolves to the inner url, naming the version after a parameter
+    struct Store;
+
+    impl SourceResolver for Store {
+        fn resolve<'a>(&'a self, source: CustomSource<'a>) -> SourceFuture<'a> {
+            Box::pin(async move {
+                let download_url = source.inner_url().ok_or("Not a url")?;
+                Ok(ResolvedArtifact {
+                    download_url,
+                    filename: source.file.filename.clone(),
+                    size: None,
+                    hashes: Hashes::default(),
+                    mirrors: Vec::new(),
+                    extract: None,
+                    ids: UpstreamIds::default(),
+                    details: ProjectDetails {
+                        version: source.params.get("version").cloned(),
+                        ..ProjectDetails::default()
+                    },
+                })
+            })
+        }
+    }
+
+    // Custom sources go to the resolver of their scheme, and round trip without one
+    #[test]
+    fn custom() {
+        let source = Source::Custom {
+            url: Url::parse("mycompany+https://store.example.com/lib.jar").unwrap(),
+            params: BTreeMap::from([("version".into(), "1.2".into())]),
+        };
+        assert_eq!(source.custom_scheme(), Some("mycompany"));
+        let json = serde_json::to_value(&source).unwrap();
+        assert_eq!(serde_json::from_value::<Source>(json).unwrap(), source);
+
+        let file = ManagedFile {
+            source,
+            ..ManagedFile::default()
+        };
+        let resolver = Resolver::new(MockClient::default());
+        assert!(matches!(
+            block_on(resolver.resolve(&file, &Versions::default())),
+            Err(ResolveError::UnknownScheme { .. })
+        ));
+        let resolver = resolver.with_sources(SourceRegistry::default().with("mycompany", Store));
+        let artifact = block_on(resolver.resolve(&file, &Versions::default())).unwrap();
+        assert_eq!(
+            artifact.download_url.as_str(),
+            "https://store.example.com/lib.jar"
+        );
+        assert_eq!(artifact.details.version.as_deref(), Some("1.2"));
+
+        let broken = ManagedFile {
+            source: Source::Custom {
+                url: Url::parse("mycompany:lib").unwrap(),
+                params: BTreeMap::new(),
+            },
+            ..ManagedFile::default()
+        };
+        let error = block_on(resolver.resolve(&broken, &Versions::default())).unwrap_err();
+        assert!(matches!(error, ResolveError::Custom { .. }));
+        assert_eq!(
+            error.to_string(),
+            "Resolving a mycompany source failed: Not a url"
+        );
+    }
+}
@@ -0,0 +1,122 @@
+//! Resolving git sources by building them from a checkout
+
+use snafu::{OptionExt, ResultExt};
+use url::Url;
+
+use super::{
+    GitSnafu, NoBuildSnafu, NoGitSnafu, ProjectDetails, ResolveError, ResolvedArtifact, Resolver,
+    UpstreamIds,
+};
+use crate::{git::GitRef, http::HttpClient, types::Build};
+
+/// Checks out and builds a git source
+///
+/// Checking out and building block the calling thread until they are done.
+pub(super) fn resolve<C: HttpClient>(
+    resolver: &Resolver<C>,
+    url: &Url,
+    reference: &GitRef,
+    build: Option<&Build>,
+) -> Result<ResolvedArtifact, ResolveError> {
+    let git = resolver
+        .git
+        .as_ref()
+        .context(NoGitSnafu { url: url.clone() })?;
+    let build = build.context(NoBuildSnafu { url: url.clone() })?;
+    let output = git.build(url, reference, build).context(GitSnafu)?;
+    let download_url =
+        Url::from_file_path(&output.path).expect("Built files are found by absolute paths");
+    let filename = match &build.filename {
+        Some(filename) => filename.clone(),
+        None => output
+            .path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned(),
+    };
+    Ok(ResolvedArtifact {
+        download_url,
+        filename,
+        size: Some(output.size),
+        hashes: output.hashes,
+        mirrors: Vec::new(),
+        extract: None,
+        ids: UpstreamIds {
+            commit: Some(output.commit.clone()),
+            ..UpstreamIds::default()
+        },
+        details: ProjectDetails {
+            version: Some(output.commit[..output.commit.len().min(12)].to_string()),
+            url: Some(url.clone()),
+            ..ProjectDetails::default()
+        },
+    })
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::{
+        git::{testing::Upstream, Git, GitError},
+        hash::blake3,
+        http::testing::{block_on, MockClient},
+        types::{ManagedFile, Source, Versions},
+    };
+
+    // Git sources resolve to their build, once the resolver has somewhere to check them out and
+    // builds are allowed
+    #[cfg(unix)]
+    #[test]
+    fn build() {
+        let Some(upstream) = Upstream::new("resolve") else {
+            return;
+        };
+        let commit = upstream.commit(&[("build.sh", "mkdir -p out && echo hi > out/mod.jar")]);
+        let mut file = ManagedFile {
+            source: Source::Git {
+                url: upstream.url.clone(),
+                branch: Some("main".into()),
+                tag: None,
+                rev: None,
+                build: None,
+            },
+            ..ManagedFile::default()
+        };
+        let resolver = Resolver::new(MockClient::default());
+        let resolve = |resolver: &Resolver<MockClient>, file: &ManagedFile| {
+            block_on(resolver.resolve(file, &Versions::default()))
+        };
+        assert!(matches!(
+            resolve(&resolver, &file),
+            Err(ResolveError::NoGit { .. })
+        ));
+        let resolver = resolver.with_git(Git::new(upstream.cache()));
+        assert!(matches!(
+            resolve(&resolver, &file),
+            Err(ResolveError::NoBuild { .. })
+        ));
+
+        if let Source::Git { build, .. } = &mut file.source {
+            *build = Some(Build {
+                command: vec!["sh".into(), "build.sh".into()],
+                artifact: "out/*.jar".into(),
+                filename: Some("mod-dev.jar".into()),
+            });
+        }
+        assert!(matches!(
+            resolve(&resolver, &file),
+            Err(ResolveError::Git {
+                source: GitError::BuildNotAllowed { .. }
+            })
+        ));
+        let resolver = resolver.with_builds(true);
+        let artifact = resolve(&resolver, &file).unwrap();
+        assert_eq!(artifact.filename, "mod-dev.jar");
+        assert_eq!(artifact.size, Some(3));
+        assert_eq!(artifact.hashes.blake3, Some(blake3(b"hi\n")));
+        assert_eq!(artifact.details.version.as_deref(), Some(&commit[..12]));
+        let path = artifact.download_url.to_file_path().unwrap();
+        assert_eq!(std::fs::read(path).unwrap(), b"hi\n");
+    }
+}
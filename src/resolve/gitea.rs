@@ -0,0 +1,143 @@
+//! Resolution of [`Source::SlugReleases`](crate::types::Source::SlugReleases) through the
+//! releases API of Gitea, and of Forgejo, which Codeberg runs
+
+use serde::Deserialize;
+use snafu::ResultExt;
+use url::Url;
+
+use super::{
+    api_url,
+    releases::{self, next_page, Matcher, MAX_PAGES},
+    HttpSnafu, ResolveError, ResolvedArtifact, Resolver,
+};
+use crate::{
+    http::{HttpClient, Response},
+    types::{Forge, ForgeSlug},
+};
+
+/// How many releases are asked for at once, the most Gitea allows by default
+const LIMIT: &str = "50";
+
+/// A release, as returned by `/repos/{owner}/{repo}/releases`
+#[derive(Deserialize)]
+struct Release {
+    /// The release title
+    name: Option<String>,
+    /// The tag the release was made from
+    tag_name: String,
+    /// The release's page
+    html_url: Option<Url>,
+    /// Whether the release is an unpublished draft
+    #[serde(default)]
+    draft: bool,
+    /// The files attached to the release
+    #[serde(default)]
+    assets: Vec<Asset>,
+}
+
+/// A file attached to a release
+#[derive(Deserialize)]
+struct Asset {
+    /// The file name
+    name: String,
+    /// The size of the file
+    size: u64,
+    /// Where to download the file
+    browser_download_url: Url,
+}
+
+/// Resolves the single matching artifact of the newest matching release, from Codeberg or the
+/// Gitea instance the slug names
+pub(super) async fn resolve_release<C: HttpClient>(
+    resolver: &Resolver<C>,
+    slug: &ForgeSlug,
+    matcher: &Matcher<'_>,
+) -> Result<ResolvedArtifact, ResolveError> {
+    let base = match &slug.forge {
+        Forge::Gitea { .. } => slug
+            .forge
+            .web_url()
+            .join("api/v1/")
+            .expect("Relative paths join onto forge urls"),
+        _ => resolver.endpoints.codeberg.clone(),
+    };
+    let mut url = api_url(&base, ["repos", &slug.owner, &slug.project, "releases"]);
+    url.query_pairs_mut().append_pair("limit", LIMIT);
+    let mut next = Some(url);
+    let mut pages = 0;
+    while let Some(url) = next.take().filter(|_| pages < MAX_PAGES) {
+        pages += 1;
+        let response = resolver
+            .get(resolver.request(url))
+            .await
+            .and_then(Response::error_for_status)
+            .context(HttpSnafu)?;
+        next = next_page(&response);
+        let found: Vec<Release> = response.json().await.context(HttpSnafu)?;
+        for release in found.into_iter().filter(|release| !release.draft) {
+            let release = releases::Release {
+                title: release.name,
+                tag: release.tag_name,
+                page: release.html_url,
+                assets: release
+                    .assets
+                    .into_iter()
+                    .map(|asset| releases::Asset {
+                        name: asset.name,
+                        size: Some(asset.size),
+                        url: asset.browser_download_url,
+                        sha256: None,
+                    })
+                    .collect(),
+            };
+            if let Some(artifact) = matcher.pick(release)? {
+                return Ok(artifact);
+            }
+        }
+    }
+    Err(matcher.no_match())
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use crate::{
+        http::testing::{block_on, MockClient},
+        resolve::Resolver,
+        types::{ManagedFile, Source, Versions},
+    };
+
+    // Codeberg and self-hosted instances answer the same API at different hosts
+    #[test]
+    fn releases() {
+        let releases = r#"[
+            {"name": "Draft", "tag_name": "v2.0.0", "draft": true, "assets": []},
+            {"name": "Version 1", "tag_name": "v1.0.0", "assets": [
+                {"name": "mod-1.0.0.jar", "size": 10,
+                 "browser_download_url": "https://codeberg.org/o/r/releases/download/v1.0.0/mod-1.0.0.jar"}
+            ]}
+        ]"#;
+        let client = MockClient::default()
+            .with(
+                "https://codeberg.org/api/v1/repos/o/r/releases?limit=50",
+                releases,
+            )
+            .with(
+                "https://git.example.org/api/v1/repos/o/r/releases?limit=50",
+                releases,
+            );
+        let resolver = Resolver::new(client);
+        for slug in ["codeberg:o/r", "gitea:git.example.org:o/r"] {
+            let file = ManagedFile {
+                source: Source::SlugReleases {
+                    slug: slug.to_string(),
+                    artifact_regex: r"\.jar$".to_string(),
+                    release_regex: None,
+                },
+                ..ManagedFile::default()
+            };
+            let artifact = block_on(resolver.resolve(&file, &Versions::default())).unwrap();
+            assert_eq!(artifact.filename, "mod-1.0.0.jar");
+            assert_eq!(artifact.size, Some(10));
+        }
+    }
+}
@@ -0,0 +1,250 @@
+//! Resolution of [`Source::SlugReleases`](crate::types::Source::SlugReleases) through the
+//! GitHub releases API
+
+use serde::Deserialize;
+use snafu::{ensure, ResultExt};
+use url::Url;
+
+use super::{
+    api_url, decode_hash,
+    releases::{self, next_page, Matcher, MAX_PAGES},
+    HttpSnafu, RateLimitedSnafu, ResolveError, ResolvedArtifact, Resolver,
+};
+use crate::{
+    http::{HttpClient, Response},
+    types::ForgeSlug,
+};
+
+/// A release, as returned by `/repos/{owner}/{repo}/releases`
+#[derive(Deserialize)]
+struct Release {
+    /// The release title
+    name: Option<String>,
+    /// The tag the release was made from
+    tag_name: String,
+    /// The release's page
+    html_url: Option<Url>,
+    /// Whether the release is an unpublished draft
+    #[serde(default)]
+    draft: bool,
+    /// The files attached to the release
+    #[serde(default)]
+    assets: Vec<Asset>,
+}
+
+/// A file attached to a release
+#[derive(Deserialize)]
+struct Asset {
+    /// The file name
+    name: String,
+    /// The size of the file
+    size: u64,
+    /// Where to download the file
+    browser_download_url: Url,
+    /// The hash of the file, as `algorithm:hex`
+    digest: Option<String>,
+}
+
+/// How many releases are asked for at once, the most GitHub allows
+const PER_PAGE: &str = "100";
+
+/// Resolves the single matching artifact of the newest matching release
+///
+/// Releases are paged through newest first, stopping at the first one with a matching
+/// artifact.
+pub(super) async fn resolve_release<C: HttpClient>(
+    resolver: &Resolver<C>,
+    slug: &ForgeSlug,
+    matcher: &Matcher<'_>,
+) -> Result<ResolvedArtifact, ResolveError> {
+    let mut url = api_url(
+        &resolver.endpoints.github,
+        ["repos", &slug.owner, &slug.project, "releases"],
+    );
+    url.query_pairs_mut().append_pair("per_page", PER_PAGE);
+    let mut next = Some(url);
+    let mut pages = 0;
+    while let Some(url) = next.take().filter(|_| pages < MAX_PAGES) {
+        pages += 1;
+        let response = fetch(resolver, url).await?;
+        next = next_page(&response);
+        let releases: Vec<Release> = response.json().await.context(HttpSnafu)?;
+        for release in releases.into_iter().filter(|release| !release.draft) {
+            let release = releases::Release {
+                title: release.name,
+                tag: release.tag_name,
+                page: release.html_url,
+                assets: release
+                    .assets
+                    .into_iter()
+                    .map(|asset| releases::Asset {
+                        sha256: asset
+                            .digest
+                            .as_deref()
+                            .and_then(|digest| digest.strip_prefix("sha256:"))
+                            .and_then(|hex| decode_hash("sha256", hex)),
+                        name: asset.name,
+                        size: Some(asset.size),
+                        url: asset.browser_download_url,
+                    })
+                    .collect(),
+            };
+            if let Some(artifact) = matcher.pick(release)? {
+                return Ok(artifact);
+            }
+        }
+    }
+    Err(matcher.no_match())
+}
+
+/// Requests something from the GitHub API, with the GitHub token if there is one
+pub(super) async fn fetch<C: HttpClient>(
+    resolver: &Resolver<C>,
+    url: Url,
+) -> Result<Response<C::Body>, ResolveError> {
+    let mut request = resolver
+        .request(url)
+        .with_header("Accept", "application/vnd.github+json");
+    let token = resolver.credentials.github.as_ref();
+    if let Some(token) = token {
+        request = request.with_header("Authorization", format!("Bearer {token}"));
+    }
+    let response = resolver.get(request).await.context(HttpSnafu)?;
+    // GitHub answers 403 rather than 429 when the limit runs out
+    let exhausted = matches!(response.status, 403 | 429)
+        && response.header("x-ratelimit-remaining") == Some("0");
+    ensure!(
+        !exhausted,
+        RateLimitedSnafu {
+            service: "GitHub",
+            authenticated: token.is_some(),
+        }
+    );
+    response.error_for_status().context(HttpSnafu)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::{
+        http::testing::{block_on, MockClient},
+        types::{ManagedFile, Source, Versions},
+    };
+
+    /// The first page of releases of the example repository
+    const FIRST_PAGE: &str = "https://api.github.com/repos/o/r/releases?per_page=100";
+
+    /// The releases of the example repository
+    const RELEASES: &str = r#"[
+        {"name": "Nightly", "tag_name": "nightly", "draft": false, "assets": [
+            {"name": "mod-nightly.jar", "size": 1,
+             "browser_download_url": "https://github.com/o/r/releases/download/nightly/mod-nightly.jar"}
+        ]},
+        {"name": null, "tag_name": "v2.0.0", "draft": true, "assets": []},
+        {"name": "Version 1.1", "tag_name": "v1.1.0", "draft": false, "assets": [
+            {"name": "mod-1.1.0.jar", "size": 10,
+             "browser_download_url": "https://github.com/o/r/releases/download/v1.1.0/mod-1.1.0.jar",
+             "digest": "sha256:0101010101010101010101010101010101010101010101010101010101010101"},
+            {"name": "mod-1.1.0-sources.jar", "size": 5,
+             "browser_download_url": "https://github.com/o/r/releases/download/v1.1.0/mod-1.1.0-sources.jar"}
+        ]}
+    ]"#;
+
+    /// Resolves against the example releases
+    fn resolve(
+        slug: &str,
+        artifact_regex: &str,
+        release_regex: Option<&str>,
+    ) -> Result<ResolvedArtifact, ResolveError> {
+        let client = MockClient::default().with(FIRST_PAGE, RELEASES);
+        let file = ManagedFile {
+            source: Source::SlugReleases {
+                slug: slug.to_string(),
+                artifact_regex: artifact_regex.to_string(),
+                release_regex: release_regex.map(str::to_string),
+            },
+            ..ManagedFile::default()
+        };
+        block_on(Resolver::new(client).resolve(&file, &Versions::default()))
+    }
+
+    // The newest release with exactly one matching artifact wins
+    #[test]
+    fn releases() {
+        let artifact = resolve("github:o/r", r"^mod-[\d.]+\.jar$", None).unwrap();
+        assert_eq!(artifact.filename, "mod-1.1.0.jar");
+        assert_eq!(artifact.hashes.sha256, Some([1; 32]));
+        assert_eq!(artifact.details.version.as_deref(), Some("v1.1.0"));
+        assert_eq!(
+            artifact.details.url.unwrap().as_str(),
+            "https://github.com/o/r"
+        );
+        let artifact = resolve("github:o/r", r"\.jar$", Some("^Nightly$")).unwrap();
+        assert_eq!(artifact.filename, "mod-nightly.jar");
+        assert!(matches!(
+            resolve("github:o/r", r"^mod-1.*\.jar$", None),
+            Err(ResolveError::AmbiguousArtifact { .. })
+        ));
+        assert!(matches!(
+            resolve("github:o/r", r"\.zip$", None),
+            Err(ResolveError::NoMatchingVersion { .. })
+        ));
+        assert!(matches!(
+            resolve("github:o/r", "(", None),
+            Err(ResolveError::InvalidRegex { .. })
+        ));
+        assert!(matches!(
+            resolve("sourcehut:o/r", r"\.jar$", None),
+            Err(ResolveError::UnsupportedForge { .. })
+        ));
+        assert!(matches!(
+            resolve("o/r", r"\.jar$", None),
+            Err(ResolveError::InvalidSlug { .. })
+        ));
+    }
+
+    // Older releases are found on later pages, as far as the page limit goes
+    #[test]
+    fn pages() {
+        let file = ManagedFile {
+            source: Source::SlugReleases {
+                slug: "github:o/r".to_string(),
+                artifact_regex: r"^mod-1\.0\.0\.jar$".to_string(),
+                release_regex: None,
+            },
+            ..ManagedFile::default()
+        };
+        let second = "https://api.github.com/repositories/1/releases?per_page=100&page=2";
+        let older = r#"[{"name": "Version 1.0", "tag_name": "v1.0.0", "assets": [
+            {"name": "mod-1.0.0.jar", "size": 10,
+             "browser_download_url": "https://github.com/o/r/releases/download/v1.0.0/mod-1.0.0.jar"}
+        ]}]"#;
+        let client = MockClient::default()
+            .with(FIRST_PAGE, RELEASES)
+            .with_header(
+                FIRST_PAGE,
+                "Link",
+                &format!(r#"<{second}>; rel="next", <{second}>; rel="last""#),
+            )
+            .with(second, older);
+        let resolver = Resolver::new(client);
+        let artifact = block_on(resolver.resolve(&file, &Versions::default())).unwrap();
+        assert_eq!(artifact.filename, "mod-1.0.0.jar");
+        assert_eq!(resolver.client().requests.lock().unwrap().len(), 2);
+
+        // Running out of anonymous requests points at setting a token
+        let client = MockClient::default()
+            .with_status(FIRST_PAGE, 403, "{}")
+            .with_header(FIRST_PAGE, "X-RateLimit-Remaining", "0");
+        let error =
+            block_on(Resolver::new(client).resolve(&file, &Versions::default())).unwrap_err();
+        assert!(matches!(
+            error,
+            ResolveError::RateLimited {
+                authenticated: false,
+                ..
+            }
+        ));
+        assert!(error.suggestion().unwrap().contains("FFPACK_GITHUB_TOKEN"));
+    }
+}
@@ -0,0 +1,161 @@
+//! Resolution of [`Source::SlugReleases`](crate::types::Source::SlugReleases) through the
+//! GitLab releases API
+
+use serde::Deserialize;
+use snafu::ResultExt;
+use url::Url;
+
+use super::{
+    api_url,
+    releases::{self, next_page, Matcher, MAX_PAGES},
+    HttpSnafu, ResolveError, ResolvedArtifact, Resolver,
+};
+use crate::{
+    http::{HttpClient, Response},
+    types::ForgeSlug,
+};
+
+/// How many releases are asked for at once, the most GitLab allows
+const PER_PAGE: &str = "100";
+
+/// A release, as returned by `/projects/{path}/releases`
+#[derive(Deserialize)]
+struct Release {
+    /// The release title
+    name: Option<String>,
+    /// The tag the release was made from
+    tag_name: String,
+    /// Whether the release is scheduled for the future, and not out yet
+    #[serde(default, rename = "upcoming_release")]
+    upcoming: bool,
+    /// The files attached to the release
+    #[serde(default)]
+    assets: Assets,
+    /// Links to the release's pages
+    #[serde(default, rename = "_links")]
+    links: ReleaseLinks,
+}
+
+/// The pages of a release
+#[derive(Deserialize, Default)]
+struct ReleaseLinks {
+    /// The release's own page
+    #[serde(rename = "self")]
+    page: Option<Url>,
+}
+
+/// The files of a release
+#[derive(Deserialize, Default)]
+struct Assets {
+    /// Links to the uploaded files, GitLab keeping no files of its own for releases
+    #[serde(default)]
+    links: Vec<Link>,
+}
+
+/// A file linked from a release
+#[derive(Deserialize)]
+struct Link {
+    /// The name shown for the file
+    name: String,
+    /// Where the file is hosted
+    url: Url,
+    /// The permanent url GitLab redirects to `url` from, if there is one
+    direct_asset_url: Option<Url>,
+}
+
+/// Resolves the single matching artifact of the newest matching release
+///
+/// GitLab doesn't list sizes or digests for release files, so these are left to the download
+/// to find out.
+pub(super) async fn resolve_release<C: HttpClient>(
+    resolver: &Resolver<C>,
+    slug: &ForgeSlug,
+    matcher: &Matcher<'_>,
+) -> Result<ResolvedArtifact, ResolveError> {
+    // The project is named by its whole path, as a single escaped segment
+    let path = slug.path();
+    let mut url = api_url(&resolver.endpoints.gitlab, ["projects", &path, "releases"]);
+    url.query_pairs_mut().append_pair("per_page", PER_PAGE);
+    let mut next = Some(url);
+    let mut pages = 0;
+    while let Some(url) = next.take().filter(|_| pages < MAX_PAGES) {
+        pages += 1;
+        let response = resolver
+            .get(resolver.request(url))
+            .await
+            .and_then(Response::error_for_status)
+            .context(HttpSnafu)?;
+        next = next_page(&response);
+        let found: Vec<Release> = response.json().await.context(HttpSnafu)?;
+        for release in found.into_iter().filter(|release| !release.upcoming) {
+            let release = releases::Release {
+                title: release.name,
+                tag: release.tag_name,
+                page: release.links.page,
+                assets: release
+                    .assets
+                    .links
+                    .into_iter()
+                    .map(|link| releases::Asset {
+                        name: link.name,
+                        size: None,
+                        url: link.direct_asset_url.unwrap_or(link.url),
+                        sha256: None,
+                    })
+                    .collect(),
+            };
+            if let Some(artifact) = matcher.pick(release)? {
+                return Ok(artifact);
+            }
+        }
+    }
+    Err(matcher.no_match())
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use crate::{
+        http::testing::{block_on, MockClient},
+        resolve::Resolver,
+        types::{ManagedFile, Source, Versions},
+    };
+
+    // Releases of nested projects are found, preferring permanent links to files
+    #[test]
+    fn releases() {
+        let releases = r#"[
+            {"name": "Next", "tag_name": "v2.0.0", "upcoming_release": true, "assets": {"links": [
+                {"name": "mod-2.0.0.jar", "url": "https://example.org/mod-2.0.0.jar"}
+            ]}},
+            {"name": "Version 1", "tag_name": "v1.0.0", "assets": {"links": [
+                {"name": "mod-1.0.0.jar", "url": "https://example.org/mod-1.0.0.jar",
+                 "direct_asset_url": "https://gitlab.com/g/s/r/-/releases/v1.0.0/downloads/mod.jar"}
+            ]}}
+        ]"#;
+        let client = MockClient::default().with(
+            "https://gitlab.com/api/v4/projects/g%2Fs%2Fr/releases?per_page=100",
+            releases,
+        );
+        let file = ManagedFile {
+            source: Source::SlugReleases {
+                slug: "gitlab:g/s/r".to_string(),
+                artifact_regex: r"^mod-.*\.jar$".to_string(),
+                release_regex: None,
+            },
+            ..ManagedFile::default()
+        };
+        let artifact =
+            block_on(Resolver::new(client).resolve(&file, &Versions::default())).unwrap();
+        assert_eq!(artifact.filename, "mod-1.0.0.jar");
+        assert_eq!(
+            artifact.download_url.as_str(),
+            "https://gitlab.com/g/s/r/-/releases/v1.0.0/downloads/mod.jar"
+        );
+        assert_eq!(artifact.size, None);
+        assert_eq!(artifact.details.authors, ["g/s"]);
+        assert_eq!(
+            artifact.details.url.unwrap().as_str(),
+            "https://gitlab.com/g/s/r"
+        );
+    }
+}
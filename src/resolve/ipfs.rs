@@ -0,0 +1,264 @@
+//! Resolution of [`Source::Ipfs`](crate::types::Source::Ipfs) to a gateway url
+//!
+//! A CID names a file by a digest, but only a CID of the `raw` codec is a digest of the file
+//! itself; the usual `dag-pb` CIDs digest a tree of chunks that gateways put back together.
+//! Digests of raw CIDs are checked like any other, and every file is checked against the blake3
+//! hash its source records.
+
+use snafu::{ensure, OptionExt};
+use url::Url;
+
+use super::{
+    api_url, CidMismatchSnafu, InvalidCidSnafu, ProjectDetails, ResolveError, ResolvedArtifact,
+    Resolver, UpstreamIds,
+};
+use crate::{
+    http::HttpClient,
+    types::{HashAlgorithm, Hashes},
+};
+
+/// The multicodec of a CID naming a file's bytes directly
+const RAW: u64 = 0x55;
+/// The multicodec of a CID naming a UnixFS tree
+const DAG_PB: u64 = 0x70;
+/// The multihash code of SHA-256
+const SHA2_256: u64 = 0x12;
+/// The multihash code of blake3
+const BLAKE3: u64 = 0x1e;
+
+/// The digits of base58 as bitcoin writes them, which CIDs use
+const BASE58: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+/// The digits of base32 as RFC 4648 writes them, lowercased
+const BASE32: &[u8] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+/// A parsed content id
+#[derive(Debug, PartialEq, Eq)]
+struct Cid {
+    /// The multicodec of the content
+    codec: u64,
+    /// The multihash code of the digest
+    hash: u64,
+    /// The digest
+    digest: Vec<u8>,
+}
+
+impl Cid {
+    /// Parses a CIDv0 or a CIDv1 in base32, base58, or hex, returning `None` if it isn't one
+    fn parse(cid: &str) -> Option<Self> {
+        // Version 0 is a bare base58 SHA-256 multihash of a UnixFS tree
+        if cid.len() == 46 && cid.starts_with("Qm") {
+            let bytes = base58(cid)?;
+            let (hash, digest) = multihash(&bytes)?;
+            return Some(Self {
+                codec: DAG_PB,
+                hash,
+                digest,
+            });
+        }
+        let mut chars = cid.chars();
+        let bytes = match chars.next()? {
+            'b' => base32(chars.as_str())?,
+            'B' => base32(&chars.as_str().to_lowercase())?,
+            'z' => base58(chars.as_str())?,
+            'f' => hex::decode(chars.as_str()).ok()?,
+            _ => return None,
+        };
+        let mut rest = &bytes[..];
+        let version = varint(&mut rest)?;
+        let codec = varint(&mut rest)?;
+        let (hash, digest) = multihash(rest)?;
+        (version == 1).then_some(Self {
+            codec,
+            hash,
+            digest,
+        })
+    }
+
+    /// The digests of the file a raw CID names, which are none for other CIDs
+    fn file_hashes(&self) -> Hashes {
+        let mut hashes = Hashes::default();
+        if self.codec == RAW {
+            let algorithm = match self.hash {
+                SHA2_256 => HashAlgorithm::Sha256,
+                BLAKE3 => HashAlgorithm::Blake3,
+                _ => return hashes,
+            };
+            hashes.set(algorithm, &self.digest);
+        }
+        hashes
+    }
+}
+
+/// Reads an unsigned varint, as multiformats write them, from the front of `bytes`
+fn varint(bytes: &mut &[u8]) -> Option<u64> {
+    let mut value = 0;
+    for (index, &byte) in bytes.iter().enumerate().take(9) {
+        value |= u64::from(byte & 0x7f) << (7 * index);
+        if byte & 0x80 == 0 {
+            *bytes = &bytes[index + 1..];
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// Splits a multihash into its code and digest
+fn multihash(mut bytes: &[u8]) -> Option<(u64, Vec<u8>)> {
+    let code = varint(&mut bytes)?;
+    let length = usize::try_from(varint(&mut bytes)?).ok()?;
+    (bytes.len() == length).then(|| (code, bytes.to_vec()))
+}
+
+/// Decodes base58, returning `None` for anything else
+fn base58(text: &str) -> Option<Vec<u8>> {
+    // Digits are accumulated into a little-endian big number
+    let mut number: Vec<u8> = Vec::new();
+    for c in text.bytes() {
+        let mut carry = u32::try_from(BASE58.iter().position(|&digit| digit == c)?).ok()?;
+        for byte in &mut number {
+            carry += u32::from(*byte) * 58;
+            *byte = (carry & 0xff) as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            number.push((carry & 0xff) as u8);
+            carry >>= 8;
+        }
+    }
+    // Leading ones stand for leading zero bytes
+    let zeros = text.bytes().take_while(|&c| c == b'1').count();
+    number.extend(std::iter::repeat_n(0, zeros));
+    number.reverse();
+    Some(number)
+}
+
+/// Decodes unpadded lowercase base32, returning `None` for anything else
+fn base32(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len() * 5 / 8);
+    let (mut buffer, mut bits) = (0u32, 0);
+    for c in text.bytes() {
+        let digit = u32::try_from(BASE32.iter().position(|&digit| digit == c)?).ok()?;
+        buffer = (buffer << 5) | digit;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push(((buffer >> bits) & 0xff) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(bytes)
+}
+
+/// Resolves a file on IPFS to its url on the source's gateway, or the resolver's
+pub(super) fn resolve<C: HttpClient>(
+    resolver: &Resolver<C>,
+    cid: &str,
+    gateway: Option<&Url>,
+    hashes: &Hashes,
+    filename: &str,
+) -> Result<ResolvedArtifact, ResolveError> {
+    let parsed = Cid::parse(cid).context(InvalidCidSnafu { cid })?;
+    let named = parsed.file_hashes();
+    // A source whose hash differs from its CID can't ever verify, whichever is wrong
+    let agrees = HashAlgorithm::ALL.into_iter().all(|algorithm| {
+        match (named.get(algorithm), hashes.get(algorithm)) {
+            (Some(named), Some(recorded)) => named == recorded,
+            _ => true,
+        }
+    });
+    ensure!(agrees, CidMismatchSnafu { cid });
+    let mut all = named;
+    all.merge(hashes);
+    let gateway = gateway.unwrap_or(&resolver.endpoints.ipfs);
+    Ok(ResolvedArtifact {
+        download_url: api_url(gateway, ["ipfs", cid]),
+        filename: filename.to_string(),
+        size: None,
+        hashes: all,
+        mirrors: Vec::new(),
+        extract: None,
+        ids: UpstreamIds::default(),
+        details: ProjectDetails::default(),
+    })
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::{
+        hash::blake3,
+        http::testing::{block_on, MockClient},
+        types::{ManagedFile, Source, Versions},
+    };
+
+    // CIDs are read in each encoding, and only raw ones name the file's digest
+    #[test]
+    fn cids() {
+        let v0 = Cid::parse("QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG").unwrap();
+        assert_eq!(v0.codec, DAG_PB);
+        assert_eq!(v0.hash, SHA2_256);
+        assert_eq!(v0.digest.len(), 32);
+        assert!(v0.file_hashes().is_empty());
+        let v1 = Cid::parse("bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi").unwrap();
+        assert_eq!(v1.codec, DAG_PB);
+
+        // The raw CID of "hello", in base32 and hex
+        let digest = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        let hex = format!("f01551220{digest}");
+        let raw = Cid::parse(&hex).unwrap();
+        assert_eq!(
+            raw.file_hashes().sha256,
+            hex::decode(digest).unwrap().try_into().ok()
+        );
+        let base32 = "bafkreibm6jg3ux5qumhcn2b3flc3tyu6dmlb4xa7u5bf44yegnrjhc4yeq";
+        assert_eq!(Cid::parse(base32), Some(raw));
+
+        for invalid in ["", "Qm", "bafk!", "fzz", "f0155122000", "x1234"] {
+            assert_eq!(Cid::parse(invalid), None, "{invalid}");
+        }
+    }
+
+    // Files resolve to the gateway, with the digest a raw CID names
+    #[test]
+    fn gateways() {
+        let resolver = Resolver::new(MockClient::default());
+        let resolve = |cid: &str, gateway: Option<&str>, sha256: Option<[u8; 32]>| {
+            let file = ManagedFile {
+                source: Source::Ipfs {
+                    cid: cid.into(),
+                    gateway: gateway.map(|gateway| Url::parse(gateway).unwrap()),
+                    blake3: blake3(b"hello"),
+                    hashes: Hashes {
+                        sha256,
+                        ..Hashes::default()
+                    },
+                },
+                ..ManagedFile::default()
+            };
+            block_on(resolver.resolve(&file, &Versions::default()))
+        };
+        let raw = "bafkreibm6jg3ux5qumhcn2b3flc3tyu6dmlb4xa7u5bf44yegnrjhc4yeq";
+        let artifact = resolve(raw, None, None).unwrap();
+        assert_eq!(
+            artifact.download_url.as_str(),
+            format!("https://ipfs.io/ipfs/{raw}")
+        );
+        assert_eq!(artifact.hashes.blake3, Some(blake3(b"hello")));
+        assert!(artifact.hashes.sha256.is_some());
+        let artifact = resolve(raw, Some("https://dweb.example.org/"), None).unwrap();
+        assert!(artifact
+            .download_url
+            .as_str()
+            .starts_with("https://dweb.example.org/ipfs/"));
+
+        assert!(matches!(
+            resolve(raw, None, Some([0; 32])),
+            Err(ResolveError::CidMismatch { .. })
+        ));
+        assert!(matches!(
+            resolve("not a cid", None, None),
+            Err(ResolveError::InvalidCid { .. })
+        ));
+        assert!(resolver.client().requests.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,228 @@
+//! Resolution of [`Source::Jenkins`](crate::types::Source::Jenkins) through the JSON API of a
+//! Jenkins instance
+
+use regex::Regex;
+use serde::Deserialize;
+use snafu::{ensure, OptionExt, ResultExt};
+use url::Url;
+
+use super::{
+    api_url, decode_hash, AmbiguousArtifactSnafu, HttpSnafu, InvalidRegexSnafu,
+    NoMatchingVersionSnafu, ProjectDetails, ResolveError, ResolvedArtifact, Resolver, UpstreamIds,
+};
+use crate::{http::HttpClient, types::Hashes};
+
+/// The fields of a build that are asked for, rather than everything Jenkins knows about it
+const TREE: &str = "number,url,artifacts[fileName,relativePath],fingerprint[fileName,hash]";
+
+/// A build, as returned by `/job/{job}/{build}/api/json`
+#[derive(Deserialize)]
+struct Build {
+    /// The build number
+    number: u32,
+    /// The build's page, which its artifacts are found under
+    url: Url,
+    /// The files the build archived
+    #[serde(default)]
+    artifacts: Vec<Artifact>,
+    /// The MD5 digests Jenkins recorded for the build's files
+    #[serde(default)]
+    fingerprint: Vec<Fingerprint>,
+}
+
+/// A file archived by a build
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Artifact {
+    /// The file name
+    file_name: String,
+    /// The path of the file in the build's workspace
+    relative_path: String,
+}
+
+/// A digest recorded for a file
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Fingerprint {
+    /// The file name
+    file_name: String,
+    /// The MD5 digest of the file
+    hash: String,
+}
+
+/// Resolves the single matching artifact of a build, the last successful one unless `number`
+/// pins another
+pub(super) async fn resolve<C: HttpClient>(
+    resolver: &Resolver<C>,
+    base_url: &Url,
+    job: &str,
+    artifact_regex: &str,
+    number: Option<u32>,
+) -> Result<ResolvedArtifact, ResolveError> {
+    let regex = Regex::new(artifact_regex).context(InvalidRegexSnafu {
+        regex: artifact_regex,
+    })?;
+    let build = number.map_or_else(|| "lastSuccessfulBuild".to_string(), |n| n.to_string());
+    // Jobs in folders are nested as `job/folder/job/name`
+    let path: Vec<&str> = job.split('/').flat_map(|name| ["job", name]).collect();
+    let mut url = api_url(
+        base_url,
+        path.iter().copied().chain([&*build, "api", "json"]),
+    );
+    url.query_pairs_mut().append_pair("tree", TREE);
+    let versions = match number {
+        Some(number) => format!("build {number}"),
+        None => "a successful build".to_string(),
+    };
+    let response = resolver
+        .get(resolver.request(url))
+        .await
+        .context(HttpSnafu)?;
+    ensure!(
+        response.status != 404,
+        NoMatchingVersionSnafu {
+            project: job,
+            versions: &versions,
+        }
+    );
+    let build: Build = response
+        .error_for_status()
+        .context(HttpSnafu)?
+        .json()
+        .await
+        .context(HttpSnafu)?;
+
+    let mut matches: Vec<_> = build
+        .artifacts
+        .into_iter()
+        .filter(|artifact| regex.is_match(&artifact.file_name))
+        .collect();
+    ensure!(
+        matches.len() <= 1,
+        AmbiguousArtifactSnafu {
+            project: job,
+            release: format!("build {}", build.number),
+            matches: matches
+                .iter()
+                .map(|artifact| artifact.file_name.clone())
+                .collect::<Vec<_>>(),
+        }
+    );
+    let artifact = matches.pop().context(NoMatchingVersionSnafu {
+        project: job,
+        versions: format!("{versions} with an artifact matching {regex}"),
+    })?;
+    let md5 = build
+        .fingerprint
+        .iter()
+        .find(|fingerprint| fingerprint.file_name == artifact.file_name)
+        .and_then(|fingerprint| decode_hash("md5", &fingerprint.hash));
+    let download_url = api_url(
+        &build.url,
+        ["artifact"]
+            .into_iter()
+            .chain(artifact.relative_path.split('/')),
+    );
+    let page = api_url(base_url, path.iter().copied().chain([""]));
+    let changes = api_url(&build.url, ["changes"]);
+    Ok(ResolvedArtifact {
+        download_url,
+        filename: artifact.file_name,
+        size: None,
+        hashes: Hashes {
+            md5,
+            ..Hashes::default()
+        },
+        mirrors: Vec::new(),
+        extract: None,
+        ids: UpstreamIds {
+            version_id: Some(build.number.to_string()),
+            ..UpstreamIds::default()
+        },
+        details: ProjectDetails {
+            name: job.rsplit('/').next().map(Into::into),
+            version: Some(build.number.to_string()),
+            url: Some(page),
+            authors: Vec::new(),
+            license: None,
+            changelog: Some(changes),
+        },
+    })
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::{
+        http::testing::{block_on, MockClient},
+        types::{ManagedFile, Source, Versions},
+    };
+
+    // The last successful build is used unless a build is pinned, and folders nest
+    #[test]
+    fn builds() {
+        let build = |number: u32| {
+            format!(
+                r#"{{"number": {number},
+                "url": "https://ci.example.org/job/mods/job/lib/{number}/",
+                "artifacts": [
+                    {{"fileName": "lib-{number}.jar", "relativePath": "build/libs/lib-{number}.jar"}},
+                    {{"fileName": "lib-{number}-sources.jar",
+                      "relativePath": "build/libs/lib-{number}-sources.jar"}}
+                ],
+                "fingerprint": [{{"fileName": "lib-{number}.jar", "hash": "{}"}}]}}"#,
+                "03".repeat(16)
+            )
+        };
+        let api = |build: &str| {
+            format!(
+                "https://ci.example.org/job/mods/job/lib/{build}/api/json?tree=number%2Curl%2C\
+                 artifacts%5BfileName%2CrelativePath%5D%2Cfingerprint%5BfileName%2Chash%5D"
+            )
+        };
+        let client = MockClient::default()
+            .with(&api("lastSuccessfulBuild"), build(12))
+            .with(&api("7"), build(7));
+        let resolver = Resolver::new(client);
+        let resolve = |regex: &str, build: Option<u32>| {
+            let file = ManagedFile {
+                source: Source::Jenkins {
+                    base_url: Url::parse("https://ci.example.org/").unwrap(),
+                    job: "mods/lib".into(),
+                    artifact_regex: regex.into(),
+                    build,
+                },
+                ..ManagedFile::default()
+            };
+            block_on(resolver.resolve(&file, &Versions::default()))
+        };
+
+        let artifact = resolve(r"^lib-\d+\.jar$", None).unwrap();
+        assert_eq!(
+            artifact.download_url.as_str(),
+            "https://ci.example.org/job/mods/job/lib/12/artifact/build/libs/lib-12.jar"
+        );
+        assert_eq!(artifact.hashes.md5, Some([3; 16]));
+        assert_eq!(artifact.details.version.as_deref(), Some("12"));
+        assert_eq!(
+            artifact.details.url.unwrap().as_str(),
+            "https://ci.example.org/job/mods/job/lib/"
+        );
+        let pinned = resolve(r"sources\.jar$", Some(7)).unwrap();
+        assert_eq!(pinned.filename, "lib-7-sources.jar");
+        assert_eq!(pinned.hashes.md5, None);
+
+        assert!(matches!(
+            resolve(r"\.jar$", None),
+            Err(ResolveError::AmbiguousArtifact { .. })
+        ));
+        assert!(matches!(
+            resolve(r"\.zip$", None),
+            Err(ResolveError::NoMatchingVersion { .. })
+        ));
+        assert!(matches!(
+            resolve(r"\.jar$", Some(8)),
+            Err(ResolveError::NoMatchingVersion { .. })
+        ));
+    }
+}
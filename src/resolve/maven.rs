@@ -0,0 +1,293 @@
+//! Resolution of [`Source::Maven`](crate::types::Source::Maven) from a maven repository
+//!
+//! Versions other than an exact one are looked up in the artifact's `maven-metadata.xml`, and
+//! snapshots in the metadata of the snapshot, which names the timestamped build to use. The
+//! file's SHA-1 comes from the `.sha1` file maven repositories publish next to it.
+
+mod version;
+
+use snafu::{OptionExt, ResultExt};
+use url::Url;
+
+use self::version::{MavenVersion, VersionRange};
+use super::{
+    api_url, decode_hash, HttpSnafu, InvalidVersionRangeSnafu, NoMatchingVersionSnafu,
+    ProjectDetails, ResolveError, ResolvedArtifact, Resolver, UpstreamIds,
+};
+use crate::{
+    http::{HttpClient, Response},
+    types::Hashes,
+};
+
+/// The file name of the metadata listing an artifact's versions, or a snapshot's builds
+const METADATA: &str = "maven-metadata.xml";
+
+/// Returns the text inside each `<tag>` element of an XML document, trimmed
+///
+/// Maven metadata is simple enough not to need a real XML parser: the elements read from it
+/// hold only text, or elements that do.
+fn elements<'a>(xml: &'a str, tag: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+    let open = format!("<{tag}>");
+    let close = format!("</{tag}>");
+    let mut rest = xml;
+    std::iter::from_fn(move || {
+        let start = rest.find(&open)? + open.len();
+        let end = start + rest[start..].find(&close)?;
+        let text = rest[start..end].trim();
+        rest = &rest[end + close.len()..];
+        Some(text)
+    })
+}
+
+/// Returns the text inside the first `<tag>` element of an XML document, if there is one
+fn element<'a>(xml: &'a str, tag: &'a str) -> Option<&'a str> {
+    elements(xml, tag).next().filter(|text| !text.is_empty())
+}
+
+/// Which version of an artifact a source asks for
+enum Wanted {
+    /// Exactly this version
+    Exact(String),
+    /// The newest version, snapshots included
+    Latest,
+    /// The newest version that isn't a snapshot
+    Release,
+    /// The newest version inside a range
+    Range(VersionRange),
+}
+
+impl Wanted {
+    /// Reads the version given in a source
+    fn new(version: &str) -> Result<Self, ResolveError> {
+        Ok(match version {
+            "latest" | "LATEST" => Wanted::Latest,
+            "release" | "RELEASE" => Wanted::Release,
+            range if range.starts_with(['[', '(']) => Wanted::Range(
+                VersionRange::parse(range).context(InvalidVersionRangeSnafu { range })?,
+            ),
+            exact => Wanted::Exact(exact.to_string()),
+        })
+    }
+
+    /// Picks the version from an artifact's metadata
+    fn pick(&self, metadata: &str) -> Option<String> {
+        let newest = |include: &dyn Fn(&MavenVersion) -> bool| {
+            elements(metadata, "version")
+                .map(MavenVersion::new)
+                .filter(|version| include(version))
+                .max()
+                .map(|version| version.as_str().to_string())
+        };
+        match self {
+            Wanted::Exact(version) => Some(version.clone()),
+            // The tags are only updated by some tools, so the listing is trusted over them
+            Wanted::Latest => {
+                newest(&|_| true).or_else(|| element(metadata, "latest").map(Into::into))
+            }
+            Wanted::Release => newest(&|version| !version.is_snapshot())
+                .or_else(|| element(metadata, "release").map(Into::into)),
+            Wanted::Range(range) => newest(&|version| range.contains(version)),
+        }
+    }
+}
+
+/// Returns the value that names the file of a snapshot build, like `1.0-20240101.120000-3`
+fn snapshot_value(metadata: &str, version: &str, classifier: Option<&str>) -> Option<String> {
+    let listed = elements(metadata, "snapshotVersion").find_map(|snapshot| {
+        let extension = element(snapshot, "extension").unwrap_or("jar");
+        (extension == "jar" && element(snapshot, "classifier") == classifier)
+            .then(|| element(snapshot, "value"))
+            .flatten()
+    });
+    if let Some(value) = listed {
+        return Some(value.to_string());
+    }
+    // Older repositories only name the newest build
+    let timestamp = element(metadata, "timestamp")?;
+    let build = element(metadata, "buildNumber")?;
+    let base = version.strip_suffix("-SNAPSHOT")?;
+    Some(format!("{base}-{timestamp}-{build}"))
+}
+
+/// Fetches a text file from the repository, returning `None` if it isn't there
+async fn fetch<C: HttpClient>(
+    resolver: &Resolver<C>,
+    url: Url,
+) -> Result<Option<String>, ResolveError> {
+    let response = resolver
+        .get(resolver.request(url))
+        .await
+        .context(HttpSnafu)?;
+    if response.status == 404 {
+        return Ok(None);
+    }
+    let bytes = Response::error_for_status(response)
+        .context(HttpSnafu)?
+        .bytes()
+        .await
+        .context(HttpSnafu)?;
+    Ok(Some(String::from_utf8_lossy(&bytes).into_owned()))
+}
+
+/// Resolves the jar of a maven artifact
+pub(super) async fn resolve<C: HttpClient>(
+    resolver: &Resolver<C>,
+    repository: &Url,
+    group: &str,
+    artifact: &str,
+    version: &str,
+    classifier: Option<&str>,
+) -> Result<ResolvedArtifact, ResolveError> {
+    let coordinates = format!("{group}:{artifact}");
+    let base: Vec<&str> = group.split('.').chain([artifact]).collect();
+    let wanted = Wanted::new(version)?;
+    let version = match wanted {
+        Wanted::Exact(version) => version,
+        wanted => {
+            let url = api_url(repository, base.iter().copied().chain([METADATA]));
+            let metadata = fetch(resolver, url).await?.unwrap_or_default();
+            wanted.pick(&metadata).context(NoMatchingVersionSnafu {
+                project: &coordinates,
+                versions: version,
+            })?
+        }
+    };
+
+    let mut value = version.clone();
+    if MavenVersion::new(&version).is_snapshot() {
+        let url = api_url(
+            repository,
+            base.iter().copied().chain([&*version, METADATA]),
+        );
+        if let Some(metadata) = fetch(resolver, url).await? {
+            if let Some(snapshot) = snapshot_value(&metadata, &version, classifier) {
+                value = snapshot;
+            }
+        }
+    }
+    let filename = match classifier {
+        Some(classifier) => format!("{artifact}-{value}-{classifier}.jar"),
+        None => format!("{artifact}-{value}.jar"),
+    };
+    let download_url = api_url(
+        repository,
+        base.iter().copied().chain([&*version, &*filename]),
+    );
+    let checksum = api_url(
+        repository,
+        base.iter()
+            .copied()
+            .chain([&*version, &format!("{filename}.sha1")]),
+    );
+    // Checksum files hold the digest, sometimes followed by the file name
+    let sha1 = fetch(resolver, checksum).await?.and_then(|checksum| {
+        let digest = checksum.split_whitespace().next()?;
+        decode_hash("sha1", digest)
+    });
+    Ok(ResolvedArtifact {
+        download_url,
+        filename,
+        size: None,
+        hashes: Hashes {
+            sha1,
+            ..Hashes::default()
+        },
+        mirrors: Vec::new(),
+        extract: None,
+        ids: UpstreamIds {
+            version_id: Some(version.clone()),
+            ..UpstreamIds::default()
+        },
+        details: ProjectDetails {
+            name: Some(artifact.to_string()),
+            version: Some(version),
+            ..ProjectDetails::default()
+        },
+    })
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+    use crate::{
+        http::testing::{block_on, MockClient},
+        types::{ManagedFile, Source, Versions},
+    };
+
+    // Versions resolve exactly, from the listing, or to the newest snapshot build
+    #[test]
+    fn versions() {
+        let metadata = "<metadata><versioning>
+            <latest>0.93.0-SNAPSHOT</latest><release>0.92.0</release>
+            <versions>
+                <version>0.90.0</version><version>0.92.0</version><version>0.91.2</version>
+                <version>0.93.0-SNAPSHOT</version>
+            </versions>
+        </versioning></metadata>";
+        let snapshot = "<metadata><versioning><snapshotVersions>
+            <snapshotVersion><classifier>dev</classifier><extension>jar</extension>
+                <value>0.93.0-20240101.120000-3</value></snapshotVersion>
+            <snapshotVersion><extension>pom</extension>
+                <value>0.93.0-20240101.120000-3</value></snapshotVersion>
+            <snapshotVersion><extension>jar</extension>
+                <value>0.93.0-20240101.120000-3</value></snapshotVersion>
+        </snapshotVersions></versioning></metadata>";
+        let base = "https://maven.example.org/net/example/lib";
+        let sha1 = "aa".repeat(20);
+        let client = MockClient::default()
+            .with(&format!("{base}/maven-metadata.xml"), metadata)
+            .with(
+                &format!("{base}/0.93.0-SNAPSHOT/maven-metadata.xml"),
+                snapshot,
+            )
+            .with(
+                &format!("{base}/0.92.0/lib-0.92.0.jar.sha1"),
+                format!("{sha1}  lib-0.92.0.jar\n"),
+            );
+        let resolver = Resolver::new(client);
+        let resolve = |version: &str, classifier: Option<&str>| {
+            let file = ManagedFile {
+                source: Source::Maven {
+                    repository: Url::parse("https://maven.example.org").unwrap(),
+                    group: "net.example".into(),
+                    artifact: "lib".into(),
+                    version: version.into(),
+                    classifier: classifier.map(Into::into),
+                },
+                ..ManagedFile::default()
+            };
+            block_on(resolver.resolve(&file, &Versions::default()))
+        };
+
+        let release = resolve("release", None).unwrap();
+        assert_eq!(
+            release.download_url.as_str(),
+            format!("{base}/0.92.0/lib-0.92.0.jar")
+        );
+        assert_eq!(release.hashes.sha1, Some([0xaa; 20]));
+        assert_eq!(release.details.version.as_deref(), Some("0.92.0"));
+        let ranged = resolve("[0.91,0.92)", None).unwrap();
+        assert_eq!(ranged.filename, "lib-0.91.2.jar");
+        assert_eq!(ranged.hashes.sha1, None);
+        let exact = resolve("0.80.0", Some("sources")).unwrap();
+        assert_eq!(exact.filename, "lib-0.80.0-sources.jar");
+        let latest = resolve("latest", Some("dev")).unwrap();
+        assert_eq!(
+            latest.download_url.as_str(),
+            format!("{base}/0.93.0-SNAPSHOT/lib-0.93.0-20240101.120000-3-dev.jar")
+        );
+        assert_eq!(
+            resolve("0.93.0-SNAPSHOT", None).unwrap().filename,
+            "lib-0.93.0-20240101.120000-3.jar"
+        );
+
+        assert!(matches!(
+            resolve("[1.0,2.0)", None),
+            Err(ResolveError::NoMatchingVersion { .. })
+        ));
+        assert!(matches!(
+            resolve("[1.0", None),
+            Err(ResolveError::InvalidVersionRange { .. })
+        ));
+    }
+}
@@ -0,0 +1,276 @@
+//! Maven's ordering of versions, and the version ranges built on it
+//!
+//! Maven versions aren't semver: `1.0` and `1` are the same version, `1.0-alpha` comes before
+//! `1.0` but `1.0-sp` after it, and anything else goes. The ordering follows Maven's
+//! `ComparableVersion` closely enough for the versions mods and libraries are published under.
+
+use std::cmp::Ordering;
+
+/// The rank of a release among qualifiers, which trailing items compare against
+const RELEASE: usize = 5;
+
+/// One part of a version, split at separators and where digits meet letters
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Item {
+    /// A number, without leading zeros
+    Number(String),
+    /// A qualifier, lowercased
+    Qualifier(String),
+}
+
+impl Item {
+    /// Returns the rank of a qualifier, with unknown qualifiers after every known one
+    fn rank(qualifier: &str) -> usize {
+        match qualifier {
+            "alpha" | "a" => 0,
+            "beta" | "b" => 1,
+            "milestone" | "m" => 2,
+            "rc" | "cr" => 3,
+            "snapshot" => 4,
+            "" | "ga" | "final" | "release" => RELEASE,
+            "sp" => 6,
+            _ => 7,
+        }
+    }
+
+    /// Compares against another item, or against the end of a shorter version
+    fn compare(this: Option<&Item>, other: Option<&Item>) -> Ordering {
+        match (this, other) {
+            (Some(Item::Number(a)), Some(Item::Number(b))) => (a.len(), a).cmp(&(b.len(), b)),
+            (Some(Item::Number(a)), None) if a.is_empty() => Ordering::Equal,
+            (Some(Item::Number(_)), Some(Item::Qualifier(_)) | None) => Ordering::Greater,
+            (Some(Item::Qualifier(_)), Some(Item::Number(_))) => Ordering::Less,
+            (Some(Item::Qualifier(a)), Some(Item::Qualifier(b))) => {
+                (Item::rank(a), a).cmp(&(Item::rank(b), b))
+            }
+            (Some(Item::Qualifier(a)), None) => Item::rank(a).cmp(&RELEASE),
+            (None, Some(_)) => Item::compare(other, this).reverse(),
+            (None, None) => Ordering::Equal,
+        }
+    }
+}
+
+/// A version, ordered the way Maven orders them
+#[derive(Debug, Clone)]
+pub(super) struct MavenVersion {
+    /// The version as written
+    text: String,
+    /// The items of the version, without the trailing ones that don't change its order
+    items: Vec<Item>,
+}
+
+impl MavenVersion {
+    /// Splits a version into its items
+    pub(super) fn new(text: &str) -> Self {
+        let mut items = Vec::new();
+        let mut current = String::new();
+        let mut push = |current: &mut String| {
+            if current.starts_with(|c: char| c.is_ascii_digit()) {
+                let number = current.trim_start_matches('0');
+                items.push(Item::Number(number.to_string()));
+            } else {
+                items.push(Item::Qualifier(current.to_lowercase()));
+            }
+            current.clear();
+        };
+        for c in text.trim().chars() {
+            if c == '.' || c == '-' || c == '_' {
+                push(&mut current);
+            } else {
+                let digit = c.is_ascii_digit();
+                if current.starts_with(|c: char| c.is_ascii_digit()) != digit && !current.is_empty()
+                {
+                    push(&mut current);
+                }
+                current.push(c);
+            }
+        }
+        push(&mut current);
+        while items
+            .last()
+            .is_some_and(|item| Item::compare(Some(item), None) == Ordering::Equal)
+        {
+            items.pop();
+        }
+        Self {
+            text: text.trim().to_string(),
+            items,
+        }
+    }
+
+    /// Returns the version as written
+    pub(super) fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns true for snapshot versions, which change under the same name
+    pub(super) fn is_snapshot(&self) -> bool {
+        self.text.ends_with("-SNAPSHOT")
+    }
+}
+
+impl PartialEq for MavenVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for MavenVersion {}
+
+impl PartialOrd for MavenVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for MavenVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let length = self.items.len().max(other.items.len());
+        (0..length)
+            .map(|index| Item::compare(self.items.get(index), other.items.get(index)))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+/// One end of a range
+#[derive(Debug, Clone)]
+struct Bound {
+    /// The version at the end
+    version: MavenVersion,
+    /// Whether the version itself is inside the range
+    inclusive: bool,
+}
+
+/// A maven version range, like `[1.0,2.0)` or `(,1.0],[1.2,)`
+#[derive(Debug, Clone)]
+pub(super) struct VersionRange {
+    /// The intervals of the range, as lower and upper bounds, with `None` for no bound
+    intervals: Vec<(Option<Bound>, Option<Bound>)>,
+}
+
+impl VersionRange {
+    /// Parses a range of one or more comma separated intervals, returning `None` if it isn't
+    /// one
+    pub(super) fn parse(range: &str) -> Option<Self> {
+        let mut intervals = Vec::new();
+        let mut rest = range.trim();
+        while !rest.is_empty() {
+            let inclusive_lower = match rest.chars().next()? {
+                '[' => true,
+                '(' => false,
+                _ => return None,
+            };
+            let close = rest.find([']', ')'])?;
+            let inclusive_upper = rest[close..].starts_with(']');
+            let inner = &rest[1..close];
+            let bound = |version: &str, inclusive| {
+                let version = version.trim();
+                (!version.is_empty()).then(|| Bound {
+                    version: MavenVersion::new(version),
+                    inclusive,
+                })
+            };
+            let interval = match inner.split_once(',') {
+                Some((lower, upper)) => {
+                    (bound(lower, inclusive_lower), bound(upper, inclusive_upper))
+                }
+                // A single version has to be inclusive, as in `[1.0]`
+                None if inclusive_lower && inclusive_upper && !inner.trim().is_empty() => {
+                    (bound(inner, true), bound(inner, true))
+                }
+                None => return None,
+            };
+            intervals.push(interval);
+            rest = rest[close + 1..].trim_start();
+            if let Some(after) = rest.strip_prefix(',') {
+                rest = after.trim_start();
+                if rest.is_empty() {
+                    return None;
+                }
+            } else if !rest.is_empty() {
+                return None;
+            }
+        }
+        (!intervals.is_empty()).then_some(Self { intervals })
+    }
+
+    /// Returns true if `version` is inside any interval of the range
+    pub(super) fn contains(&self, version: &MavenVersion) -> bool {
+        self.intervals.iter().any(|(lower, upper)| {
+            let above = lower
+                .as_ref()
+                .is_none_or(|lower| match version.cmp(&lower.version) {
+                    Ordering::Greater => true,
+                    Ordering::Equal => lower.inclusive,
+                    Ordering::Less => false,
+                });
+            let below = upper
+                .as_ref()
+                .is_none_or(|upper| match version.cmp(&upper.version) {
+                    Ordering::Less => true,
+                    Ordering::Equal => upper.inclusive,
+                    Ordering::Greater => false,
+                });
+            above && below
+        })
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    // Versions order the way Maven orders them
+    #[test]
+    fn ordering() {
+        let ascending = [
+            "1.0-alpha1",
+            "1.0-alpha2",
+            "1.0-beta",
+            "1.0-rc1",
+            "1.0-SNAPSHOT",
+            "1.0",
+            "1.0-sp1",
+            "1.0-whatever",
+            "1.0.1",
+            "1.2",
+            "1.10",
+            "2.0.0+build.1",
+        ];
+        for pair in ascending.windows(2) {
+            assert!(
+                MavenVersion::new(pair[0]) < MavenVersion::new(pair[1]),
+                "{} < {}",
+                pair[0],
+                pair[1]
+            );
+        }
+        assert_eq!(MavenVersion::new("1"), MavenVersion::new("1.0.0"));
+        assert_eq!(MavenVersion::new("1.0-final"), MavenVersion::new("1.0"));
+        assert!(MavenVersion::new("1.0-SNAPSHOT").is_snapshot());
+    }
+
+    // Ranges hold the versions between their bounds, and anything else isn't a range
+    #[test]
+    fn ranges() {
+        let contains = |range: &str, version: &str| {
+            VersionRange::parse(range)
+                .unwrap()
+                .contains(&MavenVersion::new(version))
+        };
+        assert!(contains("[1.0,2.0)", "1.0"));
+        assert!(contains("[1.0,2.0)", "1.9.9"));
+        assert!(!contains("[1.0,2.0)", "2.0"));
+        assert!(!contains("(1.0,2.0]", "1.0"));
+        assert!(contains("(1.0,2.0]", "2.0"));
+        assert!(contains("[1.5]", "1.5.0"));
+        assert!(!contains("[1.5]", "1.6"));
+        assert!(contains("(,1.0],[1.2,)", "0.1"));
+        assert!(!contains("(,1.0],[1.2,)", "1.1"));
+        assert!(contains("(,1.0],[1.2,)", "3"));
+        for invalid in ["1.0", "[1.0", "(1.0)", "[1.0,2.0),", "[1.0,2.0) x", ""] {
+            assert!(VersionRange::parse(invalid).is_none(), "{invalid}");
+        }
+    }
+}
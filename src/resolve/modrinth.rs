@@ -0,0 +1,808 @@
+//! Resolution of [`Source::Modrinth`](crate::types::Source::Modrinth) through the Modrinth API
+
+use std::collections::BTreeMap;
+
+use serde::{de::DeserializeOwned, Deserialize};
+use snafu::{ensure, ResultExt};
+use tracing::{debug, warn};
+use url::Url;
+
+use super::{
+    api_url, decode_hash, CompatibilityPolicy, HttpSnafu, NoMatchingVersionSnafu, ProjectDetails,
+    ResolveError, ResolvedArtifact, Resolver, SearchHit, UpstreamIds,
+};
+use crate::{
+    collection::Collection,
+    http::{HttpClient, HttpError, Response},
+    types::{Hashes, Source, Versions},
+};
+
+/// A project, as returned by `/project/{slug}`
+#[derive(Deserialize)]
+struct Project {
+    /// The display name
+    title: String,
+    /// What kind of project it is, like `mod` or `shader`
+    #[serde(rename = "project_type")]
+    kind: String,
+    /// The license the project is under
+    license: Option<License>,
+}
+
+/// The license of a project
+#[derive(Deserialize)]
+struct License {
+    /// The SPDX identifier of the license, or one Modrinth made up for custom licenses
+    id: String,
+}
+
+/// The slug of a project, as returned by `/project/{id}`
+#[derive(Deserialize)]
+struct ProjectSlug {
+    /// The project's slug
+    slug: String,
+}
+
+/// The version a file belongs to, as returned by `/version_file/{hash}`
+#[derive(Deserialize)]
+struct HashedVersion {
+    /// The id of the version
+    id: String,
+    /// The id of the project
+    project_id: String,
+}
+
+/// The results of a search, as returned by `/search`
+#[derive(Deserialize)]
+struct SearchResults {
+    /// The projects found
+    hits: Vec<SearchResult>,
+}
+
+/// A project found by a search
+#[derive(Deserialize)]
+struct SearchResult {
+    /// The project's slug
+    slug: String,
+    /// The display name
+    title: String,
+    /// The short description
+    #[serde(default)]
+    description: Option<String>,
+    /// How many times the project has been downloaded
+    #[serde(default)]
+    downloads: u64,
+}
+
+/// A collection, as returned by `/v3/collection/{id}`
+#[derive(Deserialize)]
+struct CollectionListing {
+    /// The display name
+    name: String,
+    /// The ids of the projects in it
+    projects: Vec<String>,
+}
+
+/// A project, as listed by `/projects`
+#[derive(Deserialize)]
+struct ListedProject {
+    /// The project's id
+    id: String,
+    /// The project's slug
+    slug: String,
+    /// The display name
+    title: String,
+    /// The short description
+    #[serde(default)]
+    description: Option<String>,
+    /// How many times the project has been downloaded
+    #[serde(default)]
+    downloads: u64,
+    /// What kind of project it is, like `mod` or `shader`
+    #[serde(rename = "project_type")]
+    kind: String,
+}
+
+/// The folders the files of each kind of project packs can carry install into
+const FOLDERS: [(&str, &str); 4] = [
+    ("mod", "mods"),
+    ("resourcepack", "resourcepacks"),
+    ("shader", "shaderpacks"),
+    ("datapack", "datapacks"),
+];
+
+/// A member of a project's team, as returned by `/project/{slug}/members`
+#[derive(Deserialize)]
+struct Member {
+    /// The member's account
+    user: User,
+}
+
+/// A user account
+#[derive(Deserialize)]
+struct User {
+    /// The user's name
+    username: String,
+}
+
+/// A version of a project, as returned by `/project/{slug}/version`
+#[derive(Deserialize)]
+struct Version {
+    /// The id of the version
+    #[serde(default)]
+    id: Option<String>,
+    /// The version number, like `0.5.3`
+    #[serde(default, rename = "version_number")]
+    number: Option<String>,
+    /// The loaders the version is built for
+    #[serde(default)]
+    loaders: Vec<String>,
+    /// The Minecraft versions the version is built for
+    #[serde(default)]
+    game_versions: Vec<String>,
+    /// When the version was published, as an RFC 3339 timestamp
+    #[serde(default)]
+    date_published: Option<String>,
+    /// The files of the version
+    files: Vec<File>,
+}
+
+/// A file of a version
+#[derive(Deserialize)]
+struct File {
+    /// Where to download the file
+    url: Url,
+    /// The name of the file
+    filename: String,
+    /// Whether this is the main file of the version
+    #[serde(default)]
+    primary: bool,
+    /// The size of the file
+    size: u64,
+    /// Hashes of the file by algorithm
+    #[serde(default)]
+    hashes: BTreeMap<String, String>,
+}
+
+/// Resolves the file of a project to use in the pack: the one of the pinned version if there
+/// is one, otherwise the newest compatible one
+///
+/// Depending on the resolver's [`CompatibilityPolicy`], builds for other loaders the pack's loader
+/// can run are considered too. The API is asked to filter versions, but what it returns is
+/// checked again, so that a listing from a mirror or a stale cache can't slip in a build for
+/// the wrong game. A pinned version is used whatever it was built for, with a warning if that
+/// doesn't match the pack.
+pub(super) async fn resolve<C: HttpClient>(
+    resolver: &Resolver<C>,
+    slug: &str,
+    version_id: Option<&str>,
+    versions: &Versions,
+) -> Result<ResolvedArtifact, ResolveError> {
+    let minecraft = versions.minecraft.to_string();
+    let compatible = versions.loader.compatible_loaders();
+    let loader = compatible[0];
+    let accepted = if resolver.compatibility == CompatibilityPolicy::Deny {
+        &compatible[..1]
+    } else {
+        compatible
+    };
+    let newest = if let Some(id) = version_id {
+        let url = api_url(&resolver.endpoints.modrinth, ["version", id]);
+        let version: Version = fetch(resolver, url).await.context(HttpSnafu)?;
+        if !version.supports(&minecraft, accepted) {
+            warn!(
+                project = slug,
+                version = id,
+                "Pinned version isn't made for minecraft {minecraft} on {loader}"
+            );
+        }
+        Some(version)
+    } else {
+        latest(resolver, slug, &minecraft, accepted).await?
+    };
+    if let Some(version) = &newest {
+        let foreign =
+            !version.loaders.is_empty() && !version.loaders.iter().any(|name| name == loader);
+        if foreign && resolver.compatibility == CompatibilityPolicy::Warn {
+            warn!(
+                project = slug,
+                loaders = ?version.loaders,
+                "Using a build made for another loader than {loader}"
+            );
+        }
+    }
+    let (id, number, mut files) = newest
+        .map(|version| (version.id, version.number, version.files))
+        .unwrap_or_default();
+    let index = files.iter().position(|file| file.primary).unwrap_or(0);
+    ensure!(
+        index < files.len(),
+        NoMatchingVersionSnafu {
+            project: slug,
+            versions: format!("minecraft {minecraft} on {loader}"),
+        }
+    );
+    let file = files.swap_remove(index);
+    let mut details = details(resolver, slug, number).await;
+    // Each version's page holds its changelog
+    details.changelog = details
+        .url
+        .as_ref()
+        .zip(id.as_deref())
+        .map(|(page, id)| api_url(page, ["version", id]));
+    Ok(ResolvedArtifact {
+        download_url: file.url,
+        filename: file.filename,
+        size: Some(file.size),
+        hashes: Hashes {
+            sha1: file
+                .hashes
+                .get("sha1")
+                .and_then(|hex| decode_hash("sha1", hex)),
+            sha512: file
+                .hashes
+                .get("sha512")
+                .and_then(|hex| decode_hash("sha512", hex)),
+            ..Hashes::default()
+        },
+        mirrors: Vec::new(),
+        extract: None,
+        ids: UpstreamIds {
+            version_id: id,
+            ..UpstreamIds::default()
+        },
+        details,
+    })
+}
+
+/// Finds the newest version of a project built for `minecraft` and one of `loaders`
+async fn latest<C: HttpClient>(
+    resolver: &Resolver<C>,
+    slug: &str,
+    minecraft: &str,
+    loaders: &[&str],
+) -> Result<Option<Version>, ResolveError> {
+    let quoted: Vec<_> = loaders.iter().map(|name| format!("\"{name}\"")).collect();
+    let mut url = api_url(&resolver.endpoints.modrinth, ["project", slug, "version"]);
+    url.query_pairs_mut()
+        .append_pair("loaders", &format!("[{}]", quoted.join(",")))
+        .append_pair("game_versions", &format!("[\"{minecraft}\"]"));
+    let found: Vec<Version> = fetch(resolver, url).await.context(HttpSnafu)?;
+    Ok(newest(
+        found
+            .into_iter()
+            .filter(|version| version.supports(minecraft, loaders)),
+    ))
+}
+
+impl Version {
+    /// Returns true if the version is built for `minecraft` and one of `loaders`, counting
+    /// lists the API left out as matching anything
+    fn supports(&self, minecraft: &str, loaders: &[&str]) -> bool {
+        let game = self.game_versions.is_empty()
+            || self
+                .game_versions
+                .iter()
+                .any(|version| version == minecraft);
+        let loader = self.loaders.is_empty()
+            || self
+                .loaders
+                .iter()
+                .any(|name| loaders.contains(&name.as_str()));
+        game && loader
+    }
+}
+
+/// Returns the day an RFC 3339 timestamp's calendar date is, counted from the Unix epoch
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    // Years start in March here, so that leap days come last
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Parses an RFC 3339 timestamp, like `2023-09-16T01:00:00.25+02:00`, into seconds and
+/// nanoseconds since the Unix epoch, returning `None` if it isn't one
+fn timestamp(raw: &str) -> Option<(i64, u32)> {
+    let (date, time) = raw.split_once(['T', 't', ' '])?;
+    let mut date = date.splitn(3, '-').map(str::parse::<i64>);
+    let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+    let (time, offset) = time.split_at(time.find(['Z', 'z', '+', '-'])?);
+    let (clock, fraction) = time.split_once('.').unwrap_or((time, ""));
+    let mut clock = clock.splitn(3, ':').map(str::parse::<i64>);
+    let (hour, minute, second) = (
+        clock.next()?.ok()?,
+        clock.next()?.ok()?,
+        clock.next()?.ok()?,
+    );
+    if !fraction.bytes().all(|digit| digit.is_ascii_digit()) {
+        return None;
+    }
+    let nanos = format!("{fraction:0<9}")[..9].parse().ok()?;
+    let offset = match offset.split_at(1) {
+        ("Z" | "z", "") => 0,
+        (sign, offset) => {
+            let (hours, minutes) = offset.split_once(':')?;
+            let offset = hours.parse::<i64>().ok()? * 3600 + minutes.parse::<i64>().ok()? * 60;
+            if sign == "-" {
+                -offset
+            } else {
+                offset
+            }
+        }
+    };
+    let days = days_from_civil(year, month, day);
+    let seconds = days * 86_400 + hour * 3600 + minute * 60 + second - offset;
+    Some((seconds, nanos))
+}
+
+/// Picks the most recently published version, or the first listed where dates are missing or
+/// unreadable, as the API lists versions newest first
+fn newest(versions: impl Iterator<Item = Version>) -> Option<Version> {
+    versions.reduce(|newest, version| {
+        let published = |version: &Version| version.date_published.as_deref().and_then(timestamp);
+        match (published(&newest), published(&version)) {
+            (Some(current), Some(candidate)) if candidate > current => version,
+            _ => newest,
+        }
+    })
+}
+
+/// Finds the project and version a file with the SHA-1 digest `sha1` was published in, as a
+/// source pinned to that version
+pub(super) async fn identify<C: HttpClient>(
+    resolver: &Resolver<C>,
+    sha1: &str,
+) -> Result<Option<Source>, ResolveError> {
+    let mut url = api_url(&resolver.endpoints.modrinth, ["version_file", sha1]);
+    url.query_pairs_mut().append_pair("algorithm", "sha1");
+    let response = send(resolver, url).await.context(HttpSnafu)?;
+    // Files Modrinth doesn't host are simply not found
+    if response.status == 404 {
+        return Ok(None);
+    }
+    let version: HashedVersion = response
+        .error_for_status()
+        .context(HttpSnafu)?
+        .json()
+        .await
+        .context(HttpSnafu)?;
+    let url = api_url(
+        &resolver.endpoints.modrinth,
+        ["project", &version.project_id],
+    );
+    let project: ProjectSlug = fetch(resolver, url).await.context(HttpSnafu)?;
+    Ok(Some(Source::Modrinth {
+        slug: project.slug,
+        version_id: Some(version.id),
+    }))
+}
+
+/// Searches for mods with versions for the pack's minecraft version and a loader it accepts
+pub(super) async fn search<C: HttpClient>(
+    resolver: &Resolver<C>,
+    query: &str,
+    versions: &Versions,
+    limit: usize,
+) -> Result<Vec<SearchHit>, ResolveError> {
+    let compatible = versions.loader.compatible_loaders();
+    let accepted = if resolver.compatibility == CompatibilityPolicy::Deny {
+        &compatible[..1]
+    } else {
+        compatible
+    };
+    let loaders: Vec<_> = accepted
+        .iter()
+        .map(|loader| format!("categories:{loader}"))
+        .collect();
+    let facets = serde_json::json!([
+        loaders,
+        [format!("versions:{}", versions.minecraft)],
+        ["project_type:mod"],
+    ]);
+    let mut url = api_url(&resolver.endpoints.modrinth, ["search"]);
+    url.query_pairs_mut()
+        .append_pair("query", query)
+        .append_pair("facets", &facets.to_string())
+        .append_pair("limit", &limit.to_string());
+    let results: SearchResults = fetch(resolver, url).await.context(HttpSnafu)?;
+    debug!(query, hits = results.hits.len(), "Searched Modrinth");
+    Ok(results
+        .hits
+        .into_iter()
+        .map(|hit| SearchHit {
+            source: Source::Modrinth {
+                slug: hit.slug,
+                version_id: None,
+            },
+            name: hit.title,
+            description: hit.description,
+            downloads: hit.downloads,
+            folder: "mods",
+        })
+        .collect())
+}
+
+/// Finds the projects in a collection, leaving out the kinds packs can't carry, like modpacks
+pub(super) async fn collection<C: HttpClient>(
+    resolver: &Resolver<C>,
+    id: &str,
+) -> Result<Collection, ResolveError> {
+    // Collections are only in version 3 of the API
+    let v3 = resolver
+        .endpoints
+        .modrinth
+        .join("../v3/")
+        .expect("API base urls can have paths");
+    let listing: CollectionListing = fetch(resolver, api_url(&v3, ["collection", id]))
+        .await
+        .context(HttpSnafu)?;
+    let mut projects = BTreeMap::new();
+    if listing.projects.is_empty() {
+        return Ok(Collection {
+            id: id.to_string(),
+            name: listing.name,
+            projects,
+        });
+    }
+    let mut url = api_url(&resolver.endpoints.modrinth, ["projects"]);
+    url.query_pairs_mut()
+        .append_pair("ids", &serde_json::json!(listing.projects).to_string());
+    let listed: Vec<ListedProject> = fetch(resolver, url).await.context(HttpSnafu)?;
+    for project in listed {
+        let Some((_, folder)) = FOLDERS.iter().find(|(kind, _)| *kind == project.kind) else {
+            debug!(
+                project = project.slug.as_str(),
+                kind = project.kind.as_str(),
+                "Skipping project packs can't carry"
+            );
+            continue;
+        };
+        let hit = SearchHit {
+            source: Source::Modrinth {
+                slug: project.slug,
+                version_id: None,
+            },
+            name: project.title,
+            description: project.description,
+            downloads: project.downloads,
+            folder,
+        };
+        projects.insert(project.id, hit);
+    }
+    debug!(id, projects = projects.len(), "Found Modrinth collection");
+    Ok(Collection {
+        id: id.to_string(),
+        name: listing.name,
+        projects,
+    })
+}
+
+/// Sends an API request, with the Modrinth token if there is one
+async fn send<C: HttpClient>(
+    resolver: &Resolver<C>,
+    url: Url,
+) -> Result<Response<C::Body>, HttpError> {
+    let mut request = resolver.request(url);
+    if let Some(token) = &resolver.credentials.modrinth {
+        request = request.with_header("Authorization", token);
+    }
+    resolver.get(request).await
+}
+
+/// Sends an API request like [`send`], and parses the response
+async fn fetch<C: HttpClient, T: DeserializeOwned>(
+    resolver: &Resolver<C>,
+    url: Url,
+) -> Result<T, HttpError> {
+    send(resolver, url).await?.error_for_status()?.json().await
+}
+
+/// Looks up the name and authors of a project, which are only nice to have, so failures are
+/// logged rather than returned
+async fn details<C: HttpClient>(
+    resolver: &Resolver<C>,
+    slug: &str,
+    version: Option<String>,
+) -> ProjectDetails {
+    let page = |kind: &str| Url::parse(&format!("https://modrinth.com/{kind}/{slug}")).ok();
+    let mut details = ProjectDetails {
+        version,
+        url: page("project"),
+        ..ProjectDetails::default()
+    };
+    if !resolver.details {
+        return details;
+    }
+    let url = api_url(&resolver.endpoints.modrinth, ["project", slug]);
+    match fetch::<_, Project>(resolver, url).await {
+        Ok(project) => {
+            details.name = Some(project.title);
+            details.url = page(&project.kind);
+            details.license = project.license.map(|license| license.id);
+        }
+        Err(error) => debug!(project = slug, %error, "Couldn't look up project"),
+    }
+    let url = api_url(&resolver.endpoints.modrinth, ["project", slug, "members"]);
+    match fetch::<_, Vec<Member>>(resolver, url).await {
+        Ok(members) => {
+            details.authors = members
+                .into_iter()
+                .map(|member| member.user.username)
+                .collect();
+        }
+        Err(error) => debug!(project = slug, %error, "Couldn't look up project members"),
+    }
+    details
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use crate::{
+        http::testing::{block_on, MockClient},
+        resolve::{CompatibilityPolicy, Credentials, ProjectDetails, ResolveError, Resolver},
+        types::{Loader, ManagedFile, Minecraft, Source, Versions},
+    };
+
+    /// The query sent for sodium on fabric 1.20.1
+    const QUERY: &str = "https://api.modrinth.com/v2/project/sodium/version\
+        ?loaders=%5B%22fabric%22%5D&game_versions=%5B%221.20.1%22%5D";
+
+    // The primary file of the newest compatible version is picked
+    #[test]
+    fn newest_primary_file() {
+        let versions = Versions {
+            minecraft: Minecraft::new("1.20.1").unwrap(),
+            loader: Loader::new_fabric("0.14.21".parse().unwrap()),
+            java: None,
+        };
+        let file = ManagedFile {
+            source: Source::Modrinth {
+                slug: "sodium".to_string(),
+                version_id: None,
+            },
+            ..ManagedFile::default()
+        };
+        let sha1 = "aa".repeat(20);
+        let body = format!(
+            r#"[
+                {{"id": "AbCd", "version_number": "0.5.0", "files": [
+                    {{"url": "https://cdn.modrinth.com/extra.jar", "filename": "extra.jar",
+                      "primary": false, "size": 1, "hashes": {{}}}},
+                    {{"url": "https://cdn.modrinth.com/sodium-0.5.jar", "filename": "sodium-0.5.jar",
+                      "primary": true, "size": 100, "hashes": {{"sha1": "{sha1}", "sha512": "bad"}}}}
+                ]}},
+                {{"files": [
+                    {{"url": "https://cdn.modrinth.com/sodium-0.4.jar", "filename": "sodium-0.4.jar",
+                      "primary": true, "size": 90, "hashes": {{}}}}
+                ]}}
+            ]"#
+        );
+        let credentials = Credentials {
+            modrinth: Some("mrp_token".to_string()),
+            ..Credentials::default()
+        };
+        let client = MockClient::default()
+            .with(QUERY, body.clone())
+            .with(
+                "https://api.modrinth.com/v2/project/sodium",
+                r#"{"title": "Sodium", "project_type": "mod", "license": {"id": "LGPL-3.0-only"}}"#,
+            )
+            .with(
+                "https://api.modrinth.com/v2/project/sodium/members",
+                r#"[{"user": {"username": "jellysquid3"}, "role": "Owner"}]"#,
+            );
+        let resolver = Resolver::new(client)
+            .with_credentials(credentials)
+            .with_user_agent("tester/1.0");
+        let artifact = block_on(resolver.resolve(&file, &versions)).unwrap();
+        assert_eq!(artifact.filename, "sodium-0.5.jar");
+        assert_eq!(
+            artifact.details,
+            ProjectDetails {
+                name: Some("Sodium".to_string()),
+                version: Some("0.5.0".to_string()),
+                url: Some("https://modrinth.com/mod/sodium".parse().unwrap()),
+                authors: vec!["jellysquid3".to_string()],
+                license: Some("LGPL-3.0-only".to_string()),
+                changelog: Some(
+                    "https://modrinth.com/mod/sodium/version/AbCd"
+                        .parse()
+                        .unwrap()
+                ),
+            }
+        );
+        assert_eq!(artifact.size, Some(100));
+        assert_eq!(artifact.hashes.sha1, Some([0xaa; 20]));
+        assert_eq!(artifact.hashes.sha512, None);
+        let headers = &resolver.client().requests.lock().unwrap()[0].headers;
+        assert_eq!(
+            headers[..],
+            [
+                ("User-Agent".to_string(), "tester/1.0".to_string()),
+                ("Authorization".to_string(), "mrp_token".to_string())
+            ]
+        );
+
+        // Details are left to what the version listing says when extra lookups are off, or
+        // fail
+        let resolver = Resolver::new(MockClient::default().with(QUERY, body)).with_details(false);
+        let artifact = block_on(resolver.resolve(&file, &versions)).unwrap();
+        assert_eq!(artifact.details.name, None);
+        assert_eq!(artifact.details.version.as_deref(), Some("0.5.0"));
+        assert_eq!(resolver.client().requests.lock().unwrap().len(), 1);
+
+        let resolver = Resolver::new(MockClient::default().with(QUERY, "[]"));
+        assert!(matches!(
+            block_on(resolver.resolve(&file, &versions)),
+            Err(ResolveError::NoMatchingVersion { .. })
+        ));
+    }
+
+    // Versions the API shouldn't have listed are skipped, and publication dates win over order,
+    // whatever offset they are written with
+    #[test]
+    fn filters_listing() {
+        let versions = Versions {
+            minecraft: Minecraft::new("1.20.1").unwrap(),
+            loader: Loader::new_fabric("0.14.21".parse().unwrap()),
+            java: None,
+        };
+        let file = ManagedFile {
+            source: Source::Modrinth {
+                slug: "sodium".to_string(),
+                version_id: None,
+            },
+            ..ManagedFile::default()
+        };
+        let version = |name: &str, game: &str, loader: &str, date: &str| {
+            format!(
+                r#"{{"game_versions": ["{game}"], "loaders": ["{loader}"],
+                    "date_published": "{date}", "files": [
+                    {{"url": "https://cdn.modrinth.com/{name}", "filename": "{name}",
+                      "primary": true, "size": 1}}
+                ]}}"#
+            )
+        };
+        let body = format!(
+            "[{}, {}, {}, {}, {}]",
+            version("new-game.jar", "1.20.2", "fabric", "2023-10-02T00:00:00Z"),
+            version("forge.jar", "1.20.1", "forge", "2023-10-01T00:00:00Z"),
+            version("oldest.jar", "1.20.1", "fabric", "2023-08-30T00:00:00Z"),
+            version("older.jar", "1.20.1", "fabric", "2023-09-16T01:00:00+02:00"),
+            version("newer.jar", "1.20.1", "fabric", "2023-09-15T23:30:00.25Z"),
+        );
+        let resolver = Resolver::new(MockClient::default().with(QUERY, body)).with_details(false);
+        let artifact = block_on(resolver.resolve(&file, &versions)).unwrap();
+        assert_eq!(artifact.filename, "newer.jar");
+    }
+
+    // A pinned version is fetched directly, even if it isn't for the pack's game
+    #[test]
+    fn pinned() {
+        let versions = Versions {
+            minecraft: Minecraft::new("1.20.1").unwrap(),
+            loader: Loader::new_fabric("0.14.21".parse().unwrap()),
+            java: None,
+        };
+        let file = ManagedFile {
+            source: Source::Modrinth {
+                slug: "sodium".to_string(),
+                version_id: Some("rAfhHfow".to_string()),
+            },
+            ..ManagedFile::default()
+        };
+        let body = r#"{"version_number": "0.4.10", "game_versions": ["1.19.4"],
+            "loaders": ["fabric"], "files": [
+            {"url": "https://cdn.modrinth.com/sodium-0.4.10.jar", "filename": "sodium-0.4.10.jar",
+             "primary": true, "size": 1}
+        ]}"#;
+        let client =
+            MockClient::default().with("https://api.modrinth.com/v2/version/rAfhHfow", body);
+        let resolver = Resolver::new(client).with_details(false);
+        let artifact = block_on(resolver.resolve(&file, &versions)).unwrap();
+        assert_eq!(artifact.filename, "sodium-0.4.10.jar");
+        assert_eq!(artifact.details.version.as_deref(), Some("0.4.10"));
+        assert_eq!(resolver.client().requests.lock().unwrap().len(), 1);
+    }
+
+    // Quilt packs accept Fabric builds unless told not to
+    #[test]
+    fn fabric_on_quilt() {
+        let versions = Versions {
+            minecraft: Minecraft::new("1.20.1").unwrap(),
+            loader: Loader::new_quilt("0.19.0".parse().unwrap()),
+            java: None,
+        };
+        let file = ManagedFile {
+            source: Source::Modrinth {
+                slug: "sodium".to_string(),
+                version_id: None,
+            },
+            ..ManagedFile::default()
+        };
+        let both = "https://api.modrinth.com/v2/project/sodium/version\
+            ?loaders=%5B%22quilt%22%2C%22fabric%22%5D&game_versions=%5B%221.20.1%22%5D";
+        let quilt = "https://api.modrinth.com/v2/project/sodium/version\
+            ?loaders=%5B%22quilt%22%5D&game_versions=%5B%221.20.1%22%5D";
+        let body = r#"[{"loaders": ["fabric"], "files": [
+            {"url": "https://cdn.modrinth.com/sodium.jar", "filename": "sodium.jar",
+             "primary": true, "size": 1}
+        ]}]"#;
+        let client = || MockClient::default().with(both, body).with(quilt, "[]");
+        for policy in [CompatibilityPolicy::Allow, CompatibilityPolicy::Warn] {
+            let resolver = Resolver::new(client()).with_compatibility(policy);
+            let artifact = block_on(resolver.resolve(&file, &versions)).unwrap();
+            assert_eq!(artifact.filename, "sodium.jar");
+        }
+        let resolver = Resolver::new(client()).with_compatibility(CompatibilityPolicy::Deny);
+        assert!(matches!(
+            block_on(resolver.resolve(&file, &versions)),
+            Err(ResolveError::NoMatchingVersion { .. })
+        ));
+    }
+
+    // Searches are limited to mods for the pack's minecraft version and accepted loaders
+    #[test]
+    fn search() {
+        let versions = Versions {
+            minecraft: Minecraft::new("1.20.1").unwrap(),
+            loader: Loader::new_quilt("0.19.0".parse().unwrap()),
+            java: None,
+        };
+        let url = "https://api.modrinth.com/v2/search?query=sodium\
+            &facets=%5B%5B%22categories%3Aquilt%22%2C%22categories%3Afabric%22%5D%2C\
+            %5B%22versions%3A1.20.1%22%5D%2C%5B%22project_type%3Amod%22%5D%5D&limit=5";
+        let body = r#"{"hits": [{"slug": "sodium", "title": "Sodium",
+            "description": "A rendering engine", "downloads": 1000}]}"#;
+        let resolver = Resolver::new(MockClient::default().with(url, body));
+        let hits = block_on(resolver.search_modrinth("sodium", &versions, 5)).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(
+            hits[0].source,
+            Source::Modrinth {
+                slug: "sodium".into(),
+                version_id: None,
+            }
+        );
+        assert_eq!(hits[0].downloads, 1000);
+        assert_eq!(hits[0].description.as_deref(), Some("A rendering engine"));
+    }
+
+    // Collections list their projects by id, which are looked up together, skipping modpacks
+    #[test]
+    fn collection() {
+        let projects = "https://api.modrinth.com/v2/projects\
+            ?ids=%5B%22AANobbMI%22%2C%22YL57xq9U%22%2C%221KVo5zza%22%5D";
+        let body = r#"[
+            {"id": "AANobbMI", "slug": "sodium", "title": "Sodium", "project_type": "mod"},
+            {"id": "YL57xq9U", "slug": "iris", "title": "Iris Shaders", "project_type": "shader",
+                "downloads": 10},
+            {"id": "1KVo5zza", "slug": "fabulously-optimized", "title": "FO",
+                "project_type": "modpack"}
+        ]"#;
+        let client = MockClient::default()
+            .with(
+                "https://api.modrinth.com/v3/collection/abc123",
+                r#"{"name": "Performance", "projects": ["AANobbMI", "YL57xq9U", "1KVo5zza"]}"#,
+            )
+            .with(projects, body);
+        let resolver = Resolver::new(client);
+        let collection = block_on(resolver.modrinth_collection("abc123")).unwrap();
+        assert_eq!(collection.name, "Performance");
+        let ids: Vec<_> = collection.projects.keys().map(String::as_str).collect();
+        assert_eq!(ids, ["AANobbMI", "YL57xq9U"]);
+        let iris = &collection.projects["YL57xq9U"];
+        assert_eq!(iris.folder, "shaderpacks");
+        assert_eq!(
+            iris.source,
+            Source::Modrinth {
+                slug: "iris".into(),
+                version_id: None,
+            }
+        );
+    }
+}
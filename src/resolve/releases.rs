@@ -0,0 +1,147 @@
+//! Picking an artifact out of a repository's releases, shared by the forges that have them
+//!
+//! Each forge's API describes releases its own way; its module translates them into a
+//! [`Release`] and leaves the matching to a [`Matcher`].
+
+use regex::Regex;
+use snafu::ResultExt;
+use url::Url;
+
+use super::{
+    AmbiguousArtifactSnafu, InvalidRegexSnafu, NoMatchingVersionSnafu, ProjectDetails,
+    ResolveError, ResolvedArtifact, UpstreamIds,
+};
+use crate::{
+    http::{Body, Response},
+    types::{ForgeSlug, Hashes},
+};
+
+/// How many pages of releases are looked through for a match before giving up
+pub(super) const MAX_PAGES: usize = 10;
+
+/// A release, as far as matching goes
+pub(super) struct Release {
+    /// The release title, if it has one
+    pub(super) title: Option<String>,
+    /// The tag the release was made from
+    pub(super) tag: String,
+    /// The release's page, which holds its notes
+    pub(super) page: Option<Url>,
+    /// The files attached to the release
+    pub(super) assets: Vec<Asset>,
+}
+
+/// A file attached to a release
+pub(super) struct Asset {
+    /// The file name
+    pub(super) name: String,
+    /// The size of the file, if the forge lists it
+    pub(super) size: Option<u64>,
+    /// Where to download the file
+    pub(super) url: Url,
+    /// The SHA-256 digest of the file, if the forge lists it
+    pub(super) sha256: Option<[u8; 32]>,
+}
+
+/// Finds the release and artifact a slug source asks for
+pub(super) struct Matcher<'a> {
+    /// The repository the releases belong to
+    slug: &'a ForgeSlug,
+    /// Which artifacts of a release match
+    artifact_regex: Regex,
+    /// Which releases match, if not all of them
+    release_regex: Option<Regex>,
+}
+
+impl<'a> Matcher<'a> {
+    /// Compiles the regexes of a slug source
+    pub(super) fn new(
+        slug: &'a ForgeSlug,
+        artifact_regex: &str,
+        release_regex: Option<&str>,
+    ) -> Result<Self, ResolveError> {
+        let compile = |regex: &str| Regex::new(regex).context(InvalidRegexSnafu { regex });
+        Ok(Self {
+            slug,
+            artifact_regex: compile(artifact_regex)?,
+            release_regex: release_regex.map(compile).transpose()?,
+        })
+    }
+
+    /// Picks the single matching artifact of a release, if the release matches and has one
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if more than one artifact matches, rather than guessing between them
+    pub(super) fn pick(&self, release: Release) -> Result<Option<ResolvedArtifact>, ResolveError> {
+        if let Some(regex) = &self.release_regex {
+            let title = release.title.as_deref().unwrap_or(&release.tag);
+            if !regex.is_match(title) && !regex.is_match(&release.tag) {
+                return Ok(None);
+            }
+        }
+        let mut matches: Vec<_> = release
+            .assets
+            .into_iter()
+            .filter(|asset| self.artifact_regex.is_match(&asset.name))
+            .collect();
+        if matches.len() > 1 {
+            return AmbiguousArtifactSnafu {
+                project: self.slug.to_string(),
+                release: release.tag,
+                matches: matches
+                    .into_iter()
+                    .map(|asset| asset.name)
+                    .collect::<Vec<_>>(),
+            }
+            .fail();
+        }
+        Ok(matches.pop().map(|asset| ResolvedArtifact {
+            download_url: asset.url,
+            filename: asset.name,
+            size: asset.size,
+            hashes: Hashes {
+                sha256: asset.sha256,
+                ..Hashes::default()
+            },
+            mirrors: Vec::new(),
+            extract: None,
+            ids: UpstreamIds {
+                version_id: Some(release.tag.clone()),
+                ..UpstreamIds::default()
+            },
+            details: ProjectDetails {
+                name: Some(self.slug.project.clone()),
+                version: Some(release.tag),
+                url: Some(self.slug.web_url()),
+                authors: vec![self.slug.owner.clone()],
+                license: None,
+                changelog: release.page,
+            },
+        }))
+    }
+
+    /// The error for a repository without any matching release
+    pub(super) fn no_match(&self) -> ResolveError {
+        NoMatchingVersionSnafu {
+            project: self.slug.to_string(),
+            versions: format!(
+                "a release with an artifact matching {}",
+                self.artifact_regex
+            ),
+        }
+        .build()
+    }
+}
+
+/// Returns the url of the next page of a listing, from its `Link` header
+pub(super) fn next_page<B: Body>(response: &Response<B>) -> Option<Url> {
+    response.header("Link")?.split(',').find_map(|link| {
+        let mut parts = link.split(';');
+        let target = parts.next()?.trim().strip_prefix('<')?.strip_suffix('>')?;
+        parts
+            .any(|param| param.trim() == r#"rel="next""#)
+            .then(|| Url::parse(target).ok())
+            .flatten()
+    })
+}
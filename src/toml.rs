@@ -0,0 +1,703 @@
+//! Reading and writing the subset of TOML that the formats packs are converted to and from use
+//!
+//! packwiz projects and mod metadata only need strings, integers, booleans, arrays, and tables,
+//! so floats and dates are refused rather than carried. Documents are read into a [`Table`] and
+//! written back with plain keys first, then tables, then arrays of tables, each sorted by key.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+};
+
+use snafu::Snafu;
+
+/// A table of keys to values, sorted by key
+pub type Table = BTreeMap<String, Value>;
+
+/// A value in a document
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Value {
+    /// A string
+    String(String),
+    /// An integer
+    Integer(i64),
+    /// A boolean
+    Boolean(bool),
+    /// An array, which is an array of tables if every element is a table
+    Array(Vec<Value>),
+    /// A table
+    Table(Table),
+}
+
+impl Value {
+    /// Returns the string, if this is one
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    /// Returns the integer, if this is one
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            Value::Integer(integer) => Some(*integer),
+            _ => None,
+        }
+    }
+
+    /// Returns the boolean, if this is one
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Boolean(boolean) => Some(*boolean),
+            _ => None,
+        }
+    }
+
+    /// Returns the elements, if this is an array
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    /// Returns the table, if this is one
+    pub fn as_table(&self) -> Option<&Table> {
+        match self {
+            Value::Table(table) => Some(table),
+            _ => None,
+        }
+    }
+}
+
+impl From<&str> for Value {
+    fn from(string: &str) -> Self {
+        Value::String(string.into())
+    }
+}
+
+impl From<String> for Value {
+    fn from(string: String) -> Self {
+        Value::String(string)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(integer: i64) -> Self {
+        Value::Integer(integer)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(boolean: bool) -> Self {
+        Value::Boolean(boolean)
+    }
+}
+
+impl From<Table> for Value {
+    fn from(table: Table) -> Self {
+        Value::Table(table)
+    }
+}
+
+/// Returns the value at a dotted path of keys under `table`, like `update.modrinth.mod-id`
+pub fn get<'a>(table: &'a Table, path: &str) -> Option<&'a Value> {
+    let mut keys = path.split('.');
+    let mut value = table.get(keys.next()?)?;
+    for key in keys {
+        value = value.as_table()?.get(key)?;
+    }
+    Some(value)
+}
+
+/// A document that isn't TOML, or uses parts of it this module doesn't read
+#[derive(Debug, Snafu)]
+#[snafu(display("Invalid TOML on line {}: {}", line, message))]
+pub struct TomlError {
+    /// The line the problem is on, counting from 1
+    line: usize,
+    /// What is wrong
+    message: String,
+}
+
+impl TomlError {
+    /// The line the problem is on, counting from 1
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+/// Reads a document into its root table
+///
+/// # Errors
+///
+/// Returns an error if the document isn't valid TOML, defines a key twice, or holds floats or
+/// dates
+pub fn parse(input: &str) -> Result<Table, TomlError> {
+    Parser {
+        input,
+        pos: 0,
+        line: 1,
+    }
+    .document()
+}
+
+/// Writes a table out as a document
+pub fn to_string(table: &Table) -> String {
+    let mut out = String::new();
+    write_table(&mut out, &mut Vec::new(), table);
+    out
+}
+
+/// Returns true for arrays that are written as arrays of tables
+fn is_table_array(value: &Value) -> bool {
+    matches!(value, Value::Array(array)
+        if !array.is_empty() && array.iter().all(|value| matches!(value, Value::Table(_))))
+}
+
+/// Writes the keys of a table, then its tables and arrays of tables under headers
+fn write_table<'a>(out: &mut String, path: &mut Vec<&'a str>, table: &'a Table) {
+    for (key, value) in table {
+        if !matches!(value, Value::Table(_)) && !is_table_array(value) {
+            let _ = writeln!(out, "{} = {}", format_key(key), format_value(value));
+        }
+    }
+    for (key, value) in table {
+        if let Value::Table(inner) = value {
+            path.push(key);
+            header(out, path, false);
+            write_table(out, path, inner);
+            path.pop();
+        }
+    }
+    for (key, value) in table {
+        if let (true, Value::Array(array)) = (is_table_array(value), value) {
+            path.push(key);
+            for inner in array.iter().filter_map(Value::as_table) {
+                header(out, path, true);
+                write_table(out, path, inner);
+            }
+            path.pop();
+        }
+    }
+}
+
+/// Writes the header of a table, or of an element of an array of tables
+fn header(out: &mut String, path: &[&str], array: bool) {
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    let path: Vec<_> = path.iter().map(|key| format_key(key)).collect();
+    let (open, close) = if array { ("[[", "]]") } else { ("[", "]") };
+    let _ = writeln!(out, "{open}{}{close}", path.join("."));
+}
+
+/// Returns true for characters allowed in bare keys
+fn is_bare(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+/// Formats a key, quoting it unless it is bare
+fn format_key(key: &str) -> String {
+    if !key.is_empty() && key.chars().all(is_bare) {
+        key.into()
+    } else {
+        format_string(key)
+    }
+}
+
+/// Formats a string as a basic string
+fn format_string(string: &str) -> String {
+    let mut out = String::from('"');
+    for c in string.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04X}", u32::from(c));
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Formats a value inline
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::String(string) => format_string(string),
+        Value::Integer(integer) => integer.to_string(),
+        Value::Boolean(boolean) => boolean.to_string(),
+        Value::Array(array) => {
+            let values: Vec<_> = array.iter().map(format_value).collect();
+            format!("[{}]", values.join(", "))
+        }
+        Value::Table(table) => {
+            let pairs: Vec<_> = table
+                .iter()
+                .map(|(key, value)| format!("{} = {}", format_key(key), format_value(value)))
+                .collect();
+            if pairs.is_empty() {
+                "{}".into()
+            } else {
+                format!("{{ {} }}", pairs.join(", "))
+            }
+        }
+    }
+}
+
+/// Walks down `path` from `table`, creating missing tables and entering the last element of
+/// arrays of tables
+fn navigate<'t>(mut table: &'t mut Table, path: &[String]) -> Result<&'t mut Table, String> {
+    for key in path {
+        let value = table
+            .entry(key.clone())
+            .or_insert_with(|| Value::Table(Table::new()));
+        table = match value {
+            Value::Table(inner) => inner,
+            Value::Array(array) => match array.last_mut() {
+                Some(Value::Table(inner)) => inner,
+                _ => return Err(format!("`{key}` is not a table")),
+            },
+            _ => return Err(format!("`{key}` is not a table")),
+        };
+    }
+    Ok(table)
+}
+
+/// Inserts a value at a dotted key under `table`, refusing to redefine keys
+fn insert(table: &mut Table, key: &[String], value: Value) -> Result<(), String> {
+    let (last, parents) = key.split_last().ok_or("Empty key")?;
+    let table = navigate(table, parents)?;
+    if table.contains_key(last) {
+        return Err(format!("`{last}` is defined twice"));
+    }
+    table.insert(last.clone(), value);
+    Ok(())
+}
+
+/// Reads a document from start to end
+struct Parser<'a> {
+    /// The whole document
+    input: &'a str,
+    /// The byte offset of the next character
+    pos: usize,
+    /// The line of the next character, counting from 1
+    line: usize,
+}
+
+impl Parser<'_> {
+    /// Fails at the current line
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, TomlError> {
+        Err(TomlError {
+            line: self.line,
+            message: message.into(),
+        })
+    }
+
+    /// Returns the rest of the document
+    fn rest(&self) -> &str {
+        &self.input[self.pos..]
+    }
+
+    /// Returns the next character without consuming it
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    /// Consumes the next character
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    /// Consumes `s` if the document continues with it
+    fn eat(&mut self, s: &str) -> bool {
+        if !self.rest().starts_with(s) {
+            return false;
+        }
+        for _ in s.chars() {
+            self.bump();
+        }
+        true
+    }
+
+    /// Skips spaces and tabs
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.bump();
+        }
+    }
+
+    /// Skips whitespace, newlines, and comments
+    fn skip_blank(&mut self) {
+        loop {
+            match self.peek() {
+                Some(' ' | '\t' | '\r' | '\n') => {
+                    self.bump();
+                }
+                Some('#') => {
+                    while !matches!(self.peek(), None | Some('\n')) {
+                        self.bump();
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+
+    /// Consumes the end of a line, allowing a comment before it
+    fn end_of_line(&mut self) -> Result<(), TomlError> {
+        self.skip_whitespace();
+        if self.peek() == Some('#') {
+            while !matches!(self.peek(), None | Some('\n')) {
+                self.bump();
+            }
+        }
+        self.eat("\r");
+        match self.bump() {
+            None | Some('\n') => Ok(()),
+            Some(c) => self.error(format!("Expected the end of the line, found `{c}`")),
+        }
+    }
+
+    /// Reads the statements of the document into its root table
+    fn document(mut self) -> Result<Table, TomlError> {
+        let mut root = Table::new();
+        let mut current = Vec::new();
+        // The tables headers have defined, which another header can't define again
+        let mut defined = BTreeSet::new();
+        loop {
+            self.skip_blank();
+            if self.peek().is_none() {
+                return Ok(root);
+            }
+            if self.eat("[[") {
+                let path = self.key()?;
+                if !self.eat("]]") {
+                    return self.error("Expected `]]`");
+                }
+                let (last, parents) = path.split_last().expect("keys have a part");
+                let parent = navigate(&mut root, parents).or_else(|message| self.error(message))?;
+                let array = parent
+                    .entry(last.clone())
+                    .or_insert_with(|| Value::Array(Vec::new()));
+                match array {
+                    Value::Array(array) => array.push(Value::Table(Table::new())),
+                    _ => return self.error(format!("`{last}` is not an array of tables")),
+                }
+                // The tables of the element before belong to it, so the new one can have its own
+                defined.retain(|table: &Vec<String>| !table.starts_with(&path));
+                current = path;
+            } else if self.eat("[") {
+                let path = self.key()?;
+                if !self.eat("]") {
+                    return self.error("Expected `]`");
+                }
+                navigate(&mut root, &path).or_else(|message| self.error(message))?;
+                if !defined.insert(path.clone()) {
+                    return self.error(format!("`[{}]` is defined twice", path.join(".")));
+                }
+                current = path;
+            } else {
+                let key = self.key()?;
+                if !self.eat("=") {
+                    return self.error("Expected `=` after a key");
+                }
+                self.skip_whitespace();
+                let value = self.value()?;
+                let table = navigate(&mut root, &current).or_else(|message| self.error(message))?;
+                insert(table, &key, value).or_else(|message| self.error(message))?;
+            }
+            self.end_of_line()?;
+        }
+    }
+
+    /// Reads a possibly dotted key, and the whitespace around it
+    fn key(&mut self) -> Result<Vec<String>, TomlError> {
+        let mut parts = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let part = match self.peek() {
+                Some('"') => {
+                    self.bump();
+                    self.basic_string()?
+                }
+                Some('\'') => {
+                    self.bump();
+                    self.literal_string()?
+                }
+                _ => {
+                    let start = self.pos;
+                    while self.peek().is_some_and(is_bare) {
+                        self.bump();
+                    }
+                    if start == self.pos {
+                        return self.error("Expected a key");
+                    }
+                    self.input[start..self.pos].to_owned()
+                }
+            };
+            parts.push(part);
+            self.skip_whitespace();
+            if !self.eat(".") {
+                return Ok(parts);
+            }
+        }
+    }
+
+    /// Reads a value
+    fn value(&mut self) -> Result<Value, TomlError> {
+        match self.peek() {
+            Some('"') if self.eat("\"\"\"") => self.multiline_string(true).map(Value::String),
+            Some('"') => {
+                self.bump();
+                self.basic_string().map(Value::String)
+            }
+            Some('\'') if self.eat("'''") => self.multiline_string(false).map(Value::String),
+            Some('\'') => {
+                self.bump();
+                self.literal_string().map(Value::String)
+            }
+            Some('[') => {
+                self.bump();
+                self.array()
+            }
+            Some('{') => {
+                self.bump();
+                self.inline_table()
+            }
+            _ if self.eat("true") => Ok(Value::Boolean(true)),
+            _ if self.eat("false") => Ok(Value::Boolean(false)),
+            _ => self.integer(),
+        }
+    }
+
+    /// Reads the rest of a basic string, after its opening quote
+    fn basic_string(&mut self) -> Result<String, TomlError> {
+        let mut string = String::new();
+        loop {
+            match self.bump() {
+                None | Some('\n') => return self.error("Unterminated string"),
+                Some('"') => return Ok(string),
+                Some('\\') => string.push(self.escape()?),
+                Some(c) => string.push(c),
+            }
+        }
+    }
+
+    /// Reads the rest of a literal string, after its opening quote
+    fn literal_string(&mut self) -> Result<String, TomlError> {
+        let start = self.pos;
+        loop {
+            match self.bump() {
+                None | Some('\n') => return self.error("Unterminated string"),
+                Some('\'') => return Ok(self.input[start..self.pos - 1].to_owned()),
+                Some(_) => {}
+            }
+        }
+    }
+
+    /// Reads the rest of a multi-line string, after its opening quotes, with escapes if it is a
+    /// basic string
+    fn multiline_string(&mut self, basic: bool) -> Result<String, TomlError> {
+        let close = if basic { "\"\"\"" } else { "'''" };
+        // A newline straight after the opening quotes is trimmed
+        if !self.eat("\n") {
+            self.eat("\r\n");
+        }
+        let mut string = String::new();
+        loop {
+            if self.eat(close) {
+                // Up to two quotes can end the string right before the closing ones
+                let quote = if basic { '"' } else { '\'' };
+                for _ in 0..2 {
+                    if self.peek() == Some(quote) {
+                        self.bump();
+                        string.push(quote);
+                    }
+                }
+                return Ok(string);
+            }
+            match self.bump() {
+                None => return self.error("Unterminated string"),
+                Some('\\') if basic => {
+                    if matches!(self.peek(), Some(' ' | '\t' | '\r' | '\n')) {
+                        // A backslash at the end of a line trims all whitespace after it
+                        self.skip_blank_in_string();
+                    } else {
+                        string.push(self.escape()?);
+                    }
+                }
+                Some(c) => string.push(c),
+            }
+        }
+    }
+
+    /// Skips the whitespace and newlines after a line ending backslash
+    fn skip_blank_in_string(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\r' | '\n')) {
+            self.bump();
+        }
+    }
+
+    /// Reads an escape sequence, after its backslash
+    fn escape(&mut self) -> Result<char, TomlError> {
+        let digits = match self.bump() {
+            Some('"') => return Ok('"'),
+            Some('\\') => return Ok('\\'),
+            Some('n') => return Ok('\n'),
+            Some('t') => return Ok('\t'),
+            Some('r') => return Ok('\r'),
+            Some('b') => return Ok('\u{8}'),
+            Some('f') => return Ok('\u{c}'),
+            Some('u') => 4,
+            Some('U') => 8,
+            _ => return self.error("Invalid escape sequence"),
+        };
+        let hex = self.rest().get(..digits).unwrap_or_default().to_owned();
+        let c = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32);
+        match c {
+            Some(c) if hex.len() == digits => {
+                self.pos += digits;
+                Ok(c)
+            }
+            _ => self.error("Invalid unicode escape"),
+        }
+    }
+
+    /// Reads the rest of an array, after its opening bracket
+    fn array(&mut self) -> Result<Value, TomlError> {
+        let mut array = Vec::new();
+        loop {
+            self.skip_blank();
+            if self.eat("]") {
+                return Ok(Value::Array(array));
+            }
+            array.push(self.value()?);
+            self.skip_blank();
+            if !self.eat(",") {
+                self.skip_blank();
+                if self.eat("]") {
+                    return Ok(Value::Array(array));
+                }
+                return self.error("Expected `,` or `]` in an array");
+            }
+        }
+    }
+
+    /// Reads the rest of an inline table, after its opening brace
+    fn inline_table(&mut self) -> Result<Value, TomlError> {
+        let mut table = Table::new();
+        self.skip_whitespace();
+        if self.eat("}") {
+            return Ok(Value::Table(table));
+        }
+        loop {
+            let key = self.key()?;
+            if !self.eat("=") {
+                return self.error("Expected `=` after a key");
+            }
+            self.skip_whitespace();
+            let value = self.value()?;
+            insert(&mut table, &key, value).or_else(|message| self.error(message))?;
+            self.skip_whitespace();
+            if self.eat("}") {
+                return Ok(Value::Table(table));
+            }
+            if !self.eat(",") {
+                return self.error("Expected `,` or `}` in an inline table");
+            }
+        }
+    }
+
+    /// Reads an integer, in decimal or with a `0x`, `0o`, or `0b` prefix
+    fn integer(&mut self) -> Result<Value, TomlError> {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '+' | '-' | '.' | ':'))
+        {
+            self.bump();
+        }
+        let raw = self.input[start..self.pos].replace('_', "");
+        let (radix, digits) = match raw.get(..2) {
+            Some("0x") => (16, &raw[2..]),
+            Some("0o") => (8, &raw[2..]),
+            Some("0b") => (2, &raw[2..]),
+            _ => (10, &raw[..]),
+        };
+        match i64::from_str_radix(digits, radix) {
+            Ok(integer) if !digits.is_empty() => Ok(Value::Integer(integer)),
+            _ if raw.is_empty() => self.error("Expected a value"),
+            _ => self.error(format!("Unsupported value `{raw}`")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    // Documents using every supported part of TOML are read, and written back the same
+    #[test]
+    fn round_trip() {
+        let document = r#"
+            # A comment
+            name = "Example \"pack\"" # after a value
+            literal = 'C:\mods'
+            dotted.key = 0x10
+            authors = [
+                "alice",
+                "bob", # trailing comma
+            ]
+            inline = { enabled = true, count = -1_000 }
+            description = """Two \
+              lines"""
+
+            [index]
+            "hash-format" = "sha256"
+
+            [[files]]
+            file = "mods/a.pw.toml"
+
+            [[files]]
+            file = "mods/b.pw.toml"
+            metafile = false
+
+            [files.option]
+            optional = true
+        "#;
+        let table = parse(document).unwrap();
+        assert_eq!(table["name"].as_str(), Some("Example \"pack\""));
+        assert_eq!(table["literal"].as_str(), Some("C:\\mods"));
+        assert_eq!(get(&table, "dotted.key").unwrap().as_integer(), Some(16));
+        assert_eq!(table["authors"].as_array().unwrap().len(), 2);
+        assert_eq!(get(&table, "inline.count"), Some(&Value::Integer(-1000)));
+        assert_eq!(table["description"].as_str(), Some("Two lines"));
+        assert_eq!(get(&table, "index.hash-format"), Some(&"sha256".into()));
+        let files = table["files"].as_array().unwrap();
+        assert_eq!(files.len(), 2);
+        let second = files[1].as_table().unwrap();
+        assert_eq!(get(second, "option.optional"), Some(&true.into()));
+        assert_eq!(parse(&to_string(&table)).unwrap(), table);
+
+        let error = parse("a = 1\na = 2").unwrap_err();
+        assert_eq!(error.line(), 2);
+        let error = parse("[a]\nb = 1\n[c]\n[a]\nd = 2").unwrap_err();
+        assert_eq!(error.line(), 4);
+        assert!(parse("[a.b]\n[a]").is_ok());
+        assert!(parse("[[a]]\n[a.b]\n[[a]]\n[a.b]").is_ok());
+        assert!(parse("pi = 3.14").is_err());
+        assert!(parse("name = \"unterminated").is_err());
+    }
+}
@@ -2,17 +2,30 @@
 //!
 //! TODO: Improve Documentation
 
+mod asset;
 mod files;
+mod hashes;
+mod license;
 mod loader;
+mod localized;
 mod minecraft;
+mod slug;
 
 // Rexport types
-pub use files::{ManagedFile, Side, Source};
+pub use asset::{Asset, GalleryImage};
+pub use files::{Build, FileKind, ManagedFile, Side, Source, DISABLED_SUFFIX};
+pub use hashes::{HashAlgorithm, Hashes};
+pub use license::{License, LicenseError};
 pub use loader::Loader;
+pub use localized::{LocalizedError, LocalizedString};
 pub use minecraft::Minecraft;
+pub use slug::{Forge, ForgeSlug, SlugError};
 
-use semver::Version;
-use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+use semver::{Prerelease, Version, VersionReq};
+use serde::{Deserialize, Deserializer, Serialize};
+use url::Url;
 
 /// The versions of minecraft and the launcher for this instance of the pack
 ///
@@ -23,29 +36,777 @@ pub struct Versions {
     pub minecraft: Minecraft,
     /// The loader this pack works with
     pub loader: Loader,
+    /// The Java runtime the pack needs, if it differs from what the minecraft version implies
+    ///
+    /// See [`Versions::java_requirement`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub java: Option<JavaRequirement>,
+}
+
+impl Versions {
+    /// Returns the Java runtime the pack needs, either as declared or as implied by the
+    /// minecraft version
+    pub fn java_requirement(&self) -> Option<JavaRequirement> {
+        self.java
+            .clone()
+            .or_else(|| JavaRequirement::for_minecraft(&self.minecraft))
+    }
+}
+
+/// The Java runtime needed to run a pack
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Hash)]
+pub struct JavaRequirement {
+    /// The oldest major Java version that works (`17` for Java 17)
+    pub min_major: u16,
+    /// The major Java version to install, if newer than the minimum
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recommended_major: Option<u16>,
+    /// The amount of memory to give the game, in megabytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recommended_memory_mb: Option<u32>,
+}
+
+impl JavaRequirement {
+    /// Creates a requirement for the given major Java version, with no recommendations
+    pub fn new(min_major: u16) -> Self {
+        Self {
+            min_major,
+            recommended_major: None,
+            recommended_memory_mb: None,
+        }
+    }
+
+    /// Returns the major Java version to install
+    pub fn recommended(&self) -> u16 {
+        self.recommended_major.unwrap_or(self.min_major)
+    }
+
+    /// Returns the Java version vanilla minecraft needs, or `None` for snapshots, whose
+    /// requirements aren't tracked
+    ///
+    /// Pre-releases and release candidates need what their release does, and classic, alpha, and
+    /// beta versions need Java 8, the oldest ffpack knows
+    pub fn for_minecraft(minecraft: &Minecraft) -> Option<Self> {
+        if matches!(
+            minecraft,
+            Minecraft::OldClassic { .. } | Minecraft::OldAlpha { .. } | Minecraft::OldBeta { .. }
+        ) {
+            return Some(Self::new(8));
+        }
+        let Some(Minecraft::Release {
+            major,
+            minor,
+            patch,
+        }) = minecraft.release()
+        else {
+            return None;
+        };
+        let version = (major, minor, patch.unwrap_or(0));
+        let min_major = if version >= (1, 20, 5) {
+            21
+        } else if version >= (1, 18, 0) {
+            17
+        } else if version >= (1, 17, 0) {
+            16
+        } else {
+            8
+        };
+        Some(Self::new(min_major))
+    }
 }
 
 /// The metadata for the pack
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Hash)]
 pub struct Metadata {
     /// The name of this mod pack
-    name: String,
+    name: LocalizedString,
     /// An optional description for this mod pack
     #[serde(skip_serializing_if = "Option::is_none")]
-    description: Option<String>,
-    /// The author of the pack
-    author: String,
+    description: Option<LocalizedString>,
+    /// The people behind the pack
+    ///
+    /// For backwards compatibility this also accepts the older single `author` string
+    #[serde(alias = "author", deserialize_with = "deserialize_contributors")]
+    authors: Vec<Contributor>,
     /// The version of the pack
     version: Version,
+    /// The license of the pack, as an SPDX expression
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    license: Option<License>,
+    /// Links to the pack's website, source, and community pages
+    #[serde(default, skip_serializing_if = "Links::is_empty")]
+    links: Links,
+    /// Categories for pack indexes and exporters, normalized by [`normalize_tag`]
+    #[serde(
+        default,
+        skip_serializing_if = "BTreeSet::is_empty",
+        deserialize_with = "deserialize_tags"
+    )]
+    tags: BTreeSet<String>,
+    /// The pack's icon
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    icon: Option<Asset>,
+    /// Screenshots and other images showing off the pack
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    gallery: Vec<GalleryImage>,
+    /// The versions of ffpack that understand this manifest, checked by
+    /// [`Pack::from_json`](crate::Pack::from_json)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    requires: Option<VersionReq>,
+    /// The release channel this version of the pack is published on
+    #[serde(default, skip_serializing_if = "Channel::is_release")]
+    channel: Channel,
+}
+
+/// The suggested vocabulary of tags
+///
+/// Tags outside of this list are allowed, but may not map onto the categories of every platform.
+/// See [`Metadata::unknown_tags`].
+pub const KNOWN_TAGS: &[&str] = &[
+    "adventure",
+    "challenging",
+    "combat",
+    "exploration",
+    "kitchen-sink",
+    "lightweight",
+    "magic",
+    "multiplayer",
+    "optimization",
+    "quests",
+    "tech",
+    "vanilla-plus",
+];
+
+/// Normalizes a tag, trimming and lowercasing it and joining words with `-`
+///
+/// Returns `None` if the tag is blank
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let words: Vec<_> = tag.split_whitespace().collect();
+    if words.is_empty() {
+        None
+    } else {
+        Some(words.join("-").to_lowercase())
+    }
+}
+
+impl Metadata {
+    /// Creates metadata for a pack with the given name, author, and version, and no description
+    pub fn new(
+        name: impl Into<LocalizedString>,
+        author: impl Into<Contributor>,
+        version: Version,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            description: None,
+            authors: vec![author.into()],
+            version,
+            license: None,
+            links: Links::default(),
+            tags: BTreeSet::new(),
+            icon: None,
+            gallery: Vec::new(),
+            requires: None,
+            channel: Channel::default(),
+        }
+    }
+
+    /// Sets the description, consuming and returning `self` for chaining off of
+    /// [`Metadata::new`]
+    #[must_use]
+    pub fn with_description(mut self, description: impl Into<LocalizedString>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Sets the license, consuming and returning `self` for chaining off of [`Metadata::new`]
+    #[must_use]
+    pub fn with_license(mut self, license: License) -> Self {
+        self.license = Some(license);
+        self
+    }
+
+    /// Adds a contributor, consuming and returning `self` for chaining off of [`Metadata::new`]
+    #[must_use]
+    pub fn with_author(mut self, author: impl Into<Contributor>) -> Self {
+        self.authors.push(author.into());
+        self
+    }
+
+    /// Sets the project links, consuming and returning `self` for chaining off of
+    /// [`Metadata::new`]
+    #[must_use]
+    pub fn with_links(mut self, links: Links) -> Self {
+        self.links = links;
+        self
+    }
+
+    /// Adds a tag, consuming and returning `self` for chaining off of [`Metadata::new`]
+    #[must_use]
+    pub fn with_tag(mut self, tag: impl AsRef<str>) -> Self {
+        self.add_tag(tag);
+        self
+    }
+
+    /// Sets the icon, consuming and returning `self` for chaining off of [`Metadata::new`]
+    #[must_use]
+    pub fn with_icon(mut self, icon: Asset) -> Self {
+        self.icon = Some(icon);
+        self
+    }
+
+    /// Sets the required ffpack versions, consuming and returning `self` for chaining off of
+    /// [`Metadata::new`]
+    #[must_use]
+    pub fn with_requires(mut self, requires: VersionReq) -> Self {
+        self.requires = Some(requires);
+        self
+    }
+
+    /// Sets the release channel, consuming and returning `self` for chaining off of
+    /// [`Metadata::new`]
+    #[must_use]
+    pub fn with_channel(mut self, channel: Channel) -> Self {
+        self.channel = channel;
+        self
+    }
+
+    /// Returns the name of the pack in the given language, or the default name for `None`
+    ///
+    /// See [`LocalizedString::get`] for how missing translations fall back.
+    pub fn name(&self, lang: Option<&str>) -> &str {
+        self.name.get(lang)
+    }
+
+    /// Returns the name of the pack with all of its translations
+    pub fn localized_name(&self) -> &LocalizedString {
+        &self.name
+    }
+
+    /// Sets the name of the pack
+    pub fn set_name(&mut self, name: impl Into<LocalizedString>) {
+        self.name = name.into();
+    }
+
+    /// Returns the description of the pack in the given language, if it has one
+    ///
+    /// See [`LocalizedString::get`] for how missing translations fall back.
+    pub fn description(&self, lang: Option<&str>) -> Option<&str> {
+        self.description
+            .as_ref()
+            .map(|description| description.get(lang))
+    }
+
+    /// Returns the description of the pack with all of its translations, if it has one
+    pub fn localized_description(&self) -> Option<&LocalizedString> {
+        self.description.as_ref()
+    }
+
+    /// Sets or clears the description of the pack
+    pub fn set_description(&mut self, description: Option<LocalizedString>) {
+        self.description = description;
+    }
+
+    /// Returns the authors and other contributors of the pack
+    pub fn authors(&self) -> &[Contributor] {
+        &self.authors
+    }
+
+    /// Returns a mutable reference to the contributors of the pack
+    pub fn authors_mut(&mut self) -> &mut Vec<Contributor> {
+        &mut self.authors
+    }
+
+    /// Adds a contributor to the pack
+    pub fn add_author(&mut self, author: impl Into<Contributor>) {
+        self.authors.push(author.into());
+    }
+
+    /// Returns the version of the pack
+    pub fn version(&self) -> &Version {
+        &self.version
+    }
+
+    /// Sets the version of the pack
+    pub fn set_version(&mut self, version: Version) {
+        self.version = version;
+    }
+
+    /// Returns the license of the pack, if it declares one
+    pub fn license(&self) -> Option<&License> {
+        self.license.as_ref()
+    }
+
+    /// Sets or clears the license of the pack
+    pub fn set_license(&mut self, license: Option<License>) {
+        self.license = license;
+    }
+
+    /// Returns the project links of the pack
+    pub fn links(&self) -> &Links {
+        &self.links
+    }
+
+    /// Returns a mutable reference to the project links of the pack
+    pub fn links_mut(&mut self) -> &mut Links {
+        &mut self.links
+    }
+
+    /// Returns the pack's tags, in sorted order
+    pub fn tags(&self) -> &BTreeSet<String> {
+        &self.tags
+    }
+
+    /// Normalizes and adds a tag, returning false if it was blank or already present
+    pub fn add_tag(&mut self, tag: impl AsRef<str>) -> bool {
+        normalize_tag(tag.as_ref()).is_some_and(|tag| self.tags.insert(tag))
+    }
+
+    /// Removes a tag, returning true if it was present
+    pub fn remove_tag(&mut self, tag: impl AsRef<str>) -> bool {
+        normalize_tag(tag.as_ref()).is_some_and(|tag| self.tags.remove(&tag))
+    }
+
+    /// Returns the icon of the pack, if it has one
+    pub fn icon(&self) -> Option<&Asset> {
+        self.icon.as_ref()
+    }
+
+    /// Sets or clears the icon of the pack
+    pub fn set_icon(&mut self, icon: Option<Asset>) {
+        self.icon = icon;
+    }
+
+    /// Returns the gallery of the pack
+    pub fn gallery(&self) -> &[GalleryImage] {
+        &self.gallery
+    }
+
+    /// Returns a mutable reference to the gallery of the pack
+    pub fn gallery_mut(&mut self) -> &mut Vec<GalleryImage> {
+        &mut self.gallery
+    }
+
+    /// Returns the versions of ffpack that understand this manifest, if restricted
+    pub fn requires(&self) -> Option<&VersionReq> {
+        self.requires.as_ref()
+    }
+
+    /// Sets or clears the versions of ffpack that understand this manifest
+    pub fn set_requires(&mut self, requires: Option<VersionReq>) {
+        self.requires = requires;
+    }
+
+    /// Returns the declared release channel of the pack
+    ///
+    /// See [`Metadata::release_channel`] for the channel the pack should be published on.
+    pub fn channel(&self) -> Channel {
+        self.channel
+    }
+
+    /// Sets the declared release channel of the pack
+    pub fn set_channel(&mut self, channel: Channel) {
+        self.channel = channel;
+    }
+
+    /// Returns the channel the pack should be published on: the declared channel, or a less
+    /// stable one if the version's pre-release component implies it
+    pub fn release_channel(&self) -> Channel {
+        self.channel
+            .min(Channel::from_prerelease(&self.version.pre))
+    }
+
+    /// Returns true if this version of the pack is an alpha or beta, either by its declared
+    /// channel or by a pre-release version like `1.2.0-beta.1`
+    pub fn is_prerelease(&self) -> bool {
+        self.release_channel() != Channel::Release
+    }
+
+    /// Returns the tags that aren't in [`KNOWN_TAGS`]
+    pub fn unknown_tags(&self) -> impl Iterator<Item = &str> {
+        self.tags
+            .iter()
+            .map(String::as_str)
+            .filter(|tag| !KNOWN_TAGS.contains(tag))
+    }
 }
 
 impl Default for Metadata {
     fn default() -> Self {
         Self {
-            name: "My super cool modpack!".to_string(),
-            description: Some("Totally a real mod pack!".to_string()),
-            author: "Your name here!".to_string(),
+            name: LocalizedString::from("My super cool modpack!"),
+            description: Some(LocalizedString::from("Totally a real mod pack!")),
+            authors: vec![Contributor::from("Your name here!")],
             version: Version::parse("0.0.1").unwrap(),
+            license: None,
+            links: Links::default(),
+            tags: BTreeSet::new(),
+            icon: None,
+            gallery: Vec::new(),
+            requires: None,
+            channel: Channel::default(),
+        }
+    }
+}
+
+/// How stable a release of a pack is
+///
+/// Ordered from least to most stable
+#[derive(
+    Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord, Default,
+)]
+pub enum Channel {
+    /// Early, possibly broken builds
+    Alpha,
+    /// Testing builds, mostly working
+    Beta,
+    /// Stable releases
+    #[default]
+    Release,
+}
+
+impl Channel {
+    /// Infers the channel from a semver pre-release component
+    ///
+    /// No pre-release means a release, `alpha` (and `dev`, `nightly`, and `snapshot`) means an
+    /// alpha, and anything else, such as `beta` or `rc`, means a beta
+    pub fn from_prerelease(pre: &Prerelease) -> Self {
+        if pre.is_empty() {
+            return Channel::Release;
+        }
+        let first = pre.as_str().split('.').next().unwrap_or_default();
+        let first = first.to_ascii_lowercase();
+        if ["alpha", "dev", "nightly", "snapshot"]
+            .iter()
+            .any(|prefix| first.starts_with(prefix))
+        {
+            Channel::Alpha
+        } else {
+            Channel::Beta
+        }
+    }
+
+    /// Returns true if this is [`Channel::Release`]
+    pub fn is_release(&self) -> bool {
+        *self == Channel::Release
+    }
+}
+
+/// Links to a pack's web presence
+///
+/// Exporters pull these into the formats that support them (e.g. the website and issue tracker
+/// on Modrinth or CurseForge)
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Hash, Default)]
+pub struct Links {
+    /// The pack's website
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub homepage: Option<Url>,
+    /// Where the pack's source (the manifest and its assets) lives
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<Url>,
+    /// The pack's issue tracker
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issues: Option<Url>,
+    /// Invite link to the pack's Discord server
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discord: Option<Url>,
+    /// The pack's wiki
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wiki: Option<Url>,
+}
+
+impl Links {
+    /// Returns true if no links are set
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+/// Someone who works on a pack
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Hash)]
+pub struct Contributor {
+    /// The contributor's name
+    pub name: String,
+    /// What they do for the pack (`Maintainer`, `Artist`, ...)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    /// A link to the contributor's page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<Url>,
+}
+
+impl Contributor {
+    /// Creates a contributor with the given name and role
+    pub fn new(name: impl Into<String>, role: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            role: Some(role.into()),
+            url: None,
+        }
+    }
+}
+
+impl From<String> for Contributor {
+    fn from(name: String) -> Self {
+        Self {
+            name,
+            role: None,
+            url: None,
+        }
+    }
+}
+
+impl From<&str> for Contributor {
+    fn from(name: &str) -> Self {
+        Self::from(name.to_string())
+    }
+}
+
+/// Deserializes the contributor list, also accepting a single name as older manifests used,
+/// and bare names within the list
+fn deserialize_contributors<'de, D>(deserializer: D) -> Result<Vec<Contributor>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    /// A single list entry, either a bare name or a full contributor
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Entry {
+        /// Just the name
+        Name(String),
+        /// A full contributor
+        Full(Contributor),
+    }
+    /// The field's value, either one name or a list
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Field {
+        /// The legacy single author string
+        Single(String),
+        /// A list of contributors
+        List(Vec<Entry>),
+    }
+    Ok(match Field::deserialize(deserializer)? {
+        Field::Single(name) => vec![Contributor::from(name)],
+        Field::List(entries) => entries
+            .into_iter()
+            .map(|entry| match entry {
+                Entry::Name(name) => Contributor::from(name),
+                Entry::Full(contributor) => contributor,
+            })
+            .collect(),
+    })
+}
+
+/// Deserializes the tag set, normalizing each tag and dropping blank ones
+fn deserialize_tags<'de, D>(deserializer: D) -> Result<BTreeSet<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .filter_map(|tag| normalize_tag(tag))
+        .collect())
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    // Older manifests with a single author string still load
+    #[test]
+    fn legacy_author() {
+        let metadata: Metadata =
+            serde_json::from_str(r#"{"name": "Pack", "author": "Alice, Bob", "version": "1.0.0"}"#)
+                .unwrap();
+        assert_eq!(metadata.authors(), [Contributor::from("Alice, Bob")]);
+        // And are written back out in the new form
+        let value = serde_json::to_value(&metadata).unwrap();
+        assert_eq!(value["authors"][0]["name"], "Alice, Bob");
+        assert!(value.get("author").is_none());
+    }
+
+    // Lists can mix bare names and full contributors
+    #[test]
+    fn contributor_list() {
+        let metadata: Metadata = serde_json::from_str(
+            r#"{
+                "name": "Pack",
+                "authors": [
+                    "Alice",
+                    {"name": "Bob", "role": "Artist", "url": "https://example.org/bob"}
+                ],
+                "version": "1.0.0"
+            }"#,
+        )
+        .unwrap();
+        let bob = Contributor {
+            url: Some(Url::parse("https://example.org/bob").unwrap()),
+            ..Contributor::new("Bob", "Artist")
+        };
+        assert_eq!(metadata.authors(), [Contributor::from("Alice"), bob]);
+    }
+
+    // Tags are normalized and deduplicated on the way in
+    #[test]
+    fn tags() {
+        let metadata: Metadata = serde_json::from_str(
+            r#"{
+                "name": "Pack",
+                "authors": ["Alice"],
+                "version": "1.0.0",
+                "tags": ["Tech", "tech", " Kitchen  Sink ", "", "Homebrew"]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            metadata.tags().iter().collect::<Vec<_>>(),
+            ["homebrew", "kitchen-sink", "tech"]
+        );
+        assert_eq!(metadata.unknown_tags().collect::<Vec<_>>(), ["homebrew"]);
+        let mut metadata = metadata;
+        assert!(!metadata.add_tag("TECH"));
+        assert!(!metadata.add_tag("  "));
+        assert!(metadata.remove_tag("Kitchen Sink"));
+        assert!(metadata.add_tag("Technology"));
+        assert_eq!(metadata.unknown_tags().count(), 2);
+    }
+
+    // Icons and gallery images can come from the repository or a url
+    #[test]
+    fn assets() {
+        let metadata: Metadata = serde_json::from_str(
+            r#"{
+                "name": "Pack",
+                "authors": ["Alice"],
+                "version": "1.0.0",
+                "icon": {"Path": {"path": "assets/icon.png"}},
+                "gallery": [
+                    {
+                        "image": {
+                            "Url": {
+                                "url": "https://example.org/images/base.png",
+                                "blake3": "0000000000000000000000000000000000000000000000000000000000000000"
+                            }
+                        },
+                        "title": "A base"
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(metadata.icon().and_then(Asset::file_name), Some("icon.png"));
+        assert_eq!(metadata.gallery().len(), 1);
+        assert_eq!(metadata.gallery()[0].image.file_name(), Some("base.png"));
+        assert_eq!(metadata.gallery()[0].title.as_deref(), Some("A base"));
+    }
+
+    // Names and descriptions can be translated
+    #[test]
+    fn localized() {
+        let metadata: Metadata = serde_json::from_str(
+            r#"{
+                "name": {"en": "Tech pack", "de": "Technikpaket"},
+                "description": "Gears and pipes",
+                "authors": ["Alice"],
+                "version": "1.0.0"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(metadata.name(Some("de-CH")), "Technikpaket");
+        assert_eq!(metadata.name(None), "Tech pack");
+        assert_eq!(metadata.description(Some("de")), Some("Gears and pipes"));
+    }
+
+    // The channel comes from the declaration or the version, whichever is less stable
+    #[test]
+    fn channels() {
+        let metadata = |version: &str, channel| {
+            Metadata::new("Pack", "Alice", Version::parse(version).unwrap()).with_channel(channel)
+        };
+        assert!(!metadata("1.0.0", Channel::Release).is_prerelease());
+        assert_eq!(
+            metadata("1.0.0", Channel::Beta).release_channel(),
+            Channel::Beta
+        );
+        assert_eq!(
+            metadata("1.0.0-rc.1", Channel::Release).release_channel(),
+            Channel::Beta
+        );
+        assert_eq!(
+            metadata("1.0.0-alpha.3", Channel::Beta).release_channel(),
+            Channel::Alpha
+        );
+        assert_eq!(
+            metadata("1.0.0-beta", Channel::Alpha).release_channel(),
+            Channel::Alpha
+        );
+        let value = serde_json::to_value(metadata("1.0.0", Channel::Release)).unwrap();
+        assert!(value.get("channel").is_none());
+        let value = serde_json::to_value(metadata("1.0.0", Channel::Beta)).unwrap();
+        assert_eq!(value["channel"], "Beta");
+    }
+
+    // Declared java requirements win over the ones implied by minecraft
+    #[test]
+    fn java_requirement() {
+        let mut versions = Versions::default();
+        for (minecraft, java) in [
+            ("b1.7.3", 8),
+            ("1.12.2", 8),
+            ("1.17.1", 16),
+            ("1.20.4", 17),
+            ("1.20.5", 21),
+        ] {
+            versions.minecraft = Minecraft::new(minecraft).unwrap();
+            assert_eq!(
+                versions.java_requirement(),
+                Some(JavaRequirement::new(java))
+            );
+        }
+        versions.java = Some(JavaRequirement {
+            recommended_major: Some(22),
+            recommended_memory_mb: Some(6144),
+            ..JavaRequirement::new(21)
+        });
+        assert_eq!(versions.java_requirement().unwrap().recommended(), 22);
+        versions.minecraft = Minecraft::new("24w14a").unwrap();
+        versions.java = None;
+        assert!(versions.java_requirement().is_none());
+    }
+
+    // Sources keep blake3 as their primary digest, with others alongside it
+    #[test]
+    fn source_hashes() {
+        let json = serde_json::json!({"Url": {
+            "url": "https://example.org/mod.jar",
+            "blake3": "01".repeat(32),
+            "hashes": {"sha1": "02".repeat(20), "blake3": "03".repeat(32)}
+        }});
+        let source: Source = serde_json::from_value(json).unwrap();
+        let hashes = source.hashes().unwrap();
+        assert_eq!(hashes.blake3, Some([1; 32]));
+        assert_eq!(hashes.sha1, Some([2; 20]));
+        let plain = serde_json::to_value(Source::default()).unwrap();
+        assert!(plain["Url"].get("hashes").is_none());
+        assert!(Source::Modrinth {
+            slug: "sodium".to_string(),
+            version_id: None,
         }
+        .hashes()
+        .is_none());
+        // Sources written before versions could be pinned still load, and float
+        let floating: Source =
+            serde_json::from_value(serde_json::json!({"Modrinth": {"slug": "sodium"}})).unwrap();
+        assert_eq!(
+            floating,
+            Source::Modrinth {
+                slug: "sodium".to_string(),
+                version_id: None,
+            }
+        );
+        assert!(serde_json::to_value(&floating).unwrap()["Modrinth"]
+            .get("version_id")
+            .is_none());
     }
 }
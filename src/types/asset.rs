@@ -0,0 +1,59 @@
+//! Type wrapper for images and other assets describing a pack
+
+use relative_path::RelativePathBuf;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// Where an asset, such as the pack icon, comes from
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Hash)]
+pub enum Asset {
+    /// Path to a file in the repository
+    Path {
+        /// The path the file is located at relative to the directory the manifest is in
+        path: RelativePathBuf,
+    },
+    /// File hosted elsewhere
+    Url {
+        /// The url to download the file from
+        url: Url,
+        /// The blake3 hash of the file
+        #[serde(with = "hex::serde")]
+        blake3: [u8; 32],
+    },
+}
+
+impl Asset {
+    /// Returns the file name of the asset, if it has one
+    pub fn file_name(&self) -> Option<&str> {
+        match self {
+            Asset::Path { path } => path.file_name(),
+            Asset::Url { url, .. } => url
+                .path_segments()
+                .and_then(Iterator::last)
+                .filter(|name| !name.is_empty()),
+        }
+    }
+}
+
+/// An image in the pack's gallery
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Hash)]
+pub struct GalleryImage {
+    /// The image itself
+    pub image: Asset,
+    /// Optional title for the image
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Optional description for the image
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl From<Asset> for GalleryImage {
+    fn from(image: Asset) -> Self {
+        Self {
+            image,
+            title: None,
+            description: None,
+        }
+    }
+}
@@ -1,23 +1,50 @@
 //! Type wrapper for dealing with files
 
+use std::{collections::BTreeMap, fs, io, path::Path};
+
 use relative_path::RelativePathBuf;
 use serde::{Deserialize, Serialize};
 use url::Url;
 
+use super::{HashAlgorithm, Hashes};
+use crate::hash::blake3;
+
 /// Marker to determine if this mod is needed on the server, the client, or both
-#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Hash, PartialOrd, Ord)]
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Hash, PartialOrd, Ord, Default)]
 pub enum Side {
     /// Client side
     Client,
     /// Server side
     Server,
     /// Both server and client side
+    #[default]
     Both,
 }
 
-impl Default for Side {
-    fn default() -> Self {
-        Side::Both
+/// How a file is installed, beyond being placed at its path
+#[derive(
+    PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Copy, Hash, PartialOrd, Ord, Default,
+)]
+pub enum FileKind {
+    /// A file placed at its path, like any modern mod
+    #[default]
+    Regular,
+    /// A legacy Forge coremod
+    ///
+    /// Forge for 1.5 and older loads these from `coremods/`, and up to 1.7.10 from `mods/`, so
+    /// the file's path decides which; the kind tells exporters it isn't an ordinary mod.
+    Coremod,
+    /// A jarmod, patched into the minecraft jar instead of being loaded from a folder
+    ///
+    /// Jarmods are applied in path order, which matters when several patch the same classes.
+    Jarmod,
+}
+
+impl FileKind {
+    /// Returns true for regular files, which don't need the kind written out
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    pub fn is_regular(&self) -> bool {
+        *self == Self::Regular
     }
 }
 
@@ -40,6 +67,48 @@ pub struct ManagedFile {
     pub side: Side,
     /// The source of this file
     pub source: Source,
+    /// Whether the file is installed normally
+    ///
+    /// Disabled files are installed with a `.disabled` suffix, which loaders skip, so they can be
+    /// pulled temporarily without losing their entry.
+    #[serde(default = "enabled_default", skip_serializing_if = "is_enabled")]
+    pub enabled: bool,
+    /// Free-form notes for the pack's maintainers ("waiting on the 1.20 port"), never exported
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    /// How the file is installed, for legacy coremods and jarmods
+    #[serde(default, skip_serializing_if = "FileKind::is_regular")]
+    pub kind: FileKind,
+    /// The id of the Modrinth collection the file was added from, which syncing the collection
+    /// removes it again with once it leaves
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collection: Option<String>,
+}
+
+/// Files are enabled unless stated otherwise
+fn enabled_default() -> bool {
+    true
+}
+
+/// Returns true for enabled files, which don't need the flag written out
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_enabled(enabled: &bool) -> bool {
+    *enabled
+}
+
+/// The suffix loaders skip files with
+pub const DISABLED_SUFFIX: &str = ".disabled";
+
+impl ManagedFile {
+    /// Returns the path the file is installed at, with [`DISABLED_SUFFIX`] appended if it is
+    /// disabled
+    pub fn install_path(&self) -> RelativePathBuf {
+        if self.enabled {
+            self.path.clone()
+        } else {
+            RelativePathBuf::from(format!("{}{DISABLED_SUFFIX}", self.path))
+        }
+    }
 }
 
 impl PartialOrd for ManagedFile {
@@ -64,6 +133,10 @@ impl Default for ManagedFile {
             path: RelativePathBuf::from_path("mods/MyAwesomeMod.jar").unwrap(),
             side: Side::default(),
             source: Source::default(),
+            enabled: true,
+            notes: None,
+            kind: FileKind::Regular,
+            collection: None,
         }
     }
 }
@@ -78,22 +151,79 @@ pub enum Source {
         /// The blake3 hash of this url
         #[serde(with = "hex::serde")]
         blake3: [u8; 32],
+        /// Digests under other algorithms, for platforms that don't use blake3
+        #[serde(default, skip_serializing_if = "Hashes::is_empty")]
+        hashes: Hashes,
+        /// Other urls serving the same file, tried in order when `url` is gone or failing
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        mirrors: Vec<Url>,
     },
     /// Path to a file in the repository
+    ///
+    /// This is how packs carry files that aren't published anywhere, like private configuration
+    /// mods. The file is copied into instances and checked against its hash like a download;
+    /// [`Source::path_with_hash`] creates a source with the hash filled in.
     Path {
-        /// The path the file is located at relative to the directory the manifest is in
+        /// The path the file is located at relative to the directory the manifest is in, which
+        /// it can't lead out of
         path: RelativePathBuf,
-        /// The blake3 hash of this url
+        /// The blake3 hash of the file
+        #[serde(with = "hex::serde")]
+        blake3: [u8; 32],
+        /// Digests under other algorithms, for platforms that don't use blake3
+        #[serde(default, skip_serializing_if = "Hashes::is_empty")]
+        hashes: Hashes,
+    },
+    /// File published on IPFS, fetched through an HTTP gateway
+    ///
+    /// Content addressing keeps abandoned mods available for as long as anyone pins them.
+    /// Downloads are checked against the CID where it's a plain digest of the file, and against
+    /// the blake3 hash always.
+    Ipfs {
+        /// The content id of the file
+        cid: String,
+        /// The gateway to fetch through, defaulting to the resolver's
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        gateway: Option<Url>,
+        /// The blake3 hash of the file
         #[serde(with = "hex::serde")]
         blake3: [u8; 32],
+        /// Digests under other algorithms, for platforms that don't use blake3
+        #[serde(default, skip_serializing_if = "Hashes::is_empty")]
+        hashes: Hashes,
+    },
+    /// File from an artifact store an embedder plugs in, named by a url with a custom scheme
+    /// like `mycompany+https://artifacts.example.com/mods/lib/1.2`
+    ///
+    /// The scheme up to its first `+` picks the
+    /// [`SourceResolver`](crate::resolve::SourceResolver) the url goes to. Sources nothing is
+    /// registered for still load and save unchanged, they just can't be resolved.
+    Custom {
+        /// The url of the file in the store
+        url: Url,
+        /// Anything else the store's resolver needs, kept as written
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        params: BTreeMap<String, String>,
     },
     /// Git repoistory
+    ///
+    /// The most specific of `rev`, `tag`, and `branch` picks what is checked out; see
+    /// [`GitRef`](crate::git::GitRef).
     Git {
         /// The url of the repository
         url: Url,
         /// The branch to work off of, defaulting to the default branch
         #[serde(skip_serializing_if = "Option::is_none")]
         branch: Option<String>,
+        /// The tag to check out, instead of the tip of a branch
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tag: Option<String>,
+        /// The exact commit to check out, as pinned by a lockfile
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        rev: Option<String>,
+        /// How to build the file from the checked out sources
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        build: Option<Build>,
     },
     /// Slug for a supported forge (`github:username/project`, `gitlab:username/project`, etc)
     Slug {
@@ -111,6 +241,9 @@ pub enum Source {
     /// return one result
     ///
     /// You can optionally specify a release regex to match a specific range of releases
+    ///
+    /// Releases can be resolved from GitHub, GitLab, Codeberg, and self-hosted Gitea instances;
+    /// see [`ForgeSlug`](crate::types::ForgeSlug) for how slugs for each are written.
     SlugReleases {
         /// The slug
         slug: String,
@@ -124,12 +257,100 @@ pub enum Source {
     Modrinth {
         /// Slug for the mod
         slug: String,
+        /// The id of the version to use, instead of the newest compatible one
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        version_id: Option<String>,
     },
     /// Curseforge mod
     Curseforge {
         /// Slug for the mod
         slug: String,
+        /// The id of the file to use, instead of the newest compatible one
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        file_id: Option<u64>,
     },
+    /// Artifact uploaded by a GitHub Actions workflow, for projects that only publish
+    /// development builds from CI
+    ///
+    /// Artifacts are zip archives; the file is taken out of the archive after downloading.
+    /// Downloading them from GitHub needs a token, even for public repositories, which
+    /// [nightly.link](https://nightly.link) can stand in for.
+    GithubActions {
+        /// The repository, as a `github:owner/project` slug
+        slug: String,
+        /// The file name of the workflow, like `build.yml`
+        workflow: String,
+        /// The name of the artifact
+        artifact: String,
+        /// The regex for matching the file in the artifact, defaulting to any jar
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        file_regex: Option<String>,
+        /// The branch whose runs are used, defaulting to any
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        branch: Option<String>,
+        /// The id of the workflow run to use, instead of the newest successful one
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        run_id: Option<u64>,
+        /// Whether to download through nightly.link, which needs no token
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        nightly_link: bool,
+    },
+    /// Artifact archived by a Jenkins job
+    Jenkins {
+        /// The url of the Jenkins instance, like `https://ci.example.org/`
+        base_url: Url,
+        /// The name of the job, with folders separated by `/`
+        job: String,
+        /// The regex for matching the artifact's file name, which must match only one
+        artifact_regex: String,
+        /// The number of the build to use, instead of the last successful one
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        build: Option<u32>,
+    },
+    /// Jar published to a maven repository
+    Maven {
+        /// The url of the repository, like `https://maven.fabricmc.net/`
+        repository: Url,
+        /// The group id, like `net.fabricmc.fabric-api`
+        group: String,
+        /// The artifact id, like `fabric-api`
+        artifact: String,
+        /// The version to use
+        ///
+        /// Besides an exact version, this can be `latest` or `release` for the newest version
+        /// the repository lists, with or without snapshots, or a maven version range like
+        /// `[1.0,2.0)` for the newest version inside it.
+        version: String,
+        /// The classifier of the jar, like `dev` or `sources`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        classifier: Option<String>,
+    },
+}
+
+/// How a git source is built into the file it provides
+///
+/// The command runs in the root of the checkout, and must leave exactly one file matching
+/// `artifact` behind. Builds are cached by commit, so they only run again when the source moves
+/// to another one.
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Hash, PartialOrd, Ord)]
+pub struct Build {
+    /// The program to run and its arguments, defaulting to `./gradlew build`
+    #[serde(default = "Build::gradle")]
+    pub command: Vec<String>,
+    /// A glob matching the built file, relative to the checkout, like `build/libs/*-dev.jar`
+    ///
+    /// `*` and `?` match within a single path component, and `**` matches any number of them.
+    pub artifact: String,
+    /// The name the built file is given, since build outputs often carry a version in theirs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+}
+
+impl Build {
+    /// The command most mods build with, running the Gradle wrapper they ship
+    pub fn gradle() -> Vec<String> {
+        vec!["./gradlew".to_string(), "build".to_string()]
+    }
 }
 
 impl Default for Source {
@@ -137,6 +358,105 @@ impl Default for Source {
         Self::Url {
             url: Url::parse("https://example.org/mods/MyAwesomeMod-1.2.3.jar").unwrap(),
             blake3: Default::default(),
+            hashes: Hashes::default(),
+            mirrors: Vec::new(),
+        }
+    }
+}
+
+impl Source {
+    /// Creates a source for the file at `path` relative to `root`, the directory the manifest
+    /// is in, recording its blake3 hash
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be read
+    pub fn path_with_hash(root: &Path, path: impl Into<RelativePathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let blake3 = blake3(&fs::read(path.to_path(root))?);
+        Ok(Source::Path {
+            path,
+            blake3,
+            hashes: Hashes::default(),
+        })
+    }
+
+    /// Returns the scheme that picks the resolver of a custom source, like `mycompany` for
+    /// `mycompany+https://…`
+    pub fn custom_scheme(&self) -> Option<&str> {
+        match self {
+            Source::Custom { url, .. } => url.scheme().split('+').next(),
+            _ => None,
+        }
+    }
+
+    /// Returns the source with whatever pins it to one version removed, so that it resolves to
+    /// the newest compatible one
+    ///
+    /// Maven sources at an exact version float to the newest release, and git sources to the tip
+    /// of their branch or their tag. Url, path, and IPFS sources name fixed content, so have
+    /// nothing newer to float to and return `None`.
+    pub fn floating(&self) -> Option<Source> {
+        let mut floating = self.clone();
+        match &mut floating {
+            Source::Url { .. } | Source::Path { .. } | Source::Ipfs { .. } => return None,
+            Source::Modrinth { version_id, .. } => *version_id = None,
+            Source::Curseforge { file_id, .. } => *file_id = None,
+            Source::GithubActions { run_id, .. } => *run_id = None,
+            Source::Jenkins { build, .. } => *build = None,
+            Source::Git { rev, .. } => *rev = None,
+            Source::Maven { version, .. } => {
+                let floats = ["latest", "LATEST", "release", "RELEASE"].contains(&&**version)
+                    || version.starts_with(['[', '('])
+                    || version.ends_with("-SNAPSHOT");
+                if !floats {
+                    *version = "release".into();
+                }
+            }
+            Source::Custom { .. } | Source::Slug { .. } | Source::SlugReleases { .. } => {}
+        }
+        Some(floating)
+    }
+
+    /// Returns every known digest of a url, path, or IPFS source, with blake3 as recorded in
+    /// the source taking precedence
+    ///
+    /// Other sources are resolved to a file later, so have no digests of their own.
+    pub fn hashes(&self) -> Option<Hashes> {
+        match self {
+            Source::Url { blake3, hashes, .. }
+            | Source::Path { blake3, hashes, .. }
+            | Source::Ipfs { blake3, hashes, .. } => {
+                let mut all = Hashes::blake3(*blake3);
+                all.merge(hashes);
+                Some(all)
+            }
+            _ => None,
+        }
+    }
+
+    /// Records a digest of a url, path, or IPFS source, replacing the primary hash for blake3
+    ///
+    /// Returns false, leaving the source alone, for other sources or a digest of the wrong
+    /// length.
+    pub fn set_hash(&mut self, algorithm: HashAlgorithm, digest: &[u8]) -> bool {
+        match self {
+            Source::Url { blake3, hashes, .. }
+            | Source::Path { blake3, hashes, .. }
+            | Source::Ipfs { blake3, hashes, .. } => {
+                if algorithm == HashAlgorithm::Blake3 {
+                    match digest.try_into() {
+                        Ok(digest) => {
+                            *blake3 = digest;
+                            true
+                        }
+                        Err(_) => false,
+                    }
+                } else {
+                    hashes.set(algorithm, digest)
+                }
+            }
+            _ => false,
         }
     }
 }
@@ -0,0 +1,249 @@
+//! Digests of a file under the hash algorithms different platforms use
+
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+/// A hash algorithm files can be identified by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    /// BLAKE3, the algorithm manifests are built around
+    Blake3,
+    /// SHA-1, published by Modrinth and CurseForge
+    Sha1,
+    /// SHA-256, used by packwiz and GitHub
+    Sha256,
+    /// SHA-512, published by Modrinth
+    Sha512,
+    /// MD5, published by CurseForge
+    Md5,
+}
+
+impl HashAlgorithm {
+    /// Every algorithm, strongest first
+    pub const ALL: [Self; 5] = [
+        Self::Blake3,
+        Self::Sha512,
+        Self::Sha256,
+        Self::Sha1,
+        Self::Md5,
+    ];
+
+    /// Returns the lowercase name of the algorithm, as used in manifests and by most APIs
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Blake3 => "blake3",
+            Self::Sha1 => "sha1",
+            Self::Sha256 => "sha256",
+            Self::Sha512 => "sha512",
+            Self::Md5 => "md5",
+        }
+    }
+
+    /// Looks up an algorithm by name, ignoring case and dashes (`SHA-256` and `sha256` are the
+    /// same)
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.replace('-', "").to_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.name() == name)
+    }
+}
+
+impl Display for HashAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Serde helpers for optional digests stored as hex strings
+mod hex_option {
+    use std::fmt::Display;
+
+    use hex::FromHex;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    /// Serializes a present digest as hex
+    #[allow(clippy::ref_option)]
+    pub(super) fn serialize<S: Serializer, T: AsRef<[u8]>>(
+        hash: &Option<T>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match hash {
+            Some(hash) => hex::serde::serialize(hash, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    /// Deserializes an optional hex digest
+    pub(super) fn deserialize<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: FromHex,
+        T::Error: Display,
+    {
+        Option::<String>::deserialize(deserializer)?
+            .map(|hex| T::from_hex(hex).map_err(D::Error::custom))
+            .transpose()
+    }
+}
+
+/// The digests of a file, as far as they are known
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Serialize, Deserialize)]
+pub struct Hashes {
+    /// The BLAKE3 digest
+    #[serde(default, with = "hex_option", skip_serializing_if = "Option::is_none")]
+    pub blake3: Option<[u8; 32]>,
+    /// The SHA-1 digest
+    #[serde(default, with = "hex_option", skip_serializing_if = "Option::is_none")]
+    pub sha1: Option<[u8; 20]>,
+    /// The SHA-256 digest
+    #[serde(default, with = "hex_option", skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<[u8; 32]>,
+    /// The SHA-512 digest
+    #[serde(default, with = "hex_option", skip_serializing_if = "Option::is_none")]
+    pub sha512: Option<[u8; 64]>,
+    /// The MD5 digest
+    #[serde(default, with = "hex_option", skip_serializing_if = "Option::is_none")]
+    pub md5: Option<[u8; 16]>,
+}
+
+impl Hashes {
+    /// Creates hashes with only a BLAKE3 digest
+    pub fn blake3(blake3: [u8; 32]) -> Self {
+        Self {
+            blake3: Some(blake3),
+            ..Self::default()
+        }
+    }
+
+    /// Returns true if no digests are known
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Returns the digest under an algorithm, if known
+    pub fn get(&self, algorithm: HashAlgorithm) -> Option<&[u8]> {
+        match algorithm {
+            HashAlgorithm::Blake3 => self.blake3.as_ref().map(|hash| &hash[..]),
+            HashAlgorithm::Sha1 => self.sha1.as_ref().map(|hash| &hash[..]),
+            HashAlgorithm::Sha256 => self.sha256.as_ref().map(|hash| &hash[..]),
+            HashAlgorithm::Sha512 => self.sha512.as_ref().map(|hash| &hash[..]),
+            HashAlgorithm::Md5 => self.md5.as_ref().map(|hash| &hash[..]),
+        }
+    }
+
+    /// Returns the digest under an algorithm as hex, if known
+    pub fn hex(&self, algorithm: HashAlgorithm) -> Option<String> {
+        self.get(algorithm).map(hex::encode)
+    }
+
+    /// Sets the digest under an algorithm, returning false if it is the wrong length
+    pub fn set(&mut self, algorithm: HashAlgorithm, digest: &[u8]) -> bool {
+        /// Copies the digest into a slot of the right size
+        fn fill<const N: usize>(slot: &mut Option<[u8; N]>, digest: &[u8]) -> bool {
+            match digest.try_into() {
+                Ok(digest) => {
+                    *slot = Some(digest);
+                    true
+                }
+                Err(_) => false,
+            }
+        }
+        match algorithm {
+            HashAlgorithm::Blake3 => fill(&mut self.blake3, digest),
+            HashAlgorithm::Sha1 => fill(&mut self.sha1, digest),
+            HashAlgorithm::Sha256 => fill(&mut self.sha256, digest),
+            HashAlgorithm::Sha512 => fill(&mut self.sha512, digest),
+            HashAlgorithm::Md5 => fill(&mut self.md5, digest),
+        }
+    }
+
+    /// Collects digests listed as algorithm names and hex, such as the `hashes` of a Modrinth
+    /// file, skipping unknown algorithms and malformed digests
+    pub fn from_hex<'a>(digests: impl IntoIterator<Item = (&'a str, &'a str)>) -> Self {
+        let mut hashes = Self::default();
+        for (name, digest) in digests {
+            if let (Some(algorithm), Ok(digest)) =
+                (HashAlgorithm::from_name(name), hex::decode(digest))
+            {
+                hashes.set(algorithm, &digest);
+            }
+        }
+        hashes
+    }
+
+    /// Returns the algorithms with known digests, strongest first
+    pub fn algorithms(&self) -> impl Iterator<Item = HashAlgorithm> + '_ {
+        HashAlgorithm::ALL
+            .into_iter()
+            .filter(|&algorithm| self.get(algorithm).is_some())
+    }
+
+    /// Returns the first known digest among the algorithms a format accepts, in the format's
+    /// order of preference
+    pub fn preferred(&self, accepted: &[HashAlgorithm]) -> Option<(HashAlgorithm, &[u8])> {
+        accepted
+            .iter()
+            .find_map(|&algorithm| Some((algorithm, self.get(algorithm)?)))
+    }
+
+    /// Returns the first algorithm, strongest first, that both sets of hashes know but disagree
+    /// on
+    pub fn mismatch(&self, other: &Hashes) -> Option<HashAlgorithm> {
+        self.algorithms().find(|&algorithm| {
+            other
+                .get(algorithm)
+                .is_some_and(|digest| Some(digest) != self.get(algorithm))
+        })
+    }
+
+    /// Fills in digests from `other` that aren't known yet, keeping the ones that are
+    pub fn merge(&mut self, other: &Hashes) {
+        for algorithm in HashAlgorithm::ALL {
+            if let (None, Some(digest)) = (self.get(algorithm), other.get(algorithm)) {
+                self.set(algorithm, digest);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    // Digests round trip through hex, and are compared per algorithm
+    #[test]
+    fn hashes() {
+        let hashes = Hashes::from_hex([
+            ("sha1", "aa".repeat(20).as_str()),
+            ("SHA-512", "bb".repeat(64).as_str()),
+            ("md5", "too short"),
+            ("crc32", "00000000"),
+        ]);
+        assert_eq!(hashes.sha1, Some([0xaa; 20]));
+        assert_eq!(hashes.sha512, Some([0xbb; 64]));
+        assert_eq!(hashes.md5, None);
+        assert_eq!(
+            hashes.algorithms().collect::<Vec<_>>(),
+            [HashAlgorithm::Sha512, HashAlgorithm::Sha1]
+        );
+        let json = serde_json::to_value(&hashes).unwrap();
+        assert_eq!(json["sha1"], "aa".repeat(20));
+        assert!(json.get("blake3").is_none());
+        assert_eq!(serde_json::from_value::<Hashes>(json).unwrap(), hashes);
+
+        let preferred = hashes.preferred(&[HashAlgorithm::Sha256, HashAlgorithm::Sha1]);
+        assert_eq!(preferred, Some((HashAlgorithm::Sha1, &[0xaa; 20][..])));
+        assert_eq!(hashes.preferred(&[HashAlgorithm::Md5]), None);
+
+        let mut other = Hashes::blake3([1; 32]);
+        assert_eq!(hashes.mismatch(&other), None);
+        other.sha1 = Some([0; 20]);
+        assert_eq!(hashes.mismatch(&other), Some(HashAlgorithm::Sha1));
+        other.merge(&hashes);
+        assert_eq!(other.sha1, Some([0; 20]));
+        assert_eq!(other.sha512, Some([0xbb; 64]));
+    }
+}
@@ -0,0 +1,422 @@
+//! Type wrapper for SPDX license expressions
+
+use std::{fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use snafu::{ensure, OptionExt, Snafu};
+use tracing::instrument;
+
+/// A parsed node of a license expression
+#[derive(PartialEq, Eq, Debug, Clone, Hash)]
+enum Expression {
+    /// A single license, optionally with the `+` operator and an exception
+    License {
+        /// The license identifier (`MIT`, `LicenseRef-custom`, ...)
+        id: String,
+        /// Whether the `+` ("or any later version") operator was applied
+        or_later: bool,
+        /// The exception applied with `WITH`, if any
+        exception: Option<String>,
+    },
+    /// Both sides apply
+    And(Box<Expression>, Box<Expression>),
+    /// Either side may be chosen
+    Or(Box<Expression>, Box<Expression>),
+}
+
+impl Expression {
+    /// Writes the expression, only adding the parentheses needed to preserve its meaning
+    fn write(&self, f: &mut std::fmt::Formatter<'_>, parent_is_and: bool) -> std::fmt::Result {
+        match self {
+            Expression::License {
+                id,
+                or_later,
+                exception,
+            } => {
+                write!(f, "{id}")?;
+                if *or_later {
+                    write!(f, "+")?;
+                }
+                if let Some(exception) = exception {
+                    write!(f, " WITH {exception}")?;
+                }
+                Ok(())
+            }
+            Expression::And(left, right) => {
+                left.write(f, true)?;
+                write!(f, " AND ")?;
+                right.write(f, true)
+            }
+            Expression::Or(left, right) => {
+                // AND binds tighter than OR, so an OR under an AND needs grouping
+                if parent_is_and {
+                    write!(f, "(")?;
+                }
+                left.write(f, false)?;
+                write!(f, " OR ")?;
+                right.write(f, false)?;
+                if parent_is_and {
+                    write!(f, ")")?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Collects the license identifiers in this expression, in order of appearance
+    fn collect_ids<'a>(&'a self, ids: &mut Vec<&'a str>) {
+        match self {
+            Expression::License { id, .. } => ids.push(id),
+            Expression::And(left, right) | Expression::Or(left, right) => {
+                left.collect_ids(ids);
+                right.collect_ids(ids);
+            }
+        }
+    }
+}
+
+/// A validated [SPDX license expression](https://spdx.github.io/spdx-spec/v2.3/SPDX-license-expressions/)
+///
+/// The expression is checked for well-formedness (identifiers, `+`, `WITH`, `AND`, `OR`, and
+/// parentheses) but identifiers are not checked against the SPDX license list, so custom
+/// `LicenseRef-` identifiers and licenses newer than this library work. It serializes as its
+/// canonical string form, e.g. `MIT OR Apache-2.0`.
+#[derive(PartialEq, Eq, Debug, Clone, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct License {
+    /// The parsed expression
+    expression: Expression,
+}
+
+impl License {
+    /// Parses and validates a license expression
+    #[instrument(skip(from), fields(raw = from.as_ref()), err)]
+    pub fn new(from: impl AsRef<str>) -> Result<License, LicenseError> {
+        let tokens = tokenize(from.as_ref())?;
+        ensure!(!tokens.is_empty(), EmptySnafu);
+        let mut parser = Parser {
+            tokens: &tokens,
+            position: 0,
+        };
+        let expression = parser.or()?;
+        if let Some(token) = parser.peek() {
+            return UnexpectedTokenSnafu {
+                token: token.to_string(),
+            }
+            .fail();
+        }
+        Ok(Self { expression })
+    }
+
+    /// Returns the license identifiers referenced by this expression, in order of appearance
+    pub fn identifiers(&self) -> Vec<&str> {
+        let mut ids = Vec::new();
+        self.expression.collect_ids(&mut ids);
+        ids
+    }
+
+    /// Returns true if this is a single license, with no `AND` or `OR`
+    pub fn is_simple(&self) -> bool {
+        matches!(self.expression, Expression::License { .. })
+    }
+}
+
+impl Display for License {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.expression.write(f, false)
+    }
+}
+
+impl FromStr for License {
+    type Err = LicenseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+impl TryFrom<String> for License {
+    type Error = LicenseError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::new(value)
+    }
+}
+
+impl From<License> for String {
+    fn from(license: License) -> Self {
+        license.to_string()
+    }
+}
+
+/// A lexical token of a license expression
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+enum Token<'a> {
+    /// An identifier
+    Id(&'a str),
+    /// `+`
+    Plus,
+    /// `(`
+    Open,
+    /// `)`
+    Close,
+    /// `AND`
+    And,
+    /// `OR`
+    Or,
+    /// `WITH`
+    With,
+}
+
+impl Display for Token<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Id(id) => write!(f, "{id}"),
+            Token::Plus => write!(f, "+"),
+            Token::Open => write!(f, "("),
+            Token::Close => write!(f, ")"),
+            Token::And => write!(f, "AND"),
+            Token::Or => write!(f, "OR"),
+            Token::With => write!(f, "WITH"),
+        }
+    }
+}
+
+/// Splits an expression into tokens
+fn tokenize(raw: &str) -> Result<Vec<Token<'_>>, LicenseError> {
+    let mut tokens = Vec::new();
+    let mut rest = raw;
+    while let Some(c) = rest.chars().next() {
+        match c {
+            c if c.is_whitespace() => rest = &rest[c.len_utf8()..],
+            '(' | ')' | '+' => {
+                tokens.push(match c {
+                    '(' => Token::Open,
+                    ')' => Token::Close,
+                    _ => Token::Plus,
+                });
+                rest = &rest[1..];
+            }
+            _ => {
+                let end = rest
+                    .find(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | '+'))
+                    .unwrap_or(rest.len());
+                let word = &rest[..end];
+                rest = &rest[end..];
+                tokens.push(match word {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "WITH" => Token::With,
+                    _ => {
+                        ensure!(
+                            valid_id(word),
+                            InvalidIdentifierSnafu {
+                                identifier: word.to_string()
+                            }
+                        );
+                        Token::Id(word)
+                    }
+                });
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Checks an identifier against the SPDX `idstring` production, allowing the `DocumentRef-x:`
+/// prefix on license references
+fn valid_id(word: &str) -> bool {
+    let is_idstring = |s: &str| {
+        !s.is_empty()
+            && s.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+    };
+    match word.split_once(':') {
+        Some((document, license)) => {
+            document.starts_with("DocumentRef-")
+                && is_idstring(document)
+                && license.starts_with("LicenseRef-")
+                && is_idstring(license)
+        }
+        None => is_idstring(word),
+    }
+}
+
+/// Recursive descent parser over a token stream
+struct Parser<'a, 'b> {
+    /// The tokens being parsed
+    tokens: &'b [Token<'a>],
+    /// Index of the next token
+    position: usize,
+}
+
+impl<'a> Parser<'a, '_> {
+    /// Returns the next token without consuming it
+    fn peek(&self) -> Option<Token<'a>> {
+        self.tokens.get(self.position).copied()
+    }
+
+    /// Consumes and returns the next token
+    fn next(&mut self) -> Result<Token<'a>, LicenseError> {
+        let token = self.peek().context(UnexpectedEndSnafu)?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    /// `or := and ("OR" and)*`
+    fn or(&mut self) -> Result<Expression, LicenseError> {
+        let mut expression = self.and()?;
+        while self.peek() == Some(Token::Or) {
+            self.position += 1;
+            expression = Expression::Or(Box::new(expression), Box::new(self.and()?));
+        }
+        Ok(expression)
+    }
+
+    /// `and := simple ("AND" simple)*`
+    fn and(&mut self) -> Result<Expression, LicenseError> {
+        let mut expression = self.simple()?;
+        while self.peek() == Some(Token::And) {
+            self.position += 1;
+            expression = Expression::And(Box::new(expression), Box::new(self.simple()?));
+        }
+        Ok(expression)
+    }
+
+    /// `simple := "(" or ")" | id ["+"] ["WITH" id]`
+    fn simple(&mut self) -> Result<Expression, LicenseError> {
+        match self.next()? {
+            Token::Open => {
+                let expression = self.or()?;
+                match self.next()? {
+                    Token::Close => Ok(expression),
+                    token => UnexpectedTokenSnafu {
+                        token: token.to_string(),
+                    }
+                    .fail(),
+                }
+            }
+            Token::Id(id) => {
+                let or_later = self.peek() == Some(Token::Plus);
+                if or_later {
+                    self.position += 1;
+                }
+                let exception = if self.peek() == Some(Token::With) {
+                    self.position += 1;
+                    match self.next()? {
+                        Token::Id(exception) => Some(exception.to_string()),
+                        token => {
+                            return UnexpectedTokenSnafu {
+                                token: token.to_string(),
+                            }
+                            .fail()
+                        }
+                    }
+                } else {
+                    None
+                };
+                Ok(Expression::License {
+                    id: id.to_string(),
+                    or_later,
+                    exception,
+                })
+            }
+            token => UnexpectedTokenSnafu {
+                token: token.to_string(),
+            }
+            .fail(),
+        }
+    }
+}
+
+/// Error that occurs while parsing a license expression
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum LicenseError {
+    /// The expression was empty
+    #[snafu(display("License expression is empty"))]
+    Empty,
+    /// The expression contained an invalid license identifier
+    #[snafu(display("Invalid license identifier: {}", identifier))]
+    InvalidIdentifier {
+        /// The offending identifier
+        identifier: String,
+    },
+    /// A token appeared where it isn't allowed
+    #[snafu(display("Unexpected token in license expression: {}", token))]
+    UnexpectedToken {
+        /// The offending token
+        token: String,
+    },
+    /// The expression ended early
+    #[snafu(display("License expression ended unexpectedly"))]
+    UnexpectedEnd,
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    // Valid expressions parse, and display in their canonical form
+    #[test]
+    fn canonical_form() {
+        let pairs = [
+            ("MIT", "MIT"),
+            ("  MIT   OR Apache-2.0 ", "MIT OR Apache-2.0"),
+            (
+                "GPL-2.0-or-later WITH Classpath-exception-2.0",
+                "GPL-2.0-or-later WITH Classpath-exception-2.0",
+            ),
+            ("LGPL-2.1+", "LGPL-2.1+"),
+            ("(MIT)", "MIT"),
+            (
+                "(MIT OR CC0-1.0) AND LicenseRef-assets",
+                "(MIT OR CC0-1.0) AND LicenseRef-assets",
+            ),
+            ("MIT OR (CC0-1.0 AND Zlib)", "MIT OR CC0-1.0 AND Zlib"),
+            (
+                "DocumentRef-pack:LicenseRef-art",
+                "DocumentRef-pack:LicenseRef-art",
+            ),
+        ];
+        for (raw, canonical) in pairs {
+            let license = License::new(raw).unwrap();
+            assert_eq!(license.to_string(), canonical);
+            // The canonical form must mean the same thing
+            assert_eq!(License::new(canonical).unwrap(), license);
+        }
+    }
+
+    // Malformed expressions are rejected
+    #[test]
+    fn invalid() {
+        for raw in [
+            "",
+            "   ",
+            "MIT OR",
+            "AND MIT",
+            "(MIT",
+            "MIT)",
+            "MIT WITH",
+            "MIT Apache-2.0",
+            "MIT/X11",
+            "mit or apache",
+            "Foo:LicenseRef-x",
+        ] {
+            assert!(License::new(raw).is_err(), "{raw:?} should be invalid");
+        }
+    }
+
+    // Serialization goes through the string form
+    #[test]
+    fn serde() {
+        let license: License = serde_json::from_str("\"MPL-2.0 AND (MIT OR Unlicense)\"").unwrap();
+        assert_eq!(license.identifiers(), ["MPL-2.0", "MIT", "Unlicense"]);
+        assert!(!license.is_simple());
+        assert_eq!(
+            serde_json::to_string(&license).unwrap(),
+            "\"MPL-2.0 AND (MIT OR Unlicense)\""
+        );
+        assert!(serde_json::from_str::<License>("\"MIT OR\"").is_err());
+    }
+}
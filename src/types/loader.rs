@@ -40,6 +40,17 @@ impl Loader {
         }
     }
 
+    /// Returns the lowercase names of the loaders whose mods this loader can run, its own first
+    ///
+    /// Quilt can usually run Fabric mods, but not the other way around.
+    pub fn compatible_loaders(&self) -> &'static [&'static str] {
+        match self {
+            Loader::Quilt(_) => &["quilt", "fabric"],
+            Loader::Fabric(_) => &["fabric"],
+            Loader::Forge(_) => &["forge"],
+        }
+    }
+
     /// Returns the version of this loader
     pub fn version(&self) -> &Version {
         match self {
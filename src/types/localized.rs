@@ -0,0 +1,200 @@
+//! Type wrapper for strings with translations
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use snafu::{ensure, Snafu};
+
+/// A string that is either plain text, or a set of translations keyed by
+/// [BCP-47](https://www.rfc-editor.org/info/bcp47) language tag
+///
+/// Serializes as either a plain string or a map, e.g. `{"en": "Tech pack", "de": "Technikpaket"}`
+#[derive(PartialEq, Eq, Debug, Clone, Hash, Serialize, Deserialize)]
+#[serde(try_from = "RawLocalized", into = "RawLocalized")]
+pub struct LocalizedString {
+    /// The translations, or a single entry under [`UNTRANSLATED`] for plain text
+    translations: BTreeMap<String, String>,
+}
+
+/// Key used for plain, untranslated text
+const UNTRANSLATED: &str = "";
+
+/// The language used when no translation matches, if the string has it
+const FALLBACK_LANGUAGE: &str = "en";
+
+impl LocalizedString {
+    /// Creates a string with translations
+    ///
+    /// # Errors
+    ///
+    /// Fails if there are no translations or a key isn't a well-formed language tag
+    pub fn new(
+        translations: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self, LocalizedError> {
+        let translations: BTreeMap<_, _> = translations.into_iter().collect();
+        ensure!(!translations.is_empty(), EmptySnafu);
+        if let Some(tag) = translations.keys().find(|tag| !valid_tag(tag)) {
+            return InvalidTagSnafu { tag: tag.clone() }.fail();
+        }
+        Ok(Self { translations })
+    }
+
+    /// Returns the text for a language, falling back to less specific tags (`zh-Hant-TW`, then
+    /// `zh-Hant`, then `zh`), then English, then the first translation
+    ///
+    /// `None` asks for the default text.
+    pub fn get(&self, lang: Option<&str>) -> &str {
+        let mut lang = lang.unwrap_or(FALLBACK_LANGUAGE);
+        loop {
+            if let Some(text) = self.find(lang) {
+                return text;
+            }
+            match lang.rfind('-') {
+                Some(index) => lang = &lang[..index],
+                None => break,
+            }
+        }
+        self.find(FALLBACK_LANGUAGE)
+            .or_else(|| self.translations.values().next().map(String::as_str))
+            .unwrap_or_default()
+    }
+
+    /// Returns the exact translation for a language tag, ignoring case
+    fn find(&self, lang: &str) -> Option<&str> {
+        self.translations
+            .iter()
+            .find(|(tag, _)| tag.eq_ignore_ascii_case(lang))
+            .map(|(_, text)| text.as_str())
+    }
+
+    /// Returns true if this is plain text with no translations
+    pub fn is_plain(&self) -> bool {
+        self.translations.contains_key(UNTRANSLATED)
+    }
+
+    /// Returns the language tags this string is translated into
+    pub fn languages(&self) -> impl Iterator<Item = &str> {
+        self.translations
+            .keys()
+            .map(String::as_str)
+            .filter(|tag| *tag != UNTRANSLATED)
+    }
+}
+
+impl From<String> for LocalizedString {
+    fn from(text: String) -> Self {
+        Self {
+            translations: [(UNTRANSLATED.to_string(), text)].into_iter().collect(),
+        }
+    }
+}
+
+impl From<&str> for LocalizedString {
+    fn from(text: &str) -> Self {
+        Self::from(text.to_string())
+    }
+}
+
+/// Checks the shape of a language tag: alphanumeric subtags of 1 to 8 characters separated by
+/// `-`, starting with an alphabetic language subtag
+fn valid_tag(tag: &str) -> bool {
+    let mut subtags = tag.split('-');
+    let language = subtags.next().unwrap_or_default();
+    (2..=8).contains(&language.len())
+        && language.chars().all(|c| c.is_ascii_alphabetic())
+        && subtags.all(|subtag| {
+            (1..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric())
+        })
+}
+
+/// The serialized form of a [`LocalizedString`]
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum RawLocalized {
+    /// Plain text
+    Plain(String),
+    /// Translations
+    Translations(BTreeMap<String, String>),
+}
+
+impl TryFrom<RawLocalized> for LocalizedString {
+    type Error = LocalizedError;
+
+    fn try_from(value: RawLocalized) -> Result<Self, Self::Error> {
+        match value {
+            RawLocalized::Plain(text) => Ok(Self::from(text)),
+            RawLocalized::Translations(translations) => Self::new(translations),
+        }
+    }
+}
+
+impl From<LocalizedString> for RawLocalized {
+    fn from(value: LocalizedString) -> Self {
+        let mut translations = value.translations;
+        match translations.remove(UNTRANSLATED) {
+            Some(text) => RawLocalized::Plain(text),
+            None => RawLocalized::Translations(translations),
+        }
+    }
+}
+
+/// Error that occurs while building a [`LocalizedString`]
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum LocalizedError {
+    /// There were no translations
+    #[snafu(display("Translated string has no translations"))]
+    Empty,
+    /// A key wasn't a language tag
+    #[snafu(display("Invalid language tag: {}", tag))]
+    InvalidTag {
+        /// The offending tag
+        tag: String,
+    },
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    // Lookups fall back through less specific tags, then English, then anything
+    #[test]
+    fn fallback() {
+        let string: LocalizedString = serde_json::from_str(
+            r#"{"en": "Tech pack", "de": "Technikpaket", "zh-Hant": "科技包"}"#,
+        )
+        .unwrap();
+        assert_eq!(string.get(Some("de")), "Technikpaket");
+        assert_eq!(string.get(Some("DE-at")), "Technikpaket");
+        assert_eq!(string.get(Some("zh-Hant-TW")), "科技包");
+        assert_eq!(string.get(Some("zh")), "Tech pack");
+        assert_eq!(string.get(None), "Tech pack");
+        let german =
+            LocalizedString::new([("de".to_string(), "Technikpaket".to_string())]).unwrap();
+        assert_eq!(german.get(Some("fr")), "Technikpaket");
+        let plain = LocalizedString::from("Tech pack");
+        assert_eq!(plain.get(Some("de")), "Tech pack");
+        assert_eq!(plain.languages().count(), 0);
+    }
+
+    // Both forms round trip, and bad tags are rejected
+    #[test]
+    fn serde() {
+        for json in [
+            r#""Tech pack""#,
+            r#"{"de":"Technikpaket","en":"Tech pack"}"#,
+        ] {
+            let string: LocalizedString = serde_json::from_str(json).unwrap();
+            assert_eq!(serde_json::to_string(&string).unwrap(), json);
+        }
+        for json in [
+            "{}",
+            r#"{"": "x"}"#,
+            r#"{"e": "x"}"#,
+            r#"{"en-": "x"}"#,
+            r#"{"en_US": "x"}"#,
+        ] {
+            assert!(serde_json::from_str::<LocalizedString>(json).is_err());
+        }
+    }
+}
@@ -1,22 +1,137 @@
 //! Type wrapper for the minecraft version scheme
 
-use std::{cmp::Ordering, fmt::Display, num::ParseIntError};
+use std::{cmp::Ordering, fmt::Display, num::ParseIntError, sync::LazyLock};
 
-use once_cell::sync::Lazy;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use snafu::{ResultExt, Snafu};
 use tracing::{debug, instrument, trace};
 
+/// The week each release came out, as `<release> <YY>w<WW>`, oldest first
+///
+/// This is how snapshots are placed among releases, and is refreshed from Mojang's version
+/// manifest as the file describes
+const RELEASE_WEEKS: &str = include_str!("minecraft/releases.txt");
+
+/// The releases in [`RELEASE_WEEKS`], as `((year, week), release)`
+static RELEASES: LazyLock<Vec<((u16, u16), Minecraft)>> = LazyLock::new(|| {
+    RELEASE_WEEKS
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let (release, week) = line.split_once(' ').expect("Releases have weeks");
+            let (year, week) = week.split_once('w').expect("Release weeks are YYwWW");
+            let year = year.parse().expect("Release years are numbers");
+            let week = week.parse().expect("Release weeks are numbers");
+            let release = Minecraft::new(release).expect("Releases are valid versions");
+            ((year, week), release)
+        })
+        .collect()
+});
+
+/// The components of a release-track version, in the order they compare by
+///
+/// These are the release's major, minor, and patch versions, followed by the stage on the way to
+/// that release as `(stage, number)`
+type ReleaseKey = (u16, u16, Option<u16>, (u8, u16));
+
+/// The components of a classic, alpha, or beta version, in the order they compare by
+///
+/// These are its major, minor, and patch versions and the letter after them, followed by its
+/// revision and the letter after that
+type LegacyKey = (
+    u16,
+    u16,
+    Option<u16>,
+    Option<char>,
+    Option<u16>,
+    Option<char>,
+);
+
 /// A decoded Minecraft version
 ///
-/// This currently treats a snapshot version as greater than any release version
+/// Classic versions sort before alpha versions, which sort before beta versions, which sort
+/// before every release. Pre-releases and release candidates sort before the release they lead
+/// up to, pre-releases first, and snapshots before those, placed by the week they came out.
+/// Snapshots newer than every release ffpack knows of sort after all of them
 ///
 ///
 /// TODO: Document
 #[derive(PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Hash)]
 #[serde(tag = "type")]
 pub enum Minecraft {
+    /// Classic version of the game, from before alpha (`cx.y.z_NN`, such as `c0.0.13a_03` or
+    /// `c0.30_01c`)
+    OldClassic {
+        /// Major version (`x` in `cx.y.z_NN`)
+        major: u16,
+        /// Minor version (`y` in `cx.y.z_NN`)
+        minor: u16,
+        /// Patch version (`z` in `cx.y.z_NN`)
+        ///
+        /// This value will be `None` if not specified
+        #[serde(skip_serializing_if = "Option::is_none")]
+        patch: Option<u16>,
+        /// The letter after the version numbers (`a` in `c0.0.13a_03`)
+        ///
+        /// This value will be `None` if not specified
+        #[serde(skip_serializing_if = "Option::is_none")]
+        suffix: Option<char>,
+        /// Revision of the version (`NN` in `cx.y.z_NN`), always written with two digits
+        ///
+        /// This value will be `None` if not specified
+        #[serde(skip_serializing_if = "Option::is_none")]
+        revision: Option<u16>,
+        /// The letter after the revision (`c` in `c0.30_01c`)
+        ///
+        /// This value will be `None` if not specified
+        #[serde(skip_serializing_if = "Option::is_none")]
+        revision_suffix: Option<char>,
+    },
+    /// Alpha version of the game, from before beta (`ax.y.z_NN`, such as `a1.2.6`)
+    OldAlpha {
+        /// Major version (`x` in `ax.y.z_NN`)
+        major: u16,
+        /// Minor version (`y` in `ax.y.z_NN`)
+        minor: u16,
+        /// Patch version (`z` in `ax.y.z_NN`)
+        ///
+        /// This value will be `None` if not specified
+        #[serde(skip_serializing_if = "Option::is_none")]
+        patch: Option<u16>,
+        /// The letter after the version numbers (`a` in `a1.2.2a`)
+        ///
+        /// This value will be `None` if not specified
+        #[serde(skip_serializing_if = "Option::is_none")]
+        suffix: Option<char>,
+        /// Revision of the version (`NN` in `ax.y.z_NN`), always written with two digits
+        ///
+        /// This value will be `None` if not specified
+        #[serde(skip_serializing_if = "Option::is_none")]
+        revision: Option<u16>,
+    },
+    /// Beta version of the game, from before 1.0 (`bx.y.z_NN`, such as `b1.7.3`)
+    OldBeta {
+        /// Major version (`x` in `bx.y.z_NN`)
+        major: u16,
+        /// Minor version (`y` in `bx.y.z_NN`)
+        minor: u16,
+        /// Patch version (`z` in `bx.y.z_NN`)
+        ///
+        /// This value will be `None` if not specified
+        #[serde(skip_serializing_if = "Option::is_none")]
+        patch: Option<u16>,
+        /// The letter after the version numbers (`b` in `b1.3b`)
+        ///
+        /// This value will be `None` if not specified
+        #[serde(skip_serializing_if = "Option::is_none")]
+        suffix: Option<char>,
+        /// Revision of the version (`NN` in `bx.y.z_NN`), always written with two digits
+        ///
+        /// This value will be `None` if not specified
+        #[serde(skip_serializing_if = "Option::is_none")]
+        revision: Option<u16>,
+    },
     /// Release version of the game
     ///
     /// This does not include snapshots post 1.0
@@ -31,6 +146,34 @@ pub enum Minecraft {
         #[serde(skip_serializing_if = "Option::is_none")]
         patch: Option<u16>,
     },
+    /// Pre-release of a release version (`x.y.z-preN`)
+    PreRelease {
+        /// Major version of the release (`x` in `x.y.z-preN`)
+        major: u16,
+        /// Minor version of the release (`y` in `x.y.z-preN`)
+        minor: u16,
+        /// Patch version of the release (`z` in `x.y.z-preN`)
+        ///
+        /// This value will be `None` if not specified
+        #[serde(skip_serializing_if = "Option::is_none")]
+        patch: Option<u16>,
+        /// Which pre-release this is (`N` in `x.y.z-preN`)
+        pre: u16,
+    },
+    /// Release candidate of a release version (`x.y.z-rcN`), which follows its pre-releases
+    ReleaseCandidate {
+        /// Major version of the release (`x` in `x.y.z-rcN`)
+        major: u16,
+        /// Minor version of the release (`y` in `x.y.z-rcN`)
+        minor: u16,
+        /// Patch version of the release (`z` in `x.y.z-rcN`)
+        ///
+        /// This value will be `None` if not specified
+        #[serde(skip_serializing_if = "Option::is_none")]
+        patch: Option<u16>,
+        /// Which release candidate this is (`N` in `x.y.z-rcN`)
+        rc: u16,
+    },
     /// Snapshot version of the game after the release
     Snapshot {
         /// The year component of the snapshot (`AA` in `AAwBBx`)
@@ -52,12 +195,18 @@ impl Minecraft {
     #[instrument(skip(from), fields(raw = from.as_ref()), err)]
     pub fn new(from: impl AsRef<str>) -> Result<Minecraft, MinecraftVersionError> {
         // Build our regexes (lazily)
-        /// Regex for matching a release version (`x.y.z` or `x.y`)
-        static RELEASE_REGEX: Lazy<Regex> =
-            Lazy::new(|| Regex::new(r"^(\d+)\.(\d+)(?:\.(\d+))?$").unwrap());
+        /// Regex for matching a release version (`x.y.z` or `x.y`), optionally followed by a
+        /// pre-release or release candidate (`-preN` or `-rcN`)
+        static RELEASE_REGEX: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r"^(\d+)\.(\d+)(?:\.(\d+))?(?:-(pre|rc)(\d+))?$").unwrap());
         /// Regex for matching a snapshot version (`XXwYYZ`)
-        static SNAPSHOT_REGEX: Lazy<Regex> =
-            Lazy::new(|| Regex::new(r"^(\d+)w(\d+)(\w+)$").unwrap());
+        static SNAPSHOT_REGEX: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r"^(\d+)w(\d+)(\w+)$").unwrap());
+        /// Regex for matching a classic, alpha, or beta version (`cx.y.z_NN`, `ax.y.z_NN`, or
+        /// `bx.y.z_NN`, with the patch, revision, and a letter after either optional)
+        static LEGACY_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(r"^([abc])(\d+)\.(\d+)(?:\.(\d+))?([a-z])?(?:_(\d+)([a-z])?)?$").unwrap()
+        });
         debug!("Parsing Version");
         let from = from.as_ref();
         // Attempt to match a Release Version
@@ -80,11 +229,36 @@ impl Minecraft {
             } else {
                 None
             };
-            Ok(Self::Release {
-                major,
-                minor,
-                patch,
-            })
+            match captures.get(4).map(|stage| stage.as_str()) {
+                None => Ok(Self::Release {
+                    major,
+                    minor,
+                    patch,
+                }),
+                Some(stage) => {
+                    let number = captures
+                        .get(5)
+                        .unwrap()
+                        .as_str()
+                        .parse()
+                        .context(InvalidComponentSnafu)?;
+                    if stage == "pre" {
+                        Ok(Self::PreRelease {
+                            major,
+                            minor,
+                            patch,
+                            pre: number,
+                        })
+                    } else {
+                        Ok(Self::ReleaseCandidate {
+                            major,
+                            minor,
+                            patch,
+                            rc: number,
+                        })
+                    }
+                }
+            }
         } else if let Some(captures) = SNAPSHOT_REGEX.captures(from) {
             // Attempt to match a snapshot
             let year = captures
@@ -105,6 +279,72 @@ impl Minecraft {
                 week,
                 specifier,
             })
+        } else if let Some(captures) = LEGACY_REGEX.captures(from) {
+            // Attempt to match a classic, alpha, or beta version
+            trace!("Parsing a classic, alpha, or beta version");
+            let major = captures
+                .get(2)
+                .unwrap()
+                .as_str()
+                .parse()
+                .context(InvalidComponentSnafu)?;
+            let minor = captures
+                .get(3)
+                .unwrap()
+                .as_str()
+                .parse()
+                .context(InvalidComponentSnafu)?;
+            let patch = if let Some(patch_raw) = captures.get(4) {
+                Some(patch_raw.as_str().parse().context(InvalidComponentSnafu)?)
+            } else {
+                None
+            };
+            let letter = |index| {
+                captures
+                    .get(index)
+                    .and_then(|letter: regex::Match<'_>| letter.as_str().chars().next())
+            };
+            let suffix = letter(5);
+            let revision = if let Some(revision_raw) = captures.get(6) {
+                Some(
+                    revision_raw
+                        .as_str()
+                        .parse()
+                        .context(InvalidComponentSnafu)?,
+                )
+            } else {
+                None
+            };
+            let revision_suffix = letter(7);
+            match captures.get(1).unwrap().as_str() {
+                "c" => Ok(Self::OldClassic {
+                    major,
+                    minor,
+                    patch,
+                    suffix,
+                    revision,
+                    revision_suffix,
+                }),
+                // Only classic versions have a letter after their revision
+                _ if revision_suffix.is_some() => NoSupportedPatternSnafu {
+                    version: from.to_string(),
+                }
+                .fail(),
+                "a" => Ok(Self::OldAlpha {
+                    major,
+                    minor,
+                    patch,
+                    suffix,
+                    revision,
+                }),
+                _ => Ok(Self::OldBeta {
+                    major,
+                    minor,
+                    patch,
+                    suffix,
+                    revision,
+                }),
+            }
         } else {
             // No matching pattern
             NoSupportedPatternSnafu {
@@ -114,12 +354,155 @@ impl Minecraft {
         }
     }
 
+    /// Returns the `major.minor` release family of this version (`1.19` for `1.19.2`, or for
+    /// `1.19.1-pre2`)
+    ///
+    /// Snapshots don't belong to a family, even the one of the release they lead up to, as they
+    /// aren't compatible with its releases, and neither do classic, alpha, and beta versions, so
+    /// this returns `None` for them
+    pub fn family(&self) -> Option<Minecraft> {
+        let Some(Minecraft::Release { major, minor, .. }) = self.release() else {
+            return None;
+        };
+        Some(Minecraft::Release {
+            major,
+            minor,
+            patch: None,
+        })
+    }
+
+    /// Returns the release this version is, or that it is a pre-release or release candidate
+    /// of, or `None` for snapshots and classic, alpha, and beta versions
+    pub fn release(&self) -> Option<Minecraft> {
+        match *self {
+            Minecraft::Release {
+                major,
+                minor,
+                patch,
+            }
+            | Minecraft::PreRelease {
+                major,
+                minor,
+                patch,
+                ..
+            }
+            | Minecraft::ReleaseCandidate {
+                major,
+                minor,
+                patch,
+                ..
+            } => Some(Minecraft::Release {
+                major,
+                minor,
+                patch,
+            }),
+            Minecraft::OldClassic { .. }
+            | Minecraft::OldAlpha { .. }
+            | Minecraft::OldBeta { .. }
+            | Minecraft::Snapshot { .. } => None,
+        }
+    }
+
+    /// Returns the release this version leads up to, which for snapshots is the first release
+    /// from the week they came out or later
+    ///
+    /// This returns `None` for classic, alpha, and beta versions, and for snapshots newer than
+    /// every release ffpack knows of
+    pub fn target(&self) -> Option<Minecraft> {
+        let Minecraft::Snapshot { year, week, .. } = *self else {
+            return self.release();
+        };
+        let index = RELEASES.partition_point(|(released, _)| *released < (year, week));
+        RELEASES.get(index).map(|(_, release)| release.clone())
+    }
+
+    /// Returns the release components of the release this version leads up to, or `None` if it
+    /// leads up to none that is known
+    ///
+    /// Snapshots are stage 0, pre-releases are stage 1, release candidates are stage 2, and the
+    /// release itself is stage 3
+    fn release_key(&self) -> Option<ReleaseKey> {
+        let stage = match *self {
+            Minecraft::Snapshot { .. } => (0, 0),
+            Minecraft::PreRelease { pre, .. } => (1, pre),
+            Minecraft::ReleaseCandidate { rc, .. } => (2, rc),
+            Minecraft::Release { .. } => (3, 0),
+            Minecraft::OldClassic { .. }
+            | Minecraft::OldAlpha { .. }
+            | Minecraft::OldBeta { .. } => return None,
+        };
+        let Some(Minecraft::Release {
+            major,
+            minor,
+            patch,
+        }) = self.target()
+        else {
+            return None;
+        };
+        Some((major, minor, patch, stage))
+    }
+
+    /// Returns the components of this version if it is a snapshot, as `(year, week, specifier)`
+    fn snapshot_key(&self) -> Option<(u16, u16, &str)> {
+        match self {
+            Minecraft::Snapshot {
+                year,
+                week,
+                specifier,
+            } => Some((*year, *week, specifier)),
+            _ => None,
+        }
+    }
+
+    /// Returns the components of this version if it is a classic, alpha, or beta version
+    fn legacy_key(&self) -> Option<LegacyKey> {
+        match *self {
+            Minecraft::OldClassic {
+                major,
+                minor,
+                patch,
+                suffix,
+                revision,
+                revision_suffix,
+            } => Some((major, minor, patch, suffix, revision, revision_suffix)),
+            Minecraft::OldAlpha {
+                major,
+                minor,
+                patch,
+                suffix,
+                revision,
+            }
+            | Minecraft::OldBeta {
+                major,
+                minor,
+                patch,
+                suffix,
+                revision,
+            } => Some((major, minor, patch, suffix, revision, None)),
+            _ => None,
+        }
+    }
+
+    /// Returns true if both versions are releases in the same `major.minor` family
+    pub fn is_same_family(&self, other: &Minecraft) -> bool {
+        self.family()
+            .is_some_and(|family| other.family() == Some(family))
+    }
+
     /// Internal function used for simplifying ordering
     fn order_priority(&self) -> usize {
-        // This must always return values that are different for each version
+        // This must always return values that are different for each kind of version, with
+        // pre-releases, release candidates, and the snapshots of known releases the same kind as
+        // releases
         match self {
-            Minecraft::Release { .. } => 1,
-            Minecraft::Snapshot { .. } => 2,
+            Minecraft::OldClassic { .. } => 1,
+            Minecraft::OldAlpha { .. } => 2,
+            Minecraft::OldBeta { .. } => 3,
+            Minecraft::Snapshot { .. } if self.target().is_none() => 5,
+            Minecraft::Release { .. }
+            | Minecraft::PreRelease { .. }
+            | Minecraft::ReleaseCandidate { .. }
+            | Minecraft::Snapshot { .. } => 4,
         }
     }
 }
@@ -134,75 +517,19 @@ impl Ord for Minecraft {
     fn cmp(&self, other: &Self) -> Ordering {
         // Only attempt a comparison if the version is of the same order priority
         match self.order_priority().cmp(&other.order_priority()) {
-            // We know that the two values must be of the same "type", so we have to do a deep
-            // comparison
-            Ordering::Equal => match self {
-                Minecraft::Release {
-                    major: major_self,
-                    minor: minor_self,
-                    patch: patch_self,
-                } => {
-                    if let Minecraft::Release {
-                        major: major_other,
-                        minor: minor_other,
-                        patch: patch_other,
-                    } = other
-                    {
-                        // do a semver sytle comparison
-                        match (
-                            major_self.cmp(major_other),
-                            minor_self.cmp(minor_other),
-                            patch_self.cmp(patch_other),
-                        ) {
-                            // If the major versions are equal, and the minor versions are equal,
-                            // compare on the patch version
-                            (Ordering::Equal, Ordering::Equal, patch) => patch,
-                            // If the major versions are equal, but the minor versions aren't, then
-                            // compare on the minor version
-                            (Ordering::Equal, minor, _) => minor,
-                            // If the major version aren't equal, just compare on those
-                            (major, _, _) => major,
-                        }
-                    } else {
-                        // This is unreachable as `order_priority` _must_ return unique values for
-                        // each variant
-                        unreachable!()
-                    }
-                }
-                Minecraft::Snapshot {
-                    year: year_self,
-                    week: week_self,
-                    specifier: specifier_self,
-                } => {
-                    if let Minecraft::Snapshot {
-                        year: year_other,
-                        week: week_other,
-                        specifier: specifer_other,
-                    } = other
-                    {
-                        match (
-                            year_self.cmp(year_other),
-                            week_self.cmp(week_other),
-                            specifier_self.cmp(specifer_other),
-                        ) {
-                            // If the years are equal, and the weeks are equal, compare on the
-                            // specifier character/characters. These are in lexigraphical order,
-                            // hopefully
-                            (Ordering::Equal, Ordering::Equal, specifier) => specifier,
-                            // If the years are equal, but the weeks aren't, then compare on the
-                            // week
-                            (Ordering::Equal, week, _) => week,
-                            // If the years aren't equal, just compare on those
-                            (year, _, _) => year,
-                        }
-                    } else {
-                        // This is unreachable as `order_priority` _must_ return unique values for
-                        // each variant
-                        unreachable!()
-                    }
-                }
-            },
-            // If they aren't of the same type, we only need the shallow comparison provided by `order_priority`
+            // We know that the two values must be of the same kind, so we have to do a deep
+            // comparison on the components of that kind, which are `None` for both versions for
+            // every other kind. Tuples compare in order, each component only mattering when the
+            // ones before it are equal, which makes this a semver style comparison for releases.
+            // Snapshots leading up to the same release compare by when they came out, with the
+            // specifier character/characters in lexigraphical order, hopefully
+            Ordering::Equal => self
+                .legacy_key()
+                .cmp(&other.legacy_key())
+                .then_with(|| self.release_key().cmp(&other.release_key()))
+                .then_with(|| self.snapshot_key().cmp(&other.snapshot_key())),
+            // If they aren't of the same type, we only need the shallow comparison provided by
+            // `order_priority`
             x => x,
         }
     }
@@ -217,16 +544,56 @@ impl Display for Minecraft {
                 patch,
             } => {
                 if let Some(patch) = patch {
-                    write!(f, "{}.{}.{}", major, minor, patch)
+                    write!(f, "{major}.{minor}.{patch}")
                 } else {
-                    write!(f, "{}.{}", major, minor)
+                    write!(f, "{major}.{minor}")
+                }
+            }
+            Minecraft::OldClassic { .. }
+            | Minecraft::OldAlpha { .. }
+            | Minecraft::OldBeta { .. } => {
+                let prefix = match self {
+                    Minecraft::OldClassic { .. } => 'c',
+                    Minecraft::OldAlpha { .. } => 'a',
+                    _ => 'b',
+                };
+                let (major, minor, patch, suffix, revision, revision_suffix) = self
+                    .legacy_key()
+                    .expect("Classic, alpha, and beta versions have legacy keys");
+                write!(f, "{prefix}{major}.{minor}")?;
+                if let Some(patch) = patch {
+                    write!(f, ".{patch}")?;
                 }
+                if let Some(suffix) = suffix {
+                    write!(f, "{suffix}")?;
+                }
+                if let Some(revision) = revision {
+                    write!(f, "_{revision:02}")?;
+                }
+                if let Some(revision_suffix) = revision_suffix {
+                    write!(f, "{revision_suffix}")?;
+                }
+                Ok(())
+            }
+            Minecraft::PreRelease { pre, .. } => {
+                write!(
+                    f,
+                    "{}-pre{pre}",
+                    self.release().expect("Pre-releases have releases")
+                )
+            }
+            Minecraft::ReleaseCandidate { rc, .. } => {
+                write!(
+                    f,
+                    "{}-rc{rc}",
+                    self.release().expect("Release candidates have releases")
+                )
             }
             Minecraft::Snapshot {
                 year,
                 week,
                 specifier,
-            } => write!(f, "{}w{}{}", year, week, specifier),
+            } => write!(f, "{year}w{week:02}{specifier}"),
         }
     }
 }
@@ -285,6 +652,44 @@ mod unit_tests {
                     patch: None,
                 },
             ),
+            (
+                "b1.7.3",
+                Minecraft::OldBeta {
+                    major: 1,
+                    minor: 7,
+                    patch: Some(3),
+                    suffix: None,
+                    revision: None,
+                },
+            ),
+            (
+                "a1.2.6",
+                Minecraft::OldAlpha {
+                    major: 1,
+                    minor: 2,
+                    patch: Some(6),
+                    suffix: None,
+                    revision: None,
+                },
+            ),
+            (
+                "1.19.1-pre2",
+                Minecraft::PreRelease {
+                    major: 1,
+                    minor: 19,
+                    patch: Some(1),
+                    pre: 2,
+                },
+            ),
+            (
+                "1.19-rc1",
+                Minecraft::ReleaseCandidate {
+                    major: 1,
+                    minor: 19,
+                    patch: None,
+                    rc: 1,
+                },
+            ),
             (
                 "18w10d",
                 Minecraft::Snapshot {
@@ -298,20 +703,109 @@ mod unit_tests {
             match Minecraft::new(raw) {
                 Ok(parsed) => assert_eq!(version, parsed),
                 Err(e) => {
-                    println!("Failed to parse version: {}", raw);
-                    println!("Error: {:?}", e);
+                    println!("Failed to parse version: {raw}");
+                    println!("Error: {e:?}");
                     panic!("Test failed");
                 }
             }
         }
     }
 
+    // Classic, alpha, and beta versions can have letters after their numbers and revisions
+    #[test]
+    fn legacy() {
+        let pairs = vec![
+            (
+                "a1.2.2a",
+                Minecraft::OldAlpha {
+                    major: 1,
+                    minor: 2,
+                    patch: Some(2),
+                    suffix: Some('a'),
+                    revision: None,
+                },
+            ),
+            (
+                "b1.3b",
+                Minecraft::OldBeta {
+                    major: 1,
+                    minor: 3,
+                    patch: None,
+                    suffix: Some('b'),
+                    revision: None,
+                },
+            ),
+            (
+                "c0.0.13a_03",
+                Minecraft::OldClassic {
+                    major: 0,
+                    minor: 0,
+                    patch: Some(13),
+                    suffix: Some('a'),
+                    revision: Some(3),
+                    revision_suffix: None,
+                },
+            ),
+            (
+                "c0.30_01c",
+                Minecraft::OldClassic {
+                    major: 0,
+                    minor: 30,
+                    patch: None,
+                    suffix: None,
+                    revision: Some(1),
+                    revision_suffix: Some('c'),
+                },
+            ),
+        ];
+        for (raw, version) in pairs {
+            assert_eq!(Minecraft::new(raw).unwrap(), version, "{raw}");
+        }
+        assert!(Minecraft::new("a1.0_01c").is_err());
+    }
+
     // Test the ordering
     #[test]
     fn order() {
         // An ordered list of test version
         let versions: Vec<Minecraft> = vec![
-            "1.1", "1.6.2", "1.18", "1.18.1", "1.18.2", "1.19", "18w10d", "22w28a", "22w28b",
+            "c0.0.11a",
+            "c0.0.13a",
+            "c0.0.13a_03",
+            "c0.30_01c",
+            "a1.0.4",
+            "a1.2.2",
+            "a1.2.2a",
+            "a1.2.2b",
+            "a1.2.3_04",
+            "a1.2.6",
+            "b1.1_02",
+            "b1.3b",
+            "b1.7.3",
+            "b1.8.1",
+            "1.1",
+            "12w30e",
+            "1.3.1",
+            "1.6.2",
+            "18w10d",
+            "1.13",
+            "1.18",
+            "1.18.1",
+            "1.18.2",
+            "22w11a",
+            "1.19-pre1",
+            "1.19-pre5",
+            "1.19-rc1",
+            "1.19-rc2",
+            "1.19",
+            "22w28a",
+            "22w28b",
+            "1.19.1-pre2",
+            "1.19.1-rc1",
+            "1.19.1",
+            "1.20",
+            "99w01a",
+            "99w02a",
         ]
         .into_iter()
         .map(|x| Minecraft::new(x).unwrap())
@@ -328,12 +822,50 @@ mod unit_tests {
     #[test]
     fn display() {
         let versions_raw = vec![
-            "1.1", "1.6.2", "1.18", "1.18.1", "1.18.2", "1.19", "18w10d", "22w28a", "22w28b",
+            "c0.0.11a",
+            "c0.0.13a_03",
+            "c0.30_01c",
+            "a1.2.2a",
+            "b1.3b",
+            "a1.2.6",
+            "a1.2.3_04",
+            "b1.1_02",
+            "b1.7.3",
+            "1.1",
+            "1.6.2",
+            "1.18",
+            "1.18.1",
+            "1.18.2",
+            "1.19",
+            "1.19.1-pre2",
+            "1.19-rc1",
+            "18w10d",
+            "22w06a",
+            "22w28a",
+            "22w28b",
         ];
         for version_raw in versions_raw {
             let parsed = Minecraft::new(version_raw).unwrap();
-            let displayed = format!("{}", parsed);
+            let displayed = format!("{parsed}");
             assert_eq!(version_raw, &displayed);
         }
     }
+
+    // Releases group by major.minor, and snapshots stand alone, though they lead up to releases
+    #[test]
+    fn family() {
+        let version = |raw| Minecraft::new(raw).unwrap();
+        assert_eq!(version("1.19.2").family(), Some(version("1.19")));
+        assert_eq!(version("1.19").family(), Some(version("1.19")));
+        assert!(version("1.19").is_same_family(&version("1.19.4")));
+        assert!(!version("1.19.4").is_same_family(&version("1.20")));
+        assert_eq!(version("1.19.1-pre2").family(), Some(version("1.19")));
+        assert_eq!(version("1.19-rc1").release(), Some(version("1.19")));
+        assert_eq!(version("22w13a").family(), None);
+        assert_eq!(version("b1.7.3").family(), None);
+        assert_eq!(version("22w28a").target(), Some(version("1.19.1")));
+        assert_eq!(version("1.19-rc1").target(), Some(version("1.19")));
+        assert_eq!(version("99w01a").target(), None);
+        assert!(!version("22w13a").is_same_family(&version("22w13a")));
+    }
 }
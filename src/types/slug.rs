@@ -0,0 +1,202 @@
+//! Slugs naming a repository on a code forge, like `github:owner/project`
+
+use std::{fmt::Display, str::FromStr};
+
+use snafu::{ensure, OptionExt, Snafu};
+use url::Url;
+
+/// A code forge that slugs can point at
+#[derive(PartialEq, Eq, Debug, Clone, Hash)]
+pub enum Forge {
+    /// [GitHub](https://github.com), written `github:`
+    Github,
+    /// [GitLab](https://gitlab.com), written `gitlab:`
+    Gitlab,
+    /// [Codeberg](https://codeberg.org), written `codeberg:`
+    Codeberg,
+    /// A self-hosted Gitea or Forgejo instance, written `gitea:<host>:`
+    Gitea {
+        /// The instance's host name, like `git.example.org`
+        host: String,
+    },
+}
+
+impl Forge {
+    /// Returns the forge's website, which repositories are found under
+    ///
+    /// # Panics
+    ///
+    /// Panics if a Gitea host isn't a valid host name, which parsing a slug rules out
+    pub fn web_url(&self) -> Url {
+        let host = match self {
+            Forge::Github => "github.com",
+            Forge::Gitlab => "gitlab.com",
+            Forge::Codeberg => "codeberg.org",
+            Forge::Gitea { host } => host,
+        };
+        Url::parse(&format!("https://{host}/")).expect("Hosts are checked when parsing")
+    }
+}
+
+/// A repository on a code forge, as given in slug sources
+///
+/// Slugs are written `forge:owner/project`. GitLab projects can sit in nested groups, so their
+/// owner may contain slashes itself (`gitlab:group/subgroup/project`); self-hosted Gitea
+/// instances name their host between the forge and the path
+/// (`gitea:git.example.org:owner/project`).
+#[derive(PartialEq, Eq, Debug, Clone, Hash)]
+pub struct ForgeSlug {
+    /// The forge hosting the repository
+    pub forge: Forge,
+    /// The user, organization, or group owning the repository
+    pub owner: String,
+    /// The repository's name
+    pub project: String,
+}
+
+impl ForgeSlug {
+    /// Parses a slug
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the slug isn't of the form `forge:owner/project`, or names a forge
+    /// that isn't supported
+    pub fn new(slug: &str) -> Result<Self, SlugError> {
+        let (forge, rest) = slug.split_once(':').context(FormatSnafu { slug })?;
+        let (forge, path) = match forge {
+            "github" => (Forge::Github, rest),
+            "gitlab" => (Forge::Gitlab, rest),
+            "codeberg" => (Forge::Codeberg, rest),
+            "gitea" => {
+                let (host, path) = rest.split_once(':').context(FormatSnafu { slug })?;
+                let valid = !host.is_empty()
+                    && Url::parse(&format!("https://{host}/"))
+                        .is_ok_and(|url| url.host_str() == Some(host));
+                ensure!(valid, FormatSnafu { slug });
+                let host = host.to_string();
+                (Forge::Gitea { host }, path)
+            }
+            _ => return UnknownForgeSnafu { slug, forge }.fail(),
+        };
+        let (owner, project) = path.rsplit_once('/').context(FormatSnafu { slug })?;
+        let nested = forge == Forge::Gitlab;
+        let valid = !project.is_empty()
+            && !owner.is_empty()
+            && owner
+                .split('/')
+                .all(|part| !part.is_empty() && (nested || part == owner));
+        ensure!(valid, FormatSnafu { slug });
+        Ok(Self {
+            forge,
+            owner: owner.to_string(),
+            project: project.to_string(),
+        })
+    }
+
+    /// Returns the path of the repository on its forge, `owner/project`
+    pub fn path(&self) -> String {
+        format!("{}/{}", self.owner, self.project)
+    }
+
+    /// Returns the repository's page on its forge
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`Forge::web_url`]
+    pub fn web_url(&self) -> Url {
+        let mut url = self.forge.web_url();
+        url.path_segments_mut()
+            .expect("Forge urls can have paths")
+            .pop_if_empty()
+            .extend(self.owner.split('/'))
+            .push(&self.project);
+        url
+    }
+}
+
+impl Display for ForgeSlug {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.forge {
+            Forge::Github => write!(f, "github:")?,
+            Forge::Gitlab => write!(f, "gitlab:")?,
+            Forge::Codeberg => write!(f, "codeberg:")?,
+            Forge::Gitea { host } => write!(f, "gitea:{host}:")?,
+        }
+        write!(f, "{}/{}", self.owner, self.project)
+    }
+}
+
+impl FromStr for ForgeSlug {
+    type Err = SlugError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+/// Error that occurs while parsing a slug
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum SlugError {
+    /// The slug wasn't of the form `forge:owner/project`
+    #[snafu(display("Invalid slug: {}", slug))]
+    Format {
+        /// The slug
+        slug: String,
+    },
+    /// The slug named a forge that isn't supported
+    #[snafu(display("Unsupported forge {} in slug: {}", forge, slug))]
+    UnknownForge {
+        /// The slug
+        slug: String,
+        /// The forge it named
+        forge: String,
+    },
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    // Slugs parse into their parts, and display the way they were written
+    #[test]
+    fn parse() {
+        for slug in [
+            "github:o/r",
+            "gitlab:group/subgroup/r",
+            "codeberg:o/r",
+            "gitea:git.example.org:o/r",
+        ] {
+            assert_eq!(ForgeSlug::new(slug).unwrap().to_string(), slug);
+        }
+        let nested = ForgeSlug::new("gitlab:group/subgroup/r").unwrap();
+        assert_eq!(nested.owner, "group/subgroup");
+        assert_eq!(
+            nested.web_url().as_str(),
+            "https://gitlab.com/group/subgroup/r"
+        );
+        let gitea = ForgeSlug::new("gitea:git.example.org:o/r").unwrap();
+        assert_eq!(gitea.web_url().as_str(), "https://git.example.org/o/r");
+
+        for invalid in [
+            "o/r",
+            "github:o",
+            "github:o/",
+            "github:/r",
+            "github:a/b/c",
+            "gitlab:a//c",
+            "gitea:o/r",
+            "gitea::o/r",
+            "gitea:bad host:o/r",
+        ] {
+            assert!(
+                matches!(ForgeSlug::new(invalid), Err(SlugError::Format { .. })),
+                "{invalid} should be invalid"
+            );
+        }
+        assert!(matches!(
+            ForgeSlug::new("sourcehut:o/r"),
+            Err(SlugError::UnknownForge { .. })
+        ));
+    }
+}
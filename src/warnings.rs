@@ -0,0 +1,161 @@
+//! Warnings about manifests that load fine, but use forms the format is moving away from
+//!
+//! [`Pack::from_json_with_warnings`] returns these alongside the pack so that frontends can show
+//! them; [`Pack::from_json`] logs them instead.
+
+use std::fmt::Display;
+
+use relative_path::RelativePathBuf;
+use serde_json::Value;
+
+use crate::{
+    types::{ManagedFile, Metadata, Source, DISABLED_SUFFIX},
+    Pack,
+};
+
+/// Something in a manifest worth updating
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum LoadWarning {
+    /// A field that has been replaced by another was used
+    DeprecatedField {
+        /// The field that was used
+        field: &'static str,
+        /// The field to use instead
+        replacement: &'static str,
+    },
+    /// A file is disabled by the suffix in its path rather than its `enabled` flag
+    LegacyDisabledPath {
+        /// The file's path
+        path: RelativePathBuf,
+    },
+    /// Content from the example manifest is still in place
+    Placeholder {
+        /// Where the placeholder is
+        field: String,
+    },
+    /// A file's blake3 hash is all zeros, as in the example manifest, and can never match
+    PlaceholderHash {
+        /// The file's path
+        path: RelativePathBuf,
+    },
+}
+
+impl Display for LoadWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadWarning::DeprecatedField { field, replacement } => {
+                write!(f, "{field} is deprecated, use {replacement} instead")
+            }
+            LoadWarning::LegacyDisabledPath { path } => write!(
+                f,
+                "{path} is disabled through its path, set `enabled` to false instead"
+            ),
+            LoadWarning::Placeholder { field } => {
+                write!(f, "{field} still has the example manifest's content")
+            }
+            LoadWarning::PlaceholderHash { path } => {
+                write!(f, "{path} has a placeholder hash")
+            }
+        }
+    }
+}
+
+/// A placeholder warning for a field
+fn placeholder(field: impl Into<String>) -> LoadWarning {
+    LoadWarning::Placeholder {
+        field: field.into(),
+    }
+}
+
+/// Checks a loaded pack, and the raw manifest it came from, for things worth updating
+pub(crate) fn check(raw: &Value, pack: &Pack) -> Vec<LoadWarning> {
+    let mut warnings = Vec::new();
+    if raw["metadata"].get("author").is_some() {
+        warnings.push(LoadWarning::DeprecatedField {
+            field: "metadata.author",
+            replacement: "metadata.authors",
+        });
+    }
+
+    let example = Metadata::default();
+    let metadata = &pack.metadata;
+    if metadata.localized_name() == example.localized_name() {
+        warnings.push(placeholder("metadata.name"));
+    }
+    if metadata.description(None).is_some()
+        && metadata.description(None) == example.description(None)
+    {
+        warnings.push(placeholder("metadata.description"));
+    }
+    if metadata.authors().iter().any(|author| {
+        example
+            .authors()
+            .iter()
+            .any(|example| example.name == author.name)
+    }) {
+        warnings.push(placeholder("metadata.authors"));
+    }
+
+    let example = ManagedFile::default();
+    for file in &pack.managed_files {
+        if file.name.is_some() && file.name == example.name {
+            warnings.push(placeholder(format!("managed_files[{}].name", file.path)));
+        }
+        if file.source == example.source {
+            warnings.push(placeholder(format!("managed_files[{}].source", file.path)));
+        }
+        if let Source::Url { blake3, .. }
+        | Source::Path { blake3, .. }
+        | Source::Ipfs { blake3, .. } = &file.source
+        {
+            if blake3 == &[0; 32] {
+                warnings.push(LoadWarning::PlaceholderHash {
+                    path: file.path.clone(),
+                });
+            }
+        }
+        if file.path.as_str().ends_with(DISABLED_SUFFIX) {
+            warnings.push(LoadWarning::LegacyDisabledPath {
+                path: file.path.clone(),
+            });
+        }
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::*;
+
+    // The example manifest is flagged, and legacy forms are pointed at their replacements
+    #[test]
+    fn warnings() {
+        let json = serde_json::to_string(&Pack::default()).unwrap();
+        let (_, warnings) = Pack::from_json_with_warnings(&json).unwrap();
+        let placeholders = warnings
+            .iter()
+            .filter(|warning| matches!(warning, LoadWarning::Placeholder { .. }))
+            .count();
+        assert_eq!(placeholders, 5);
+        assert!(warnings.contains(&LoadWarning::PlaceholderHash {
+            path: RelativePathBuf::from("mods/MyAwesomeMod.jar")
+        }));
+
+        let mut value = serde_json::to_value(Pack::default()).unwrap();
+        value["metadata"]["name"] = "Real pack".into();
+        value["metadata"]["description"] = Value::Null;
+        value["metadata"]["author"] = "Someone".into();
+        value["metadata"].as_object_mut().unwrap().remove("authors");
+        value["managed_files"] = serde_json::json!([]);
+        let (pack, warnings) = Pack::from_json_with_warnings(&value.to_string()).unwrap();
+        assert_eq!(pack.metadata.authors()[0].name, "Someone");
+        assert_eq!(
+            warnings,
+            [LoadWarning::DeprecatedField {
+                field: "metadata.author",
+                replacement: "metadata.authors",
+            }]
+        );
+    }
+}
@@ -0,0 +1,230 @@
+//! Finding pack manifests on disk
+//!
+//! A repository holds either a single pack, with its [`MANIFEST_NAME`] at the root, or several
+//! related packs under [`PACKS_DIR`], each in its own directory (`packs/<name>/ffpack.json`).
+//! Manifests are found by searching upward from the working directory, the way cargo finds
+//! `Cargo.toml`.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use snafu::{ensure, ResultExt, Snafu};
+
+/// The file name of a pack manifest
+pub const MANIFEST_NAME: &str = "ffpack.json";
+
+/// The directory holding the packs of a multi-pack repository
+pub const PACKS_DIR: &str = "packs";
+
+/// Finds the manifest to operate on
+///
+/// An explicit `manifest_path` is used as is, unless a `pack` is also named, in which case the
+/// search for the pack starts from the manifest's directory instead. Otherwise the nearest
+/// manifest above `start` is used; with a `pack` named, the nearest repository containing that
+/// pack is used.
+///
+/// # Errors
+///
+/// Returns an error if no matching manifest exists, or if the directory tree can't be read
+pub fn locate(
+    start: &Path,
+    manifest_path: Option<&Path>,
+    pack: Option<&str>,
+) -> Result<PathBuf, LocateError> {
+    let start = match (manifest_path, pack) {
+        (Some(path), None) => {
+            ensure!(path.is_file(), MissingSnafu { path });
+            return Ok(path.to_path_buf());
+        }
+        (Some(path), Some(_)) => path.parent().unwrap_or(Path::new(".")),
+        (None, _) => start,
+    };
+    match pack {
+        Some(name) => locate_pack(start, name),
+        None => locate_nearest(start),
+    }
+}
+
+/// Finds the manifest of the pack called `name` in the nearest repository above `start`
+fn locate_pack(start: &Path, name: &str) -> Result<PathBuf, LocateError> {
+    let mut available = None;
+    for dir in start.ancestors() {
+        let manifest = dir.join(PACKS_DIR).join(name).join(MANIFEST_NAME);
+        if manifest.is_file() {
+            return Ok(manifest);
+        }
+        if available.is_none() && dir.join(PACKS_DIR).is_dir() {
+            available = Some(list_packs(dir)?);
+        }
+    }
+    match available {
+        Some(available) => UnknownPackSnafu { name, available }.fail(),
+        None => NotFoundSnafu { start }.fail(),
+    }
+}
+
+/// Finds the nearest manifest above `start`, or the only pack of the nearest multi-pack
+/// repository
+fn locate_nearest(start: &Path) -> Result<PathBuf, LocateError> {
+    for dir in start.ancestors() {
+        let manifest = dir.join(MANIFEST_NAME);
+        if manifest.is_file() {
+            return Ok(manifest);
+        }
+        if dir.join(PACKS_DIR).is_dir() {
+            let packs = list_packs(dir)?;
+            match packs.as_slice() {
+                [] => {}
+                [only] => return Ok(dir.join(PACKS_DIR).join(only).join(MANIFEST_NAME)),
+                _ => {
+                    return AmbiguousSnafu {
+                        root: dir,
+                        packs: packs.clone(),
+                    }
+                    .fail()
+                }
+            }
+        }
+    }
+    NotFoundSnafu { start }.fail()
+}
+
+/// Lists the names of the packs in the multi-pack repository at `root`, in sorted order
+///
+/// # Errors
+///
+/// Returns an error if the packs directory exists but can't be read
+pub fn list_packs(root: &Path) -> Result<Vec<String>, LocateError> {
+    let dir = root.join(PACKS_DIR);
+    let entries = match dir.read_dir() {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error).context(IoSnafu { path: dir }),
+    };
+    let mut packs = Vec::new();
+    for entry in entries {
+        let entry = entry.context(IoSnafu { path: &dir })?;
+        if entry.path().join(MANIFEST_NAME).is_file() {
+            if let Some(name) = entry.file_name().to_str() {
+                packs.push(name.to_string());
+            }
+        }
+    }
+    packs.sort();
+    Ok(packs)
+}
+
+/// Error that occurs while finding a manifest
+#[derive(Debug, Snafu)]
+#[non_exhaustive]
+pub enum LocateError {
+    /// No manifest exists at or above the starting directory
+    #[snafu(display(
+        "Could not find {} in {} or any parent directory",
+        MANIFEST_NAME,
+        start.display()
+    ))]
+    NotFound {
+        /// The directory the search started from
+        start: PathBuf,
+    },
+    /// The explicitly given manifest doesn't exist
+    #[snafu(display("Manifest {} does not exist", path.display()))]
+    Missing {
+        /// The given path
+        path: PathBuf,
+    },
+    /// The repository has no pack by the requested name
+    #[snafu(display("No pack named {}, available packs: {}", name, available.join(", ")))]
+    UnknownPack {
+        /// The requested name
+        name: String,
+        /// The packs the repository does have
+        available: Vec<String>,
+    },
+    /// The repository has several packs, and none was selected
+    #[snafu(display(
+        "{} contains several packs, select one with -p: {}",
+        root.display(),
+        packs.join(", ")
+    ))]
+    Ambiguous {
+        /// The root of the repository
+        root: PathBuf,
+        /// The packs in the repository
+        packs: Vec<String>,
+    },
+    /// The directory tree couldn't be read
+    #[snafu(display("Failed to read {}: {}", path.display(), source))]
+    Io {
+        /// The path being read
+        path: PathBuf,
+        /// The underlying error
+        source: io::Error,
+    },
+}
+
+impl LocateError {
+    /// Suggests how the user might fix the problem, if there is a likely fix
+    pub fn suggestion(&self) -> Option<String> {
+        match self {
+            LocateError::NotFound { .. } => Some(format!(
+                "Run ffpack inside a pack repository, or point it at a {MANIFEST_NAME} with \
+                 --manifest-path"
+            )),
+            LocateError::UnknownPack { .. } => Some(format!(
+                "Packs are the directories under {PACKS_DIR}/ holding a {MANIFEST_NAME}"
+            )),
+            LocateError::Missing { .. }
+            | LocateError::Ambiguous { .. }
+            | LocateError::Io { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use std::fs;
+
+    use super::*;
+
+    // Packs are found upward, selected by name, and an unselected choice between several is an
+    // error
+    #[test]
+    fn locate_packs() {
+        let root = std::env::temp_dir().join(format!("ffpack-workspace-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for pack in ["alpha", "beta"] {
+            fs::create_dir_all(root.join("packs").join(pack).join("config")).unwrap();
+            fs::write(root.join("packs").join(pack).join(MANIFEST_NAME), "{}").unwrap();
+        }
+        let alpha = root.join("packs/alpha").join(MANIFEST_NAME);
+
+        let nested = root.join("packs/alpha/config");
+        assert_eq!(locate(&nested, None, None).unwrap(), alpha);
+        assert!(matches!(
+            locate(&root, None, None),
+            Err(LocateError::Ambiguous { .. })
+        ));
+        let beta = locate(&root, None, Some("beta")).unwrap();
+        assert_eq!(beta, root.join("packs/beta").join(MANIFEST_NAME));
+        // Naming a pack from inside another one still finds it
+        assert_eq!(locate(&nested, None, Some("beta")).unwrap(), beta);
+        match locate(&root, None, Some("gamma")) {
+            Err(LocateError::UnknownPack { available, .. }) => {
+                assert_eq!(available, ["alpha", "beta"]);
+            }
+            other => panic!("Expected an unknown pack, got {other:?}"),
+        }
+
+        assert_eq!(locate(&root, Some(&alpha), None).unwrap(), alpha);
+        assert_eq!(locate(&root, Some(&alpha), Some("beta")).unwrap(), beta);
+        assert!(matches!(
+            locate(&root, Some(&root.join(MANIFEST_NAME)), None),
+            Err(LocateError::Missing { .. })
+        ));
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
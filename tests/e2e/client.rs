@@ -0,0 +1,96 @@
+//! A minimal blocking HTTP/1.1 client, enough to talk to the [`Registry`](crate::registry)
+
+use std::{
+    future::Future,
+    io::{Read, Write},
+    net::TcpStream,
+    pin::pin,
+    sync::Arc,
+    task::{Context, Poll, Wake, Waker},
+    thread::{self, Thread},
+};
+
+use ffpack::http::{FullBody, HttpClient, HttpError, Request, Response};
+
+/// A client speaking plain HTTP over a fresh connection per request
+///
+/// It blocks inside `get`, which is fine for tests polled by [`block_on`].
+#[derive(Debug, Default)]
+pub struct TcpClient;
+
+impl HttpClient for TcpClient {
+    type Body = FullBody;
+
+    async fn get(&self, request: Request) -> Result<Response<FullBody>, HttpError> {
+        fetch(&request).map_err(|source| HttpError::Transport {
+            url: request.url.clone(),
+            source: Box::new(source),
+        })
+    }
+}
+
+/// Sends a request and reads the whole response
+fn fetch(request: &Request) -> std::io::Result<Response<FullBody>> {
+    let url = &request.url;
+    let unsupported = || std::io::Error::other(format!("Can't fetch {url}"));
+    if url.scheme() != "http" {
+        return Err(unsupported());
+    }
+    let host = url.host_str().ok_or_else(unsupported)?;
+    let port = url.port_or_known_default().ok_or_else(unsupported)?;
+    let mut stream = TcpStream::connect((host, port))?;
+    let target = &url[url::Position::BeforePath..url::Position::AfterQuery];
+    write!(
+        stream,
+        "GET {target} HTTP/1.1\r\nHost: {host}:{port}\r\nConnection: close\r\n"
+    )?;
+    for (name, value) in &request.headers {
+        write!(stream, "{name}: {value}\r\n")?;
+    }
+    write!(stream, "\r\n")?;
+    stream.flush()?;
+
+    let mut raw = Vec::new();
+    stream.read_to_end(&mut raw)?;
+    let split = raw
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .ok_or_else(|| std::io::Error::other("Response has no end of headers"))?;
+    let head = String::from_utf8_lossy(&raw[..split]).into_owned();
+    let mut lines = head.split("\r\n");
+    let status = lines
+        .next()
+        .and_then(|line| line.split(' ').nth(1))
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| std::io::Error::other("Malformed status line"))?;
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.to_string(), value.trim().to_string()))
+        .collect();
+    Ok(Response {
+        url: url.clone(),
+        status,
+        headers,
+        body: FullBody(raw[split + 4..].to_vec()),
+    })
+}
+
+/// Runs a future to completion on the current thread
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    /// Wakes the blocked thread
+    struct ThreadWaker(Thread);
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut context = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
@@ -0,0 +1,425 @@
+//! End to end tests, running whole workflows against a local mock registry
+//!
+//! Each test gets its own [`Registry`] on a free port and its own scratch directory, and drives
+//! the library the way the command line does: write a manifest, find and load it, lock every
+//! file, install or export the result, and check what ended up on disk. New workflows belong here
+//! as steps on [`Project`].
+
+mod client;
+mod modrinth;
+mod registry;
+
+use std::{
+    collections::BTreeSet,
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use ffpack::{
+    archive::ZipArchive,
+    download::{DownloadJob, DownloadOptions, Downloader},
+    export::mrpack,
+    hash::{blake3, hash_file, Hasher},
+    http::{CachingClient, HttpClient, HttpError, NetworkPolicy},
+    install::{self, InstanceState, PathRules, Platform},
+    lock::{LockError, Lockfile},
+    resolve::{user_agent, ResolveError, Resolver},
+    types::{HashAlgorithm, Loader, ManagedFile, Metadata, Minecraft, Side, Source, Versions},
+    workspace, Pack,
+};
+use relative_path::RelativePathBuf;
+
+use crate::{
+    client::{block_on, TcpClient},
+    registry::Registry,
+};
+
+/// A pack in a scratch directory, removed again when dropped
+struct Project {
+    /// The directory holding the manifest
+    root: PathBuf,
+}
+
+impl Project {
+    /// Creates an empty pack for fabric on 1.20.1, as `ffpack init` would
+    fn init(name: &str) -> Self {
+        let root = std::env::temp_dir().join(format!("ffpack-e2e-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let pack = Pack {
+            metadata: Metadata::new(name, "Tester", semver::Version::new(1, 0, 0)),
+            versions: Versions {
+                minecraft: Minecraft::new("1.20.1").unwrap(),
+                loader: Loader::new_fabric("0.14.21".parse().unwrap()),
+                java: None,
+            },
+            managed_files: BTreeSet::new(),
+        };
+        let project = Self { root };
+        project.save(&pack);
+        project
+    }
+
+    /// Finds the manifest the way the command line does
+    fn manifest(&self) -> PathBuf {
+        workspace::locate(&self.root, None, None).unwrap()
+    }
+
+    /// Finds and loads the manifest
+    fn load(&self) -> Pack {
+        Pack::from_json(&fs::read_to_string(self.manifest()).unwrap()).unwrap()
+    }
+
+    /// Writes the manifest
+    fn save(&self, pack: &Pack) {
+        let json = serde_json::to_string_pretty(pack).unwrap();
+        fs::write(self.root.join(workspace::MANIFEST_NAME), json).unwrap();
+    }
+
+    /// Adds a file to the manifest
+    fn add(&self, path: &str, source: Source) {
+        self.add_for(path, Side::Both, source);
+    }
+
+    /// Adds a file to the manifest that is only needed on `side`
+    fn add_for(&self, path: &str, side: Side, source: Source) {
+        let mut pack = self.load();
+        let filename = path.rsplit('/').next().unwrap().to_string();
+        pack.managed_files.insert(ManagedFile {
+            path: RelativePathBuf::from(path),
+            filename,
+            side,
+            source,
+            ..ManagedFile::default()
+        });
+        self.save(&pack);
+    }
+
+    /// Returns a resolver for `pack` that asks `registry` in place of the real APIs
+    fn resolver<'c, C: HttpClient>(
+        &self,
+        client: &'c C,
+        registry: &Registry,
+        network: NetworkPolicy,
+        pack: &Pack,
+    ) -> Resolver<&'c C> {
+        Resolver::new(client)
+            .with_root(&self.root)
+            .with_endpoints(registry.endpoints())
+            .with_network(network)
+            .with_user_agent(user_agent(&pack.metadata))
+    }
+
+    /// Resolves every file in the manifest into something to download, without a lockfile
+    fn resolve<C: HttpClient>(
+        &self,
+        client: &C,
+        registry: &Registry,
+        network: NetworkPolicy,
+    ) -> Result<Vec<DownloadJob>, ResolveError> {
+        let pack = self.load();
+        let resolver = self.resolver(client, registry, network, &pack);
+        pack.managed_files
+            .iter()
+            .map(|file| {
+                let artifact = block_on(resolver.resolve(file, &pack.versions))?;
+                Ok(DownloadJob {
+                    artifact,
+                    path: file.path.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Locks the pack as `ffpack lock` does, keeping what the lockfile next to the manifest
+    /// already locked, and saves the lockfile
+    fn lock<C: HttpClient>(&self, client: &C, registry: &Registry) -> Result<Lockfile, LockError> {
+        let pack = self.load();
+        let path = Lockfile::path_for(&self.manifest());
+        let previous = Lockfile::load(&path)?.unwrap_or_default();
+        let resolver = self.resolver(client, registry, NetworkPolicy::Online, &pack);
+        let lockfile = block_on(Lockfile::lock(&resolver, &pack, &previous))?;
+        lockfile.save(&path)?;
+        Ok(lockfile)
+    }
+
+    /// Exports the pack as locked to an mrpack, returning where it is
+    fn export_mrpack(&self, lock: &Lockfile) -> PathBuf {
+        let path = self.root.join("pack.mrpack");
+        let file = File::create(&path).unwrap();
+        mrpack::export(&self.load(), lock, &self.root, file).unwrap();
+        path
+    }
+
+    /// Downloads resolved files into an instance directory, returning where it is
+    ///
+    /// Paths follow the Windows rules whatever the host, as the strictest target.
+    fn install<C: HttpClient>(
+        &self,
+        client: &C,
+        jobs: &[DownloadJob],
+        network: NetworkPolicy,
+    ) -> PathBuf {
+        let instance = self.root.join("instance");
+        let mut jobs = jobs.to_vec();
+        let rules = PathRules::default().with_platform(Platform::Windows);
+        let state = install::prepare(&mut jobs, &rules).unwrap();
+        let downloader = Downloader::new(client, DownloadOptions::default().with_network(network));
+        for result in block_on(downloader.download(&jobs, &instance)) {
+            result.unwrap();
+        }
+        state.save(&instance).unwrap();
+        instance
+    }
+
+    /// Checks every installed file against the digests it was resolved with
+    fn verify(instance: &Path, jobs: &[DownloadJob]) {
+        let state = InstanceState::load(instance).unwrap();
+        for job in jobs {
+            let known = &job.artifact.hashes;
+            assert!(!known.is_empty(), "{} has no digests to verify", job.path);
+            let path = state.installed_path(&job.path).to_path(instance);
+            let actual = hash_file(&path, known.algorithms()).unwrap();
+            assert_eq!(known.mismatch(&actual), None, "{} is corrupt", job.path);
+        }
+    }
+}
+
+impl Drop for Project {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+/// Fills a registry with a mod on each backend, and a file served straight from a url
+fn publish(registry: &Registry) -> Source {
+    registry.modrinth_project("sodium", "Sodium", &["jellysquid3"]);
+    registry.modrinth_version("sodium", "fabric", "sodium-0.4.jar", b"sodium 0.4");
+    registry.modrinth_version("sodium", "fabric", "sodium-0.5.jar", b"sodium 0.5");
+    registry.github_release("o/lib", "v1.0.0", "lib-1.0.0.jar", b"lib 1.0.0");
+    let url = registry.file("/files/config.zip", b"config");
+    Source::Url {
+        url,
+        blake3: blake3(b"config"),
+        hashes: Default::default(),
+        mirrors: Vec::new(),
+    }
+}
+
+/// Reads the entry named `name` out of an archive
+fn read_entry(archive: &mut ZipArchive<File>, name: &str) -> Vec<u8> {
+    let index = archive
+        .entries()
+        .iter()
+        .position(|entry| entry.name() == name)
+        .unwrap_or_else(|| panic!("{name} isn't in the archive"));
+    let mut contents = Vec::new();
+    archive
+        .open(index)
+        .unwrap()
+        .read_to_end(&mut contents)
+        .unwrap();
+    contents
+}
+
+// A pack with files from every kind of source installs and verifies
+#[test]
+fn init_add_install_verify() {
+    let registry = Registry::start();
+    let config = publish(&registry);
+    let project = Project::init("Everything Pack");
+    fs::create_dir_all(project.root.join("local")).unwrap();
+    fs::write(project.root.join("local/tweaks.jar"), b"tweaks").unwrap();
+    project.add(
+        "mods/sodium.jar",
+        Source::Modrinth {
+            slug: "sodium".to_string(),
+            version_id: None,
+        },
+    );
+    project.add(
+        "mods/lib.jar",
+        Source::SlugReleases {
+            slug: "github:o/lib".to_string(),
+            artifact_regex: r"^lib-.*\.jar$".to_string(),
+            release_regex: None,
+        },
+    );
+    project.add("config.zip", config);
+    // The primary url is gone, so the mirror has to serve it
+    let notes = registry.file("/files/notes.txt", b"notes");
+    project.add(
+        "config/notes: read me?.txt",
+        Source::Url {
+            url: registry.url("/gone/notes.txt"),
+            blake3: blake3(b"notes"),
+            hashes: Default::default(),
+            mirrors: vec![notes],
+        },
+    );
+    project.add(
+        "mods/tweaks.jar",
+        Source::Path {
+            path: RelativePathBuf::from("local/tweaks.jar"),
+            blake3: blake3(b"tweaks"),
+            hashes: Default::default(),
+        },
+    );
+
+    let jobs = project.lock(&TcpClient, &registry).unwrap().jobs();
+    let instance = project.install(&TcpClient, &jobs, NetworkPolicy::Online);
+    Project::verify(&instance, &jobs);
+    let read = |path: &str| fs::read(instance.join(path)).unwrap();
+    assert_eq!(read("mods/sodium.jar"), b"sodium 0.5");
+    assert_eq!(read("mods/lib.jar"), b"lib 1.0.0");
+    assert_eq!(read("mods/tweaks.jar"), b"tweaks");
+    assert_eq!(read("config.zip"), b"config");
+    // Names Windows can't hold are renamed, and verification still finds them
+    assert_eq!(read("config/notes_ read me_.txt"), b"notes");
+
+    // What's known about each project comes along for mod lists and credits
+    let sodium = jobs
+        .iter()
+        .find(|job| job.path == "mods/sodium.jar")
+        .unwrap();
+    let details = &sodium.artifact.details;
+    assert_eq!(details.name.as_deref(), Some("Sodium"));
+    assert_eq!(details.version.as_deref(), Some("0.5"));
+    assert_eq!(details.authors, ["jellysquid3"]);
+    let lib = jobs.iter().find(|job| job.path == "mods/lib.jar").unwrap();
+    assert_eq!(lib.artifact.details.version.as_deref(), Some("v1.0.0"));
+
+    // API requests name the pack, and ask for builds matching it
+    let requests = registry.requests();
+    let query = requests
+        .iter()
+        .find(|request| request.target.starts_with("/modrinth/"))
+        .unwrap();
+    assert!(query.target.contains("game_versions=%5B%221.20.1%22%5D"));
+    assert!(query
+        .header("User-Agent")
+        .unwrap()
+        .contains("Everything-Pack"));
+}
+
+// Once a cache has seen everything, the same pack installs again without the network
+#[test]
+fn offline_reinstall() {
+    let registry = Registry::start();
+    publish(&registry);
+    let project = Project::init("Offline Pack");
+    project.add(
+        "mods/sodium.jar",
+        Source::Modrinth {
+            slug: "sodium".to_string(),
+            version_id: None,
+        },
+    );
+    let client = CachingClient::new(TcpClient, project.root.join("cache"));
+    let offline = NetworkPolicy::Offline;
+
+    // Nothing is cached yet
+    assert!(matches!(
+        project.resolve(&client, &registry, offline),
+        Err(ResolveError::Http {
+            source: HttpError::WouldRequireNetwork { .. }
+        })
+    ));
+    let jobs = project
+        .resolve(&client, &registry, NetworkPolicy::Online)
+        .unwrap();
+    project.install(&client, &jobs, NetworkPolicy::Online);
+    let seen = registry.requests().len();
+
+    // A plain client can't promise to stay offline, so it isn't allowed to try
+    assert!(project.resolve(&TcpClient, &registry, offline).is_err());
+    fs::remove_dir_all(project.root.join("instance")).unwrap();
+    let jobs = project.resolve(&client, &registry, offline).unwrap();
+    let instance = project.install(&client, &jobs, offline);
+    Project::verify(&instance, &jobs);
+    assert_eq!(registry.requests().len(), seen);
+
+    // Back online, cached responses are revalidated rather than fetched again
+    registry.modrinth_version("sodium", "fabric", "sodium-0.6.jar", b"sodium 0.6");
+    let jobs = project
+        .resolve(&client, &registry, NetworkPolicy::Online)
+        .unwrap();
+    assert_eq!(jobs[0].artifact.filename, "sodium-0.6.jar");
+    let revalidated = registry.requests()[seen..]
+        .iter()
+        .all(|request| request.header("If-None-Match").is_some());
+    assert!(revalidated);
+}
+
+// A locked pack stays as locked when its mods update, and exports to an mrpack that installs it
+#[test]
+fn lock_export() {
+    let registry = Registry::start();
+    publish(&registry);
+    let project = Project::init("Export Pack");
+    fs::create_dir_all(project.root.join("config")).unwrap();
+    fs::write(project.root.join("config/client.toml"), b"fov = 90").unwrap();
+    project.add(
+        "mods/sodium.jar",
+        Source::Modrinth {
+            slug: "sodium".to_string(),
+            version_id: None,
+        },
+    );
+    project.add_for(
+        "config/client.toml",
+        Side::Client,
+        Source::Path {
+            path: RelativePathBuf::from("config/client.toml"),
+            blake3: blake3(b"fov = 90"),
+            hashes: Default::default(),
+        },
+    );
+
+    let lock = project.lock(&TcpClient, &registry).unwrap();
+    let path = Lockfile::path_for(&project.manifest());
+    assert_eq!(Lockfile::load(&path).unwrap().as_ref(), Some(&lock));
+    assert!(lock.verify_against(&project.load()).is_empty());
+    let sodium = RelativePathBuf::from("mods/sodium.jar");
+    assert_eq!(
+        lock.get(&sodium).unwrap().artifact.filename,
+        "sodium-0.5.jar"
+    );
+    // Locking again keeps what was locked, even with a newer version out
+    registry.modrinth_version("sodium", "fabric", "sodium-0.6.jar", b"sodium 0.6");
+    assert_eq!(project.lock(&TcpClient, &registry).unwrap(), lock);
+
+    let mrpack = project.export_mrpack(&lock);
+    let mut archive = ZipArchive::new(File::open(mrpack).unwrap()).unwrap();
+    assert_eq!(
+        read_entry(&mut archive, "client-overrides/config/client.toml"),
+        b"fov = 90"
+    );
+    let json = read_entry(&mut archive, "modrinth.index.json");
+    let index: serde_json::Value = serde_json::from_slice(&json).unwrap();
+    assert_eq!(index["name"], "Export Pack");
+    assert_eq!(index["dependencies"]["minecraft"], "1.20.1");
+    assert_eq!(index["dependencies"]["fabric-loader"], "0.14.21");
+    let files = index["files"].as_array().unwrap();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0]["path"], "mods/sodium.jar");
+    assert_eq!(files[0]["fileSize"], b"sodium 0.5".len());
+    let mut hasher = Hasher::new(HashAlgorithm::ALL);
+    hasher.update(b"sodium 0.5");
+    let expected = hasher.finalize();
+    assert_eq!(
+        files[0]["hashes"]["sha512"],
+        expected.hex(HashAlgorithm::Sha512).unwrap()
+    );
+
+    // What the index lists downloads to the file that was locked
+    let download = files[0]["downloads"][0].as_str().unwrap();
+    assert!(download.ends_with("sodium-0.5.jar"));
+    let instance = project.install(&TcpClient, &lock.jobs(), NetworkPolicy::Online);
+    Project::verify(&instance, &lock.jobs());
+    assert_eq!(
+        fs::read(instance.join("mods/sodium.jar")).unwrap(),
+        b"sodium 0.5"
+    );
+}
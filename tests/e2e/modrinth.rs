@@ -0,0 +1,97 @@
+//! Resolving Modrinth sources against responses in the shape the real API gives
+//!
+//! The fixtures carry every field Modrinth sends, most of which ffpack ignores, so that a
+//! change in what we deserialize is checked against the real layout rather than the minimal
+//! one the fake registry writes.
+
+use ffpack::{
+    http::NetworkPolicy,
+    resolve::{CompatibilityPolicy, ResolveError, ResolvedArtifact, Resolver},
+    types::{Loader, ManagedFile, Minecraft, Source, Versions},
+};
+
+use crate::{
+    client::{block_on, TcpClient},
+    registry::Registry,
+};
+
+/// Serves the recorded sodium responses
+fn sodium() -> Registry {
+    let registry = Registry::start();
+    let fixture = |path: &str, body: &[u8]| {
+        registry.file(&format!("/modrinth/v2/project/sodium{path}"), body);
+    };
+    fixture("", include_bytes!("fixtures/modrinth/sodium-project.json"));
+    fixture(
+        "/members",
+        include_bytes!("fixtures/modrinth/sodium-members.json"),
+    );
+    fixture(
+        "/version",
+        include_bytes!("fixtures/modrinth/sodium-versions.json"),
+    );
+    registry
+}
+
+/// Resolves sodium for `loader` on 1.20.1
+fn resolve(
+    registry: &Registry,
+    loader: Loader,
+    compatibility: CompatibilityPolicy,
+) -> Result<ResolvedArtifact, ResolveError> {
+    let versions = Versions {
+        minecraft: Minecraft::new("1.20.1").unwrap(),
+        loader,
+        java: None,
+    };
+    let file = ManagedFile {
+        source: Source::Modrinth {
+            slug: "sodium".to_string(),
+            version_id: None,
+        },
+        ..ManagedFile::default()
+    };
+    let resolver = Resolver::new(TcpClient)
+        .with_endpoints(registry.endpoints())
+        .with_network(NetworkPolicy::Online)
+        .with_compatibility(compatibility);
+    block_on(resolver.resolve(&file, &versions))
+}
+
+// The newest version for the pack's game and loader is picked, even when the listing holds
+// others
+#[test]
+fn recorded_listing() {
+    let registry = sodium();
+    let fabric = Loader::new_fabric("0.14.21".parse().unwrap());
+    let artifact = resolve(&registry, fabric, CompatibilityPolicy::default()).unwrap();
+    assert_eq!(artifact.filename, "sodium-fabric-mc1.20.1-0.5.3.jar");
+    assert_eq!(
+        artifact.download_url.as_str(),
+        "https://cdn.modrinth.com/data/AANobbMI/versions/OihdIimA/\
+         sodium-fabric-mc1.20.1-0.5.3.jar"
+    );
+    assert_eq!(artifact.size, Some(871_024));
+    assert_eq!(
+        artifact.hashes.sha1.map(hex::encode).as_deref(),
+        Some("bdafdfa29e23a8eaf07bfa94b06a39af953a88cf")
+    );
+    assert!(artifact.hashes.sha512.is_some());
+    let details = &artifact.details;
+    assert_eq!(details.name.as_deref(), Some("Sodium"));
+    assert_eq!(details.version.as_deref(), Some("mc1.20.1-0.5.3"));
+    assert_eq!(details.authors, ["jellysquid3", "IMS"]);
+    assert_eq!(
+        details.url.as_ref().map(url::Url::as_str),
+        Some("https://modrinth.com/mod/sodium")
+    );
+
+    // The one Quilt build is for another game version, so it doesn't count on its own
+    let quilt = Loader::new_quilt("0.19.0".parse().unwrap());
+    let artifact = resolve(&registry, quilt.clone(), CompatibilityPolicy::Allow).unwrap();
+    assert_eq!(artifact.filename, "sodium-fabric-mc1.20.1-0.5.3.jar");
+    assert!(matches!(
+        resolve(&registry, quilt, CompatibilityPolicy::Deny),
+        Err(ResolveError::NoMatchingVersion { .. })
+    ));
+}
@@ -0,0 +1,297 @@
+//! A local HTTP server standing in for Modrinth, GitHub, and the CDNs behind them
+
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+};
+
+use ffpack::{
+    hash::{blake3, Hasher},
+    resolve::Endpoints,
+    types::HashAlgorithm,
+};
+use url::Url;
+
+/// A request the registry received
+#[derive(Debug, Clone)]
+pub struct Recorded {
+    /// The path and query that were asked for
+    pub target: String,
+    /// The request headers, in the order they were sent
+    pub headers: Vec<(String, String)>,
+}
+
+impl Recorded {
+    /// Returns the value of a header, ignoring case in its name
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// What the registry knows, shared with the thread serving it
+#[derive(Debug, Default)]
+struct State {
+    /// Bodies by path, served with an ETag of their blake3 hash
+    routes: HashMap<String, Vec<u8>>,
+    /// Modrinth projects by slug, as their title and the usernames of their members
+    projects: HashMap<String, (String, Vec<String>)>,
+    /// Modrinth versions by project slug, newest first
+    modrinth: HashMap<String, Vec<serde_json::Value>>,
+    /// GitHub releases by `owner/repo`, newest first
+    github: HashMap<String, Vec<serde_json::Value>>,
+    /// The requests received so far
+    requests: Vec<Recorded>,
+}
+
+/// A registry serving on a local port until dropped
+///
+/// Routes match on the path alone, so tests can assert on the queries the resolver sent
+/// through [`Registry::requests`] rather than having to reproduce them up front.
+pub struct Registry {
+    /// Where the registry listens
+    address: SocketAddr,
+    /// The routes and the requests seen
+    state: Arc<Mutex<State>>,
+    /// Set to stop the server thread
+    stop: Arc<AtomicBool>,
+    /// The server thread
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Registry {
+    /// Starts a registry on a free port
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let state = Arc::new(Mutex::new(State::default()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = thread::spawn({
+            let (state, stop) = (state.clone(), stop.clone());
+            move || {
+                for stream in listener.incoming() {
+                    if stop.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    if let Ok(stream) = stream {
+                        // A client hanging up early is the client's problem, not the registry's
+                        let _ = serve(stream, &state);
+                    }
+                }
+            }
+        });
+        Self {
+            address,
+            state,
+            stop,
+            thread: Some(thread),
+        }
+    }
+
+    /// Returns the url of a path on the registry
+    pub fn url(&self, path: &str) -> Url {
+        Url::parse(&format!("http://{}{path}", self.address)).unwrap()
+    }
+
+    /// Returns the API endpoints to point a resolver at the registry
+    pub fn endpoints(&self) -> Endpoints {
+        Endpoints {
+            modrinth: self.url("/modrinth/v2/"),
+            curseforge: self.url("/curseforge/v1/"),
+            github: self.url("/github/"),
+            gitlab: self.url("/gitlab/api/v4/"),
+            codeberg: self.url("/codeberg/api/v1/"),
+            nightly_link: self.url("/nightly/"),
+            ipfs: self.url("/ipfs-gateway/"),
+        }
+    }
+
+    /// Serves `body` at `path`, returning its url
+    ///
+    /// This overrides anything the registry would answer on its own, so recorded API responses
+    /// can be served too.
+    pub fn file(&self, path: &str, body: &[u8]) -> Url {
+        let mut state = self.state.lock().unwrap();
+        state.routes.insert(path.to_string(), body.to_vec());
+        self.url(path)
+    }
+
+    /// Creates a Modrinth project, which can be resolved without this as long as it has
+    /// versions, but then has no name or authors
+    pub fn modrinth_project(&self, slug: &str, title: &str, members: &[&str]) {
+        let members = members.iter().map(ToString::to_string).collect();
+        let mut state = self.state.lock().unwrap();
+        state
+            .projects
+            .insert(slug.to_string(), (title.to_string(), members));
+    }
+
+    /// Publishes a new version of a Modrinth project, with a single primary file
+    pub fn modrinth_version(&self, slug: &str, loader: &str, filename: &str, contents: &[u8]) {
+        let url = self.file(&format!("/cdn/modrinth/{slug}/{filename}"), contents);
+        let version = serde_json::json!({
+            "version_number": filename.trim_end_matches(".jar").rsplit('-').next(),
+            "loaders": [loader],
+            "files": [{
+                "url": url,
+                "filename": filename,
+                "primary": true,
+                "size": contents.len(),
+                "hashes": digests(contents, &[HashAlgorithm::Sha1, HashAlgorithm::Sha512]),
+            }],
+        });
+        let mut state = self.state.lock().unwrap();
+        state
+            .modrinth
+            .entry(slug.to_string())
+            .or_default()
+            .insert(0, version);
+    }
+
+    /// Publishes a new GitHub release of `repo` (as `owner/repo`) with a single asset
+    pub fn github_release(&self, repo: &str, tag: &str, filename: &str, contents: &[u8]) {
+        let url = self.file(
+            &format!("/cdn/github/{repo}/releases/download/{tag}/{filename}"),
+            contents,
+        );
+        let sha256 = digests(contents, &[HashAlgorithm::Sha256])["sha256"].clone();
+        let release = serde_json::json!({
+            "name": tag,
+            "tag_name": tag,
+            "assets": [{
+                "name": filename,
+                "size": contents.len(),
+                "browser_download_url": url,
+                "digest": format!("sha256:{}", sha256.as_str().unwrap()),
+            }],
+        });
+        let mut state = self.state.lock().unwrap();
+        state
+            .github
+            .entry(repo.to_string())
+            .or_default()
+            .insert(0, release);
+    }
+
+    /// Returns the requests received so far
+    pub fn requests(&self) -> Vec<Recorded> {
+        self.state.lock().unwrap().requests.clone()
+    }
+}
+
+impl Drop for Registry {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        // Wake the accept loop so it sees the flag
+        let _ = TcpStream::connect(self.address);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Returns the hex digests of `contents` keyed by algorithm name, as the APIs list them
+fn digests(contents: &[u8], algorithms: &[HashAlgorithm]) -> serde_json::Value {
+    let mut hasher = Hasher::new(algorithms.iter().copied());
+    hasher.update(contents);
+    let hashes = hasher.finalize();
+    algorithms
+        .iter()
+        .map(|&algorithm| {
+            (
+                algorithm.name().to_string(),
+                hashes.hex(algorithm).unwrap().into(),
+            )
+        })
+        .collect::<serde_json::Map<_, _>>()
+        .into()
+}
+
+/// Answers a single request on a connection, then closes it
+fn serve(stream: TcpStream, state: &Mutex<State>) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let target = line.split(' ').nth(1).unwrap_or("/").to_string();
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        reader.read_line(&mut line)?;
+        match line.trim_end().split_once(':') {
+            Some((name, value)) => headers.push((name.to_string(), value.trim().to_string())),
+            None => break,
+        }
+    }
+    let request = Recorded { target, headers };
+    let (status, body, etag) = {
+        let mut state = state.lock().unwrap();
+        let answer = route(&state, &request);
+        state.requests.push(request);
+        answer
+    };
+    let reason = match status {
+        200 => "OK",
+        304 => "Not Modified",
+        _ => "Not Found",
+    };
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {status} {reason}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        body.len()
+    )?;
+    if let Some(etag) = etag {
+        write!(stream, "ETag: {etag}\r\n")?;
+    }
+    write!(stream, "\r\n")?;
+    stream.write_all(&body)?;
+    stream.flush()
+}
+
+/// Picks the status, body, and ETag to answer a request with
+fn route(state: &State, request: &Recorded) -> (u16, Vec<u8>, Option<String>) {
+    let path = request.target.split('?').next().unwrap_or_default();
+    let segments: Vec<_> = path.trim_matches('/').split('/').collect();
+    let body = match segments[..] {
+        // Fixed bodies, like recorded responses, take precedence over the fake APIs
+        _ if state.routes.contains_key(path) => state.routes.get(path).cloned(),
+        ["modrinth", "v2", "project", slug] => state.projects.get(slug).map(|(title, _)| {
+            serde_json::to_vec(&serde_json::json!({"title": title, "project_type": "mod"})).unwrap()
+        }),
+        ["modrinth", "v2", "project", slug, "members"] => {
+            state.projects.get(slug).map(|(_, members)| {
+                let members: Vec<_> = members
+                    .iter()
+                    .map(|name| serde_json::json!({"user": {"username": name}}))
+                    .collect();
+                serde_json::to_vec(&members).unwrap()
+            })
+        }
+        ["modrinth", "v2", "project", slug, "version"] => Some(listing(state.modrinth.get(slug))),
+        ["github", "repos", owner, repo, "releases"] => {
+            Some(listing(state.github.get(&format!("{owner}/{repo}"))))
+        }
+        _ => state.routes.get(path).cloned(),
+    };
+    let Some(body) = body else {
+        return (404, Vec::new(), None);
+    };
+    let etag = format!("\"{}\"", hex::encode(blake3(&body)));
+    if request.header("If-None-Match") == Some(etag.as_str()) {
+        return (304, Vec::new(), Some(etag));
+    }
+    (200, body, Some(etag))
+}
+
+/// Serializes a list of versions or releases, which is empty for unknown projects
+fn listing(entries: Option<&Vec<serde_json::Value>>) -> Vec<u8> {
+    serde_json::to_vec(entries.map_or(&[][..], Vec::as_slice)).unwrap()
+}